@@ -0,0 +1,137 @@
+//! Loads a scene dumped with `specs_physics::scenes::dump`, steps it headless
+//! and prints a summary of every step:
+//!
+//! ```text
+//! cargo run --example inspector --features inspector-bin -- scene.txt [steps]
+//! ```
+
+use std::{
+    env,
+    fs,
+    io::{self, Write},
+    process,
+};
+
+use specs::{Join, World, WorldExt};
+use specs_physics::{
+    events::{ContactEvents, ContactType},
+    nphysics::object::BodyStatus,
+    physics_dispatcher,
+    scenarios::Scenario,
+    scenes,
+    Physics,
+    PhysicsBody,
+    SimplePosition,
+};
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let path = match args.next() {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: inspector <scene> [steps]");
+            process::exit(2);
+        }
+    };
+    let steps = args.next().map_or(60, |steps| {
+        steps.parse().unwrap_or_else(|_| {
+            eprintln!("invalid number of steps: {}", steps);
+            process::exit(2);
+        })
+    });
+
+    let scenario = fs::read_to_string(&path)
+        .map_err(|error| error.to_string())
+        .and_then(|text| scenes::parse::<f32>(&text).map_err(|error| error.to_string()))
+        .unwrap_or_else(|error| {
+            eprintln!("failed to load {}: {}", path, error);
+            process::exit(1);
+        });
+
+    let stdout = io::stdout();
+    if let Err(error) = inspect(&scenario, &path, steps, &mut stdout.lock()) {
+        eprintln!("failed to write the summary: {}", error);
+        process::exit(1);
+    }
+}
+
+/// Spawns the `Scenario` loaded from the `source` and writes a summary of
+/// every one of the `steps` to `out`.
+fn inspect<W: Write>(
+    scenario: &Scenario<f32>,
+    source: &str,
+    steps: usize,
+    out: &mut W,
+) -> io::Result<()> {
+    let mut world = World::new();
+    let mut dispatcher = physics_dispatcher::<f32, SimplePosition<f32>>();
+    dispatcher.setup(&mut world);
+    let mut contact_event_reader = world.fetch_mut::<ContactEvents>().register_reader();
+
+    let entities = scenario.spawn(&mut world, SimplePosition);
+    writeln!(out, "loaded {} objects from {}", entities.len(), source)?;
+
+    for _ in 0..steps {
+        dispatcher.dispatch(&world);
+        world.maintain();
+
+        let (mut started, mut stopped) = (0, 0);
+        for contact_event in world
+            .read_resource::<ContactEvents>()
+            .read(&mut contact_event_reader)
+        {
+            match contact_event.contact_type {
+                ContactType::Started => started += 1,
+                ContactType::Stopped => stopped += 1,
+            }
+        }
+
+        // summarise the motion of the dynamic bodies
+        let (mut dynamic, mut kinetic_energy, mut max_speed) = (0, 0.0, 0.0f32);
+        for physics_body in (&world.read_storage::<PhysicsBody<f32>>()).join() {
+            if physics_body.body_status != BodyStatus::Dynamic {
+                continue;
+            }
+            let speed = physics_body.velocity.linear.norm();
+            dynamic += 1;
+            kinetic_energy += 0.5 * physics_body.mass * speed * speed;
+            max_speed = max_speed.max(speed);
+        }
+
+        writeln!(
+            out,
+            "step {:>5}: {} dynamic bodies, {} contacts started, {} stopped, kinetic energy {:.4}, max speed {:.4}",
+            world.read_resource::<Physics<f32>>().tick(),
+            dynamic,
+            started,
+            stopped,
+            kinetic_energy,
+            max_speed
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use specs_physics::{scenarios::Scenario, scenes};
+
+    use super::inspect;
+
+    #[test]
+    fn step_dumped_scene() {
+        let dump = scenes::dump(&Scenario::<f32>::rain(5, 1));
+        let scenario = scenes::parse::<f32>(&dump).unwrap();
+
+        let mut out = Vec::new();
+        inspect(&scenario, "rain.txt", 10, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 11);
+        assert_eq!(lines[0], "loaded 6 objects from rain.txt");
+        for line in &lines[1..] {
+            assert!(line.starts_with("step"));
+            assert!(line.contains(": 5 dynamic bodies,"));
+        }
+    }
+}
@@ -0,0 +1,238 @@
+//! # Baking module
+//! Precomputed collision data for large static geometry. Building the
+//! bounding volume hierarchy of a triangle mesh dominates the load time of
+//! big levels; `BakedShapes` builds the meshes once, e.g. in an asset
+//! pipeline, writes them to disk and loads them at runtime without rebuilding
+//! their hierarchies. Requires the "baking" feature.
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    io::{Read, Write},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    colliders::{IntoMesh, Shape},
+    nalgebra::RealField,
+    ncollide::shape::{ShapeHandle, TriMesh},
+};
+
+/// The version of the format written by `BakedShapes::write_to`. Files
+/// written with a different version have to be baked again.
+pub const BAKED_FORMAT_VERSION: u32 = 1;
+
+/// The reasons reading or writing `BakedShapes` can fail.
+#[derive(Debug)]
+pub enum BakeError {
+    /// The data could not be encoded or decoded, including IO errors.
+    Encoding(bincode::Error),
+    /// The data was written with a different `BAKED_FORMAT_VERSION`.
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for BakeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BakeError::Encoding(error) => write!(f, "invalid baked shapes: {}", error),
+            BakeError::UnsupportedVersion(version) => {
+                write!(f, "unsupported baked shapes version {}", version)
+            }
+        }
+    }
+}
+
+impl Error for BakeError {}
+
+impl From<bincode::Error> for BakeError {
+    fn from(error: bincode::Error) -> Self {
+        BakeError::Encoding(error)
+    }
+}
+
+/// A named collection of triangle meshes whose bounding volume hierarchies
+/// have already been built. The meshes are used by `PhysicsCollider`s through
+/// `Shape::Baked`, which shares the mesh between all of them.
+///
+/// # Example
+///
+/// ```rust
+/// use specs_physics::{baking::BakedShapes, PhysicsColliderBuilder};
+///
+/// # fn load(level: &[u8]) -> Result<(), specs_physics::baking::BakeError> {
+/// let baked_shapes = BakedShapes::<f32>::read_from(level)?;
+/// if let Some(shape) = baked_shapes.shape("terrain") {
+///     let physics_collider = PhysicsColliderBuilder::from(shape).build();
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct BakedShapes<N: RealField> {
+    meshes: HashMap<String, ShapeHandle<N>>,
+}
+
+impl<N: RealField> BakedShapes<N> {
+    /// Creates a new, empty `BakedShapes` collection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds the triangle mesh of the given `IntoMesh` and stores it under
+    /// the given name, replacing a previously baked mesh of the same name.
+    pub fn bake(&mut self, name: impl Into<String>, mesh: &dyn IntoMesh<N = N>) {
+        let data = mesh.points();
+        self.insert(name, TriMesh::new(data.0, data.1, data.2));
+    }
+
+    /// Stores an already built triangle mesh under the given name.
+    pub fn insert(&mut self, name: impl Into<String>, mesh: TriMesh<N>) {
+        self.meshes.insert(name.into(), ShapeHandle::new(mesh));
+    }
+
+    /// Returns the `Shape` of the mesh baked under the given name.
+    pub fn shape(&self, name: &str) -> Option<Shape<N>> {
+        self.meshes.get(name).map(|handle| Shape::Baked {
+            handle: handle.clone(),
+        })
+    }
+
+    /// The names of all baked meshes.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.meshes.keys().map(String::as_str)
+    }
+
+    /// The number of baked meshes.
+    pub fn len(&self) -> usize {
+        self.meshes.len()
+    }
+
+    /// Whether no meshes have been baked.
+    pub fn is_empty(&self) -> bool {
+        self.meshes.is_empty()
+    }
+
+    /// Writes all baked meshes, including their bounding volume hierarchies,
+    /// to the given writer.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), BakeError>
+    where
+        TriMesh<N>: Serialize,
+    {
+        // meshes are written in the order of their names, so that baking the
+        // same level twice produces identical files
+        let mut meshes = self
+            .meshes
+            .iter()
+            .filter_map(|(name, handle)| Some((name.as_str(), handle.as_shape::<TriMesh<N>>()?)))
+            .collect::<Vec<_>>();
+        meshes.sort_by_key(|(name, _)| *name);
+
+        bincode::serialize_into(&mut writer, &BAKED_FORMAT_VERSION)?;
+        bincode::serialize_into(&mut writer, &meshes)?;
+        Ok(())
+    }
+
+    /// Reads meshes written by `write_to` from the given reader.
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self, BakeError>
+    where
+        TriMesh<N>: DeserializeOwned,
+    {
+        let version: u32 = bincode::deserialize_from(&mut reader)?;
+        if version != BAKED_FORMAT_VERSION {
+            return Err(BakeError::UnsupportedVersion(version));
+        }
+
+        let meshes: Vec<(String, TriMesh<N>)> = bincode::deserialize_from(&mut reader)?;
+        Ok(Self {
+            meshes: meshes
+                .into_iter()
+                .map(|(name, mesh)| (name, ShapeHandle::new(mesh)))
+                .collect(),
+        })
+    }
+}
+
+impl<N: RealField> Default for BakedShapes<N> {
+    fn default() -> Self {
+        Self {
+            meshes: HashMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        baking::{BakeError, BakedShapes},
+        colliders::Shape,
+        nalgebra::Point3,
+        ncollide::shape::{ShapeHandle, TriMesh},
+    };
+
+    // a unit quad made of two triangles, rising by the given slope along x
+    fn quad(slope: f32) -> TriMesh<f32> {
+        TriMesh::new(
+            vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, slope, 0.0),
+                Point3::new(1.0, slope, 1.0),
+                Point3::new(0.0, 0.0, 1.0),
+            ],
+            vec![Point3::new(0, 1, 2), Point3::new(0, 2, 3)],
+            None,
+        )
+    }
+
+    fn baked_handle(baked_shapes: &BakedShapes<f32>, name: &str) -> ShapeHandle<f32> {
+        match baked_shapes.shape(name) {
+            Some(Shape::Baked { handle }) => handle,
+            _ => panic!("{} is not baked", name),
+        }
+    }
+
+    #[test]
+    fn load_written_shapes() {
+        let mut baked_shapes = BakedShapes::<f32>::new();
+        baked_shapes.insert("floor", quad(0.0));
+        baked_shapes.insert("ramp", quad(0.5));
+
+        let mut data = Vec::new();
+        baked_shapes.write_to(&mut data).unwrap();
+        let loaded = BakedShapes::<f32>::read_from(data.as_slice()).unwrap();
+
+        let mut names = loaded.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["floor", "ramp"]);
+        for name in names {
+            let original = baked_handle(&baked_shapes, name);
+            let reloaded = baked_handle(&loaded, name);
+            assert_eq!(original.local_aabb(), reloaded.local_aabb());
+
+            let original = original.as_shape::<TriMesh<f32>>().unwrap();
+            let reloaded = reloaded.as_shape::<TriMesh<f32>>().unwrap();
+            assert_eq!(original.points(), reloaded.points());
+            let indices = |mesh: &TriMesh<f32>| {
+                mesh.faces()
+                    .iter()
+                    .map(|face| face.indices)
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(indices(original), indices(reloaded));
+        }
+
+        // writing the loaded shapes again produces identical data
+        let mut rewritten = Vec::new();
+        loaded.write_to(&mut rewritten).unwrap();
+        assert_eq!(data, rewritten);
+    }
+
+    #[test]
+    fn reject_other_format_versions() {
+        let data = bincode::serialize(&0u32).unwrap();
+        match BakedShapes::<f32>::read_from(data.as_slice()) {
+            Err(BakeError::UnsupportedVersion(0)) => {}
+            _ => panic!("read baked shapes of version 0"),
+        }
+    }
+}
@@ -1,11 +1,14 @@
-use specs::{Component, DenseVecStorage, FlaggedStorage};
+use std::fmt;
+
+use specs::{Component, DenseVecStorage, FlaggedStorage, NullStorage};
 
 use crate::{
-    nalgebra::{Isometry3, Matrix3, Point3, RealField},
+    nalgebra::{Isometry3, Matrix3, Point3, RealField, Translation3, UnitQuaternion, Vector3},
     nphysics::{
-        algebra::{Force3, ForceType, Velocity3},
+        algebra::{Force3, Velocity3},
         object::{Body, BodyHandle, BodyPart, BodyStatus, RigidBody, RigidBodyDesc},
     },
+    PhysicsStorage,
 };
 
 pub mod util {
@@ -44,13 +47,30 @@ pub mod util {
 ///
 /// Initially, it is used to position bodies in the nphysics `World`. Then after
 /// progressing the `World` it is used to synchronise the updated positions back
-/// towards Specs.
+/// towards Specs. Positions are full isometries, so the orientation of bodies
+/// is synchronised in both directions along with their translation.
 pub trait Position<N: RealField>:
     Component<Storage = FlaggedStorage<Self, DenseVecStorage<Self>>> + Send + Sync
 {
     fn isometry(&self) -> &Isometry3<N>;
     fn isometry_mut(&mut self) -> &mut Isometry3<N>;
     fn set_isometry(&mut self, isometry: &Isometry3<N>) -> &mut Self;
+
+    /// The translation part of the isometry.
+    fn translation(&self) -> &Translation3<N> {
+        &self.isometry().translation
+    }
+
+    /// The orientation part of the isometry.
+    fn rotation(&self) -> &UnitQuaternion<N> {
+        &self.isometry().rotation
+    }
+
+    /// Replaces the orientation, keeping the translation.
+    fn set_rotation(&mut self, rotation: &UnitQuaternion<N>) -> &mut Self {
+        self.isometry_mut().rotation = *rotation;
+        self
+    }
 }
 
 #[cfg(feature = "amethyst")]
@@ -84,7 +104,7 @@ pub struct PhysicsBody<N: RealField> {
 }
 
 impl<N: RealField> Component for PhysicsBody<N> {
-    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+    type Storage = FlaggedStorage<Self, PhysicsStorage<Self>>;
 }
 
 impl<N: RealField> PhysicsBody<N> {
@@ -97,6 +117,20 @@ impl<N: RealField> PhysicsBody<N> {
         self
     }
 
+    /// Returns the center of mass in world space for the body placed at the
+    /// given `isometry`.
+    pub fn center_of_mass(&self, isometry: &Isometry3<N>) -> Point3<N> {
+        isometry * self.local_center_of_mass
+    }
+
+    /// Moves the local center of mass by the given local `offset`, e.g. to
+    /// lower it and keep a vehicle from rolling over. The change is applied
+    /// to the physics `World` during the next frame.
+    pub fn shift_center_of_mass(&mut self, offset: &Vector3<N>) -> &mut Self {
+        self.local_center_of_mass += offset;
+        self
+    }
+
     /// For creating new rigid body from this component's values
     pub(crate) fn to_rigid_body_desc(&self) -> RigidBodyDesc<N> {
         RigidBodyDesc::new()
@@ -108,7 +142,8 @@ impl<N: RealField> PhysicsBody<N> {
             .local_center_of_mass(self.local_center_of_mass)
     }
 
-    /// Note: applies forces by draining external force property
+    /// Note: the external forces are not applied, they are drained separately
+    /// for every substep of the frame
     pub(crate) fn apply_to_physics_world(&mut self, rigid_body: &mut RigidBody<N>) -> &mut Self {
         rigid_body.enable_gravity(self.gravity_enabled);
         rigid_body.set_status(self.body_status);
@@ -116,7 +151,6 @@ impl<N: RealField> PhysicsBody<N> {
         rigid_body.set_angular_inertia(self.angular_inertia);
         rigid_body.set_mass(self.mass);
         rigid_body.set_local_center_of_mass(self.local_center_of_mass);
-        rigid_body.apply_force(0, &self.drain_external_force(), ForceType::Force, true);
         self
     }
 
@@ -130,16 +164,52 @@ impl<N: RealField> PhysicsBody<N> {
         let local_inertia = rigid_body.local_inertia();
         self.angular_inertia = local_inertia.angular;
         self.mass = local_inertia.linear;
+
+        // the colliders of a body contribute to its center of mass as well
+        self.local_center_of_mass = rigid_body
+            .position()
+            .inverse_transform_point(&rigid_body.center_of_mass());
         self
     }
 
-    fn drain_external_force(&mut self) -> Force3<N> {
+    /// Checks whether applying this `PhysicsBody` would leave the given
+    /// `RigidBody` unchanged, i.e. it only holds the values written back from
+    /// the physics `World` and no external forces.
+    pub(crate) fn matches_physics_world(&self, rigid_body: &RigidBody<N>) -> bool {
+        let mut written_back = *self;
+        written_back.update_from_physics_world(rigid_body);
+
+        self.gravity_enabled == written_back.gravity_enabled
+            && self.body_status == written_back.body_status
+            && self.velocity.linear == written_back.velocity.linear
+            && self.velocity.angular == written_back.velocity.angular
+            && self.angular_inertia == written_back.angular_inertia
+            && self.mass == written_back.mass
+            && self.local_center_of_mass == written_back.local_center_of_mass
+            && self.external_forces.linear == Vector3::zeros()
+            && self.external_forces.angular == Vector3::zeros()
+    }
+
+    pub(crate) fn drain_external_force(&mut self) -> Force3<N> {
         let value = self.external_forces;
         self.external_forces = Force3::<N>::zero();
         value
     }
 }
 
+/// A compact, single line summary of the `PhysicsBody` for logging large
+/// scenes.
+impl<N: RealField> fmt::Display for PhysicsBody<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let linear = &self.velocity.linear;
+        write!(
+            f,
+            "{:?} body with mass {} moving at [{}, {}, {}]",
+            self.body_status, self.mass, linear.x, linear.y, linear.z
+        )
+    }
+}
+
 /// The `PhysicsBodyBuilder` implements the builder pattern for `PhysicsBody`s
 /// and is the recommended way of instantiating and customising new
 /// `PhysicsBody` instances.
@@ -231,3 +301,70 @@ impl<N: RealField> PhysicsBodyBuilder<N> {
         }
     }
 }
+
+/// The `SimulationFocus` `Component` marks the `Entity`s, e.g. players and
+/// cameras, around which bodies are kept active when an `ActivationRadius`
+/// exists; see the `ApplyActivationRadiusSystem`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SimulationFocus;
+
+impl Component for SimulationFocus {
+    type Storage = NullStorage<Self>;
+}
+
+/// The `SimulationRate` `Component` makes the `PhysicsBody` of its `Entity`
+/// simulate at a fraction of the full rate, which is a coarse but effective
+/// way to reduce the cost of background clutter. Reduced rate bodies are
+/// stepped every second or fourth frame with a correspondingly larger time
+/// step and are frozen in between; contacts are still resolved with the
+/// regular time step. The frames are staggered by the `Entity` index, so that
+/// not all bodies are stepped in the same frame.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SimulationRate {
+    Full,
+    Half,
+    Quarter,
+}
+
+impl SimulationRate {
+    /// The number of frames per simulated frame.
+    pub fn divisor(self) -> u32 {
+        match self {
+            SimulationRate::Full => 1,
+            SimulationRate::Half => 2,
+            SimulationRate::Quarter => 4,
+        }
+    }
+}
+
+impl Default for SimulationRate {
+    fn default() -> Self {
+        SimulationRate::Full
+    }
+}
+
+impl Component for SimulationRate {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// The `MaxAngularVelocity` `Component` limits the angular speed of the
+/// `PhysicsBody` of its `Entity`, in radians per second. Small props resolving
+/// deep penetrations can otherwise pick up absurd spins, which strobe
+/// visually. The limit is enforced after every step by the
+/// `ApplyMaxAngularVelocitiesSystem`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MaxAngularVelocity<N: RealField>(pub N);
+
+impl<N: RealField> Component for MaxAngularVelocity<N> {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// The `SimulationRateFrame` `Resource` is shared by the
+/// `ApplySimulationRatesSystem` and the `RestoreSimulationRatesSystem`; it
+/// holds the bodies which were modified for the current frame.
+#[derive(Default)]
+pub struct SimulationRateFrame {
+    pub(crate) frame: u32,
+    pub(crate) scaled: Vec<(BodyHandle, u32)>,
+    pub(crate) frozen: Vec<BodyHandle>,
+}
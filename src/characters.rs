@@ -0,0 +1,353 @@
+//! # Characters module
+//! Kinematic character controllers. A `CharacterController` moves the
+//! kinematic `PhysicsBody` of its `Entity` according to a desired velocity,
+//! keeps it on the ground and lets it fall under `Gravity` otherwise. The
+//! `PhysicsCollider` of the character is expected to be an upright
+//! `Shape::Capsule` matching the dimensions of the `CharacterController`;
+//! resizing the controller, e.g. to crouch, resizes the capsule as well.
+//! Characters overlapping the sensor of a `Climbable` climb instead, and swim
+//! within the sensor of a `WaterVolume`. With a push mass, characters push
+//! the dynamic bodies they walk into. The `NavAgent` couples the output
+//! of a navigation system to characters and other bodies. Characters with
+//! `debug` enabled report the queries of every move as a `CharacterTrace`.
+
+use specs::{Component, DenseVecStorage, Entity};
+
+use crate::{
+    nalgebra::{self as na, Point3, RealField, Unit, Vector3},
+    queries::QueryHit,
+};
+
+/// The movement state of a `CharacterController` after its last move, e.g.
+/// to select animations.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CharacterState {
+    /// Standing on walkable ground.
+    Grounded,
+    /// Jumping or falling.
+    Airborne,
+    /// Climbing a `Climbable`.
+    Climbing,
+    /// Swimming in a `WaterVolume`.
+    Swimming,
+}
+
+/// A scene query made while moving a `CharacterController`, as reported by a
+/// `CharacterTrace`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CharacterQuery<N: RealField> {
+    /// The ray cast straight down looking for ground, and the nearest
+    /// collider it hit, whether walkable or not.
+    GroundRay {
+        origin: Point3<N>,
+        max_toi: N,
+        hit: Option<QueryHit<N>>,
+    },
+    /// The capsule overlap test looking for `Climbable`s and `WaterVolume`s.
+    VolumeOverlap { hits: Vec<Entity> },
+    /// The overlap test of the advanced capsule looking for bodies to push.
+    PushOverlap { hits: Vec<Entity> },
+}
+
+/// How the move of a `CharacterController` has been resolved, as reported by
+/// a `CharacterTrace`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CharacterResolution<N: RealField> {
+    /// Kept on the ground hit by the ground ray, moving down by the
+    /// `correction` to close the gap, or up if it is negative to resolve a
+    /// penetration.
+    Grounded { correction: N },
+    /// The ground ray hit a collider too steep to stand on, so the character
+    /// falls instead.
+    TooSteep { normal: Vector3<N> },
+    /// Moving upwards, ignoring any ground.
+    Jumping,
+    /// Falling without any ground in reach.
+    Falling,
+    /// Climbing the `Climbable` of the given `Entity`.
+    Climbing(Entity),
+    /// Swimming in the `WaterVolume` of the given `Entity`.
+    Swimming(Entity),
+}
+
+/// The `CharacterController` `Component` drives the kinematic `PhysicsBody`
+/// of its `Entity`, see the `MoveCharactersSystem`.
+///
+/// With `slope_snap` enabled, grounded characters move along the ground plane
+/// instead of horizontally and are pulled down onto ground up to the given
+/// distance below their feet, so they follow ramps and small ledges down
+/// instead of launching off them.
+///
+/// # Example
+///
+/// ```rust
+/// use specs_physics::{characters::CharacterController, nalgebra::Vector3};
+///
+/// let mut controller = CharacterController::<f32>::new(0.5, 0.3).with_slope_snap(0.3);
+/// controller.desired_velocity = Vector3::new(4.0, 0.0, 0.0);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CharacterController<N: RealField> {
+    /// The velocity the character is supposed to move with. Only its
+    /// horizontal part is used, unless the character is swimming.
+    pub desired_velocity: Vector3<N>,
+    /// The half height of the cylindrical part of the capsule.
+    pub half_height: N,
+    /// The radius of the capsule.
+    pub radius: N,
+    /// The steepest slope in radians the character can stand on.
+    pub max_slope: N,
+    /// The distance below the feet the character snaps down to the ground,
+    /// or `None` to disable snapping.
+    pub slope_snap: Option<N>,
+    /// The current vertical velocity while airborne.
+    pub vertical_velocity: N,
+    /// The virtual mass with which the character pushes the dynamic bodies
+    /// it walks into, or `None` to not push them. Kinematic bodies push
+    /// dynamic bodies with infinite force, so the `CollisionGroups` of the
+    /// character should not interact with the pushed bodies.
+    pub push_mass: Option<N>,
+    /// Whether the `MoveCharactersSystem` emits a `CharacterTrace` for every
+    /// move of the character.
+    pub debug: bool,
+    pub(crate) state: CharacterState,
+    pub(crate) ground_normal: Vector3<N>,
+    pub(crate) requested_half_height: Option<N>,
+    pub(crate) resize_blocked: bool,
+}
+
+impl<N: RealField> Component for CharacterController<N> {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl<N: RealField> CharacterController<N> {
+    /// Creates a new `CharacterController` for a capsule with the given
+    /// dimensions, which can stand on slopes of up to 45 degrees.
+    pub fn new(half_height: N, radius: N) -> Self {
+        Self {
+            desired_velocity: Vector3::zeros(),
+            half_height,
+            radius,
+            max_slope: N::frac_pi_4(),
+            slope_snap: None,
+            vertical_velocity: N::zero(),
+            push_mass: None,
+            debug: false,
+            state: CharacterState::Airborne,
+            ground_normal: Vector3::y(),
+            requested_half_height: None,
+            resize_blocked: false,
+        }
+    }
+
+    /// Enables snapping to ground up to the given distance below the feet.
+    pub fn with_slope_snap(mut self, distance: N) -> Self {
+        self.slope_snap = Some(distance);
+        self
+    }
+
+    /// Pushes the dynamic bodies the character walks into with the given
+    /// virtual mass.
+    pub fn with_push_mass(mut self, push_mass: N) -> Self {
+        self.push_mass = Some(push_mass);
+        self
+    }
+
+    /// Enables the `CharacterTrace`s of the character.
+    pub fn with_debug(mut self) -> Self {
+        self.debug = true;
+        self
+    }
+
+    /// The movement state after the last move.
+    pub fn state(&self) -> CharacterState {
+        self.state
+    }
+
+    /// Whether the character stood on walkable ground after the last move.
+    pub fn is_grounded(&self) -> bool {
+        self.state == CharacterState::Grounded
+    }
+
+    /// The normal of the ground the character stands on, or the world y axis
+    /// while airborne.
+    pub fn ground_normal(&self) -> &Vector3<N> {
+        &self.ground_normal
+    }
+
+    /// Requests changing the half height of the capsule, e.g. to crouch or
+    /// stand up again, keeping the feet in place. Shrinking always succeeds,
+    /// while growing is delayed by the `ResizeCharactersSystem` as long as the
+    /// taller capsule would overlap other colliders.
+    pub fn resize(&mut self, half_height: N) {
+        self.requested_half_height = Some(half_height);
+        self.resize_blocked = false;
+    }
+
+    /// Withdraws a pending `resize` request.
+    pub fn cancel_resize(&mut self) {
+        self.requested_half_height = None;
+        self.resize_blocked = false;
+    }
+
+    /// The half height requested by `resize` which has not been applied yet.
+    pub fn requested_half_height(&self) -> Option<N> {
+        self.requested_half_height
+    }
+
+    /// The distance from the center of the capsule to its lowest point.
+    pub fn foot_offset(&self) -> N {
+        self.half_height + self.radius
+    }
+
+    /// Whether ground with the given normal is flat enough to stand on.
+    pub fn is_walkable(&self, normal: &Vector3<N>) -> bool {
+        normal.y >= self.max_slope.cos()
+    }
+
+    /// Projects the `desired_velocity` onto the plane with the given normal,
+    /// keeping its horizontal speed, so the character moves along slopes.
+    pub fn velocity_along(&self, normal: &Vector3<N>) -> Vector3<N> {
+        let horizontal = Vector3::new(self.desired_velocity.x, N::zero(), self.desired_velocity.z);
+        if normal.y <= N::default_epsilon() {
+            return horizontal;
+        }
+
+        // the vertical component which keeps the velocity within the plane
+        let rise = -(normal.x * horizontal.x + normal.z * horizontal.z) / normal.y;
+        Vector3::new(horizontal.x, rise, horizontal.z)
+    }
+}
+
+/// The `Climbable` `Component` marks a sensor `PhysicsCollider` as a
+/// climbable volume, e.g. a ladder or a vine-covered wall. While a
+/// `CharacterController` overlaps it, gravity is suppressed and moving
+/// towards the climb surface moves the character up, moving away from it
+/// moves the character down.
+///
+/// # Example
+///
+/// ```rust
+/// use specs_physics::{characters::Climbable, nalgebra::Vector3};
+///
+/// // a ladder leaning against a wall facing towards positive x
+/// let ladder = Climbable::<f32>::new(Vector3::x_axis());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Climbable<N: RealField> {
+    /// The world space normal of the climb surface, pointing towards the
+    /// climbing character.
+    pub normal: Unit<Vector3<N>>,
+    /// The factor the speed towards the surface is scaled with when it is
+    /// mapped to the climbing speed.
+    pub speed: N,
+}
+
+impl<N: RealField> Component for Climbable<N> {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl<N: RealField> Climbable<N> {
+    /// Creates a new `Climbable` climbed at the speed the character walks.
+    pub fn new(normal: Unit<Vector3<N>>) -> Self {
+        Self {
+            normal,
+            speed: N::one(),
+        }
+    }
+
+    /// Maps the desired velocity of a character onto the climb surface:
+    /// movement towards the surface becomes upward movement, sideways
+    /// movement along the surface is kept.
+    pub fn climb_velocity(&self, desired_velocity: &Vector3<N>) -> Vector3<N> {
+        let normal = self.normal.into_inner();
+        let towards = -desired_velocity.dot(&normal);
+        let sideways = desired_velocity - normal * (-towards);
+        Vector3::new(sideways.x, towards * self.speed, sideways.z)
+    }
+}
+
+/// The `WaterVolume` `Component` marks a sensor `PhysicsCollider` as water.
+/// A `CharacterController` overlapping it swims: its `desired_velocity` is
+/// followed in all three dimensions, the `Gravity` is reduced by the
+/// `buoyancy`, a fraction of one for neutral buoyancy, and sinking or rising
+/// is slowed down by the `drag`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WaterVolume<N: RealField> {
+    pub buoyancy: N,
+    pub drag: N,
+}
+
+impl<N: RealField> Component for WaterVolume<N> {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl<N: RealField> WaterVolume<N> {
+    /// Computes the vertical velocity of a swimming character after a step
+    /// of length `dt`.
+    pub fn vertical_velocity(&self, vertical_velocity: N, gravity: N, dt: N) -> N {
+        let accelerated = vertical_velocity + gravity * (N::one() - self.buoyancy) * dt;
+        // implicit drag, which cannot reverse the direction
+        accelerated / (N::one() + self.drag * dt)
+    }
+}
+
+/// The `NavAgent` `Component` converts the desired velocity computed by a
+/// navigation system, e.g. from a path on a navigation mesh, into movement of
+/// the `PhysicsBody` of its `Entity`, limited to `max_speed` and
+/// `max_acceleration`. Obstacles are not avoided locally; collisions with
+/// them are resolved by the physics `World` instead. See the
+/// `ApplyNavAgentsSystem` for how the velocity is applied.
+///
+/// # Example
+///
+/// ```rust
+/// use specs_physics::{characters::NavAgent, nalgebra::Vector3};
+///
+/// let mut agent = NavAgent::<f32>::new(3.5, 10.0);
+/// agent.desired_velocity = Vector3::new(0.0, 0.0, 3.0);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NavAgent<N: RealField> {
+    /// The velocity requested by the navigation system.
+    pub desired_velocity: Vector3<N>,
+    pub max_speed: N,
+    pub max_acceleration: N,
+}
+
+impl<N: RealField> Component for NavAgent<N> {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl<N: RealField> NavAgent<N> {
+    /// Creates a new, resting `NavAgent`.
+    pub fn new(max_speed: N, max_acceleration: N) -> Self {
+        Self {
+            desired_velocity: Vector3::zeros(),
+            max_speed,
+            max_acceleration,
+        }
+    }
+
+    /// Steers the `velocity` towards the `desired_velocity` over a step of
+    /// length `dt` and returns the new velocity.
+    pub fn steer(&self, velocity: &Vector3<N>, dt: N) -> Vector3<N> {
+        let target = clamp_norm(self.desired_velocity, self.max_speed);
+        velocity + clamp_norm(target - velocity, self.max_acceleration * dt)
+    }
+}
+
+fn clamp_norm<N: RealField>(vector: Vector3<N>, max: N) -> Vector3<N> {
+    let norm = vector.norm();
+    if norm > max && norm > N::zero() {
+        vector * (max / norm)
+    } else {
+        vector
+    }
+}
+
+/// The skin width below the feet within which a character counts as standing
+/// on the ground.
+pub(crate) fn ground_tolerance<N: RealField>() -> N {
+    na::convert(0.05)
+}
@@ -1,9 +1,9 @@
-use std::{f32::consts::PI, fmt, ops::Deref};
+use std::{collections::HashMap, f32::consts::PI, fmt, ops::Deref};
 
-use specs::{Component, DenseVecStorage, FlaggedStorage};
+use specs::{Component, FlaggedStorage};
 
 use crate::{
-    nalgebra::{DMatrix, Isometry3, Point2, Point3, RealField, Unit, Vector3},
+    nalgebra::{self as na, DMatrix, Isometry3, Point2, Point3, RealField, Unit, Vector3},
     ncollide::{
         shape::{
             Ball,
@@ -19,12 +19,14 @@ use crate::{
             TriMesh,
             Triangle,
         },
-        world::CollisionGroups,
+        world::{CollisionGroups, GeometricQueryType},
     },
     nphysics::{
         material::{BasicMaterial, MaterialHandle},
         object::ColliderHandle,
     },
+    validation::{is_non_negative, is_positive, InvalidInput},
+    PhysicsStorage,
 };
 
 pub type MeshData<N> = (Vec<Point3<N>>, Vec<Point3<usize>>, Option<Vec<Point2<N>>>);
@@ -54,6 +56,11 @@ impl<'clone, N: RealField> Clone for Box<dyn IntoMesh<N = N> + 'clone> {
 /// having to know the underlying nphysics API.
 #[derive(Clone)]
 pub enum Shape<N: RealField> {
+    /// A `ShapeHandle` which has already been built, e.g. a triangle mesh
+    /// loaded from `BakedShapes`.
+    Baked {
+        handle: ShapeHandle<N>,
+    },
     Ball {
         radius: N,
     },
@@ -74,13 +81,35 @@ pub enum Shape<N: RealField> {
         heights: DMatrix<N>,
         scale: Vector3<N>,
     },
+    /// An infinite half-space bounded by the plane through the origin of the
+    /// collider, with everything behind the plane being solid. A cheaper and
+    /// more robust ground than a giant `Cuboid`; it should only be attached
+    /// to static colliders.
     Plane {
         normal: Unit<Vector3<N>>,
     },
+    /// Segments connecting the `points`, e.g. the outline of 2D terrain in
+    /// the xy plane. Without `indices`, consecutive points are connected.
     Polyline {
         points: Vec<Point3<N>>,
         indices: Option<Vec<Point2<usize>>>,
     },
+    /// A `Cuboid` whose edges and corners are rounded off with the
+    /// `border_radius`, which slides far more smoothly over edges and seams.
+    /// The `half_extents` include the rounding. Built as a convex hull of
+    /// sampled points.
+    RoundedCuboid {
+        half_extents: Vector3<N>,
+        border_radius: N,
+    },
+    /// A cylinder along the y axis whose rims are rounded off with the
+    /// `border_radius`, e.g. a wheel. The `half_height` and `radius` include
+    /// the rounding. Built as a convex hull of sampled points.
+    RoundedCylinder {
+        half_height: N,
+        radius: N,
+        border_radius: N,
+    },
     Segment {
         a: Point3<N>,
         b: Point3<N>,
@@ -99,20 +128,36 @@ impl<N: RealField> Shape<N> {
     /// Converts a `Shape` and its values into its corresponding `ShapeHandle`
     /// type. The `ShapeHandle` is used to define a `Collider` in the
     /// `PhysicsWorld`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `Shape` can not be built, see `try_handle`.
     pub fn handle(&self) -> ShapeHandle<N> {
-        match self {
+        self.try_handle()
+            .unwrap_or_else(|error| panic!("Failed to build shape: {}", error))
+    }
+
+    /// Converts a `Shape` into its `ShapeHandle` like `handle`, but returns
+    /// `InvalidInput::Shape` for convex hulls and rounded shapes which can not
+    /// be built from their values, e.g. coplanar points or rounded shapes
+    /// without volume.
+    pub fn try_handle(&self) -> Result<ShapeHandle<N>, InvalidInput> {
+        Ok(match self {
+            Shape::Baked { handle } => handle.clone(),
             Shape::Ball { radius } => ShapeHandle::new(Ball::<N>::new(*radius)),
             Shape::Capsule {
                 half_height,
                 radius,
             } => ShapeHandle::new(Capsule::new(*half_height, *radius)),
             Shape::Compound { parts } => ShapeHandle::new(Compound::new(
-                parts.iter().map(|part| (part.0, part.1.handle())).collect(),
+                parts
+                    .iter()
+                    .map(|(isometry, part)| part.try_handle().map(|handle| (*isometry, handle)))
+                    .collect::<Result<_, _>>()?,
             )),
-            Shape::ConvexHull { points } => ShapeHandle::new(
-                ConvexHull::try_from_points(&points)
-                    .expect("Failed to generate Convex Hull from points."),
-            ),
+            Shape::ConvexHull { points } => {
+                ShapeHandle::new(ConvexHull::try_from_points(&points).ok_or(InvalidInput::Shape)?)
+            }
             Shape::Cuboid { half_extents } => ShapeHandle::new(Cuboid::new(*half_extents)),
             Shape::HeightField { heights, scale } => {
                 ShapeHandle::new(HeightField::new(heights.clone(), *scale))
@@ -121,16 +166,571 @@ impl<N: RealField> Shape<N> {
             Shape::Polyline { points, indices } => {
                 ShapeHandle::new(Polyline::new(points.clone(), indices.clone()))
             }
+            Shape::RoundedCuboid {
+                half_extents,
+                border_radius,
+            } => ShapeHandle::new(rounded_cuboid(half_extents, *border_radius)?),
+            Shape::RoundedCylinder {
+                half_height,
+                radius,
+                border_radius,
+            } => ShapeHandle::new(rounded_cylinder(*half_height, *radius, *border_radius)?),
             Shape::Segment { a, b } => ShapeHandle::new(Segment::new(*a, *b)),
             Shape::TriMesh { handle } => {
                 let data = handle.points();
                 ShapeHandle::new(TriMesh::new(data.0, data.1, data.2))
             }
             Shape::Triangle { a, b, c } => ShapeHandle::new(Triangle::new(*a, *b, *c)),
+        })
+    }
+
+    /// Returns a `Shape::Plane` facing up the y axis, the half-space of a
+    /// flat ground.
+    pub fn ground() -> Self {
+        Shape::Plane {
+            normal: Vector3::y_axis(),
+        }
+    }
+
+    /// Returns a `Shape::Polyline` chaining the points in order. Closed chains
+    /// also connect the last point to the first one, e.g. the outline of a
+    /// floating island or a hole cut into destructible terrain.
+    pub fn chain(points: Vec<Point3<N>>, closed: bool) -> Self {
+        let mut indices = (1..points.len())
+            .map(|i| Point2::new(i - 1, i))
+            .collect::<Vec<_>>();
+        if closed && points.len() > 2 {
+            indices.push(Point2::new(points.len() - 1, 0));
+        }
+        Shape::Polyline {
+            points,
+            indices: Some(indices),
+        }
+    }
+
+    /// Removes the part at the given index of a `Shape::Compound` and returns
+    /// it, or `None` for other `Shape`s and indices out of bounds.
+    ///
+    /// Changing the parts of the `Shape` of a `PhysicsCollider` keeps its
+    /// collider in the physics `World` and only replaces its shape; with a
+    /// `ShapeCache`, the `ShapeHandle`s of the unchanged parts are reused.
+    /// This allows destruction systems to chip chunks off a compound collider
+    /// without recreating it.
+    pub fn remove_part(&mut self, index: usize) -> Option<(Isometry3<N>, Shape<N>)> {
+        match self {
+            Shape::Compound { parts } if index < parts.len() => Some(parts.remove(index)),
+            _ => None,
+        }
+    }
+
+    /// Adds parts to a `Shape::Compound`, e.g. the fragments of a chunk
+    /// removed with `remove_part`. Returns whether the `Shape` is a
+    /// `Shape::Compound`.
+    pub fn add_parts<I>(&mut self, new_parts: I) -> bool
+    where
+        I: IntoIterator<Item = (Isometry3<N>, Shape<N>)>,
+    {
+        match self {
+            Shape::Compound { parts } => {
+                parts.extend(new_parts);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Replaces the part at the given index of a `Shape::Compound` with its
+    /// fragments and returns the replaced part, or `None` for other `Shape`s
+    /// and indices out of bounds. The fragments are positioned relative to
+    /// the replaced part.
+    pub fn fracture_part<I>(
+        &mut self,
+        index: usize,
+        fragments: I,
+    ) -> Option<(Isometry3<N>, Shape<N>)>
+    where
+        I: IntoIterator<Item = (Isometry3<N>, Shape<N>)>,
+    {
+        let (isometry, part) = self.remove_part(index)?;
+        self.add_parts(
+            fragments
+                .into_iter()
+                .map(|(fragment_isometry, fragment)| (isometry * fragment_isometry, fragment)),
+        );
+        Some((isometry, part))
+    }
+
+    /// Returns the `ShapeKey` identifying this `Shape` by its parameters, or
+    /// `None` for `Shape`s which are not worth or not possible to cache.
+    pub fn key(&self) -> Option<ShapeKey> {
+        let (kind, values): (&'static str, Vec<N>) = match self {
+            Shape::Ball { radius } => ("ball", vec![*radius]),
+            Shape::Capsule {
+                half_height,
+                radius,
+            } => ("capsule", vec![*half_height, *radius]),
+            Shape::ConvexHull { points } => (
+                "convex_hull",
+                points
+                    .iter()
+                    .flat_map(|p| p.coords.iter().cloned())
+                    .collect(),
+            ),
+            Shape::Cuboid { half_extents } => ("cuboid", half_extents.iter().cloned().collect()),
+            Shape::Plane { normal } => ("plane", normal.iter().cloned().collect()),
+            Shape::RoundedCuboid {
+                half_extents,
+                border_radius,
+            } => (
+                "rounded_cuboid",
+                half_extents
+                    .iter()
+                    .cloned()
+                    .chain(Some(*border_radius))
+                    .collect(),
+            ),
+            Shape::RoundedCylinder {
+                half_height,
+                radius,
+                border_radius,
+            } => (
+                "rounded_cylinder",
+                vec![*half_height, *radius, *border_radius],
+            ),
+            Shape::Segment { a, b } => (
+                "segment",
+                a.coords.iter().chain(b.coords.iter()).cloned().collect(),
+            ),
+            Shape::Triangle { a, b, c } => (
+                "triangle",
+                a.coords
+                    .iter()
+                    .chain(b.coords.iter())
+                    .chain(c.coords.iter())
+                    .cloned()
+                    .collect(),
+            ),
+            _ => return None,
+        };
+
+        let bits = values
+            .iter()
+            .map(|value| value.to_subset().map(f64::to_bits))
+            .collect::<Option<Vec<u64>>>()?;
+
+        Some(ShapeKey { kind, bits })
+    }
+}
+
+impl<N: RealField> fmt::Debug for Shape<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Shape::Baked { .. } => f.write_str("Baked { .. }"),
+            Shape::Ball { radius } => f.debug_struct("Ball").field("radius", radius).finish(),
+            Shape::Capsule {
+                half_height,
+                radius,
+            } => f
+                .debug_struct("Capsule")
+                .field("half_height", half_height)
+                .field("radius", radius)
+                .finish(),
+            Shape::Compound { parts } => f.debug_struct("Compound").field("parts", parts).finish(),
+            Shape::ConvexHull { points } => f
+                .debug_struct("ConvexHull")
+                .field("points", points)
+                .finish(),
+            Shape::Cuboid { half_extents } => f
+                .debug_struct("Cuboid")
+                .field("half_extents", half_extents)
+                .finish(),
+            Shape::HeightField { heights, scale } => f
+                .debug_struct("HeightField")
+                .field("heights", heights)
+                .field("scale", scale)
+                .finish(),
+            Shape::Plane { normal } => f.debug_struct("Plane").field("normal", normal).finish(),
+            Shape::Polyline { points, indices } => f
+                .debug_struct("Polyline")
+                .field("points", points)
+                .field("indices", indices)
+                .finish(),
+            Shape::RoundedCuboid {
+                half_extents,
+                border_radius,
+            } => f
+                .debug_struct("RoundedCuboid")
+                .field("half_extents", half_extents)
+                .field("border_radius", border_radius)
+                .finish(),
+            Shape::RoundedCylinder {
+                half_height,
+                radius,
+                border_radius,
+            } => f
+                .debug_struct("RoundedCylinder")
+                .field("half_height", half_height)
+                .field("radius", radius)
+                .field("border_radius", border_radius)
+                .finish(),
+            Shape::Segment { a, b } => f
+                .debug_struct("Segment")
+                .field("a", a)
+                .field("b", b)
+                .finish(),
+            // the mesh is only generated when the collider is created
+            Shape::TriMesh { .. } => f.write_str("TriMesh { .. }"),
+            Shape::Triangle { a, b, c } => f
+                .debug_struct("Triangle")
+                .field("a", a)
+                .field("b", b)
+                .field("c", c)
+                .finish(),
+        }
+    }
+}
+
+/// A compact, single line summary of the `Shape` which omits vertex data.
+impl<N: RealField> fmt::Display for Shape<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Shape::Baked { .. } => f.write_str("Baked"),
+            Shape::Ball { radius } => write!(f, "Ball(radius: {})", radius),
+            Shape::Capsule {
+                half_height,
+                radius,
+            } => write!(
+                f,
+                "Capsule(half_height: {}, radius: {})",
+                half_height, radius
+            ),
+            Shape::Compound { parts } => write!(f, "Compound({} parts)", parts.len()),
+            Shape::ConvexHull { points } => write!(f, "ConvexHull({} points)", points.len()),
+            Shape::Cuboid { half_extents } => write!(
+                f,
+                "Cuboid(half_extents: [{}, {}, {}])",
+                half_extents.x, half_extents.y, half_extents.z
+            ),
+            Shape::HeightField { heights, .. } => write!(
+                f,
+                "HeightField({}x{} heights)",
+                heights.nrows(),
+                heights.ncols()
+            ),
+            Shape::Plane { normal } => write!(
+                f,
+                "Plane(normal: [{}, {}, {}])",
+                normal.x, normal.y, normal.z
+            ),
+            Shape::Polyline { points, .. } => write!(f, "Polyline({} points)", points.len()),
+            Shape::RoundedCuboid {
+                half_extents,
+                border_radius,
+            } => write!(
+                f,
+                "RoundedCuboid(half_extents: [{}, {}, {}], border_radius: {})",
+                half_extents.x, half_extents.y, half_extents.z, border_radius
+            ),
+            Shape::RoundedCylinder {
+                half_height,
+                radius,
+                border_radius,
+            } => write!(
+                f,
+                "RoundedCylinder(half_height: {}, radius: {}, border_radius: {})",
+                half_height, radius, border_radius
+            ),
+            Shape::Segment { .. } => f.write_str("Segment"),
+            Shape::TriMesh { .. } => f.write_str("TriMesh"),
+            Shape::Triangle { .. } => f.write_str("Triangle"),
+        }
+    }
+}
+
+/// The number of subdivisions of a quarter circle of rounded shapes.
+const ROUNDING_SUBDIVISIONS: usize = 4;
+/// The number of segments around the axis of rounded cylinders.
+const CYLINDER_SEGMENTS: usize = 16;
+
+/// Builds the convex hull of a `Shape::RoundedCuboid` from the sampled
+/// rounded corners, one octant of a sphere per corner. The `border_radius` is
+/// clamped to the smallest half extent so the rounding stays within the
+/// `half_extents`.
+fn rounded_cuboid<N: RealField>(
+    half_extents: &Vector3<N>,
+    border_radius: N,
+) -> Result<ConvexHull<N>, InvalidInput> {
+    if !half_extents.iter().all(|value| is_positive(*value)) || !is_non_negative(border_radius) {
+        return Err(InvalidInput::Shape);
+    }
+    let border_radius = border_radius
+        .min(half_extents.x)
+        .min(half_extents.y)
+        .min(half_extents.z);
+    let inner = half_extents.map(|value| value - border_radius);
+    let subdivisions: N = na::convert(ROUNDING_SUBDIVISIONS as f64);
+
+    // the directions within the positive octant, mirrored into all others
+    let mut directions = vec![Vector3::y()];
+    for i in 1..=ROUNDING_SUBDIVISIONS {
+        let polar = N::frac_pi_2() * na::convert(i as f64) / subdivisions;
+        for j in 0..=ROUNDING_SUBDIVISIONS {
+            let azimuth = N::frac_pi_2() * na::convert(j as f64) / subdivisions;
+            directions.push(Vector3::new(
+                polar.sin() * azimuth.cos(),
+                polar.cos(),
+                polar.sin() * azimuth.sin(),
+            ));
+        }
+    }
+
+    let mut points = Vec::with_capacity(directions.len() * 8);
+    for &x in &[N::one(), -N::one()] {
+        for &y in &[N::one(), -N::one()] {
+            for &z in &[N::one(), -N::one()] {
+                let sign = Vector3::new(x, y, z);
+                let corner = inner.component_mul(&sign);
+                points.extend(directions.iter().map(|direction| {
+                    Point3::from(corner + direction.component_mul(&sign) * border_radius)
+                }));
+            }
+        }
+    }
+    ConvexHull::try_from_points(&points).ok_or(InvalidInput::Shape)
+}
+
+/// Builds the convex hull of a `Shape::RoundedCylinder` from the sampled
+/// rounded rims, a quarter circle per segment and rim. The `border_radius` is
+/// clamped like the one of `rounded_cuboid`.
+fn rounded_cylinder<N: RealField>(
+    half_height: N,
+    radius: N,
+    border_radius: N,
+) -> Result<ConvexHull<N>, InvalidInput> {
+    if !is_positive(half_height) || !is_positive(radius) || !is_non_negative(border_radius) {
+        return Err(InvalidInput::Shape);
+    }
+    let border_radius = border_radius.min(half_height).min(radius);
+    let inner_half_height = half_height - border_radius;
+    let inner_radius = radius - border_radius;
+    let subdivisions: N = na::convert(ROUNDING_SUBDIVISIONS as f64);
+    let segments: N = na::convert(CYLINDER_SEGMENTS as f64);
+
+    let mut points = Vec::new();
+    for segment in 0..CYLINDER_SEGMENTS {
+        let azimuth = N::two_pi() * na::convert(segment as f64) / segments;
+        let (sin, cos) = azimuth.sin_cos();
+        for &y in &[N::one(), -N::one()] {
+            for i in 0..=ROUNDING_SUBDIVISIONS {
+                // from the side of the cylinder up to its cap
+                let elevation = N::frac_pi_2() * na::convert(i as f64) / subdivisions;
+                let distance = inner_radius + border_radius * elevation.cos();
+                points.push(Point3::new(
+                    cos * distance,
+                    y * (inner_half_height + border_radius * elevation.sin()),
+                    sin * distance,
+                ));
+            }
+        }
+    }
+    ConvexHull::try_from_points(&points).ok_or(InvalidInput::Shape)
+}
+
+/// The `ShapeKey` identifies a `Shape` by its kind and the exact bit pattern of
+/// its parameters.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ShapeKey {
+    kind: &'static str,
+    bits: Vec<u64>,
+}
+
+/// The `ShapeCache` `Resource` allows identical `Shape`s to share a single
+/// `ShapeHandle`. Creating thousands of colliders with the same dimensions will
+/// otherwise allocate a new `ShapeHandle` per collider.
+///
+/// The cache is opt-in; insert it into the `World` and the
+/// `SyncCollidersToPhysicsSystem` will use it when creating colliders.
+pub struct ShapeCache<N: RealField> {
+    handles: HashMap<ShapeKey, ShapeHandle<N>>,
+    hits: usize,
+    misses: usize,
+}
+
+impl<N: RealField> ShapeCache<N> {
+    /// Creates a new, empty `ShapeCache`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the `ShapeHandle` for the given `Shape`, reusing a previously
+    /// created `ShapeHandle` for identical `Shape`s. The parts of
+    /// `Shape::Compound`s are cached individually.
+    pub fn handle(&mut self, shape: &Shape<N>) -> ShapeHandle<N> {
+        if let Shape::Compound { parts } = shape {
+            return ShapeHandle::new(Compound::new(
+                parts
+                    .iter()
+                    .map(|part| (part.0, self.handle(&part.1)))
+                    .collect(),
+            ));
+        }
+
+        match shape.key() {
+            Some(key) => {
+                if let Some(handle) = self.handles.get(&key) {
+                    self.hits += 1;
+                    return handle.clone();
+                }
+
+                self.misses += 1;
+                let handle = shape.handle();
+                self.handles.insert(key, handle.clone());
+                handle
+            }
+            None => shape.handle(),
+        }
+    }
+
+    /// The number of distinct `ShapeHandle`s held by the cache.
+    pub fn len(&self) -> usize {
+        self.handles.len()
+    }
+
+    /// Whether the cache holds no `ShapeHandle`s.
+    pub fn is_empty(&self) -> bool {
+        self.handles.is_empty()
+    }
+
+    /// The number of lookups which were served from the cache.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// The number of lookups which had to create a new `ShapeHandle`.
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// Drops all cached `ShapeHandle`s. Existing colliders keep their shapes.
+    pub fn clear(&mut self) {
+        self.handles.clear();
+    }
+}
+
+impl<N: RealField> Default for ShapeCache<N> {
+    fn default() -> Self {
+        Self {
+            handles: HashMap::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+}
+
+/// The `QueryGroups` are a bitmask of up to 32 groups used to filter scene
+/// queries such as ray casts. Unlike the `CollisionGroups`, they do not affect
+/// contact generation, so queries can see sensors or objects that do not
+/// collide with anything, and ignore objects that do.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct QueryGroups(pub u32);
+
+impl QueryGroups {
+    /// Member of every group, seen by all queries.
+    pub const ALL: QueryGroups = QueryGroups(u32::max_value());
+    /// Member of no group, invisible to all queries.
+    pub const NONE: QueryGroups = QueryGroups(0);
+
+    /// Returns the `QueryGroups` with the given group, `0..32`, added.
+    pub fn with(self, group: usize) -> Self {
+        assert!(group < 32, "QueryGroups only support the groups 0..32");
+        QueryGroups(self.0 | 1 << group)
+    }
+
+    /// Checks whether the given group, `0..32`, is contained.
+    pub fn contains(self, group: usize) -> bool {
+        group < 32 && self.0 & 1 << group != 0
+    }
+
+    /// Checks whether both `QueryGroups` share at least one group.
+    pub fn intersects(self, other: QueryGroups) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl Default for QueryGroups {
+    fn default() -> Self {
+        QueryGroups::ALL
+    }
+}
+
+/// The `ContactThrottle` limits the `ContactEvent`s of a `PhysicsCollider`,
+/// e.g. to keep rolling or bouncing objects from flooding the `ContactEvents`
+/// channel with micro-contacts. A contact only emits a `Started` event if at
+/// least `min_interval` seconds of simulated time passed since the last
+/// `Started` event of the same pair of colliders and the impulse of the
+/// impact, estimated from the relative velocity and the masses of both
+/// bodies, reaches `min_impulse`. The `Stopped` events of suppressed contacts
+/// are suppressed as well. If both colliders of a contact are throttled, the
+/// stricter limits apply. `System`s reacting to `ContactEvent`s, e.g. the
+/// `ApplyImpactResponsesSystem`, do not see suppressed contacts either.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ContactThrottle<N: RealField> {
+    pub min_interval: N,
+    pub min_impulse: N,
+}
+
+impl<N: RealField> ContactThrottle<N> {
+    /// Combines the limits of two `ContactThrottle`s into the stricter one.
+    pub fn max(self, other: Self) -> Self {
+        Self {
+            min_interval: self.min_interval.max(other.min_interval),
+            min_impulse: self.min_impulse.max(other.min_impulse),
+        }
+    }
+}
+
+impl<N: RealField> Default for ContactThrottle<N> {
+    fn default() -> Self {
+        Self {
+            min_interval: N::zero(),
+            min_impulse: N::zero(),
         }
     }
 }
 
+/// The `EventRole` of a `PhysicsCollider` decides on which side of the
+/// `ContactEvent`s and `ProximityEvent`s it is reported. Events between a
+/// `Source` and a `Target` always report the `Source` as `collider1`, e.g.
+/// with the player as `Source`, matching "player touched X" does not have to
+/// check both orders. Colliders acting as `Both` are reported after `Source`s
+/// and before `Target`s; pairs of equal roles keep the order of the collision
+/// detection.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum EventRole {
+    Source,
+    Target,
+    Both,
+}
+
+impl EventRole {
+    /// Checks whether a pair of colliders reported in the order of their
+    /// roles, `self` first, has to be swapped.
+    pub fn reported_after(self, other: EventRole) -> bool {
+        let rank = |role| match role {
+            EventRole::Source => 0,
+            EventRole::Both => 1,
+            EventRole::Target => 2,
+        };
+        rank(self) > rank(other)
+    }
+}
+
+impl Default for EventRole {
+    fn default() -> Self {
+        EventRole::Both
+    }
+}
+
 /// The `PhysicsCollider` `Component` represents a `Collider` in the physics
 /// world. A physics `Collider` is automatically created when this `Component`
 /// is added to an `Entity`. Value changes are automatically synchronised with
@@ -141,28 +741,50 @@ pub struct PhysicsCollider<N: RealField> {
     pub(crate) handle: Option<ColliderHandle>,
     /// The shape of this collider.
     pub shape: Shape<N>,
-    /// The position/rotation offset of the collider from the entity it is attached to.
+    /// The position/rotation offset of the collider from the entity it is
+    /// attached to.
     pub offset_from_parent: Isometry3<N>,
     pub density: N,
     /// The physics material of which this collider is composed.
     /// Defines properties like bounciness and others.
     pub material: MaterialHandle<N>,
-    /// Margin between the detection zone of what is "near" the collider and the actual collider.
+    /// Margin between the detection zone of what is "near" the collider and the
+    /// actual collider.
     pub margin: N,
     /// Collision groups this collider is part of.
     /// Defines with which other colliders this collider can interact.
     pub collision_groups: CollisionGroups,
+    /// Query groups this collider is part of.
+    /// Defines which scene queries, e.g. ray casts, can see this collider.
+    pub query_groups: QueryGroups,
     /// Prediction amount of the linear momentum.
     pub linear_prediction: N,
     /// Prediction amount of the angular momentum.
     pub angular_prediction: N,
-    /// Whether this collider is a sensor and only emits events without interacting (true) or
-    /// if it is a regular collider (false).
+    /// Whether this collider is a sensor and only emits events without
+    /// interacting (true) or if it is a regular collider (false).
     pub sensor: bool,
+    /// Whether the body of this collider sticks to whatever it touches first,
+    /// e.g. arrows embedding into surfaces. See `StuckTo`.
+    pub sticky: bool,
+    /// Whether the body of this collider stops on contact instead of bouncing
+    /// off, e.g. projectiles coming to rest in their target. See the
+    /// `ApplyStopOnContactSystem`.
+    pub stop_on_contact: bool,
+    /// Whether contacts on the internal edges of this `TriMesh` or
+    /// `HeightField` collider are corrected, so that bodies rolling or
+    /// sliding across flat welded faces are not bumped by the edges between
+    /// them. See the `ApplyInternalEdgeCorrectionSystem`.
+    pub smooth_edges: bool,
+    /// The limits below which the contacts of this collider do not emit
+    /// `ContactEvent`s, if any.
+    pub contact_throttle: Option<ContactThrottle<N>>,
+    /// The side of the events this collider is reported on.
+    pub event_role: EventRole,
 }
 
 impl<N: RealField> Component for PhysicsCollider<N> {
-    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+    type Storage = FlaggedStorage<Self, PhysicsStorage<Self>>;
 }
 
 impl<N: RealField> fmt::Debug for PhysicsCollider<N> {
@@ -171,32 +793,75 @@ impl<N: RealField> fmt::Debug for PhysicsCollider<N> {
             f,
             "PhysicsCollider {{ \
              handle: {:?}, \
+             shape: {:?}, \
              offset_from_parent: {:?}, \
              density: {}, \
              margin: {}, \
              collision_group: {:?}, \
+             query_groups: {:?}, \
              linear_prediction: {}, \
              angular_prediction: {}, \
-             sensor: {} \
+             sensor: {}, \
+             sticky: {}, \
+             stop_on_contact: {}, \
+             smooth_edges: {}, \
+             contact_throttle: {:?}, \
+             event_role: {:?} \
              }}",
             self.handle,
+            self.shape,
             self.offset_from_parent,
             self.density,
             self.margin,
             self.collision_groups,
+            self.query_groups,
             self.linear_prediction,
             self.angular_prediction,
             self.sensor,
+            self.sticky,
+            self.stop_on_contact,
+            self.smooth_edges,
+            self.contact_throttle,
+            self.event_role,
         )?;
         Ok(())
     }
 }
 
+/// A compact, single line summary of the `PhysicsCollider` for logging large
+/// scenes.
+impl<N: RealField> fmt::Display for PhysicsCollider<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} collider with density {}", self.shape, self.density)?;
+        if self.sensor {
+            f.write_str(" (sensor)")?;
+        }
+        Ok(())
+    }
+}
+
 impl<N: RealField> PhysicsCollider<N> {
     /// Returns the `ShapeHandle` for `shape`, taking the `margin` into
-    /// consideration.
-    pub(crate) fn shape_handle(&self) -> ShapeHandle<N> {
-        self.shape.handle()
+    /// consideration. If a `ShapeCache` is given, identical shapes will share
+    /// the same `ShapeHandle`.
+    pub(crate) fn shape_handle(&self, shape_cache: Option<&mut ShapeCache<N>>) -> ShapeHandle<N> {
+        match shape_cache {
+            Some(shape_cache) => shape_cache.handle(&self.shape),
+            None => self.shape.handle(),
+        }
+    }
+
+    /// Returns the `GeometricQueryType` the collider is created with, which
+    /// holds the `margin` and the predictions.
+    pub(crate) fn query_type(&self) -> GeometricQueryType<N> {
+        if self.sensor {
+            GeometricQueryType::Proximity(self.linear_prediction)
+        } else {
+            GeometricQueryType::Contacts(
+                self.margin + self.linear_prediction,
+                self.angular_prediction,
+            )
+        }
     }
 }
 
@@ -233,9 +898,15 @@ pub struct PhysicsColliderBuilder<N: RealField> {
     material: MaterialHandle<N>,
     margin: N,
     collision_groups: CollisionGroups,
+    query_groups: QueryGroups,
     linear_prediction: N,
     angular_prediction: N,
     sensor: bool,
+    sticky: bool,
+    stop_on_contact: bool,
+    smooth_edges: bool,
+    contact_throttle: Option<ContactThrottle<N>>,
+    event_role: EventRole,
 }
 
 impl<N: RealField> From<Shape<N>> for PhysicsColliderBuilder<N> {
@@ -249,9 +920,15 @@ impl<N: RealField> From<Shape<N>> for PhysicsColliderBuilder<N> {
             material: MaterialHandle::new(BasicMaterial::default()),
             margin: N::from_f32(0.2).unwrap(), // default was: 0.01
             collision_groups: CollisionGroups::default(),
+            query_groups: QueryGroups::default(),
             linear_prediction: N::from_f32(0.002).unwrap(),
             angular_prediction: N::from_f32(PI / 180.0 * 5.0).unwrap(),
             sensor: false,
+            sticky: false,
+            stop_on_contact: false,
+            smooth_edges: false,
+            contact_throttle: None,
+            event_role: EventRole::Both,
         }
     }
 }
@@ -287,6 +964,12 @@ impl<N: RealField> PhysicsColliderBuilder<N> {
         self
     }
 
+    /// Sets the `query_groups` value of the `PhysicsColliderBuilder`.
+    pub fn query_groups(mut self, query_groups: QueryGroups) -> Self {
+        self.query_groups = query_groups;
+        self
+    }
+
     /// Sets the `linear_prediction` value of the `PhysicsColliderBuilder`.
     pub fn linear_prediction(mut self, linear_prediction: N) -> Self {
         self.linear_prediction = linear_prediction;
@@ -305,6 +988,36 @@ impl<N: RealField> PhysicsColliderBuilder<N> {
         self
     }
 
+    /// Sets the `sticky` value of the `PhysicsColliderBuilder`.
+    pub fn sticky(mut self, sticky: bool) -> Self {
+        self.sticky = sticky;
+        self
+    }
+
+    /// Sets the `stop_on_contact` value of the `PhysicsColliderBuilder`.
+    pub fn stop_on_contact(mut self, stop_on_contact: bool) -> Self {
+        self.stop_on_contact = stop_on_contact;
+        self
+    }
+
+    /// Sets the `smooth_edges` value of the `PhysicsColliderBuilder`.
+    pub fn smooth_edges(mut self, smooth_edges: bool) -> Self {
+        self.smooth_edges = smooth_edges;
+        self
+    }
+
+    /// Sets the `contact_throttle` value of the `PhysicsColliderBuilder`.
+    pub fn contact_throttle(mut self, contact_throttle: ContactThrottle<N>) -> Self {
+        self.contact_throttle = Some(contact_throttle);
+        self
+    }
+
+    /// Sets the `event_role` value of the `PhysicsColliderBuilder`.
+    pub fn event_role(mut self, event_role: EventRole) -> Self {
+        self.event_role = event_role;
+        self
+    }
+
     /// Builds the `PhysicsCollider` from the values set in the
     /// `PhysicsColliderBuilder` instance.
     pub fn build(self) -> PhysicsCollider<N> {
@@ -316,9 +1029,173 @@ impl<N: RealField> PhysicsColliderBuilder<N> {
             material: self.material,
             margin: self.margin,
             collision_groups: self.collision_groups,
+            query_groups: self.query_groups,
             linear_prediction: self.linear_prediction,
             angular_prediction: self.angular_prediction,
             sensor: self.sensor,
+            sticky: self.sticky,
+            stop_on_contact: self.stop_on_contact,
+            smooth_edges: self.smooth_edges,
+            contact_throttle: self.contact_throttle,
+            event_role: self.event_role,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Shape, ShapeCache};
+    use crate::{
+        nalgebra::{DMatrix, Isometry3, Point3, Vector3},
+        ncollide::shape::{Ball, ShapeHandle},
+        validation::InvalidInput,
+    };
+
+    #[test]
+    fn keep_rounding_within_dimensions() {
+        let cuboid = Shape::RoundedCuboid {
+            half_extents: Vector3::new(1.0f32, 0.1, 1.0),
+            border_radius: 0.5,
+        }
+        .try_handle()
+        .unwrap();
+        let aabb = cuboid.local_aabb();
+        assert!((aabb.maxs().coords - Vector3::new(1.0, 0.1, 1.0)).norm() < 1.0e-5);
+        assert!((aabb.mins().coords + Vector3::new(1.0, 0.1, 1.0)).norm() < 1.0e-5);
+
+        let cylinder = Shape::RoundedCylinder {
+            half_height: 0.25f32,
+            radius: 1.0,
+            border_radius: 0.5,
+        }
+        .try_handle()
+        .unwrap();
+        let aabb = cylinder.local_aabb();
+        assert!((aabb.maxs().y - 0.25).abs() < 1.0e-5);
+        assert!((aabb.maxs().x - 1.0).abs() < 1.0e-5);
+    }
+
+    #[test]
+    fn reject_degenerate_rounded_shapes() {
+        let flat_cuboid = Shape::RoundedCuboid {
+            half_extents: Vector3::new(1.0f32, 0.0, 1.0),
+            border_radius: 0.0,
+        };
+        assert_eq!(flat_cuboid.try_handle().err(), Some(InvalidInput::Shape));
+
+        let invalid_cuboid = Shape::RoundedCuboid {
+            half_extents: Vector3::new(1.0f32, 1.0, 1.0),
+            border_radius: std::f32::NAN,
+        };
+        assert_eq!(invalid_cuboid.try_handle().err(), Some(InvalidInput::Shape));
+
+        let flat_cylinder = Shape::RoundedCylinder {
+            half_height: 1.0f32,
+            radius: 0.0,
+            border_radius: 0.0,
+        };
+        assert_eq!(flat_cylinder.try_handle().err(), Some(InvalidInput::Shape));
+
+        let compound = Shape::Compound {
+            parts: vec![(Isometry3::identity(), flat_cylinder)],
+        };
+        assert_eq!(compound.try_handle().err(), Some(InvalidInput::Shape));
+    }
+
+    #[test]
+    fn share_handles_of_identical_shapes() {
+        let mut cache = ShapeCache::<f32>::new();
+        let ball = Shape::Ball { radius: 0.5 };
+
+        cache.handle(&ball);
+        cache.handle(&ball);
+        assert_eq!((cache.len(), cache.hits(), cache.misses()), (1, 1, 1));
+
+        // shapes of other dimensions or kinds get their own handles
+        cache.handle(&Shape::Ball { radius: 0.25 });
+        cache.handle(&Shape::Cuboid {
+            half_extents: Vector3::repeat(0.5),
+        });
+        assert_eq!((cache.len(), cache.hits(), cache.misses()), (3, 1, 3));
+
+        // the parts of compounds are looked up individually
+        cache.handle(&Shape::Compound {
+            parts: vec![
+                (Isometry3::translation(-1.0, 0.0, 0.0), ball.clone()),
+                (Isometry3::translation(1.0, 0.0, 0.0), ball.clone()),
+            ],
+        });
+        assert_eq!((cache.len(), cache.hits(), cache.misses()), (3, 3, 3));
+
+        // shapes without a key bypass the cache
+        cache.handle(&Shape::Polyline {
+            points: vec![Point3::origin(), Point3::new(1.0, 0.0, 0.0)],
+            indices: None,
+        });
+        assert_eq!((cache.len(), cache.hits(), cache.misses()), (3, 3, 3));
+
+        cache.clear();
+        assert!(cache.is_empty());
+        cache.handle(&ball);
+        assert_eq!((cache.len(), cache.hits(), cache.misses()), (1, 3, 4));
+    }
+
+    #[test]
+    fn format_shapes() {
+        let ball = Shape::Ball { radius: 0.5f32 };
+        assert_eq!(format!("{}", ball), "Ball(radius: 0.5)");
+        assert_eq!(format!("{:?}", ball), "Ball { radius: 0.5 }");
+
+        let capsule = Shape::Capsule {
+            half_height: 1.5f32,
+            radius: 0.25,
+        };
+        assert_eq!(
+            format!("{}", capsule),
+            "Capsule(half_height: 1.5, radius: 0.25)"
+        );
+        assert_eq!(
+            format!("{:?}", capsule),
+            "Capsule { half_height: 1.5, radius: 0.25 }"
+        );
+
+        let cuboid = Shape::Cuboid {
+            half_extents: Vector3::new(0.5f32, 1.5, 2.5),
+        };
+        assert_eq!(
+            format!("{}", cuboid),
+            "Cuboid(half_extents: [0.5, 1.5, 2.5])"
+        );
+
+        let rounded_cuboid = Shape::RoundedCuboid {
+            half_extents: Vector3::new(0.5f32, 1.5, 2.5),
+            border_radius: 0.125,
+        };
+        assert_eq!(
+            format!("{}", rounded_cuboid),
+            "RoundedCuboid(half_extents: [0.5, 1.5, 2.5], border_radius: 0.125)"
+        );
+
+        // large shapes are summarised instead of listing all of their data
+        let compound = Shape::Compound {
+            parts: vec![(Isometry3::identity(), ball.clone()); 2],
+        };
+        assert_eq!(format!("{}", compound), "Compound(2 parts)");
+        let height_field = Shape::HeightField {
+            heights: DMatrix::<f32>::zeros(2, 3),
+            scale: Vector3::repeat(1.0),
+        };
+        assert_eq!(format!("{}", height_field), "HeightField(2x3 heights)");
+        let polyline = Shape::Polyline {
+            points: vec![Point3::<f32>::origin(); 4],
+            indices: None,
+        };
+        assert_eq!(format!("{}", polyline), "Polyline(4 points)");
+
+        let baked = Shape::Baked {
+            handle: ShapeHandle::new(Ball::new(0.5f32)),
+        };
+        assert_eq!(format!("{}", baked), "Baked");
+        assert_eq!(format!("{:?}", baked), "Baked { .. }");
+    }
+}
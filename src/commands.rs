@@ -0,0 +1,151 @@
+//! # Commands module
+//! Deferred mutations of the physics `World`. Any `System` can queue
+//! `PhysicsCommand`s through a shared `Read<PhysicsCommands<N>>`, so these
+//! `System`s can run in parallel instead of waiting for the write lock of the
+//! `Physics` `Resource`. The `ApplyPhysicsCommandsSystem` applies all queued
+//! commands in order right before the simulation is stepped.
+
+use std::sync::{Mutex, PoisonError};
+
+use specs::Entity;
+
+use crate::{
+    nalgebra::{Isometry3, RealField},
+    nphysics::algebra::{Force3, Velocity3},
+};
+
+/// A single mutation of the physics `World` queued in the `PhysicsCommands`.
+#[derive(Copy, Clone, Debug)]
+pub enum PhysicsCommand<N: RealField> {
+    /// Applies an impulse at the center of mass of the body of the `Entity`.
+    ApplyImpulse { entity: Entity, impulse: Force3<N> },
+    /// Replaces the velocity of the body of the `Entity`.
+    SetVelocity {
+        entity: Entity,
+        velocity: Velocity3<N>,
+    },
+    /// Moves the body of the `Entity` to the given pose without sweeping it
+    /// through the space in between.
+    Teleport {
+        entity: Entity,
+        isometry: Isometry3<N>,
+    },
+    /// Removes the body of the `Entity` from the physics `World` together
+    /// with its `PhysicsBody` `Component`.
+    RemoveBody { entity: Entity },
+}
+
+impl<N: RealField> PhysicsCommand<N> {
+    /// The `Entity` whose body is mutated by this command.
+    pub fn entity(&self) -> Entity {
+        match self {
+            PhysicsCommand::ApplyImpulse { entity, .. }
+            | PhysicsCommand::SetVelocity { entity, .. }
+            | PhysicsCommand::Teleport { entity, .. }
+            | PhysicsCommand::RemoveBody { entity } => *entity,
+        }
+    }
+
+    /// Returns the same command targeting the body of another `Entity`, e.g.
+    /// of a replayed one.
+    pub(crate) fn with_entity(mut self, other: Entity) -> Self {
+        match &mut self {
+            PhysicsCommand::ApplyImpulse { entity, .. }
+            | PhysicsCommand::SetVelocity { entity, .. }
+            | PhysicsCommand::Teleport { entity, .. }
+            | PhysicsCommand::RemoveBody { entity } => *entity = other,
+        }
+        self
+    }
+}
+
+/// The `PhysicsCommands` `Resource` queues `PhysicsCommand`s until the
+/// `ApplyPhysicsCommandsSystem` applies them. Queueing only requires shared
+/// access to the `Resource`.
+///
+/// # Example
+///
+/// ```rust
+/// use specs::{Builder, World, WorldExt};
+/// use specs_physics::{commands::PhysicsCommands, nalgebra::Isometry3};
+///
+/// let mut world = World::new();
+/// world.insert(PhysicsCommands::<f32>::default());
+///
+/// let entity = world.create_entity().build();
+/// world
+///     .read_resource::<PhysicsCommands<f32>>()
+///     .teleport(entity, Isometry3::translation(0.0, 10.0, 0.0));
+/// ```
+#[derive(Debug)]
+pub struct PhysicsCommands<N: RealField> {
+    queue: Mutex<Vec<PhysicsCommand<N>>>,
+}
+
+impl<N: RealField> PhysicsCommands<N> {
+    /// Queues the given `PhysicsCommand`.
+    pub fn push(&self, command: PhysicsCommand<N>) {
+        self.queue
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(command);
+    }
+
+    /// Queues an impulse at the center of mass of the body of the `Entity`.
+    pub fn apply_impulse(&self, entity: Entity, impulse: Force3<N>) {
+        self.push(PhysicsCommand::ApplyImpulse { entity, impulse });
+    }
+
+    /// Queues replacing the velocity of the body of the `Entity`.
+    pub fn set_velocity(&self, entity: Entity, velocity: Velocity3<N>) {
+        self.push(PhysicsCommand::SetVelocity { entity, velocity });
+    }
+
+    /// Queues moving the body of the `Entity` to the given pose.
+    pub fn teleport(&self, entity: Entity, isometry: Isometry3<N>) {
+        self.push(PhysicsCommand::Teleport { entity, isometry });
+    }
+
+    /// Queues removing the body of the `Entity`.
+    pub fn remove_body(&self, entity: Entity) {
+        self.push(PhysicsCommand::RemoveBody { entity });
+    }
+
+    /// The number of queued commands.
+    pub fn len(&self) -> usize {
+        self.queue
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Whether no commands are queued.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copies the queued commands without removing them, e.g. to record them.
+    pub(crate) fn queued(&self) -> Vec<PhysicsCommand<N>> {
+        self.queue
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Removes all queued commands in the order they were queued; the
+    /// exclusive borrow makes locking unnecessary.
+    pub(crate) fn drain(&mut self) -> impl Iterator<Item = PhysicsCommand<N>> + '_ {
+        self.queue
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .drain(..)
+    }
+}
+
+impl<N: RealField> Default for PhysicsCommands<N> {
+    fn default() -> Self {
+        Self {
+            queue: Mutex::new(Vec::new()),
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! # Debug module
+//! Resources for visualising the physics `World`. The `DebugRenderSystem`
+//! fills the `DebugRender` `Resource` with lines every frame, which can be
+//! drawn by any renderer capable of drawing debug lines.
+
+use crate::nalgebra::{self as na, Isometry3, Point3, RealField, Rotation3, Unit, Vector3};
+
+/// RGBA colour of a `DebugLine`.
+pub type DebugColor = [f32; 4];
+
+/// Colour of joint anchors.
+pub const ANCHOR_COLOR: DebugColor = [1.0, 1.0, 0.0, 1.0];
+/// Colour of joint axes.
+pub const AXIS_COLOR: DebugColor = [0.0, 1.0, 1.0, 1.0];
+/// Colour of joint limits.
+pub const LIMIT_COLOR: DebugColor = [1.0, 0.5, 0.0, 1.0];
+/// Colour of motor targets.
+pub const MOTOR_COLOR: DebugColor = [1.0, 0.0, 1.0, 1.0];
+/// Colour of collider bounding boxes.
+pub const AABB_COLOR: DebugColor = [0.5, 0.5, 0.5, 1.0];
+/// Colour of collider shapes.
+pub const SHAPE_COLOR: DebugColor = [0.0, 1.0, 0.0, 1.0];
+/// Colour of contact points and normals.
+pub const CONTACT_COLOR: DebugColor = [1.0, 0.0, 0.0, 1.0];
+/// Colour of sensor overlaps.
+pub const SENSOR_COLOR: DebugColor = [0.0, 0.5, 1.0, 1.0];
+/// Colour of body velocities.
+pub const VELOCITY_COLOR: DebugColor = [0.0, 0.0, 1.0, 1.0];
+/// Colour of centers of mass.
+pub const CENTER_OF_MASS_COLOR: DebugColor = [1.0, 1.0, 1.0, 1.0];
+
+/// The number of segments used to draw a full circle.
+const CIRCLE_SEGMENTS: usize = 32;
+
+/// A single line in world space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DebugLine<N: RealField> {
+    pub start: Point3<N>,
+    pub end: Point3<N>,
+    pub color: DebugColor,
+}
+
+/// The `DebugRender` `Resource` contains the lines drawn by the
+/// `DebugRenderSystem` during the last frame. Each category of lines can be
+/// toggled individually to keep busy scenes readable.
+#[derive(Clone, Debug)]
+pub struct DebugRender<N: RealField> {
+    pub lines: Vec<DebugLine<N>>,
+    /// Draw the bounding boxes of all colliders.
+    pub aabbs: bool,
+    /// Draw the shapes of all colliders.
+    pub shapes: bool,
+    /// Draw contact points and their normals.
+    pub contacts: bool,
+    /// Draw lines between intersecting sensors and colliders.
+    pub sensors: bool,
+    /// Draw the linear velocity of all bodies as arrows.
+    pub velocities: bool,
+    /// Draw joint anchors, axes, limits and motor targets.
+    pub joints: bool,
+    /// Draw the centers of mass of all dynamic bodies.
+    pub centers_of_mass: bool,
+}
+
+impl<N: RealField> Default for DebugRender<N> {
+    /// Draws everything but bounding boxes and velocities, which quickly
+    /// clutter larger scenes.
+    fn default() -> Self {
+        Self {
+            lines: Vec::new(),
+            aabbs: false,
+            shapes: true,
+            contacts: true,
+            sensors: true,
+            velocities: false,
+            joints: true,
+            centers_of_mass: true,
+        }
+    }
+}
+
+impl<N: RealField> DebugRender<N> {
+    /// Removes all lines.
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    /// Adds a line from `start` to `end`.
+    pub fn line(&mut self, start: Point3<N>, end: Point3<N>, color: DebugColor) {
+        self.lines.push(DebugLine { start, end, color });
+    }
+
+    /// Adds three axis aligned lines crossing at `center`.
+    pub fn cross(&mut self, center: Point3<N>, half_size: N, color: DebugColor) {
+        for axis in &[Vector3::x(), Vector3::y(), Vector3::z()] {
+            let offset = axis * half_size;
+            self.line(center - offset, center + offset, color);
+        }
+    }
+
+    /// Adds an arrow from `start` along `vector`.
+    pub fn arrow(&mut self, start: Point3<N>, vector: Vector3<N>, color: DebugColor) {
+        let end = start + vector;
+        self.line(start, end, color);
+
+        let length = vector.norm();
+        if length > N::default_epsilon() {
+            let direction = Unit::new_unchecked(vector / length);
+            let head = perpendicular(&direction) * (length * na::convert(0.1));
+            let back = vector * na::convert::<f64, N>(0.2);
+            self.line(end, end - back + head, color);
+            self.line(end, end - back - head, color);
+        }
+    }
+
+    /// Adds the twelve edges of a box with the given `half_extents` placed at
+    /// `isometry`.
+    pub fn cuboid(
+        &mut self,
+        isometry: &Isometry3<N>,
+        half_extents: &Vector3<N>,
+        color: DebugColor,
+    ) {
+        let corner = |x: N, y: N, z: N| {
+            isometry * Point3::new(x * half_extents.x, y * half_extents.y, z * half_extents.z)
+        };
+        let (one, minus_one) = (N::one(), -N::one());
+
+        for &(a, b) in &[
+            (one, one),
+            (one, minus_one),
+            (minus_one, one),
+            (minus_one, minus_one),
+        ] {
+            self.line(corner(minus_one, a, b), corner(one, a, b), color);
+            self.line(corner(a, minus_one, b), corner(a, one, b), color);
+            self.line(corner(a, b, minus_one), corner(a, b, one), color);
+        }
+    }
+
+    /// Adds a circle with the given `radius` around the `axis` through
+    /// `center`.
+    pub fn circle(
+        &mut self,
+        center: Point3<N>,
+        axis: &Unit<Vector3<N>>,
+        radius: N,
+        color: DebugColor,
+    ) {
+        let from = perpendicular(axis) * radius;
+        self.arc(center, axis, from, N::two_pi(), color);
+    }
+
+    /// Adds an arc around the `axis` through `center`, starting at
+    /// `center + from` and spanning the given `angle`.
+    pub fn arc(
+        &mut self,
+        center: Point3<N>,
+        axis: &Unit<Vector3<N>>,
+        from: Vector3<N>,
+        angle: N,
+        color: DebugColor,
+    ) {
+        let segments = segment_count(angle);
+        let step = Rotation3::from_axis_angle(axis, angle / na::convert(segments as f64));
+
+        let mut offset = from;
+        for _ in 0..segments {
+            let next = step * offset;
+            self.line(center + offset, center + next, color);
+            offset = next;
+        }
+    }
+}
+
+/// Returns an arbitrary unit vector perpendicular to the given `axis`.
+fn perpendicular<N: RealField>(axis: &Unit<Vector3<N>>) -> Vector3<N> {
+    let other = if axis.x.abs() < na::convert(0.9) {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    };
+    axis.cross(&other).normalize()
+}
+
+/// The number of segments needed to draw an arc of the given `angle` smoothly.
+fn segment_count<N: RealField>(angle: N) -> usize {
+    let fraction = angle.abs() / N::two_pi() * na::convert(CIRCLE_SEGMENTS as f64);
+    (0..CIRCLE_SEGMENTS)
+        .find(|segments| na::convert::<f64, N>(*segments as f64) >= fraction)
+        .unwrap_or(CIRCLE_SEGMENTS)
+        .max(1)
+}
@@ -0,0 +1,195 @@
+//! # Diagnostics module
+//! Resources for controlling the log verbosity of the physics `System`s and
+//! for receiving structured diagnostics events instead of log lines.
+
+use std::fmt;
+
+use log::{Level, LevelFilter};
+use specs::world::Index;
+
+use crate::{
+    events::PhysicsEventChannel,
+    handles::JointOwner,
+    shrev::EventChannel,
+    validation::InvalidInput,
+};
+
+/// The `LogSource` identifies the physics `System` a log line or diagnostic
+/// originates from.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum LogSource {
+    /// `SyncBodiesToPhysicsSystem` and `SyncBodiesFromPhysicsSystem`.
+    Bodies,
+    /// `SyncCollidersToPhysicsSystem`.
+    Colliders,
+    /// The `System`s synchronising joints, e.g.
+    /// `SyncWheelJointsToPhysicsSystem`.
+    Joints,
+    /// `SyncParametersToPhysicsSystem`.
+    Parameters,
+    /// `PhysicsStepperSystem`.
+    Stepper,
+}
+
+/// The `PhysicsLogConfig` controls the maximum log level per physics
+/// `System`. Large scenes can turn down the verbosity of the systems that
+/// log on every insert/update without silencing the rest of the crate.
+///
+/// If `structured` is set, log lines are replaced by `PhysicsDiagnostic`
+/// events written to the `PhysicsDiagnostics` channel.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PhysicsLogConfig {
+    pub bodies: LevelFilter,
+    pub colliders: LevelFilter,
+    pub joints: LevelFilter,
+    pub parameters: LevelFilter,
+    pub stepper: LevelFilter,
+    pub structured: bool,
+}
+
+impl PhysicsLogConfig {
+    /// Creates a `PhysicsLogConfig` with the same `LevelFilter` for every
+    /// `System`.
+    pub fn with_level(level: LevelFilter) -> Self {
+        Self {
+            bodies: level,
+            colliders: level,
+            joints: level,
+            parameters: level,
+            stepper: level,
+            structured: false,
+        }
+    }
+
+    /// Returns the `LevelFilter` configured for the given `LogSource`.
+    pub fn level(&self, source: LogSource) -> LevelFilter {
+        match source {
+            LogSource::Bodies => self.bodies,
+            LogSource::Colliders => self.colliders,
+            LogSource::Joints => self.joints,
+            LogSource::Parameters => self.parameters,
+            LogSource::Stepper => self.stepper,
+        }
+    }
+
+    /// Checks whether a message of the given `Level` should be emitted for the
+    /// given `LogSource`.
+    pub fn enabled(&self, source: LogSource, level: Level) -> bool {
+        level <= self.level(source)
+    }
+}
+
+impl Default for PhysicsLogConfig {
+    /// Defaults to `LevelFilter::Trace` for all `System`s, which defers the
+    /// filtering to the global logger.
+    fn default() -> Self {
+        Self::with_level(LevelFilter::Trace)
+    }
+}
+
+/// The `DiagnosticKind` describes what happened in a physics `System`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiagnosticKind {
+    BodyInserted(Index),
+    BodyUpdated(Index),
+    BodyRemoved(Index),
+    OrphanedBodyRemoved(Index),
+    /// A body left the `ActivationRadius` of every `SimulationFocus`.
+    BodyDeactivated(Index),
+    BodyReactivated(Index),
+    ColliderInserted(Index),
+    ColliderUpdated(Index),
+    ColliderRemoved(Index),
+    ColliderUpdateDeferred(Index),
+    ColliderUpdateDropped(Index),
+    OrphanedColliderRemoved(Index),
+    /// A `Component` failed validation and was not synchronised to the
+    /// physics `World`; only emitted with the "validation" feature.
+    InvalidInput(Index, InvalidInput),
+    JointInserted(Index),
+    JointRemoved(Index),
+    /// A joint `Component` was ignored, as its `Entity` already owns the
+    /// constraint of another one.
+    JointConflict(Index, JointOwner),
+    CommandApplied(Index),
+    /// A `PhysicsCommand` targeted an `Entity` without a body.
+    CommandDropped(Index),
+    ConfigApplied,
+    GravityChanged,
+    ProfilingToggled(bool),
+    IntegrationParametersChanged,
+    TimeStepChanged,
+    /// A non-positive `TimeStep` was ignored.
+    TimeStepRejected,
+    StepsDropped(usize),
+    /// Events exceeding the `EventCapacity` were dropped.
+    EventsDropped(PhysicsEventChannel, usize),
+}
+
+/// The `PhysicsDiagnostic` is the structured counterpart of a log line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhysicsDiagnostic {
+    pub source: LogSource,
+    pub level: Level,
+    pub kind: DiagnosticKind,
+}
+
+/// `PhysicsDiagnostics` is a custom `EventChannel` type used to expose
+/// `PhysicsDiagnostic`s.
+pub type PhysicsDiagnostics = EventChannel<PhysicsDiagnostic>;
+
+/// Per-`System` helper which either logs a message or emits a
+/// `PhysicsDiagnostic`, depending on the `PhysicsLogConfig`.
+pub(crate) struct SystemLogger<'a> {
+    config: PhysicsLogConfig,
+    source: LogSource,
+    diagnostics: &'a mut PhysicsDiagnostics,
+}
+
+impl<'a> SystemLogger<'a> {
+    pub(crate) fn new(
+        config: Option<&PhysicsLogConfig>,
+        source: LogSource,
+        diagnostics: &'a mut PhysicsDiagnostics,
+    ) -> Self {
+        Self {
+            config: config.cloned().unwrap_or_default(),
+            source,
+            diagnostics,
+        }
+    }
+
+    /// Logs the `message` with the given `target`, which should be the
+    /// `module_path!()` of the call site so log filters keep working per
+    /// module, or emits it as a `PhysicsDiagnostic` in structured mode.
+    pub(crate) fn log(
+        &mut self,
+        target: &str,
+        level: Level,
+        kind: DiagnosticKind,
+        message: fmt::Arguments,
+    ) {
+        if !self.config.enabled(self.source, level) {
+            return;
+        }
+
+        if self.config.structured {
+            self.diagnostics.single_write(PhysicsDiagnostic {
+                source: self.source,
+                level,
+                kind,
+            });
+        } else {
+            log!(target: target, level, "{}", message);
+        }
+    }
+
+    /// Logs a `Level::Debug` message without a `DiagnosticKind`, e.g. a single
+    /// `ComponentEvent`. These messages are tracing aids only and are dropped
+    /// in structured mode.
+    pub(crate) fn debug(&mut self, target: &str, message: fmt::Arguments) {
+        if self.config.enabled(self.source, Level::Debug) && !self.config.structured {
+            log!(target: target, Level::Debug, "{}", message);
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use std::{f32::consts::PI, fmt};
+
+use specs::{Component, DenseVecStorage, FlaggedStorage};
+
+use crate::{
+    dim2::{
+        ncollide::{
+            shape::{Ball, Capsule, ConvexPolygon, Cuboid, Plane, Polyline, Segment, ShapeHandle},
+            world::CollisionGroups,
+        },
+        nphysics::{
+            material::{BasicMaterial, MaterialHandle},
+            object::ColliderHandle,
+        },
+    },
+    nalgebra::{Isometry2, Point2, RealField, Unit, Vector2},
+    validation::InvalidInput,
+};
+
+/// The 2D `Shape`s a `PhysicsCollider` can have, all lying in the xy plane.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape<N: RealField> {
+    Ball {
+        radius: N,
+    },
+    Capsule {
+        half_height: N,
+        radius: N,
+    },
+    /// The convex hull of the `points`.
+    ConvexPolygon {
+        points: Vec<Point2<N>>,
+    },
+    Cuboid {
+        half_extents: Vector2<N>,
+    },
+    /// An infinite half-plane bounded by the line through the origin of the
+    /// collider, with everything behind the line being solid.
+    Plane {
+        normal: Unit<Vector2<N>>,
+    },
+    /// Segments connecting the `points`, e.g. the outline of terrain. Without
+    /// `indices`, consecutive points are connected.
+    Polyline {
+        points: Vec<Point2<N>>,
+        indices: Option<Vec<Point2<usize>>>,
+    },
+    Segment {
+        a: Point2<N>,
+        b: Point2<N>,
+    },
+}
+
+impl<N: RealField> Shape<N> {
+    /// Converts a `Shape` and its values into its corresponding `ShapeHandle`
+    /// type. The `ShapeHandle` is used to define a `Collider` in the physics
+    /// `World`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `Shape` can not be built, see `try_handle`.
+    pub fn handle(&self) -> ShapeHandle<N> {
+        self.try_handle()
+            .unwrap_or_else(|error| panic!("Failed to build 2D shape: {}", error))
+    }
+
+    /// Converts a `Shape` into its `ShapeHandle` like `handle`, but returns
+    /// `InvalidInput::Shape` for convex polygons which can not be built from
+    /// their points, e.g. collinear ones.
+    pub fn try_handle(&self) -> Result<ShapeHandle<N>, InvalidInput> {
+        Ok(match self {
+            Shape::Ball { radius } => ShapeHandle::new(Ball::<N>::new(*radius)),
+            Shape::Capsule {
+                half_height,
+                radius,
+            } => ShapeHandle::new(Capsule::new(*half_height, *radius)),
+            Shape::ConvexPolygon { points } => ShapeHandle::new(
+                ConvexPolygon::try_from_points(&points).ok_or(InvalidInput::Shape)?,
+            ),
+            Shape::Cuboid { half_extents } => ShapeHandle::new(Cuboid::new(*half_extents)),
+            Shape::Plane { normal } => ShapeHandle::new(Plane::new(*normal)),
+            Shape::Polyline { points, indices } => {
+                ShapeHandle::new(Polyline::new(points.clone(), indices.clone()))
+            }
+            Shape::Segment { a, b } => ShapeHandle::new(Segment::new(*a, *b)),
+        })
+    }
+
+    /// Returns a `Shape::Polyline` chaining the points in order. Closed chains
+    /// also connect the last point to the first one, e.g. the outline of a
+    /// platform or a cave.
+    pub fn chain(points: Vec<Point2<N>>, closed: bool) -> Self {
+        let mut indices = (1..points.len())
+            .map(|i| Point2::new(i - 1, i))
+            .collect::<Vec<_>>();
+        if closed && points.len() > 2 {
+            indices.push(Point2::new(points.len() - 1, 0));
+        }
+        Shape::Polyline {
+            points,
+            indices: Some(indices),
+        }
+    }
+}
+
+/// The `PhysicsCollider` `Component` represents a `Collider` in the 2D
+/// physics `World`. A physics `Collider` is automatically created when this
+/// `Component` is added to an `Entity`, and is attached to the `PhysicsBody`
+/// of the same `Entity` if there is one.
+#[derive(Clone)]
+pub struct PhysicsCollider<N: RealField> {
+    /// The handle to the collider in the physics world.
+    pub(crate) handle: Option<ColliderHandle>,
+    /// The shape of this collider.
+    pub shape: Shape<N>,
+    /// The position/rotation offset of the collider from the entity it is
+    /// attached to.
+    pub offset_from_parent: Isometry2<N>,
+    pub density: N,
+    /// The physics material of which this collider is composed.
+    pub material: MaterialHandle<N>,
+    /// Margin between the detection zone of what is "near" the collider and the
+    /// actual collider.
+    pub margin: N,
+    /// Collision groups this collider is part of.
+    pub collision_groups: CollisionGroups,
+    /// Prediction amount of the linear momentum.
+    pub linear_prediction: N,
+    /// Prediction amount of the angular momentum.
+    pub angular_prediction: N,
+    /// Whether this collider is a sensor and only emits events without
+    /// interacting (true) or if it is a regular collider (false).
+    pub sensor: bool,
+}
+
+impl<N: RealField> Component for PhysicsCollider<N> {
+    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+}
+
+impl<N: RealField> fmt::Debug for PhysicsCollider<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PhysicsCollider {{ \
+             handle: {:?}, \
+             shape: {:?}, \
+             offset_from_parent: {:?}, \
+             density: {}, \
+             margin: {}, \
+             collision_group: {:?}, \
+             linear_prediction: {}, \
+             angular_prediction: {}, \
+             sensor: {} \
+             }}",
+            self.handle,
+            self.shape,
+            self.offset_from_parent,
+            self.density,
+            self.margin,
+            self.collision_groups,
+            self.linear_prediction,
+            self.angular_prediction,
+            self.sensor,
+        )?;
+        Ok(())
+    }
+}
+
+/// The `PhysicsColliderBuilder` implements the builder pattern for 2D
+/// `PhysicsCollider`s.
+///
+/// # Example
+///
+/// ```rust
+/// use specs_physics::{
+///     dim2::colliders::{PhysicsColliderBuilder, Shape},
+///     nalgebra::{Isometry2, Vector2},
+/// };
+///
+/// let physics_collider = PhysicsColliderBuilder::from(Shape::Cuboid {
+///     half_extents: Vector2::new(10.0, 0.5),
+/// })
+/// .offset_from_parent(Isometry2::identity())
+/// .density(1.2)
+/// .build();
+/// ```
+pub struct PhysicsColliderBuilder<N: RealField> {
+    shape: Shape<N>,
+    offset_from_parent: Isometry2<N>,
+    density: N,
+    material: MaterialHandle<N>,
+    margin: N,
+    collision_groups: CollisionGroups,
+    linear_prediction: N,
+    angular_prediction: N,
+    sensor: bool,
+}
+
+impl<N: RealField> From<Shape<N>> for PhysicsColliderBuilder<N> {
+    /// Creates a new `PhysicsColliderBuilder` from the given `Shape`. This
+    /// also populates the `PhysicsCollider` with sane defaults.
+    fn from(shape: Shape<N>) -> Self {
+        Self {
+            shape,
+            offset_from_parent: Isometry2::identity(),
+            density: N::from_f32(1.3).unwrap(),
+            material: MaterialHandle::new(BasicMaterial::default()),
+            margin: N::from_f32(0.01).unwrap(),
+            collision_groups: CollisionGroups::default(),
+            linear_prediction: N::from_f32(0.002).unwrap(),
+            angular_prediction: N::from_f32(PI / 180.0 * 5.0).unwrap(),
+            sensor: false,
+        }
+    }
+}
+
+impl<N: RealField> PhysicsColliderBuilder<N> {
+    /// Sets the `offset_from_parent` value of the `PhysicsColliderBuilder`.
+    pub fn offset_from_parent(mut self, offset_from_parent: Isometry2<N>) -> Self {
+        self.offset_from_parent = offset_from_parent;
+        self
+    }
+
+    /// Sets the `density` value of the `PhysicsColliderBuilder`.
+    pub fn density(mut self, density: N) -> Self {
+        self.density = density;
+        self
+    }
+
+    /// Sets the `material` value of the `PhysicsColliderBuilder`.
+    pub fn material(mut self, material: MaterialHandle<N>) -> Self {
+        self.material = material;
+        self
+    }
+
+    /// Sets the `margin` value of the `PhysicsColliderBuilder`.
+    pub fn margin(mut self, margin: N) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Sets the `collision_groups` value of the `PhysicsColliderBuilder`.
+    pub fn collision_groups(mut self, collision_groups: CollisionGroups) -> Self {
+        self.collision_groups = collision_groups;
+        self
+    }
+
+    /// Sets the `linear_prediction` value of the `PhysicsColliderBuilder`.
+    pub fn linear_prediction(mut self, linear_prediction: N) -> Self {
+        self.linear_prediction = linear_prediction;
+        self
+    }
+
+    /// Sets the `angular_prediction` value of the `PhysicsColliderBuilder`.
+    pub fn angular_prediction(mut self, angular_prediction: N) -> Self {
+        self.angular_prediction = angular_prediction;
+        self
+    }
+
+    /// Sets the `sensor` value of the `PhysicsColliderBuilder`.
+    pub fn sensor(mut self, sensor: bool) -> Self {
+        self.sensor = sensor;
+        self
+    }
+
+    /// Builds the `PhysicsCollider` from the values set in the
+    /// `PhysicsColliderBuilder` instance.
+    pub fn build(self) -> PhysicsCollider<N> {
+        PhysicsCollider {
+            handle: None,
+            shape: self.shape,
+            offset_from_parent: self.offset_from_parent,
+            density: self.density,
+            material: self.material,
+            margin: self.margin,
+            collision_groups: self.collision_groups,
+            linear_prediction: self.linear_prediction,
+            angular_prediction: self.angular_prediction,
+            sensor: self.sensor,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Shape;
+    use crate::{
+        nalgebra::{Point2, Vector2},
+        validation::InvalidInput,
+    };
+
+    #[test]
+    fn chain_points() {
+        let points = vec![
+            Point2::new(0.0f32, 0.0),
+            Point2::new(1.0, 0.0),
+            Point2::new(1.0, 1.0),
+        ];
+        let indices = |shape| match shape {
+            Shape::Polyline { indices, .. } => indices.unwrap(),
+            shape => panic!("unexpected shape: {:?}", shape),
+        };
+
+        assert_eq!(
+            indices(Shape::chain(points.clone(), false)),
+            vec![Point2::new(0, 1), Point2::new(1, 2)]
+        );
+        assert_eq!(
+            indices(Shape::chain(points, true)),
+            vec![Point2::new(0, 1), Point2::new(1, 2), Point2::new(2, 0)]
+        );
+    }
+
+    #[test]
+    fn reject_collinear_convex_polygon() {
+        let collinear = Shape::ConvexPolygon {
+            points: vec![
+                Point2::new(0.0f32, 0.0),
+                Point2::new(1.0, 0.0),
+                Point2::new(2.0, 0.0),
+            ],
+        };
+        assert_eq!(collinear.try_handle().err(), Some(InvalidInput::Shape));
+
+        let cuboid = Shape::Cuboid {
+            half_extents: Vector2::new(1.0f32, 1.0),
+        };
+        assert!(cuboid.try_handle().is_ok());
+    }
+}
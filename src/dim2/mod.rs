@@ -0,0 +1,216 @@
+//! # 2D module
+//! A first-class 2D mode built on nphysics2d and ncollide2d, enabled with the
+//! "dim2" feature. Games living in a plane do not pay for a third dimension
+//! or have to pin their bodies to a fake z coordinate.
+//!
+//! The module mirrors the core of the 3D API: `Position`s are `Isometry2`s,
+//! the `PhysicsBody` and `PhysicsCollider` `Component`s describe the objects
+//! in the 2D physics `World`, and `physics_dispatcher` builds a `Dispatcher`
+//! running the 2D versions of the `SyncBodiesToPhysicsSystem`, the
+//! `SyncCollidersToPhysicsSystem`, the `SyncParametersToPhysicsSystem`, the
+//! `PhysicsStepperSystem` and the `SyncBodiesFromPhysicsSystem`. Contacts and
+//! proximities are reported through the regular `ContactEvents` and
+//! `ProximityEvents` channels, and the `TimeStep`, `DeltaTime`,
+//! `PhysicsProfilingEnabled` and `PhysicsIntegrationParameters` `Resource`s
+//! are shared with the 3D mode. Joints, characters,
+//! queries and the other extensions of the 3D mode are not available in 2D
+//! yet.
+//!
+//! # Example
+//!
+//! ```rust
+//! use specs::{Builder, World, WorldExt};
+//! use specs_physics::dim2::{
+//!     self,
+//!     bodies::{PhysicsBodyBuilder, SimplePosition},
+//!     colliders::{PhysicsColliderBuilder, Shape},
+//!     nphysics::object::BodyStatus,
+//!     Gravity,
+//! };
+//! use specs_physics::nalgebra::{Isometry2, Vector2};
+//!
+//! let mut world = World::new();
+//! let mut dispatcher = dim2::physics_dispatcher::<f32, SimplePosition<f32>>();
+//! dispatcher.setup(&mut world);
+//! world.insert(Gravity(Vector2::new(0.0, -9.81)));
+//!
+//! world
+//!     .create_entity()
+//!     .with(SimplePosition::<f32>(Isometry2::translation(0.0, 2.0)))
+//!     .with(
+//!         PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+//!             .gravity_enabled(true)
+//!             .build(),
+//!     )
+//!     .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+//!     .build();
+//! dispatcher.dispatch(&world);
+//! ```
+
+use std::collections::HashMap;
+
+use specs::{world::Index, Dispatcher, DispatcherBuilder, Entity};
+
+pub use ncollide2d as ncollide;
+pub use nphysics2d as nphysics;
+
+use crate::{
+    handles::entity_from_user_data,
+    nalgebra::{Isometry2, RealField, Vector2},
+};
+
+use self::{
+    bodies::Position,
+    nphysics::{
+        object::{BodyHandle, ColliderHandle},
+        world::World,
+    },
+    systems::{
+        PhysicsStepperSystem,
+        SyncBodiesFromPhysicsSystem,
+        SyncBodiesToPhysicsSystem,
+        SyncCollidersToPhysicsSystem,
+        SyncParametersToPhysicsSystem,
+    },
+};
+
+pub mod bodies;
+pub mod colliders;
+pub mod systems;
+
+/// The 2D counterpart of the `Physics` `Resource`, holding the nphysics2d
+/// `World` and the handles of the objects owned by `Entity`s.
+pub struct Physics<N: RealField> {
+    pub(crate) world: World<N>,
+    pub(crate) body_handles: HashMap<Index, BodyHandle>,
+    pub(crate) collider_handles: HashMap<Index, ColliderHandle>,
+    pub(crate) tick: u64,
+}
+
+impl<N: RealField> Physics<N> {
+    /// Creates a new instance of the physics structure.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reports the internal value for the timestep.
+    /// See also `TimeStep` for setting this value.
+    pub fn timestep(&self) -> N {
+        self.world.timestep()
+    }
+
+    /// Reports the number of steps simulated so far, which is also the tick
+    /// index of the last step.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Reports the internal value for the gravity.
+    /// See also `Gravity` for setting this value.
+    pub fn gravity(&self) -> &Vector2<N> {
+        self.world.gravity()
+    }
+
+    /// Retrieves the `Entity` owning the given collider.
+    pub fn collider_entity(&self, handle: ColliderHandle) -> Option<Entity> {
+        entity_from_user_data(self.world.collider(handle)?.user_data())
+    }
+
+    /// Retrieves the `Entity` owning the given body.
+    pub fn body_entity(&self, handle: BodyHandle) -> Option<Entity> {
+        entity_from_user_data(self.world.rigid_body(handle)?.user_data())
+    }
+
+    /// Retrieves the position of the body of the given `Entity` in the
+    /// physics `World`.
+    pub fn body_position(&self, entity: Entity) -> Option<Isometry2<N>> {
+        let handle = self.body_handles.get(&entity.id())?;
+        Some(*self.world.rigid_body(*handle)?.position())
+    }
+}
+
+impl<N: RealField> Default for Physics<N> {
+    fn default() -> Self {
+        Self {
+            world: World::new(),
+            body_handles: HashMap::new(),
+            collider_handles: HashMap::new(),
+            tick: 0,
+        }
+    }
+}
+
+/// The `Gravity` `Resource` of the 2D mode, applied to the physics `World` by
+/// the `SyncParametersToPhysicsSystem`. Defaults to no gravity.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Gravity<N: RealField>(pub Vector2<N>);
+
+impl<N: RealField> Default for Gravity<N> {
+    fn default() -> Self {
+        Self(Vector2::zeros())
+    }
+}
+
+/// Convenience function for configuring and building a `Dispatcher` with all
+/// required 2D physics related `System`s.
+///
+/// # Examples
+/// ```
+/// use specs_physics::dim2::{self, bodies::SimplePosition};
+/// let dispatcher = dim2::physics_dispatcher::<f32, SimplePosition<f32>>();
+/// ```
+pub fn physics_dispatcher<'a, 'b, N, P>() -> Dispatcher<'a, 'b>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    let mut dispatcher_builder = DispatcherBuilder::new();
+    register_physics_systems::<N, P>(&mut dispatcher_builder);
+
+    dispatcher_builder.build()
+}
+
+/// Convenience function for registering all required 2D physics related
+/// `System`s to the given `DispatcherBuilder`.
+pub fn register_physics_systems<N, P>(dispatcher_builder: &mut DispatcherBuilder)
+where
+    N: RealField,
+    P: Position<N>,
+{
+    // bodies first, as colliders are attached to their parent bodies
+    dispatcher_builder.add(
+        SyncBodiesToPhysicsSystem::<N, P>::default(),
+        "sync_bodies_to_physics_system_2d",
+        &[],
+    );
+    dispatcher_builder.add(
+        SyncCollidersToPhysicsSystem::<N, P>::default(),
+        "sync_colliders_to_physics_system_2d",
+        &["sync_bodies_to_physics_system_2d"],
+    );
+
+    // the simulation parameters are independent of the objects
+    dispatcher_builder.add(
+        SyncParametersToPhysicsSystem::<N>::default(),
+        "sync_parameters_to_physics_system_2d",
+        &[],
+    );
+
+    // step the World once all objects and parameters are synchronised
+    dispatcher_builder.add(
+        PhysicsStepperSystem::<N>::default(),
+        "physics_stepper_system_2d",
+        &[
+            "sync_bodies_to_physics_system_2d",
+            "sync_colliders_to_physics_system_2d",
+            "sync_parameters_to_physics_system_2d",
+        ],
+    );
+
+    // write the simulated poses back to the Components
+    dispatcher_builder.add(
+        SyncBodiesFromPhysicsSystem::<N, P>::default(),
+        "sync_bodies_from_physics_system_2d",
+        &["physics_stepper_system_2d"],
+    );
+}
@@ -0,0 +1,16 @@
+//! The 2D versions of the core physics `System`s. They reuse the
+//! `ComponentEvent` bookkeeping of their 3D counterparts.
+
+pub use self::{
+    physics_stepper::PhysicsStepperSystem,
+    sync_bodies_from_physics::SyncBodiesFromPhysicsSystem,
+    sync_bodies_to_physics::SyncBodiesToPhysicsSystem,
+    sync_colliders_to_physics::SyncCollidersToPhysicsSystem,
+    sync_parameters_to_physics::SyncParametersToPhysicsSystem,
+};
+
+mod physics_stepper;
+mod sync_bodies_from_physics;
+mod sync_bodies_to_physics;
+mod sync_colliders_to_physics;
+mod sync_parameters_to_physics;
@@ -0,0 +1,249 @@
+use log::Level;
+use specs::{Read, System, SystemData, World, Write, WriteExpect};
+
+use crate::{
+    diagnostics::{DiagnosticKind, LogSource, PhysicsDiagnostics, PhysicsLogConfig, SystemLogger},
+    dim2::{
+        ncollide::{events::ContactEvent as NContactEvent, query::Proximity as NProximity},
+        nphysics::object::ColliderHandle,
+        Physics,
+    },
+    events::{ContactEvent, ContactEvents, ContactType, ProximityEvent, ProximityEvents},
+    handles::entity_from_user_data,
+    nalgebra::RealField,
+    ncollide::query::Proximity,
+    parameters::{DeltaTime, TimeStep},
+};
+
+/// The 2D `PhysicsStepperSystem` progresses the 2D physics `World`, applying
+/// the `TimeStep` `Resource` first. Like its 3D counterpart, it accumulates
+/// the elapsed time of a `DeltaTime` `Resource` and progresses the `World` in
+/// as many fixed `TimeStep`s as fit into the accumulator; without a
+/// `DeltaTime` the `World` is progressed once per dispatch. The contacts and
+/// proximities of every step are published as regular `ContactEvent`s and
+/// `ProximityEvent`s.
+pub struct PhysicsStepperSystem<N> {
+    accumulator: N,
+}
+
+impl<'s, N: RealField> System<'s> for PhysicsStepperSystem<N> {
+    type SystemData = (
+        Option<Read<'s, TimeStep<N>>>,
+        Option<Read<'s, DeltaTime<N>>>,
+        WriteExpect<'s, Physics<N>>,
+        Write<'s, ContactEvents>,
+        Write<'s, ProximityEvents>,
+        Option<Read<'s, PhysicsLogConfig>>,
+        Write<'s, PhysicsDiagnostics>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            time_step,
+            delta_time,
+            mut physics,
+            mut contact_events,
+            mut proximity_events,
+            log_config,
+            mut diagnostics,
+        ) = data;
+        let mut logger = SystemLogger::new(
+            log_config.as_ref().map(|config| &**config),
+            LogSource::Stepper,
+            &mut diagnostics,
+        );
+
+        if let Some(time_step) = time_step {
+            // non-positive timesteps would never drain the accumulator and are
+            // rejected, keeping the previous timestep of the World
+            if time_step.0 <= N::zero() {
+                logger.log(
+                    module_path!(),
+                    Level::Error,
+                    DiagnosticKind::TimeStepRejected,
+                    format_args!(
+                        "Rejected non-positive TimeStep {:?}, keeping 2D worlds timestep {}",
+                        time_step.0,
+                        physics.world.timestep()
+                    ),
+                );
+            } else if physics.world.timestep() != time_step.0 {
+                logger.log(
+                    module_path!(),
+                    Level::Warn,
+                    DiagnosticKind::TimeStepChanged,
+                    format_args!(
+                        "TimeStep and world.timestep() differ, changing 2D worlds timestep \
+                         from {} to: {:?}",
+                        physics.world.timestep(),
+                        time_step.0
+                    ),
+                );
+                physics.world.set_timestep(time_step.0);
+            }
+        }
+
+        // determine the number of substeps to run this frame; without a DeltaTime
+        // resource the world is progressed exactly once per dispatch
+        let timestep = physics.world.timestep();
+        let substeps = match delta_time {
+            Some(delta_time) => {
+                self.accumulator += delta_time.0;
+                let substeps = (self.accumulator / timestep).floor().max(N::zero());
+                self.accumulator -= substeps * timestep;
+                substeps
+                    .to_subset()
+                    .map_or(0, |substeps: f64| substeps as usize)
+            }
+            None => 1,
+        };
+
+        for _ in 0..substeps {
+            physics.world.step();
+            physics.tick += 1;
+            write_events(
+                &physics,
+                &mut contact_events,
+                &mut proximity_events,
+                &mut logger,
+            );
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("dim2::PhysicsStepperSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+    }
+}
+
+impl<N: RealField> Default for PhysicsStepperSystem<N> {
+    fn default() -> Self {
+        Self {
+            accumulator: N::zero(),
+        }
+    }
+}
+
+/// Publishes the contacts and proximities of the last step, skipping the
+/// events of colliders without an `Entity`.
+fn write_events<N: RealField>(
+    physics: &Physics<N>,
+    contact_events: &mut ContactEvents,
+    proximity_events: &mut ProximityEvents,
+    logger: &mut SystemLogger,
+) {
+    let entity = |handle: ColliderHandle| {
+        physics
+            .world
+            .collider(handle)
+            .and_then(|collider| entity_from_user_data(collider.user_data()))
+    };
+    let collider_world = physics.world.collider_world();
+
+    contact_events.iter_write(
+        collider_world
+            .contact_events()
+            .iter()
+            .filter_map(|contact_event| {
+                logger.debug(
+                    module_path!(),
+                    format_args!("Got 2D ContactEvent: {:?}", contact_event),
+                );
+                let (handle1, handle2, contact_type) = match contact_event {
+                    NContactEvent::Started(handle1, handle2) => {
+                        (*handle1, *handle2, ContactType::Started)
+                    }
+                    NContactEvent::Stopped(handle1, handle2) => {
+                        (*handle1, *handle2, ContactType::Stopped)
+                    }
+                };
+                Some(ContactEvent {
+                    collider1: entity(handle1)?,
+                    collider2: entity(handle2)?,
+                    contact_type,
+                    tick: physics.tick,
+                })
+            }),
+    );
+
+    proximity_events.iter_write(collider_world.proximity_events().iter().filter_map(
+        |proximity_event| {
+            logger.debug(
+                module_path!(),
+                format_args!("Got 2D ProximityEvent: {:?}", proximity_event),
+            );
+            Some(ProximityEvent {
+                collider1: entity(proximity_event.collider1)?,
+                collider2: entity(proximity_event.collider2)?,
+                prev_status: proximity(proximity_event.prev_status),
+                new_status: proximity(proximity_event.new_status),
+                tick: physics.tick,
+            })
+        },
+    ));
+}
+
+/// Maps the ncollide2d `Proximity` to the one reported by `ProximityEvent`s.
+fn proximity(proximity: NProximity) -> Proximity {
+    match proximity {
+        NProximity::Intersecting => Proximity::Intersecting,
+        NProximity::WithinMargin => Proximity::WithinMargin,
+        NProximity::Disjoint => Proximity::Disjoint,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        dim2::{systems::PhysicsStepperSystem, Physics},
+        parameters::{DeltaTime, TimeStep},
+    };
+
+    #[test]
+    fn reject_non_positive_timestep() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                PhysicsStepperSystem::<f32>::default(),
+                "physics_stepper_system_2d",
+                &[],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+
+        // the default timestep of 1/60s is kept, fitting three substeps
+        world.insert(TimeStep(0.0f32));
+        world.insert(DeltaTime(0.055f32));
+        dispatcher.dispatch(&world);
+
+        let physics = world.read_resource::<Physics<f32>>();
+        assert!(physics.timestep() > 0.0);
+        assert_eq!(physics.tick(), 3);
+    }
+
+    #[test]
+    fn accumulate_delta_time() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                PhysicsStepperSystem::<f32>::default(),
+                "physics_stepper_system_2d",
+                &[],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+        world.insert(TimeStep(0.25f32));
+
+        // the remainder of a frame is carried over to the next one
+        world.insert(DeltaTime(0.375f32));
+        dispatcher.dispatch(&world);
+        assert_eq!(world.read_resource::<Physics<f32>>().tick(), 1);
+        dispatcher.dispatch(&world);
+        assert_eq!(world.read_resource::<Physics<f32>>().tick(), 3);
+    }
+}
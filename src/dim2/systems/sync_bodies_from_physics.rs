@@ -0,0 +1,150 @@
+use std::marker::PhantomData;
+
+use specs::{Join, ReadExpect, System, SystemData, World, WriteStorage};
+
+use crate::{
+    dim2::{
+        bodies::{PhysicsBody, Position},
+        nphysics::object::Body,
+        Physics,
+    },
+    nalgebra::RealField,
+};
+
+/// The 2D `SyncBodiesFromPhysicsSystem` writes the updated poses and
+/// velocities of the `RigidBody`s in the 2D physics `World` back to their
+/// `Position` and `PhysicsBody` `Component`s. Sleeping bodies are skipped.
+pub struct SyncBodiesFromPhysicsSystem<N, P> {
+    n_marker: PhantomData<N>,
+    p_marker: PhantomData<P>,
+}
+
+impl<'s, N, P> System<'s> for SyncBodiesFromPhysicsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    type SystemData = (
+        ReadExpect<'s, Physics<N>>,
+        WriteStorage<'s, PhysicsBody<N>>,
+        WriteStorage<'s, P>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (physics, mut physics_bodies, mut positions) = data;
+
+        // only awake bodies are written, so that sleeping bodies do not flag
+        // their Components as modified every frame
+        for (mut physics_body, mut position) in (
+            &mut physics_bodies.restrict_mut(),
+            &mut positions.restrict_mut(),
+        )
+            .join()
+        {
+            let rigid_body = match physics_body
+                .get_unchecked()
+                .handle
+                .and_then(|handle| physics.world.rigid_body(handle))
+            {
+                Some(rigid_body) if rigid_body.is_active() => rigid_body,
+                _ => continue,
+            };
+
+            position
+                .get_mut_unchecked()
+                .set_isometry(rigid_body.position());
+            physics_body
+                .get_mut_unchecked()
+                .update_from_physics_world(rigid_body);
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("dim2::SyncBodiesFromPhysicsSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+    }
+}
+
+impl<N, P> Default for SyncBodiesFromPhysicsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+            p_marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        dim2::{
+            bodies::{PhysicsBodyBuilder, SimplePosition},
+            colliders::{PhysicsColliderBuilder, Shape},
+            nphysics::object::BodyStatus,
+            Gravity,
+        },
+        events::{ContactEvents, ContactType},
+        nalgebra::{Isometry2, Vector2},
+    };
+
+    #[test]
+    fn drop_ball_onto_ground() {
+        let mut world = World::new();
+        let mut dispatcher = crate::dim2::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+        world.insert(Gravity(Vector2::<f32>::new(0.0, -9.81)));
+        let mut reader_id = world.write_resource::<ContactEvents>().register_reader();
+
+        let ground = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry2::identity()))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Cuboid {
+                    half_extents: Vector2::new(10.0, 0.5),
+                })
+                .build(),
+            )
+            .build();
+        let ball = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry2::translation(0.0, 3.0)))
+            .with(
+                PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                    .gravity_enabled(true)
+                    .build(),
+            )
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .build();
+
+        for _ in 0..120 {
+            dispatcher.dispatch(&world);
+        }
+
+        // the ball came to rest on top of the ground
+        let y = world
+            .read_storage::<SimplePosition<f32>>()
+            .get(ball)
+            .unwrap()
+            .0
+            .translation
+            .vector
+            .y;
+        assert!((y - 1.0).abs() < 0.1);
+
+        let contact_events = world.read_resource::<ContactEvents>();
+        assert!(contact_events.read(&mut reader_id).any(|event| {
+            event.contact_type == ContactType::Started
+                && (event.collider1 == ball || event.collider2 == ball)
+                && (event.collider1 == ground || event.collider2 == ground)
+        }));
+    }
+}
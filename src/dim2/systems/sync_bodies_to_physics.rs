@@ -0,0 +1,231 @@
+use std::marker::PhantomData;
+
+use log::Level;
+use specs::{
+    storage::ComponentEvent,
+    world::Index,
+    Entities,
+    Entity,
+    Join,
+    Read,
+    ReadStorage,
+    ReaderId,
+    System,
+    SystemData,
+    World,
+    Write,
+    WriteExpect,
+    WriteStorage,
+};
+
+use crate::{
+    diagnostics::{DiagnosticKind, LogSource, PhysicsDiagnostics, PhysicsLogConfig, SystemLogger},
+    dim2::{
+        bodies::{PhysicsBody, Position},
+        Physics,
+    },
+    nalgebra::RealField,
+    systems::{iterate_component_events, rescan_components, ComponentEvents},
+};
+
+/// The 2D `SyncBodiesToPhysicsSystem` handles the synchronisation of
+/// `PhysicsBody` `Component`s into the 2D physics `World`.
+pub struct SyncBodiesToPhysicsSystem<N, P> {
+    positions_reader_id: Option<ReaderId<ComponentEvent>>,
+    physics_bodies_reader_id: Option<ReaderId<ComponentEvent>>,
+    position_events: ComponentEvents,
+    physics_body_events: ComponentEvents,
+    rescan: bool,
+
+    n_marker: PhantomData<N>,
+    p_marker: PhantomData<P>,
+}
+
+impl<'s, N, P> System<'s> for SyncBodiesToPhysicsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, P>,
+        WriteExpect<'s, Physics<N>>,
+        WriteStorage<'s, PhysicsBody<N>>,
+        Option<Read<'s, PhysicsLogConfig>>,
+        Write<'s, PhysicsDiagnostics>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, positions, mut physics, mut physics_bodies, log_config, mut diagnostics) =
+            data;
+        let mut logger = SystemLogger::new(
+            log_config.as_ref().map(|config| &**config),
+            LogSource::Bodies,
+            &mut diagnostics,
+        );
+
+        // collect all ComponentEvents for the Position storage
+        iterate_component_events(
+            &positions,
+            self.positions_reader_id.as_mut().unwrap(),
+            &mut self.position_events,
+            &mut logger,
+        );
+
+        // collect all ComponentEvents for the PhysicsBody storage
+        iterate_component_events(
+            &physics_bodies,
+            self.physics_bodies_reader_id.as_mut().unwrap(),
+            &mut self.physics_body_events,
+            &mut logger,
+        );
+
+        // PhysicsBodies inserted before the reader id was registered
+        if self.rescan {
+            self.rescan = false;
+            rescan_components(
+                &physics_bodies,
+                &mut self.physics_body_events.inserted,
+                |id| physics.body_handles.contains_key(&id),
+                &mut logger,
+            );
+        }
+
+        let (position_events, physics_body_events) =
+            (&self.position_events, &self.physics_body_events);
+
+        // handle removed events first, so that re-inserted PhysicsBodies are
+        // not removed again
+        for id in (&position_events.removed | &physics_body_events.removed).join() {
+            logger.debug(
+                module_path!(),
+                format_args!("Removed 2D PhysicsBody with id: {}", id),
+            );
+            remove_rigid_body(id, &mut physics, &mut logger);
+        }
+
+        // handle inserted events
+        for (entity, position, physics_body, _) in (
+            &entities,
+            &positions,
+            &mut physics_bodies,
+            &position_events.inserted | &physics_body_events.inserted,
+        )
+            .join()
+        {
+            logger.debug(
+                module_path!(),
+                format_args!("Inserted 2D PhysicsBody with id: {}", entity.id()),
+            );
+            add_rigid_body(entity, position, &mut physics, physics_body, &mut logger);
+        }
+
+        // handle modified events
+        for (position, physics_body, id) in (
+            &positions,
+            &mut physics_bodies,
+            &position_events.modified | &physics_body_events.modified,
+        )
+            .join()
+        {
+            let rigid_body = match physics_body
+                .handle
+                .and_then(|handle| physics.world.rigid_body_mut(handle))
+            {
+                Some(rigid_body) => rigid_body,
+                None => continue,
+            };
+
+            // the PhysicsBody was modified, update everything but the position
+            if physics_body_events.modified.contains(id) {
+                physics_body.apply_to_physics_world(rigid_body);
+            }
+            // the Position was modified, update the position directly
+            if position_events.modified.contains(id) {
+                rigid_body.set_position(*position.isometry());
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("dim2::SyncBodiesToPhysicsSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+
+        // register reader id for the Position storage
+        let mut position_storage: WriteStorage<P> = SystemData::fetch(&res);
+        self.positions_reader_id = Some(position_storage.register_reader());
+
+        // register reader id for the PhysicsBody storage
+        let mut physics_body_storage: WriteStorage<PhysicsBody<N>> = SystemData::fetch(&res);
+        self.physics_bodies_reader_id = Some(physics_body_storage.register_reader());
+        self.rescan = true;
+    }
+}
+
+impl<N, P> Default for SyncBodiesToPhysicsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    fn default() -> Self {
+        Self {
+            positions_reader_id: None,
+            physics_bodies_reader_id: None,
+            position_events: ComponentEvents::default(),
+            physics_body_events: ComponentEvents::default(),
+            rescan: false,
+            n_marker: PhantomData,
+            p_marker: PhantomData,
+        }
+    }
+}
+
+fn add_rigid_body<N, P>(
+    entity: Entity,
+    position: &P,
+    physics: &mut Physics<N>,
+    physics_body: &mut PhysicsBody<N>,
+    logger: &mut SystemLogger,
+) where
+    N: RealField,
+    P: Position<N>,
+{
+    // a PhysicsBody re-inserted without being removed replaces its body
+    remove_rigid_body(entity.id(), physics, logger);
+
+    let handle = physics_body
+        .to_rigid_body_desc()
+        .position(*position.isometry())
+        .user_data(entity)
+        .build(&mut physics.world)
+        .handle();
+
+    physics_body.handle = Some(handle);
+    physics.body_handles.insert(entity.id(), handle);
+
+    logger.log(
+        module_path!(),
+        Level::Info,
+        DiagnosticKind::BodyInserted(entity.id()),
+        format_args!(
+            "Inserted 2D rigid body to world with values: {:?}",
+            physics_body
+        ),
+    );
+}
+
+fn remove_rigid_body<N: RealField>(id: Index, physics: &mut Physics<N>, logger: &mut SystemLogger) {
+    if let Some(handle) = physics.body_handles.remove(&id) {
+        // remove body if it still exists in the PhysicsWorld
+        physics.world.remove_bodies(&[handle]);
+        logger.log(
+            module_path!(),
+            Level::Info,
+            DiagnosticKind::BodyRemoved(id),
+            format_args!("Removed 2D rigid body from world with id: {}", id),
+        );
+    }
+}
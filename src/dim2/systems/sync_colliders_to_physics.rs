@@ -0,0 +1,313 @@
+use std::marker::PhantomData;
+
+use log::Level;
+use specs::{
+    storage::ComponentEvent,
+    world::Index,
+    Entities,
+    Entity,
+    Join,
+    Read,
+    ReadStorage,
+    ReaderId,
+    System,
+    SystemData,
+    World,
+    Write,
+    WriteExpect,
+    WriteStorage,
+};
+
+use crate::{
+    diagnostics::{DiagnosticKind, LogSource, PhysicsDiagnostics, PhysicsLogConfig, SystemLogger},
+    dim2::{
+        bodies::Position,
+        colliders::PhysicsCollider,
+        nphysics::object::{BodyPartHandle, ColliderDesc},
+        Physics,
+    },
+    nalgebra::{Isometry2, RealField},
+    systems::{iterate_component_events, rescan_components, ComponentEvents},
+};
+
+/// The 2D `SyncCollidersToPhysicsSystem` handles the synchronisation of
+/// `PhysicsCollider` `Component`s into the 2D physics `World`. Colliders are
+/// attached to the `PhysicsBody` of their own `Entity`, or to the ground at
+/// their `Position` if there is none.
+pub struct SyncCollidersToPhysicsSystem<N, P> {
+    positions_reader_id: Option<ReaderId<ComponentEvent>>,
+    physics_colliders_reader_id: Option<ReaderId<ComponentEvent>>,
+    position_events: ComponentEvents,
+    physics_collider_events: ComponentEvents,
+    rescan: bool,
+
+    n_marker: PhantomData<N>,
+    p_marker: PhantomData<P>,
+}
+
+impl<'s, N, P> System<'s> for SyncCollidersToPhysicsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, P>,
+        WriteExpect<'s, Physics<N>>,
+        WriteStorage<'s, PhysicsCollider<N>>,
+        Option<Read<'s, PhysicsLogConfig>>,
+        Write<'s, PhysicsDiagnostics>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, positions, mut physics, mut physics_colliders, log_config, mut diagnostics) =
+            data;
+        let mut logger = SystemLogger::new(
+            log_config.as_ref().map(|config| &**config),
+            LogSource::Colliders,
+            &mut diagnostics,
+        );
+
+        // collect all ComponentEvents for the Position storage
+        iterate_component_events(
+            &positions,
+            self.positions_reader_id.as_mut().unwrap(),
+            &mut self.position_events,
+            &mut logger,
+        );
+
+        // collect all ComponentEvents for the PhysicsCollider storage
+        iterate_component_events(
+            &physics_colliders,
+            self.physics_colliders_reader_id.as_mut().unwrap(),
+            &mut self.physics_collider_events,
+            &mut logger,
+        );
+
+        // PhysicsColliders inserted before the reader id was registered
+        if self.rescan {
+            self.rescan = false;
+            rescan_components(
+                &physics_colliders,
+                &mut self.physics_collider_events.inserted,
+                |id| physics.collider_handles.contains_key(&id),
+                &mut logger,
+            );
+        }
+
+        let (position_events, physics_collider_events) =
+            (&self.position_events, &self.physics_collider_events);
+
+        // handle removed events first, so that re-inserted PhysicsColliders
+        // are not removed again
+        for id in (&position_events.removed | &physics_collider_events.removed).join() {
+            logger.debug(
+                module_path!(),
+                format_args!("Removed 2D PhysicsCollider with id: {}", id),
+            );
+            remove_collider(id, &mut physics, &mut logger);
+        }
+
+        // handle inserted events
+        for (entity, position, physics_collider, _) in (
+            &entities,
+            &positions,
+            &mut physics_colliders,
+            &position_events.inserted | &physics_collider_events.inserted,
+        )
+            .join()
+        {
+            logger.debug(
+                module_path!(),
+                format_args!("Inserted 2D PhysicsCollider with id: {}", entity.id()),
+            );
+            add_collider(
+                entity,
+                position,
+                &mut physics,
+                physics_collider,
+                &mut logger,
+            );
+        }
+
+        // handle modified events
+        for (position, physics_collider, id) in (
+            &positions,
+            &physics_colliders,
+            &position_events.modified | &physics_collider_events.modified,
+        )
+            .join()
+        {
+            let handle = match physics_collider.handle {
+                Some(handle) if physics.world.collider(handle).is_some() => handle,
+                _ => continue,
+            };
+            let on_ground = !physics.body_handles.contains_key(&id);
+            let collider_world = physics.world.collider_world_mut();
+
+            // an invalid Shape keeps the previous collider
+            if physics_collider_events.modified.contains(id) {
+                match physics_collider.shape.try_handle() {
+                    Ok(shape_handle) => {
+                        collider_world
+                            .set_collision_groups(handle, physics_collider.collision_groups);
+                        collider_world
+                            .as_collision_world_mut()
+                            .set_shape(handle, shape_handle);
+                        logger.log(
+                            module_path!(),
+                            Level::Info,
+                            DiagnosticKind::ColliderUpdated(id),
+                            format_args!(
+                                "Updated 2D collider in world with values: {:?}",
+                                physics_collider
+                            ),
+                        );
+                    }
+                    Err(error) => logger.log(
+                        module_path!(),
+                        Level::Error,
+                        DiagnosticKind::InvalidInput(id, error),
+                        format_args!("Skipped updating 2D collider with id {}: {}", id, error),
+                    ),
+                }
+            }
+
+            // colliders attached to bodies move with them
+            if on_ground && position_events.modified.contains(id) {
+                collider_world
+                    .as_collision_world_mut()
+                    .set_position(handle, ground_position(position, physics_collider));
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("dim2::SyncCollidersToPhysicsSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+
+        // register reader id for the Position storage
+        let mut position_storage: WriteStorage<P> = SystemData::fetch(&res);
+        self.positions_reader_id = Some(position_storage.register_reader());
+
+        // register reader id for the PhysicsCollider storage
+        let mut physics_collider_storage: WriteStorage<PhysicsCollider<N>> =
+            SystemData::fetch(&res);
+        self.physics_colliders_reader_id = Some(physics_collider_storage.register_reader());
+        self.rescan = true;
+    }
+}
+
+impl<N, P> Default for SyncCollidersToPhysicsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    fn default() -> Self {
+        Self {
+            positions_reader_id: None,
+            physics_colliders_reader_id: None,
+            position_events: ComponentEvents::default(),
+            physics_collider_events: ComponentEvents::default(),
+            rescan: false,
+            n_marker: PhantomData,
+            p_marker: PhantomData,
+        }
+    }
+}
+
+fn add_collider<N, P>(
+    entity: Entity,
+    position: &P,
+    physics: &mut Physics<N>,
+    physics_collider: &mut PhysicsCollider<N>,
+    logger: &mut SystemLogger,
+) where
+    N: RealField,
+    P: Position<N>,
+{
+    let id = entity.id();
+
+    // a PhysicsCollider re-inserted without being removed replaces its collider
+    remove_collider(id, physics, logger);
+
+    let shape_handle = match physics_collider.shape.try_handle() {
+        Ok(shape_handle) => shape_handle,
+        Err(error) => {
+            logger.log(
+                module_path!(),
+                Level::Error,
+                DiagnosticKind::InvalidInput(id, error),
+                format_args!("Skipped inserting 2D collider with id {}: {}", id, error),
+            );
+            return;
+        }
+    };
+
+    // attach the collider to the body of the same Entity, or to the ground
+    let parent = physics
+        .body_handles
+        .get(&id)
+        .and_then(|handle| physics.world.rigid_body(*handle))
+        .map(|rigid_body| rigid_body.part_handle());
+    let (parent, isometry) = match parent {
+        Some(parent) => (parent, physics_collider.offset_from_parent),
+        None => (
+            BodyPartHandle::ground(),
+            ground_position(position, physics_collider),
+        ),
+    };
+
+    let handle = ColliderDesc::new(shape_handle)
+        .position(isometry)
+        .density(physics_collider.density)
+        .material(physics_collider.material.clone())
+        .margin(physics_collider.margin)
+        .collision_groups(physics_collider.collision_groups)
+        .linear_prediction(physics_collider.linear_prediction)
+        .angular_prediction(physics_collider.angular_prediction)
+        .sensor(physics_collider.sensor)
+        .user_data(entity)
+        .build_with_parent(parent, &mut physics.world)
+        .unwrap()
+        .handle();
+
+    physics_collider.handle = Some(handle);
+    physics.collider_handles.insert(id, handle);
+
+    logger.log(
+        module_path!(),
+        Level::Info,
+        DiagnosticKind::ColliderInserted(id),
+        format_args!(
+            "Inserted 2D collider to world with values: {:?}",
+            physics_collider
+        ),
+    );
+}
+
+fn remove_collider<N: RealField>(id: Index, physics: &mut Physics<N>, logger: &mut SystemLogger) {
+    if let Some(handle) = physics.collider_handles.remove(&id) {
+        // colliders are implicitly removed with their parent body
+        if physics.world.collider(handle).is_some() {
+            physics.world.remove_colliders(&[handle]);
+        }
+        logger.log(
+            module_path!(),
+            Level::Info,
+            DiagnosticKind::ColliderRemoved(id),
+            format_args!("Removed 2D collider from world with id: {}", id),
+        );
+    }
+}
+
+fn ground_position<N, P>(position: &P, physics_collider: &PhysicsCollider<N>) -> Isometry2<N>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    position.isometry() * physics_collider.offset_from_parent
+}
@@ -0,0 +1,131 @@
+use std::marker::PhantomData;
+
+use log::Level;
+use specs::{Read, System, SystemData, World, Write, WriteExpect};
+
+use crate::{
+    diagnostics::{DiagnosticKind, LogSource, PhysicsDiagnostics, PhysicsLogConfig, SystemLogger},
+    dim2::{Gravity, Physics},
+    nalgebra::RealField,
+    parameters::{PhysicsIntegrationParameters, PhysicsProfilingEnabled},
+};
+
+/// The 2D `SyncParametersToPhysicsSystem` synchronises the `Gravity`, the
+/// `PhysicsProfilingEnabled` and the `PhysicsIntegrationParameters` with the
+/// 2D physics `World`.
+pub struct SyncParametersToPhysicsSystem<N> {
+    n_marker: PhantomData<N>,
+}
+
+impl<'s, N: RealField> System<'s> for SyncParametersToPhysicsSystem<N> {
+    type SystemData = (
+        Option<Read<'s, Gravity<N>>>,
+        Option<Read<'s, PhysicsProfilingEnabled>>,
+        Option<Read<'s, PhysicsIntegrationParameters<N>>>,
+        Option<Read<'s, PhysicsLogConfig>>,
+        Write<'s, PhysicsDiagnostics>,
+        WriteExpect<'s, Physics<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (gravity, profiling, integration_params, log_config, mut diagnostics, mut physics) =
+            data;
+        let mut logger = SystemLogger::new(
+            log_config.as_ref().map(|config| &**config),
+            LogSource::Parameters,
+            &mut diagnostics,
+        );
+
+        if let Some(gravity) = gravity {
+            if gravity.0 != *physics.world.gravity() {
+                logger.log(
+                    module_path!(),
+                    Level::Info,
+                    DiagnosticKind::GravityChanged,
+                    format_args!(
+                        "2D physics gravity modified from {}, updating to {}.",
+                        physics.world.gravity(),
+                        gravity.0
+                    ),
+                );
+                physics.world.set_gravity(gravity.0);
+            }
+        }
+
+        if let Some(enable_profiling) = profiling {
+            if enable_profiling.0 != physics.world.performance_counters().enabled() {
+                logger.log(
+                    module_path!(),
+                    Level::Info,
+                    DiagnosticKind::ProfilingToggled(enable_profiling.0),
+                    format_args!(
+                        "2D physics performance counters enabled: {}.",
+                        enable_profiling.0
+                    ),
+                );
+                if enable_profiling.0 {
+                    physics.world.enable_performance_counters();
+                } else {
+                    physics.world.disable_performance_counters();
+                }
+            }
+        }
+
+        if let Some(params) = integration_params {
+            if *params != *physics.world.integration_parameters() {
+                params.apply_2d(physics.world.integration_parameters_mut());
+                logger.log(
+                    module_path!(),
+                    Level::Info,
+                    DiagnosticKind::IntegrationParametersChanged,
+                    format_args!("2D integration parameters have been updated."),
+                );
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("dim2::SyncParametersToPhysicsSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+    }
+}
+
+impl<N: RealField> Default for SyncParametersToPhysicsSystem<N> {
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        dim2::{systems::SyncParametersToPhysicsSystem, Gravity, Physics},
+        nalgebra::Vector2,
+    };
+
+    #[test]
+    fn update_gravity() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncParametersToPhysicsSystem::<f32>::default(),
+                "sync_parameters_to_physics_system_2d",
+                &[],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+
+        world.insert(Gravity(Vector2::<f32>::new(1.0, -9.81)));
+        dispatcher.dispatch(&world);
+
+        let physics = world.read_resource::<Physics<f32>>();
+        assert_eq!(*physics.gravity(), Vector2::new(1.0, -9.81));
+    }
+}
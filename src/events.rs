@@ -1,9 +1,20 @@
-use specs::Entity;
+//! # Events module
+//! The events published by the physics `System`s into `EventChannel`
+//! `Resource`s. Colliders and bodies are resolved to their `Entity`s, so
+//! gameplay code never has to deal with nphysics handles.
 
-use crate::{ncollide::query::Proximity, shrev::EventChannel};
+use specs::{Entity, ReadStorage};
+
+use crate::{
+    characters::{CharacterQuery, CharacterResolution},
+    colliders::{EventRole, PhysicsCollider},
+    nalgebra::{RealField, Vector3},
+    ncollide::query::Proximity,
+    shrev::EventChannel,
+};
 
 /// The `ContactType` is set accordingly to whether a contact began or ended.
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum ContactType {
     /// Event occurring when two collision objects start being in contact.
     Started,
@@ -19,6 +30,9 @@ pub struct ContactEvent {
     pub collider2: Entity,
 
     pub contact_type: ContactType,
+    /// The tick index of the step the contact started or stopped in, see
+    /// `Physics::tick`.
+    pub tick: u64,
 }
 
 /// `ContactEvents` is a custom `EventChannel` type used to expose
@@ -35,8 +49,201 @@ pub struct ProximityEvent {
 
     pub prev_status: Proximity,
     pub new_status: Proximity,
+    /// The tick index of the step the proximity changed in, see
+    /// `Physics::tick` and `SensorWorld::tick`.
+    pub tick: u64,
 }
 
 /// `ProximityEvent` is a custom `EventChannel` type used to expose
 /// `ProximityEvent`s.
 pub type ProximityEvents = EventChannel<ProximityEvent>;
+
+/// The `PickupEvent` is emitted by the `CollectPickupsSystem` once a
+/// `PickupCollector` reaches a `Pickup`. It is emitted again only after the
+/// collector left the `Pickup` and came back, so most games simply delete
+/// the `Pickup` `Entity` upon the event.
+#[derive(Debug)]
+pub struct PickupEvent {
+    pub collector: Entity,
+    pub pickup: Entity,
+}
+
+/// `PickupEvents` is a custom `EventChannel` type used to expose
+/// `PickupEvent`s.
+pub type PickupEvents = EventChannel<PickupEvent>;
+
+/// Orders the `Entity`s of a pair of colliders by their `EventRole`s, so
+/// that the source of an event is reported as `collider1`.
+pub(crate) fn oriented_pair<N: RealField>(
+    physics_colliders: &ReadStorage<PhysicsCollider<N>>,
+    collider1: Entity,
+    collider2: Entity,
+) -> (Entity, Entity) {
+    let role = |entity| {
+        physics_colliders
+            .get(entity)
+            .map_or(EventRole::Both, |physics_collider| {
+                physics_collider.event_role
+            })
+    };
+    if role(collider1).reported_after(role(collider2)) {
+        (collider2, collider1)
+    } else {
+        (collider1, collider2)
+    }
+}
+
+/// The `PhysicsEventChannel` names the `EventChannel`s limited by the
+/// `EventCapacity`.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum PhysicsEventChannel {
+    Contacts,
+    Proximities,
+}
+
+/// The `DroppedEventCounts` `Resource` counts the events dropped since the
+/// start of the simulation because they exceeded the `EventCapacity`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct DroppedEventCounts {
+    pub contacts: usize,
+    pub proximities: usize,
+}
+
+impl DroppedEventCounts {
+    /// Returns the number of dropped events of the given channel.
+    pub fn get(&self, channel: PhysicsEventChannel) -> usize {
+        match channel {
+            PhysicsEventChannel::Contacts => self.contacts,
+            PhysicsEventChannel::Proximities => self.proximities,
+        }
+    }
+
+    /// Adds the given number of dropped events of the given channel.
+    pub(crate) fn add(&mut self, channel: PhysicsEventChannel, dropped: usize) {
+        match channel {
+            PhysicsEventChannel::Contacts => self.contacts += dropped,
+            PhysicsEventChannel::Proximities => self.proximities += dropped,
+        }
+    }
+}
+
+/// The `StepsDroppedEvent` is emitted when the `PhysicsStepperSystem` had to
+/// drop substeps because the `StepBudget` was exceeded.
+#[derive(Debug)]
+pub struct StepsDroppedEvent {
+    /// The number of substeps the accumulated `DeltaTime` demanded.
+    pub requested: usize,
+    /// The number of substeps which were dropped.
+    pub dropped: usize,
+}
+
+/// `StepsDroppedEvents` is a custom `EventChannel` type used to expose
+/// `StepsDroppedEvent`s.
+pub type StepsDroppedEvents = EventChannel<StepsDroppedEvent>;
+
+/// The `ElevatorEventKind` describes which stop an `Elevator` reached.
+#[derive(Debug)]
+pub enum ElevatorEventKind {
+    /// The cabin arrived at the given target floor.
+    ReachedFloor(usize),
+    /// The cabin reached the lower end stop.
+    ReachedMinStop,
+    /// The cabin reached the upper end stop.
+    ReachedMaxStop,
+}
+
+/// The `ElevatorEvent` is emitted once when the cabin of an `Elevator` reaches
+/// a stop.
+#[derive(Debug)]
+pub struct ElevatorEvent {
+    pub elevator: Entity,
+    pub kind: ElevatorEventKind,
+}
+
+/// `ElevatorEvents` is a custom `EventChannel` type used to expose
+/// `ElevatorEvent`s.
+pub type ElevatorEvents = EventChannel<ElevatorEvent>;
+
+/// The `CharacterEventKind` describes what happened to the capsule of a
+/// `CharacterController`.
+#[derive(Debug, PartialEq)]
+pub enum CharacterEventKind {
+    /// The capsule has been resized to the requested half height.
+    Resized,
+    /// Growing the capsule has been denied because the taller capsule would
+    /// overlap other colliders. Emitted once per `resize` request; the
+    /// request stays pending until there is enough room.
+    ResizeBlocked,
+    /// The character started swimming in a `WaterVolume`.
+    EnteredWater,
+    /// The character left all `WaterVolume`s.
+    ExitedWater,
+}
+
+/// The `CharacterEvent` is emitted by the `ResizeCharactersSystem` and the
+/// `MoveCharactersSystem`, e.g. to drive crouching or swimming animations.
+#[derive(Debug)]
+pub struct CharacterEvent {
+    pub character: Entity,
+    pub kind: CharacterEventKind,
+}
+
+/// `CharacterEvents` is a custom `EventChannel` type used to expose
+/// `CharacterEvent`s.
+pub type CharacterEvents = EventChannel<CharacterEvent>;
+
+/// The `CharacterTrace` is emitted by the `MoveCharactersSystem` for every
+/// move of a `CharacterController` with `debug` enabled, e.g. to find out why
+/// a character gets stuck on the seam between two colliders.
+#[derive(Debug)]
+pub struct CharacterTrace<N: RealField> {
+    pub character: Entity,
+    /// The scene queries made during the move, in order.
+    pub queries: Vec<CharacterQuery<N>>,
+    /// How the move has been resolved.
+    pub resolution: CharacterResolution<N>,
+    /// The velocity the kinematic body has been given.
+    pub velocity: Vector3<N>,
+}
+
+/// `CharacterTraces` is a custom `EventChannel` type used to expose
+/// `CharacterTrace`s.
+pub type CharacterTraces<N> = EventChannel<CharacterTrace<N>>;
+
+/// The `TunnelingRiskEvent` is emitted by the `ApplyTunnelingPredictionSystem`
+/// when an `Entity` moves far enough per step to pass through thin colliders
+/// and its `linear_prediction` has been raised.
+#[derive(Debug)]
+pub struct TunnelingRiskEvent<N: RealField> {
+    pub entity: Entity,
+    /// The distance the body travels per step.
+    pub travel: N,
+    /// The smallest extent of the collider of the `Entity`.
+    pub size: N,
+    /// The raised `linear_prediction` of the collider.
+    pub linear_prediction: N,
+}
+
+/// `TunnelingRiskEvents` is a custom `EventChannel` type used to expose
+/// `TunnelingRiskEvent`s.
+pub type TunnelingRiskEvents<N> = EventChannel<TunnelingRiskEvent<N>>;
+
+/// The `DamageEvent` is emitted by the `ApplyImpactResponsesSystem` when an
+/// `Entity` with an `ImpactResponse` takes damage from an impact.
+#[derive(Debug)]
+pub struct DamageEvent<N: RealField> {
+    /// The `Entity` taking the damage.
+    pub entity: Entity,
+    /// The `Entity` whose collider hit it.
+    pub source: Entity,
+    /// The damage according to the `DamageCurve` of the `ImpactResponse`.
+    pub damage: N,
+    /// The relative speed of both `Entity`s at the impact.
+    pub impact_speed: N,
+    /// The tick index of the step the impact occurred in.
+    pub tick: u64,
+}
+
+/// `DamageEvents` is a custom `EventChannel` type used to expose
+/// `DamageEvent`s.
+pub type DamageEvents<N> = EventChannel<DamageEvent<N>>;
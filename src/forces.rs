@@ -0,0 +1,636 @@
+//! # Forces module
+//! Utility `Component`s which are translated into forces on `PhysicsBody`s by
+//! their respective `System`s. All of these `System`s accumulate external
+//! forces on the `PhysicsBody` and therefore have to run before the
+//! `SyncBodiesToPhysicsSystem`.
+
+use specs::{Component, DenseVecStorage, Entity};
+
+use crate::nalgebra::{self as na, Isometry3, Point3, RealField, Unit, UnitQuaternion, Vector3};
+
+/// The `PdController` drives a `PhysicsBody` towards a target position and
+/// velocity using a proportional-derivative controller. The controller gains
+/// are expressed per unit of mass, so the same values behave identically for
+/// light and heavy bodies.
+///
+/// The forces are computed with an implicit formulation which takes the
+/// `TimeStep` into account, keeping the controller stable and frame-rate
+/// independent even for very stiff settings.
+///
+/// # Example
+///
+/// ```rust
+/// use specs_physics::{forces::PdController, nalgebra::Point3};
+///
+/// // reach the target within roughly half a second without overshooting
+/// let controller = PdController::critically_damped(Point3::new(0.0f32, 5.0, 0.0), 2.0);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PdController<N: RealField> {
+    /// The position the body is pulled towards.
+    pub target_position: Point3<N>,
+    /// The velocity the body is driven towards.
+    pub target_velocity: Vector3<N>,
+    /// Spring constant per unit of mass.
+    pub stiffness: N,
+    /// Damping constant per unit of mass.
+    pub damping: N,
+}
+
+impl<N: RealField> Component for PdController<N> {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl<N: RealField> PdController<N> {
+    /// Creates a new `PdController` with the given gains and no target
+    /// velocity.
+    pub fn new(target_position: Point3<N>, stiffness: N, damping: N) -> Self {
+        Self {
+            target_position,
+            target_velocity: Vector3::zeros(),
+            stiffness,
+            damping,
+        }
+    }
+
+    /// Creates a critically damped `PdController` oscillating with the given
+    /// `frequency` in Hz, which reaches its target as fast as possible without
+    /// overshooting.
+    pub fn critically_damped(target_position: Point3<N>, frequency: N) -> Self {
+        let omega = N::two_pi() * frequency;
+        Self::new(
+            target_position,
+            omega * omega,
+            na::convert::<f64, N>(2.0) * omega,
+        )
+    }
+
+    /// Computes the acceleration towards the target for a body at `position`
+    /// moving with `velocity` over a step of length `dt`.
+    pub fn acceleration(&self, position: &Vector3<N>, velocity: &Vector3<N>, dt: N) -> Vector3<N> {
+        // implicit (backward Euler) spring; the effective gains shrink as the
+        // timestep grows, which prevents the overshooting explicit springs show
+        let g = N::one() / (N::one() + self.damping * dt + self.stiffness * dt * dt);
+        let stiffness = self.stiffness * g;
+        let damping = (self.damping + self.stiffness * dt) * g;
+
+        (self.target_position.coords - position) * stiffness
+            + (self.target_velocity - velocity) * damping
+    }
+}
+
+/// The `Falloff` determines how the pull of an `Attractor` weakens with the
+/// distance to the attracted body.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Falloff {
+    /// Full strength within the whole radius.
+    Constant,
+    /// Linearly decreasing from full strength at the center to zero at the
+    /// radius.
+    Linear,
+    /// Decreasing with the squared distance, like gravity between planets.
+    /// Distances below one unit are treated as one unit to avoid infinite
+    /// forces.
+    InverseSquare,
+}
+
+/// The `Attractor` pulls all dynamic `PhysicsBody`s within its `radius`
+/// towards the `Position` of its `Entity`, e.g. point gravity for planets or
+/// a magnet gun. The `strength` is an acceleration, so bodies of all masses
+/// are pulled equally fast; negative values repel bodies instead.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attractor<N: RealField> {
+    pub strength: N,
+    pub radius: N,
+    pub falloff: Falloff,
+}
+
+impl<N: RealField> Component for Attractor<N> {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl<N: RealField> Attractor<N> {
+    /// Computes the acceleration an `Attractor` at `center` causes on a body
+    /// at `position`.
+    pub fn acceleration(&self, center: &Vector3<N>, position: &Vector3<N>) -> Vector3<N> {
+        let offset = center - position;
+        let distance = offset.norm();
+        if distance >= self.radius || distance <= N::default_epsilon() {
+            return Vector3::zeros();
+        }
+
+        let factor = match self.falloff {
+            Falloff::Constant => N::one(),
+            Falloff::Linear => N::one() - distance / self.radius,
+            Falloff::InverseSquare => N::one() / (distance * distance).max(N::one()),
+        };
+
+        offset / distance * (self.strength * factor)
+    }
+}
+
+/// The region covered by a `GravityVolume`, relative to the `Position` of its
+/// `Entity`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VolumeShape<N: RealField> {
+    Cuboid { half_extents: Vector3<N> },
+    Sphere { radius: N },
+}
+
+impl<N: RealField> VolumeShape<N> {
+    /// Checks whether the given point, expressed in the local space of the
+    /// volume, lies inside the volume.
+    pub fn contains_local_point(&self, point: &Point3<N>) -> bool {
+        match self {
+            VolumeShape::Cuboid { half_extents } => point
+                .coords
+                .iter()
+                .zip(half_extents.iter())
+                .all(|(coordinate, half_extent)| coordinate.abs() <= *half_extent),
+            VolumeShape::Sphere { radius } => point.coords.norm_squared() <= *radius * *radius,
+        }
+    }
+}
+
+/// The gravity applied inside of a `GravityVolume`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GravityField<N: RealField> {
+    /// Constant gravity in the local space of the volume, e.g. a box where
+    /// gravity points sideways.
+    Directional(Vector3<N>),
+    /// Gravity of the given strength pointing towards the center of the
+    /// volume, e.g. a small planet. Negative strengths push bodies outwards.
+    Radial(N),
+}
+
+/// The `GravityVolume` replaces the global `Gravity` for all dynamic,
+/// gravity-enabled `PhysicsBody`s whose `Position` lies inside of it. If
+/// volumes overlap, the one with the highest `priority` wins.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GravityVolume<N: RealField> {
+    pub shape: VolumeShape<N>,
+    pub field: GravityField<N>,
+    pub priority: i32,
+}
+
+impl<N: RealField> Component for GravityVolume<N> {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl<N: RealField> GravityVolume<N> {
+    /// Computes the gravity inside of the volume placed at `isometry` for a
+    /// body at `position`, or `None` if the body lies outside of the volume.
+    pub fn gravity_at(&self, isometry: &Isometry3<N>, position: &Point3<N>) -> Option<Vector3<N>> {
+        let local_position = isometry.inverse_transform_point(position);
+        if !self.shape.contains_local_point(&local_position) {
+            return None;
+        }
+
+        Some(match self.field {
+            GravityField::Directional(gravity) => isometry.rotation * gravity,
+            GravityField::Radial(strength) => {
+                let offset = isometry.translation.vector - position.coords;
+                let distance = offset.norm();
+                if distance <= N::default_epsilon() {
+                    Vector3::zeros()
+                } else {
+                    offset / distance * strength
+                }
+            }
+        })
+    }
+}
+
+/// The `UprightStabilizer` applies a corrective torque which keeps the local
+/// `up` axis of a `PhysicsBody` aligned with a `target` direction, e.g. for
+/// hoverbikes or physical characters that shouldn't tip over. Rotation around
+/// the `target` direction itself is left untouched.
+///
+/// The gains are expressed as angular accelerations and scaled by the angular
+/// inertia of the `PhysicsBody`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UprightStabilizer<N: RealField> {
+    /// The axis in the local space of the body which should point upwards.
+    pub up: Unit<Vector3<N>>,
+    /// The world space direction the `up` axis is aligned with.
+    pub target: Unit<Vector3<N>>,
+    /// Angular spring constant.
+    pub stiffness: N,
+    /// Angular damping constant.
+    pub damping: N,
+}
+
+impl<N: RealField> Component for UprightStabilizer<N> {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl<N: RealField> UprightStabilizer<N> {
+    /// Creates an `UprightStabilizer` keeping the local y axis aligned with
+    /// the world y axis.
+    pub fn new(stiffness: N, damping: N) -> Self {
+        Self {
+            up: Vector3::y_axis(),
+            target: Vector3::y_axis(),
+            stiffness,
+            damping,
+        }
+    }
+
+    /// Computes the corrective angular acceleration for a body with the given
+    /// `rotation` and `angular_velocity`.
+    pub fn angular_acceleration(
+        &self,
+        rotation: &UnitQuaternion<N>,
+        angular_velocity: &Vector3<N>,
+    ) -> Vector3<N> {
+        align_axis(
+            &(rotation * self.up),
+            &self.target,
+            angular_velocity,
+            self.stiffness,
+            self.damping,
+        )
+    }
+}
+
+/// Computes the angular acceleration turning the world space `axis` of a body
+/// towards the `target` direction. Only the turning motion is damped, not the
+/// spin around the `target` direction.
+fn align_axis<N: RealField>(
+    axis: &Unit<Vector3<N>>,
+    target: &Unit<Vector3<N>>,
+    angular_velocity: &Vector3<N>,
+    stiffness: N,
+    damping: N,
+) -> Vector3<N> {
+    let (axis, target) = (axis.into_inner(), target.into_inner());
+    let rotation_axis = axis.cross(&target);
+    let sin = rotation_axis.norm();
+    let angle = sin.atan2(axis.dot(&target));
+
+    let correction = if sin > N::default_epsilon() {
+        rotation_axis / sin * (angle * stiffness)
+    } else {
+        Vector3::zeros()
+    };
+
+    let spin = target * angular_velocity.dot(&target);
+    correction - (angular_velocity - spin) * damping
+}
+
+/// The target a `LookAt` turns its body towards.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LookAtTarget<N: RealField> {
+    /// A fixed point in world space.
+    Point(Point3<N>),
+    /// The position of another `Entity`.
+    Entity(Entity),
+}
+
+/// The `LookAt` applies a corrective torque which turns the local `forward`
+/// axis of a `PhysicsBody` towards a target point or `Entity`, e.g. for
+/// turrets or homing missiles that should stay physical. Like the
+/// `UprightStabilizer`, it leaves the rotation around the aimed direction
+/// untouched and its gains are angular accelerations scaled by the angular
+/// inertia of the `PhysicsBody`.
+///
+/// # Example
+///
+/// ```rust
+/// use specs_physics::{
+///     forces::{LookAt, LookAtTarget},
+///     nalgebra::Point3,
+/// };
+///
+/// // a turret aiming at the origin within roughly a second
+/// let turret = LookAt::new(LookAtTarget::Point(Point3::<f32>::origin()), 40.0, 12.0);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LookAt<N: RealField> {
+    /// The axis in the local space of the body which should point at the
+    /// target.
+    pub forward: Unit<Vector3<N>>,
+    pub target: LookAtTarget<N>,
+    /// Angular spring constant.
+    pub stiffness: N,
+    /// Angular damping constant.
+    pub damping: N,
+}
+
+impl<N: RealField> Component for LookAt<N> {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl<N: RealField> LookAt<N> {
+    /// Creates a `LookAt` turning the local z axis towards the target.
+    pub fn new(target: LookAtTarget<N>, stiffness: N, damping: N) -> Self {
+        Self {
+            forward: Vector3::z_axis(),
+            target,
+            stiffness,
+            damping,
+        }
+    }
+
+    /// Computes the corrective angular acceleration for a body with the given
+    /// `rotation` and `angular_velocity`, looking from `position` at the
+    /// `target_position`. Only damping applies while both positions coincide.
+    pub fn angular_acceleration(
+        &self,
+        rotation: &UnitQuaternion<N>,
+        angular_velocity: &Vector3<N>,
+        position: &Point3<N>,
+        target_position: &Point3<N>,
+    ) -> Vector3<N> {
+        let forward = rotation * self.forward;
+        let target =
+            Unit::try_new(target_position - position, N::default_epsilon()).unwrap_or(forward);
+        align_axis(
+            &forward,
+            &target,
+            angular_velocity,
+            self.stiffness,
+            self.damping,
+        )
+    }
+}
+
+/// The `TopDownFriction` brakes a `PhysicsBody` proportionally to its
+/// velocity within the plane orthogonal to `normal`, simulating the ground
+/// friction of top-down games without an actual floor to slide on. Movement
+/// along the `normal` is left untouched.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TopDownFriction<N: RealField> {
+    /// The braking rate, i.e. the deceleration per unit of velocity.
+    pub friction: N,
+    /// The world space normal of the plane the body moves on.
+    pub normal: Unit<Vector3<N>>,
+}
+
+impl<N: RealField> Component for TopDownFriction<N> {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl<N: RealField> TopDownFriction<N> {
+    /// Creates a `TopDownFriction` braking the movement on the xz plane.
+    pub fn new(friction: N) -> Self {
+        Self {
+            friction,
+            normal: Vector3::y_axis(),
+        }
+    }
+
+    /// Computes the braking acceleration for a body moving with `velocity`
+    /// over a step of length `dt`.
+    pub fn acceleration(&self, velocity: &Vector3<N>, dt: N) -> Vector3<N> {
+        let normal = self.normal.into_inner();
+        let planar_velocity = velocity - normal * velocity.dot(&normal);
+
+        // implicit formulation, so high friction values stop the body without
+        // reversing its direction
+        -planar_velocity * (self.friction / (N::one() + self.friction * dt))
+    }
+}
+
+/// The `LiftCurve` maps the angle of attack, in radians, to the lift
+/// coefficient of an `Aerodynamics` body: the coefficient grows linearly with
+/// the `slope` until it is capped at `max_coefficient`, roughly like a wing
+/// before it stalls.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LiftCurve<N: RealField> {
+    pub slope: N,
+    pub max_coefficient: N,
+}
+
+impl<N: RealField> LiftCurve<N> {
+    /// Computes the lift coefficient at the given angle of attack.
+    pub fn coefficient(&self, angle_of_attack: N) -> N {
+        (self.slope * angle_of_attack)
+            .max(-self.max_coefficient)
+            .min(self.max_coefficient)
+    }
+}
+
+/// The `Aerodynamics` apply velocity-dependent drag and, optionally, lift to a
+/// `PhysicsBody`, e.g. for gliders or leaves falling slowly. Both forces grow
+/// with the squared speed and the `reference_area`; the lift pushes along the
+/// local `normal` of the body, perpendicular to its velocity.
+///
+/// # Example
+///
+/// ```rust
+/// use specs_physics::forces::{Aerodynamics, LiftCurve};
+///
+/// let leaf = Aerodynamics::<f32>::new(1.2, 0.01).with_lift(LiftCurve {
+///     slope: 6.0,
+///     max_coefficient: 1.0,
+/// });
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aerodynamics<N: RealField> {
+    pub drag_coefficient: N,
+    pub reference_area: N,
+    /// The density of the surrounding air, 1.225 at sea level by default.
+    pub air_density: N,
+    pub lift: Option<LiftCurve<N>>,
+    /// The axis in the local space of the body lift is generated along.
+    pub normal: Unit<Vector3<N>>,
+}
+
+impl<N: RealField> Component for Aerodynamics<N> {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl<N: RealField> Aerodynamics<N> {
+    /// Creates new `Aerodynamics` with drag only, generating lift along the
+    /// local y axis once a `LiftCurve` is added.
+    pub fn new(drag_coefficient: N, reference_area: N) -> Self {
+        Self {
+            drag_coefficient,
+            reference_area,
+            air_density: na::convert(1.225),
+            lift: None,
+            normal: Vector3::y_axis(),
+        }
+    }
+
+    /// Adds the given `LiftCurve`.
+    pub fn with_lift(mut self, lift: LiftCurve<N>) -> Self {
+        self.lift = Some(lift);
+        self
+    }
+
+    /// Computes the aerodynamic force on a body with the given `rotation`
+    /// moving with the given `velocity` through resting air.
+    pub fn force(&self, rotation: &UnitQuaternion<N>, velocity: &Vector3<N>) -> Vector3<N> {
+        let speed = velocity.norm();
+        if speed <= N::default_epsilon() {
+            return Vector3::zeros();
+        }
+
+        let direction = velocity / speed;
+        let dynamic_pressure =
+            na::convert::<f64, N>(0.5) * self.air_density * speed * speed * self.reference_area;
+        let drag = -direction * (dynamic_pressure * self.drag_coefficient);
+
+        let lift = match self.lift {
+            Some(lift_curve) => {
+                // the air hits the underside of the body if it moves against its
+                // normal, which yields a positive angle of attack
+                let normal = (rotation * self.normal).into_inner();
+                let alignment = direction.dot(&normal).max(-N::one()).min(N::one());
+                let perpendicular = normal - direction * alignment;
+                let norm = perpendicular.norm();
+                if norm <= N::default_epsilon() {
+                    Vector3::zeros()
+                } else {
+                    let coefficient = lift_curve.coefficient(-alignment.asin());
+                    perpendicular / norm * (dynamic_pressure * coefficient)
+                }
+            }
+            None => Vector3::zeros(),
+        };
+
+        drag + lift
+    }
+}
+
+/// The `Buoyancy` lets a `PhysicsBody` float on the `WaterSurface`. The body
+/// is treated as spanning `half_height` above and below its `Position`; the
+/// submerged fraction of that span is pushed up against the `Gravity` with
+/// `buoyancy` times its strength, so values above one float and values below
+/// one sink slowly. The `damping` slows down submerged bodies like the drag
+/// of the water would.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Buoyancy<N: RealField> {
+    pub half_height: N,
+    pub buoyancy: N,
+    pub damping: N,
+}
+
+impl<N: RealField> Component for Buoyancy<N> {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl<N: RealField> Buoyancy<N> {
+    /// Computes the submerged fraction of a body at `position` for the given
+    /// water height, from zero above the water to one fully submerged.
+    pub fn submerged_fraction(&self, position: &Vector3<N>, water_height: N) -> N {
+        if self.half_height <= N::zero() {
+            return if position.y <= water_height {
+                N::one()
+            } else {
+                N::zero()
+            };
+        }
+
+        let bottom = position.y - self.half_height;
+        ((water_height - bottom) / (self.half_height + self.half_height))
+            .max(N::zero())
+            .min(N::one())
+    }
+}
+
+/// The `WaterSurface` `Resource` determines the height of the water the
+/// `Buoyancy` bodies float on. Besides a flat plane, the height can be
+/// sampled from a closure receiving the x and z coordinates and the elapsed
+/// simulation time, so bodies follow animated waves.
+///
+/// # Example
+///
+/// ```rust
+/// use specs_physics::forces::WaterSurface;
+///
+/// let water_surface = WaterSurface::<f32>::new(|x, _z, time| (x + time).sin() * 0.5);
+/// assert_eq!(water_surface.height_at(0.0, 0.0), 0.0);
+/// ```
+pub struct WaterSurface<N: RealField> {
+    sampler: Box<dyn Fn(N, N, N) -> N + Send + Sync>,
+    /// The simulation time passed to the sampler, advanced by the
+    /// `ApplyBuoyancySystem`.
+    pub elapsed: N,
+}
+
+impl<N: RealField> WaterSurface<N> {
+    /// Creates a `WaterSurface` sampling its height from the given closure.
+    pub fn new<F>(sampler: F) -> Self
+    where
+        F: Fn(N, N, N) -> N + Send + Sync + 'static,
+    {
+        Self {
+            sampler: Box::new(sampler),
+            elapsed: N::zero(),
+        }
+    }
+
+    /// Creates a flat `WaterSurface` at the given height.
+    pub fn flat(height: N) -> Self {
+        Self::new(move |_, _, _| height)
+    }
+
+    /// Samples the height of the water at the given x and z coordinates.
+    pub fn height_at(&self, x: N, z: N) -> N {
+        (self.sampler)(x, z, self.elapsed)
+    }
+}
+
+impl<N: RealField> Default for WaterSurface<N> {
+    fn default() -> Self {
+        Self::flat(N::zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        forces::{PdController, UprightStabilizer},
+        nalgebra::{Point3, UnitQuaternion, Vector3},
+    };
+
+    /// Integrates a unit mass driven by the `controller` with semi-implicit
+    /// Euler for the given `duration`, asserting that the body never passes
+    /// the target on the way.
+    fn drive(controller: &PdController<f32>, dt: f32, duration: f32) -> Vector3<f32> {
+        let (mut position, mut velocity) = (Vector3::zeros(), Vector3::zeros());
+        for _ in 0..(duration / dt).round() as usize {
+            velocity += controller.acceleration(&position, &velocity, dt) * dt;
+            position += velocity * dt;
+            assert!(position.x <= controller.target_position.x);
+        }
+        position
+    }
+
+    #[test]
+    fn converge_without_overshoot() {
+        let controller = PdController::critically_damped(Point3::new(1.0f32, 0.0, 0.0), 1.0);
+
+        let position = drive(&controller, 1.0 / 60.0, 3.0);
+        assert!((position - controller.target_position.coords).norm() < 1.0e-3);
+    }
+
+    #[test]
+    fn converge_independent_of_step_size() {
+        let controller = PdController::critically_damped(Point3::new(1.0f32, 0.0, 0.0), 1.0);
+
+        for &duration in &[0.5, 1.0, 3.0] {
+            let position = drive(&controller, 1.0 / 60.0, duration);
+            let halved = drive(&controller, 1.0 / 120.0, duration);
+            assert!((position - halved).norm() < 1.0e-2);
+        }
+    }
+
+    #[test]
+    fn turn_tilted_body_upright() {
+        let stabilizer = UprightStabilizer::new(40.0f32, 12.0);
+        let tilt = UnitQuaternion::from_axis_angle(&Vector3::x_axis(), 0.5);
+
+        // the correction turns the body back around the negative x axis
+        let acceleration = stabilizer.angular_acceleration(&tilt, &Vector3::zeros());
+        assert!((acceleration - Vector3::new(-20.0, 0.0, 0.0)).norm() < 1.0e-4);
+
+        // spin around the target direction is neither corrected nor damped
+        let spin = Vector3::new(0.0, 3.0, 0.0);
+        let acceleration = stabilizer.angular_acceleration(&UnitQuaternion::identity(), &spin);
+        assert_eq!(acceleration, Vector3::zeros());
+    }
+}
@@ -0,0 +1,329 @@
+//! # Fracture module
+//! Destruction building blocks. `fracture` breaks the body of an `Entity`
+//! into dynamic debris `Entity`s, which continue the motion of the original
+//! body and are pushed apart by the impact that broke it.
+
+use specs::{Builder, Entity, World, WorldExt};
+
+use crate::{
+    bodies::{PhysicsBody, Position},
+    colliders::{PhysicsCollider, Shape},
+    nalgebra::{self as na, Isometry3, Matrix3, Point3, RealField, Translation3, Vector3},
+    nphysics::{algebra::Velocity3, object::BodyStatus},
+    PhysicsBodyBuilder,
+};
+
+/// Breaks the body of the given `Entity` into `pieces` debris `Entity`s and
+/// deletes the original `Entity`. Returns the debris `Entity`s, or nothing if
+/// the `Entity` lacks a `Position`, `PhysicsBody` or `PhysicsCollider`.
+///
+/// The pieces are convex boxes partitioning the bounding box of the
+/// `PhysicsCollider`, created by repeatedly halving the largest piece along
+/// its longest axis. They share the mass of the original body by volume and
+/// inherit its velocity at their position; the `impulse` applied at the
+/// `impact_point` is distributed among them, favouring the pieces closest to
+/// the impact. The debris colliders keep the material, groups and
+/// predictions of the original `PhysicsCollider`.
+///
+/// # Example
+///
+/// ```rust
+/// use specs::{Builder, World, WorldExt};
+/// use specs_physics::{
+///     colliders::Shape,
+///     fracture,
+///     nalgebra::{Isometry3, Point3, Vector3},
+///     nphysics::object::BodyStatus,
+///     PhysicsBodyBuilder,
+///     PhysicsColliderBuilder,
+///     SimplePosition,
+/// };
+///
+/// let mut world = World::new();
+/// let mut dispatcher = specs_physics::physics_dispatcher::<f32, SimplePosition<f32>>();
+/// dispatcher.setup(&mut world);
+///
+/// let vase = world
+///     .create_entity()
+///     .with(SimplePosition::<f32>(Isometry3::identity()))
+///     .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+///     .with(
+///         PhysicsColliderBuilder::<f32>::from(Shape::Cuboid {
+///             half_extents: Vector3::new(0.2, 0.5, 0.2),
+///         })
+///         .build(),
+///     )
+///     .build();
+///
+/// let debris = fracture::fracture(
+///     &mut world,
+///     vase,
+///     8,
+///     &Point3::new(0.2, 0.0, 0.0),
+///     &Vector3::new(-5.0, 0.0, 0.0),
+///     SimplePosition,
+/// );
+/// assert_eq!(debris.len(), 8);
+/// assert!(!world.is_alive(vase));
+/// ```
+pub fn fracture<N, P, F>(
+    world: &mut World,
+    entity: Entity,
+    pieces: usize,
+    impact_point: &Point3<N>,
+    impulse: &Vector3<N>,
+    position: F,
+) -> Vec<Entity>
+where
+    N: RealField,
+    P: Position<N>,
+    F: Fn(Isometry3<N>) -> P,
+{
+    let (isometry, physics_body, physics_collider) = {
+        let positions = world.read_storage::<P>();
+        let physics_bodies = world.read_storage::<PhysicsBody<N>>();
+        let physics_colliders = world.read_storage::<PhysicsCollider<N>>();
+        match (
+            positions.get(entity),
+            physics_bodies.get(entity),
+            physics_colliders.get(entity),
+        ) {
+            (Some(position), Some(physics_body), Some(physics_collider)) => (
+                *position.isometry(),
+                *physics_body,
+                physics_collider.clone(),
+            ),
+            _ => return Vec::new(),
+        }
+    };
+
+    // the pieces in the frame of the collider
+    let aabb = physics_collider.shape.handle().local_aabb();
+    let boxes = split_box(aabb.center().coords, aabb.half_extents(), pieces.max(1));
+    let volume = |half_extents: &Vector3<N>| half_extents.x * half_extents.y * half_extents.z;
+    let total_volume = boxes.iter().fold(N::zero(), |sum, (_, half_extents)| {
+        sum + volume(half_extents)
+    });
+
+    let collider_isometry = isometry * physics_collider.offset_from_parent;
+    let center_of_mass = isometry * physics_body.local_center_of_mass;
+    let centers = boxes
+        .iter()
+        .map(|(center, _)| collider_isometry * Point3::from(*center))
+        .collect::<Vec<_>>();
+
+    // pieces closer to the impact take a larger share of the impulse
+    let weights = centers
+        .iter()
+        .map(|center| N::one() / (N::one() + na::distance(center, impact_point)))
+        .collect::<Vec<_>>();
+    let total_weight = weights.iter().fold(N::zero(), |sum, weight| sum + *weight);
+
+    let mut debris = Vec::with_capacity(boxes.len());
+    for (((_, half_extents), center), weight) in boxes.iter().zip(centers).zip(weights) {
+        let share = if total_volume > N::zero() {
+            volume(half_extents) / total_volume
+        } else {
+            N::one() / na::convert(boxes.len() as f64)
+        };
+        let mass = physics_body.mass * share;
+
+        let mut linear = physics_body.velocity.linear
+            + physics_body
+                .velocity
+                .angular
+                .cross(&(center - center_of_mass));
+        if mass > N::zero() {
+            linear += impulse * (weight / total_weight) / mass;
+        }
+
+        let mut piece_collider = physics_collider.clone();
+        piece_collider.handle = None;
+        piece_collider.shape = Shape::Cuboid {
+            half_extents: *half_extents,
+        };
+        piece_collider.offset_from_parent = Isometry3::identity();
+
+        let piece_isometry = Isometry3::from_parts(
+            Translation3::from(center.coords),
+            collider_isometry.rotation,
+        );
+        debris.push(
+            world
+                .create_entity()
+                .with(position(piece_isometry))
+                .with(
+                    PhysicsBodyBuilder::from(BodyStatus::Dynamic)
+                        .gravity_enabled(physics_body.gravity_enabled)
+                        .velocity(Velocity3::new(linear, physics_body.velocity.angular))
+                        .mass(mass)
+                        .angular_inertia(box_inertia(half_extents, mass))
+                        .build(),
+                )
+                .with(piece_collider)
+                .build(),
+        );
+    }
+
+    world
+        .delete_entity(entity)
+        .expect("Failed to delete fractured Entity");
+    debris
+}
+
+/// Partitions the box with the given center and half extents into `pieces`
+/// boxes by halving the largest box along its longest axis.
+fn split_box<N: RealField>(
+    center: Vector3<N>,
+    half_extents: Vector3<N>,
+    pieces: usize,
+) -> Vec<(Vector3<N>, Vector3<N>)> {
+    let mut boxes = vec![(center, half_extents)];
+    while boxes.len() < pieces {
+        let largest = (0..boxes.len())
+            .max_by(|i, j| {
+                let (volume1, volume2) = (boxes[*i].1.product(), boxes[*j].1.product());
+                volume1
+                    .partial_cmp(&volume2)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .unwrap();
+        let (center, mut half_extents) = boxes.swap_remove(largest);
+        let axis = half_extents.imax();
+        half_extents[axis] *= na::convert(0.5);
+
+        let mut offset = Vector3::zeros();
+        offset[axis] = half_extents[axis];
+        boxes.push((center - offset, half_extents));
+        boxes.push((center + offset, half_extents));
+    }
+    boxes
+}
+
+/// The angular inertia of a solid box.
+fn box_inertia<N: RealField>(half_extents: &Vector3<N>, mass: N) -> Matrix3<N> {
+    let squared = half_extents.component_mul(half_extents);
+    let factor = mass / na::convert(3.0);
+    Matrix3::from_diagonal(&Vector3::new(
+        (squared.y + squared.z) * factor,
+        (squared.x + squared.z) * factor,
+        (squared.x + squared.y) * factor,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        colliders::Shape,
+        fracture::fracture,
+        nalgebra::{Isometry3, Point3, Vector3},
+        nphysics::{algebra::Velocity3, object::BodyStatus},
+        Physics,
+        PhysicsBody,
+        PhysicsBodyBuilder,
+        PhysicsColliderBuilder,
+        PhysicsHandles,
+        SimplePosition,
+    };
+
+    fn create_vase(world: &mut World, x: f32) -> Entity {
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(x, 0.0, 0.0)))
+            .with(
+                PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                    .gravity_enabled(false)
+                    .mass(2.0)
+                    .velocity(Velocity3::new(
+                        Vector3::new(1.0, 0.0, 0.0),
+                        Vector3::new(0.0, 0.0, 1.0),
+                    ))
+                    .build(),
+            )
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Cuboid {
+                    half_extents: Vector3::new(0.2, 0.5, 0.2),
+                })
+                .build(),
+            )
+            .build()
+    }
+
+    #[test]
+    fn break_into_debris() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+
+        let intact = create_vase(&mut world, 0.0);
+        let struck = create_vase(&mut world, 5.0);
+        dispatcher.dispatch(&world);
+        world.maintain();
+
+        // without an impulse the pieces continue the rotating motion
+        let origin = world
+            .read_storage::<SimplePosition<f32>>()
+            .get(intact)
+            .unwrap()
+            .0
+            .translation
+            .vector;
+        let debris = fracture(
+            &mut world,
+            intact,
+            8,
+            &Point3::origin(),
+            &Vector3::zeros(),
+            SimplePosition,
+        );
+        assert_eq!(debris.len(), 8);
+        assert!(!world.is_alive(intact));
+        {
+            let positions = world.read_storage::<SimplePosition<f32>>();
+            let physics_bodies = world.read_storage::<PhysicsBody<f32>>();
+            let mut mass = 0.0;
+            for piece in &debris {
+                let offset = positions.get(*piece).unwrap().0.translation.vector - origin;
+                let physics_body = physics_bodies.get(*piece).unwrap();
+                let expected = Vector3::new(1.0 - offset.y, offset.x, 0.0);
+                assert!((physics_body.velocity.linear - expected).norm() < 1.0e-5);
+                assert!((physics_body.velocity.angular - Vector3::z()).norm() < 1.0e-5);
+                assert_eq!(physics_body.body_status, BodyStatus::Dynamic);
+                mass += physics_body.mass;
+            }
+            assert!((mass - 2.0).abs() < 1.0e-5);
+        }
+
+        // the impulse adds to the momentum of the pieces
+        let impulse = Vector3::new(-5.0, 0.0, 1.0);
+        let debris = fracture(
+            &mut world,
+            struck,
+            8,
+            &Point3::new(5.2, 0.0, 0.0),
+            &impulse,
+            SimplePosition,
+        );
+        {
+            let physics_bodies = world.read_storage::<PhysicsBody<f32>>();
+            let momentum = debris.iter().fold(Vector3::zeros(), |sum, piece| {
+                let physics_body = physics_bodies.get(*piece).unwrap();
+                sum + physics_body.velocity.linear * physics_body.mass
+            });
+            assert!((momentum - (Vector3::new(2.0, 0.0, 0.0) + impulse)).norm() < 1.0e-4);
+        }
+
+        // the original bodies leave the physics world, the pieces enter it
+        dispatcher.dispatch(&world);
+        world.maintain();
+        let physics = world.read_resource::<Physics<f32>>();
+        assert_eq!(physics.world.bodies().count(), 16);
+        assert_eq!(physics.world.colliders().count(), 16);
+        let physics_handles = world.read_resource::<PhysicsHandles>();
+        assert!(debris
+            .iter()
+            .all(|piece| physics_handles.body_handle(*piece).is_some()));
+    }
+}
@@ -0,0 +1,219 @@
+//! # Fuzzing module
+//! Property-based tests of the synchronisation `System`s. Random sequences of
+//! `Component` insertions, modifications and removals and `Entity` deletions
+//! are applied between dispatches of the physics `Dispatcher`; after every
+//! dispatch the `PhysicsHandles` have to match both the `Component`s and the
+//! nphysics `World`. Only compiled for tests, as `proptest` is a
+//! dev-dependency.
+
+use proptest::prelude::*;
+use specs::{Builder, Entity, Join, World, WorldExt};
+
+use crate::{
+    colliders::Shape,
+    nalgebra::{Isometry3, Vector3},
+    nphysics::{algebra::Velocity3, object::BodyStatus},
+    physics_dispatcher,
+    Physics,
+    PhysicsBody,
+    PhysicsBodyBuilder,
+    PhysicsCollider,
+    PhysicsColliderBuilder,
+    PhysicsHandles,
+    SimplePosition,
+};
+
+/// A single change of the `World`. `Entity`s are picked by index modulo the
+/// number of `Entity`s created so far, deleted ones included.
+#[derive(Clone, Debug)]
+enum Operation {
+    Create { dynamic: bool, collider: bool },
+    InsertBody(usize, bool),
+    ModifyBody(usize),
+    RemoveBody(usize),
+    InsertCollider(usize),
+    ModifyCollider(usize),
+    RemoveCollider(usize),
+    Move(usize),
+    Delete(usize),
+    Dispatch,
+}
+
+fn operation() -> impl Strategy<Value = Operation> {
+    prop_oneof![
+        (any::<bool>(), any::<bool>())
+            .prop_map(|(dynamic, collider)| Operation::Create { dynamic, collider }),
+        (any::<usize>(), any::<bool>())
+            .prop_map(|(index, dynamic)| Operation::InsertBody(index, dynamic)),
+        any::<usize>().prop_map(Operation::ModifyBody),
+        any::<usize>().prop_map(Operation::RemoveBody),
+        any::<usize>().prop_map(Operation::InsertCollider),
+        any::<usize>().prop_map(Operation::ModifyCollider),
+        any::<usize>().prop_map(Operation::RemoveCollider),
+        any::<usize>().prop_map(Operation::Move),
+        any::<usize>().prop_map(Operation::Delete),
+        Just(Operation::Dispatch),
+    ]
+}
+
+fn body(dynamic: bool) -> PhysicsBody<f32> {
+    let body_status = if dynamic {
+        BodyStatus::Dynamic
+    } else {
+        BodyStatus::Static
+    };
+    PhysicsBodyBuilder::<f32>::from(body_status).build()
+}
+
+fn collider() -> PhysicsCollider<f32> {
+    PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build()
+}
+
+/// Applies the `Operation` to the `World`. Operations on deleted `Entity`s
+/// are ignored.
+fn apply(world: &mut World, entities: &mut Vec<Entity>, operation: &Operation) {
+    let entity = match *operation {
+        Operation::Create {
+            dynamic,
+            collider: with_collider,
+        } => {
+            let mut builder = world
+                .create_entity()
+                .with(SimplePosition::<f32>(Isometry3::translation(
+                    entities.len() as f32,
+                    0.0,
+                    0.0,
+                )))
+                .with(body(dynamic));
+            if with_collider {
+                builder = builder.with(collider());
+            }
+            entities.push(builder.build());
+            return;
+        }
+        Operation::Dispatch => return,
+        Operation::InsertBody(index, _)
+        | Operation::ModifyBody(index)
+        | Operation::RemoveBody(index)
+        | Operation::InsertCollider(index)
+        | Operation::ModifyCollider(index)
+        | Operation::RemoveCollider(index)
+        | Operation::Move(index)
+        | Operation::Delete(index) => {
+            if entities.is_empty() {
+                return;
+            }
+            let entity = entities[index % entities.len()];
+            if !world.is_alive(entity) {
+                return;
+            }
+            entity
+        }
+    };
+
+    match *operation {
+        Operation::InsertBody(_, dynamic) => {
+            world
+                .write_storage::<PhysicsBody<f32>>()
+                .insert(entity, body(dynamic))
+                .unwrap();
+        }
+        Operation::ModifyBody(_) => {
+            if let Some(physics_body) = world.write_storage::<PhysicsBody<f32>>().get_mut(entity) {
+                physics_body.velocity = Velocity3::linear(0.0, 1.0, 0.0);
+            }
+        }
+        Operation::RemoveBody(_) => {
+            world.write_storage::<PhysicsBody<f32>>().remove(entity);
+        }
+        Operation::InsertCollider(_) => {
+            world
+                .write_storage::<PhysicsCollider<f32>>()
+                .insert(entity, collider())
+                .unwrap();
+        }
+        Operation::ModifyCollider(_) => {
+            if let Some(physics_collider) = world
+                .write_storage::<PhysicsCollider<f32>>()
+                .get_mut(entity)
+            {
+                physics_collider.margin = 0.1;
+            }
+        }
+        Operation::RemoveCollider(_) => {
+            world.write_storage::<PhysicsCollider<f32>>().remove(entity);
+        }
+        Operation::Move(_) => {
+            if let Some(position) = world.write_storage::<SimplePosition<f32>>().get_mut(entity) {
+                position.0.translation.vector += Vector3::new(0.0, 0.0, 1.0);
+            }
+        }
+        Operation::Delete(_) => {
+            world.delete_entity(entity).unwrap();
+        }
+        Operation::Create { .. } | Operation::Dispatch => unreachable!(),
+    }
+}
+
+/// Checks that the `PhysicsHandles` match the `Component`s and the nphysics
+/// `World`.
+fn check_invariants(world: &World) -> Result<(), TestCaseError> {
+    let physics = world.fetch::<Physics<f32>>();
+    let handles = world.fetch::<PhysicsHandles>();
+    let entities = world.entities();
+    let positions = world.read_storage::<SimplePosition<f32>>();
+    let physics_bodies = world.read_storage::<PhysicsBody<f32>>();
+    let physics_colliders = world.read_storage::<PhysicsCollider<f32>>();
+
+    let mut bodies = 0;
+    for (entity, ..) in (&entities, &positions, &physics_bodies).join() {
+        let handle = handles.body_handle(entity);
+        prop_assert!(handle.is_some(), "{:?} has no body handle", entity);
+        prop_assert_eq!(physics.body_entity(handle.unwrap()), Some(entity));
+        bodies += 1;
+    }
+    prop_assert_eq!(handles.body_handles.len(), bodies);
+
+    let mut colliders = 0;
+    for (entity, ..) in (&entities, &positions, &physics_colliders).join() {
+        let collider_handles = handles.collider_handles(entity);
+        prop_assert!(
+            !collider_handles.is_empty(),
+            "{:?} has no collider handle",
+            entity
+        );
+        for handle in collider_handles {
+            prop_assert_eq!(physics.collider_entity(*handle), Some(entity));
+        }
+        colliders += 1;
+    }
+    prop_assert_eq!(handles.collider_handles.len(), colliders);
+    prop_assert_eq!(
+        physics.world.collider_world().colliders().count(),
+        handles.collider_handles.handle_count()
+    );
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn sync_systems_keep_handles_consistent(
+        operations in prop::collection::vec(operation(), 1..48)
+    ) {
+        let mut world = World::new();
+        let mut dispatcher = physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+
+        let mut entities = Vec::new();
+        for operation in operations.iter().chain(Some(&Operation::Dispatch)) {
+            apply(&mut world, &mut entities, operation);
+            if let Operation::Dispatch = operation {
+                world.maintain();
+                dispatcher.dispatch(&world);
+                check_invariants(&world)?;
+            }
+        }
+    }
+}
@@ -0,0 +1,343 @@
+//! # Golden module
+//! Regression checks of the simulation behaviour. A `Scenario` is simulated
+//! for a fixed number of steps and the final poses of its objects are compared
+//! with golden poses recorded earlier, so changes to the order of the
+//! synchronisation `System`s or to the solver configuration cannot silently
+//! alter the simulation. Golden poses are stored as plain text, one pose per
+//! line, which keeps their changes reviewable.
+
+use std::{env, error::Error, fmt, fs, io, path::Path};
+
+use specs::{Entity, World, WorldExt};
+
+use crate::{
+    bodies::Position,
+    nalgebra::{self as na, Isometry3, Quaternion, RealField, Translation3, UnitQuaternion},
+    physics_dispatcher,
+    scenarios::Scenario,
+    SimplePosition,
+};
+
+/// The environment variable which makes `check_golden` overwrite the golden
+/// files with the current results instead of comparing them.
+pub const BLESS_VAR: &str = "SPECS_PHYSICS_BLESS";
+
+/// The reasons checking against golden poses can fail.
+#[derive(Debug)]
+pub enum GoldenError<N: RealField> {
+    /// The golden file could not be read or written.
+    Io(io::Error),
+    /// The given line of the golden file is malformed.
+    Parse(usize),
+    /// The golden poses were recorded after a different number of steps.
+    StepsDiffer { expected: usize, actual: usize },
+    /// The golden poses were recorded for a different number of objects.
+    ObjectsDiffer { expected: usize, actual: usize },
+    /// The poses of some objects deviate beyond the `GoldenTolerance`.
+    Deviations(Vec<PoseDeviation<N>>),
+}
+
+impl<N: RealField> fmt::Display for GoldenError<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GoldenError::Io(error) => write!(f, "golden file not accessible: {}", error),
+            GoldenError::Parse(line) => write!(f, "invalid golden pose on line {}", line),
+            GoldenError::StepsDiffer { expected, actual } => write!(
+                f,
+                "golden poses recorded after {} steps, simulated {}",
+                expected, actual
+            ),
+            GoldenError::ObjectsDiffer { expected, actual } => write!(
+                f,
+                "golden poses recorded for {} objects, simulated {}",
+                expected, actual
+            ),
+            GoldenError::Deviations(deviations) => {
+                write!(
+                    f,
+                    "{} poses deviate from the golden poses",
+                    deviations.len()
+                )?;
+                for deviation in deviations {
+                    write!(
+                        f,
+                        "\n  object {}: {} away, rotated by {}",
+                        deviation.index, deviation.distance, deviation.angle
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<N: RealField> Error for GoldenError<N> {}
+
+impl<N: RealField> From<io::Error> for GoldenError<N> {
+    fn from(error: io::Error) -> Self {
+        GoldenError::Io(error)
+    }
+}
+
+/// The `GoldenTolerance` bounds how far simulated poses may deviate from the
+/// golden poses. Solvers are rarely bit-exact across platforms and compiler
+/// versions, so small deviations are accepted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GoldenTolerance<N: RealField> {
+    /// The maximum distance between the translations.
+    ///
+    /// default: 1.0e-3
+    pub translation: N,
+    /// The maximum angle between the rotations, in radians.
+    ///
+    /// default: 1.0e-3
+    pub rotation: N,
+}
+
+impl<N: RealField> Default for GoldenTolerance<N> {
+    fn default() -> Self {
+        Self {
+            translation: na::convert(1.0e-3),
+            rotation: na::convert(1.0e-3),
+        }
+    }
+}
+
+/// The deviation of a single simulated pose from its golden pose.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PoseDeviation<N: RealField> {
+    /// The index of the object in the `Scenario`.
+    pub index: usize,
+    /// The distance between the translations.
+    pub distance: N,
+    /// The angle between the rotations, in radians.
+    pub angle: N,
+}
+
+/// The poses of all objects of a `Scenario` after simulating it for a number
+/// of steps.
+///
+/// # Example
+///
+/// ```rust
+/// use specs_physics::{
+///     golden::{GoldenPoses, GoldenTolerance},
+///     scenarios::Scenario,
+/// };
+///
+/// let scenario = Scenario::<f32>::box_stack(2, 2, 0.5);
+/// let golden = GoldenPoses::record(&scenario, 10);
+///
+/// let restored = GoldenPoses::<f32>::from_text(&golden.to_text()).unwrap();
+/// let actual = GoldenPoses::record(&scenario, 10);
+/// assert!(restored
+///     .compare(&actual, &GoldenTolerance::default())
+///     .is_ok());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct GoldenPoses<N: RealField> {
+    /// The number of steps simulated.
+    pub steps: usize,
+    /// The final poses, in the order of the `ScenarioObject`s.
+    pub poses: Vec<Isometry3<N>>,
+}
+
+impl<N: RealField> GoldenPoses<N> {
+    /// Spawns the `Scenario` into a new `World` and runs the default physics
+    /// `Dispatcher` for the given number of steps.
+    pub fn record(scenario: &Scenario<N>, steps: usize) -> Self {
+        let mut world = World::new();
+        let mut dispatcher = physics_dispatcher::<N, SimplePosition<N>>();
+        dispatcher.setup(&mut world);
+
+        let entities = scenario.spawn(&mut world, SimplePosition);
+        for _ in 0..steps {
+            dispatcher.dispatch(&world);
+            world.maintain();
+        }
+        Self::capture(&world, &entities, steps)
+    }
+
+    /// Collects the poses of the given `Entity`s after `steps` steps.
+    pub(crate) fn capture(world: &World, entities: &[Entity], steps: usize) -> Self {
+        let positions = world.read_storage::<SimplePosition<N>>();
+        let poses = entities
+            .iter()
+            .map(|entity| {
+                positions
+                    .get(*entity)
+                    .map_or_else(Isometry3::identity, |position| *position.isometry())
+            })
+            .collect();
+        Self { steps, poses }
+    }
+
+    /// Compares the `actual` poses with these golden poses and returns every
+    /// pose deviating beyond the `GoldenTolerance`.
+    pub fn compare(
+        &self,
+        actual: &GoldenPoses<N>,
+        tolerance: &GoldenTolerance<N>,
+    ) -> Result<(), GoldenError<N>> {
+        if self.steps != actual.steps {
+            return Err(GoldenError::StepsDiffer {
+                expected: self.steps,
+                actual: actual.steps,
+            });
+        }
+        if self.poses.len() != actual.poses.len() {
+            return Err(GoldenError::ObjectsDiffer {
+                expected: self.poses.len(),
+                actual: actual.poses.len(),
+            });
+        }
+
+        let deviations = self
+            .poses
+            .iter()
+            .zip(&actual.poses)
+            .enumerate()
+            .map(|(index, (expected, actual))| PoseDeviation {
+                index,
+                distance: (actual.translation.vector - expected.translation.vector).norm(),
+                angle: expected.rotation.angle_to(&actual.rotation),
+            })
+            .filter(|deviation| {
+                deviation.distance > tolerance.translation || deviation.angle > tolerance.rotation
+            })
+            .collect::<Vec<_>>();
+        if deviations.is_empty() {
+            Ok(())
+        } else {
+            Err(GoldenError::Deviations(deviations))
+        }
+    }
+
+    /// Formats the poses as text: a `steps` line followed by one line per
+    /// pose, holding the translation and the rotation quaternion as
+    /// `x y z i j k w`.
+    pub fn to_text(&self) -> String {
+        let mut text = format!("steps {}\n", self.steps);
+        for pose in &self.poses {
+            let quaternion = pose.rotation.quaternion();
+            let values = [
+                pose.translation.vector.x,
+                pose.translation.vector.y,
+                pose.translation.vector.z,
+                quaternion.i,
+                quaternion.j,
+                quaternion.k,
+                quaternion.w,
+            ];
+            let values = values
+                .iter()
+                .map(|value| value.to_subset().unwrap_or(std::f64::NAN).to_string())
+                .collect::<Vec<_>>();
+            text.push_str(&values.join(" "));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Parses poses formatted by `to_text`.
+    pub fn from_text(text: &str) -> Result<Self, GoldenError<N>> {
+        let mut lines = text.lines().enumerate();
+        let mut header = lines
+            .next()
+            .map(|(_, line)| line.split_whitespace())
+            .ok_or(GoldenError::Parse(1))?;
+        let steps = match (header.next(), header.next()) {
+            (Some("steps"), Some(steps)) => steps.parse().map_err(|_| GoldenError::Parse(1))?,
+            _ => return Err(GoldenError::Parse(1)),
+        };
+
+        let mut poses = Vec::new();
+        for (index, line) in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let values = line
+                .split_whitespace()
+                .map(|value| value.parse::<f64>().map(na::convert::<f64, N>))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| GoldenError::Parse(index + 1))?;
+            if values.len() != 7 {
+                return Err(GoldenError::Parse(index + 1));
+            }
+            poses.push(Isometry3::from_parts(
+                Translation3::new(values[0], values[1], values[2]),
+                UnitQuaternion::from_quaternion(Quaternion::new(
+                    values[6], values[3], values[4], values[5],
+                )),
+            ));
+        }
+        Ok(Self { steps, poses })
+    }
+}
+
+/// Simulates the `Scenario` for the given number of steps and compares the
+/// final poses with the golden poses stored at `path`. If the file does not
+/// exist yet, or the `SPECS_PHYSICS_BLESS` environment variable is set, the
+/// current poses are written to it instead, so intended behaviour changes
+/// are recorded by re-running the tests with the variable set and reviewing
+/// the diff of the golden files.
+pub fn check_golden<N: RealField>(
+    scenario: &Scenario<N>,
+    steps: usize,
+    path: impl AsRef<Path>,
+    tolerance: &GoldenTolerance<N>,
+) -> Result<(), GoldenError<N>> {
+    let path = path.as_ref();
+    let actual = GoldenPoses::record(scenario, steps);
+    if env::var_os(BLESS_VAR).is_some() || !path.exists() {
+        info!("Recording golden poses to {}", path.display());
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        fs::write(path, actual.to_text())?;
+        return Ok(());
+    }
+
+    let expected = GoldenPoses::from_text(&fs::read_to_string(path)?)?;
+    expected.compare(&actual, tolerance)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{check_golden, GoldenTolerance};
+    use crate::scenarios::Scenario;
+
+    fn golden_path(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("golden")
+            .join(format!("{}.txt", name))
+    }
+
+    #[test]
+    fn box_stack_matches_golden() {
+        let scenario = Scenario::<f32>::box_stack(3, 4, 0.5);
+        if let Err(error) = check_golden(
+            &scenario,
+            120,
+            golden_path("box_stack"),
+            &GoldenTolerance::default(),
+        ) {
+            panic!("{}", error);
+        }
+    }
+
+    #[test]
+    fn rain_matches_golden() {
+        let scenario = Scenario::<f32>::rain(32, 7);
+        if let Err(error) = check_golden(
+            &scenario,
+            120,
+            golden_path("rain"),
+            &GoldenTolerance::default(),
+        ) {
+            panic!("{}", error);
+        }
+    }
+}
@@ -0,0 +1,344 @@
+//! # Handles module
+//! Maps between Specs `Entity` indices and the handles of the objects they own
+//! in the nphysics `World`, and back from the objects to their `Entity`.
+
+use std::{
+    any::Any,
+    collections::{hash_map, HashMap},
+};
+
+use smallvec::SmallVec;
+use specs::{world::Index, Entity};
+
+use crate::{
+    colliders::ShapeKey,
+    nphysics::{
+        joint::ConstraintHandle,
+        object::{BodyHandle, ColliderHandle},
+    },
+};
+
+/// The `PhysicsHandles` `Resource` maps `Entity` indices to the handles of the
+/// bodies, colliders and joint constraints they own in the nphysics `World`.
+/// It is kept apart from the `Physics` `Resource`, so `System`s that only look
+/// up handles do not contend on the write lock of the whole physics `World`.
+#[derive(Clone, Debug, Default)]
+pub struct PhysicsHandles {
+    /// Hashmap of Entities to internal Physics bodies.
+    /// Necessary for reacting to removed Components.
+    pub(crate) body_handles: HashMap<Index, BodyHandle>,
+    /// Map of Entities to internal Collider handles, supporting multiple
+    /// Colliders per Entity. Necessary for reacting to removed Components.
+    pub(crate) collider_handles: ColliderHandles,
+    /// Hashmap of Entities to the `ShapeKey` of the shape their collider was
+    /// last built with. Necessary for skipping rebuilds of unchanged shapes.
+    pub(crate) shape_keys: HashMap<Index, ShapeKey>,
+    /// Hashmap of Entities to internal joint constraints and the joint
+    /// `Component` owning them. Necessary for reacting to removed Components.
+    pub(crate) joint_handles: HashMap<Index, (JointOwner, ConstraintHandle)>,
+}
+
+/// The `JointOwner` names the joint `Component` whose constraint is stored in
+/// the `PhysicsHandles`. Every `Entity` can own at most one joint `Component`;
+/// the owner keeps the joint `System`s from replacing or removing the
+/// constraints of each other.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum JointOwner {
+    Joint,
+    WheelJoint,
+    HingedDoor,
+    Elevator,
+    Attachment,
+    Sticky,
+}
+
+impl PhysicsHandles {
+    /// Creates a new, empty `PhysicsHandles` `Resource`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Retrieves the handle of the internal RigidBody owned by the given
+    /// `Entity`.
+    pub fn body_handle(&self, entity: Entity) -> Option<BodyHandle> {
+        self.body_handles.get(&entity.id()).cloned()
+    }
+
+    /// Retrieves the handles of all internal Colliders owned by the given
+    /// `Entity`.
+    pub fn collider_handles(&self, entity: Entity) -> &[ColliderHandle] {
+        self.collider_handles.get(entity.id())
+    }
+
+    /// Retrieves the handle of the internal joint constraint owned by the given
+    /// `Entity`.
+    pub fn joint_handle(&self, entity: Entity) -> Option<ConstraintHandle> {
+        self.joint_handles
+            .get(&entity.id())
+            .map(|(_, joint_handle)| *joint_handle)
+    }
+}
+
+/// Per `Entity` storage of `ColliderHandle`s. Most `Entity`s own a single
+/// collider, which is stored inline without allocating.
+pub type ColliderHandleVec = SmallVec<[ColliderHandle; 1]>;
+
+/// The `ColliderHandles` map every `Entity` index to the collection of
+/// `ColliderHandle`s it owns in the nphysics `World`.
+#[derive(Clone, Debug, Default)]
+pub struct ColliderHandles {
+    handles: HashMap<Index, ColliderHandleVec>,
+}
+
+impl ColliderHandles {
+    /// Creates a new, empty `ColliderHandles` map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a `ColliderHandle` to the collection of the given index.
+    pub fn insert(&mut self, id: Index, handle: ColliderHandle) {
+        self.handles.entry(id).or_default().push(handle);
+    }
+
+    /// Returns all `ColliderHandle`s of the given index; this is empty if the
+    /// index has no colliders.
+    pub fn get(&self, id: Index) -> &[ColliderHandle] {
+        self.handles
+            .get(&id)
+            .map(|handles| handles.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Returns the first `ColliderHandle` of the given index.
+    pub fn first(&self, id: Index) -> Option<ColliderHandle> {
+        self.get(id).first().cloned()
+    }
+
+    /// Checks whether the given index owns any colliders.
+    pub fn contains(&self, id: Index) -> bool {
+        self.handles.contains_key(&id)
+    }
+
+    /// Removes and returns all `ColliderHandle`s of the given index.
+    pub fn remove(&mut self, id: Index) -> Option<ColliderHandleVec> {
+        self.handles.remove(&id)
+    }
+
+    /// Removes a single `ColliderHandle` from the collection of the given
+    /// index, dropping the index entirely once it has no colliders left.
+    pub fn remove_handle(&mut self, id: Index, handle: ColliderHandle) -> bool {
+        if let hash_map::Entry::Occupied(mut entry) = self.handles.entry(id) {
+            let position = entry.get().iter().position(|h| *h == handle);
+            if let Some(position) = position {
+                entry.get_mut().remove(position);
+                if entry.get().is_empty() {
+                    entry.remove();
+                }
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// The number of indices owning at least one collider.
+    pub fn len(&self) -> usize {
+        self.handles.len()
+    }
+
+    /// Whether no index owns a collider.
+    pub fn is_empty(&self) -> bool {
+        self.handles.is_empty()
+    }
+
+    /// The total number of `ColliderHandle`s across all indices.
+    pub fn handle_count(&self) -> usize {
+        self.handles.values().map(|handles| handles.len()).sum()
+    }
+
+    /// Iterates over all indices and their `ColliderHandle`s.
+    pub fn iter(&self) -> impl Iterator<Item = (Index, &[ColliderHandle])> + '_ {
+        self.handles
+            .iter()
+            .map(|(id, handles)| (*id, handles.as_slice()))
+    }
+
+    /// Iterates over all `(Index, ColliderHandle)` pairs.
+    pub fn iter_handles(&self) -> impl Iterator<Item = (Index, ColliderHandle)> + '_ {
+        self.handles
+            .iter()
+            .flat_map(|(id, handles)| handles.iter().map(move |handle| (*id, *handle)))
+    }
+}
+
+/// Returns the `Entity` stored in the user data of a body or collider. All
+/// bodies and colliders created by the synchronisation `System`s carry the
+/// `Entity` owning them, so a recycled index can not be mistaken for the
+/// `Entity` that originally created the object. Returns `None` for objects
+/// created directly in the nphysics `World`.
+pub fn entity_from_user_data(user_data: Option<&(dyn Any + Send + Sync)>) -> Option<Entity> {
+    user_data?.downcast_ref::<Entity>().cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc::{self, Receiver, Sender},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use specs::{prelude::*, rayon::ThreadPoolBuilder, Read};
+
+    use crate::{
+        handles::{ColliderHandles, PhysicsHandles},
+        ncollide::shape::{Ball, ShapeHandle},
+        nphysics::{
+            object::{ColliderDesc, ColliderHandle},
+            world::World as PhysicsWorld,
+        },
+        Physics,
+    };
+
+    // creates the given number of colliders in an nphysics World, only to
+    // obtain distinct handles
+    fn collider_handles(count: usize) -> Vec<ColliderHandle> {
+        let mut world = PhysicsWorld::<f32>::new();
+        (0..count)
+            .map(|_| {
+                ColliderDesc::new(ShapeHandle::new(Ball::new(0.5f32)))
+                    .build(&mut world)
+                    .handle()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn store_multiple_handles_per_index() {
+        let handles = collider_handles(3);
+        let mut collider_handles = ColliderHandles::new();
+        assert!(collider_handles.is_empty());
+        assert!(collider_handles.get(1).is_empty());
+
+        collider_handles.insert(1, handles[0]);
+        collider_handles.insert(1, handles[1]);
+        collider_handles.insert(2, handles[2]);
+
+        assert_eq!(collider_handles.get(1), &handles[0..2]);
+        assert_eq!(collider_handles.first(1), Some(handles[0]));
+        assert_eq!(collider_handles.first(2), Some(handles[2]));
+        assert!(collider_handles.contains(2));
+        assert!(!collider_handles.contains(3));
+        assert_eq!(collider_handles.len(), 2);
+        assert_eq!(collider_handles.handle_count(), 3);
+
+        let mut pairs = collider_handles.iter_handles().collect::<Vec<_>>();
+        pairs.sort_by_key(|(id, _)| *id);
+        assert_eq!(pairs.len(), 3);
+        assert_eq!(pairs[2], (2, handles[2]));
+    }
+
+    #[test]
+    fn drop_index_without_handles() {
+        let handles = collider_handles(3);
+        let mut collider_handles = ColliderHandles::new();
+        collider_handles.insert(1, handles[0]);
+        collider_handles.insert(1, handles[1]);
+
+        // removing a handle the index does not own changes nothing
+        assert!(!collider_handles.remove_handle(1, handles[2]));
+        assert!(!collider_handles.remove_handle(2, handles[0]));
+
+        assert!(collider_handles.remove_handle(1, handles[0]));
+        assert_eq!(collider_handles.get(1), &handles[1..2]);
+        assert!(collider_handles.remove_handle(1, handles[1]));
+        assert!(!collider_handles.contains(1));
+        assert!(collider_handles.is_empty());
+
+        collider_handles.insert(3, handles[2]);
+        assert_eq!(
+            collider_handles.remove(3).unwrap().as_slice(),
+            &handles[2..3]
+        );
+        assert!(collider_handles.remove(3).is_none());
+    }
+
+    /// Signals the other `Rendezvous` and waits for its signal, which only
+    /// succeeds if both `System`s run at the same time.
+    struct Rendezvous {
+        sender: Sender<()>,
+        receiver: Receiver<()>,
+        met: Arc<AtomicBool>,
+    }
+
+    impl Rendezvous {
+        fn pair() -> (Self, Self, Arc<AtomicBool>) {
+            let (first_sender, first_receiver) = mpsc::channel();
+            let (second_sender, second_receiver) = mpsc::channel();
+            let met = Arc::new(AtomicBool::new(true));
+            (
+                Rendezvous {
+                    sender: first_sender,
+                    receiver: second_receiver,
+                    met: met.clone(),
+                },
+                Rendezvous {
+                    sender: second_sender,
+                    receiver: first_receiver,
+                    met: met.clone(),
+                },
+                met,
+            )
+        }
+
+        fn meet(&self) {
+            self.sender.send(()).unwrap();
+            if self.receiver.recv_timeout(Duration::from_secs(5)).is_err() {
+                self.met.store(false, Ordering::SeqCst);
+            }
+        }
+    }
+
+    struct WritePhysicsSystem(Rendezvous);
+
+    impl<'s> System<'s> for WritePhysicsSystem {
+        type SystemData = WriteExpect<'s, Physics<f32>>;
+
+        fn run(&mut self, _: Self::SystemData) {
+            self.0.meet();
+        }
+    }
+
+    struct LookUpHandlesSystem(Rendezvous);
+
+    impl<'s> System<'s> for LookUpHandlesSystem {
+        type SystemData = Read<'s, PhysicsHandles>;
+
+        fn run(&mut self, _: Self::SystemData) {
+            self.0.meet();
+        }
+    }
+
+    #[test]
+    fn look_up_handles_while_physics_is_written() {
+        let mut world = World::new();
+        world.insert(Physics::<f32>::default());
+
+        // both Systems block until the other one runs, so they need a thread each
+        let (write, look_up, met) = Rendezvous::pair();
+        let pool = ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with_pool(Arc::new(pool))
+            .with(WritePhysicsSystem(write), "write_physics_system", &[])
+            .with(LookUpHandlesSystem(look_up), "look_up_handles_system", &[])
+            .build();
+        dispatcher.setup(&mut world);
+        dispatcher.dispatch(&world);
+
+        assert!(met.load(Ordering::SeqCst));
+    }
+}
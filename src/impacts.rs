@@ -0,0 +1,67 @@
+//! # Impacts module
+//! Gameplay responses to collisions. Entities with an `ImpactResponse` are
+//! knocked back and take damage when a collider hits them; the
+//! `ApplyImpactResponsesSystem` computes both from the `ContactEvent`s of the
+//! last step and emits a `DamageEvent` per damaging impact.
+
+use specs::{Component, DenseVecStorage};
+
+use crate::nalgebra::RealField;
+
+/// The `DamageCurve` maps the speed of an impact to the damage it deals.
+/// Impacts below the `threshold` speed deal no damage.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DamageCurve<N: RealField> {
+    /// Impacts never deal damage.
+    None,
+    /// The damage grows linearly with the speed above the `threshold`.
+    Linear { threshold: N, factor: N },
+    /// The damage grows with the squared speed above the `threshold`, so hard
+    /// impacts hurt disproportionately more than light ones.
+    Quadratic { threshold: N, factor: N },
+}
+
+impl<N: RealField> DamageCurve<N> {
+    /// Computes the damage of an impact with the given relative speed.
+    pub fn damage(&self, impact_speed: N) -> N {
+        match *self {
+            DamageCurve::None => N::zero(),
+            DamageCurve::Linear { threshold, factor } => {
+                (impact_speed - threshold).max(N::zero()) * factor
+            }
+            DamageCurve::Quadratic { threshold, factor } => {
+                let excess = (impact_speed - threshold).max(N::zero());
+                excess * excess * factor
+            }
+        }
+    }
+}
+
+/// The `ImpactResponse` `Component` makes an `Entity` react to the impacts of
+/// other colliders. The knockback is a change of velocity away from the
+/// impact of `knockback_scale` times the impact speed, so bodies of all
+/// masses are knocked back equally far.
+///
+/// # Example
+///
+/// ```rust
+/// use specs_physics::impacts::{DamageCurve, ImpactResponse};
+///
+/// let impact_response = ImpactResponse {
+///     knockback_scale: 0.5f32,
+///     damage_curve: DamageCurve::Linear {
+///         threshold: 2.0,
+///         factor: 10.0,
+///     },
+/// };
+/// assert_eq!(impact_response.damage_curve.damage(4.0), 20.0);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImpactResponse<N: RealField> {
+    pub knockback_scale: N,
+    pub damage_curve: DamageCurve<N>,
+}
+
+impl<N: RealField> Component for ImpactResponse<N> {
+    type Storage = DenseVecStorage<Self>;
+}
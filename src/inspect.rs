@@ -0,0 +1,308 @@
+//! # Inspect module
+//! Reflection of the physics `Component`s for editors and inspectors. The
+//! `Inspect` trait lists the editable fields of a `Component` by name, and
+//! `edit` changes a single field through the `FlaggedStorage`, so that the
+//! synchronisation `System`s apply the edit to the live physics `World`.
+
+use std::fmt;
+
+use specs::{Component, Entity, WriteStorage};
+
+use crate::{
+    bodies::PhysicsBody,
+    colliders::PhysicsCollider,
+    nalgebra::{Isometry3, Matrix3, Point3, RealField, Vector3},
+    nphysics::object::BodyStatus,
+};
+
+/// The value of a single field of an inspected `Component`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum InspectValue<N: RealField> {
+    Bool(bool),
+    Scalar(N),
+    Vector(Vector3<N>),
+    Matrix(Matrix3<N>),
+    Isometry(Isometry3<N>),
+    BodyStatus(BodyStatus),
+}
+
+/// The reasons an edit of an inspected `Component` can fail.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InspectError {
+    /// The `Component` has no field with the given name.
+    UnknownField(String),
+    /// The value does not match the type of the named field.
+    TypeMismatch(&'static str),
+    /// The `Entity` has no `Component` of the inspected type.
+    MissingComponent,
+}
+
+impl fmt::Display for InspectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InspectError::UnknownField(name) => write!(f, "unknown field `{}`", name),
+            InspectError::TypeMismatch(name) => write!(f, "invalid value for field `{}`", name),
+            InspectError::MissingComponent => write!(f, "missing component"),
+        }
+    }
+}
+
+/// Implemented by physics `Component`s whose fields can be inspected and
+/// edited by name.
+pub trait Inspect<N: RealField> {
+    /// Whether edits have to re-insert the `Component`, as the physics `World`
+    /// can not update it in place.
+    const REINSERT_ON_EDIT: bool = false;
+
+    /// Returns the names and current values of all editable fields.
+    fn fields(&self) -> Vec<(&'static str, InspectValue<N>)>;
+
+    /// Sets the field with the given name.
+    fn set_field(&mut self, name: &str, value: InspectValue<N>) -> Result<(), InspectError>;
+}
+
+impl<N: RealField> Inspect<N> for PhysicsBody<N> {
+    fn fields(&self) -> Vec<(&'static str, InspectValue<N>)> {
+        vec![
+            ("gravity_enabled", InspectValue::Bool(self.gravity_enabled)),
+            ("body_status", InspectValue::BodyStatus(self.body_status)),
+            (
+                "linear_velocity",
+                InspectValue::Vector(self.velocity.linear),
+            ),
+            (
+                "angular_velocity",
+                InspectValue::Vector(self.velocity.angular),
+            ),
+            (
+                "angular_inertia",
+                InspectValue::Matrix(self.angular_inertia),
+            ),
+            ("mass", InspectValue::Scalar(self.mass)),
+            (
+                "local_center_of_mass",
+                InspectValue::Vector(self.local_center_of_mass.coords),
+            ),
+        ]
+    }
+
+    fn set_field(&mut self, name: &str, value: InspectValue<N>) -> Result<(), InspectError> {
+        match (name, value) {
+            ("gravity_enabled", InspectValue::Bool(value)) => self.gravity_enabled = value,
+            ("body_status", InspectValue::BodyStatus(value)) => self.body_status = value,
+            ("linear_velocity", InspectValue::Vector(value)) => self.velocity.linear = value,
+            ("angular_velocity", InspectValue::Vector(value)) => self.velocity.angular = value,
+            ("angular_inertia", InspectValue::Matrix(value)) => self.angular_inertia = value,
+            ("mass", InspectValue::Scalar(value)) => self.mass = value,
+            ("local_center_of_mass", InspectValue::Vector(value)) => {
+                self.local_center_of_mass = Point3::from(value)
+            }
+            (name, _) => return Err(field_error(&self.fields(), name)),
+        }
+        Ok(())
+    }
+}
+
+impl<N: RealField> Inspect<N> for PhysicsCollider<N> {
+    /// nphysics colliders can not be modified in place, edited
+    /// `PhysicsCollider`s are therefore rebuilt.
+    const REINSERT_ON_EDIT: bool = true;
+
+    fn fields(&self) -> Vec<(&'static str, InspectValue<N>)> {
+        vec![
+            (
+                "offset_from_parent",
+                InspectValue::Isometry(self.offset_from_parent),
+            ),
+            ("density", InspectValue::Scalar(self.density)),
+            ("margin", InspectValue::Scalar(self.margin)),
+            (
+                "linear_prediction",
+                InspectValue::Scalar(self.linear_prediction),
+            ),
+            (
+                "angular_prediction",
+                InspectValue::Scalar(self.angular_prediction),
+            ),
+            ("sensor", InspectValue::Bool(self.sensor)),
+            ("sticky", InspectValue::Bool(self.sticky)),
+            ("stop_on_contact", InspectValue::Bool(self.stop_on_contact)),
+            ("smooth_edges", InspectValue::Bool(self.smooth_edges)),
+        ]
+    }
+
+    fn set_field(&mut self, name: &str, value: InspectValue<N>) -> Result<(), InspectError> {
+        match (name, value) {
+            ("offset_from_parent", InspectValue::Isometry(value)) => {
+                self.offset_from_parent = value
+            }
+            ("density", InspectValue::Scalar(value)) => self.density = value,
+            ("margin", InspectValue::Scalar(value)) => self.margin = value,
+            ("linear_prediction", InspectValue::Scalar(value)) => self.linear_prediction = value,
+            ("angular_prediction", InspectValue::Scalar(value)) => self.angular_prediction = value,
+            ("sensor", InspectValue::Bool(value)) => self.sensor = value,
+            ("sticky", InspectValue::Bool(value)) => self.sticky = value,
+            ("stop_on_contact", InspectValue::Bool(value)) => self.stop_on_contact = value,
+            ("smooth_edges", InspectValue::Bool(value)) => self.smooth_edges = value,
+            (name, _) => return Err(field_error(&self.fields(), name)),
+        }
+        Ok(())
+    }
+}
+
+/// Sets a single field of the `Component` of the given `Entity`. The edit
+/// flags the `Component`, which makes the synchronisation `System`s apply it
+/// to the physics `World` during the next frame.
+pub fn edit<N, T>(
+    storage: &mut WriteStorage<T>,
+    entity: Entity,
+    name: &str,
+    value: InspectValue<N>,
+) -> Result<(), InspectError>
+where
+    N: RealField,
+    T: Inspect<N> + Component + Clone,
+{
+    let mut component = storage
+        .get(entity)
+        .cloned()
+        .ok_or(InspectError::MissingComponent)?;
+    component.set_field(name, value)?;
+
+    // removing and inserting the Component within a frame is coalesced into a
+    // single insertion, which replaces the existing physics objects
+    if T::REINSERT_ON_EDIT {
+        storage.remove(entity);
+    }
+    storage
+        .insert(entity, component)
+        .map_err(|_| InspectError::MissingComponent)?;
+    Ok(())
+}
+
+fn field_error<N: RealField>(
+    fields: &[(&'static str, InspectValue<N>)],
+    name: &str,
+) -> InspectError {
+    match fields.iter().find(|(field, _)| *field == name) {
+        Some((field, _)) => InspectError::TypeMismatch(field),
+        None => InspectError::UnknownField(name.to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::{prelude::*, storage::ComponentEvent};
+
+    use crate::{
+        colliders::Shape,
+        handles::PhysicsHandles,
+        inspect::{edit, InspectError, InspectValue},
+        nalgebra::{Isometry3, Vector3},
+        nphysics::object::BodyStatus,
+        Physics,
+        PhysicsBody,
+        PhysicsBodyBuilder,
+        PhysicsCollider,
+        PhysicsColliderBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn apply_edits_to_physics_world() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+
+        let entity = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(
+                PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                    .gravity_enabled(false)
+                    .build(),
+            )
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .build();
+        dispatcher.dispatch(&world);
+        world.maintain();
+
+        let mut reader = world.write_storage::<PhysicsBody<f32>>().register_reader();
+        let velocity = Vector3::new(1.0, 0.0, 0.0);
+        edit(
+            &mut world.write_storage::<PhysicsBody<f32>>(),
+            entity,
+            "linear_velocity",
+            InspectValue::Vector(velocity),
+        )
+        .unwrap();
+        edit(
+            &mut world.write_storage::<PhysicsCollider<f32>>(),
+            entity,
+            "margin",
+            InspectValue::Scalar(0.1f32),
+        )
+        .unwrap();
+
+        // the edit is visible to the synchronisation Systems as a modification
+        let events = world
+            .read_storage::<PhysicsBody<f32>>()
+            .channel()
+            .read(&mut reader)
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(events, vec![ComponentEvent::Modified(entity.id())]);
+
+        dispatcher.dispatch(&world);
+        world.maintain();
+
+        let physics = world.read_resource::<Physics<f32>>();
+        let handles = world.read_resource::<PhysicsHandles>();
+        let rigid_body = physics
+            .world
+            .rigid_body(handles.body_handle(entity).unwrap())
+            .unwrap();
+        assert!((rigid_body.velocity().linear - velocity).norm() < 1.0e-5);
+
+        // the edited collider is rebuilt rather than added a second time
+        let collider_handles = handles.collider_handles(entity);
+        assert_eq!(collider_handles.len(), 1);
+        let collider = physics.world.collider(collider_handles[0]).unwrap();
+        assert_eq!(collider.margin(), 0.1);
+    }
+
+    #[test]
+    fn reject_invalid_edits() {
+        let mut world = World::new();
+        world.register::<PhysicsBody<f32>>();
+        let entity = world
+            .create_entity()
+            .with(
+                PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                    .mass(1.0)
+                    .build(),
+            )
+            .build();
+        let missing = world.create_entity().build();
+
+        let mut storage = world.write_storage::<PhysicsBody<f32>>();
+        assert_eq!(
+            edit(
+                &mut storage,
+                entity,
+                "mass",
+                InspectValue::<f32>::Bool(true)
+            ),
+            Err(InspectError::TypeMismatch("mass"))
+        );
+        assert_eq!(
+            edit(&mut storage, entity, "weight", InspectValue::Scalar(1.0f32)),
+            Err(InspectError::UnknownField("weight".to_owned()))
+        );
+        assert_eq!(
+            edit(&mut storage, missing, "mass", InspectValue::Scalar(1.0f32)),
+            Err(InspectError::MissingComponent)
+        );
+        assert_eq!(storage.get(entity).unwrap().mass, 1.0);
+    }
+}
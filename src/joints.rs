@@ -0,0 +1,595 @@
+//! # Joints module
+//! High-level joint `Component`s which connect the `PhysicsBody` of their own
+//! `Entity` to the `PhysicsBody` of another `Entity`. The constraints are
+//! created in the nphysics `World` by their respective `System`s once both
+//! bodies exist, and are removed again with the `Component` or either body.
+//! Every `Entity` can own at most one joint `Component`; further ones are
+//! ignored and reported with a `DiagnosticKind::JointConflict`.
+
+use std::{collections::HashMap, error::Error, fmt};
+
+use specs::{
+    world::Index,
+    Component,
+    DenseVecStorage,
+    Entity,
+    FlaggedStorage,
+    ReadStorage,
+    WriteStorage,
+};
+
+use crate::nalgebra::{self as na, Isometry3, Point3, RealField, Unit, UnitQuaternion, Vector3};
+
+/// The motor of a `WheelJoint`, spinning the wheel around its axle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WheelMotor<N: RealField> {
+    /// The targeted angular velocity of the wheel relative to the chassis in
+    /// radians per second.
+    pub target_velocity: N,
+    /// The maximum torque the motor can apply. The motor applies its full
+    /// torque once the wheel is off by one radian per second or more.
+    pub max_torque: N,
+}
+
+impl<N: RealField> WheelMotor<N> {
+    /// Computes the torque the motor applies for the given angular velocity
+    /// of the wheel relative to the chassis.
+    pub fn torque(&self, relative_velocity: N) -> N {
+        na::clamp(
+            (self.target_velocity - relative_velocity) * self.max_torque,
+            -self.max_torque,
+            self.max_torque,
+        )
+    }
+}
+
+/// The `WheelJoint` attaches the `PhysicsBody` of its `Entity`, the wheel, to
+/// the `PhysicsBody` of the `chassis` `Entity`. The wheel slides along the
+/// `suspension_axis` held by a spring, rotates freely around the `axle` and
+/// can optionally be driven by a `WheelMotor`.
+///
+/// All vectors are expressed in the local space of the chassis. The origin of
+/// the wheel body is kept on the line through the `anchor` along the
+/// `suspension_axis` and rests at the `anchor` itself.
+///
+/// # Example
+///
+/// ```rust
+/// use specs::{Builder, World, WorldExt};
+/// use specs_physics::{
+///     joints::{WheelJoint, WheelMotor},
+///     nalgebra::{Point3, Vector3},
+/// };
+///
+/// let mut world = World::new();
+/// world.register::<WheelJoint<f32>>();
+/// let chassis = world.create_entity().build();
+///
+/// let wheel_joint = WheelJoint::new(chassis, Point3::new(1.0, -0.5, 1.0), Vector3::x_axis())
+///     .with_suspension(2000.0, 100.0)
+///     .with_motor(WheelMotor {
+///         target_velocity: 10.0,
+///         max_torque: 50.0,
+///     });
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WheelJoint<N: RealField> {
+    pub chassis: Entity,
+    pub anchor: Point3<N>,
+    pub suspension_axis: Unit<Vector3<N>>,
+    pub axle: Unit<Vector3<N>>,
+    /// Spring constant of the suspension.
+    pub stiffness: N,
+    /// Damping constant of the suspension.
+    pub damping: N,
+    pub motor: Option<WheelMotor<N>>,
+}
+
+impl<N: RealField> Component for WheelJoint<N> {
+    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+}
+
+impl<N: RealField> WheelJoint<N> {
+    /// Creates a new `WheelJoint` with a vertical suspension without spring
+    /// and no motor.
+    pub fn new(chassis: Entity, anchor: Point3<N>, axle: Unit<Vector3<N>>) -> Self {
+        Self {
+            chassis,
+            anchor,
+            suspension_axis: Vector3::y_axis(),
+            axle,
+            stiffness: N::zero(),
+            damping: N::zero(),
+            motor: None,
+        }
+    }
+
+    /// Sets the suspension spring.
+    pub fn with_suspension(mut self, stiffness: N, damping: N) -> Self {
+        self.stiffness = stiffness;
+        self.damping = damping;
+        self
+    }
+
+    /// Sets the `WheelMotor`.
+    pub fn with_motor(mut self, motor: WheelMotor<N>) -> Self {
+        self.motor = Some(motor);
+        self
+    }
+
+    /// Computes the suspension force along the `suspension_axis` for the given
+    /// `offset` of the wheel from the `anchor` and relative `speed` along the
+    /// axis.
+    pub fn suspension_force(&self, offset: N, speed: N) -> N {
+        -(offset * self.stiffness + speed * self.damping)
+    }
+}
+
+/// The `Elevator` moves the `PhysicsBody` of its `Entity`, the cabin, along
+/// the `axis` of a prismatic joint between the `floors` of a shaft. The cabin
+/// travels towards the `target_floor` with the given `speed`, pushed by a
+/// motor which can apply at most `max_force`. `ElevatorEvent`s are emitted
+/// when the cabin arrives at the target floor or reaches an end stop.
+///
+/// The shaft starts at the `anchor` and is attached to the `PhysicsBody` of
+/// the `base` `Entity`, or to the ground if there is no `base`. The `anchor`
+/// and `axis` are expressed in the local space of the `base`, the `floors`
+/// and travel limits as offsets from the `anchor` along the `axis`.
+///
+/// # Example
+///
+/// ```rust
+/// use specs_physics::{
+///     joints::Elevator,
+///     nalgebra::{Point3, Vector3},
+/// };
+///
+/// let mut elevator =
+///     Elevator::new(Point3::origin(), Vector3::y_axis(), vec![0.0f32, 4.0, 8.0], 2.0, 5000.0);
+/// elevator.go_to(2);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Elevator<N: RealField> {
+    pub base: Option<Entity>,
+    pub anchor: Point3<N>,
+    pub axis: Unit<Vector3<N>>,
+    /// The lower end stop of the cabin.
+    pub min_offset: N,
+    /// The upper end stop of the cabin.
+    pub max_offset: N,
+    pub floors: Vec<N>,
+    pub target_floor: usize,
+    pub speed: N,
+    pub max_force: N,
+}
+
+impl<N: RealField> Component for Elevator<N> {
+    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+}
+
+impl<N: RealField> Elevator<N> {
+    /// Creates a new `Elevator` attached to the ground, targeting the first
+    /// floor. The travel limits enclose all `floors`.
+    pub fn new(
+        anchor: Point3<N>,
+        axis: Unit<Vector3<N>>,
+        floors: Vec<N>,
+        speed: N,
+        max_force: N,
+    ) -> Self {
+        let min_offset = floors.iter().cloned().fold(N::zero(), N::min);
+        let max_offset = floors.iter().cloned().fold(N::zero(), N::max);
+        Self {
+            base: None,
+            anchor,
+            axis,
+            min_offset,
+            max_offset,
+            floors,
+            target_floor: 0,
+            speed,
+            max_force,
+        }
+    }
+
+    /// Attaches the shaft to the `PhysicsBody` of the given `Entity`.
+    pub fn with_base(mut self, base: Entity) -> Self {
+        self.base = Some(base);
+        self
+    }
+
+    /// Sends the cabin to the given floor.
+    pub fn go_to(&mut self, floor: usize) {
+        self.target_floor = floor;
+    }
+
+    /// The offset of the `target_floor`, limited to the travel limits. If the
+    /// `target_floor` doesn't exist, the cabin stays at the lower end stop.
+    pub fn target_offset(&self) -> N {
+        self.floors
+            .get(self.target_floor)
+            .map_or(self.min_offset, |offset| {
+                offset.max(self.min_offset).min(self.max_offset)
+            })
+    }
+
+    /// Computes the velocity the motor drives the cabin at `offset` with, so
+    /// that it arrives at the target without overshooting within a step of
+    /// length `dt`.
+    pub fn motor_velocity(&self, offset: N, dt: N) -> N {
+        let remaining = self.target_offset() - offset;
+        if remaining.abs() <= self.speed * dt {
+            remaining / dt
+        } else if remaining > N::zero() {
+            self.speed
+        } else {
+            -self.speed
+        }
+    }
+}
+
+/// The spring of a `HingedDoor`, pulling the door back into its closed pose.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DoorSpring<N: RealField> {
+    /// Angular spring constant.
+    pub stiffness: N,
+    /// Angular damping constant.
+    pub damping: N,
+}
+
+/// The `HingedDoor` attaches the `PhysicsBody` of its `Entity`, the door, to
+/// the `PhysicsBody` of the `frame` `Entity` with a revolute joint, or to the
+/// ground if there is no `frame`. The `anchor` and `axis` of the hinge are
+/// expressed in the local space of the `frame`.
+///
+/// Angles are measured around the `axis` from the closed pose, which is the
+/// pose of the door when the joint is first created. A locked door is held in
+/// its current pose until it is unlocked again.
+///
+/// # Example
+///
+/// ```rust
+/// use specs_physics::{
+///     joints::{DoorSpring, HingedDoor},
+///     nalgebra::{Point3, Vector3},
+/// };
+///
+/// let mut door = HingedDoor::new(Point3::new(0.5f32, 0.0, 0.0), Vector3::y_axis())
+///     .with_limits(-1.5, 1.5)
+///     .with_spring(DoorSpring {
+///         stiffness: 20.0,
+///         damping: 5.0,
+///     });
+/// door.lock();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HingedDoor<N: RealField> {
+    pub frame: Option<Entity>,
+    pub anchor: Point3<N>,
+    pub axis: Unit<Vector3<N>>,
+    pub min_angle: Option<N>,
+    pub max_angle: Option<N>,
+    pub spring: Option<DoorSpring<N>>,
+    pub locked: bool,
+}
+
+impl<N: RealField> Component for HingedDoor<N> {
+    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+}
+
+impl<N: RealField> HingedDoor<N> {
+    /// Creates a new, unlocked `HingedDoor` attached to the ground, swinging
+    /// freely around its hinge.
+    pub fn new(anchor: Point3<N>, axis: Unit<Vector3<N>>) -> Self {
+        Self {
+            frame: None,
+            anchor,
+            axis,
+            min_angle: None,
+            max_angle: None,
+            spring: None,
+            locked: false,
+        }
+    }
+
+    /// Attaches the hinge to the `PhysicsBody` of the given `Entity`.
+    pub fn with_frame(mut self, frame: Entity) -> Self {
+        self.frame = Some(frame);
+        self
+    }
+
+    /// Limits the opening angle of the door.
+    pub fn with_limits(mut self, min_angle: N, max_angle: N) -> Self {
+        self.min_angle = Some(min_angle);
+        self.max_angle = Some(max_angle);
+        self
+    }
+
+    /// Sets the `DoorSpring` closing the door automatically.
+    pub fn with_spring(mut self, spring: DoorSpring<N>) -> Self {
+        self.spring = Some(spring);
+        self
+    }
+
+    /// Locks the door in its current pose.
+    pub fn lock(&mut self) {
+        self.locked = true;
+    }
+
+    /// Unlocks the door, allowing it to swing again.
+    pub fn unlock(&mut self) {
+        self.locked = false;
+    }
+
+    /// Computes the torque around the `axis` the `DoorSpring` applies at the
+    /// given `angle` and angular `velocity`.
+    pub fn spring_torque(&self, angle: N, velocity: N) -> N {
+        self.spring.map_or(N::zero(), |spring| {
+            -(angle * spring.stiffness + velocity * spring.damping)
+        })
+    }
+}
+
+/// The `HingedDoorPoses` `Resource` remembers the closed pose of every
+/// `HingedDoor` as the rotation of the door relative to its frame when its
+/// constraint was first created. It is maintained by the
+/// `SyncHingedDoorsToPhysicsSystem`, which forgets the pose once the
+/// `HingedDoor` is removed.
+#[derive(Clone, Debug)]
+pub struct HingedDoorPoses<N: RealField> {
+    closed_rotations: HashMap<Index, UnitQuaternion<N>>,
+}
+
+impl<N: RealField> HingedDoorPoses<N> {
+    /// Returns the closed rotation of the `HingedDoor` of the given `Entity`
+    /// relative to its frame, if the door has been created yet.
+    pub fn closed_rotation(&self, entity: Entity) -> Option<&UnitQuaternion<N>> {
+        self.closed_rotations.get(&entity.id())
+    }
+
+    /// Returns the closed rotation of the `HingedDoor` with the given id,
+    /// remembering the given `rotation` if the door has none yet.
+    pub(crate) fn closed_rotation_or_insert(
+        &mut self,
+        id: Index,
+        rotation: UnitQuaternion<N>,
+    ) -> UnitQuaternion<N> {
+        *self.closed_rotations.entry(id).or_insert(rotation)
+    }
+
+    pub(crate) fn remove(&mut self, id: Index) {
+        self.closed_rotations.remove(&id);
+    }
+}
+
+impl<N: RealField> Default for HingedDoorPoses<N> {
+    fn default() -> Self {
+        Self {
+            closed_rotations: HashMap::new(),
+        }
+    }
+}
+
+/// The kind of constraint a `PhysicsJoint` creates. The `axis` is expressed
+/// in the local space of the other body.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JointKind<N: RealField> {
+    /// Lets the bodies rotate freely around the anchor.
+    Ball,
+    /// Welds the bodies together in their relative pose.
+    Fixed,
+    /// Lets the body slide along the `axis` through the anchor.
+    Prismatic { axis: Unit<Vector3<N>> },
+    /// Lets the body rotate around the `axis` through the anchor.
+    Revolute { axis: Unit<Vector3<N>> },
+}
+
+/// The `PhysicsJoint` connects the `PhysicsBody` of its `Entity` to the
+/// `PhysicsBody` of the `other` `Entity`, or to the ground if there is no
+/// `other`, with a generic constraint of the given `JointKind`. The `anchor`
+/// is expressed in the local space of the other body, or in world space for
+/// the ground; the body of the `Entity` is attached at wherever the anchor
+/// lies when the constraint is created. The constraint is created, recreated
+/// on modification and removed by the `SyncJointsToPhysicsSystem`.
+///
+/// # Example
+///
+/// ```rust
+/// use specs_physics::{joints::PhysicsJoint, nalgebra::Point3};
+///
+/// // a pendulum swinging around a point above the origin
+/// let joint = PhysicsJoint::<f32>::ball(None, Point3::new(0.0, 2.0, 0.0));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PhysicsJoint<N: RealField> {
+    pub other: Option<Entity>,
+    pub kind: JointKind<N>,
+    pub anchor: Point3<N>,
+}
+
+impl<N: RealField> Component for PhysicsJoint<N> {
+    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+}
+
+impl<N: RealField> PhysicsJoint<N> {
+    /// Creates a new `PhysicsJoint` of the given `JointKind`.
+    pub fn new(other: Option<Entity>, kind: JointKind<N>, anchor: Point3<N>) -> Self {
+        Self {
+            other,
+            kind,
+            anchor,
+        }
+    }
+
+    /// Creates a new ball `PhysicsJoint`.
+    pub fn ball(other: Option<Entity>, anchor: Point3<N>) -> Self {
+        Self::new(other, JointKind::Ball, anchor)
+    }
+
+    /// Creates a new fixed `PhysicsJoint`.
+    pub fn fixed(other: Option<Entity>, anchor: Point3<N>) -> Self {
+        Self::new(other, JointKind::Fixed, anchor)
+    }
+
+    /// Creates a new prismatic `PhysicsJoint` sliding along the `axis`.
+    pub fn prismatic(other: Option<Entity>, anchor: Point3<N>, axis: Unit<Vector3<N>>) -> Self {
+        Self::new(other, JointKind::Prismatic { axis }, anchor)
+    }
+
+    /// Creates a new revolute `PhysicsJoint` rotating around the `axis`.
+    pub fn revolute(other: Option<Entity>, anchor: Point3<N>, axis: Unit<Vector3<N>>) -> Self {
+        Self::new(other, JointKind::Revolute { axis }, anchor)
+    }
+}
+
+/// The `StuckTo` `Component` is inserted by the `SyncStickyCollidersSystem`
+/// once the sticky `PhysicsCollider` of its `Entity` touches the collider of
+/// the given `Entity`, and holds the body of its `Entity` in place relative
+/// to the other body with a fixed joint, e.g. an arrow embedded in a moving
+/// target. Colliders without a `PhysicsBody` of their own are treated as
+/// ground. Removing the `Component` releases the body again.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StuckTo(pub Entity);
+
+impl Component for StuckTo {
+    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+}
+
+/// The `Sockets` `Component` names anchor poses in the local space of the
+/// `PhysicsBody` of its `Entity`, at which other bodies can be attached, e.g.
+/// the hand of a character holding a weapon or the hitch of a truck.
+///
+/// # Example
+///
+/// ```rust
+/// use specs_physics::{joints::Sockets, nalgebra::Isometry3};
+///
+/// let sockets = Sockets::new()
+///     .with("right_hand", Isometry3::translation(0.4f32, 1.2, 0.1))
+///     .with("back", Isometry3::translation(0.0, 1.4, -0.2));
+/// assert!(sockets.get("right_hand").is_some());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sockets<N: RealField> {
+    sockets: HashMap<String, Isometry3<N>>,
+}
+
+impl<N: RealField> Component for Sockets<N> {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl<N: RealField> Sockets<N> {
+    /// Creates an empty `Sockets` registry.
+    pub fn new() -> Self {
+        Self {
+            sockets: HashMap::new(),
+        }
+    }
+
+    /// Adds a socket with the given local pose.
+    pub fn with(mut self, name: impl Into<String>, pose: Isometry3<N>) -> Self {
+        self.insert(name, pose);
+        self
+    }
+
+    /// Inserts or replaces a socket, returning its previous pose.
+    pub fn insert(&mut self, name: impl Into<String>, pose: Isometry3<N>) -> Option<Isometry3<N>> {
+        self.sockets.insert(name.into(), pose)
+    }
+
+    /// Removes a socket, returning its pose.
+    pub fn remove(&mut self, name: &str) -> Option<Isometry3<N>> {
+        self.sockets.remove(name)
+    }
+
+    /// Returns the local pose of the named socket.
+    pub fn get(&self, name: &str) -> Option<&Isometry3<N>> {
+        self.sockets.get(name)
+    }
+
+    /// Iterates over the names and local poses of all sockets.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Isometry3<N>)> {
+        self.sockets
+            .iter()
+            .map(|(name, pose)| (name.as_str(), pose))
+    }
+}
+
+impl<N: RealField> Default for Sockets<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The `Attachment` welds the `PhysicsBody` of its `Entity` to the named
+/// socket of the `parent` `Entity` with a fixed joint. The origin of the
+/// attached body is snapped onto the socket when the joint is created by the
+/// `SyncAttachmentsToPhysicsSystem`, so the body does not have to be placed
+/// beforehand. Removing the `Component` detaches the body again.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attachment {
+    pub parent: Entity,
+    pub socket: String,
+}
+
+impl Component for Attachment {
+    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+}
+
+impl Attachment {
+    /// Creates an `Attachment` to the named socket of the `parent`.
+    pub fn new(parent: Entity, socket: impl Into<String>) -> Self {
+        Self {
+            parent,
+            socket: socket.into(),
+        }
+    }
+}
+
+/// The reasons attaching a body to a socket can fail.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttachError {
+    /// The parent `Entity` has no socket with the given name.
+    UnknownSocket(String),
+    /// The child `Entity` is no longer alive.
+    DeadEntity(Entity),
+}
+
+impl fmt::Display for AttachError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AttachError::UnknownSocket(name) => write!(f, "unknown socket `{}`", name),
+            AttachError::DeadEntity(entity) => write!(f, "dead entity {:?}", entity),
+        }
+    }
+}
+
+impl Error for AttachError {}
+
+/// Attaches the `child` to the named socket of the `parent` by inserting an
+/// `Attachment`, replacing any previous one. Fails if the `parent` has no such
+/// socket.
+pub fn attach<N: RealField>(
+    sockets: &ReadStorage<Sockets<N>>,
+    attachments: &mut WriteStorage<Attachment>,
+    child: Entity,
+    parent: Entity,
+    socket: &str,
+) -> Result<(), AttachError> {
+    if sockets
+        .get(parent)
+        .and_then(|sockets| sockets.get(socket))
+        .is_none()
+    {
+        return Err(AttachError::UnknownSocket(socket.to_owned()));
+    }
+    attachments
+        .insert(child, Attachment::new(parent, socket))
+        .map_err(|_| AttachError::DeadEntity(child))?;
+    Ok(())
+}
+
+/// Detaches the `child` from its socket, returning the removed `Attachment`.
+pub fn detach(attachments: &mut WriteStorage<Attachment>, child: Entity) -> Option<Attachment> {
+    attachments.remove(child)
+}
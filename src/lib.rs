@@ -132,6 +132,31 @@
 //!     .build();
 //! ```
 //!
+//! Static ground is best represented by a `Shape::Plane`, an infinite
+//! half-space which is cheaper and more robust than a giant `Shape::Cuboid`;
+//! `Shape::ground()` returns one facing up. Characters and vehicles slide far
+//! more smoothly with a `Shape::RoundedCuboid` or `Shape::RoundedCylinder`
+//! than with sharp-edged primitives. Terrain outlines, e.g. in the xy
+//! plane of a 2D game, are represented exactly by the `Shape::Polyline`
+//! returned by `Shape::chain`, which can also be closed into a loop. Replacing
+//! the `Shape` of a `PhysicsCollider` updates its [Collider][], so
+//! destructible terrain can be cut at runtime, and `Shape::remove_part`,
+//! `Shape::add_parts` and `Shape::fracture_part` break compound colliders
+//! apart piece by piece without recreating them.
+//!
+//! The outlines of sprites can be traced from their alpha channel with the
+//! `specs_physics::sprites` module: `sprites::outline_shape` returns a closed
+//! `Shape::Polyline` following the opaque pixels and `sprites::convex_shape`
+//! a `Shape::ConvexHull` around them, both in the xy plane.
+//!
+//! A `specs_physics::colliders::ContactThrottle` set through
+//! `PhysicsColliderBuilder::contact_throttle` keeps rolling or bouncing
+//! colliders from flooding the `ContactEvents` channel: contacts following
+//! the last one of the same pair within `min_interval` seconds, or with an
+//! impulse below `min_impulse`, emit no `ContactEvent`s.
+//! `PhysicsColliderBuilder::event_role` marks colliders as the `Source` or
+//! `Target` of events, e.g. to always report the player as `collider1`.
+//!
 //! To assign multiple [Collider][]'s the the same body, [Entity hierarchy][]
 //! can be used. This utilises [specs-hierarchy][].
 //!
@@ -143,7 +168,8 @@
 //! 1. `specs_physics::systems::SyncBodiesToPhysicsSystem` - handles the
 //! creation, modification and removal of [RigidBody][]'s based on the
 //! `PhysicsBody` `Component` and an implementation of the `Position`
-//! *trait*.
+//! *trait*. Modified `Position`s within the
+//! `specs_physics::parameters::PoseEpsilon` of the simulated pose are skipped.
 //!
 //! 2. `specs_physics::systems::SyncCollidersToPhysicsSystem` - handles
 //! the creation, modification and removal of [Collider][]'s based on the
@@ -156,7 +182,11 @@
 //! 4. `specs_physics::systems::PhysicsStepperSystem` - handles the progression
 //! of the [nphysics][] `World` and causes objects to actually move and
 //! change their position. This `System` is the backbone for collision
-//! detection.
+//! detection. If a `specs_physics::parameters::DeltaTime` `Resource` exists,
+//! the `World` is progressed in fixed substeps limited by the
+//! `specs_physics::parameters::StepBudget`. Stacks of bodies with very
+//! different masses stay stable with a
+//! `specs_physics::parameters::MassScaling` `Resource`.
 //!
 //! 5. `specs_physics::systems::SyncBodiesFromPhysicsSystem` -
 //! handles the synchronisation of [RigidBody][] positions and dynamics back
@@ -211,6 +241,12 @@
 //!     .build();
 //! ```
 //!
+//! The `SyncBodiesToPhysicsSystem` and `SyncCollidersToPhysicsSystem` can also
+//! be added once per `specs_physics::systems::SyncPhase` using `with_phase`,
+//! which allows running custom `System`s between the insertion, update and
+//! removal of physics objects. `register_physics_systems()` registers the
+//! phases as separate `System`s.
+//!
 //! If you're using [Amethyst][] Transforms directly, you'd pass the generic
 //! arguments like so:
 //!
@@ -227,6 +263,499 @@
 //! `DispatcherBuilder` as an argument and registers the required `System`s for
 //! you.
 //!
+//! #### Utility forces
+//!
+//! The `specs_physics::forces` module contains `Component`s such as the
+//! `PdController` or the `Buoyancy`, which are converted into forces on the
+//! `PhysicsBody` of the same `Entity` by their respective `System`s. Buoyant
+//! bodies float on the `specs_physics::forces::WaterSurface` `Resource`, whose
+//! height can be sampled from a closure to follow animated waves. Turrets and
+//! homing bodies turn towards a point or another `Entity` with a
+//! `specs_physics::forces::LookAt`, applied as a torque by the
+//! `specs_physics::systems::ApplyLookAtsSystem`. These
+//! `System`s are not part of the default `Dispatcher` and have to run before
+//! the `SyncBodiesToPhysicsSystem`, which `register_physics_systems_after()`
+//! takes care of:
+//!
+//! ```rust
+//! use specs::DispatcherBuilder;
+//! use specs_physics::{systems::ApplyPdControllersSystem, SimplePosition};
+//!
+//! let mut dispatcher_builder = DispatcherBuilder::new().with(
+//!     ApplyPdControllersSystem::<f32, SimplePosition<f32>>::default(),
+//!     "apply_pd_controllers_system",
+//!     &[],
+//! );
+//! specs_physics::register_physics_systems_after::<f32, SimplePosition<f32>>(
+//!     &mut dispatcher_builder,
+//!     &["apply_pd_controllers_system"],
+//! );
+//! ```
+//!
+//! `System`s that have to run relative to the physics stages, e.g. after the
+//! simulated poses have been written back, can depend on the `System` names
+//! exported by `specs_physics::PhysicsStages`.
+//!
+//! #### Simulation level-of-detail
+//!
+//! The `specs_physics::systems::ApplyActivationRadiusSystem` puts dynamic
+//! bodies to sleep, or disables them, once they are further than the
+//! `specs_physics::parameters::ActivationRadius` away from every `Entity`
+//! marked with the `specs_physics::bodies::SimulationFocus` `Component`, and
+//! reactivates them when a focus comes near. Like the utility force
+//! `System`s, it is not part of the default `Dispatcher` and has to run
+//! between the `SyncBodiesToPhysicsSystem` and the `PhysicsStepperSystem`.
+//!
+//! #### Simulation rates
+//!
+//! Adding the `specs_physics::bodies::SimulationRate` `Component` simulates a
+//! body at half or quarter rate, stepped every second or fourth frame with a
+//! correspondingly scaled time step. The
+//! `specs_physics::systems::ApplySimulationRatesSystem` and
+//! `specs_physics::systems::RestoreSimulationRatesSystem` are part of the
+//! default `Dispatcher` and run right before and after the
+//! `PhysicsStepperSystem`.
+//!
+//! #### Spin limits
+//!
+//! The `specs_physics::bodies::MaxAngularVelocity` `Component` caps the angular
+//! speed of a body, keeping small props from spinning at thousands of RPM
+//! after resolving penetrations. The
+//! `specs_physics::systems::ApplyMaxAngularVelocitiesSystem` is not part of
+//! the default `Dispatcher` and has to run between the `PhysicsStepperSystem`
+//! and the `SyncBodiesFromPhysicsSystem`.
+//!
+//! #### Tunneling prediction
+//!
+//! Fast, small bodies such as bullets can pass through thin colliders between
+//! two steps. The `specs_physics::systems::ApplyTunnelingPredictionSystem`
+//! raises the `linear_prediction` of the colliders of bodies travelling more
+//! than half their size per step and reports them as
+//! `specs_physics::events::TunnelingRiskEvent`s. It is not part of the
+//! default `Dispatcher` and has to run before the
+//! `SyncCollidersToPhysicsSystem`.
+//!
+//! #### Character controllers
+//!
+//! The `specs_physics::characters::CharacterController` moves a kinematic
+//! `PhysicsBody` with a desired velocity, keeps it on the ground and lets it
+//! fall otherwise; with slope snapping enabled, characters follow ramps and
+//! small ledges down instead of launching off them. Characters overlapping
+//! the sensor of a `specs_physics::characters::Climbable` climb it instead,
+//! and swim within a `specs_physics::characters::WaterVolume`, as reported by
+//! their `CharacterState`. The
+//! `specs_physics::systems::MoveCharactersSystem` is not part of the default
+//! `Dispatcher` and has to run before the `SyncBodiesToPhysicsSystem`.
+//! Resizing the capsule at runtime, e.g. to crouch, is applied by the
+//! `specs_physics::systems::ResizeCharactersSystem` running before it, which
+//! denies standing up while blocked and reports both through
+//! `specs_physics::events::CharacterEvents`. AI movement plugs in through the
+//! `specs_physics::characters::NavAgent`, whose
+//! `specs_physics::systems::ApplyNavAgentsSystem` steers characters and other
+//! bodies towards the velocity requested by a navigation system. Characters
+//! with a push mass shove the dynamic bodies they walk into as if they had
+//! that mass, instead of ignoring them or pushing with infinite force.
+//! Setting the `debug` flag of a controller emits a
+//! `specs_physics::events::CharacterTrace` for every move, listing the queries
+//! made, what they hit and how the move was resolved, e.g. to diagnose
+//! characters getting stuck on the seams between colliders.
+//!
+//! #### Scripted motion
+//!
+//! Moving hazards and cutscene platforms are declared with a
+//! `specs_physics::motion::ScriptedMotion`, which follows keyframed poses or
+//! a function of time, once, looping or back and forth. The
+//! `specs_physics::systems::ApplyScriptedMotionSystem` drives kinematic
+//! bodies along it with the velocity reaching the next pose, so bodies resting
+//! on them are carried along. It is not part of the default `Dispatcher` and
+//! has to run before the `SyncBodiesToPhysicsSystem`.
+//!
+//! Rails, rollercoasters and camera dollies confine a body to a
+//! `specs_physics::motion::Spline` with a `PathConstraint`, optionally driven
+//! by a `PathMotor`. The `specs_physics::systems::ApplyPathConstraintsSystem`
+//! keeps only the velocity along the path, so gravity and collisions still
+//! move the body along it, and has to run before the
+//! `SyncBodiesToPhysicsSystem` as well.
+//!
+//! #### Impact responses
+//!
+//! `Entity`s with the `specs_physics::impacts::ImpactResponse` `Component`
+//! are knocked back when another collider hits them and take damage according
+//! to their `DamageCurve`, reported through the
+//! `specs_physics::events::DamageEvents` channel. The
+//! `specs_physics::systems::ApplyImpactResponsesSystem` is not part of the
+//! default `Dispatcher` and has to run between the `PhysicsStepperSystem` and
+//! the `SyncBodiesFromPhysicsSystem`, so the physics `System`s have to be
+//! registered manually to use it.
+//!
+//! `specs_physics::fracture::fracture` breaks the body of an `Entity` into
+//! dynamic debris `Entity`s with convex pieces, which inherit the velocity of
+//! the original body and are pushed apart by the impact that broke it. The
+//! original `Entity` is deleted.
+//!
+//! #### Joints
+//!
+//! The `specs_physics::joints` module contains high-level joint `Component`s
+//! such as the `WheelJoint`, the `Elevator` and the `HingedDoor`, which connect
+//! the `PhysicsBody` of their `Entity` to the `PhysicsBody` of another
+//! `Entity`. Their `System`s are part of the default `Dispatcher` and run
+//! between the `SyncBodiesToPhysicsSystem` and the `PhysicsStepperSystem`.
+//! Plain ball, fixed, prismatic and revolute constraints are described by the
+//! generic `PhysicsJoint` `Component`, which the `SyncJointsToPhysicsSystem`
+//! creates, recreates and removes in the nphysics `World` like the
+//! `SyncCollidersToPhysicsSystem` does for colliders.
+//!
+//! Named anchor poses are registered with the `Sockets` `Component` of a
+//! parent `Entity`; `specs_physics::joints::attach` inserts an `Attachment`,
+//! which snaps the body of the child onto the socket and welds it there, e.g.
+//! to equip a character or to assemble modular vehicles.
+//!
+//! Bodies with a `sticky` `PhysicsCollider` are fixed to the first collider
+//! they touch, e.g. arrows embedding into a target, and marked with the
+//! `specs_physics::joints::StuckTo` `Component`. The
+//! `specs_physics::systems::SyncStickyCollidersSystem` is not part of the
+//! default `Dispatcher` and has to run after the `PhysicsStepperSystem`.
+//! Bodies whose `PhysicsCollider` has `stop_on_contact` set come to rest
+//! against whatever they hit instead of bouncing off, which is applied by the
+//! `specs_physics::systems::ApplyStopOnContactSystem` running between the
+//! `PhysicsStepperSystem` and the `SyncBodiesFromPhysicsSystem`.
+//! Likewise, the `specs_physics::systems::ApplyInternalEdgeCorrectionSystem`
+//! keeps bodies rolling across `TriMesh` and `HeightField` colliders with
+//! `smooth_edges` set from being bumped by the edges between flat faces.
+//!
+//! #### Inspecting components
+//!
+//! `PhysicsBody` and `PhysicsCollider` implement the
+//! `specs_physics::inspect::Inspect` trait, which exposes their fields by name
+//! for editors. Edits made with `specs_physics::inspect::edit` flag the
+//! `Component`s and are applied to the running simulation.
+//!
+//! #### Configuration reloading
+//!
+//! Inserting a `specs_physics::parameters::PhysicsConfig` `Resource` makes the
+//! `specs_physics::systems::ApplyPhysicsConfigSystem` apply its gravity,
+//! integration parameters, collision matrix and material coefficients to the
+//! running simulation. Replacing the `Resource`, e.g. from a file watcher,
+//! applies the values that changed without restarting the game.
+//!
+//! #### Physics commands
+//!
+//! Instead of fetching the `Physics` `Resource` for writing, `System`s can
+//! queue impulses, velocity changes, teleports and body removals in the
+//! `specs_physics::commands::PhysicsCommands` `Resource`. Queueing only needs a
+//! `Read<PhysicsCommands<N>>`, so these `System`s can run in parallel; the
+//! `specs_physics::systems::ApplyPhysicsCommandsSystem` applies the commands
+//! right before the simulation is stepped.
+//!
+//! ### Queries
+//!
+//! The `specs_physics::queries` module casts rays against the colliders of
+//! the physics `World`. Queries are filtered by the
+//! `specs_physics::colliders::QueryGroups` of the `PhysicsCollider`s, which
+//! are independent of their `CollisionGroups`: a ray can hit sensors or
+//! colliders that do not collide with anything, and skip solid ones. The
+//! `_filtered` variants additionally take a predicate to exclude `Entity`s,
+//! e.g. the shooter, while the hits are traversed. For piercing projectiles,
+//! `Physics::ray_cast_all` returns every hit along a ray sorted by distance,
+//! and `Physics::ray_cast_batch` casts hundreds of rays at once, e.g. for
+//! vision cones.
+//! Ray hits are reported as `specs_physics::queries::QueryHit`s, carrying
+//! the `Entity`s of the hit body and collider, the hit point and normal, and
+//! the id of the hit shape feature.
+//! A `specs_physics::queries::RayQuery` casts the same ray every frame, e.g.
+//! for wheels or sensor whiskers, and narrows the search down to the
+//! colliders in front of its last hit.
+//! `Physics::pick` unprojects a screen position through the camera matrices
+//! and returns the `Entity` under the cursor, e.g. for editor selection.
+//! `Physics::line_of_sight` checks whether the segment between two `Entity`s
+//! is unobstructed, ignoring their own colliders, e.g. for AI perception,
+//! and `Physics::audio_occlusion` turns a bundle of such rays into an
+//! occlusion factor for audio middleware.
+//! `Physics::overlaps` tests an arbitrary `Shape` at a pose against the
+//! colliders, e.g. for instantaneous ability hitboxes, while the
+//! `specs_physics::queries::HitboxWindow` sweeps a hitbox over several frames
+//! and reports every `Entity` once per activation.
+//! `Physics::nearest_bodies` gathers the k nearest dynamic bodies around a
+//! point from the broad phase, so flocking and crowd `System`s do not have to
+//! maintain their own spatial structures.
+//! The `specs_physics::queries::CameraCollision` sweeps a sphere from a
+//! third-person camera's target towards its desired position and returns
+//! how far the camera can move out without clipping into walls.
+//!
+//! ### Sensor-only mode
+//!
+//! Games that only need overlap and trigger queries can skip the dynamics
+//! altogether and replace all physics `System`s with the
+//! `specs_physics::systems::SyncSensorsSystem`. It registers the
+//! `PhysicsCollider`s of `Entity`s with a `Position` in the
+//! `specs_physics::sensors::SensorWorld`, a plain collision detection world
+//! without rigid bodies or solver, and reports their overlaps as
+//! `ProximityEvent`s. `PhysicsBody` `Component`s are ignored in this mode.
+//!
+//! Thousands of tiny static triggers, such as coins or pellets, are cheaper
+//! as `specs_physics::sensors::Pickup`s than as colliders. The
+//! `specs_physics::systems::CollectPickupsSystem` sorts them into the cells
+//! of the `PickupGrid` `Resource` and tests them against the `Position`s of
+//! `PickupCollector`s only, reporting every reached `Pickup` as a
+//! `PickupEvent`. It works with and without the physics `World`.
+//!
+//! ### Debug rendering
+//!
+//! The `specs_physics::systems::DebugRenderSystem` visualises collider
+//! shapes, bounding boxes, contacts, sensor overlaps, velocities, centers of
+//! mass and joints. It is not part of the default `Dispatcher`; every frame it
+//! replaces the lines of the `specs_physics::debug::DebugRender` `Resource`,
+//! which can then be drawn by your renderer of choice. Each category can be
+//! toggled on the `DebugRender` `Resource`:
+//!
+//! ```rust
+//! use specs::{World, WorldExt};
+//! use specs_physics::debug::DebugRender;
+//!
+//! let mut world = World::new();
+//! world.insert(DebugRender::<f32> {
+//!     contacts: false,
+//!     velocities: true,
+//!     ..DebugRender::default()
+//! });
+//! ```
+//!
+//! ### Recording and replay
+//!
+//! Hard to reproduce simulation bugs can be captured by adding the
+//! `specs_physics::systems::RecordPhysicsInputsSystem` right before the
+//! `SyncBodiesToPhysicsSystem`. It records every input of the physics `World`
+//! into the `specs_physics::recording::PhysicsRecording` `Resource`, which can
+//! be fed back into a fresh `World` with a `PhysicsReplayer` to reproduce the
+//! exact same simulation.
+//!
+//! ### Events
+//!
+//! The `PhysicsStepperSystem` drains the contact and proximity events of the
+//! nphysics `World` after every step and publishes them with the `Entity`s of
+//! both colliders resolved into the `specs_physics::events::ContactEvents`
+//! and `ProximityEvents` `EventChannel` `Resource`s, so no additional
+//! `System` is needed. Gameplay code registers a reader and reacts to the
+//! events once the default `Dispatcher` ran:
+//!
+//! ```rust
+//! use specs::{World, WorldExt};
+//! use specs_physics::{
+//!     events::{ContactEvents, ContactType},
+//!     SimplePosition,
+//! };
+//!
+//! let mut world = World::new();
+//! let mut dispatcher = specs_physics::physics_dispatcher::<f32, SimplePosition<f32>>();
+//! dispatcher.setup(&mut world);
+//! let mut reader_id = world.write_resource::<ContactEvents>().register_reader();
+//!
+//! dispatcher.dispatch(&world);
+//! for event in world.read_resource::<ContactEvents>().read(&mut reader_id) {
+//!     if event.contact_type == ContactType::Started {
+//!         println!("{:?} touched {:?}", event.collider1, event.collider2);
+//!     }
+//! }
+//! ```
+//!
+//! ### Event ticks
+//!
+//! `ContactEvent`s, `ProximityEvent`s and `DamageEvent`s carry the `tick`
+//! index of the fixed step they occurred in, as counted by `Physics::tick`,
+//! so replays, netcode and analytics can correlate them precisely even when
+//! the `PhysicsStepperSystem` runs several substeps per dispatch.
+//!
+//! The `ContactEvent`s and `ProximityEvent`s of several substeps are coalesced
+//! into a clean per-frame view: each pair of colliders reports at most one
+//! `Started` and one `Stopped` event per dispatch, and a pair separating only
+//! for a substep reports nothing. Inserting
+//! `specs_physics::parameters::RawSubstepEvents(true)` disables the
+//! coalescing.
+//!
+//! ### Event subscriptions
+//!
+//! Gameplay `System`s usually care about a few kinds of pairs only, e.g. the
+//! player touching a hazard. A `specs_physics::subscriptions::Subscription`
+//! built with `Subscribe::pairs().with_component::<Player>()
+//! .against_layer("hazard").into_channel::<PlayerHitHazard>()` forwards the
+//! matching `ContactEvent`s and `ProximityEvent`s to the
+//! `PairEvents<PlayerHitHazard>` channel, with the player always as the
+//! `subject`. Layers are collision groups named in the `CollisionLayers`
+//! `Resource`. `Subscription`s inspect arbitrary `Component`s and have to be
+//! added with `DispatcherBuilder::with_thread_local`.
+//!
+//! ### Event capacity
+//!
+//! The `EventChannel`s of `shrev` grow as long as any reader lags behind, so
+//! a single frame with a pile of touching colliders can produce thousands of
+//! `ContactEvent`s. Inserting a `specs_physics::parameters::EventCapacity`
+//! `Resource` limits the number of `ContactEvent`s and `ProximityEvent`s the
+//! `PhysicsStepperSystem` writes per dispatch. Its `OverflowPolicy` either
+//! drops the oldest events of the dispatch, counting them in the
+//! `specs_physics::events::DroppedEventCounts` `Resource` and reporting a
+//! `DiagnosticKind::EventsDropped`, or panics.
+//!
+//! ### Metrics
+//!
+//! With the "metrics" feature enabled, the `PhysicsStepperSystem` emits the
+//! step duration, body and collider counts and the number of contact and
+//! proximity events per step through the [metrics][] facade. After every
+//! dispatch it also reports the number of events written to the
+//! `ContactEvents` and `ProximityEvents` channels and the number of touching
+//! collider pairs as gauges.
+//!
+//! ```toml
+//! [dependencies]
+//! specs-physics = { version = "0.3", features = ["metrics"] }
+//! ```
+//!
+//! ### Validation
+//!
+//! NaN, infinite or negative values in `Position`s, `PhysicsBody`s or
+//! `PhysicsCollider`s usually surface as panics deep inside the nphysics
+//! solver. With the "validation" feature enabled, the synchronisation
+//! `System`s check every inserted or modified `Component` first, skip the
+//! offending `Entity` and report it at `Level::Error` together with a
+//! `DiagnosticKind::InvalidInput`. The checks are also available as plain
+//! functions in `specs_physics::validation`.
+//!
+//! ```toml
+//! [dependencies]
+//! specs-physics = { version = "0.3", features = ["validation"] }
+//! ```
+//!
+//! ### Storage
+//!
+//! `PhysicsBody` and `PhysicsCollider` are stored in a `DenseVecStorage` by
+//! default. Scenes with physics on nearly every `Entity` can enable the
+//! "vec-storage" feature to store them in a `VecStorage` instead.
+//!
+//! ```toml
+//! [dependencies]
+//! specs-physics = { version = "0.3", features = ["vec-storage"] }
+//! ```
+//!
+//! ### Baked static geometry
+//!
+//! Building the bounding volume hierarchies of large triangle meshes can
+//! dominate the load time of a level. With the "baking" feature enabled,
+//! `specs_physics::baking::BakedShapes` builds the meshes ahead of time,
+//! writes them to disk and loads them back without rebuilding their
+//! hierarchies. The loaded meshes are used through `Shape::Baked`:
+//!
+//! ```toml
+//! [dependencies]
+//! specs-physics = { version = "0.3", features = ["baking"] }
+//! ```
+//!
+//! ### Map import
+//!
+//! With the "maps" feature enabled, `specs_physics::maps::MapImport` reads
+//! the collision layers of Tiled and LDtk JSON exports into a `Scenario` of
+//! static colliders. The solid tiles of a layer are merged into few
+//! rectangles sharing one compound collider, and the shapes of Tiled object
+//! layers become colliders of their own:
+//!
+//! ```toml
+//! [dependencies]
+//! specs-physics = { version = "0.3", features = ["maps"] }
+//! ```
+//!
+//! ### Terrain streaming
+//!
+//! `specs_physics::terrain::TerrainChunks` splits a large height field into
+//! chunk colliders which are loaded and unloaded around a focus point as the
+//! player moves. The chunks share the heights along their borders and have
+//! `smooth_edges` set, so with the `ApplyInternalEdgeCorrectionSystem`
+//! running, bodies cross the seams between chunks without catching on them.
+//! `Physics::modify_heightfield` overwrites a region of the heights of a
+//! height field collider at runtime, e.g. for craters or digging, and
+//! `TerrainChunks::modify` applies such changes to all loaded chunks.
+//!
+//! ### Background collider loading
+//!
+//! Inserting a `specs_physics::loading::ColliderLoader` `Resource` makes the
+//! `SyncCollidersToPhysicsSystem` build triangle meshes, convex hulls and
+//! height fields on a pool of worker threads. Until their collider enters the
+//! physics `World`, the `Entity`s are marked with the
+//! `specs_physics::loading::ColliderPending` `Component`.
+//!
+//! ### Parallel write-back
+//!
+//! Scenes with many thousands of bodies can enable the "parallel" feature,
+//! which makes the `SyncBodiesFromPhysicsSystem` copy the simulated poses back
+//! into the `Position`s with a `par_join` instead of on a single thread.
+//!
+//! ```toml
+//! [dependencies]
+//! specs-physics = { version = "0.3", features = ["parallel"] }
+//! ```
+//!
+//! ### 2D mode
+//!
+//! Games living in a plane can enable the "dim2" feature and use the
+//! `specs_physics::dim2` module instead, which runs the core `System`s on
+//! nphysics2d with `Isometry2` `Position`s and 2D `Shape`s. Its `Dispatcher`
+//! publishes into the same `ContactEvents` and `ProximityEvents` channels.
+//! The extensions of the 3D mode, such as joints, characters and queries,
+//! are not available in 2D yet.
+//!
+//! ### Testing
+//!
+//! Integration tests of downstream crates can assert on the physics state
+//! with the `assert_body_at!(world, entity, position, eps)`,
+//! `assert_in_contact!(world, a, b)` and `assert_not_in_contact!(world, a, b)`
+//! macros, which build on `Physics::body_position` and `Physics::in_contact`.
+//!
+//! Simulation behaviour is guarded by golden-scene regression tests:
+//! `golden::check_golden` simulates a `Scenario` for a number of steps and
+//! compares the final poses with a stored text file within a
+//! `GoldenTolerance`. Intended changes are recorded by running the tests with
+//! the `SPECS_PHYSICS_BLESS` environment variable set.
+//!
+//! With the "ordering-checks" feature, `ordering::check_orderings` runs a
+//! `Scenario` once per legal order of the physics `System`s, sampling evenly
+//! when there are too many orders, and fails if any order changes the
+//! simulation, which uncovers dependencies between `System`s missing from
+//! `register_physics_systems`.
+//!
+//! ```toml
+//! [dev-dependencies]
+//! specs-physics = { version = "0.3", features = ["ordering-checks"] }
+//! ```
+//!
+//! ### Scene dumps
+//!
+//! `scenes::capture` takes a snapshot of the physics `Entity`s of a `World`
+//! and `scenes::dump` writes it as plain text, which `scenes::parse` reads
+//! back as a `Scenario`. The `inspector` example, enabled by the
+//! "inspector-bin" feature, loads such a dump, steps it headless and prints a
+//! summary of every step, which helps bisecting behaviour changes of
+//! nphysics without running the whole game:
+//!
+//! ```text
+//! cargo run --example inspector --features inspector-bin -- scene.txt 120
+//! ```
+//!
+//! ### Logging
+//!
+//! All `System`s log through the [log][] crate. The verbosity can be tuned per
+//! `System` by inserting a `specs_physics::diagnostics::PhysicsLogConfig`
+//! `Resource`, which also allows replacing log lines with structured
+//! `PhysicsDiagnostic` events written to the `PhysicsDiagnostics` channel.
+//!
+//! ```rust
+//! use specs::{World, WorldExt};
+//! use specs_physics::diagnostics::PhysicsLogConfig;
+//!
+//! let mut world = World::new();
+//! world.insert(PhysicsLogConfig {
+//!     bodies: log::LevelFilter::Warn,
+//!     colliders: log::LevelFilter::Warn,
+//!     ..PhysicsLogConfig::default()
+//! });
+//! ```
+//!
 //! [Specs]: https://slide-rs.github.io/specs/
 //! [nphysics]: https://www.nphysics.org/
 //! [nalgebra]: https://nalgebra.org/
@@ -235,71 +764,159 @@
 //! [Amethyst]: https://amethyst.rs/
 //! [Entity hierarchy]: https://github.com/bamling/specs-physics/blob/master/examples/hierarchy.rs
 //! [specs-hierarchy]: https://github.com/rustgd/specs-hierarchy
+//! [log]: https://docs.rs/log
+//! [metrics]: https://docs.rs/metrics
 
 #[macro_use]
 extern crate log;
+#[cfg(feature = "metrics")]
+#[macro_use]
+extern crate metrics_facade;
 
 pub use nalgebra;
 pub use ncollide3d as ncollide;
 pub use nphysics3d as nphysics;
 pub use shrev;
 
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::Deref};
 
 use specs::{
-    world::Index,
+    storage::MaskedStorage,
     Component,
     DenseVecStorage,
     Dispatcher,
     DispatcherBuilder,
     Entity,
     FlaggedStorage,
+    Storage,
+    System,
+    WriteStorage,
 };
 use specs_hierarchy::Parent;
 
 pub use self::{
     bodies::{util::SimplePosition, PhysicsBody, PhysicsBodyBuilder},
     colliders::{PhysicsCollider, PhysicsColliderBuilder},
+    handles::PhysicsHandles,
 };
 
+/// The inner `Storage` of the `PhysicsBody` and `PhysicsCollider`
+/// `Component`s. Scenes with physics on nearly every `Entity` perform better
+/// with `VecStorage`, which is used with the "vec-storage" feature enabled.
+#[cfg(not(feature = "vec-storage"))]
+pub type PhysicsStorage<T> = DenseVecStorage<T>;
+/// The inner `Storage` of the `PhysicsBody` and `PhysicsCollider`
+/// `Component`s. Scenes with physics on nearly every `Entity` perform better
+/// with `VecStorage`, which is used with the "vec-storage" feature enabled.
+#[cfg(feature = "vec-storage")]
+pub type PhysicsStorage<T> = specs::VecStorage<T>;
+
 use self::{
     bodies::Position,
-    nalgebra::{RealField, Vector3},
+    colliders::{QueryGroups, Shape},
+    handles::entity_from_user_data,
+    nalgebra::{
+        self as na,
+        DMatrix,
+        Isometry3,
+        Matrix4,
+        Point2,
+        Point3,
+        RealField,
+        Vector2,
+        Vector3,
+        Vector4,
+    },
+    ncollide::{
+        query::Ray,
+        shape::{HeightField, ShapeHandle},
+        world::CollisionGroups,
+    },
     nphysics::{
+        algebra::Force3,
         counters::Counters,
         material::MaterialsCoefficientsTable,
         object::{BodyHandle, ColliderHandle},
         solver::IntegrationParameters,
         world::World,
     },
+    queries::QueryHit,
     systems::{
+        ApplyPhysicsCommandsSystem,
+        ApplyPhysicsConfigSystem,
+        ApplySimulationRatesSystem,
         PhysicsStepperSystem,
+        RestoreSimulationRatesSystem,
+        SyncAttachmentsToPhysicsSystem,
         SyncBodiesFromPhysicsSystem,
         SyncBodiesToPhysicsSystem,
         SyncCollidersToPhysicsSystem,
+        SyncElevatorsToPhysicsSystem,
+        SyncHingedDoorsToPhysicsSystem,
+        SyncJointsToPhysicsSystem,
         SyncParametersToPhysicsSystem,
+        SyncPhase,
+        SyncWheelJointsToPhysicsSystem,
     },
+    terrain::HeightFieldError,
 };
 
+#[cfg(feature = "baking")]
+pub mod baking;
 pub mod bodies;
+pub mod characters;
 pub mod colliders;
+pub mod commands;
+pub mod debug;
+pub mod diagnostics;
+#[cfg(feature = "dim2")]
+pub mod dim2;
 pub mod events;
+pub mod forces;
+pub mod fracture;
+#[cfg(test)]
+mod fuzzing;
+pub mod golden;
+pub mod handles;
+pub mod impacts;
+pub mod inspect;
+pub mod joints;
+pub mod loading;
+#[cfg(feature = "maps")]
+pub mod maps;
+pub mod motion;
+#[cfg(feature = "ordering-checks")]
+pub mod ordering;
 pub mod parameters;
+pub mod queries;
+pub mod recording;
+pub mod scenarios;
+pub mod scenes;
+pub mod sensors;
+pub mod sprites;
+pub mod subscriptions;
 pub mod systems;
+pub mod terrain;
+pub mod testing;
+pub mod validation;
 
 /// Resource holding the internal fields where physics computation occurs.
-/// Some inspection methods are exposed to allow debugging.
+/// Some inspection methods are exposed to allow debugging. The handles of the
+/// objects owned by `Entity`s are stored in the separate `PhysicsHandles`
+/// `Resource`.
 pub struct Physics<N: RealField> {
     /// Core structure where physics computation and synchronization occurs.
     /// Also contains ColliderWorld.
     pub(crate) world: World<N>,
 
-    /// Hashmap of Entities to internal Physics bodies.
-    /// Necessary for reacting to removed Components.
-    pub(crate) body_handles: HashMap<Index, BodyHandle>,
-    /// Hashmap of Entities to internal Collider handles.
-    /// Necessary for reacting to removed Components.
-    pub(crate) collider_handles: HashMap<Index, ColliderHandle>,
+    /// The external forces applied to bodies during the current frame. They
+    /// are applied before every substep of the `PhysicsStepperSystem`, as
+    /// nphysics clears the forces of its bodies after each step, and dropped
+    /// once the frame was simulated.
+    pub(crate) frame_forces: HashMap<BodyHandle, Force3<N>>,
+
+    /// The number of steps simulated so far.
+    pub(crate) tick: u64,
 }
 
 // Some non-mutating methods for diagnostics and testing
@@ -315,6 +932,13 @@ impl<N: RealField> Physics<N> {
         self.world.timestep()
     }
 
+    /// Reports the number of fixed steps simulated so far, which is also the
+    /// tick index of the last step. Physics events are stamped with the tick
+    /// of the step they occurred in.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
     /// Reports the internal value for the gravity.
     /// See also `Gravity` for setting this value.
     pub fn gravity(&self) -> &Vector3<N> {
@@ -340,19 +964,378 @@ impl<N: RealField> Physics<N> {
         self.world.integration_parameters()
     }
 
+    /// Retrieves the `Entity` owning the given internal Collider.
+    pub fn collider_entity(&self, handle: ColliderHandle) -> Option<Entity> {
+        entity_from_user_data(self.world.collider(handle)?.user_data())
+    }
+
+    /// Retrieves the `Entity` owning the given internal RigidBody.
+    pub fn body_entity(&self, handle: BodyHandle) -> Option<Entity> {
+        entity_from_user_data(self.world.rigid_body(handle)?.user_data())
+    }
+
+    /// Casts a ray from `origin` along `dir` and returns every collider hit
+    /// within `max_toi` whose `QueryGroups` intersect the given
+    /// `QueryGroups`, sorted by distance, e.g. for piercing projectiles. The
+    /// `toi` of the hits is measured in multiples of `dir`.
+    pub fn ray_cast_all<D>(
+        &self,
+        physics_colliders: &Storage<PhysicsCollider<N>, D>,
+        origin: Point3<N>,
+        dir: Vector3<N>,
+        max_toi: N,
+        query_groups: QueryGroups,
+    ) -> Vec<QueryHit<N>>
+    where
+        D: Deref<Target = MaskedStorage<PhysicsCollider<N>>>,
+    {
+        let ray = Ray::new(origin, dir);
+        queries::cast_ray_all(self, physics_colliders, &ray, max_toi, query_groups)
+    }
+
+    /// Casts many rays at once, e.g. for the vision cones of AI agents or
+    /// lidar-like sensors, and returns the nearest hit within `max_toi` of
+    /// every ray, in the order of the rays. Like `ray_cast_all`, only
+    /// colliders whose `QueryGroups` intersect the given `QueryGroups` are
+    /// hit. With the "parallel" feature enabled the rays are cast on the rayon
+    /// thread pool.
+    pub fn ray_cast_batch<D>(
+        &self,
+        physics_colliders: &Storage<PhysicsCollider<N>, D>,
+        rays: &[Ray<N>],
+        max_toi: N,
+        query_groups: QueryGroups,
+    ) -> Vec<Option<QueryHit<N>>>
+    where
+        D: Deref<Target = MaskedStorage<PhysicsCollider<N>>> + Sync,
+    {
+        queries::batch_ray_hits(self, physics_colliders, rays, max_toi, query_groups)
+    }
+
+    /// Picks the nearest collider under the given screen position, e.g. the
+    /// mouse cursor, and returns its `Entity` together with the picked point
+    /// in world space. `screen_pos` is measured in pixels from the top left
+    /// corner of a viewport of the given size; `view` and `proj` are the view
+    /// and projection matrices of the camera, with the clip space depth
+    /// ranging from -1 to 1 like the `nalgebra` projections. Only colliders
+    /// whose `QueryGroups` intersect the given `QueryGroups` can be picked,
+    /// e.g. to skip triggers or the editor gizmos. Returns `None` if nothing
+    /// is under the cursor or the matrices are not invertible.
+    pub fn pick<D>(
+        &self,
+        physics_colliders: &Storage<PhysicsCollider<N>, D>,
+        screen_pos: Point2<N>,
+        view: &Matrix4<N>,
+        proj: &Matrix4<N>,
+        viewport: Vector2<N>,
+        query_groups: QueryGroups,
+    ) -> Option<(Entity, Point3<N>)>
+    where
+        D: Deref<Target = MaskedStorage<PhysicsCollider<N>>>,
+    {
+        let inverse = (proj * view).try_inverse()?;
+
+        // the screen y axis points down, the normalized device y axis up
+        let two: N = na::convert(2.0);
+        let x = two * screen_pos.x / viewport.x - N::one();
+        let y = N::one() - two * screen_pos.y / viewport.y;
+        let near = Point3::from_homogeneous(inverse * Vector4::new(x, y, -N::one(), N::one()))?;
+        let far = Point3::from_homogeneous(inverse * Vector4::new(x, y, N::one(), N::one()))?;
+
+        // the ray spans from the near to the far plane within a toi of 1
+        let ray = Ray::new(near, far - near);
+        queries::cast_ray(self, physics_colliders, &ray, N::one(), query_groups)
+            .map(|hit| (hit.collider_entity, hit.point))
+    }
+
+    /// Tests the given `Shape` at the given pose against all colliders
+    /// interacting with the given `CollisionGroups` and returns the `Entity`s
+    /// of the overlapped ones. This allows instantaneous hitbox tests, e.g. for
+    /// sword arcs or cone attacks, without creating temporary sensors.
+    pub fn overlaps(
+        &self,
+        shape: &Shape<N>,
+        isometry: &Isometry3<N>,
+        collision_groups: &CollisionGroups,
+    ) -> Vec<Entity> {
+        queries::overlapping(self, &shape.handle(), isometry, collision_groups).collect()
+    }
+
+    /// Returns the `Entity`s of the `k` dynamic bodies nearest to the `point`
+    /// within `radius`, together with their distances, nearest first, e.g. for
+    /// the neighbors of flocking or crowd agents. The candidates are gathered
+    /// from the broad phase, so only bodies with a collider interacting with
+    /// the given `CollisionGroups` are found. An agent querying around its own
+    /// position finds itself first, so it should ask for one neighbor more.
+    pub fn nearest_bodies(
+        &self,
+        point: &Point3<N>,
+        k: usize,
+        radius: N,
+        collision_groups: &CollisionGroups,
+    ) -> Vec<(Entity, N)> {
+        queries::nearest_bodies(self, point, k, radius, collision_groups)
+    }
+
+    /// Retrieves the position of the body of the given `Entity` in the
+    /// physics `World`, which may differ from its `Position` until the
+    /// `SyncBodiesFromPhysicsSystem` ran.
+    pub fn body_position(&self, handles: &PhysicsHandles, entity: Entity) -> Option<Isometry3<N>> {
+        let rigid_body = self.world.rigid_body(handles.body_handle(entity)?)?;
+        Some(*rigid_body.position())
+    }
+
+    /// Checks whether the segment between the bodies of two `Entity`s is
+    /// unobstructed by colliders whose `QueryGroups` intersect the
+    /// `blocking_groups`, e.g. whether an AI agent can see its target. What
+    /// blocks the sight is thereby configured independently of what collides,
+    /// e.g. glass panes can stop bodies without blocking the sight. The
+    /// colliders of both `Entity`s do not block the segment. `Entity`s without
+    /// a body or collider in the physics `World` are never in sight.
+    pub fn line_of_sight<D>(
+        &self,
+        handles: &PhysicsHandles,
+        physics_colliders: &Storage<PhysicsCollider<N>, D>,
+        a: Entity,
+        b: Entity,
+        blocking_groups: QueryGroups,
+    ) -> bool
+    where
+        D: Deref<Target = MaskedStorage<PhysicsCollider<N>>>,
+    {
+        match (
+            self.entity_position(handles, a),
+            self.entity_position(handles, b),
+        ) {
+            (Some(start), Some(end)) => {
+                !self.segment_blocked(&start, &end, a, b, physics_colliders, blocking_groups)
+            }
+            _ => false,
+        }
+    }
+
+    /// Estimates how much sound from the `emitter` is occluded on its way to
+    /// the `listener`, as a factor from 0, unobstructed, to 1, fully
+    /// occluded, e.g. to drive the low-pass filter of audio middleware. A
+    /// bundle of parallel rays is cast between both `Entity`s, one along the
+    /// direct path and the others offset by `radius` around it, and the
+    /// factor is the share of rays blocked by colliders whose `QueryGroups`
+    /// intersect the `blocking_groups`, e.g. to let sound pass through
+    /// foliage that blocks the sight. The colliders of both `Entity`s block no
+    /// rays. Returns `None` if either `Entity` has no body or collider in the
+    /// physics `World`.
+    pub fn audio_occlusion<D>(
+        &self,
+        handles: &PhysicsHandles,
+        physics_colliders: &Storage<PhysicsCollider<N>, D>,
+        listener: Entity,
+        emitter: Entity,
+        blocking_groups: QueryGroups,
+        radius: N,
+    ) -> Option<N>
+    where
+        D: Deref<Target = MaskedStorage<PhysicsCollider<N>>>,
+    {
+        let start = self.entity_position(handles, listener)?;
+        let end = self.entity_position(handles, emitter)?;
+
+        // two axes perpendicular to the direct path
+        let direction = (end - start)
+            .try_normalize(N::default_epsilon())
+            .unwrap_or_else(Vector3::x);
+        let up = if direction.y.abs() < na::convert(0.9) {
+            Vector3::y()
+        } else {
+            Vector3::x()
+        };
+        let side = direction.cross(&up).normalize();
+        let up = side.cross(&direction);
+
+        let offsets = [
+            Vector3::zeros(),
+            side * radius,
+            -side * radius,
+            up * radius,
+            -up * radius,
+        ];
+        let blocked = offsets
+            .iter()
+            .filter(|&&offset| {
+                self.segment_blocked(
+                    &(start + offset),
+                    &(end + offset),
+                    listener,
+                    emitter,
+                    physics_colliders,
+                    blocking_groups,
+                )
+            })
+            .count();
+        Some(na::convert::<f64, N>(blocked as f64) / na::convert(offsets.len() as f64))
+    }
+
+    /// Retrieves the position of the body of the given `Entity`, or of its
+    /// first collider if it has no body.
+    fn entity_position(&self, handles: &PhysicsHandles, entity: Entity) -> Option<Point3<N>> {
+        let isometry = self.body_position(handles, entity).or_else(|| {
+            let handle = handles.collider_handles(entity).first()?;
+            self.world
+                .collider(*handle)
+                .map(|collider| *collider.position())
+        })?;
+        Some(Point3::from(isometry.translation.vector))
+    }
+
+    /// Checks whether a collider in the `blocking_groups`, other than those of
+    /// the `Entity`s `a` and `b`, intersects the segment.
+    fn segment_blocked<D>(
+        &self,
+        start: &Point3<N>,
+        end: &Point3<N>,
+        a: Entity,
+        b: Entity,
+        physics_colliders: &Storage<PhysicsCollider<N>, D>,
+        blocking_groups: QueryGroups,
+    ) -> bool
+    where
+        D: Deref<Target = MaskedStorage<PhysicsCollider<N>>>,
+    {
+        let ray = Ray::new(*start, end - start);
+        // the QueryGroups decide what blocks, see queries::cast_ray_filtered
+        let collision_groups = CollisionGroups::default();
+        queries::ray_hits(self, &ray, &collision_groups, N::one(), |entity| {
+            entity != a
+                && entity != b
+                && queries::in_query_groups(physics_colliders, entity, blocking_groups)
+        })
+        .any(|hit| hit.entity != a && hit.entity != b)
+    }
+
+    /// Checks whether any collider of the first `Entity` touches any collider
+    /// of the second one, according to the contacts of the last step.
+    pub fn in_contact(&self, handles: &PhysicsHandles, entity1: Entity, entity2: Entity) -> bool {
+        let collision_world = self.world.collider_world().as_collision_world();
+        handles.collider_handles(entity1).iter().any(|handle1| {
+            handles.collider_handles(entity2).iter().any(|handle2| {
+                collision_world
+                    .contact_pair(*handle1, *handle2, true)
+                    .is_some()
+            })
+        })
+    }
+
     /// Retrieves the internal lookup table for friction and restitution
     /// constants. Exposing this for modification is TODO.
     pub fn materials_coefficients_table(&self) -> &MaterialsCoefficientsTable<N> {
         self.world.materials_coefficients_table()
     }
+
+    /// Computes a checksum of the positions and velocities of all bodies. The
+    /// checksum only depends on the exact simulation state, so lockstep
+    /// multiplayer games can compare it between peers after every step to
+    /// detect diverging simulations.
+    pub fn state_checksum(&self, handles: &PhysicsHandles) -> u64 {
+        // bodies are hashed in the order of their Entities, as the order of the
+        // handle map differs between runs
+        let mut ids = handles.body_handles.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+
+        let mut checksum = FNV_OFFSET_BASIS;
+        for id in ids {
+            checksum = fnv1a(checksum, u64::from(id));
+            if let Some(rigid_body) = self.world.rigid_body(handles.body_handles[&id]) {
+                let position = rigid_body.position();
+                let velocity = rigid_body.velocity();
+                for value in position
+                    .translation
+                    .vector
+                    .iter()
+                    .chain(position.rotation.coords.iter())
+                    .chain(velocity.linear.iter())
+                    .chain(velocity.angular.iter())
+                {
+                    let bits = value.to_subset().map_or(0, f64::to_bits);
+                    checksum = fnv1a(checksum, bits);
+                }
+            }
+        }
+
+        checksum
+    }
+}
+
+// Forces applied by the synchronisation Systems
+impl<N: RealField> Physics<N> {
+    /// Adds an external force to the body of the given handle for all
+    /// substeps of the current frame.
+    pub(crate) fn apply_frame_force(&mut self, handle: BodyHandle, force: &Force3<N>) {
+        *self.frame_forces.entry(handle).or_insert_with(Force3::zero) += *force;
+    }
+}
+
+// Runtime modifications of the simulated shapes
+impl<N: RealField> Physics<N> {
+    /// Overwrites the heights of the `HeightField` collider of the given
+    /// `Entity`, starting at the row and column of the `region`, with the
+    /// given `heights`, e.g. to blow craters into terrain or to dig. Only the
+    /// bounding volume of the modified collider is updated in the broad phase.
+    /// The `Shape` of the `PhysicsCollider` is changed along without flagging
+    /// the `Component`, so the collider is not rebuilt from scratch.
+    pub fn modify_heightfield(
+        &mut self,
+        physics_colliders: &mut WriteStorage<PhysicsCollider<N>>,
+        entity: Entity,
+        region: (usize, usize),
+        heights: &DMatrix<N>,
+    ) -> Result<(), HeightFieldError> {
+        physics_colliders.set_event_emission(false);
+        let result = match physics_colliders.get_mut(entity) {
+            Some(physics_collider) => {
+                let handle = physics_collider.handle;
+                match &mut physics_collider.shape {
+                    Shape::HeightField {
+                        heights: target,
+                        scale,
+                    } => terrain::write_heights(target, region, heights)
+                        .map(|()| (handle, target.clone(), *scale)),
+                    _ => Err(HeightFieldError::NoHeightField(entity)),
+                }
+            }
+            None => Err(HeightFieldError::NoHeightField(entity)),
+        };
+        physics_colliders.set_event_emission(true);
+        let (handle, heights, scale) = result?;
+
+        // colliders which have not been inserted yet are built from the
+        // modified Shape
+        if let Some(handle) = handle {
+            self.world
+                .collider_world_mut()
+                .as_collision_world_mut()
+                .set_shape(handle, ShapeHandle::new(HeightField::new(heights, scale)));
+        }
+        Ok(())
+    }
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Feeds the bytes of `value` into the FNV-1a `hash`; unlike the hashers of
+/// the standard library, FNV-1a is guaranteed to be stable across platforms
+/// and Rust versions.
+fn fnv1a(hash: u64, value: u64) -> u64 {
+    value.to_le_bytes().iter().fold(hash, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    })
 }
 
 impl<N: RealField> Default for Physics<N> {
     fn default() -> Self {
         Self {
             world: World::new(),
-            body_handles: HashMap::new(),
-            collider_handles: HashMap::new(),
+            frame_forces: HashMap::new(),
+            tick: 0,
         }
     }
 }
@@ -393,58 +1376,495 @@ where
     dispatcher_builder.build()
 }
 
+/// The names of the physics `System`s registered by `register_physics_systems`.
+/// Downstream `System`s can depend on them to run relative to the physics
+/// stages, e.g. game logic reading the simulated `Position`s should depend on
+/// `PhysicsStages::WRITE_BACK`.
+///
+/// The `System`s depend on each other as follows; `SYNC_BODIES` and
+/// `SYNC_COLLIDERS` name the last `System` of their stage:
+///
+/// ```text
+/// APPLY_CONFIG ──────────────────────────┬──> SYNC_PARAMETERS ───────────────┐
+/// INSERT_BODIES ──> UPDATE_BODIES ──> REMOVE_BODIES (SYNC_BODIES)            │
+///      │                                  └──> APPLY_COMMANDS ──> joints ────┤
+///      └──> INSERT_COLLIDERS ──> UPDATE_COLLIDERS ──> REMOVE_COLLIDERS ──────┤
+///                                           (SYNC_COLLIDERS)                 │
+///                                                   APPLY_RATES <────────────┘
+///                                                     └──> STEP
+///                                                           └──> RESTORE_RATES
+///                                                                 └──> WRITE_BACK
+/// ```
+///
+/// `INSERT_COLLIDERS` also depends on `APPLY_CONFIG`, the joints are
+/// `JOINTS`, `WHEEL_JOINTS`, `ELEVATORS`, `HINGED_DOORS` and `ATTACHMENTS`.
+///
+/// # Examples
+/// ```
+/// use specs::{DispatcherBuilder, System};
+/// use specs_physics::{PhysicsStages, SimplePosition};
+///
+/// struct GameLogicSystem;
+///
+/// impl<'s> System<'s> for GameLogicSystem {
+///     type SystemData = ();
+///
+///     fn run(&mut self, _: Self::SystemData) {}
+/// }
+///
+/// let mut dispatcher_builder = DispatcherBuilder::new();
+/// specs_physics::register_physics_systems::<f32, SimplePosition<f32>>(&mut dispatcher_builder);
+/// dispatcher_builder.add(GameLogicSystem, "game_logic_system", &[PhysicsStages::WRITE_BACK]);
+/// ```
+pub struct PhysicsStages;
+
+impl PhysicsStages {
+    /// The `ApplyPhysicsCommandsSystem`.
+    pub const APPLY_COMMANDS: &'static str = "apply_physics_commands_system";
+    /// The `ApplyPhysicsConfigSystem`.
+    pub const APPLY_CONFIG: &'static str = "apply_physics_config_system";
+    /// The `ApplySimulationRatesSystem`.
+    pub const APPLY_RATES: &'static str = "apply_simulation_rates_system";
+    /// The `SyncAttachmentsToPhysicsSystem`.
+    pub const ATTACHMENTS: &'static str = "sync_attachments_to_physics_system";
+    /// The `SyncElevatorsToPhysicsSystem`.
+    pub const ELEVATORS: &'static str = "sync_elevators_to_physics_system";
+    /// The `SyncHingedDoorsToPhysicsSystem`.
+    pub const HINGED_DOORS: &'static str = "sync_hinged_doors_to_physics_system";
+    /// The `SyncPhase::Insert` of the `SyncBodiesToPhysicsSystem`.
+    pub const INSERT_BODIES: &'static str = "insert_bodies_to_physics_system";
+    /// The `SyncPhase::Insert` of the `SyncCollidersToPhysicsSystem`.
+    pub const INSERT_COLLIDERS: &'static str = "insert_colliders_to_physics_system";
+    /// The `SyncJointsToPhysicsSystem`.
+    pub const JOINTS: &'static str = "sync_joints_to_physics_system";
+    /// The `SyncPhase::Remove` of the `SyncBodiesToPhysicsSystem`.
+    pub const REMOVE_BODIES: &'static str = "remove_bodies_from_physics_system";
+    /// The `SyncPhase::Remove` of the `SyncCollidersToPhysicsSystem`.
+    pub const REMOVE_COLLIDERS: &'static str = "remove_colliders_from_physics_system";
+    /// The `RestoreSimulationRatesSystem`.
+    pub const RESTORE_RATES: &'static str = "restore_simulation_rates_system";
+    /// The `PhysicsStepperSystem`.
+    pub const STEP: &'static str = "physics_stepper_system";
+    /// All bodies have been synchronised to the physics `World`.
+    pub const SYNC_BODIES: &'static str = Self::REMOVE_BODIES;
+    /// All colliders have been synchronised to the physics `World`.
+    pub const SYNC_COLLIDERS: &'static str = Self::REMOVE_COLLIDERS;
+    /// The `SyncParametersToPhysicsSystem`.
+    pub const SYNC_PARAMETERS: &'static str = "sync_parameters_to_physics_system";
+    /// The `SyncPhase::Update` of the `SyncBodiesToPhysicsSystem`.
+    pub const UPDATE_BODIES: &'static str = "update_bodies_to_physics_system";
+    /// The `SyncPhase::Update` of the `SyncCollidersToPhysicsSystem`.
+    pub const UPDATE_COLLIDERS: &'static str = "update_colliders_to_physics_system";
+    /// The `SyncWheelJointsToPhysicsSystem`.
+    pub const WHEEL_JOINTS: &'static str = "sync_wheel_joints_to_physics_system";
+    /// The `SyncBodiesFromPhysicsSystem`.
+    pub const WRITE_BACK: &'static str = "sync_bodies_from_physics_system";
+}
+
 /// Convenience function for registering all required physics related `System`s
 /// to the given `DispatcherBuilder`. This also serves as a blueprint on how
-///// to properly set up the `System`s and have them depend on each other.
+/// to properly set up the `System`s and have them depend on each other; see
+/// `PhysicsStages` for the resulting dependency graph.
 pub fn register_physics_systems<N, P>(dispatcher_builder: &mut DispatcherBuilder)
 where
     N: RealField,
     P: Position<N>,
 {
-    // add SyncBodiesToPhysicsSystem first since we have to start with bodies;
-    // colliders can exist without a body but in most cases have a body parent
-    dispatcher_builder.add(
-        SyncBodiesToPhysicsSystem::<N, P>::default(),
-        "sync_bodies_to_physics_system",
-        &[],
+    register_physics_systems_after::<N, P>(dispatcher_builder, &[]);
+}
+
+/// Registers all required physics related `System`s like
+/// `register_physics_systems`, but makes the physics `System`s wait for the
+/// given, already registered `System`s, e.g. the ones applying forces to
+/// `PhysicsBody`s.
+///
+/// # Examples
+/// ```
+/// use specs::DispatcherBuilder;
+/// use specs_physics::{systems::ApplyPdControllersSystem, SimplePosition};
+///
+/// let mut dispatcher_builder = DispatcherBuilder::new().with(
+///     ApplyPdControllersSystem::<f32, SimplePosition<f32>>::default(),
+///     "apply_pd_controllers_system",
+///     &[],
+/// );
+/// specs_physics::register_physics_systems_after::<f32, SimplePosition<f32>>(
+///     &mut dispatcher_builder,
+///     &["apply_pd_controllers_system"],
+/// );
+/// ```
+pub fn register_physics_systems_after<N, P>(
+    dispatcher_builder: &mut DispatcherBuilder,
+    dependencies: &[&str],
+) where
+    N: RealField,
+    P: Position<N>,
+{
+    add_physics_systems::<N, P, _>(dispatcher_builder, dependencies);
+}
+
+/// Receives the physics `System`s from `add_physics_systems`, so the same
+/// registration feeds both the `DispatcherBuilder` and the ordering checks.
+pub(crate) trait SystemRegistry {
+    fn add<S>(&mut self, system: S, name: &str, dependencies: &[&str])
+    where
+        S: for<'c> System<'c> + Send + 'static;
+}
+
+impl<'a, 'b> SystemRegistry for DispatcherBuilder<'a, 'b> {
+    fn add<S>(&mut self, system: S, name: &str, dependencies: &[&str])
+    where
+        S: for<'c> System<'c> + Send + 'static,
+    {
+        DispatcherBuilder::add(self, system, name, dependencies);
+    }
+}
+
+/// Adds the physics `System`s with their dependencies to the `SystemRegistry`.
+pub(crate) fn add_physics_systems<N, P, R>(registry: &mut R, dependencies: &[&str])
+where
+    N: RealField,
+    P: Position<N>,
+    R: SystemRegistry,
+{
+    // add ApplyPhysicsConfigSystem first as it writes the simulation parameters
+    // and collision groups synchronised by the following Systems
+    registry.add(
+        ApplyPhysicsConfigSystem::<N>::default(),
+        PhysicsStages::APPLY_CONFIG,
+        dependencies,
+    );
+
+    // add the phases of the SyncBodiesToPhysicsSystem next since we have to start
+    // with bodies; colliders can exist without a body but in most cases have a
+    // body parent. Each phase is a separate System, which allows users to run
+    // their own Systems in between.
+    registry.add(
+        SyncBodiesToPhysicsSystem::<N, P>::with_phase(SyncPhase::Insert),
+        PhysicsStages::INSERT_BODIES,
+        dependencies,
+    );
+    registry.add(
+        SyncBodiesToPhysicsSystem::<N, P>::with_phase(SyncPhase::Update),
+        PhysicsStages::UPDATE_BODIES,
+        &[PhysicsStages::INSERT_BODIES],
+    );
+    registry.add(
+        SyncBodiesToPhysicsSystem::<N, P>::with_phase(SyncPhase::Remove),
+        PhysicsStages::REMOVE_BODIES,
+        &[PhysicsStages::UPDATE_BODIES],
+    );
+
+    // add the phases of the SyncCollidersToPhysicsSystem next; inserting
+    // colliders depends on their parent bodies being inserted
+    registry.add(
+        SyncCollidersToPhysicsSystem::<N, P>::with_phase(SyncPhase::Insert),
+        PhysicsStages::INSERT_COLLIDERS,
+        &[PhysicsStages::APPLY_CONFIG, PhysicsStages::INSERT_BODIES],
+    );
+    registry.add(
+        SyncCollidersToPhysicsSystem::<N, P>::with_phase(SyncPhase::Update),
+        PhysicsStages::UPDATE_COLLIDERS,
+        &[PhysicsStages::INSERT_COLLIDERS],
+    );
+    registry.add(
+        SyncCollidersToPhysicsSystem::<N, P>::with_phase(SyncPhase::Remove),
+        PhysicsStages::REMOVE_COLLIDERS,
+        &[PhysicsStages::UPDATE_COLLIDERS],
     );
 
-    // add SyncCollidersToPhysicsSystem next with SyncBodiesToPhysicsSystem as its
-    // dependency
-    dispatcher_builder.add(
-        SyncCollidersToPhysicsSystem::<N, P>::default(),
-        "sync_colliders_to_physics_system",
-        &["sync_bodies_to_physics_system"],
+    // add ApplyPhysicsCommandsSystem after all body phases, as the queued commands
+    // target existing bodies
+    registry.add(
+        ApplyPhysicsCommandsSystem::<N>::default(),
+        PhysicsStages::APPLY_COMMANDS,
+        &[PhysicsStages::REMOVE_BODIES],
+    );
+
+    // add the joint Systems after all body phases and commands as joint
+    // constraints require both of their bodies to exist
+    registry.add(
+        SyncJointsToPhysicsSystem::<N>::default(),
+        PhysicsStages::JOINTS,
+        &[PhysicsStages::APPLY_COMMANDS],
+    );
+    registry.add(
+        SyncWheelJointsToPhysicsSystem::<N>::default(),
+        PhysicsStages::WHEEL_JOINTS,
+        &[PhysicsStages::APPLY_COMMANDS],
+    );
+    registry.add(
+        SyncElevatorsToPhysicsSystem::<N>::default(),
+        PhysicsStages::ELEVATORS,
+        &[PhysicsStages::APPLY_COMMANDS],
+    );
+    registry.add(
+        SyncHingedDoorsToPhysicsSystem::<N>::default(),
+        PhysicsStages::HINGED_DOORS,
+        &[PhysicsStages::APPLY_COMMANDS],
+    );
+    registry.add(
+        SyncAttachmentsToPhysicsSystem::<N>::default(),
+        PhysicsStages::ATTACHMENTS,
+        &[PhysicsStages::APPLY_COMMANDS],
     );
 
-    // add SyncParametersToPhysicsSystem; this System can be added at any point in
-    // time as it merely synchronizes the simulation parameters of the world,
-    // thus it has no other dependencies.
-    dispatcher_builder.add(
+    // add SyncParametersToPhysicsSystem; it merely synchronizes the simulation
+    // parameters of the world, thus it only depends on the
+    // ApplyPhysicsConfigSystem writing them
+    registry.add(
         SyncParametersToPhysicsSystem::<N>::default(),
-        "sync_parameters_to_physics_system",
-        &[],
+        PhysicsStages::SYNC_PARAMETERS,
+        &[PhysicsStages::APPLY_CONFIG],
+    );
+
+    // add ApplySimulationRatesSystem after all other Systems that write data to
+    // the nphysics World, as it scales the velocities of reduced rate bodies
+    // right before the step
+    registry.add(
+        ApplySimulationRatesSystem::<N>::default(),
+        PhysicsStages::APPLY_RATES,
+        &[
+            PhysicsStages::REMOVE_BODIES,
+            PhysicsStages::REMOVE_COLLIDERS,
+            PhysicsStages::APPLY_COMMANDS,
+            PhysicsStages::JOINTS,
+            PhysicsStages::WHEEL_JOINTS,
+            PhysicsStages::ELEVATORS,
+            PhysicsStages::HINGED_DOORS,
+            PhysicsStages::ATTACHMENTS,
+            PhysicsStages::SYNC_PARAMETERS,
+        ],
     );
 
     // add PhysicsStepperSystem after all other Systems that write data to the
     // nphysics World and has to depend on them; this System is used to progress the
     // nphysics World for all existing objects
-    dispatcher_builder.add(
+    registry.add(
         PhysicsStepperSystem::<N>::default(),
-        "physics_stepper_system",
-        &[
-            "sync_bodies_to_physics_system",
-            "sync_colliders_to_physics_system",
-            "sync_parameters_to_physics_system",
-        ],
+        PhysicsStages::STEP,
+        &[PhysicsStages::APPLY_RATES],
+    );
+
+    // add RestoreSimulationRatesSystem right after the step, so that the
+    // velocities are scaled back before they are written to the Components
+    registry.add(
+        RestoreSimulationRatesSystem::<N>::default(),
+        PhysicsStages::RESTORE_RATES,
+        &[PhysicsStages::STEP],
     );
 
     // add SyncBodiesFromPhysicsSystem last as it handles the
     // synchronisation between nphysics World bodies and the Position
     // components; this depends on the PhysicsStepperSystem
-    dispatcher_builder.add(
+    registry.add(
         SyncBodiesFromPhysicsSystem::<N, P>::default(),
-        "sync_bodies_from_physics_system",
-        &["physics_stepper_system"],
+        PhysicsStages::WRITE_BACK,
+        &[PhysicsStages::RESTORE_RATES],
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use specs::{prelude::*, world::Index};
+
+    use crate::{
+        handles::PhysicsHandles,
+        nalgebra::{Isometry3, Vector3},
+        nphysics::{algebra::Velocity3, object::RigidBodyDesc},
+        parameters::Gravity,
+        scenarios::Scenario,
+        Physics,
+        PhysicsStages,
+        SimplePosition,
+        SystemRegistry,
+    };
+
+    /// Records the names and dependencies of the registered `System`s.
+    #[derive(Default)]
+    struct RecordingRegistry {
+        systems: Vec<(String, Vec<String>)>,
+    }
+
+    impl SystemRegistry for RecordingRegistry {
+        fn add<S>(&mut self, _: S, name: &str, dependencies: &[&str])
+        where
+            S: for<'c> System<'c> + Send + 'static,
+        {
+            self.systems.push((
+                name.to_owned(),
+                dependencies
+                    .iter()
+                    .map(|dependency| (*dependency).to_owned())
+                    .collect(),
+            ));
+        }
+    }
+
+    fn checksum_after_steps(seed: u64, steps: usize) -> u64 {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+        world.insert(Gravity(Vector3::<f32>::new(0.0, -9.81, 0.0)));
+
+        Scenario::<f32>::rain(20, seed).spawn(&mut world, SimplePosition);
+        for _ in 0..steps {
+            dispatcher.dispatch(&world);
+            world.maintain();
+        }
+
+        let physics = world.read_resource::<Physics<f32>>();
+        physics.state_checksum(&world.read_resource::<PhysicsHandles>())
+    }
+
+    // adds a body moving along the x axis for each id and linear velocity, in
+    // the given order
+    fn checksum_of_bodies(bodies: &[(Index, f32)]) -> u64 {
+        let mut physics = Physics::<f32>::default();
+        let mut handles = PhysicsHandles::default();
+        for &(id, velocity) in bodies {
+            let handle = RigidBodyDesc::new()
+                .position(Isometry3::translation(id as f32, 1.0, 0.0))
+                .velocity(Velocity3::linear(velocity, 0.0, 0.0))
+                .build(&mut physics.world)
+                .handle();
+            handles.body_handles.insert(id, handle);
+        }
+
+        physics.state_checksum(&handles)
+    }
+
+    #[test]
+    fn register_every_stage() {
+        let mut registry = RecordingRegistry::default();
+        crate::add_physics_systems::<f32, SimplePosition<f32>, _>(&mut registry, &["forces"]);
+
+        let stages = [
+            PhysicsStages::APPLY_COMMANDS,
+            PhysicsStages::APPLY_CONFIG,
+            PhysicsStages::APPLY_RATES,
+            PhysicsStages::ATTACHMENTS,
+            PhysicsStages::ELEVATORS,
+            PhysicsStages::HINGED_DOORS,
+            PhysicsStages::INSERT_BODIES,
+            PhysicsStages::INSERT_COLLIDERS,
+            PhysicsStages::JOINTS,
+            PhysicsStages::REMOVE_BODIES,
+            PhysicsStages::REMOVE_COLLIDERS,
+            PhysicsStages::RESTORE_RATES,
+            PhysicsStages::STEP,
+            PhysicsStages::SYNC_BODIES,
+            PhysicsStages::SYNC_COLLIDERS,
+            PhysicsStages::SYNC_PARAMETERS,
+            PhysicsStages::UPDATE_BODIES,
+            PhysicsStages::UPDATE_COLLIDERS,
+            PhysicsStages::WHEEL_JOINTS,
+            PhysicsStages::WRITE_BACK,
+        ];
+        for stage in stages.iter() {
+            assert!(
+                registry.systems.iter().any(|(name, _)| name == stage),
+                "{} is not registered",
+                stage
+            );
+        }
+
+        // every System is registered once and only depends on Systems registered
+        // before it or the given dependencies
+        for (i, (name, dependencies)) in registry.systems.iter().enumerate() {
+            let earlier = &registry.systems[..i];
+            assert!(earlier.iter().all(|(other, _)| other != name));
+            for dependency in dependencies {
+                assert!(
+                    dependency == "forces" || earlier.iter().any(|(other, _)| other == dependency),
+                    "{} depends on unknown {}",
+                    name,
+                    dependency
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn equal_checksums_of_identical_worlds() {
+        assert_eq!(checksum_after_steps(7, 30), checksum_after_steps(7, 30));
+        assert_ne!(checksum_after_steps(7, 30), checksum_after_steps(8, 30));
+    }
+
+    #[test]
+    fn change_checksum_by_one_ulp() {
+        let perturbed = f32::from_bits(2.0f32.to_bits() + 1);
+        assert_ne!(
+            checksum_of_bodies(&[(0, 1.0), (1, 2.0)]),
+            checksum_of_bodies(&[(0, 1.0), (1, perturbed)])
+        );
+    }
+
+    #[test]
+    fn ignore_insertion_order_in_checksum() {
+        assert_eq!(
+            checksum_of_bodies(&[(0, 1.0), (1, 2.0), (2, 3.0)]),
+            checksum_of_bodies(&[(2, 3.0), (0, 1.0), (1, 2.0)])
+        );
+    }
+
+    #[cfg(feature = "vec-storage")]
+    #[test]
+    fn sync_components_in_vec_storage() {
+        use specs::{FlaggedStorage, VecStorage};
+
+        use crate::{
+            colliders::Shape,
+            nphysics::object::BodyStatus,
+            PhysicsBody,
+            PhysicsBodyBuilder,
+            PhysicsCollider,
+            PhysicsColliderBuilder,
+        };
+
+        fn assert_vec_storage<T>()
+        where
+            T: Component<Storage = FlaggedStorage<T, VecStorage<T>>>,
+        {
+        }
+        assert_vec_storage::<PhysicsBody<f32>>();
+        assert_vec_storage::<PhysicsCollider<f32>>();
+
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+        world.insert(Gravity(Vector3::<f32>::new(0.0, -9.81, 0.0)));
+
+        // a body behind a large gap of Entities without physics Components
+        for _ in 0..1000 {
+            world.create_entity().build();
+        }
+        let body = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(0.0, 5.0, 0.0)))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .build();
+        for _ in 0..10 {
+            dispatcher.dispatch(&world);
+            world.maintain();
+        }
+
+        {
+            let handles = world.read_resource::<PhysicsHandles>();
+            assert!(handles.body_handle(body).is_some());
+            assert_eq!(handles.collider_handles(body).len(), 1);
+
+            let positions = world.read_storage::<SimplePosition<f32>>();
+            assert!(positions.get(body).unwrap().0.translation.vector.y < 5.0);
+        }
+
+        world.delete_entity(body).unwrap();
+        world.maintain();
+        dispatcher.dispatch(&world);
+
+        let handles = world.read_resource::<PhysicsHandles>();
+        assert!(handles.body_handle(body).is_none());
+        assert!(handles.collider_handles(body).is_empty());
+    }
+}
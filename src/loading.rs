@@ -0,0 +1,163 @@
+//! # Loading module
+//! Background construction of expensive collider shapes. Building triangle
+//! meshes, convex hulls and height fields can take long enough to hitch the
+//! main dispatch while a big level is loaded. With a `ColliderLoader`
+//! `Resource` in the `World`, the `SyncCollidersToPhysicsSystem` builds these
+//! shapes on a pool of worker threads instead; the `Entity` is marked with
+//! `ColliderPending` until its collider enters the physics `World`.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+        Mutex,
+        PoisonError,
+    },
+    thread,
+};
+
+use specs::{world::Index, Component, NullStorage};
+
+use crate::{
+    colliders::Shape,
+    nalgebra::RealField,
+    ncollide::shape::ShapeHandle,
+    validation::InvalidInput,
+};
+
+/// The `ColliderPending` `Component` marks `Entity`s whose `PhysicsCollider`
+/// shape is still being built by the `ColliderLoader`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ColliderPending;
+
+impl Component for ColliderPending {
+    type Storage = NullStorage<Self>;
+}
+
+type ShapeJob<N> = (Index, u64, Shape<N>);
+type BuiltShape<N> = (Index, u64, Result<ShapeHandle<N>, InvalidInput>);
+
+/// The `ColliderLoader` `Resource` owns the worker threads building expensive
+/// `Shape`s. The workers exit once the `ColliderLoader` is dropped.
+///
+/// # Example
+///
+/// ```rust
+/// use specs::{World, WorldExt};
+/// use specs_physics::loading::ColliderLoader;
+///
+/// let mut world = World::new();
+/// world.insert(ColliderLoader::<f32>::new(2));
+/// ```
+pub struct ColliderLoader<N: RealField> {
+    jobs: Mutex<Sender<ShapeJob<N>>>,
+    results: Mutex<Receiver<BuiltShape<N>>>,
+    // the latest ticket per Entity; results of outdated tickets are dropped
+    tickets: HashMap<Index, u64>,
+    next_ticket: u64,
+}
+
+impl<N: RealField> ColliderLoader<N> {
+    /// Creates a new `ColliderLoader` with the given number of worker
+    /// threads.
+    pub fn new(threads: usize) -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<ShapeJob<N>>();
+        let (result_sender, results) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        for index in 0..threads.max(1) {
+            let job_receiver = Arc::clone(&job_receiver);
+            let result_sender = result_sender.clone();
+            thread::Builder::new()
+                .name(format!("specs_physics_collider_loader_{}", index))
+                .spawn(move || loop {
+                    let job = job_receiver
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .recv();
+                    let (id, ticket, shape) = match job {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    if result_sender
+                        .send((id, ticket, shape.try_handle()))
+                        .is_err()
+                    {
+                        break;
+                    }
+                })
+                .expect("Failed to spawn collider loader thread");
+        }
+
+        Self {
+            jobs: Mutex::new(jobs),
+            results: Mutex::new(results),
+            tickets: HashMap::new(),
+            next_ticket: 0,
+        }
+    }
+
+    /// Whether the given `Shape` is expensive enough to be built in the
+    /// background.
+    pub fn builds_in_background(shape: &Shape<N>) -> bool {
+        match shape {
+            Shape::ConvexHull { .. }
+            | Shape::HeightField { .. }
+            | Shape::RoundedCuboid { .. }
+            | Shape::RoundedCylinder { .. }
+            | Shape::TriMesh { .. } => true,
+            Shape::Compound { parts } => parts
+                .iter()
+                .any(|(_, part)| Self::builds_in_background(part)),
+            _ => false,
+        }
+    }
+
+    /// The number of `Shape`s which are still being built.
+    pub fn pending(&self) -> usize {
+        self.tickets.len()
+    }
+
+    /// Queues building the `Shape` for the `Entity` with the given index,
+    /// superseding a previously queued `Shape` of the same `Entity`.
+    pub(crate) fn submit(&mut self, id: Index, shape: Shape<N>) {
+        let ticket = self.next_ticket;
+        self.next_ticket += 1;
+        self.tickets.insert(id, ticket);
+
+        // the workers only exit after the ColliderLoader has been dropped
+        let _ = self
+            .jobs
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .send((id, ticket, shape));
+    }
+
+    /// Drops the result of the `Shape` queued for the `Entity` with the given
+    /// index.
+    pub(crate) fn cancel(&mut self, id: Index) -> bool {
+        self.tickets.remove(&id).is_some()
+    }
+
+    /// Returns the `ShapeHandle`s which have been built since the last call,
+    /// skipping cancelled and superseded `Shape`s. `Shape`s which could not
+    /// be built are returned as `InvalidInput::Shape`.
+    pub(crate) fn completed(&mut self) -> Vec<(Index, Result<ShapeHandle<N>, InvalidInput>)> {
+        let tickets = &mut self.tickets;
+        self.results
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .try_iter()
+            .filter(|(id, ticket, _)| {
+                if tickets.get(id) == Some(ticket) {
+                    tickets.remove(id);
+                    true
+                } else {
+                    false
+                }
+            })
+            .map(|(id, _, shape_handle)| (id, shape_handle))
+            .collect()
+    }
+}
@@ -0,0 +1,557 @@
+//! # Maps module
+//! Static colliders imported from the collision layers of 2D level editors.
+//! `MapImport` reads the JSON exports of [Tiled][] and [LDtk][] and turns
+//! their solid tiles into a `Scenario` of static bodies: the solid cells of
+//! every tile layer are merged into as few rectangles as possible and
+//! combined into one compound collider per layer, while the shapes of Tiled
+//! object layers become colliders of their own. Maps lie in the xy plane with
+//! the y axis pointing up, so the top left corner of a map is at the origin
+//! and its rows extend along the negative y axis. Requires the "maps" feature.
+//!
+//! [Tiled]: https://www.mapeditor.org
+//! [LDtk]: https://ldtk.io
+
+use std::{error::Error, fmt};
+
+use serde_json::Value;
+
+use crate::{
+    colliders::Shape,
+    nalgebra::{self as na, Isometry3, Point3, RealField, Vector3},
+    nphysics::object::BodyStatus,
+    scenarios::Scenario,
+    PhysicsBodyBuilder,
+};
+
+/// The flags Tiled stores in the highest bits of a tile id.
+const TILED_FLIP_FLAGS: u64 = 0xF000_0000;
+
+/// The reasons importing a map can fail.
+#[derive(Debug)]
+pub enum MapError {
+    /// The export is not valid JSON.
+    Json(serde_json::Error),
+    /// The named field is missing or has an unexpected type.
+    InvalidField(&'static str),
+    /// The export uses a feature the importer does not support, e.g. base64
+    /// encoded tile layers.
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapError::Json(error) => write!(f, "invalid map: {}", error),
+            MapError::InvalidField(name) => write!(f, "invalid map field `{}`", name),
+            MapError::Unsupported(feature) => write!(f, "unsupported map feature: {}", feature),
+        }
+    }
+}
+
+impl Error for MapError {}
+
+impl From<serde_json::Error> for MapError {
+    fn from(error: serde_json::Error) -> Self {
+        MapError::Json(error)
+    }
+}
+
+/// The settings for importing the collision layers of a map.
+///
+/// # Example
+///
+/// ```rust
+/// use specs::{World, WorldExt};
+/// use specs_physics::{maps::MapImport, SimplePosition};
+///
+/// let tiled = r#"{
+///     "tilewidth": 16,
+///     "tileheight": 16,
+///     "layers": [{
+///         "type": "tilelayer",
+///         "name": "collision",
+///         "width": 3,
+///         "height": 2,
+///         "data": [0, 0, 0, 1, 1, 1]
+///     }]
+/// }"#;
+///
+/// let mut world = World::new();
+/// let mut dispatcher = specs_physics::physics_dispatcher::<f32, SimplePosition<f32>>();
+/// dispatcher.setup(&mut world);
+///
+/// let scenario = MapImport::<f32>::new(1.0 / 16.0)
+///     .layer("collision")
+///     .tiled(tiled)
+///     .unwrap();
+/// scenario.spawn(&mut world, SimplePosition);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapImport<N: RealField> {
+    /// The names of the layers to import. Without names, Tiled layers with
+    /// a `collision` property set to `true` and all LDtk IntGrid layers are
+    /// imported.
+    pub layers: Vec<String>,
+    /// The size of a map pixel in world units.
+    pub scale: N,
+    /// The extent of the colliders along the z axis.
+    pub depth: N,
+}
+
+impl<N: RealField> MapImport<N> {
+    /// Creates the settings for a map with the given size of a pixel in
+    /// world units.
+    pub fn new(scale: N) -> Self {
+        Self {
+            layers: Vec::new(),
+            scale,
+            depth: N::one(),
+        }
+    }
+
+    /// Adds the name of a layer to import.
+    pub fn layer(mut self, name: impl Into<String>) -> Self {
+        self.layers.push(name.into());
+        self
+    }
+
+    /// Sets the extent of the colliders along the z axis.
+    pub fn depth(mut self, depth: N) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Imports the collision layers of a map exported by Tiled in the JSON
+    /// format. Tile layers have to be stored as plain arrays, i.e. with the
+    /// CSV layer format; any non-empty tile is solid. Rectangles, ellipses,
+    /// polygons and polylines of object layers are imported as well.
+    pub fn tiled(&self, json: &str) -> Result<Scenario<N>, MapError> {
+        let map: Value = serde_json::from_str(json)?;
+        let tile_width = number(&map, "tilewidth")?;
+        let tile_height = number(&map, "tileheight")?;
+        let layers = map["layers"]
+            .as_array()
+            .ok_or(MapError::InvalidField("layers"))?;
+
+        let mut scenario = Scenario {
+            objects: Vec::new(),
+        };
+        self.tiled_layers(layers, (tile_width, tile_height), (0.0, 0.0), &mut scenario)?;
+        Ok(scenario)
+    }
+
+    /// Imports the IntGrid layers of a project exported by LDtk in the JSON
+    /// format; any non-zero value is solid. Levels stored in separate files
+    /// are skipped. Levels are placed at their world coordinates.
+    pub fn ldtk(&self, json: &str) -> Result<Scenario<N>, MapError> {
+        let project: Value = serde_json::from_str(json)?;
+        let levels = project["levels"]
+            .as_array()
+            .ok_or(MapError::InvalidField("levels"))?;
+
+        let mut scenario = Scenario {
+            objects: Vec::new(),
+        };
+        for level in levels {
+            let layers = match level["layerInstances"].as_array() {
+                Some(layers) => layers,
+                None => continue,
+            };
+            let level_offset = (
+                number(level, "worldX").unwrap_or(0.0),
+                number(level, "worldY").unwrap_or(0.0),
+            );
+
+            for layer in layers {
+                let name = layer["__identifier"].as_str().unwrap_or_default();
+                if layer["__type"].as_str() != Some("IntGrid")
+                    || (!self.layers.is_empty() && !self.layers.iter().any(|layer| layer == name))
+                {
+                    continue;
+                }
+
+                let grid_size = number(layer, "__gridSize")?;
+                let width = number(layer, "__cWid")? as usize;
+                let height = number(layer, "__cHei")? as usize;
+                let solid = layer["intGridCsv"]
+                    .as_array()
+                    .ok_or(MapError::InvalidField("intGridCsv"))?
+                    .iter()
+                    .map(|value| value.as_i64().map_or(false, |value| value != 0))
+                    .collect::<Vec<_>>();
+                if solid.len() != width * height {
+                    return Err(MapError::InvalidField("intGridCsv"));
+                }
+
+                let origin = (
+                    level_offset.0 + number(layer, "__pxTotalOffsetX").unwrap_or(0.0),
+                    level_offset.1 + number(layer, "__pxTotalOffsetY").unwrap_or(0.0),
+                );
+                self.push_grid(&solid, width, (grid_size, grid_size), origin, &mut scenario);
+            }
+        }
+        Ok(scenario)
+    }
+
+    fn tiled_layers(
+        &self,
+        layers: &[Value],
+        tile_size: (f64, f64),
+        offset: (f64, f64),
+        scenario: &mut Scenario<N>,
+    ) -> Result<(), MapError> {
+        for layer in layers {
+            let offset = (
+                offset.0 + number(layer, "offsetx").unwrap_or(0.0),
+                offset.1 + number(layer, "offsety").unwrap_or(0.0),
+            );
+            let kind = layer["type"].as_str().unwrap_or_default();
+            if kind == "group" {
+                let children = layer["layers"]
+                    .as_array()
+                    .ok_or(MapError::InvalidField("layers"))?;
+                self.tiled_layers(children, tile_size, offset, scenario)?;
+                continue;
+            }
+            if !self.is_tiled_collision_layer(layer) {
+                continue;
+            }
+
+            match kind {
+                "tilelayer" => {
+                    // infinite maps store their tiles in chunks
+                    let chunks = match layer["chunks"].as_array() {
+                        Some(chunks) => chunks.iter().collect::<Vec<_>>(),
+                        None => vec![layer],
+                    };
+                    for chunk in chunks {
+                        let width = number(chunk, "width")? as usize;
+                        let solid = tiled_solid_tiles(chunk)?;
+                        let origin = (
+                            offset.0 + number(chunk, "x").unwrap_or(0.0) * tile_size.0,
+                            offset.1 + number(chunk, "y").unwrap_or(0.0) * tile_size.1,
+                        );
+                        self.push_grid(&solid, width, tile_size, origin, scenario);
+                    }
+                }
+                "objectgroup" => {
+                    let objects = layer["objects"]
+                        .as_array()
+                        .ok_or(MapError::InvalidField("objects"))?;
+                    for object in objects {
+                        self.push_tiled_object(object, offset, scenario)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn is_tiled_collision_layer(&self, layer: &Value) -> bool {
+        let name = layer["name"].as_str().unwrap_or_default();
+        if !self.layers.is_empty() {
+            return self.layers.iter().any(|layer| layer == name);
+        }
+        layer["properties"].as_array().map_or(false, |properties| {
+            properties.iter().any(|property| {
+                property["name"].as_str() == Some("collision")
+                    && property["value"].as_bool() == Some(true)
+            })
+        })
+    }
+
+    /// Merges the solid cells of a grid into rectangles and adds them as one
+    /// static compound collider placed at the top left corner of the grid.
+    fn push_grid(
+        &self,
+        solid: &[bool],
+        width: usize,
+        cell_size: (f64, f64),
+        origin: (f64, f64),
+        scenario: &mut Scenario<N>,
+    ) {
+        let half: N = na::convert(0.5);
+        let parts = merge_cells(solid, width)
+            .into_iter()
+            .map(|(x, y, w, h)| {
+                let (w, h) = (w as f64 * cell_size.0, h as f64 * cell_size.1);
+                let center = (
+                    x as f64 * cell_size.0 + w / 2.0,
+                    y as f64 * cell_size.1 + h / 2.0,
+                );
+                (
+                    Isometry3::translation(
+                        self.to_world(center.0),
+                        -self.to_world(center.1),
+                        N::zero(),
+                    ),
+                    Shape::Cuboid {
+                        half_extents: Vector3::new(
+                            self.to_world(w) * half,
+                            self.to_world(h) * half,
+                            self.depth * half,
+                        ),
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+        if parts.is_empty() {
+            return;
+        }
+
+        scenario.push(
+            Isometry3::translation(self.to_world(origin.0), -self.to_world(origin.1), N::zero()),
+            PhysicsBodyBuilder::from(BodyStatus::Static).build(),
+            Shape::Compound { parts },
+        );
+    }
+
+    /// Adds the shape of a Tiled object as a static collider. Points and
+    /// text objects have no shape and are skipped.
+    fn push_tiled_object(
+        &self,
+        object: &Value,
+        offset: (f64, f64),
+        scenario: &mut Scenario<N>,
+    ) -> Result<(), MapError> {
+        let x = offset.0 + number(object, "x")?;
+        let y = offset.1 + number(object, "y")?;
+        let width = number(object, "width").unwrap_or(0.0);
+        let height = number(object, "height").unwrap_or(0.0);
+        let half: N = na::convert(0.5);
+
+        // objects rotate clockwise around their top left corner in Tiled
+        let rotation = number(object, "rotation").unwrap_or(0.0).to_radians();
+        let isometry = Isometry3::new(
+            Vector3::new(self.to_world(x), -self.to_world(y), N::zero()),
+            Vector3::z() * na::convert::<f64, N>(-rotation),
+        );
+
+        let points = |key: &'static str| -> Result<Vec<Point3<N>>, MapError> {
+            object[key]
+                .as_array()
+                .ok_or(MapError::InvalidField(key))?
+                .iter()
+                .map(|point| {
+                    Ok(Point3::new(
+                        self.to_world(number(point, "x")?),
+                        -self.to_world(number(point, "y")?),
+                        N::zero(),
+                    ))
+                })
+                .collect()
+        };
+
+        let (shape, local) =
+            if object["point"].as_bool() == Some(true) || object.get("text").is_some() {
+                return Ok(());
+            } else if object.get("polygon").is_some() {
+                (
+                    Shape::chain(points("polygon")?, true),
+                    Isometry3::identity(),
+                )
+            } else if object.get("polyline").is_some() {
+                (
+                    Shape::chain(points("polyline")?, false),
+                    Isometry3::identity(),
+                )
+            } else if width <= 0.0 || height <= 0.0 {
+                return Ok(());
+            } else {
+                let center = Isometry3::translation(
+                    self.to_world(width) * half,
+                    -self.to_world(height) * half,
+                    N::zero(),
+                );
+                let shape = if object["ellipse"].as_bool() == Some(true) {
+                    // ellipses are approximated by a circle of their mean radius
+                    Shape::Ball {
+                        radius: self.to_world((width + height) / 4.0),
+                    }
+                } else {
+                    Shape::Cuboid {
+                        half_extents: Vector3::new(
+                            self.to_world(width) * half,
+                            self.to_world(height) * half,
+                            self.depth * half,
+                        ),
+                    }
+                };
+                (shape, center)
+            };
+
+        scenario.push(
+            isometry * local,
+            PhysicsBodyBuilder::from(BodyStatus::Static).build(),
+            shape,
+        );
+        Ok(())
+    }
+
+    /// Converts a length in map pixels into world units.
+    fn to_world(&self, pixels: f64) -> N {
+        na::convert::<f64, N>(pixels) * self.scale
+    }
+}
+
+/// Reads a numeric field of a JSON object.
+fn number(value: &Value, key: &'static str) -> Result<f64, MapError> {
+    value[key].as_f64().ok_or(MapError::InvalidField(key))
+}
+
+/// Reads the solid tiles of a Tiled tile layer or chunk.
+fn tiled_solid_tiles(layer: &Value) -> Result<Vec<bool>, MapError> {
+    let data = match &layer["data"] {
+        Value::Array(data) => data,
+        Value::String(_) => return Err(MapError::Unsupported("encoded tile layer data")),
+        _ => return Err(MapError::InvalidField("data")),
+    };
+    let solid = data
+        .iter()
+        .map(|tile| {
+            tile.as_u64()
+                .map_or(false, |tile| tile & !TILED_FLIP_FLAGS != 0)
+        })
+        .collect::<Vec<_>>();
+
+    let width = number(layer, "width")? as usize;
+    let height = number(layer, "height")? as usize;
+    if solid.len() != width * height {
+        return Err(MapError::InvalidField("data"));
+    }
+    Ok(solid)
+}
+
+/// Greedily merges the solid cells of a row-major grid into rectangles,
+/// returned as their column, row, width and height in cells. Every cell is
+/// covered by exactly one rectangle.
+fn merge_cells(solid: &[bool], width: usize) -> Vec<(usize, usize, usize, usize)> {
+    if width == 0 {
+        return Vec::new();
+    }
+    let height = solid.len() / width;
+    let mut covered = vec![false; solid.len()];
+    let free =
+        |covered: &[bool], x: usize, y: usize| solid[y * width + x] && !covered[y * width + x];
+
+    let mut rectangles = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            if !free(&covered, x, y) {
+                continue;
+            }
+
+            // grow along the row first, then down while whole rows fit
+            let w = (x..width).take_while(|&x| free(&covered, x, y)).count();
+            let h = (y..height)
+                .take_while(|&y| (x..x + w).all(|x| free(&covered, x, y)))
+                .count();
+            for row in y..y + h {
+                for column in x..x + w {
+                    covered[row * width + column] = true;
+                }
+            }
+            rectangles.push((x, y, w, h));
+        }
+    }
+    rectangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_cells, MapImport};
+    use crate::colliders::Shape;
+
+    #[test]
+    fn merge_solid_cells() {
+        // an L shape and a single cell
+        #[rustfmt::skip]
+        let solid = [
+            true, false, false, true,
+            true, false, false, false,
+            true, true, true, false,
+        ];
+        let rectangles = merge_cells(&solid, 4);
+        assert_eq!(rectangles, vec![(0, 0, 1, 3), (3, 0, 1, 1), (1, 2, 2, 1)]);
+    }
+
+    #[test]
+    fn import_tiled_layers() {
+        let tiled = r#"{
+            "tilewidth": 16,
+            "tileheight": 16,
+            "layers": [
+                {
+                    "type": "tilelayer",
+                    "name": "decoration",
+                    "width": 2,
+                    "height": 1,
+                    "data": [1, 1]
+                },
+                {
+                    "type": "tilelayer",
+                    "name": "walls",
+                    "width": 2,
+                    "height": 2,
+                    "properties": [{ "name": "collision", "type": "bool", "value": true }],
+                    "data": [0, 2147483649, 1, 1]
+                },
+                {
+                    "type": "objectgroup",
+                    "name": "shapes",
+                    "properties": [{ "name": "collision", "type": "bool", "value": true }],
+                    "objects": [
+                        { "x": 0, "y": 32, "width": 32, "height": 8 },
+                        { "x": 0, "y": 0, "point": true },
+                        { "x": 8, "y": 8, "polyline": [{ "x": 0, "y": 0 }, { "x": 16, "y": 0 }] }
+                    ]
+                }
+            ]
+        }"#;
+
+        let scenario = MapImport::<f32>::new(1.0 / 16.0).tiled(tiled).unwrap();
+        assert_eq!(scenario.objects.len(), 3);
+        match &scenario.objects[0].physics_collider.shape {
+            Shape::Compound { parts } => assert_eq!(parts.len(), 2),
+            _ => panic!("expected a compound"),
+        }
+        let rectangle = scenario.objects[1].isometry.translation.vector;
+        assert!((rectangle.x - 1.0).abs() < 1.0e-6 && (rectangle.y + 2.25).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn import_ldtk_int_grid() {
+        let ldtk = r#"{
+            "levels": [{
+                "worldX": 64,
+                "worldY": 0,
+                "layerInstances": [{
+                    "__identifier": "Collisions",
+                    "__type": "IntGrid",
+                    "__gridSize": 8,
+                    "__cWid": 2,
+                    "__cHei": 2,
+                    "__pxTotalOffsetX": 0,
+                    "__pxTotalOffsetY": 0,
+                    "intGridCsv": [1, 1, 0, 2]
+                }]
+            }, {
+                "worldX": 0,
+                "worldY": 0,
+                "layerInstances": null
+            }]
+        }"#;
+
+        let scenario = MapImport::<f32>::new(0.125)
+            .layer("Collisions")
+            .ldtk(ldtk)
+            .unwrap();
+        assert_eq!(scenario.objects.len(), 1);
+        assert_eq!(scenario.objects[0].isometry.translation.vector.x, 8.0);
+        match &scenario.objects[0].physics_collider.shape {
+            Shape::Compound { parts } => assert_eq!(parts.len(), 2),
+            _ => panic!("expected a compound"),
+        }
+    }
+}
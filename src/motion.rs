@@ -0,0 +1,431 @@
+//! # Motion module
+//! Scripted motion of kinematic bodies. A `ScriptedMotion` describes the pose
+//! of its `Entity` over time, either as keyframes or as a function of time,
+//! and the `ApplyScriptedMotionSystem` drives the kinematic `PhysicsBody`
+//! along it, so moving hazards and cutscene platforms need no custom
+//! `System`s. A `PathConstraint` confines a body to a `Spline` instead,
+//! optionally driven along it by a `PathMotor`, while the simulation keeps
+//! moving it along the path, e.g. for rollercoasters and camera dollies.
+
+use std::{fmt, sync::Arc};
+
+use specs::{Component, DenseVecStorage};
+
+use crate::nalgebra::{self as na, Isometry3, Point3, RealField, Translation3, Vector3};
+
+/// A pose of a `ScriptedMotion` at the given time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe<N: RealField> {
+    /// The time of the pose, in seconds since the start of the motion.
+    pub time: N,
+    pub isometry: Isometry3<N>,
+}
+
+impl<N: RealField> Keyframe<N> {
+    pub fn new(time: N, isometry: Isometry3<N>) -> Self {
+        Self { time, isometry }
+    }
+}
+
+/// The `MotionPath` defines the pose of a `ScriptedMotion` over time.
+#[derive(Clone)]
+pub enum MotionPath<N: RealField> {
+    /// Poses interpolated between keyframes sorted by time; translations are
+    /// interpolated linearly and rotations spherically.
+    Keyframes(Vec<Keyframe<N>>),
+    /// Poses computed by a function of the time since the start of the
+    /// motion.
+    Function(Arc<dyn Fn(N) -> Isometry3<N> + Send + Sync>),
+}
+
+impl<N: RealField> fmt::Debug for MotionPath<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MotionPath::Keyframes(keyframes) => {
+                f.debug_tuple("Keyframes").field(keyframes).finish()
+            }
+            MotionPath::Function(_) => f.write_str("Function"),
+        }
+    }
+}
+
+/// The `MotionRepeat` describes how keyframed motion continues after the last
+/// keyframe. Functions of time are evaluated unbounded instead.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MotionRepeat {
+    /// Stays at the last keyframe.
+    Once,
+    /// Starts over at the first keyframe.
+    Loop,
+    /// Runs backwards to the first keyframe and forwards again.
+    PingPong,
+}
+
+/// The `ScriptedMotion` `Component` moves the kinematic `PhysicsBody` of its
+/// `Entity` along a `MotionPath`. The body is driven with the velocity which
+/// reaches the pose of the next step, so resting bodies are carried along
+/// and pushed by it like by any moving kinematic body. The motion starts
+/// from the current pose of the body, which should therefore be placed at
+/// the start of the path. See the `ApplyScriptedMotionSystem`.
+///
+/// # Example
+///
+/// ```rust
+/// use specs_physics::{
+///     motion::{Keyframe, MotionRepeat, ScriptedMotion},
+///     nalgebra::Isometry3,
+/// };
+///
+/// // a platform moving up and down between two floors
+/// let lift = ScriptedMotion::keyframes(vec![
+///     Keyframe::new(0.0f32, Isometry3::translation(0.0, 0.0, 0.0)),
+///     Keyframe::new(4.0, Isometry3::translation(0.0, 6.0, 0.0)),
+/// ])
+/// .repeat(MotionRepeat::PingPong);
+/// assert!((lift.pose_at(2.0).translation.vector.y - 3.0).abs() < 1.0e-6);
+///
+/// // a hazard circling around the origin
+/// let saw = ScriptedMotion::function(|t: f32| Isometry3::translation(t.cos(), 1.0, t.sin()));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ScriptedMotion<N: RealField> {
+    pub path: MotionPath<N>,
+    pub repeat: MotionRepeat,
+    /// The playback rate; zero pauses the motion.
+    ///
+    /// default: 1.0
+    pub speed: N,
+    /// The time since the start of the motion, advanced by the
+    /// `ApplyScriptedMotionSystem`.
+    pub time: N,
+}
+
+impl<N: RealField> Component for ScriptedMotion<N> {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl<N: RealField> ScriptedMotion<N> {
+    /// Creates a `ScriptedMotion` through the given keyframes, which are
+    /// sorted by time.
+    pub fn keyframes(mut keyframes: Vec<Keyframe<N>>) -> Self {
+        keyframes.sort_by(|keyframe1, keyframe2| {
+            keyframe1
+                .time
+                .partial_cmp(&keyframe2.time)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        Self::new(MotionPath::Keyframes(keyframes))
+    }
+
+    /// Creates a `ScriptedMotion` following the given function of time.
+    pub fn function<F>(function: F) -> Self
+    where
+        F: Fn(N) -> Isometry3<N> + Send + Sync + 'static,
+    {
+        Self::new(MotionPath::Function(Arc::new(function)))
+    }
+
+    fn new(path: MotionPath<N>) -> Self {
+        Self {
+            path,
+            repeat: MotionRepeat::Once,
+            speed: N::one(),
+            time: N::zero(),
+        }
+    }
+
+    /// Returns the `ScriptedMotion` with the given `MotionRepeat`.
+    pub fn repeat(mut self, repeat: MotionRepeat) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    /// Returns the `ScriptedMotion` with the given playback rate.
+    pub fn speed(mut self, speed: N) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// The time of the last keyframe, or `None` for functions of time.
+    pub fn duration(&self) -> Option<N> {
+        match &self.path {
+            MotionPath::Keyframes(keyframes) => keyframes.last().map(|keyframe| keyframe.time),
+            MotionPath::Function(_) => None,
+        }
+    }
+
+    /// Evaluates the pose at the given time since the start of the motion.
+    pub fn pose_at(&self, time: N) -> Isometry3<N> {
+        let keyframes = match &self.path {
+            MotionPath::Function(function) => return function(time),
+            MotionPath::Keyframes(keyframes) => keyframes,
+        };
+        let (first, last) = match (keyframes.first(), keyframes.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Isometry3::identity(),
+        };
+
+        let time = self.repeated_time(time, first.time, last.time);
+        match keyframes.iter().position(|keyframe| keyframe.time > time) {
+            None => last.isometry,
+            Some(0) => first.isometry,
+            Some(index) => {
+                let (from, to) = (&keyframes[index - 1], &keyframes[index]);
+                let t = (time - from.time) / (to.time - from.time);
+                interpolate(&from.isometry, &to.isometry, t)
+            }
+        }
+    }
+
+    /// Maps the time into the range of the keyframes according to the
+    /// `MotionRepeat`.
+    fn repeated_time(&self, time: N, start: N, end: N) -> N {
+        let length = end - start;
+        if time <= start || length <= N::zero() {
+            return time;
+        }
+
+        let elapsed = time - start;
+        match self.repeat {
+            MotionRepeat::Once => time,
+            MotionRepeat::Loop => start + elapsed % length,
+            MotionRepeat::PingPong => {
+                let elapsed = elapsed % (length + length);
+                if elapsed > length {
+                    end - (elapsed - length)
+                } else {
+                    start + elapsed
+                }
+            }
+        }
+    }
+}
+
+fn interpolate<N: RealField>(from: &Isometry3<N>, to: &Isometry3<N>, t: N) -> Isometry3<N> {
+    let translation = from.translation.vector.lerp(&to.translation.vector, t);
+    // opposite rotations have no unique interpolation, so they switch halfway
+    let rotation = from
+        .rotation
+        .try_slerp(&to.rotation, t, N::default_epsilon())
+        .unwrap_or(if t < na::convert(0.5) {
+            from.rotation
+        } else {
+            to.rotation
+        });
+    Isometry3::from_parts(Translation3::from(translation), rotation)
+}
+
+/// A Catmull-Rom `Spline` passing through its control points. Positions on
+/// the spline are addressed by a parameter running from zero at the first
+/// control point to the number of segments at the last one, or back at the
+/// first one for closed splines.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spline<N: RealField> {
+    points: Vec<Point3<N>>,
+    closed: bool,
+}
+
+impl<N: RealField> Spline<N> {
+    /// Creates a `Spline` through the given control points, which connects
+    /// the last point back to the first one if `closed`. Panics with less
+    /// than two points.
+    pub fn new(points: Vec<Point3<N>>, closed: bool) -> Self {
+        assert!(points.len() >= 2, "A Spline needs at least two points");
+        Self { points, closed }
+    }
+
+    pub fn points(&self) -> &[Point3<N>] {
+        &self.points
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// The number of segments, which is also the largest parameter.
+    pub fn segments(&self) -> usize {
+        if self.closed {
+            self.points.len()
+        } else {
+            self.points.len() - 1
+        }
+    }
+
+    /// The position on the `Spline` at the given parameter.
+    pub fn point(&self, param: N) -> Point3<N> {
+        let (p1, [a, b, c], u) = self.coefficients(param);
+        let (half, two): (N, N) = (na::convert(0.5), na::convert(2.0));
+        Point3::from((p1 * two + a * u + b * (u * u) + c * (u * u * u)) * half)
+    }
+
+    /// The derivative of the position on the `Spline` by the parameter, which
+    /// points in the direction of increasing parameters.
+    pub fn tangent(&self, param: N) -> Vector3<N> {
+        let (_, [a, b, c], u) = self.coefficients(param);
+        let (half, two, three): (N, N, N) = (na::convert(0.5), na::convert(2.0), na::convert(3.0));
+        (a + b * (u * two) + c * (u * u * three)) * half
+    }
+
+    /// Finds the parameter of the position on the `Spline` closest to the
+    /// given point.
+    pub fn closest_param(&self, point: &Point3<N>) -> N {
+        let segments: N = na::convert(self.segments() as f64);
+        let half = segments * na::convert(0.5);
+        self.search(point, half, half, self.segments() * CLOSEST_SAMPLES)
+    }
+
+    /// Finds the parameter of the position closest to the given point within
+    /// one segment of the `hint`, e.g. the parameter of the previous step.
+    pub fn closest_param_near(&self, point: &Point3<N>, hint: N) -> N {
+        self.search(point, hint, N::one(), CLOSEST_SAMPLES * 2)
+    }
+
+    /// Maps the parameter into the range of the `Spline`, wrapping around
+    /// closed splines.
+    pub fn wrap_param(&self, param: N) -> N {
+        let segments: N = na::convert(self.segments() as f64);
+        if self.closed {
+            let wrapped = param % segments;
+            if wrapped < N::zero() {
+                wrapped + segments
+            } else {
+                wrapped
+            }
+        } else {
+            na::clamp(param, N::zero(), segments)
+        }
+    }
+
+    /// Samples the parameters around the `center` and refines the closest
+    /// sample twice.
+    fn search(&self, point: &Point3<N>, center: N, radius: N, samples: usize) -> N {
+        let distance = |param| na::distance_squared(&self.point(param), point);
+        let (mut center, mut radius) = (center, radius);
+        for _ in 0..3 {
+            let step = (radius + radius) / na::convert(samples as f64);
+            center = (0..=samples)
+                .map(|sample| self.wrap_param(center - radius + step * na::convert(sample as f64)))
+                .min_by(|param1, param2| {
+                    distance(*param1)
+                        .partial_cmp(&distance(*param2))
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .unwrap_or(center);
+            radius = step;
+        }
+        center
+    }
+
+    /// The start of the segment of the parameter, the polynomial coefficients
+    /// of the segment and the parameter within the segment.
+    fn coefficients(&self, param: N) -> (Vector3<N>, [Vector3<N>; 3], N) {
+        let param = self.wrap_param(param);
+        let index = param
+            .floor()
+            .to_subset()
+            .map_or(0, |index: f64| index as usize)
+            .min(self.segments() - 1);
+        let u = param - na::convert(index as f64);
+
+        let count = self.points.len() as isize;
+        let control = |offset: isize| {
+            let index = index as isize + offset;
+            let index = if self.closed {
+                (index % count + count) % count
+            } else {
+                index.max(0).min(count - 1)
+            };
+            self.points[index as usize].coords
+        };
+        let (p0, p1, p2, p3) = (control(-1), control(0), control(1), control(2));
+
+        let (two, three, four, five): (N, N, N, N) = (
+            na::convert(2.0),
+            na::convert(3.0),
+            na::convert(4.0),
+            na::convert(5.0),
+        );
+        let coefficients = [
+            p2 - p0,
+            p0 * two - p1 * five + p2 * four - p3,
+            p3 - p0 + (p1 - p2) * three,
+        ];
+        (p1, coefficients, u)
+    }
+}
+
+/// The number of samples per segment when searching the closest parameter.
+const CLOSEST_SAMPLES: usize = 8;
+
+/// The motor of a `PathConstraint`, driving the body along the path.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PathMotor<N: RealField> {
+    /// The targeted speed in the direction of increasing parameters; negative
+    /// speeds drive the body backwards.
+    pub target_speed: N,
+    /// The maximum change of the speed along the path per second.
+    pub max_acceleration: N,
+}
+
+/// The `PathConstraint` `Component` confines the dynamic or kinematic
+/// `PhysicsBody` of its `Entity` to a `Spline`. The velocity of the body is
+/// projected onto the tangent of the path every step, so gravity and
+/// collisions still move it along the path, and the remaining distance to the
+/// path is corrected. Bodies stop at the ends of open splines. See the
+/// `ApplyPathConstraintsSystem`.
+///
+/// # Example
+///
+/// ```rust
+/// use specs_physics::{
+///     motion::{PathConstraint, PathMotor, Spline},
+///     nalgebra::Point3,
+/// };
+///
+/// let track = Spline::new(
+///     vec![
+///         Point3::new(0.0f32, 0.0, 0.0),
+///         Point3::new(10.0, 5.0, 0.0),
+///         Point3::new(20.0, 0.0, 10.0),
+///     ],
+///     false,
+/// );
+/// let dolly = PathConstraint::new(track).with_motor(PathMotor {
+///     target_speed: 2.0,
+///     max_acceleration: 1.0,
+/// });
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathConstraint<N: RealField> {
+    pub spline: Spline<N>,
+    pub motor: Option<PathMotor<N>>,
+    /// The fraction of the distance to the path corrected per step.
+    ///
+    /// default: 0.5
+    pub correction: N,
+    /// The parameter of the body on the `Spline`, updated by the
+    /// `ApplyPathConstraintsSystem`; `None` until the body was first located.
+    pub param: Option<N>,
+}
+
+impl<N: RealField> Component for PathConstraint<N> {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl<N: RealField> PathConstraint<N> {
+    /// Creates a `PathConstraint` without motor.
+    pub fn new(spline: Spline<N>) -> Self {
+        Self {
+            spline,
+            motor: None,
+            correction: na::convert(0.5),
+            param: None,
+        }
+    }
+
+    /// Returns the `PathConstraint` with the given `PathMotor`.
+    pub fn with_motor(mut self, motor: PathMotor<N>) -> Self {
+        self.motor = Some(motor);
+        self
+    }
+}
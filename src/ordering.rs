@@ -0,0 +1,299 @@
+//! # Ordering module
+//! Checks that the physics `System`s do not depend on the order specs
+//! happens to schedule them in. The `Dispatcher` runs `System`s without a
+//! dependency between them in parallel, so any order respecting the
+//! dependencies of `register_physics_systems` is a legal schedule; an
+//! undeclared dependency between two `System`s shows up as diverging
+//! results between such orders. Only compiled with the "ordering-checks"
+//! feature, as exhaustive checks are meant for tests.
+
+use std::{collections::HashMap, error::Error, fmt};
+
+use specs::{RunNow, System, World, WorldExt};
+
+use crate::{
+    add_physics_systems,
+    bodies::Position,
+    golden::{GoldenError, GoldenPoses, GoldenTolerance},
+    nalgebra::RealField,
+    scenarios::Scenario,
+    SimplePosition,
+    SystemRegistry,
+};
+
+/// The error of `check_orderings`: simulating the `Scenario` with the given
+/// order of the physics `System`s diverged from the first legal order.
+#[derive(Debug)]
+pub struct OrderingError<N: RealField> {
+    /// The names of the `System`s in the order they ran.
+    pub ordering: Vec<String>,
+    /// The deviations from the poses of the first legal order.
+    pub error: GoldenError<N>,
+}
+
+impl<N: RealField> fmt::Display for OrderingError<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "System order [{}] changes the simulation: {}",
+            self.ordering.join(", "),
+            self.error
+        )
+    }
+}
+
+impl<N: RealField> Error for OrderingError<N> {}
+
+struct OrderedSystem {
+    name: String,
+    system: Box<dyn for<'c> RunNow<'c>>,
+}
+
+/// The physics `System`s together with their dependency graph, which can be
+/// run sequentially in any legal order instead of through a `Dispatcher`.
+/// Legal orders are numbered from zero to `ordering_count`, which allows
+/// sampling them evenly when there are too many to check all of them.
+pub struct OrderedSystems {
+    systems: Vec<OrderedSystem>,
+    // bit masks of the indices of the dependencies of every System
+    dependencies: Vec<u64>,
+}
+
+impl SystemRegistry for OrderedSystems {
+    fn add<S>(&mut self, system: S, name: &str, dependencies: &[&str])
+    where
+        S: for<'c> System<'c> + Send + 'static,
+    {
+        assert!(
+            self.systems.len() < 64,
+            "Too many Systems for ordering checks"
+        );
+        let mask = dependencies.iter().fold(0, |mask, dependency| {
+            let index = self
+                .systems
+                .iter()
+                .position(|ordered| ordered.name == *dependency)
+                .unwrap_or_else(|| panic!("No such System registered: {}", dependency));
+            mask | 1 << index
+        });
+
+        self.systems.push(OrderedSystem {
+            name: name.to_owned(),
+            system: Box::new(system),
+        });
+        self.dependencies.push(mask);
+    }
+}
+
+impl OrderedSystems {
+    /// Collects the physics `System`s registered by `register_physics_systems`.
+    pub fn physics<N, P>() -> Self
+    where
+        N: RealField,
+        P: Position<N>,
+    {
+        let mut ordered_systems = Self {
+            systems: Vec::new(),
+            dependencies: Vec::new(),
+        };
+        add_physics_systems::<N, P, _>(&mut ordered_systems, &[]);
+        ordered_systems
+    }
+
+    /// The names of the `System`s, in registration order.
+    pub fn names(&self) -> Vec<&str> {
+        self.systems
+            .iter()
+            .map(|ordered| ordered.name.as_str())
+            .collect()
+    }
+
+    /// Sets up all `System`s like `Dispatcher::setup`.
+    pub fn setup(&mut self, world: &mut World) {
+        for ordered in &mut self.systems {
+            ordered.system.setup(world);
+        }
+    }
+
+    /// Counts the orders of the `System`s respecting their dependencies,
+    /// saturating at `u64::MAX`.
+    pub fn ordering_count(&self) -> u64 {
+        OrderingCounter::new(&self.dependencies).count(0)
+    }
+
+    /// Returns the legal order with the given number as indices of the
+    /// `System`s, or `None` if the number is not below `ordering_count`.
+    pub fn ordering(&self, mut number: u64) -> Option<Vec<usize>> {
+        let mut counter = OrderingCounter::new(&self.dependencies);
+        if number >= counter.count(0) {
+            return None;
+        }
+
+        let mut placed = 0;
+        let mut ordering = Vec::with_capacity(self.systems.len());
+        while ordering.len() < self.systems.len() {
+            for index in counter.available(placed) {
+                let count = counter.count(placed | 1 << index);
+                if number < count {
+                    placed |= 1 << index;
+                    ordering.push(index);
+                    break;
+                }
+                number -= count;
+            }
+        }
+        Some(ordering)
+    }
+
+    /// Runs every `System` once, in the given order.
+    pub fn run_in_order(&mut self, world: &World, ordering: &[usize]) {
+        for index in ordering {
+            self.systems[*index].system.run_now(world);
+        }
+    }
+}
+
+/// Counts the legal orders of the `System`s not placed yet, memoised by the
+/// bit mask of the placed `System`s.
+struct OrderingCounter<'d> {
+    dependencies: &'d [u64],
+    counts: HashMap<u64, u64>,
+}
+
+impl<'d> OrderingCounter<'d> {
+    fn new(dependencies: &'d [u64]) -> Self {
+        Self {
+            dependencies,
+            counts: HashMap::new(),
+        }
+    }
+
+    /// The `System`s which are not placed yet but whose dependencies are.
+    fn available(&self, placed: u64) -> Vec<usize> {
+        (0..self.dependencies.len())
+            .filter(|index| placed & (1 << index) == 0)
+            .filter(|index| self.dependencies[*index] & !placed == 0)
+            .collect()
+    }
+
+    fn count(&mut self, placed: u64) -> u64 {
+        if placed.count_ones() as usize == self.dependencies.len() {
+            return 1;
+        }
+        if let Some(count) = self.counts.get(&placed) {
+            return *count;
+        }
+
+        let count = self
+            .available(placed)
+            .into_iter()
+            .fold(0u64, |count, index| {
+                count.saturating_add(self.count(placed | 1 << index))
+            });
+        self.counts.insert(placed, count);
+        count
+    }
+}
+
+/// Simulates the `Scenario` for the given number of steps once for each of up
+/// to `max_orderings` legal orders of the physics `System`s, spread evenly
+/// over all legal orders, and compares the final poses with the ones of the
+/// first order. Returns the number of orders checked.
+///
+/// # Example
+///
+/// ```rust
+/// use specs_physics::{golden::GoldenTolerance, ordering, scenarios::Scenario};
+///
+/// let scenario = Scenario::<f32>::box_stack(2, 2, 0.5);
+/// let checked = ordering::check_orderings(&scenario, 3, 4, &GoldenTolerance::default()).unwrap();
+/// assert_eq!(checked, 4);
+/// ```
+pub fn check_orderings<N: RealField>(
+    scenario: &Scenario<N>,
+    steps: usize,
+    max_orderings: usize,
+    tolerance: &GoldenTolerance<N>,
+) -> Result<usize, OrderingError<N>> {
+    let count = OrderedSystems::physics::<N, SimplePosition<N>>().ordering_count();
+    let samples = count.min(max_orderings.max(1) as u64);
+
+    let (_, reference) = simulate_ordering(scenario, steps, 0);
+    for sample in 1..samples {
+        let number = u128::from(sample) * u128::from(count - 1) / u128::from(samples - 1);
+        let (ordering, poses) = simulate_ordering(scenario, steps, number as u64);
+        reference
+            .compare(&poses, tolerance)
+            .map_err(|error| OrderingError { ordering, error })?;
+    }
+    Ok(samples as usize)
+}
+
+/// Simulates the `Scenario` with the physics `System`s in the legal order
+/// with the given number and returns the names of the `System`s in that
+/// order along with the final poses.
+fn simulate_ordering<N: RealField>(
+    scenario: &Scenario<N>,
+    steps: usize,
+    number: u64,
+) -> (Vec<String>, GoldenPoses<N>) {
+    let mut ordered_systems = OrderedSystems::physics::<N, SimplePosition<N>>();
+    let ordering = ordered_systems
+        .ordering(number)
+        .expect("Ordering number out of range");
+    let mut world = World::new();
+    ordered_systems.setup(&mut world);
+
+    let entities = scenario.spawn(&mut world, SimplePosition);
+    for _ in 0..steps {
+        ordered_systems.run_in_order(&world, &ordering);
+        world.maintain();
+    }
+
+    let names = ordered_systems.names();
+    let ordering = ordering
+        .iter()
+        .map(|index| names[*index].to_owned())
+        .collect();
+    (ordering, GoldenPoses::capture(&world, &entities, steps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_orderings, OrderedSystems};
+    use crate::{golden::GoldenTolerance, scenarios::Scenario, PhysicsStages, SimplePosition};
+
+    #[test]
+    fn orderings_respect_dependencies() {
+        let ordered_systems = OrderedSystems::physics::<f32, SimplePosition<f32>>();
+        let names = ordered_systems.names();
+        let count = ordered_systems.ordering_count();
+        assert!(count > 1);
+
+        for number in &[0, count / 2, count - 1] {
+            let ordering = ordered_systems.ordering(*number).unwrap();
+            let position = |name| {
+                let index = names.iter().position(|other| *other == name).unwrap();
+                ordering.iter().position(|other| *other == index).unwrap()
+            };
+            assert!(
+                position(PhysicsStages::INSERT_BODIES) < position(PhysicsStages::INSERT_COLLIDERS)
+            );
+            assert!(position(PhysicsStages::APPLY_RATES) < position(PhysicsStages::STEP));
+            assert_eq!(position(PhysicsStages::WRITE_BACK), ordering.len() - 1);
+        }
+        assert!(ordered_systems.ordering(count).is_none());
+    }
+
+    #[test]
+    fn physics_systems_are_order_independent() {
+        let mut scenario = Scenario::<f32>::box_stack(2, 3, 0.5);
+        // skip the second ground of the rain
+        scenario
+            .objects
+            .extend(Scenario::<f32>::rain(8, 3).objects.into_iter().skip(1));
+        if let Err(error) = check_orderings(&scenario, 20, 64, &GoldenTolerance::default()) {
+            panic!("{}", error);
+        }
+    }
+}
@@ -2,11 +2,15 @@
 //! Resources for modifying the various simulation parameters of the
 //! nphysics World.
 
-use std::ops::{Deref, DerefMut};
+use std::{
+    ops::{Deref, DerefMut},
+    time::Duration,
+};
 
 use crate::{
     nalgebra::{self as na, RealField, Scalar, Vector3},
-    nphysics::solver::IntegrationParameters,
+    ncollide::world::CollisionGroups,
+    nphysics::{material::MaterialId, solver::IntegrationParameters},
 };
 
 /// The `TimeStep` is used to set the timestep of the nphysics integration, see
@@ -41,6 +45,127 @@ impl<N: RealField> Default for TimeStep<N> {
     }
 }
 
+/// The `DeltaTime` is the elapsed time since the last dispatch. If it exists,
+/// the `PhysicsStepperSystem` accumulates it and progresses the nphysics
+/// `World` in as many fixed `TimeStep`s as fit into the accumulated time.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DeltaTime<N: RealField>(pub N);
+
+impl<N: RealField> Deref for DeltaTime<N> {
+    type Target = N;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<N: RealField> DerefMut for DeltaTime<N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<N: RealField> Default for DeltaTime<N> {
+    fn default() -> Self {
+        Self(N::zero())
+    }
+}
+
+/// The `StepBudget` limits the work the `PhysicsStepperSystem` may do per
+/// dispatch when a `DeltaTime` demands multiple substeps. Once either limit is
+/// reached, the remaining substeps are dropped rather than entering the
+/// "spiral of death" where physics takes longer every frame.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct StepBudget {
+    /// Maximum number of substeps per dispatch.
+    ///
+    /// default: `8`
+    pub max_substeps: usize,
+
+    /// Maximum wall-clock time spent stepping per dispatch. The first substep
+    /// is always executed.
+    ///
+    /// The number of substeps executed within a wall-clock limit depends on
+    /// the load of the host, which makes the simulation nondeterministic:
+    /// recorded inputs no longer replay bit-identically and lockstep peers
+    /// desync. Only set it if neither is needed.
+    ///
+    /// default: `None`
+    pub max_duration: Option<Duration>,
+}
+
+impl Default for StepBudget {
+    fn default() -> Self {
+        Self {
+            max_substeps: 8,
+            max_duration: None,
+        }
+    }
+}
+
+/// The `MassScaling` makes the `PhysicsStepperSystem` raise the masses of
+/// light dynamic bodies touching much heavier ones for the duration of each
+/// step, so that within a group of touching bodies no body is more than
+/// `max_ratio` times lighter than the heaviest one. This is a common trick to
+/// keep stacks stable, e.g. a light crate below a heavy one is otherwise
+/// pushed through the floor. The real masses are restored after each step.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MassScaling<N: RealField> {
+    /// The largest ratio between the heaviest and the lightest mass within a
+    /// group of touching bodies.
+    ///
+    /// default: `10.0`
+    pub max_ratio: N,
+}
+
+impl<N: RealField> Default for MassScaling<N> {
+    fn default() -> Self {
+        Self {
+            max_ratio: na::convert(10.0),
+        }
+    }
+}
+
+/// The `OverflowPolicy` decides what happens to the events exceeding the
+/// `EventCapacity`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum OverflowPolicy {
+    /// The oldest events of the dispatch are dropped, counted in the
+    /// `DroppedEventCounts` and reported as `DiagnosticKind::EventsDropped`.
+    DropOldest,
+    /// The `PhysicsStepperSystem` panics, for catching event floods during
+    /// development.
+    Panic,
+}
+
+/// The `EventCapacity` limits the number of `ContactEvent`s and
+/// `ProximityEvent`s the `PhysicsStepperSystem` writes per dispatch, so a
+/// single frame with a pile of colliders cannot grow the `EventChannel`s
+/// without bounds while readers lag behind. It also sets the initial capacity
+/// of the channels if inserted before the `PhysicsStepperSystem` is set up.
+/// Without this `Resource` the number of events is not limited.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct EventCapacity {
+    /// Maximum number of events per channel and dispatch.
+    ///
+    /// default: `1024`
+    pub capacity: usize,
+
+    /// What happens to the events exceeding the `capacity`.
+    ///
+    /// default: `OverflowPolicy::DropOldest`
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for EventCapacity {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            overflow: OverflowPolicy::DropOldest,
+        }
+    }
+}
+
 /// `Gravity` is a newtype for `Vector3`. It represents a constant
 /// acceleration affecting all physical objects in the scene.
 #[derive(Debug, PartialEq)]
@@ -91,6 +216,99 @@ impl Default for PhysicsProfilingEnabled {
     }
 }
 
+/// Disables the coalescing of the `ContactEvent`s and `ProximityEvent`s of
+/// all substeps of a dispatch, for consumers that need every event at the
+/// granularity of single substeps. By default, the `PhysicsStepperSystem`
+/// reports at most one `Started` and one `Stopped` event per pair of colliders
+/// and dispatch.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RawSubstepEvents(pub bool);
+
+impl Deref for RawSubstepEvents {
+    type Target = bool;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for RawSubstepEvents {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Default for RawSubstepEvents {
+    fn default() -> Self {
+        Self(false)
+    }
+}
+
+/// The `PoseEpsilon` is the distance, and angle in radians, below which a
+/// modified `Position` is not written to its `RigidBody`. Skipping these tiny
+/// changes saves the broad-phase updates of the attached colliders in scenes
+/// with many slowly drifting bodies, at the cost of losing movements that stay
+/// within the epsilon of the simulated pose. Defaults to zero, which applies
+/// every change.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PoseEpsilon<N: RealField>(pub N);
+
+impl<N: RealField> Deref for PoseEpsilon<N> {
+    type Target = N;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<N: RealField> DerefMut for PoseEpsilon<N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<N: RealField> Default for PoseEpsilon<N> {
+    fn default() -> Self {
+        Self(N::zero())
+    }
+}
+
+/// The `DeactivationMode` defines what happens to dynamic bodies outside the
+/// `ActivationRadius`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DeactivationMode {
+    /// Bodies are put to sleep; contacts with awake bodies still wake them up.
+    Sleep,
+    /// Bodies are disabled and ignored by the simulation entirely.
+    Disable,
+}
+
+/// The `ActivationRadius` enables the simulation level-of-detail of the
+/// `ApplyActivationRadiusSystem`. Dynamic bodies further than `radius` away
+/// from every `SimulationFocus` are deactivated, and reactivated once they
+/// come within `radius` again. The `hysteresis` is added to the radius for
+/// deactivation, so that bodies near the border do not toggle every frame.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ActivationRadius<N: RealField> {
+    pub radius: N,
+    /// default: `0.1 * radius`
+    pub hysteresis: N,
+    /// default: `DeactivationMode::Sleep`
+    pub mode: DeactivationMode,
+}
+
+impl<N: RealField> ActivationRadius<N> {
+    /// Creates a new `ActivationRadius` with the default `hysteresis` and
+    /// `mode`.
+    pub fn new(radius: N) -> Self {
+        Self {
+            radius,
+            hysteresis: radius * na::convert(0.1),
+            mode: DeactivationMode::Sleep,
+        }
+    }
+}
+
 /// Essentially identical to the nphysics IntegrationParameters struct except
 /// without the t and dt fields. Manages the details of physics integration.
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -154,16 +372,38 @@ pub struct PhysicsIntegrationParameters<N: RealField> {
     ///
     /// default: `3`
     pub max_position_iterations: usize,
+
+    /// Maximum speed with which penetrations are resolved, e.g. the overlaps
+    /// of a stack teleported into the ground. Limits the
+    /// `max_linear_correction` so that the corrections of all position solver
+    /// iterations of a step stay below this speed, which resolves heavy
+    /// overlaps smoothly over several steps instead of pushing the bodies
+    /// apart explosively. `None` leaves the `max_linear_correction` alone.
+    ///
+    /// default: `None`
+    pub max_depenetration_velocity: Option<N>,
 }
 
 impl<N: RealField> PhysicsIntegrationParameters<N> {
+    /// The `max_linear_correction` per position solver iteration, limited by
+    /// the `max_depenetration_velocity` for steps of length `dt`.
+    pub(crate) fn linear_correction(&self, dt: N) -> N {
+        match self.max_depenetration_velocity {
+            Some(velocity) => {
+                let iterations: N = na::convert(self.max_position_iterations.max(1) as f64);
+                self.max_linear_correction.min(velocity * dt / iterations)
+            }
+            None => self.max_linear_correction,
+        }
+    }
+
     pub(crate) fn apply(&self, to: &mut IntegrationParameters<N>) {
         to.erp = self.error_reduction_parameter;
         to.warmstart_coeff = self.warmstart_coefficient;
         to.restitution_velocity_threshold = self.restitution_velocity_threshold;
         to.allowed_linear_error = self.allowed_linear_error;
         to.allowed_angular_error = self.allowed_angular_error;
-        to.max_linear_correction = self.max_linear_correction;
+        to.max_linear_correction = self.linear_correction(to.dt);
         to.max_angular_correction = self.max_angular_correction;
         to.max_stabilization_multiplier = self.max_stabilization_multiplier;
         to.max_velocity_iterations = self.max_velocity_iterations;
@@ -178,7 +418,42 @@ impl<N: RealField> PartialEq<IntegrationParameters<N>> for PhysicsIntegrationPar
             && self.restitution_velocity_threshold == other.restitution_velocity_threshold
             && self.allowed_linear_error == other.allowed_linear_error
             && self.allowed_angular_error == other.allowed_angular_error
-            && self.max_linear_correction == other.max_linear_correction
+            && self.linear_correction(other.dt) == other.max_linear_correction
+            && self.max_angular_correction == other.max_angular_correction
+            && self.max_stabilization_multiplier == other.max_stabilization_multiplier
+            && self.max_velocity_iterations == other.max_velocity_iterations
+            && self.max_position_iterations == other.max_position_iterations
+    }
+}
+
+#[cfg(feature = "dim2")]
+impl<N: RealField> PhysicsIntegrationParameters<N> {
+    /// Applies the parameters to the integration of the 2D physics `World`.
+    pub(crate) fn apply_2d(&self, to: &mut nphysics2d::solver::IntegrationParameters<N>) {
+        to.erp = self.error_reduction_parameter;
+        to.warmstart_coeff = self.warmstart_coefficient;
+        to.restitution_velocity_threshold = self.restitution_velocity_threshold;
+        to.allowed_linear_error = self.allowed_linear_error;
+        to.allowed_angular_error = self.allowed_angular_error;
+        to.max_linear_correction = self.linear_correction(to.dt);
+        to.max_angular_correction = self.max_angular_correction;
+        to.max_stabilization_multiplier = self.max_stabilization_multiplier;
+        to.max_velocity_iterations = self.max_velocity_iterations;
+        to.max_position_iterations = self.max_position_iterations;
+    }
+}
+
+#[cfg(feature = "dim2")]
+impl<N: RealField> PartialEq<nphysics2d::solver::IntegrationParameters<N>>
+    for PhysicsIntegrationParameters<N>
+{
+    fn eq(&self, other: &nphysics2d::solver::IntegrationParameters<N>) -> bool {
+        self.error_reduction_parameter == other.erp
+            && self.warmstart_coefficient == other.warmstart_coeff
+            && self.restitution_velocity_threshold == other.restitution_velocity_threshold
+            && self.allowed_linear_error == other.allowed_linear_error
+            && self.allowed_angular_error == other.allowed_angular_error
+            && self.linear_correction(other.dt) == other.max_linear_correction
             && self.max_angular_correction == other.max_angular_correction
             && self.max_stabilization_multiplier == other.max_stabilization_multiplier
             && self.max_velocity_iterations == other.max_velocity_iterations
@@ -199,6 +474,82 @@ impl<N: RealField> Default for PhysicsIntegrationParameters<N> {
             max_stabilization_multiplier: na::convert(0.2),
             max_velocity_iterations: 8,
             max_position_iterations: 3,
+            max_depenetration_velocity: None,
+        }
+    }
+}
+
+/// The `CollisionMatrix` defines which collision groups interact with each
+/// other. Row `i` lists the groups that colliders in group `i` interact with;
+/// colliders in several groups interact with the union of their rows.
+/// Colliders which are not a member of any row keep their `CollisionGroups`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CollisionMatrix(pub Vec<Vec<usize>>);
+
+impl CollisionMatrix {
+    /// Replaces the whitelist of the given `CollisionGroups` with the union of
+    /// the rows of the groups they are a member of. Returns whether the
+    /// whitelist changed.
+    pub fn apply(&self, collision_groups: &mut CollisionGroups) -> bool {
+        let rows = self
+            .0
+            .iter()
+            .enumerate()
+            .filter(|(group, _)| collision_groups.is_member_of(*group))
+            .map(|(_, row)| row)
+            .collect::<Vec<_>>();
+        if rows.is_empty() {
+            return false;
+        }
+
+        let whitelist = rows.into_iter().flatten().cloned().collect::<Vec<_>>();
+
+        let changed = (0..=CollisionGroups::max_group_id()).any(|group| {
+            collision_groups.is_group_whitelisted(group) != whitelist.contains(&group)
+        });
+        if changed {
+            collision_groups.set_whitelist(&whitelist);
+        }
+        changed
+    }
+}
+
+/// Friction and restitution coefficients used for contacts between two
+/// materials, overriding the combination of their individual coefficients.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MaterialCoefficients<N: RealField> {
+    pub material1: MaterialId,
+    pub material2: MaterialId,
+    pub friction: Option<N>,
+    pub restitution: Option<N>,
+}
+
+/// The `PhysicsConfig` bundles all tunable simulation settings, e.g. as loaded
+/// from a configuration file. Whenever the `PhysicsConfig` `Resource` is
+/// replaced, the `ApplyPhysicsConfigSystem` applies the values that changed
+/// to the live physics `World`, which allows tuning the simulation without
+/// restarting the game.
+///
+/// The `Gravity`, `PhysicsProfilingEnabled` and
+/// `PhysicsIntegrationParameters` `Resource`s are overwritten with the values
+/// of the `PhysicsConfig` when they change.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhysicsConfig<N: RealField> {
+    pub gravity: Vector3<N>,
+    pub profiling_enabled: bool,
+    pub integration_parameters: PhysicsIntegrationParameters<N>,
+    pub collision_matrix: CollisionMatrix,
+    pub material_coefficients: Vec<MaterialCoefficients<N>>,
+}
+
+impl<N: RealField> Default for PhysicsConfig<N> {
+    fn default() -> Self {
+        Self {
+            gravity: Vector3::zeros(),
+            profiling_enabled: false,
+            integration_parameters: PhysicsIntegrationParameters::default(),
+            collision_matrix: CollisionMatrix::default(),
+            material_coefficients: Vec::new(),
         }
     }
 }
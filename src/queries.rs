@@ -0,0 +1,1311 @@
+//! # Queries module
+//! Scene queries against the colliders of the physics `World`. Queries are
+//! filtered by the `QueryGroups` of the `PhysicsCollider`s instead of their
+//! `CollisionGroups`, so what a query sees is independent of what collides.
+//! Ray casts report their hits as `QueryHit`s; `RayQuery`s cast the same ray
+//! every frame, e.g. for wheels or sensor whiskers, and reuse their last hit
+//! to narrow down the colliders to test.
+//! The `HitboxWindow` sweeps an attack shape over a time window and reports
+//! each `Entity` it hits once.
+//! The `CameraCollision` keeps third-person cameras from clipping into
+//! walls.
+
+use std::{cmp::Ordering, collections::HashSet, ops::Deref};
+
+use specs::{storage::MaskedStorage, Entity, Storage};
+
+use crate::{
+    colliders::{PhysicsCollider, QueryGroups, Shape},
+    handles::entity_from_user_data,
+    nalgebra::{self as na, Isometry3, Point3, RealField, Translation3, Vector3},
+    ncollide::{
+        bounding_volume::{BoundingVolume, AABB},
+        query::{self, Proximity, Ray, RayCast, RayIntersection},
+        shape::{Ball, FeatureId, HeightField, ShapeHandle},
+        world::CollisionGroups,
+    },
+    nphysics::object::{Body, BodyStatus, Collider, ColliderHandle},
+    Physics,
+};
+
+/// A collider hit by a query, e.g. a ray cast.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct QueryHit<N: RealField> {
+    /// The `Entity` owning the body the hit collider is attached to, which
+    /// differs from the `collider_entity` for colliders attached through a
+    /// `PhysicsParent`. Colliders without a body report their own `Entity`.
+    pub entity: Entity,
+    /// The `Entity` owning the hit collider.
+    pub collider_entity: Entity,
+    /// The hit point in world space.
+    pub point: Point3<N>,
+    /// The surface normal at the hit point.
+    pub normal: Vector3<N>,
+    /// The time of impact, i.e. the distance along the ray in multiples of
+    /// its direction.
+    pub toi: N,
+    /// The feature of the shape at the hit point, e.g. to tell hits on faces
+    /// from hits on edges when orienting decals. The ids are specific to the
+    /// shape and `FeatureId::Unknown` for shapes not reporting them.
+    pub feature: FeatureId,
+}
+
+/// The former name of `QueryHit`.
+#[deprecated(note = "renamed to `QueryHit`")]
+pub type RayHit<N> = QueryHit<N>;
+
+/// Casts the ray against all colliders whose `QueryGroups` intersect the
+/// given `QueryGroups` and returns the nearest hit within `max_toi`.
+///
+/// # Example
+///
+/// ```rust
+/// use specs::{World, WorldExt};
+/// use specs_physics::{
+///     colliders::{PhysicsCollider, QueryGroups},
+///     nalgebra::{Point3, Vector3},
+///     ncollide::query::Ray,
+///     queries,
+///     Physics,
+/// };
+///
+/// let mut world = World::new();
+/// world.register::<PhysicsCollider<f32>>();
+/// world.insert(Physics::<f32>::default());
+///
+/// let ray = Ray::new(Point3::origin(), Vector3::new(0.0, -1.0, 0.0));
+/// let hit = queries::cast_ray(
+///     &world.read_resource::<Physics<f32>>(),
+///     &world.read_storage::<PhysicsCollider<f32>>(),
+///     &ray,
+///     100.0,
+///     QueryGroups::ALL,
+/// );
+/// assert!(hit.is_none());
+/// ```
+pub fn cast_ray<N, D>(
+    physics: &Physics<N>,
+    physics_colliders: &Storage<PhysicsCollider<N>, D>,
+    ray: &Ray<N>,
+    max_toi: N,
+    query_groups: QueryGroups,
+) -> Option<QueryHit<N>>
+where
+    N: RealField,
+    D: Deref<Target = MaskedStorage<PhysicsCollider<N>>>,
+{
+    cast_ray_filtered(
+        physics,
+        physics_colliders,
+        ray,
+        max_toi,
+        query_groups,
+        |_| true,
+    )
+}
+
+/// Like `cast_ray`, but additionally skips the colliders of all `Entity`s the
+/// predicate returns `false` for, e.g. the shooter or friendly `Entity`s. The
+/// predicate is evaluated while the hits are traversed.
+pub fn cast_ray_filtered<N, D, F>(
+    physics: &Physics<N>,
+    physics_colliders: &Storage<PhysicsCollider<N>, D>,
+    ray: &Ray<N>,
+    max_toi: N,
+    query_groups: QueryGroups,
+    predicate: F,
+) -> Option<QueryHit<N>>
+where
+    N: RealField,
+    D: Deref<Target = MaskedStorage<PhysicsCollider<N>>>,
+    F: Fn(Entity) -> bool,
+{
+    // the default CollisionGroups interact with all groups, filtering is done
+    // by the QueryGroups and the predicate alone
+    let collision_groups = CollisionGroups::default();
+    ray_hits(physics, ray, &collision_groups, max_toi, |entity| {
+        predicate(entity) && in_query_groups(physics_colliders, entity, query_groups)
+    })
+    .min_by(compare_toi)
+}
+
+/// Casts the ray against all colliders whose `QueryGroups` intersect the
+/// given `QueryGroups` and returns all hits within `max_toi`, sorted by
+/// distance.
+pub fn cast_ray_all<N, D>(
+    physics: &Physics<N>,
+    physics_colliders: &Storage<PhysicsCollider<N>, D>,
+    ray: &Ray<N>,
+    max_toi: N,
+    query_groups: QueryGroups,
+) -> Vec<QueryHit<N>>
+where
+    N: RealField,
+    D: Deref<Target = MaskedStorage<PhysicsCollider<N>>>,
+{
+    cast_ray_all_filtered(
+        physics,
+        physics_colliders,
+        ray,
+        max_toi,
+        query_groups,
+        |_| true,
+    )
+}
+
+/// Like `cast_ray_all`, but additionally skips the colliders of all
+/// `Entity`s the predicate returns `false` for.
+pub fn cast_ray_all_filtered<N, D, F>(
+    physics: &Physics<N>,
+    physics_colliders: &Storage<PhysicsCollider<N>, D>,
+    ray: &Ray<N>,
+    max_toi: N,
+    query_groups: QueryGroups,
+    predicate: F,
+) -> Vec<QueryHit<N>>
+where
+    N: RealField,
+    D: Deref<Target = MaskedStorage<PhysicsCollider<N>>>,
+    F: Fn(Entity) -> bool,
+{
+    // see cast_ray_filtered
+    let collision_groups = CollisionGroups::default();
+    let mut hits = ray_hits(physics, ray, &collision_groups, max_toi, |entity| {
+        predicate(entity) && in_query_groups(physics_colliders, entity, query_groups)
+    })
+    .collect::<Vec<_>>();
+    hits.sort_by(compare_toi);
+    hits
+}
+
+/// Iterates the hits of the ray within `max_toi` whose collider `Entity`
+/// satisfies the predicate.
+pub(crate) fn ray_hits<'a, N, F>(
+    physics: &'a Physics<N>,
+    ray: &'a Ray<N>,
+    collision_groups: &'a CollisionGroups,
+    max_toi: N,
+    predicate: F,
+) -> impl Iterator<Item = QueryHit<N>> + 'a
+where
+    N: RealField,
+    F: Fn(Entity) -> bool + 'a,
+{
+    physics
+        .world
+        .collider_world()
+        .interferences_with_ray(ray, collision_groups)
+        .filter(move |(_, intersection)| intersection.toi <= max_toi)
+        .filter_map(move |(collider, intersection)| {
+            let collider_entity = entity_from_user_data(collider.user_data())?;
+            if !predicate(collider_entity) {
+                return None;
+            }
+            Some(query_hit(
+                physics,
+                collider,
+                collider_entity,
+                ray,
+                &intersection,
+            ))
+        })
+}
+
+/// Casts all rays against all colliders whose `QueryGroups` intersect the
+/// given `QueryGroups` and returns the nearest hit within `max_toi` of every
+/// ray. Every ray traverses the broad phase on its own, so rays spreading
+/// out over the scene, e.g. of a lidar, only test the colliders along their
+/// own path. With the "parallel" feature enabled the rays are cast on the
+/// rayon thread pool.
+pub(crate) fn batch_ray_hits<N, D>(
+    physics: &Physics<N>,
+    physics_colliders: &Storage<PhysicsCollider<N>, D>,
+    rays: &[Ray<N>],
+    max_toi: N,
+    query_groups: QueryGroups,
+) -> Vec<Option<QueryHit<N>>>
+where
+    N: RealField,
+    D: Deref<Target = MaskedStorage<PhysicsCollider<N>>> + Sync,
+{
+    // see cast_ray_filtered
+    let collision_groups = CollisionGroups::default();
+    let nearest = |ray: &Ray<N>| {
+        ray_hits(physics, ray, &collision_groups, max_toi, |entity| {
+            in_query_groups(physics_colliders, entity, query_groups)
+        })
+        .min_by(compare_toi)
+    };
+
+    #[cfg(feature = "parallel")]
+    {
+        use specs::rayon::prelude::*;
+        rays.par_iter().map(nearest).collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        rays.iter().map(nearest).collect()
+    }
+}
+
+/// The bounds of the segment of the ray up to `max_toi`.
+fn ray_aabb<N: RealField>(ray: &Ray<N>, max_toi: N) -> AABB<N> {
+    let (start, end) = (ray.origin.coords, ray.point_at(max_toi).coords);
+    AABB::new(Point3::from(start.inf(&end)), Point3::from(start.sup(&end)))
+}
+
+/// Assembles the `QueryHit` of the ray on the collider owned by the
+/// `collider_entity`.
+fn query_hit<N: RealField>(
+    physics: &Physics<N>,
+    collider: &Collider<N>,
+    collider_entity: Entity,
+    ray: &Ray<N>,
+    intersection: &RayIntersection<N>,
+) -> QueryHit<N> {
+    let entity = physics
+        .world
+        .rigid_body(collider.body())
+        .and_then(|rigid_body| entity_from_user_data(rigid_body.user_data()))
+        .unwrap_or(collider_entity);
+    QueryHit {
+        entity,
+        collider_entity,
+        point: ray.point_at(intersection.toi),
+        normal: intersection.normal,
+        toi: intersection.toi,
+        feature: intersection.feature,
+    }
+}
+
+/// Iterates the `Entity`s of the colliders interacting with the given
+/// `CollisionGroups` which overlap the shape at the given pose.
+pub(crate) fn overlapping<'a, N: RealField>(
+    physics: &'a Physics<N>,
+    shape_handle: &'a ShapeHandle<N>,
+    isometry: &'a Isometry3<N>,
+    collision_groups: &'a CollisionGroups,
+) -> impl Iterator<Item = Entity> + 'a {
+    let aabb = shape_handle.aabb(isometry);
+    physics
+        .world
+        .collider_world()
+        .interferences_with_aabb(&aabb, collision_groups)
+        .filter(move |collider| {
+            query::proximity(
+                isometry,
+                &**shape_handle,
+                collider.position(),
+                &**collider.shape(),
+                N::zero(),
+            ) == Proximity::Intersecting
+        })
+        .filter_map(|collider| entity_from_user_data(collider.user_data()))
+}
+
+/// Collects the dynamic bodies with a collider interacting with the given
+/// `CollisionGroups` in the broad phase around the `point`, and returns the
+/// `k` nearest within `radius` together with their distance, nearest first.
+/// Distances are measured to the origins of the bodies.
+pub(crate) fn nearest_bodies<N: RealField>(
+    physics: &Physics<N>,
+    point: &Point3<N>,
+    k: usize,
+    radius: N,
+    collision_groups: &CollisionGroups,
+) -> Vec<(Entity, N)> {
+    let extents = Vector3::repeat(radius);
+    let aabb = AABB::new(point - extents, point + extents);
+
+    let mut visited = HashSet::new();
+    let mut neighbors = physics
+        .world
+        .collider_world()
+        .interferences_with_aabb(&aabb, collision_groups)
+        .filter(|collider| visited.insert(collider.body()))
+        .filter_map(|collider| {
+            let rigid_body = physics.world.rigid_body(collider.body())?;
+            if rigid_body.status() != BodyStatus::Dynamic {
+                return None;
+            }
+            let distance = na::distance(
+                point,
+                &Point3::from(rigid_body.position().translation.vector),
+            );
+            if distance > radius {
+                return None;
+            }
+            Some((entity_from_user_data(rigid_body.user_data())?, distance))
+        })
+        .collect::<Vec<_>>();
+    neighbors.sort_by(|(_, distance1), (_, distance2)| {
+        distance1.partial_cmp(distance2).unwrap_or(Ordering::Equal)
+    });
+    neighbors.truncate(k);
+    neighbors
+}
+
+/// Orders `QueryHit`s by their distance along the ray.
+pub(crate) fn compare_toi<N: RealField>(hit1: &QueryHit<N>, hit2: &QueryHit<N>) -> Ordering {
+    hit1.toi.partial_cmp(&hit2.toi).unwrap_or(Ordering::Equal)
+}
+
+/// Checks whether the `PhysicsCollider` of the `Entity` is in one of the
+/// given `QueryGroups`.
+pub(crate) fn in_query_groups<N, D>(
+    physics_colliders: &Storage<PhysicsCollider<N>, D>,
+    entity: Entity,
+    query_groups: QueryGroups,
+) -> bool
+where
+    N: RealField,
+    D: Deref<Target = MaskedStorage<PhysicsCollider<N>>>,
+{
+    physics_colliders
+        .get(entity)
+        .map_or(false, |physics_collider| {
+            physics_collider.query_groups.intersects(query_groups)
+        })
+}
+
+/// A ray cast repeatedly, e.g. every frame from a wheel or a sensor whisker,
+/// given in the local space of the pose it is cast from. The `RayQuery`
+/// remembers the collider it hit last, which is likely hit again, e.g. the
+/// ground below a wheel: once that collider is hit, only the colliders in
+/// front of the hit need to be tested, so the broad phase is queried with the
+/// bounds of the shortened ray instead of the whole ray.
+///
+/// # Example
+///
+/// ```rust
+/// use specs::{World, WorldExt};
+/// use specs_physics::{
+///     colliders::{PhysicsCollider, QueryGroups},
+///     nalgebra::{Isometry3, Point3, Vector3},
+///     ncollide::query::Ray,
+///     queries::RayQuery,
+///     Physics,
+/// };
+///
+/// let mut world = World::new();
+/// world.register::<PhysicsCollider<f32>>();
+/// world.insert(Physics::<f32>::default());
+///
+/// // a suspension ray pointing down from the hub of a wheel
+/// let mut wheel_ray = RayQuery::new(
+///     Ray::new(Point3::origin(), Vector3::new(0.0, -1.0, 0.0)),
+///     0.6,
+///     QueryGroups::ALL,
+/// );
+/// let hit = wheel_ray.cast(
+///     &world.read_resource::<Physics<f32>>(),
+///     &world.read_storage::<PhysicsCollider<f32>>(),
+///     &Isometry3::translation(0.0, 0.5, 0.0),
+/// );
+/// assert!(hit.is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct RayQuery<N: RealField> {
+    ray: Ray<N>,
+    max_toi: N,
+    query_groups: QueryGroups,
+    last_hit: Option<ColliderHandle>,
+}
+
+impl<N: RealField> RayQuery<N> {
+    /// Creates a `RayQuery` casting the `ray`, given in local space, up to
+    /// `max_toi` against the colliders whose `QueryGroups` intersect the
+    /// given `QueryGroups`.
+    pub fn new(ray: Ray<N>, max_toi: N, query_groups: QueryGroups) -> Self {
+        Self {
+            ray,
+            max_toi,
+            query_groups,
+            last_hit: None,
+        }
+    }
+
+    /// Casts the ray from the given pose and returns the nearest hit, like
+    /// `cast_ray`.
+    pub fn cast<D>(
+        &mut self,
+        physics: &Physics<N>,
+        physics_colliders: &Storage<PhysicsCollider<N>, D>,
+        isometry: &Isometry3<N>,
+    ) -> Option<QueryHit<N>>
+    where
+        D: Deref<Target = MaskedStorage<PhysicsCollider<N>>>,
+    {
+        let ray = self.ray.transform_by(isometry);
+        let query_groups = self.query_groups;
+        let cast = |collider: &Collider<N>, max_toi: N| {
+            let collider_entity = entity_from_user_data(collider.user_data())?;
+            if !in_query_groups(physics_colliders, collider_entity, query_groups) {
+                return None;
+            }
+            let intersection = collider
+                .shape()
+                .toi_and_normal_with_ray(collider.position(), &ray, true)
+                .filter(|intersection| intersection.toi <= max_toi)?;
+            Some(query_hit(
+                physics,
+                collider,
+                collider_entity,
+                &ray,
+                &intersection,
+            ))
+        };
+
+        let mut nearest = None;
+        let mut max_toi = self.max_toi;
+        if let Some(collider) = self
+            .last_hit
+            .and_then(|handle| physics.world.collider(handle))
+        {
+            if let Some(hit) = cast(collider, max_toi) {
+                max_toi = hit.toi;
+                nearest = Some((collider.handle(), hit));
+            }
+        }
+
+        let collision_groups = CollisionGroups::default();
+        for collider in physics
+            .world
+            .collider_world()
+            .interferences_with_aabb(&ray_aabb(&ray, max_toi), &collision_groups)
+        {
+            if Some(collider.handle()) == self.last_hit {
+                continue;
+            }
+            if let Some(hit) = cast(collider, max_toi) {
+                if nearest.map_or(true, |(_, nearest)| hit.toi < nearest.toi) {
+                    max_toi = hit.toi;
+                    nearest = Some((collider.handle(), hit));
+                }
+            }
+        }
+
+        self.last_hit = nearest.map(|(handle, _)| handle);
+        nearest.map(|(_, hit)| hit)
+    }
+}
+
+/// A hitbox which is active over a window of frames, e.g. the active frames of
+/// a sword swing. Every frame the hitbox is swept from its previous to its
+/// current pose in a number of substeps, so fast attacks do not tunnel
+/// through thin targets, and each `Entity` is reported only once per
+/// activation.
+///
+/// # Example
+///
+/// ```rust
+/// use specs_physics::{
+///     colliders::Shape,
+///     nalgebra::{Isometry3, Vector3},
+///     ncollide::world::CollisionGroups,
+///     queries::HitboxWindow,
+///     Physics,
+/// };
+///
+/// let physics = Physics::<f32>::default();
+/// let mut hitbox = HitboxWindow::new(
+///     &Shape::Cuboid {
+///         half_extents: Vector3::new(0.1, 0.1, 0.5),
+///     },
+///     CollisionGroups::default(),
+/// );
+///
+/// hitbox.activate();
+/// let hits = hitbox.sweep(
+///     &physics,
+///     &Isometry3::translation(-1.0, 0.0, 0.0),
+///     &Isometry3::translation(1.0, 0.0, 0.0),
+/// );
+/// assert!(hits.is_empty());
+/// hitbox.deactivate();
+/// ```
+pub struct HitboxWindow<N: RealField> {
+    shape_handle: ShapeHandle<N>,
+    collision_groups: CollisionGroups,
+    substeps: usize,
+    hit: HashSet<Entity>,
+    active: bool,
+}
+
+impl<N: RealField> HitboxWindow<N> {
+    /// Creates a new, inactive `HitboxWindow` testing the given `Shape`
+    /// against the colliders interacting with the given `CollisionGroups`.
+    pub fn new(shape: &Shape<N>, collision_groups: CollisionGroups) -> Self {
+        Self {
+            shape_handle: shape.handle(),
+            collision_groups,
+            substeps: 4,
+            hit: HashSet::new(),
+            active: false,
+        }
+    }
+
+    /// Sets the number of substeps each sweep is divided into. Defaults to 4.
+    pub fn with_substeps(mut self, substeps: usize) -> Self {
+        self.substeps = substeps.max(1);
+        self
+    }
+
+    /// Opens the window, forgetting all `Entity`s hit during a previous
+    /// activation.
+    pub fn activate(&mut self) {
+        self.hit.clear();
+        self.active = true;
+    }
+
+    /// Closes the window; sweeps report no hits until the next activation.
+    pub fn deactivate(&mut self) {
+        self.active = false;
+    }
+
+    /// Whether the window is currently open.
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// The `Entity`s hit since the last activation.
+    pub fn hit(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.hit.iter().cloned()
+    }
+
+    /// Sweeps the hitbox from the `start` to the `end` pose and returns the
+    /// `Entity`s hit for the first time during this activation, in the order
+    /// they have been hit.
+    pub fn sweep(
+        &mut self,
+        physics: &Physics<N>,
+        start: &Isometry3<N>,
+        end: &Isometry3<N>,
+    ) -> Vec<Entity> {
+        let mut new_hits = Vec::new();
+        if !self.active {
+            return new_hits;
+        }
+
+        let substeps: N = na::convert(self.substeps as f64);
+        for substep in 0..=self.substeps {
+            let t = na::convert::<_, N>(substep as f64) / substeps;
+            let isometry = interpolate(start, end, t);
+            for entity in overlapping(
+                physics,
+                &self.shape_handle,
+                &isometry,
+                &self.collision_groups,
+            ) {
+                if self.hit.insert(entity) {
+                    new_hits.push(entity);
+                }
+            }
+        }
+
+        new_hits
+    }
+}
+
+fn interpolate<N: RealField>(start: &Isometry3<N>, end: &Isometry3<N>, t: N) -> Isometry3<N> {
+    let translation = start.translation.vector.lerp(&end.translation.vector, t);
+    // slerp is not defined for rotations almost half a turn apart
+    let rotation = start
+        .rotation
+        .try_slerp(&end.rotation, t, N::default_epsilon())
+        .unwrap_or(if t < na::convert(0.5) {
+            start.rotation
+        } else {
+            end.rotation
+        });
+    Isometry3::from_parts(Translation3::from(translation), rotation)
+}
+
+/// Keeps a third-person camera from clipping into geometry by sweeping a
+/// sphere of the given `radius` from the target, e.g. the head of the
+/// player, towards the desired camera position. The sphere is cast along the
+/// whole path at once, so thin walls are not skipped and walls running
+/// alongside the path do not slow the sweep down.
+///
+/// # Example
+///
+/// ```rust
+/// use specs::{World, WorldExt};
+/// use specs_physics::{
+///     colliders::{PhysicsCollider, QueryGroups},
+///     nalgebra::Point3,
+///     queries::CameraCollision,
+///     Physics,
+/// };
+///
+/// let mut world = World::new();
+/// world.register::<PhysicsCollider<f32>>();
+/// world.insert(Physics::<f32>::default());
+///
+/// let camera_collision = CameraCollision::new(0.2, QueryGroups::ALL);
+/// let distance = camera_collision.distance(
+///     &world.read_resource::<Physics<f32>>(),
+///     &world.read_storage::<PhysicsCollider<f32>>(),
+///     &Point3::new(0.0, 1.8, 0.0),
+///     &Point3::new(0.0, 3.0, -4.0),
+///     |_| true,
+/// );
+/// assert!(distance > 4.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct CameraCollision<N: RealField> {
+    radius: N,
+    query_groups: QueryGroups,
+}
+
+impl<N: RealField> CameraCollision<N> {
+    /// Creates a `CameraCollision` sweeping a sphere of the given `radius`
+    /// against the colliders whose `QueryGroups` intersect the given
+    /// `QueryGroups`.
+    pub fn new(radius: N, query_groups: QueryGroups) -> Self {
+        Self {
+            radius,
+            query_groups,
+        }
+    }
+
+    pub fn radius(&self) -> N {
+        self.radius
+    }
+
+    /// Sweeps the sphere from the `target` towards the `desired` camera
+    /// position and returns the distance from the `target` the camera can be
+    /// placed at without clipping, up to the full distance to the `desired`
+    /// position. Colliders of `Entity`s the predicate returns `false` for,
+    /// e.g. the player, are ignored.
+    pub fn distance<D, F>(
+        &self,
+        physics: &Physics<N>,
+        physics_colliders: &Storage<PhysicsCollider<N>, D>,
+        target: &Point3<N>,
+        desired: &Point3<N>,
+        predicate: F,
+    ) -> N
+    where
+        D: Deref<Target = MaskedStorage<PhysicsCollider<N>>>,
+        F: Fn(Entity) -> bool,
+    {
+        let motion = desired - target;
+        let length = motion.norm();
+        if length <= N::default_epsilon() {
+            return N::zero();
+        }
+
+        // only the colliders along the swept sphere are tested
+        let extents = Vector3::repeat(self.radius);
+        let mut aabb = AABB::new(target - extents, target + extents);
+        aabb.merge(&AABB::new(desired - extents, desired + extents));
+        let collision_groups = CollisionGroups::default();
+
+        // the time of impact is measured in fractions of the motion
+        let ball = Ball::new(self.radius);
+        let isometry = Isometry3::translation(target.x, target.y, target.z);
+        let toi = physics
+            .world
+            .collider_world()
+            .interferences_with_aabb(&aabb, &collision_groups)
+            .filter(|collider| {
+                entity_from_user_data(collider.user_data()).map_or(false, |entity| {
+                    predicate(entity)
+                        && in_query_groups(physics_colliders, entity, self.query_groups)
+                })
+            })
+            .filter_map(|collider| sweep_toi(&ball, &isometry, &motion, collider))
+            .fold(N::one(), N::min);
+        length * toi.max(N::zero())
+    }
+}
+
+/// Computes the time of impact of the ball at the given pose moving by
+/// `motion` with the collider, in fractions of the motion. ncollide computes
+/// no time of impact against height fields, so the center of the ball is cast
+/// as a ray against them instead and stopped a radius short of the hit.
+fn sweep_toi<N: RealField>(
+    ball: &Ball<N>,
+    isometry: &Isometry3<N>,
+    motion: &Vector3<N>,
+    collider: &Collider<N>,
+) -> Option<N> {
+    let shape = &**collider.shape();
+    if shape.is_shape::<HeightField<N>>() {
+        let ray = Ray::new(Point3::from(isometry.translation.vector), *motion);
+        let toi = shape.toi_with_ray(collider.position(), &ray, true)?;
+        return Some(toi - ball.radius() / motion.norm());
+    }
+    query::time_of_impact(
+        isometry,
+        motion,
+        ball,
+        collider.position(),
+        &Vector3::zeros(),
+        shape,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use super::{cast_ray, cast_ray_filtered, CameraCollision, HitboxWindow, RayQuery};
+    use crate::{
+        colliders::{PhysicsCollider, QueryGroups, Shape},
+        nalgebra::{Isometry3, Matrix4, Perspective3, Point2, Point3, Vector2, Vector3},
+        ncollide::{query::Ray, world::CollisionGroups},
+        nphysics::object::BodyStatus,
+        Physics,
+        PhysicsBodyBuilder,
+        PhysicsColliderBuilder,
+        PhysicsHandles,
+        SimplePosition,
+    };
+
+    #[test]
+    fn reuse_last_hit() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+
+        // a crate standing on the floor below the ray
+        let floor = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::ground()).build())
+            .build();
+        let crate_entity = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(0.0, 1.0, 0.0)))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Cuboid {
+                    half_extents: Vector3::new(0.5, 0.5, 0.5),
+                })
+                .build(),
+            )
+            .build();
+        dispatcher.dispatch(&world);
+
+        let mut ray_query = RayQuery::new(
+            Ray::new(Point3::origin(), -Vector3::y()),
+            10.0,
+            QueryGroups::ALL,
+        );
+        let mut cast = |world: &World| {
+            ray_query
+                .cast(
+                    &world.read_resource::<Physics<f32>>(),
+                    &world.read_storage::<PhysicsCollider<f32>>(),
+                    &Isometry3::translation(0.0, 5.0, 0.0),
+                )
+                .map(|hit| (hit.collider_entity, hit.toi))
+        };
+        for _ in 0..2 {
+            let (entity, toi) = cast(&world).unwrap();
+            assert_eq!(entity, crate_entity);
+            assert!((toi - 3.5).abs() < 1.0e-4);
+        }
+
+        // the last hit is gone, the ray continues to the floor
+        world.delete_entity(crate_entity).unwrap();
+        world.maintain();
+        dispatcher.dispatch(&world);
+        let (entity, toi) = cast(&world).unwrap();
+        assert_eq!(entity, floor);
+        assert!((toi - 5.0).abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn filter_ray_cast_all() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+
+        // a trigger volume in a hidden group hovering above the floor
+        let floor = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::ground()).build())
+            .build();
+        let trigger = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(0.0, 2.0, 0.0)))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 })
+                    .sensor(true)
+                    .query_groups(QueryGroups::NONE.with(1))
+                    .build(),
+            )
+            .build();
+        dispatcher.dispatch(&world);
+
+        let physics = world.read_resource::<Physics<f32>>();
+        let physics_colliders = world.read_storage::<PhysicsCollider<f32>>();
+        let hits = |query_groups| {
+            physics
+                .ray_cast_all(
+                    &physics_colliders,
+                    Point3::new(0.0, 5.0, 0.0),
+                    -Vector3::y(),
+                    10.0,
+                    query_groups,
+                )
+                .into_iter()
+                .map(|hit| hit.collider_entity)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(hits(QueryGroups::ALL), vec![trigger, floor]);
+        assert_eq!(hits(QueryGroups::NONE.with(0)), vec![floor]);
+    }
+
+    #[test]
+    fn cast_ray_batch() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+
+        let floor = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::ground()).build())
+            .build();
+        let ball = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(2.0, 1.0, 0.0)))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .build();
+        dispatcher.dispatch(&world);
+
+        // rays straight down next to, onto and far above the ball
+        let rays = vec![
+            Ray::new(Point3::new(0.0, 5.0, 0.0), -Vector3::y()),
+            Ray::new(Point3::new(2.0, 5.0, 0.0), -Vector3::y()),
+            Ray::new(Point3::new(2.0, 50.0, 0.0), -Vector3::y()),
+        ];
+        let physics = world.read_resource::<Physics<f32>>();
+        let physics_colliders = world.read_storage::<PhysicsCollider<f32>>();
+        let hits = |rays: &[Ray<f32>], query_groups| {
+            physics
+                .ray_cast_batch(&physics_colliders, rays, 10.0, query_groups)
+                .into_iter()
+                .map(|hit| hit.map(|hit| hit.collider_entity))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            hits(&rays, QueryGroups::ALL),
+            vec![Some(floor), Some(ball), None]
+        );
+        assert!(hits(&[], QueryGroups::ALL).is_empty());
+
+        // the rays pass through colliders outside the QueryGroups
+        assert_eq!(hits(&rays[..2], QueryGroups::NONE), vec![None, None]);
+    }
+
+    #[test]
+    fn block_sight_by_query_groups() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+
+        // a listener and an emitter on both sides of a wall in group 1
+        let listener = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(0.0, 1.0, 0.0)))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .build();
+        let emitter = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(
+                0.0, 1.0, 10.0,
+            )))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .build();
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(0.0, 1.0, 5.0)))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Cuboid {
+                    half_extents: Vector3::new(5.0, 5.0, 0.5),
+                })
+                .query_groups(QueryGroups::NONE.with(1))
+                .build(),
+            )
+            .build();
+        dispatcher.dispatch(&world);
+
+        let physics = world.read_resource::<Physics<f32>>();
+        let handles = world.read_resource::<PhysicsHandles>();
+        let physics_colliders = world.read_storage::<PhysicsCollider<f32>>();
+        let blocked = QueryGroups::NONE.with(1);
+        let passed = QueryGroups::NONE.with(2);
+        assert!(!physics.line_of_sight(&handles, &physics_colliders, listener, emitter, blocked));
+        assert!(physics.line_of_sight(&handles, &physics_colliders, listener, emitter, passed));
+
+        let occlusion = |blocking_groups| {
+            physics
+                .audio_occlusion(
+                    &handles,
+                    &physics_colliders,
+                    listener,
+                    emitter,
+                    blocking_groups,
+                    0.25,
+                )
+                .unwrap()
+        };
+        assert!((occlusion(blocked) - 1.0).abs() < 1.0e-6);
+        assert!(occlusion(passed).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn find_nearest_bodies() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+
+        // a row of floating agents and a static obstacle closer than the
+        // second nearest agent
+        let agents = (0..5)
+            .map(|i| {
+                world
+                    .create_entity()
+                    .with(SimplePosition::<f32>(Isometry3::translation(
+                        i as f32 * 2.0,
+                        5.0,
+                        0.0,
+                    )))
+                    .with(
+                        PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                            .gravity_enabled(false)
+                            .build(),
+                    )
+                    .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+                    .build()
+            })
+            .collect::<Vec<_>>();
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(4.5, 5.0, 1.0)))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Static).build())
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .build();
+        dispatcher.dispatch(&world);
+
+        let physics = world.read_resource::<Physics<f32>>();
+        let neighbors = physics.nearest_bodies(
+            &Point3::new(4.5, 5.0, 0.0),
+            2,
+            5.0,
+            &CollisionGroups::default(),
+        );
+        assert_eq!(neighbors.len(), 2);
+        assert_eq!(neighbors[0].0, agents[2]);
+        assert_eq!(neighbors[1].0, agents[3]);
+        assert!((neighbors[0].1 - 0.5).abs() < 1.0e-3);
+    }
+
+    #[test]
+    fn stop_camera_at_wall() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+
+        // the player around the target and a wall behind it
+        let player = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(0.0, 1.0, 0.0)))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .build();
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(0.0, 1.0, 5.0)))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Cuboid {
+                    half_extents: Vector3::new(5.0, 5.0, 0.5),
+                })
+                .build(),
+            )
+            .build();
+        dispatcher.dispatch(&world);
+
+        let camera_collision = CameraCollision::new(0.25, QueryGroups::ALL);
+        let physics = world.read_resource::<Physics<f32>>();
+        let physics_colliders = world.read_storage::<PhysicsCollider<f32>>();
+        let distance = |desired: Point3<f32>| {
+            camera_collision.distance(
+                &physics,
+                &physics_colliders,
+                &Point3::new(0.0, 1.0, 0.0),
+                &desired,
+                |entity| entity != player,
+            )
+        };
+        assert!((distance(Point3::new(0.0, 1.0, 10.0)) - 4.25).abs() < 0.01);
+        assert_eq!(distance(Point3::new(0.0, 1.0, -10.0)), 10.0);
+    }
+
+    #[test]
+    fn pass_camera_along_wall() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+
+        // a long wall running alongside the path, just clear of the sphere
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(
+                10.0, 1.0, 0.8,
+            )))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Cuboid {
+                    half_extents: Vector3::new(10.0, 5.0, 0.5),
+                })
+                .build(),
+            )
+            .build();
+        dispatcher.dispatch(&world);
+
+        let camera_collision = CameraCollision::new(0.25, QueryGroups::ALL);
+        let distance = camera_collision.distance(
+            &world.read_resource::<Physics<f32>>(),
+            &world.read_storage::<PhysicsCollider<f32>>(),
+            &Point3::new(0.0, 1.0, 0.0),
+            &Point3::new(20.0, 1.0, 0.0),
+            |_| true,
+        );
+        assert!((distance - 20.0).abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn filter_ray_cast_by_query_groups() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+
+        // a ball in group 1 hovering above a floor in group 0
+        let floor = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::ground())
+                    .query_groups(QueryGroups::NONE.with(0))
+                    .build(),
+            )
+            .build();
+        let ball = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(0.0, 2.0, 0.0)))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 })
+                    .query_groups(QueryGroups::NONE.with(1))
+                    .build(),
+            )
+            .build();
+        dispatcher.dispatch(&world);
+
+        let physics = world.read_resource::<Physics<f32>>();
+        let physics_colliders = world.read_storage::<PhysicsCollider<f32>>();
+        let hit = |query_groups| {
+            let ray = Ray::new(Point3::new(0.0, 5.0, 0.0), -Vector3::y());
+            cast_ray(&physics, &physics_colliders, &ray, 10.0, query_groups)
+                .map(|hit| (hit.collider_entity, hit.toi))
+        };
+
+        let (entity, toi) = hit(QueryGroups::ALL).unwrap();
+        assert_eq!(entity, ball);
+        assert!((toi - 2.5).abs() < 1.0e-4);
+
+        // the ray passes through the ball outside of the QueryGroups
+        let (entity, toi) = hit(QueryGroups::NONE.with(0)).unwrap();
+        assert_eq!(entity, floor);
+        assert!((toi - 5.0).abs() < 1.0e-4);
+
+        assert_eq!(hit(QueryGroups::NONE.with(2)), None);
+    }
+
+    #[test]
+    fn skip_shooter_in_ray_cast() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+
+        // the ray starts within the collider of the shooter
+        let shooter = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .build();
+        let target = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(5.0, 0.0, 0.0)))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .build();
+        dispatcher.dispatch(&world);
+
+        let physics = world.read_resource::<Physics<f32>>();
+        let physics_colliders = world.read_storage::<PhysicsCollider<f32>>();
+        let shot = Ray::new(Point3::origin(), Vector3::x());
+        let hit = |ray: &Ray<f32>, predicate: &dyn Fn(Entity) -> bool| {
+            cast_ray_filtered(
+                &physics,
+                &physics_colliders,
+                ray,
+                10.0,
+                QueryGroups::ALL,
+                predicate,
+            )
+            .map(|hit| (hit.collider_entity, hit.toi))
+        };
+
+        let unfiltered = cast_ray(&physics, &physics_colliders, &shot, 10.0, QueryGroups::ALL);
+        assert_eq!(unfiltered.unwrap().collider_entity, shooter);
+
+        let (entity, toi) = hit(&shot, &|entity| entity != shooter).unwrap();
+        assert_eq!(entity, target);
+        assert!((toi - 4.5).abs() < 1.0e-4);
+
+        // nothing but the shooter lies behind it
+        let backwards = Ray::new(Point3::origin(), -Vector3::x());
+        assert_eq!(hit(&backwards, &|entity| entity != shooter), None);
+        assert_eq!(
+            hit(&shot, &|entity| entity != shooter && entity != target),
+            None
+        );
+    }
+
+    #[test]
+    fn pick_through_camera() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+
+        let ball = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 1.0 }).build())
+            .build();
+        dispatcher.dispatch(&world);
+
+        // a camera ten units in front of the ball, looking at it
+        let view = Isometry3::look_at_rh(
+            &Point3::new(0.0, 0.0, 10.0),
+            &Point3::origin(),
+            &Vector3::y(),
+        )
+        .to_homogeneous();
+        let proj = Perspective3::new(1.0, std::f32::consts::FRAC_PI_2, 0.1, 100.0).to_homogeneous();
+        let viewport = Vector2::new(800.0, 800.0);
+
+        let physics = world.read_resource::<Physics<f32>>();
+        let physics_colliders = world.read_storage::<PhysicsCollider<f32>>();
+        let pick = |screen_pos, view: &Matrix4<f32>, query_groups| {
+            physics.pick(
+                &physics_colliders,
+                screen_pos,
+                view,
+                &proj,
+                viewport,
+                query_groups,
+            )
+        };
+
+        let center = Point2::new(400.0, 400.0);
+        let (entity, point) = pick(center, &view, QueryGroups::ALL).unwrap();
+        assert_eq!(entity, ball);
+        assert!((point - Point3::new(0.0, 0.0, 1.0)).norm() < 1.0e-3);
+
+        // the corner of the screen shows nothing but empty space
+        assert_eq!(pick(Point2::origin(), &view, QueryGroups::ALL), None);
+        assert_eq!(pick(center, &view, QueryGroups::NONE), None);
+        assert_eq!(pick(center, &Matrix4::zeros(), QueryGroups::ALL), None);
+    }
+
+    #[test]
+    fn find_overlapping_entities() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+
+        // two balls in different collision groups, three units apart
+        let near = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 })
+                    .collision_groups(CollisionGroups::new().with_membership(&[0]))
+                    .build(),
+            )
+            .build();
+        let far = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(3.0, 0.0, 0.0)))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 })
+                    .collision_groups(CollisionGroups::new().with_membership(&[1]))
+                    .build(),
+            )
+            .build();
+        dispatcher.dispatch(&world);
+
+        let physics = world.read_resource::<Physics<f32>>();
+        let overlaps = |radius, x, collision_groups: CollisionGroups| {
+            let mut overlaps = physics.overlaps(
+                &Shape::Ball { radius },
+                &Isometry3::translation(x, 0.0, 0.0),
+                &collision_groups,
+            );
+            overlaps.sort();
+            overlaps
+        };
+
+        assert_eq!(overlaps(1.0, 0.5, CollisionGroups::new()), vec![near]);
+        assert_eq!(overlaps(1.2, 1.5, CollisionGroups::new()), vec![near, far]);
+        assert_eq!(overlaps(1.0, 10.0, CollisionGroups::new()), vec![]);
+
+        // the blacklisted group is ignored even when overlapping
+        let blacklist = CollisionGroups::new().with_blacklist(&[1]);
+        assert_eq!(overlaps(1.2, 1.5, blacklist), vec![near]);
+    }
+
+    #[test]
+    fn report_hitbox_hits_once() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+
+        let first = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(1.5, 0.0, 0.0)))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .build();
+        let second = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(5.0, 0.0, 0.0)))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .build();
+        dispatcher.dispatch(&world);
+
+        // the hitbox overlaps each ball during several substeps of the sweep
+        let physics = world.read_resource::<Physics<f32>>();
+        let start = Isometry3::identity();
+        let end = Isometry3::translation(6.0, 0.0, 0.0);
+        let mut hitbox = HitboxWindow::new(&Shape::Ball { radius: 1.2 }, CollisionGroups::new());
+        assert!(hitbox.sweep(&physics, &start, &end).is_empty());
+
+        hitbox.activate();
+        assert_eq!(hitbox.sweep(&physics, &start, &end), vec![first, second]);
+        assert!(hitbox.sweep(&physics, &end, &start).is_empty());
+        let mut hit = hitbox.hit().collect::<Vec<_>>();
+        hit.sort();
+        assert_eq!(hit, vec![first, second]);
+
+        hitbox.deactivate();
+        assert!(hitbox.sweep(&physics, &start, &end).is_empty());
+
+        // a new activation forgets the previous hits
+        hitbox.activate();
+        assert_eq!(hitbox.sweep(&physics, &end, &start), vec![second, first]);
+    }
+}
@@ -0,0 +1,236 @@
+//! # Recording module
+//! Recording and deterministic replay of everything that is fed into the
+//! physics `World`. The `RecordPhysicsInputsSystem` captures the `Position`,
+//! `PhysicsBody` and `PhysicsCollider` changes, the queued `PhysicsCommands`
+//! as well as the `TimeStep`, `DeltaTime` and `PhysicsConfig` of every frame
+//! into the `PhysicsRecording` `Resource`. A
+//! `PhysicsReplayer` feeds a recording back into a fresh `World` frame by
+//! frame, which reproduces the recorded simulation exactly.
+
+use std::collections::HashMap;
+
+use specs::{world::Index, Entity, World, WorldExt};
+
+use crate::{
+    bodies::{PhysicsBody, Position},
+    colliders::PhysicsCollider,
+    commands::{PhysicsCommand, PhysicsCommands},
+    nalgebra::{Isometry3, RealField},
+    parameters::{DeltaTime, PhysicsConfig, TimeStep},
+};
+
+/// A single change to the inputs of the physics `World`, identified by the
+/// index of the recorded `Entity`.
+#[derive(Clone, Debug)]
+pub enum PhysicsInput<N: RealField> {
+    /// The `Position` of a physics `Entity` was inserted or modified.
+    PositionSet {
+        id: Index,
+        isometry: Isometry3<N>,
+    },
+    /// A `PhysicsBody` was inserted or modified, including the external forces
+    /// applied to it.
+    BodySet {
+        id: Index,
+        physics_body: PhysicsBody<N>,
+    },
+    BodyRemoved {
+        id: Index,
+    },
+    /// A `PhysicsCollider` was inserted or modified.
+    ColliderSet {
+        id: Index,
+        physics_collider: PhysicsCollider<N>,
+    },
+    ColliderRemoved {
+        id: Index,
+    },
+    /// A `PhysicsCommand` was queued for the body of the `Entity`.
+    Command {
+        id: Index,
+        command: PhysicsCommand<N>,
+    },
+}
+
+impl<N: RealField> PhysicsInput<N> {
+    /// The index of the recorded `Entity` this input belongs to.
+    pub fn id(&self) -> Index {
+        match self {
+            PhysicsInput::PositionSet { id, .. }
+            | PhysicsInput::BodySet { id, .. }
+            | PhysicsInput::BodyRemoved { id }
+            | PhysicsInput::ColliderSet { id, .. }
+            | PhysicsInput::ColliderRemoved { id }
+            | PhysicsInput::Command { id, .. } => *id,
+        }
+    }
+}
+
+/// All inputs of a single frame, in the order they were recorded. The
+/// `PhysicsConfig` is only recorded in frames in which it changed.
+#[derive(Clone, Debug)]
+pub struct RecordedFrame<N: RealField> {
+    pub time_step: Option<N>,
+    pub delta_time: Option<N>,
+    pub config: Option<PhysicsConfig<N>>,
+    pub inputs: Vec<PhysicsInput<N>>,
+}
+
+/// The `PhysicsRecording` `Resource` is filled by the
+/// `RecordPhysicsInputsSystem` with one `RecordedFrame` per frame.
+#[derive(Clone, Debug)]
+pub struct PhysicsRecording<N: RealField> {
+    pub frames: Vec<RecordedFrame<N>>,
+}
+
+impl<N: RealField> Default for PhysicsRecording<N> {
+    fn default() -> Self {
+        Self { frames: Vec::new() }
+    }
+}
+
+/// The `PhysicsReplayer` feeds a `PhysicsRecording` back into a `World`. For
+/// an exact reproduction the `World` should only contain the physics `System`s
+/// and no physics `Entity`s besides the replayed ones.
+///
+/// # Example
+///
+/// ```rust
+/// use specs::{World, WorldExt};
+/// use specs_physics::{
+///     recording::{PhysicsRecording, PhysicsReplayer},
+///     SimplePosition,
+/// };
+///
+/// let mut world = World::new();
+/// let mut dispatcher = specs_physics::physics_dispatcher::<f32, SimplePosition<f32>>();
+/// dispatcher.setup(&mut world);
+///
+/// let mut replayer = PhysicsReplayer::new(PhysicsRecording::<f32>::default());
+/// while replayer.replay_frame(&mut world, SimplePosition) {
+///     dispatcher.dispatch(&world);
+///     world.maintain();
+/// }
+/// ```
+pub struct PhysicsReplayer<N: RealField> {
+    recording: PhysicsRecording<N>,
+    next_frame: usize,
+    // the replayed Entities of the recorded indices
+    entities: HashMap<Index, Entity>,
+}
+
+impl<N: RealField> PhysicsReplayer<N> {
+    /// Creates a new `PhysicsReplayer` starting at the first frame of the
+    /// `PhysicsRecording`.
+    pub fn new(recording: PhysicsRecording<N>) -> Self {
+        Self {
+            recording,
+            next_frame: 0,
+            entities: HashMap::new(),
+        }
+    }
+
+    /// Checks whether all frames have been replayed.
+    pub fn is_finished(&self) -> bool {
+        self.next_frame >= self.recording.frames.len()
+    }
+
+    /// Returns the replayed `Entity` of the given recorded index.
+    pub fn entity(&self, id: Index) -> Option<Entity> {
+        self.entities.get(&id).cloned()
+    }
+
+    /// Applies the inputs of the next frame to the given `World`, which then
+    /// has to be dispatched once. The `position` function is used to create
+    /// `Position` `Component`s for the replayed `Entity`s. Returns `false`
+    /// once all frames have been replayed.
+    pub fn replay_frame<P, F>(&mut self, world: &mut World, position: F) -> bool
+    where
+        P: Position<N>,
+        F: Fn(Isometry3<N>) -> P,
+    {
+        let frame = match self.recording.frames.get(self.next_frame) {
+            Some(frame) => frame,
+            None => return false,
+        };
+        self.next_frame += 1;
+
+        if let Some(time_step) = frame.time_step {
+            world.insert(TimeStep(time_step));
+        }
+        if let Some(delta_time) = frame.delta_time {
+            world.insert(DeltaTime(delta_time));
+        }
+        if let Some(config) = &frame.config {
+            world.insert(config.clone());
+        }
+        world
+            .entry::<PhysicsCommands<N>>()
+            .or_insert_with(PhysicsCommands::default);
+
+        let entities = world.entities();
+        let mut positions = world.write_storage::<P>();
+        let mut physics_bodies = world.write_storage::<PhysicsBody<N>>();
+        let mut physics_colliders = world.write_storage::<PhysicsCollider<N>>();
+        let commands = world.read_resource::<PhysicsCommands<N>>();
+
+        for input in &frame.inputs {
+            let entity = *self
+                .entities
+                .entry(input.id())
+                .or_insert_with(|| entities.create());
+
+            // the handles of the replayed World are kept, as they are assigned by the
+            // synchronisation Systems
+            match input {
+                PhysicsInput::PositionSet { isometry, .. } => match positions.get_mut(entity) {
+                    Some(existing) => {
+                        existing.set_isometry(isometry);
+                    }
+                    None => {
+                        positions.insert(entity, position(*isometry)).unwrap();
+                    }
+                },
+                PhysicsInput::BodySet { physics_body, .. } => {
+                    match physics_bodies.get_mut(entity) {
+                        Some(existing) => {
+                            let handle = existing.handle;
+                            *existing = *physics_body;
+                            existing.handle = handle;
+                        }
+                        None => {
+                            let mut physics_body = *physics_body;
+                            physics_body.handle = None;
+                            physics_bodies.insert(entity, physics_body).unwrap();
+                        }
+                    }
+                }
+                PhysicsInput::BodyRemoved { .. } => {
+                    physics_bodies.remove(entity);
+                }
+                PhysicsInput::ColliderSet {
+                    physics_collider, ..
+                } => match physics_colliders.get_mut(entity) {
+                    Some(existing) => {
+                        let handle = existing.handle;
+                        *existing = physics_collider.clone();
+                        existing.handle = handle;
+                    }
+                    None => {
+                        let mut physics_collider = physics_collider.clone();
+                        physics_collider.handle = None;
+                        physics_colliders.insert(entity, physics_collider).unwrap();
+                    }
+                },
+                PhysicsInput::ColliderRemoved { .. } => {
+                    physics_colliders.remove(entity);
+                }
+                PhysicsInput::Command { command, .. } => {
+                    commands.push(command.with_entity(entity));
+                }
+            }
+        }
+
+        true
+    }
+}
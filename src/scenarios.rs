@@ -0,0 +1,292 @@
+//! # Scenarios module
+//! Generators for standard stress scenes. Every `Scenario` is fully
+//! deterministic, so the same parameters always produce the exact same
+//! `Entity`s and `Component`s, which allows comparing the performance of
+//! different versions with identical inputs.
+
+use specs::{Builder, Entity, World, WorldExt};
+
+use crate::{
+    bodies::{PhysicsBody, Position},
+    colliders::{PhysicsCollider, Shape},
+    nalgebra::{self as na, Isometry3, RealField, Vector3},
+    nphysics::object::BodyStatus,
+    PhysicsBodyBuilder,
+    PhysicsColliderBuilder,
+};
+
+/// A single object of a `Scenario`, consisting of the initial placement, the
+/// `PhysicsBody` and the `PhysicsCollider`.
+#[derive(Clone)]
+pub struct ScenarioObject<N: RealField> {
+    pub isometry: Isometry3<N>,
+    pub physics_body: PhysicsBody<N>,
+    pub physics_collider: PhysicsCollider<N>,
+}
+
+/// A `Scenario` is a list of `ScenarioObject`s which can be spawned into a
+/// Specs `World`.
+///
+/// # Example
+///
+/// ```rust
+/// use specs::{World, WorldExt};
+/// use specs_physics::{scenarios::Scenario, SimplePosition};
+///
+/// let mut world = World::new();
+/// let mut dispatcher = specs_physics::physics_dispatcher::<f32, SimplePosition<f32>>();
+/// dispatcher.setup(&mut world);
+///
+/// let entities = Scenario::<f32>::box_stack(4, 10, 0.5).spawn(&mut world, SimplePosition);
+/// assert_eq!(entities.len(), 4 * 4 * 10 + 1);
+/// ```
+#[derive(Clone)]
+pub struct Scenario<N: RealField> {
+    pub objects: Vec<ScenarioObject<N>>,
+}
+
+impl<N: RealField> Scenario<N> {
+    /// Creates an empty `Scenario` with a static ground object at the origin.
+    pub fn with_ground() -> Self {
+        let mut scenario = Self {
+            objects: Vec::new(),
+        };
+        scenario.push(
+            Isometry3::translation(N::zero(), na::convert(-0.5), N::zero()),
+            PhysicsBodyBuilder::from(BodyStatus::Static).build(),
+            Shape::Cuboid {
+                half_extents: Vector3::new(
+                    na::convert(100.0),
+                    na::convert(0.5),
+                    na::convert(100.0),
+                ),
+            },
+        );
+        scenario
+    }
+
+    /// Creates a stack of `width * width * height` cubes with the given
+    /// `half_extent`, resting on a static ground.
+    pub fn box_stack(width: usize, height: usize, half_extent: N) -> Self {
+        let mut scenario = Self::with_ground();
+        let size = half_extent + half_extent;
+        let offset = size * na::convert::<f64, N>(width as f64) / na::convert(2.0);
+
+        for y in 0..height {
+            for x in 0..width {
+                for z in 0..width {
+                    scenario.push(
+                        Isometry3::translation(
+                            size * na::convert::<f64, N>(x as f64) - offset,
+                            half_extent + size * na::convert::<f64, N>(y as f64),
+                            size * na::convert::<f64, N>(z as f64) - offset,
+                        ),
+                        dynamic_body(),
+                        Shape::Cuboid {
+                            half_extents: Vector3::repeat(half_extent),
+                        },
+                    );
+                }
+            }
+        }
+
+        scenario
+    }
+
+    /// Creates `count` balls raining down from above a static ground. The
+    /// `seed` determines the pseudo random positions and radii of the balls.
+    pub fn rain(count: usize, seed: u64) -> Self {
+        let mut scenario = Self::with_ground();
+        let mut random = Lcg(seed);
+
+        for _ in 0..count {
+            let x = random.next_in(-20.0, 20.0);
+            let y = random.next_in(5.0, 50.0);
+            let z = random.next_in(-20.0, 20.0);
+            let radius = random.next_in(0.1, 0.5);
+
+            scenario.push(
+                Isometry3::translation(na::convert(x), na::convert(y), na::convert(z)),
+                dynamic_body(),
+                Shape::Ball {
+                    radius: na::convert(radius),
+                },
+            );
+        }
+
+        scenario
+    }
+
+    /// Creates a pile of `count` ragdolls dropped on top of each other. Until
+    /// joints can be created through **specs-physics** each ragdoll is a
+    /// single body with a compound shape made of capsules.
+    pub fn ragdoll_pile(count: usize) -> Self {
+        let mut scenario = Self::with_ground();
+
+        for i in 0..count {
+            // alternate the orientation of each ragdoll to form a proper pile
+            let angle = if i % 2 == 0 {
+                N::zero()
+            } else {
+                N::frac_pi_2()
+            };
+
+            scenario.push(
+                Isometry3::new(
+                    Vector3::new(N::zero(), na::convert(1.0 + 1.5 * i as f64), N::zero()),
+                    Vector3::y() * angle,
+                ),
+                dynamic_body(),
+                ragdoll_shape(),
+            );
+        }
+
+        scenario
+    }
+
+    /// Adds a `ScenarioObject` with the given `Shape` to the `Scenario`.
+    pub fn push(&mut self, isometry: Isometry3<N>, physics_body: PhysicsBody<N>, shape: Shape<N>) {
+        self.objects.push(ScenarioObject {
+            isometry,
+            physics_body,
+            physics_collider: PhysicsColliderBuilder::from(shape).build(),
+        });
+    }
+
+    /// Spawns all `ScenarioObject`s of the `Scenario` as `Entity`s in the
+    /// given `World`. The `position` function is used to create the
+    /// `Position` `Component`s from the initial placement of each object.
+    pub fn spawn<P, F>(&self, world: &mut World, position: F) -> Vec<Entity>
+    where
+        P: Position<N>,
+        F: Fn(Isometry3<N>) -> P,
+    {
+        self.objects
+            .iter()
+            .map(|object| {
+                world
+                    .create_entity()
+                    .with(position(object.isometry))
+                    .with(object.physics_body)
+                    .with(object.physics_collider.clone())
+                    .build()
+            })
+            .collect()
+    }
+}
+
+fn dynamic_body<N: RealField>() -> PhysicsBody<N> {
+    PhysicsBodyBuilder::from(BodyStatus::Dynamic)
+        .gravity_enabled(true)
+        .build()
+}
+
+/// A humanoid made of capsules for the torso, head, arms and legs.
+fn ragdoll_shape<N: RealField>() -> Shape<N> {
+    let limb = |x: f64, y: f64, half_height: f64, radius: f64| {
+        (
+            Isometry3::translation(na::convert(x), na::convert(y), N::zero()),
+            Shape::Capsule {
+                half_height: na::convert(half_height),
+                radius: na::convert(radius),
+            },
+        )
+    };
+
+    Shape::Compound {
+        parts: vec![
+            // torso
+            limb(0.0, 0.0, 0.3, 0.2),
+            // head
+            (
+                Isometry3::translation(N::zero(), na::convert(0.7), N::zero()),
+                Shape::Ball {
+                    radius: na::convert(0.15),
+                },
+            ),
+            // arms
+            limb(-0.4, 0.1, 0.25, 0.08),
+            limb(0.4, 0.1, 0.25, 0.08),
+            // legs
+            limb(-0.12, -0.8, 0.3, 0.1),
+            limb(0.12, -0.8, 0.3, 0.1),
+        ],
+    }
+}
+
+/// Minimal linear congruential generator; `Scenario`s must not depend on an
+/// external source of randomness to stay reproducible across versions.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next_in(&mut self, min: f64, max: f64) -> f64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        let unit = (self.0 >> 11) as f64 / (1u64 << 53) as f64;
+        min + (max - min) * unit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        colliders::Shape,
+        nalgebra::Isometry3,
+        nphysics::object::BodyStatus,
+        scenarios::Scenario,
+    };
+
+    // the placement and radius of every ball of a rain `Scenario`
+    fn raindrops(scenario: &Scenario<f32>) -> Vec<(Isometry3<f32>, f32)> {
+        scenario
+            .objects
+            .iter()
+            .skip(1)
+            .map(|object| match object.physics_collider.shape {
+                Shape::Ball { radius } => (object.isometry, radius),
+                _ => panic!("rain must only consist of balls"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn repeat_rain_of_same_seed() {
+        let rain = raindrops(&Scenario::rain(50, 7));
+        assert_eq!(rain.len(), 50);
+        assert_eq!(rain, raindrops(&Scenario::rain(50, 7)));
+        assert_ne!(rain, raindrops(&Scenario::rain(50, 8)));
+
+        for (isometry, radius) in rain {
+            let translation = isometry.translation.vector;
+            assert!(translation.x >= -20.0 && translation.x < 20.0);
+            assert!(translation.y >= 5.0 && translation.y < 50.0);
+            assert!(translation.z >= -20.0 && translation.z < 20.0);
+            assert!(radius >= 0.1 && radius < 0.5);
+        }
+    }
+
+    #[test]
+    fn stack_boxes_on_ground() {
+        let scenario = Scenario::<f32>::box_stack(3, 4, 0.5);
+        assert_eq!(scenario.objects.len(), 3 * 3 * 4 + 1);
+
+        let ground = &scenario.objects[0];
+        assert_eq!(ground.physics_body.body_status, BodyStatus::Static);
+        assert!(scenario.objects[1..]
+            .iter()
+            .all(|object| object.physics_body.body_status == BodyStatus::Dynamic));
+
+        // the lowest layer rests on the ground, the highest one on three layers
+        let heights = scenario.objects[1..]
+            .iter()
+            .map(|object| object.isometry.translation.vector.y)
+            .collect::<Vec<_>>();
+        assert!(heights
+            .iter()
+            .all(|height| *height >= 0.5 && *height <= 3.5));
+        assert_eq!(heights.iter().filter(|height| **height == 0.5).count(), 9);
+        assert_eq!(heights.iter().filter(|height| **height == 3.5).count(), 9);
+    }
+}
@@ -0,0 +1,425 @@
+//! # Scenes module
+//! Plain text dumps of physics scenes. `capture` takes a snapshot of the
+//! physics `Entity`s of a running `World` as a `Scenario`, `dump` writes it
+//! as text and `parse` reads it back, so scenes can be reproduced outside the
+//! game, e.g. with the `inspector` example when bisecting behaviour changes
+//! of nphysics.
+//!
+//! The dump holds the pose, the `PhysicsBody` and the basic
+//! `PhysicsCollider` settings of every object, one object per line. Materials,
+//! collision groups and `Shape`s without a textual form (`Baked`, `Polyline`
+//! and `TriMesh`) are not part of the dump.
+
+use std::{error::Error, fmt, str::SplitWhitespace};
+
+use specs::{Join, World, WorldExt};
+
+use crate::{
+    bodies::{PhysicsBody, Position},
+    colliders::{PhysicsCollider, Shape},
+    nalgebra::{
+        self as na,
+        DMatrix,
+        Isometry3,
+        Matrix3,
+        Point3,
+        Quaternion,
+        RealField,
+        Translation3,
+        Unit,
+        UnitQuaternion,
+        Vector3,
+    },
+    nphysics::{algebra::Velocity3, object::BodyStatus},
+    scenarios::{Scenario, ScenarioObject},
+    PhysicsBodyBuilder,
+    PhysicsColliderBuilder,
+};
+
+/// The version of the format written by `dump`, stored in the header line.
+pub const SCENE_FORMAT_VERSION: u32 = 1;
+
+const HEADER: &str = "specs-physics-scene";
+
+/// The reasons reading a scene dump can fail.
+#[derive(Debug, PartialEq)]
+pub enum SceneError {
+    /// The given line of the dump is malformed.
+    Parse(usize),
+    /// The dump was written with a different `SCENE_FORMAT_VERSION`.
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Parse(line) => write!(f, "invalid scene object on line {}", line),
+            SceneError::UnsupportedVersion(version) => {
+                write!(f, "unsupported scene version {}", version)
+            }
+        }
+    }
+}
+
+impl Error for SceneError {}
+
+/// Takes a snapshot of all `Entity`s with a `Position`, `PhysicsBody` and
+/// `PhysicsCollider` in the `World`, in the order of their ids.
+pub fn capture<N, P>(world: &World) -> Scenario<N>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    let positions = world.read_storage::<P>();
+    let physics_bodies = world.read_storage::<PhysicsBody<N>>();
+    let physics_colliders = world.read_storage::<PhysicsCollider<N>>();
+
+    let objects = (&positions, &physics_bodies, &physics_colliders)
+        .join()
+        .map(|(position, physics_body, physics_collider)| {
+            let mut physics_collider = physics_collider.clone();
+            physics_collider.handle = None;
+            ScenarioObject {
+                isometry: *position.isometry(),
+                physics_body: *physics_body,
+                physics_collider,
+            }
+        })
+        .collect();
+    Scenario { objects }
+}
+
+/// Writes the `Scenario` as text. Objects whose `Shape` has no textual form
+/// are skipped with a warning.
+///
+/// # Example
+///
+/// ```rust
+/// use specs_physics::{scenarios::Scenario, scenes};
+///
+/// let scenario = Scenario::<f32>::ragdoll_pile(2);
+/// let restored = scenes::parse::<f32>(&scenes::dump(&scenario)).unwrap();
+/// assert_eq!(restored.objects.len(), scenario.objects.len());
+/// ```
+pub fn dump<N: RealField>(scenario: &Scenario<N>) -> String {
+    let mut text = format!("{} {}\n", HEADER, SCENE_FORMAT_VERSION);
+    for (index, object) in scenario.objects.iter().enumerate() {
+        let mut line = String::new();
+        if dump_object(&mut line, object) {
+            text.push_str(&line);
+            text.push('\n');
+        } else {
+            warn!(
+                "Skipping object {} with a {} Shape in the scene dump",
+                index, object.physics_collider.shape
+            );
+        }
+    }
+    text
+}
+
+/// Reads a `Scenario` written by `dump`. Empty lines and lines starting with
+/// `#` are ignored.
+pub fn parse<N: RealField>(text: &str) -> Result<Scenario<N>, SceneError> {
+    let mut lines = text.lines().enumerate();
+    let mut header = lines
+        .next()
+        .map(|(_, line)| line.split_whitespace())
+        .ok_or(SceneError::Parse(1))?;
+    match (header.next(), header.next().map(str::parse::<u32>)) {
+        (Some(HEADER), Some(Ok(SCENE_FORMAT_VERSION))) => {}
+        (Some(HEADER), Some(Ok(version))) => return Err(SceneError::UnsupportedVersion(version)),
+        _ => return Err(SceneError::Parse(1)),
+    }
+
+    let mut objects = Vec::new();
+    for (index, line) in lines {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut tokens = Tokens(line.split_whitespace());
+        let object = tokens
+            .object()
+            .filter(|_| tokens.0.next().is_none())
+            .ok_or(SceneError::Parse(index + 1))?;
+        objects.push(object);
+    }
+    Ok(Scenario { objects })
+}
+
+fn dump_object<N: RealField>(line: &mut String, object: &ScenarioObject<N>) -> bool {
+    let physics_body = &object.physics_body;
+    let physics_collider = &object.physics_collider;
+    let status = match physics_body.body_status {
+        BodyStatus::Disabled => "disabled",
+        BodyStatus::Static => "static",
+        BodyStatus::Dynamic => "dynamic",
+        BodyStatus::Kinematic => "kinematic",
+    };
+
+    line.push_str("pose");
+    push_isometry(line, &object.isometry);
+    line.push_str(" body ");
+    line.push_str(status);
+    push_values(line, &[physics_body.mass]);
+    line.push_str(if physics_body.gravity_enabled {
+        " true"
+    } else {
+        " false"
+    });
+    push_values(line, physics_body.velocity.linear.as_slice());
+    push_values(line, physics_body.velocity.angular.as_slice());
+    push_values(line, physics_body.angular_inertia.as_slice());
+    push_values(line, physics_body.local_center_of_mass.coords.as_slice());
+
+    line.push_str(" collider");
+    push_values(line, &[physics_collider.margin, physics_collider.density]);
+    line.push_str(if physics_collider.sensor {
+        " true"
+    } else {
+        " false"
+    });
+    push_isometry(line, &physics_collider.offset_from_parent);
+    line.push_str(" shape");
+    dump_shape(line, &physics_collider.shape)
+}
+
+fn dump_shape<N: RealField>(line: &mut String, shape: &Shape<N>) -> bool {
+    match shape {
+        Shape::Ball { radius } => {
+            line.push_str(" ball");
+            push_values(line, &[*radius]);
+        }
+        Shape::Capsule {
+            half_height,
+            radius,
+        } => {
+            line.push_str(" capsule");
+            push_values(line, &[*half_height, *radius]);
+        }
+        Shape::Compound { parts } => {
+            line.push_str(&format!(" compound {}", parts.len()));
+            for (isometry, part) in parts {
+                push_isometry(line, isometry);
+                if !dump_shape(line, part) {
+                    return false;
+                }
+            }
+        }
+        Shape::ConvexHull { points } => {
+            line.push_str(&format!(" hull {}", points.len()));
+            for point in points {
+                push_values(line, point.coords.as_slice());
+            }
+        }
+        Shape::Cuboid { half_extents } => {
+            line.push_str(" cuboid");
+            push_values(line, half_extents.as_slice());
+        }
+        Shape::HeightField { heights, scale } => {
+            line.push_str(&format!(
+                " heightfield {} {}",
+                heights.nrows(),
+                heights.ncols()
+            ));
+            push_values(line, scale.as_slice());
+            push_values(line, heights.as_slice());
+        }
+        Shape::Plane { normal } => {
+            line.push_str(" plane");
+            push_values(line, normal.as_slice());
+        }
+        Shape::RoundedCuboid {
+            half_extents,
+            border_radius,
+        } => {
+            line.push_str(" rounded_cuboid");
+            push_values(line, half_extents.as_slice());
+            push_values(line, &[*border_radius]);
+        }
+        Shape::RoundedCylinder {
+            half_height,
+            radius,
+            border_radius,
+        } => {
+            line.push_str(" rounded_cylinder");
+            push_values(line, &[*half_height, *radius, *border_radius]);
+        }
+        Shape::Segment { a, b } => {
+            line.push_str(" segment");
+            push_values(line, a.coords.as_slice());
+            push_values(line, b.coords.as_slice());
+        }
+        Shape::Triangle { a, b, c } => {
+            line.push_str(" triangle");
+            push_values(line, a.coords.as_slice());
+            push_values(line, b.coords.as_slice());
+            push_values(line, c.coords.as_slice());
+        }
+        Shape::Baked { .. } | Shape::Polyline { .. } | Shape::TriMesh { .. } => return false,
+    }
+    true
+}
+
+/// Appends the translation and the rotation quaternion as `x y z i j k w`.
+fn push_isometry<N: RealField>(line: &mut String, isometry: &Isometry3<N>) {
+    push_values(line, isometry.translation.vector.as_slice());
+    push_values(line, isometry.rotation.quaternion().coords.as_slice());
+}
+
+fn push_values<N: RealField>(line: &mut String, values: &[N]) {
+    for value in values {
+        line.push(' ');
+        line.push_str(&value.to_subset().unwrap_or(std::f64::NAN).to_string());
+    }
+}
+
+/// The tokens of a single line of a scene dump.
+struct Tokens<'a>(SplitWhitespace<'a>);
+
+impl<'a> Tokens<'a> {
+    fn keyword(&mut self, keyword: &str) -> Option<()> {
+        if self.0.next()? == keyword {
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn flag(&mut self) -> Option<bool> {
+        self.0.next()?.parse().ok()
+    }
+
+    fn count(&mut self) -> Option<usize> {
+        self.0.next()?.parse().ok()
+    }
+
+    fn value<N: RealField>(&mut self) -> Option<N> {
+        self.0.next()?.parse::<f64>().ok().map(na::convert)
+    }
+
+    fn values<N: RealField>(&mut self, count: usize) -> Option<Vec<N>> {
+        (0..count).map(|_| self.value()).collect()
+    }
+
+    fn vector<N: RealField>(&mut self) -> Option<Vector3<N>> {
+        Some(Vector3::new(self.value()?, self.value()?, self.value()?))
+    }
+
+    fn point<N: RealField>(&mut self) -> Option<Point3<N>> {
+        self.vector().map(Point3::from)
+    }
+
+    fn isometry<N: RealField>(&mut self) -> Option<Isometry3<N>> {
+        let translation = self.vector()?;
+        let coords = self.values(4)?;
+        Some(Isometry3::from_parts(
+            Translation3::from(translation),
+            UnitQuaternion::from_quaternion(Quaternion::new(
+                coords[3], coords[0], coords[1], coords[2],
+            )),
+        ))
+    }
+
+    fn object<N: RealField>(&mut self) -> Option<ScenarioObject<N>> {
+        self.keyword("pose")?;
+        let isometry = self.isometry()?;
+
+        self.keyword("body")?;
+        let body_status = match self.0.next()? {
+            "disabled" => BodyStatus::Disabled,
+            "static" => BodyStatus::Static,
+            "dynamic" => BodyStatus::Dynamic,
+            "kinematic" => BodyStatus::Kinematic,
+            _ => return None,
+        };
+        let mass = self.value()?;
+        let gravity_enabled = self.flag()?;
+        let velocity = Velocity3::new(self.vector()?, self.vector()?);
+        let angular_inertia = Matrix3::from_column_slice(&self.values(9)?);
+        let local_center_of_mass = self.point()?;
+
+        self.keyword("collider")?;
+        let margin = self.value()?;
+        let density = self.value()?;
+        let sensor = self.flag()?;
+        let offset_from_parent = self.isometry()?;
+        self.keyword("shape")?;
+        let shape = self.shape()?;
+
+        Some(ScenarioObject {
+            isometry,
+            physics_body: PhysicsBodyBuilder::from(body_status)
+                .gravity_enabled(gravity_enabled)
+                .velocity(velocity)
+                .angular_inertia(angular_inertia)
+                .mass(mass)
+                .local_center_of_mass(local_center_of_mass)
+                .build(),
+            physics_collider: PhysicsColliderBuilder::from(shape)
+                .offset_from_parent(offset_from_parent)
+                .density(density)
+                .margin(margin)
+                .sensor(sensor)
+                .build(),
+        })
+    }
+
+    fn shape<N: RealField>(&mut self) -> Option<Shape<N>> {
+        let shape = match self.0.next()? {
+            "ball" => Shape::Ball {
+                radius: self.value()?,
+            },
+            "capsule" => Shape::Capsule {
+                half_height: self.value()?,
+                radius: self.value()?,
+            },
+            "compound" => {
+                let count = self.count()?;
+                let parts = (0..count)
+                    .map(|_| Some((self.isometry()?, self.shape()?)))
+                    .collect::<Option<_>>()?;
+                Shape::Compound { parts }
+            }
+            "hull" => {
+                let count = self.count()?;
+                let points = (0..count).map(|_| self.point()).collect::<Option<_>>()?;
+                Shape::ConvexHull { points }
+            }
+            "cuboid" => Shape::Cuboid {
+                half_extents: self.vector()?,
+            },
+            "heightfield" => {
+                let (rows, columns) = (self.count()?, self.count()?);
+                let scale = self.vector()?;
+                let heights =
+                    DMatrix::from_column_slice(rows, columns, &self.values(rows * columns)?);
+                Shape::HeightField { heights, scale }
+            }
+            "plane" => Shape::Plane {
+                normal: Unit::new_normalize(self.vector()?),
+            },
+            "rounded_cuboid" => Shape::RoundedCuboid {
+                half_extents: self.vector()?,
+                border_radius: self.value()?,
+            },
+            "rounded_cylinder" => Shape::RoundedCylinder {
+                half_height: self.value()?,
+                radius: self.value()?,
+                border_radius: self.value()?,
+            },
+            "segment" => Shape::Segment {
+                a: self.point()?,
+                b: self.point()?,
+            },
+            "triangle" => Shape::Triangle {
+                a: self.point()?,
+                b: self.point()?,
+                c: self.point()?,
+            },
+            _ => return None,
+        };
+        Some(shape)
+    }
+}
@@ -0,0 +1,254 @@
+//! # Sensors module
+//! A lightweight alternative to the physics `World` for games that only need
+//! overlap and trigger queries. The `SensorWorld` `Resource` registers
+//! `PhysicsCollider`s in a plain ncollide `CollisionWorld`, without any rigid
+//! bodies or constraint solver, which is a lot cheaper than simulating the
+//! full dynamics.
+//!
+//! Even cheaper are `Pickup`s, tiny static triggers like coins or pellets.
+//! They are not registered as colliders at all but sorted into the cells of
+//! the `PickupGrid` `Resource`, and only tested against the `Position`s of
+//! `PickupCollector`s.
+
+use std::collections::{HashMap, HashSet};
+
+use specs::{world::Index, Component, DenseVecStorage, Entity, FlaggedStorage};
+
+use crate::{
+    nalgebra::{self as na, Point3, RealField, Vector3},
+    ncollide::{
+        query::Proximity,
+        world::{CollisionObjectHandle, CollisionWorld},
+    },
+};
+
+/// The `SensorWorld` `Resource` holds the collision detection world used by
+/// the `SyncSensorsSystem`. Every `PhysicsCollider` registered in it is
+/// treated as a trigger, regardless of its `sensor` flag, and only reports
+/// `ProximityEvent`s.
+pub struct SensorWorld<N: RealField> {
+    pub(crate) world: CollisionWorld<N, Entity>,
+    pub(crate) handles: HashMap<Index, CollisionObjectHandle>,
+    pub(crate) tick: u64,
+}
+
+impl<N: RealField> SensorWorld<N> {
+    /// Creates a new, empty `SensorWorld`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Retrieves the handle of the collision object owned by the `Entity`.
+    pub fn handle(&self, entity: Entity) -> Option<CollisionObjectHandle> {
+        self.handles.get(&entity.id()).cloned()
+    }
+
+    /// Retrieves the `Entity` owning the given collision object.
+    pub fn entity(&self, handle: CollisionObjectHandle) -> Option<Entity> {
+        self.world
+            .collision_object(handle)
+            .map(|collision_object| *collision_object.data())
+    }
+
+    /// Returns the `Entity`s whose colliders currently intersect the collider
+    /// of the given `Entity`.
+    pub fn intersecting(&self, entity: Entity) -> Vec<Entity> {
+        let handle = match self.handle(entity) {
+            Some(handle) => handle,
+            None => return Vec::new(),
+        };
+
+        self.world
+            .proximities_with(handle, true)
+            .into_iter()
+            .flatten()
+            .filter(|(_, _, _, proximity)| *proximity == Proximity::Intersecting)
+            .filter_map(|(handle1, handle2, ..)| {
+                self.entity(if handle1 == handle { handle2 } else { handle1 })
+            })
+            .collect()
+    }
+
+    /// Reports the number of collision detection updates so far, which is
+    /// also the tick index of the last update.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Exposes the underlying ncollide `CollisionWorld` for ray casts and other
+    /// geometric queries.
+    pub fn collision_world(&self) -> &CollisionWorld<N, Entity> {
+        &self.world
+    }
+}
+
+impl<N: RealField> Default for SensorWorld<N> {
+    fn default() -> Self {
+        Self {
+            world: CollisionWorld::new(na::convert(0.01)),
+            handles: HashMap::new(),
+            tick: 0,
+        }
+    }
+}
+
+/// The `Pickup` `Component` turns its `Entity` into a tiny static trigger,
+/// such as a coin or a pellet, which is collected once a `PickupCollector`
+/// comes within the sum of both `radius`es. The `Pickup` is located at the
+/// `Position` of its `Entity` and sorted into the `PickupGrid` by the
+/// `CollectPickupsSystem`; it does not need a `PhysicsCollider`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pickup<N: RealField> {
+    pub radius: N,
+}
+
+impl<N: RealField> Component for Pickup<N> {
+    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+}
+
+impl<N: RealField> Pickup<N> {
+    /// Creates a new `Pickup` with the given `radius`.
+    pub fn new(radius: N) -> Self {
+        Self { radius }
+    }
+}
+
+/// The `PickupCollector` `Component` marks the `Entity`s, usually players or
+/// other tracked bodies, whose `Position` collects `Pickup`s within the sum
+/// of both `radius`es.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PickupCollector<N: RealField> {
+    pub radius: N,
+}
+
+impl<N: RealField> Component for PickupCollector<N> {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl<N: RealField> PickupCollector<N> {
+    /// Creates a new `PickupCollector` with the given `radius`.
+    pub fn new(radius: N) -> Self {
+        Self { radius }
+    }
+}
+
+/// The `PickupGrid` `Resource` sorts the `Pickup`s into the cubic cells of a
+/// uniform grid, so every `PickupCollector` only tests the `Pickup`s in the
+/// cells around it. The `cell_size` should be a few times the radius of a
+/// `Pickup`.
+pub struct PickupGrid<N: RealField> {
+    cell_size: N,
+    cells: HashMap<(i64, i64, i64), Vec<Index>>,
+    pickups: HashMap<Index, (Entity, Point3<N>, N)>,
+    max_radius: N,
+    pub(crate) overlaps: HashSet<(Index, Index)>,
+}
+
+impl<N: RealField> PickupGrid<N> {
+    /// Creates a new, empty `PickupGrid` with the given `cell_size`.
+    pub fn new(cell_size: N) -> Self {
+        assert!(cell_size > N::zero(), "Invalid cell size of PickupGrid.");
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            pickups: HashMap::new(),
+            max_radius: N::zero(),
+            overlaps: HashSet::new(),
+        }
+    }
+
+    pub fn cell_size(&self) -> N {
+        self.cell_size
+    }
+
+    /// Returns the number of `Pickup`s in the grid.
+    pub fn len(&self) -> usize {
+        self.pickups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pickups.is_empty()
+    }
+
+    /// Returns the `Entity`s of the `Pickup`s within `radius` of the `point`,
+    /// including their own radius.
+    pub fn query(&self, point: &Point3<N>, radius: N) -> Vec<Entity> {
+        let reach = radius + self.max_radius;
+        let (min, max) = (
+            self.cell(&(point - Vector3::repeat(reach))),
+            self.cell(&(point + Vector3::repeat(reach))),
+        );
+
+        let mut found = Vec::new();
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                for z in min.2..=max.2 {
+                    let ids = match self.cells.get(&(x, y, z)) {
+                        Some(ids) => ids,
+                        None => continue,
+                    };
+                    found.extend(ids.iter().filter_map(|id| {
+                        let (entity, center, pickup_radius) = self.pickups[id];
+                        let reach = radius + pickup_radius;
+                        if na::distance_squared(point, &center) <= reach * reach {
+                            Some(entity)
+                        } else {
+                            None
+                        }
+                    }));
+                }
+            }
+        }
+        found
+    }
+
+    /// Whether the `Pickup` with the given id is in the grid.
+    pub(crate) fn contains(&self, id: Index) -> bool {
+        self.pickups.contains_key(&id)
+    }
+
+    /// Inserts or moves the `Pickup` of the `Entity`.
+    pub(crate) fn insert(&mut self, entity: Entity, center: Point3<N>, radius: N) {
+        self.remove(entity.id());
+        let cell = self.cell(&center);
+        self.cells
+            .entry(cell)
+            .or_insert_with(Vec::new)
+            .push(entity.id());
+        self.pickups.insert(entity.id(), (entity, center, radius));
+        if radius > self.max_radius {
+            self.max_radius = radius;
+        }
+    }
+
+    /// Removes the `Pickup` with the given id; the maximum radius is kept as
+    /// a conservative bound.
+    pub(crate) fn remove(&mut self, id: Index) {
+        if let Some((_, center, _)) = self.pickups.remove(&id) {
+            let cell = self.cell(&center);
+            if let Some(ids) = self.cells.get_mut(&cell) {
+                ids.retain(|other| *other != id);
+                if ids.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+
+    /// Returns the cell containing the `point`.
+    fn cell(&self, point: &Point3<N>) -> (i64, i64, i64) {
+        let index = |value: N| {
+            (value / self.cell_size)
+                .floor()
+                .to_subset()
+                .map_or(0, |index: f64| index as i64)
+        };
+        (index(point.x), index(point.y), index(point.z))
+    }
+}
+
+impl<N: RealField> Default for PickupGrid<N> {
+    fn default() -> Self {
+        Self::new(na::convert(2.0))
+    }
+}
@@ -0,0 +1,333 @@
+//! # Sprites module
+//! Collider shapes traced from the alpha channel of sprites, so 2D games do
+//! not have to author their collision outlines by hand. The `AlphaMask` marks
+//! the opaque pixels of a bitmap; `outline_shape` traces their outlines into
+//! a closed `Shape::Polyline` and `convex_shape` wraps them in a convex hull.
+//! Shapes lie in the xy plane with the y axis pointing up and the center of
+//! the bitmap at the origin, scaled by the size of a pixel in world units.
+
+use std::collections::HashMap;
+
+use crate::{
+    colliders::Shape,
+    nalgebra::{self as na, Point2, Point3, RealField},
+};
+
+/// The opaque pixels of a bitmap, in rows from top to bottom.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlphaMask {
+    width: usize,
+    height: usize,
+    opaque: Vec<bool>,
+}
+
+impl AlphaMask {
+    /// Creates the mask of a bitmap with one alpha value per pixel; pixels
+    /// with an alpha of at least `threshold` are opaque.
+    pub fn from_alpha(width: usize, height: usize, alpha: &[u8], threshold: u8) -> Self {
+        assert_eq!(alpha.len(), width * height, "Invalid size of alpha bitmap.");
+        Self {
+            width,
+            height,
+            opaque: alpha.iter().map(|alpha| *alpha >= threshold).collect(),
+        }
+    }
+
+    /// Creates the mask of a bitmap with four bytes per pixel, the last one
+    /// being the alpha value; pixels with an alpha of at least `threshold`
+    /// are opaque.
+    pub fn from_rgba(width: usize, height: usize, rgba: &[u8], threshold: u8) -> Self {
+        assert_eq!(
+            rgba.len(),
+            width * height * 4,
+            "Invalid size of RGBA bitmap."
+        );
+        Self {
+            width,
+            height,
+            opaque: rgba.chunks(4).map(|pixel| pixel[3] >= threshold).collect(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Whether the pixel in column `x` and row `y`, counted from the top, is
+    /// opaque. Pixels outside of the bitmap are transparent.
+    pub fn is_opaque(&self, x: isize, y: isize) -> bool {
+        x >= 0
+            && y >= 0
+            && (x as usize) < self.width
+            && (y as usize) < self.height
+            && self.opaque[y as usize * self.width + x as usize]
+    }
+
+    /// Traces the outlines of the opaque regions along the pixel edges. The
+    /// corners are counted in pixels from the bottom left of the bitmap;
+    /// outer outlines run counterclockwise, the outlines of holes clockwise.
+    /// Only corners where the outline turns are kept.
+    pub fn outlines(&self) -> Vec<Vec<Point2<usize>>> {
+        // every pixel edge between an opaque and a transparent pixel, directed
+        // so that the opaque pixel is on its left
+        let mut edges: HashMap<(usize, usize), Vec<(usize, usize)>> = HashMap::new();
+        for y in 0..self.height as isize {
+            for x in 0..self.width as isize {
+                if !self.is_opaque(x, y) {
+                    continue;
+                }
+                let (left, right) = (x as usize, x as usize + 1);
+                let bottom = self.height - y as usize - 1;
+                let top = bottom + 1;
+
+                let mut add = |from, to| edges.entry(from).or_insert_with(Vec::new).push(to);
+                if !self.is_opaque(x, y + 1) {
+                    add((left, bottom), (right, bottom));
+                }
+                if !self.is_opaque(x + 1, y) {
+                    add((right, bottom), (right, top));
+                }
+                if !self.is_opaque(x, y - 1) {
+                    add((right, top), (left, top));
+                }
+                if !self.is_opaque(x - 1, y) {
+                    add((left, top), (left, bottom));
+                }
+            }
+        }
+
+        // chain the edges into loops; regions touching diagonally share a
+        // corner with two outgoing edges, either of which closes a loop
+        let mut starts = edges.keys().cloned().collect::<Vec<_>>();
+        starts.sort();
+        let mut outlines = Vec::new();
+        for start in starts {
+            while edges.get(&start).map_or(false, |ends| !ends.is_empty()) {
+                let mut corners = vec![start];
+                let mut corner = start;
+                loop {
+                    let next = match edges.get_mut(&corner).and_then(|ends| ends.pop()) {
+                        Some(next) => next,
+                        None => break,
+                    };
+                    if next == start {
+                        break;
+                    }
+                    corners.push(next);
+                    corner = next;
+                }
+                outlines.push(turning_corners(&corners));
+            }
+        }
+        outlines
+    }
+}
+
+/// Traces the outlines of the opaque pixels of the `mask` into a closed
+/// `Shape::Polyline`, including the outlines of holes. The outlines are
+/// simplified until they deviate at most `tolerance` from the pixel edges.
+/// Returns `None` if the `mask` has no opaque pixels.
+///
+/// # Example
+///
+/// ```rust
+/// use specs_physics::{colliders::PhysicsColliderBuilder, sprites::{self, AlphaMask}};
+///
+/// // a 4x4 sprite with an opaque 2x2 block in its center
+/// #[rustfmt::skip]
+/// let alpha = [
+///     0, 0, 0, 0,
+///     0, 255, 255, 0,
+///     0, 255, 255, 0,
+///     0, 0, 0, 0,
+/// ];
+/// let mask = AlphaMask::from_alpha(4, 4, &alpha, 128);
+/// let shape = sprites::outline_shape::<f32>(&mask, 0.25, 0.01).unwrap();
+/// let physics_collider = PhysicsColliderBuilder::from(shape).build();
+/// ```
+pub fn outline_shape<N: RealField>(
+    mask: &AlphaMask,
+    pixel_size: N,
+    tolerance: N,
+) -> Option<Shape<N>> {
+    let mut points = Vec::new();
+    let mut indices = Vec::new();
+    for outline in mask.outlines() {
+        let mut outline = outline
+            .iter()
+            .map(|corner| to_local(mask, corner, pixel_size))
+            .collect::<Vec<_>>();
+        // simplify the loop as a path returning to its first corner
+        outline.push(outline[0]);
+        let mut simplified = simplify(&outline, tolerance);
+        simplified.pop();
+        if simplified.len() < 3 {
+            continue;
+        }
+
+        let offset = points.len();
+        for i in 0..simplified.len() {
+            indices.push(Point2::new(offset + i, offset + (i + 1) % simplified.len()));
+        }
+        points.extend(
+            simplified
+                .iter()
+                .map(|point| Point3::new(point.x, point.y, N::zero())),
+        );
+    }
+
+    if points.is_empty() {
+        None
+    } else {
+        Some(Shape::Polyline {
+            points,
+            indices: Some(indices),
+        })
+    }
+}
+
+/// Wraps the opaque pixels of the `mask` in a `Shape::ConvexHull` extruded
+/// to the given `depth` along the z axis. Returns `None` if the `mask` has no
+/// opaque pixels.
+pub fn convex_shape<N: RealField>(mask: &AlphaMask, pixel_size: N, depth: N) -> Option<Shape<N>> {
+    let half: N = na::convert(0.5);
+    let half_depth = depth * half;
+    let points = mask
+        .outlines()
+        .iter()
+        .flat_map(|outline| outline.iter())
+        .map(|corner| to_local(mask, corner, pixel_size))
+        .flat_map(|point| {
+            vec![
+                Point3::new(point.x, point.y, -half_depth),
+                Point3::new(point.x, point.y, half_depth),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    if points.is_empty() {
+        None
+    } else {
+        Some(Shape::ConvexHull { points })
+    }
+}
+
+/// Converts a corner of the `mask` into local space, centered on the bitmap.
+fn to_local<N: RealField>(mask: &AlphaMask, corner: &Point2<usize>, pixel_size: N) -> Point2<N> {
+    let half: N = na::convert(0.5);
+    let width: N = na::convert(mask.width as f64);
+    let height: N = na::convert(mask.height as f64);
+    Point2::new(
+        (na::convert::<f64, N>(corner.x as f64) - width * half) * pixel_size,
+        (na::convert::<f64, N>(corner.y as f64) - height * half) * pixel_size,
+    )
+}
+
+/// Drops the corners of a closed loop at which it continues straight on.
+fn turning_corners(corners: &[(usize, usize)]) -> Vec<Point2<usize>> {
+    let direction = |from: (usize, usize), to: (usize, usize)| {
+        (
+            to.0 as isize - from.0 as isize,
+            to.1 as isize - from.1 as isize,
+        )
+    };
+    (0..corners.len())
+        .filter(|&i| {
+            let previous = corners[(i + corners.len() - 1) % corners.len()];
+            let next = corners[(i + 1) % corners.len()];
+            direction(previous, corners[i]) != direction(corners[i], next)
+        })
+        .map(|i| Point2::new(corners[i].0, corners[i].1))
+        .collect()
+}
+
+/// Simplifies the path with the Ramer-Douglas-Peucker algorithm, keeping its
+/// end points.
+fn simplify<N: RealField>(points: &[Point2<N>], tolerance: N) -> Vec<Point2<N>> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let segment = last - first;
+    let length = segment.norm();
+    let distance = |point: &Point2<N>| {
+        let offset = point - first;
+        if length <= N::default_epsilon() {
+            offset.norm()
+        } else {
+            (segment.x * offset.y - segment.y * offset.x).abs() / length
+        }
+    };
+
+    let (index, max_distance) = points[1..points.len() - 1]
+        .iter()
+        .enumerate()
+        .map(|(i, point)| (i + 1, distance(point)))
+        .fold((0, N::zero()), |max, candidate| {
+            if candidate.1 > max.1 {
+                candidate
+            } else {
+                max
+            }
+        });
+    if max_distance <= tolerance {
+        return vec![first, last];
+    }
+
+    let mut simplified = simplify(&points[..=index], tolerance);
+    simplified.pop();
+    simplified.extend(simplify(&points[index..], tolerance));
+    simplified
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{convex_shape, outline_shape, AlphaMask};
+    use crate::{colliders::Shape, nalgebra::Point2};
+
+    #[test]
+    fn trace_outline_with_hole() {
+        // a ring of opaque pixels around a transparent center
+        #[rustfmt::skip]
+        let alpha = [
+            255, 255, 255,
+            255, 0, 255,
+            255, 255, 255,
+        ];
+        let mask = AlphaMask::from_alpha(3, 3, &alpha, 128);
+        let mut outlines = mask.outlines();
+        outlines.sort_by_key(|outline| outline.len());
+
+        assert_eq!(outlines.len(), 2);
+        for outline in &outlines {
+            assert_eq!(outline.len(), 4);
+        }
+        assert!(outlines[0].contains(&Point2::new(1, 1)));
+        assert!(outlines[1].contains(&Point2::new(3, 3)));
+
+        match outline_shape::<f32>(&mask, 1.0, 0.01) {
+            Some(Shape::Polyline { points, indices }) => {
+                assert_eq!(points.len(), 8);
+                assert_eq!(indices.unwrap().len(), 8);
+                assert!(points
+                    .iter()
+                    .all(|point| point.x.abs() <= 1.5 && point.y.abs() <= 1.5));
+            }
+            _ => panic!("expected a polyline"),
+        }
+        assert!(convex_shape::<f32>(&mask, 1.0, 0.5).is_some());
+    }
+
+    #[test]
+    fn empty_mask_has_no_shape() {
+        let mask = AlphaMask::from_alpha(2, 2, &[0, 10, 0, 10], 128);
+        assert!(mask.outlines().is_empty());
+        assert!(outline_shape::<f32>(&mask, 1.0, 0.01).is_none());
+        assert!(convex_shape::<f32>(&mask, 1.0, 0.5).is_none());
+    }
+}
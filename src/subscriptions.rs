@@ -0,0 +1,573 @@
+//! # Subscriptions module
+//! Filtered views of the collision events. A `Subscription` matches the
+//! colliders of every `ContactEvent` and `ProximityEvent` against
+//! `Component`s and named `CollisionLayers` and forwards the matching pairs
+//! to its own `PairEvents` channel, so gameplay `System`s do not have to
+//! repeat the same matching boilerplate over every event batch.
+
+use std::{collections::HashMap, marker::PhantomData};
+
+use specs::{Component, Entity, ReaderId, RunNow, World, WorldExt};
+
+use crate::{
+    colliders::PhysicsCollider,
+    events::{ContactEvent, ContactEvents, ContactType, ProximityEvent, ProximityEvents},
+    nalgebra::RealField,
+    ncollide::query::Proximity,
+    shrev::EventChannel,
+};
+
+/// The `CollisionLayers` `Resource` names collision groups, so
+/// `Subscription`s can refer to them as layers.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CollisionLayers {
+    groups: HashMap<String, usize>,
+}
+
+impl CollisionLayers {
+    /// Creates `CollisionLayers` without any named layer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the `CollisionLayers` with the given collision group named.
+    pub fn with(mut self, name: impl Into<String>, group: usize) -> Self {
+        self.insert(name, group);
+        self
+    }
+
+    /// Names the given collision group, replacing the previous group of the
+    /// name.
+    pub fn insert(&mut self, name: impl Into<String>, group: usize) {
+        self.groups.insert(name.into(), group);
+    }
+
+    /// Returns the collision group of the given layer.
+    pub fn group(&self, name: &str) -> Option<usize> {
+        self.groups.get(name).cloned()
+    }
+}
+
+/// The `PairEventKind` describes whether a matched pair of colliders started
+/// or stopped touching. For `ProximityEvent`s, starting and stopping to
+/// intersect count as touching.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PairEventKind {
+    Started,
+    Stopped,
+}
+
+/// The `PairEvent` is emitted by a `Subscription` for every collision event
+/// whose colliders match its filters. The tag type `T` tells the channels of
+/// different `Subscription`s apart.
+#[derive(Debug)]
+pub struct PairEvent<T> {
+    /// The collider matching the `with_*` filters.
+    pub subject: Entity,
+    /// The collider matching the `against_*` filters.
+    pub other: Entity,
+    pub kind: PairEventKind,
+    /// The tick index of the step the event occurred in.
+    pub tick: u64,
+
+    marker: PhantomData<T>,
+}
+
+/// `PairEvents` is a custom `EventChannel` type used to expose the
+/// `PairEvent`s of a `Subscription`.
+pub type PairEvents<T> = EventChannel<PairEvent<T>>;
+
+type Filter = Box<dyn Fn(&World, Entity) -> bool + Send + Sync>;
+
+/// Entry point of the subscription builder, see `Subscribe::pairs`.
+pub struct Subscribe<N> {
+    n_marker: PhantomData<N>,
+}
+
+impl<N: RealField> Subscribe<N> {
+    /// Starts building a `Subscription` to the pairs of colliders of the
+    /// collision events. Without filters, every pair matches.
+    pub fn pairs() -> SubscriptionBuilder<N> {
+        SubscriptionBuilder {
+            subject: Vec::new(),
+            other: Vec::new(),
+            contacts: true,
+            proximities: true,
+            n_marker: PhantomData,
+        }
+    }
+}
+
+/// The `SubscriptionBuilder` collects the filters of a `Subscription`. The
+/// `with_*` filters apply to one collider of a pair, the `against_*` filters
+/// to the other one; all filters of a side have to match.
+///
+/// # Example
+///
+/// ```rust
+/// use specs::{Builder, Component, NullStorage, World, WorldExt};
+/// use specs_physics::{
+///     events::{ContactEvent, ContactEvents, ContactType},
+///     subscriptions::{PairEventKind, PairEvents, Subscribe},
+/// };
+///
+/// #[derive(Default)]
+/// struct Player;
+///
+/// impl Component for Player {
+///     type Storage = NullStorage<Self>;
+/// }
+///
+/// #[derive(Default)]
+/// struct Hazard;
+///
+/// impl Component for Hazard {
+///     type Storage = NullStorage<Self>;
+/// }
+///
+/// struct PlayerHitHazard;
+///
+/// let mut world = World::new();
+/// world.register::<Player>();
+/// world.register::<Hazard>();
+/// let mut subscription = Subscribe::<f32>::pairs()
+///     .with_component::<Player>()
+///     .against_component::<Hazard>()
+///     .into_channel::<PlayerHitHazard>();
+/// specs::RunNow::setup(&mut subscription, &mut world);
+/// let mut reader_id = world
+///     .fetch_mut::<PairEvents<PlayerHitHazard>>()
+///     .register_reader();
+///
+/// let spikes = world.create_entity().with(Hazard).build();
+/// let player = world.create_entity().with(Player).build();
+/// world.fetch_mut::<ContactEvents>().single_write(ContactEvent {
+///     collider1: spikes,
+///     collider2: player,
+///     contact_type: ContactType::Started,
+///     tick: 1,
+/// });
+/// specs::RunNow::run_now(&mut subscription, &world);
+///
+/// let pair_events = world.fetch::<PairEvents<PlayerHitHazard>>();
+/// let pair_event = pair_events.read(&mut reader_id).next().unwrap();
+/// assert_eq!((pair_event.subject, pair_event.other), (player, spikes));
+/// assert_eq!(pair_event.kind, PairEventKind::Started);
+/// ```
+pub struct SubscriptionBuilder<N> {
+    subject: Vec<Filter>,
+    other: Vec<Filter>,
+    contacts: bool,
+    proximities: bool,
+
+    n_marker: PhantomData<N>,
+}
+
+impl<N: RealField> SubscriptionBuilder<N> {
+    /// Requires the subject `Entity` to have a `C` `Component`.
+    pub fn with_component<C: Component>(mut self) -> Self {
+        self.subject.push(component_filter::<C>());
+        self
+    }
+
+    /// Requires the other `Entity` to have a `C` `Component`.
+    pub fn against_component<C: Component>(mut self) -> Self {
+        self.other.push(component_filter::<C>());
+        self
+    }
+
+    /// Requires the `PhysicsCollider` of the subject `Entity` to be a member
+    /// of the collision group named `layer` in the `CollisionLayers`.
+    pub fn with_layer(mut self, layer: impl Into<String>) -> Self {
+        self.subject.push(layer_filter::<N>(layer.into()));
+        self
+    }
+
+    /// Requires the `PhysicsCollider` of the other `Entity` to be a member of
+    /// the collision group named `layer` in the `CollisionLayers`.
+    pub fn against_layer(mut self, layer: impl Into<String>) -> Self {
+        self.other.push(layer_filter::<N>(layer.into()));
+        self
+    }
+
+    /// Only matches `ContactEvent`s.
+    pub fn contacts_only(mut self) -> Self {
+        self.contacts = true;
+        self.proximities = false;
+        self
+    }
+
+    /// Only matches `ProximityEvent`s.
+    pub fn proximities_only(mut self) -> Self {
+        self.contacts = false;
+        self.proximities = true;
+        self
+    }
+
+    /// Builds the `Subscription` writing to the `PairEvents<T>` channel.
+    pub fn into_channel<T>(self) -> Subscription<T>
+    where
+        T: Send + Sync + 'static,
+    {
+        Subscription {
+            subject: self.subject,
+            other: self.other,
+            contacts: self.contacts,
+            proximities: self.proximities,
+            contact_events_reader_id: None,
+            proximity_events_reader_id: None,
+            t_marker: PhantomData,
+        }
+    }
+}
+
+fn component_filter<C: Component>() -> Filter {
+    Box::new(|world: &World, entity| world.read_storage::<C>().contains(entity))
+}
+
+fn layer_filter<N: RealField>(layer: String) -> Filter {
+    Box::new(move |world: &World, entity| {
+        let group = match world
+            .try_fetch::<CollisionLayers>()
+            .and_then(|layers| layers.group(&layer))
+        {
+            Some(group) => group,
+            None => return false,
+        };
+        world
+            .read_storage::<PhysicsCollider<N>>()
+            .get(entity)
+            .map_or(false, |physics_collider| {
+                physics_collider.collision_groups.is_member_of(group)
+            })
+    })
+}
+
+/// The `Subscription` forwards the matching pairs of the `ContactEvents` and
+/// `ProximityEvents` to its `PairEvents<T>` channel, with the colliders
+/// ordered according to the filters. As the filters inspect arbitrary
+/// `Component`s, it has to be added to the `Dispatcher` as a thread-local
+/// `System` with `DispatcherBuilder::with_thread_local`, which also makes it
+/// run after the physics `System`s.
+pub struct Subscription<T> {
+    subject: Vec<Filter>,
+    other: Vec<Filter>,
+    contacts: bool,
+    proximities: bool,
+    contact_events_reader_id: Option<ReaderId<ContactEvent>>,
+    proximity_events_reader_id: Option<ReaderId<ProximityEvent>>,
+
+    t_marker: PhantomData<T>,
+}
+
+impl<'a, T> RunNow<'a> for Subscription<T>
+where
+    T: Send + Sync + 'static,
+{
+    fn run_now(&mut self, world: &'a World) {
+        let Subscription {
+            subject,
+            other,
+            contact_events_reader_id,
+            proximity_events_reader_id,
+            ..
+        } = self;
+        let matches =
+            |collider1, collider2| matching_pair(world, subject, other, collider1, collider2);
+
+        let mut pair_events = Vec::new();
+        let mut pair_event = |(subject, other), kind, tick| {
+            pair_events.push(PairEvent {
+                subject,
+                other,
+                kind,
+                tick,
+                marker: PhantomData,
+            })
+        };
+
+        if let Some(reader_id) = contact_events_reader_id.as_mut() {
+            let contact_events = world.fetch::<ContactEvents>();
+            for contact_event in contact_events.read(reader_id) {
+                let kind = match contact_event.contact_type {
+                    ContactType::Started => PairEventKind::Started,
+                    ContactType::Stopped => PairEventKind::Stopped,
+                };
+                if let Some(pair) = matches(contact_event.collider1, contact_event.collider2) {
+                    pair_event(pair, kind, contact_event.tick);
+                }
+            }
+        }
+
+        if let Some(reader_id) = proximity_events_reader_id.as_mut() {
+            let proximity_events = world.fetch::<ProximityEvents>();
+            for proximity_event in proximity_events.read(reader_id) {
+                // only entering and leaving the intersection is of interest
+                let kind = if proximity_event.new_status == Proximity::Intersecting {
+                    PairEventKind::Started
+                } else if proximity_event.prev_status == Proximity::Intersecting {
+                    PairEventKind::Stopped
+                } else {
+                    continue;
+                };
+                if let Some(pair) = matches(proximity_event.collider1, proximity_event.collider2) {
+                    pair_event(pair, kind, proximity_event.tick);
+                }
+            }
+        }
+
+        world.fetch_mut::<PairEvents<T>>().iter_write(pair_events);
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        info!("Subscription.setup");
+
+        // initialise required resources
+        world
+            .entry::<PairEvents<T>>()
+            .or_insert_with(PairEvents::default);
+
+        // register reader ids for the subscribed EventChannels
+        if self.contacts {
+            self.contact_events_reader_id = Some(
+                world
+                    .entry::<ContactEvents>()
+                    .or_insert_with(ContactEvents::default)
+                    .register_reader(),
+            );
+        }
+        if self.proximities {
+            self.proximity_events_reader_id = Some(
+                world
+                    .entry::<ProximityEvents>()
+                    .or_insert_with(ProximityEvents::default)
+                    .register_reader(),
+            );
+        }
+    }
+}
+
+/// Orders the colliders of a pair as subject and other, if they match the
+/// filters in either order.
+fn matching_pair(
+    world: &World,
+    subject: &[Filter],
+    other: &[Filter],
+    collider1: Entity,
+    collider2: Entity,
+) -> Option<(Entity, Entity)> {
+    let all = |filters: &[Filter], entity| filters.iter().all(|filter| filter(world, entity));
+    [(collider1, collider2), (collider2, collider1)]
+        .iter()
+        .cloned()
+        .find(|(entity1, entity2)| all(subject, *entity1) && all(other, *entity2))
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::{prelude::*, ReaderId};
+
+    use crate::{
+        colliders::Shape,
+        events::{ContactEvent, ContactEvents, ContactType, ProximityEvent, ProximityEvents},
+        ncollide::{query::Proximity, world::CollisionGroups},
+        subscriptions::{
+            CollisionLayers,
+            PairEvent,
+            PairEventKind,
+            PairEvents,
+            Subscribe,
+            Subscription,
+            SubscriptionBuilder,
+        },
+        PhysicsCollider,
+        PhysicsColliderBuilder,
+    };
+
+    #[derive(Default)]
+    struct Player;
+
+    impl Component for Player {
+        type Storage = NullStorage<Self>;
+    }
+
+    #[derive(Default)]
+    struct Hazard;
+
+    impl Component for Hazard {
+        type Storage = NullStorage<Self>;
+    }
+
+    struct Subscriber<T> {
+        subscription: Subscription<T>,
+        reader_id: ReaderId<PairEvent<T>>,
+    }
+
+    impl<T: Send + Sync + 'static> Subscriber<T> {
+        fn new(world: &mut World, builder: SubscriptionBuilder<f32>) -> Self {
+            let mut subscription = builder.into_channel::<T>();
+            RunNow::setup(&mut subscription, world);
+            let reader_id = world.fetch_mut::<PairEvents<T>>().register_reader();
+            Self {
+                subscription,
+                reader_id,
+            }
+        }
+
+        /// Runs the `Subscription` and returns the pairs it forwarded.
+        fn pairs(&mut self, world: &World) -> Vec<(Entity, Entity, PairEventKind, u64)> {
+            self.subscription.run_now(world);
+            world
+                .fetch::<PairEvents<T>>()
+                .read(&mut self.reader_id)
+                .map(|pair_event| {
+                    (
+                        pair_event.subject,
+                        pair_event.other,
+                        pair_event.kind,
+                        pair_event.tick,
+                    )
+                })
+                .collect()
+        }
+    }
+
+    fn contact(world: &World, collider1: Entity, collider2: Entity, contact_type: ContactType) {
+        world
+            .fetch_mut::<ContactEvents>()
+            .single_write(ContactEvent {
+                collider1,
+                collider2,
+                contact_type,
+                tick: 1,
+            });
+    }
+
+    fn proximity(world: &World, collider1: Entity, collider2: Entity, statuses: [Proximity; 2]) {
+        world
+            .fetch_mut::<ProximityEvents>()
+            .single_write(ProximityEvent {
+                collider1,
+                collider2,
+                prev_status: statuses[0],
+                new_status: statuses[1],
+                tick: 2,
+            });
+    }
+
+    #[test]
+    fn match_pairs_in_either_order() {
+        struct Everything;
+        struct PlayerHitHazard;
+
+        let mut world = World::new();
+        world.register::<Player>();
+        world.register::<Hazard>();
+        let mut everything = Subscriber::<Everything>::new(&mut world, Subscribe::pairs());
+        let mut player_hit_hazard = Subscriber::<PlayerHitHazard>::new(
+            &mut world,
+            Subscribe::pairs()
+                .with_component::<Player>()
+                .against_component::<Hazard>(),
+        );
+
+        let player = world.create_entity().with(Player).build();
+        let spikes = world.create_entity().with(Hazard).build();
+        let barrel = world.create_entity().build();
+        contact(&world, spikes, player, ContactType::Started);
+        contact(&world, player, barrel, ContactType::Started);
+        contact(&world, barrel, spikes, ContactType::Started);
+        contact(&world, player, spikes, ContactType::Stopped);
+
+        // without filters every pair matches in the order of the event
+        assert_eq!(
+            everything.pairs(&world),
+            vec![
+                (spikes, player, PairEventKind::Started, 1),
+                (player, barrel, PairEventKind::Started, 1),
+                (barrel, spikes, PairEventKind::Started, 1),
+                (player, spikes, PairEventKind::Stopped, 1),
+            ]
+        );
+
+        // the subject is the collider matching the `with_*` filters
+        assert_eq!(
+            player_hit_hazard.pairs(&world),
+            vec![
+                (player, spikes, PairEventKind::Started, 1),
+                (player, spikes, PairEventKind::Stopped, 1),
+            ]
+        );
+        assert_eq!(player_hit_hazard.pairs(&world), vec![]);
+    }
+
+    #[test]
+    fn match_named_layers() {
+        struct PlayerInLava;
+        struct PlayerInVoid;
+
+        let mut world = World::new();
+        world.register::<Player>();
+        world.register::<PhysicsCollider<f32>>();
+        world.insert(CollisionLayers::new().with("hazard", 3));
+        let mut player_in_lava = Subscriber::<PlayerInLava>::new(
+            &mut world,
+            Subscribe::pairs()
+                .with_component::<Player>()
+                .against_layer("hazard")
+                .proximities_only(),
+        );
+        // layers missing from the `CollisionLayers` never match
+        let mut player_in_void = Subscriber::<PlayerInVoid>::new(
+            &mut world,
+            Subscribe::pairs()
+                .with_component::<Player>()
+                .against_layer("void"),
+        );
+
+        let collider = |group| {
+            PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 1.0 })
+                .sensor(true)
+                .collision_groups(CollisionGroups::new().with_membership(&[group]))
+                .build()
+        };
+        let player = world.create_entity().with(Player).with(collider(0)).build();
+        let lava = world.create_entity().with(collider(3)).build();
+        let water = world.create_entity().with(collider(4)).build();
+        proximity(
+            &world,
+            lava,
+            player,
+            [Proximity::Disjoint, Proximity::WithinMargin],
+        );
+        proximity(
+            &world,
+            lava,
+            player,
+            [Proximity::WithinMargin, Proximity::Intersecting],
+        );
+        proximity(
+            &world,
+            player,
+            water,
+            [Proximity::WithinMargin, Proximity::Intersecting],
+        );
+        proximity(
+            &world,
+            player,
+            lava,
+            [Proximity::Intersecting, Proximity::Disjoint],
+        );
+        contact(&world, player, lava, ContactType::Started);
+
+        // only entering and leaving the intersection is forwarded
+        assert_eq!(
+            player_in_lava.pairs(&world),
+            vec![
+                (player, lava, PairEventKind::Started, 2),
+                (player, lava, PairEventKind::Stopped, 2),
+            ]
+        );
+        assert_eq!(player_in_void.pairs(&world), vec![]);
+    }
+}
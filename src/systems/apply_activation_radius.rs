@@ -0,0 +1,253 @@
+use std::marker::PhantomData;
+
+use log::Level;
+use specs::{
+    BitSet,
+    Entities,
+    Join,
+    Read,
+    ReadStorage,
+    System,
+    SystemData,
+    World,
+    Write,
+    WriteExpect,
+};
+
+use crate::{
+    bodies::{PhysicsBody, Position, SimulationFocus},
+    diagnostics::{DiagnosticKind, LogSource, PhysicsDiagnostics, PhysicsLogConfig, SystemLogger},
+    handles::PhysicsHandles,
+    nalgebra::RealField,
+    nphysics::object::{Body, BodyStatus},
+    parameters::{ActivationRadius, DeactivationMode},
+    Physics,
+};
+
+/// The `ApplyActivationRadiusSystem` gives large worlds an automatic
+/// simulation level-of-detail. If an `ActivationRadius` `Resource` exists,
+/// dynamic bodies far away from every `SimulationFocus` are put to sleep or
+/// disabled, and reactivated once a `SimulationFocus` comes near. It is not
+/// part of the default `Dispatcher` and has to run between the
+/// `SyncBodiesToPhysicsSystem` and the `PhysicsStepperSystem`.
+pub struct ApplyActivationRadiusSystem<N, P> {
+    deactivated: BitSet,
+    still_deactivated: BitSet,
+
+    n_marker: PhantomData<N>,
+    p_marker: PhantomData<P>,
+}
+
+impl<'s, N, P> System<'s> for ApplyActivationRadiusSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, P>,
+        ReadStorage<'s, SimulationFocus>,
+        ReadStorage<'s, PhysicsBody<N>>,
+        Option<Read<'s, ActivationRadius<N>>>,
+        Option<Read<'s, PhysicsLogConfig>>,
+        Write<'s, PhysicsDiagnostics>,
+        Read<'s, PhysicsHandles>,
+        WriteExpect<'s, Physics<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            positions,
+            simulation_foci,
+            physics_bodies,
+            activation_radius,
+            log_config,
+            mut diagnostics,
+            handles,
+            mut physics,
+        ) = data;
+        let mut logger = SystemLogger::new(
+            log_config.as_ref().map(|config| &**config),
+            LogSource::Bodies,
+            &mut diagnostics,
+        );
+
+        let activation_radius = match activation_radius {
+            Some(activation_radius) => *activation_radius,
+            None => return,
+        };
+
+        let foci = (&positions, &simulation_foci)
+            .join()
+            .map(|(position, _)| position.isometry().translation.vector)
+            .collect::<Vec<_>>();
+
+        // without any SimulationFocus every body would be deactivated
+        if foci.is_empty() {
+            return;
+        }
+
+        let activation_distance = activation_radius.radius * activation_radius.radius;
+        let deactivation_radius = activation_radius.radius + activation_radius.hysteresis;
+        let deactivation_distance = deactivation_radius * deactivation_radius;
+
+        // bodies which have been removed in the meantime are dropped from the
+        // deactivated bodies by only carrying over the ones that still exist
+        std::mem::swap(&mut self.deactivated, &mut self.still_deactivated);
+        self.deactivated.clear();
+        for (entity, physics_body) in (&entities, &physics_bodies).join() {
+            if physics_body.body_status != BodyStatus::Dynamic {
+                continue;
+            }
+
+            let id = entity.id();
+            let rigid_body = match handles
+                .body_handle(entity)
+                .and_then(|handle| physics.world.rigid_body_mut(handle))
+            {
+                Some(rigid_body) => rigid_body,
+                None => continue,
+            };
+
+            // the squared distance to the nearest focus, capped at the deactivation
+            // distance
+            let translation = rigid_body.position().translation.vector;
+            let distance = foci
+                .iter()
+                .map(|focus| (focus - translation).norm_squared())
+                .fold(deactivation_distance, |min, distance| {
+                    if distance < min {
+                        distance
+                    } else {
+                        min
+                    }
+                });
+            let deactivated = self.still_deactivated.contains(id);
+
+            if deactivated && distance <= activation_distance {
+                if activation_radius.mode == DeactivationMode::Disable {
+                    rigid_body.set_status(BodyStatus::Dynamic);
+                }
+                rigid_body.activate();
+
+                logger.log(
+                    module_path!(),
+                    Level::Debug,
+                    DiagnosticKind::BodyReactivated(id),
+                    format_args!("Reactivated body with id: {}", id),
+                );
+            } else if !deactivated && distance >= deactivation_distance {
+                match activation_radius.mode {
+                    DeactivationMode::Sleep => rigid_body.deactivate(),
+                    DeactivationMode::Disable => rigid_body.set_status(BodyStatus::Disabled),
+                }
+                self.deactivated.add(id);
+
+                logger.log(
+                    module_path!(),
+                    Level::Debug,
+                    DiagnosticKind::BodyDeactivated(id),
+                    format_args!("Deactivated body with id: {}", id),
+                );
+            } else if deactivated {
+                self.deactivated.add(id);
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("ApplyActivationRadiusSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+    }
+}
+
+impl<N, P> Default for ApplyActivationRadiusSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    fn default() -> Self {
+        Self {
+            deactivated: BitSet::new(),
+            still_deactivated: BitSet::new(),
+            n_marker: PhantomData,
+            p_marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        bodies::SimulationFocus,
+        handles::PhysicsHandles,
+        nalgebra::Isometry3,
+        nphysics::object::{Body, BodyStatus},
+        parameters::{ActivationRadius, DeactivationMode},
+        systems::{ApplyActivationRadiusSystem, SyncBodiesToPhysicsSystem},
+        Physics,
+        PhysicsBodyBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn disable_distant_body() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncBodiesToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_bodies_to_physics_system",
+                &[],
+            )
+            .with(
+                ApplyActivationRadiusSystem::<f32, SimplePosition<f32>>::default(),
+                "apply_activation_radius_system",
+                &["sync_bodies_to_physics_system"],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+        world.insert(ActivationRadius {
+            mode: DeactivationMode::Disable,
+            ..ActivationRadius::<f32>::new(50.0)
+        });
+
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(SimulationFocus)
+            .build();
+        let near = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(
+                10.0, 0.0, 0.0,
+            )))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .build();
+        let far = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(
+                100.0, 0.0, 0.0,
+            )))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .build();
+        dispatcher.dispatch(&world);
+
+        let physics = world.read_resource::<Physics<f32>>();
+        let handles = world.read_resource::<PhysicsHandles>();
+        let status = |entity| {
+            physics
+                .world
+                .rigid_body(handles.body_handle(entity).unwrap())
+                .unwrap()
+                .status()
+        };
+        assert_eq!(status(near), BodyStatus::Dynamic);
+        assert_eq!(status(far), BodyStatus::Disabled);
+    }
+}
@@ -0,0 +1,121 @@
+use std::marker::PhantomData;
+
+use specs::{Join, ReadStorage, System, WriteStorage};
+
+use crate::{
+    bodies::{PhysicsBody, Position},
+    forces::Aerodynamics,
+    nalgebra::RealField,
+    nphysics::{algebra::Force3, object::BodyStatus},
+};
+
+/// The `ApplyAerodynamicsSystem` converts the drag and lift of `Aerodynamics`
+/// into external forces on the `PhysicsBody` of the same `Entity`.
+pub struct ApplyAerodynamicsSystem<N, P> {
+    n_marker: PhantomData<N>,
+    p_marker: PhantomData<P>,
+}
+
+impl<'s, N, P> System<'s> for ApplyAerodynamicsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    type SystemData = (
+        ReadStorage<'s, Aerodynamics<N>>,
+        ReadStorage<'s, P>,
+        WriteStorage<'s, PhysicsBody<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (aerodynamics, positions, mut physics_bodies) = data;
+
+        for (aerodynamics, position, physics_body) in
+            (&aerodynamics, &positions, &mut physics_bodies).join()
+        {
+            if physics_body.body_status != BodyStatus::Dynamic {
+                continue;
+            }
+
+            let force =
+                aerodynamics.force(&position.isometry().rotation, &physics_body.velocity.linear);
+            physics_body.apply_external_force(&Force3::linear(force));
+        }
+    }
+}
+
+impl<N, P> Default for ApplyAerodynamicsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+            p_marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        forces::Aerodynamics,
+        nalgebra::{Isometry3, Vector3},
+        nphysics::object::BodyStatus,
+        parameters::Gravity,
+        systems::ApplyAerodynamicsSystem,
+        PhysicsBody,
+        PhysicsBodyBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn reach_terminal_velocity() {
+        let mut world = World::new();
+        let mut dispatcher_builder = DispatcherBuilder::new().with(
+            ApplyAerodynamicsSystem::<f32, SimplePosition<f32>>::default(),
+            "apply_aerodynamics_system",
+            &[],
+        );
+        crate::register_physics_systems_after::<f32, SimplePosition<f32>>(
+            &mut dispatcher_builder,
+            &["apply_aerodynamics_system"],
+        );
+        let mut dispatcher = dispatcher_builder.build();
+        dispatcher.setup(&mut world);
+        world.insert(Gravity(Vector3::<f32>::new(0.0, -9.81, 0.0)));
+
+        let aerodynamics = Aerodynamics::new(1.0f32, 1.0);
+        let falling = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(
+                0.0, 100.0, 0.0,
+            )))
+            .with(
+                PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                    .mass(1.0)
+                    .build(),
+            )
+            .with(aerodynamics)
+            .build();
+
+        // the drag balances the weight at the terminal velocity
+        let terminal_velocity = (2.0 * 9.81
+            / (aerodynamics.air_density
+                * aerodynamics.drag_coefficient
+                * aerodynamics.reference_area))
+            .sqrt();
+
+        for _ in 0..300 {
+            dispatcher.dispatch(&world);
+            world.maintain();
+        }
+
+        let physics_bodies = world.read_storage::<PhysicsBody<f32>>();
+        let velocity = physics_bodies.get(falling).unwrap().velocity.linear;
+        assert!((velocity - Vector3::new(0.0, -terminal_velocity, 0.0)).norm() < 1.0e-2);
+    }
+}
@@ -0,0 +1,79 @@
+use std::marker::PhantomData;
+
+use specs::{Entities, Join, ReadStorage, System, WriteStorage};
+
+use crate::{
+    bodies::{PhysicsBody, Position},
+    forces::Attractor,
+    nalgebra::{RealField, Vector3},
+    nphysics::{algebra::Force3, object::BodyStatus},
+};
+
+/// The `ApplyAttractorsSystem` pulls dynamic `PhysicsBody`s towards every
+/// `Attractor` in range.
+pub struct ApplyAttractorsSystem<N, P> {
+    n_marker: PhantomData<N>,
+    p_marker: PhantomData<P>,
+}
+
+impl<'s, N, P> System<'s> for ApplyAttractorsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Attractor<N>>,
+        ReadStorage<'s, P>,
+        WriteStorage<'s, PhysicsBody<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, attractors, positions, mut physics_bodies) = data;
+
+        // collect the attractors first, as the bodies have to be borrowed mutably
+        let attractors = (&entities, &attractors, &positions)
+            .join()
+            .map(|(entity, attractor, position)| {
+                (entity, *attractor, position.isometry().translation.vector)
+            })
+            .collect::<Vec<_>>();
+
+        if attractors.is_empty() {
+            return;
+        }
+
+        for (entity, position, physics_body) in (&entities, &positions, &mut physics_bodies).join()
+        {
+            if physics_body.body_status != BodyStatus::Dynamic {
+                continue;
+            }
+
+            let translation = position.isometry().translation.vector;
+            let acceleration = attractors
+                .iter()
+                .filter(|(attractor_entity, ..)| *attractor_entity != entity)
+                .fold(Vector3::zeros(), |acceleration, (_, attractor, center)| {
+                    acceleration + attractor.acceleration(center, &translation)
+                });
+
+            if acceleration != Vector3::zeros() {
+                physics_body
+                    .apply_external_force(&Force3::linear(acceleration * physics_body.mass));
+            }
+        }
+    }
+}
+
+impl<N, P> Default for ApplyAttractorsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+            p_marker: PhantomData,
+        }
+    }
+}
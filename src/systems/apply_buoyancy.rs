@@ -0,0 +1,141 @@
+use std::marker::PhantomData;
+
+use specs::{Join, Read, ReadStorage, System, Write, WriteStorage};
+
+use crate::{
+    bodies::{PhysicsBody, Position},
+    forces::{Buoyancy, WaterSurface},
+    nalgebra::{RealField, Vector3},
+    nphysics::{algebra::Force3, object::BodyStatus},
+    parameters::{Gravity, TimeStep},
+};
+
+/// The `ApplyBuoyancySystem` pushes `Buoyancy` bodies up against the `Gravity`
+/// while they are submerged below the `WaterSurface`, which is sampled at the
+/// `Position` of each body, and advances the time of the `WaterSurface` by the
+/// `TimeStep`.
+pub struct ApplyBuoyancySystem<N, P> {
+    n_marker: PhantomData<N>,
+    p_marker: PhantomData<P>,
+}
+
+impl<'s, N, P> System<'s> for ApplyBuoyancySystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    type SystemData = (
+        Option<Read<'s, Gravity<N>>>,
+        Option<Read<'s, TimeStep<N>>>,
+        Write<'s, WaterSurface<N>>,
+        ReadStorage<'s, Buoyancy<N>>,
+        ReadStorage<'s, P>,
+        WriteStorage<'s, PhysicsBody<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (gravity, time_step, mut water_surface, buoyancies, positions, mut physics_bodies) =
+            data;
+        let gravity = gravity.map_or_else(Vector3::zeros, |gravity| gravity.0);
+        let dt = time_step.map_or_else(|| TimeStep::<N>::default().0, |time_step| time_step.0);
+        water_surface.elapsed += dt;
+
+        for (buoyancy, position, physics_body) in
+            (&buoyancies, &positions, &mut physics_bodies).join()
+        {
+            if physics_body.body_status != BodyStatus::Dynamic {
+                continue;
+            }
+
+            let translation = position.isometry().translation.vector;
+            let water_height = water_surface.height_at(translation.x, translation.z);
+            let fraction = buoyancy.submerged_fraction(&translation, water_height);
+            if fraction <= N::zero() {
+                continue;
+            }
+
+            let acceleration = -gravity * (buoyancy.buoyancy * fraction)
+                - physics_body.velocity.linear * (buoyancy.damping * fraction);
+            physics_body.apply_external_force(&Force3::linear(acceleration * physics_body.mass));
+        }
+    }
+}
+
+impl<N, P> Default for ApplyBuoyancySystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+            p_marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        forces::{Buoyancy, WaterSurface},
+        nalgebra::{Isometry3, Vector3},
+        nphysics::object::BodyStatus,
+        parameters::{Gravity, TimeStep},
+        systems::ApplyBuoyancySystem,
+        PhysicsBody,
+        PhysicsBodyBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn follow_water_surface() {
+        let mut world = World::new();
+        let mut system = ApplyBuoyancySystem::<f32, SimplePosition<f32>>::default();
+        RunNow::setup(&mut system, &mut world);
+        world.insert(Gravity(Vector3::<f32>::new(0.0, -10.0, 0.0)));
+
+        // a sloped surface rising along the x axis, so bodies at the same height
+        // are dry, partially or fully submerged depending on where they float
+        world.insert(WaterSurface::<f32>::new(|x, _, _| 0.5 * x));
+        let buoyancy = Buoyancy {
+            half_height: 0.5f32,
+            buoyancy: 2.0,
+            damping: 1.0,
+        };
+        let mut float_at = |x: f32| {
+            world
+                .create_entity()
+                .with(SimplePosition::<f32>(Isometry3::translation(x, 0.0, 0.0)))
+                .with(
+                    PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                        .mass(3.0)
+                        .build(),
+                )
+                .with(buoyancy)
+                .build()
+        };
+        let dry = float_at(-2.0);
+        let partial = float_at(0.5);
+        let submerged = float_at(2.0);
+
+        system.run_now(&world);
+
+        let physics_bodies = world.read_storage::<PhysicsBody<f32>>();
+        let force = |entity| {
+            physics_bodies
+                .get(entity)
+                .unwrap()
+                .check_external_force()
+                .linear
+        };
+        assert_eq!(force(dry), Vector3::zeros());
+        assert!((force(partial) - Vector3::new(0.0, 45.0, 0.0)).norm() < 1.0e-4);
+        assert!((force(submerged) - Vector3::new(0.0, 60.0, 0.0)).norm() < 1.0e-4);
+        assert_eq!(
+            world.read_resource::<WaterSurface<f32>>().elapsed,
+            TimeStep::<f32>::default().0
+        );
+    }
+}
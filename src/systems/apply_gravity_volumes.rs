@@ -0,0 +1,79 @@
+use std::marker::PhantomData;
+
+use specs::{Join, Read, ReadStorage, System, WriteStorage};
+
+use crate::{
+    bodies::{PhysicsBody, Position},
+    forces::GravityVolume,
+    nalgebra::{Point3, RealField, Vector3},
+    nphysics::{algebra::Force3, object::BodyStatus},
+    parameters::Gravity,
+};
+
+/// The `ApplyGravityVolumesSystem` replaces the global `Gravity` with the
+/// gravity of the `GravityVolume` a `PhysicsBody` is located in. As nphysics
+/// keeps applying the global `Gravity`, the difference between both is
+/// applied as an external force.
+pub struct ApplyGravityVolumesSystem<N, P> {
+    n_marker: PhantomData<N>,
+    p_marker: PhantomData<P>,
+}
+
+impl<'s, N, P> System<'s> for ApplyGravityVolumesSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    type SystemData = (
+        Option<Read<'s, Gravity<N>>>,
+        ReadStorage<'s, GravityVolume<N>>,
+        ReadStorage<'s, P>,
+        WriteStorage<'s, PhysicsBody<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (gravity, gravity_volumes, positions, mut physics_bodies) = data;
+        let global_gravity = gravity.map_or_else(Vector3::zeros, |gravity| gravity.0);
+
+        // collect the volumes sorted by descending priority, so the first volume
+        // containing a body determines its gravity
+        let mut volumes = (&gravity_volumes, &positions)
+            .join()
+            .map(|(volume, position)| (*volume, *position.isometry()))
+            .collect::<Vec<_>>();
+        if volumes.is_empty() {
+            return;
+        }
+        volumes.sort_by(|a, b| b.0.priority.cmp(&a.0.priority));
+
+        for (position, physics_body) in (&positions, &mut physics_bodies).join() {
+            if physics_body.body_status != BodyStatus::Dynamic || !physics_body.gravity_enabled {
+                continue;
+            }
+
+            let translation = Point3::from(position.isometry().translation.vector);
+            let gravity = volumes
+                .iter()
+                .filter_map(|(volume, isometry)| volume.gravity_at(isometry, &translation))
+                .next();
+
+            if let Some(gravity) = gravity {
+                let force = Force3::linear((gravity - global_gravity) * physics_body.mass);
+                physics_body.apply_external_force(&force);
+            }
+        }
+    }
+}
+
+impl<N, P> Default for ApplyGravityVolumesSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+            p_marker: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::marker::PhantomData;
+
+use specs::{Entity, Read, ReadStorage, ReaderId, System, SystemData, World, Write, WriteExpect};
+
+use crate::{
+    bodies::PhysicsBody,
+    events::{ContactEvent, ContactEvents, ContactType, DamageEvent, DamageEvents},
+    handles::PhysicsHandles,
+    impacts::ImpactResponse,
+    nalgebra::{RealField, Vector3},
+    nphysics::object::{Body, BodyStatus},
+    Physics,
+};
+
+/// The `ApplyImpactResponsesSystem` knocks back the `Entity`s with an
+/// `ImpactResponse` when another collider starts touching them and emits a
+/// `DamageEvent` for every damaging impact. The impact speed is computed from
+/// the velocities stored in the `PhysicsBody`s, which still hold the
+/// velocities from before the step, so the system has to run after the
+/// `PhysicsStepperSystem` and before the `SyncBodiesFromPhysicsSystem`. It is
+/// not part of the default `Dispatcher`.
+pub struct ApplyImpactResponsesSystem<N> {
+    contact_events_reader_id: Option<ReaderId<ContactEvent>>,
+
+    n_marker: PhantomData<N>,
+}
+
+impl<'s, N: RealField> System<'s> for ApplyImpactResponsesSystem<N> {
+    type SystemData = (
+        Read<'s, ContactEvents>,
+        ReadStorage<'s, ImpactResponse<N>>,
+        ReadStorage<'s, PhysicsBody<N>>,
+        Read<'s, PhysicsHandles>,
+        Write<'s, DamageEvents<N>>,
+        WriteExpect<'s, Physics<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            contact_events,
+            impact_responses,
+            physics_bodies,
+            handles,
+            mut damage_events,
+            mut physics,
+        ) = data;
+
+        // colliders without a PhysicsBody on their own Entity are treated as
+        // resting
+        let velocity = |entity: Entity| {
+            physics_bodies
+                .get(entity)
+                .map_or_else(Vector3::zeros, |physics_body| physics_body.velocity.linear)
+        };
+
+        for contact_event in contact_events.read(self.contact_events_reader_id.as_mut().unwrap()) {
+            if let ContactType::Stopped = contact_event.contact_type {
+                continue;
+            }
+
+            let pairs = [
+                (contact_event.collider1, contact_event.collider2),
+                (contact_event.collider2, contact_event.collider1),
+            ];
+            for &(entity, source) in pairs.iter() {
+                let impact_response = match impact_responses.get(entity) {
+                    Some(impact_response) => impact_response,
+                    None => continue,
+                };
+
+                let relative_velocity = velocity(entity) - velocity(source);
+                let impact_speed = relative_velocity.norm();
+                if impact_speed <= N::default_epsilon() {
+                    continue;
+                }
+
+                // the knockback opposes the approach of both Entities
+                let is_dynamic = physics_bodies.get(entity).map_or(false, |physics_body| {
+                    physics_body.body_status == BodyStatus::Dynamic
+                });
+                if let Some(rigid_body) = handles
+                    .body_handle(entity)
+                    .filter(|_| is_dynamic)
+                    .and_then(|handle| physics.world.rigid_body_mut(handle))
+                {
+                    let mut knocked_back = *rigid_body.velocity();
+                    knocked_back.linear -= relative_velocity * impact_response.knockback_scale;
+                    rigid_body.set_velocity(knocked_back);
+                    rigid_body.activate();
+                }
+
+                let damage = impact_response.damage_curve.damage(impact_speed);
+                if damage > N::zero() {
+                    damage_events.single_write(DamageEvent {
+                        entity,
+                        source,
+                        damage,
+                        impact_speed,
+                        tick: contact_event.tick,
+                    });
+                }
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("ApplyImpactResponsesSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+
+        // register reader id for the ContactEvents
+        self.contact_events_reader_id = Some(res.fetch_mut::<ContactEvents>().register_reader());
+    }
+}
+
+impl<N> Default for ApplyImpactResponsesSystem<N>
+where
+    N: RealField,
+{
+    fn default() -> Self {
+        Self {
+            contact_events_reader_id: None,
+            n_marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        colliders::Shape,
+        events::DamageEvents,
+        impacts::{DamageCurve, ImpactResponse},
+        nalgebra::Isometry3,
+        nphysics::{algebra::Velocity3, object::BodyStatus},
+        systems::{
+            ApplyImpactResponsesSystem,
+            PhysicsStepperSystem,
+            SyncBodiesToPhysicsSystem,
+            SyncCollidersToPhysicsSystem,
+        },
+        PhysicsBodyBuilder,
+        PhysicsColliderBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn damage_on_impact() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncBodiesToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_bodies_to_physics_system",
+                &[],
+            )
+            .with(
+                SyncCollidersToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_colliders_to_physics_system",
+                &["sync_bodies_to_physics_system"],
+            )
+            .with(
+                PhysicsStepperSystem::<f32>::default(),
+                "physics_stepper_system",
+                &["sync_colliders_to_physics_system"],
+            )
+            .with(
+                ApplyImpactResponsesSystem::<f32>::default(),
+                "apply_impact_responses_system",
+                &["physics_stepper_system"],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+        let mut reader_id = world.fetch_mut::<DamageEvents<f32>>().register_reader();
+
+        let target = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(
+                PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                    .gravity_enabled(false)
+                    .build(),
+            )
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .with(ImpactResponse {
+                knockback_scale: 0.5,
+                damage_curve: DamageCurve::Linear {
+                    threshold: 1.0,
+                    factor: 2.0,
+                },
+            })
+            .build();
+        let projectile = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(
+                1.05, 0.0, 0.0,
+            )))
+            .with(
+                PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                    .gravity_enabled(false)
+                    .velocity(Velocity3::linear(-10.0, 0.0, 0.0))
+                    .build(),
+            )
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .build();
+        for _ in 0..5 {
+            dispatcher.dispatch(&world);
+        }
+
+        // the projectile only hits the target, which takes (10 - 1) * 2 damage
+        let damage_events = world.fetch::<DamageEvents<f32>>();
+        let damage_event = damage_events.read(&mut reader_id).next().unwrap();
+        assert_eq!(damage_event.entity, target);
+        assert_eq!(damage_event.source, projectile);
+        assert!((damage_event.damage - 18.0).abs() < 1.0e-4);
+    }
+}
@@ -0,0 +1,270 @@
+use std::marker::PhantomData;
+
+use specs::{ReadStorage, System, SystemData, World, WriteExpect};
+
+use crate::{
+    bodies::PhysicsBody,
+    colliders::PhysicsCollider,
+    handles::entity_from_user_data,
+    nalgebra::{self as na, Point3, RealField, Unit, Vector3},
+    ncollide::{
+        query::{Ray, RayCast},
+        shape::{HeightField, TriMesh},
+        world::CollisionGroups,
+    },
+    nphysics::object::{Body, BodyStatus, Collider},
+    queries::{compare_toi, ray_hits},
+    Physics,
+};
+
+/// The distance from a contact at which the faces around it are probed.
+const EDGE_PROBE_DISTANCE: f64 = 0.05;
+/// The sine of the angle below which contact normals count as face normals.
+const FACE_NORMAL_TOLERANCE: f64 = 1.0e-3;
+/// The sine of the angle below which neighbouring faces count as coplanar.
+const COPLANAR_TOLERANCE: f64 = 1.0e-2;
+
+/// The `ApplyInternalEdgeCorrectionSystem` removes the bumps bodies receive
+/// from the internal edges of `TriMesh` and `HeightField` colliders with
+/// `smooth_edges` set. A contact on the edge between two coplanar faces has a
+/// normal tilted towards the edge, which kicks bodies rolling or sliding over
+/// it upwards and backwards. The nphysics `World` offers no hook to modify
+/// contacts while they are solved, so for every such contact the velocity
+/// gained along the face normal and lost along the tilt is undone after the
+/// step, compared to the velocity stored in the `PhysicsBody` before the step.
+/// The system has to run after the `PhysicsStepperSystem` and before the
+/// `SyncBodiesFromPhysicsSystem`. It is not part of the default `Dispatcher`.
+pub struct ApplyInternalEdgeCorrectionSystem<N> {
+    n_marker: PhantomData<N>,
+}
+
+impl<'s, N: RealField> System<'s> for ApplyInternalEdgeCorrectionSystem<N> {
+    type SystemData = (
+        ReadStorage<'s, PhysicsCollider<N>>,
+        ReadStorage<'s, PhysicsBody<N>>,
+        WriteExpect<'s, Physics<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (physics_colliders, physics_bodies, mut physics) = data;
+
+        let mut corrections = Vec::new();
+        {
+            let collider_world = physics.world.collider_world();
+            let smooths_edges = |collider: &Collider<N>| {
+                let shape = collider.shape();
+                (shape.as_shape::<TriMesh<N>>().is_some()
+                    || shape.as_shape::<HeightField<N>>().is_some())
+                    && entity_from_user_data(collider.user_data())
+                        .and_then(|entity| physics_colliders.get(entity))
+                        .map_or(false, |physics_collider| physics_collider.smooth_edges)
+            };
+
+            for (handle1, handle2, _, manifold) in
+                collider_world.as_collision_world().contact_pairs(true)
+            {
+                let (collider1, collider2) = match (
+                    collider_world.collider(handle1),
+                    collider_world.collider(handle2),
+                ) {
+                    (Some(collider1), Some(collider2)) => (collider1, collider2),
+                    _ => continue,
+                };
+
+                // the contact normals point from the first to the second collider
+                let (mesh, other, mesh_first) = if smooths_edges(collider1) {
+                    (collider1, collider2, true)
+                } else if smooths_edges(collider2) {
+                    (collider2, collider1, false)
+                } else {
+                    continue;
+                };
+                let is_dynamic = physics
+                    .world
+                    .rigid_body(other.body())
+                    .map_or(false, |rigid_body| {
+                        rigid_body.status() == BodyStatus::Dynamic
+                    });
+                if !is_dynamic {
+                    continue;
+                }
+
+                for tracked_contact in manifold.contacts() {
+                    let contact = &tracked_contact.contact;
+                    let (point, normal) = if mesh_first {
+                        (contact.world1, contact.normal.into_inner())
+                    } else {
+                        (contact.world2, -contact.normal.into_inner())
+                    };
+                    if let Some((face_normal, tilt)) =
+                        internal_edge(&physics, &physics_colliders, mesh, &point, &normal)
+                    {
+                        corrections.push((other.body(), face_normal, tilt));
+                    }
+                }
+            }
+        }
+
+        for (handle, face_normal, tilt) in corrections {
+            let pre_step = match physics
+                .body_entity(handle)
+                .and_then(|entity| physics_bodies.get(entity))
+            {
+                Some(physics_body) => physics_body.velocity.linear,
+                None => continue,
+            };
+            if let Some(rigid_body) = physics.world.rigid_body_mut(handle) {
+                let mut velocity = *rigid_body.velocity();
+                velocity.linear = smooth_velocity(&pre_step, &velocity.linear, &face_normal, &tilt);
+                rigid_body.set_velocity(velocity);
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("ApplyInternalEdgeCorrectionSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+    }
+}
+
+impl<N> Default for ApplyInternalEdgeCorrectionSystem<N>
+where
+    N: RealField,
+{
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+        }
+    }
+}
+
+/// Checks whether the contact at the given `point` of the `mesh` lies on an
+/// edge between coplanar faces. Returns the normal of the faces and the
+/// direction the contact `normal` is tilted in within their plane.
+fn internal_edge<N: RealField>(
+    physics: &Physics<N>,
+    physics_colliders: &ReadStorage<PhysicsCollider<N>>,
+    mesh: &Collider<N>,
+    point: &Point3<N>,
+    normal: &Vector3<N>,
+) -> Option<(Unit<Vector3<N>>, Unit<Vector3<N>>)> {
+    let probe: N = na::convert(EDGE_PROBE_DISTANCE);
+    let face_normal = |origin: &Point3<N>, up: &Vector3<N>| {
+        face_normal(physics, physics_colliders, mesh, origin, up)
+    };
+    let face = face_normal(&(point + normal * probe), normal)?;
+    let up = face.into_inner();
+
+    // contacts on the faces themselves need no correction
+    let tilt = Unit::try_new(
+        normal - up * normal.dot(&up),
+        na::convert(FACE_NORMAL_TOLERANCE),
+    )?;
+
+    // the faces on both sides of the edge have to lie in the same plane, real
+    // corners keep their contacts
+    let tolerance: N = na::convert(COPLANAR_TOLERANCE);
+    for &side in &[probe, -probe] {
+        let origin = point + tilt.into_inner() * side + up * probe;
+        let other = face_normal(&origin, &up)?.into_inner();
+        if other.dot(&up) <= N::zero() || other.cross(&up).norm() > tolerance {
+            return None;
+        }
+    }
+    Some((face, tilt))
+}
+
+/// Casts a short ray from the `origin` against the `up` direction onto the
+/// `mesh` and returns the normal of the hit face, facing `up`. Rays missing
+/// the `mesh` continue onto the neighbouring colliders with `smooth_edges`
+/// set, which stitches the seams between e.g. the chunks of a terrain.
+fn face_normal<N: RealField>(
+    physics: &Physics<N>,
+    physics_colliders: &ReadStorage<PhysicsCollider<N>>,
+    mesh: &Collider<N>,
+    origin: &Point3<N>,
+    up: &Vector3<N>,
+) -> Option<Unit<Vector3<N>>> {
+    let two: N = na::convert(2.0);
+    let max_toi = two * na::convert::<f64, N>(EDGE_PROBE_DISTANCE);
+    let ray = Ray::new(*origin, -up);
+    let normal = mesh
+        .shape()
+        .toi_and_normal_with_ray(mesh.position(), &ray, false)
+        .filter(|intersection| intersection.toi <= max_toi)
+        .map(|intersection| intersection.normal)
+        .or_else(|| {
+            let collision_groups = CollisionGroups::default();
+            ray_hits(physics, &ray, &collision_groups, max_toi, |entity| {
+                physics_colliders
+                    .get(entity)
+                    .map_or(false, |physics_collider| physics_collider.smooth_edges)
+            })
+            .min_by(compare_toi)
+            .map(|hit| hit.normal)
+        })?;
+
+    let normal = if normal.dot(up) < N::zero() {
+        -normal
+    } else {
+        normal
+    };
+    Unit::try_new(normal, N::default_epsilon())
+}
+
+/// Removes the velocity an internal edge added along the `face_normal` and
+/// against the `tilt` during the step. Bodies which were dropping onto the
+/// faces rather than moving along them keep their bounce.
+fn smooth_velocity<N: RealField>(
+    pre_step: &Vector3<N>,
+    velocity: &Vector3<N>,
+    face_normal: &Unit<Vector3<N>>,
+    tilt: &Unit<Vector3<N>>,
+) -> Vector3<N> {
+    let (up, tilt) = (face_normal.into_inner(), tilt.into_inner());
+    let approach = pre_step.dot(&up);
+    let sliding = (pre_step - up * approach).norm();
+    if -approach > sliding {
+        return *velocity;
+    }
+
+    let mut velocity = *velocity;
+    let separation = velocity.dot(&up) - approach.max(N::zero());
+    if separation > N::zero() {
+        velocity -= up * separation;
+    }
+    let pushed_back = velocity.dot(&tilt) - pre_step.dot(&tilt);
+    if pushed_back > N::zero() {
+        velocity -= tilt * pushed_back;
+    }
+    velocity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::smooth_velocity;
+    use crate::nalgebra::{Unit, Vector3};
+
+    #[test]
+    fn remove_edge_bump() {
+        let face_normal = Vector3::y_axis();
+        let tilt = Unit::new_normalize(Vector3::new(1.0f32, 0.0, 0.0));
+
+        // rolling towards -x, the edge kicked the body up and back
+        let pre_step = Vector3::new(-4.0, -0.1, 0.0);
+        let bumped = Vector3::new(-3.5, 0.8, 0.0);
+        let smoothed = smooth_velocity(&pre_step, &bumped, &face_normal, &tilt);
+        assert!((smoothed - Vector3::new(-4.0, 0.0, 0.0)).norm() < 1.0e-6);
+
+        // a body dropped onto the edge keeps its bounce
+        let pre_step = Vector3::new(0.0, -5.0, 0.0);
+        let bounced = Vector3::new(0.5, 2.0, 0.0);
+        assert_eq!(
+            smooth_velocity(&pre_step, &bounced, &face_normal, &tilt),
+            bounced
+        );
+    }
+}
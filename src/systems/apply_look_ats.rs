@@ -0,0 +1,145 @@
+use std::marker::PhantomData;
+
+use specs::{Join, ReadStorage, System, WriteStorage};
+
+use crate::{
+    bodies::{PhysicsBody, Position},
+    forces::{LookAt, LookAtTarget},
+    nalgebra::{Point3, RealField, Vector3},
+    nphysics::{algebra::Force3, object::BodyStatus},
+};
+
+/// The `ApplyLookAtsSystem` converts `LookAt`s into external torques on the
+/// `PhysicsBody` of the same `Entity`. `LookAt`s targeting an `Entity` without
+/// a `Position` apply no torque.
+pub struct ApplyLookAtsSystem<N, P> {
+    n_marker: PhantomData<N>,
+    p_marker: PhantomData<P>,
+}
+
+impl<'s, N, P> System<'s> for ApplyLookAtsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    type SystemData = (
+        ReadStorage<'s, LookAt<N>>,
+        ReadStorage<'s, P>,
+        WriteStorage<'s, PhysicsBody<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (look_ats, positions, mut physics_bodies) = data;
+
+        for (look_at, position, physics_body) in (&look_ats, &positions, &mut physics_bodies).join()
+        {
+            if physics_body.body_status != BodyStatus::Dynamic {
+                continue;
+            }
+
+            let target_position = match look_at.target {
+                LookAtTarget::Point(point) => point,
+                LookAtTarget::Entity(entity) => match positions.get(entity) {
+                    Some(target) => Point3::from(target.isometry().translation.vector),
+                    None => continue,
+                },
+            };
+
+            let isometry = position.isometry();
+            let acceleration = look_at.angular_acceleration(
+                &isometry.rotation,
+                &physics_body.velocity.angular,
+                &Point3::from(isometry.translation.vector),
+                &target_position,
+            );
+
+            // the angular inertia is expressed in local space; rotate it into world
+            // space before scaling the acceleration
+            let rotation_matrix = isometry.rotation.to_rotation_matrix();
+            let inertia = rotation_matrix.matrix()
+                * physics_body.angular_inertia
+                * rotation_matrix.matrix().transpose();
+
+            physics_body
+                .apply_external_force(&Force3::new(Vector3::zeros(), inertia * acceleration));
+        }
+    }
+}
+
+impl<N, P> Default for ApplyLookAtsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+            p_marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        bodies::Position,
+        forces::{LookAt, LookAtTarget},
+        nalgebra::{Isometry3, Matrix3, Vector3},
+        nphysics::object::BodyStatus,
+        systems::ApplyLookAtsSystem,
+        PhysicsBodyBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn turn_towards_target() {
+        let mut world = World::new();
+        let mut dispatcher_builder = DispatcherBuilder::new().with(
+            ApplyLookAtsSystem::<f32, SimplePosition<f32>>::default(),
+            "apply_look_ats_system",
+            &[],
+        );
+        crate::register_physics_systems_after::<f32, SimplePosition<f32>>(
+            &mut dispatcher_builder,
+            &["apply_look_ats_system"],
+        );
+        let mut dispatcher = dispatcher_builder.build();
+        dispatcher.setup(&mut world);
+
+        // a turret facing along z with its target off to the side on the x axis
+        let target = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(5.0, 0.0, 0.0)))
+            .build();
+        let turret = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(
+                PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                    .gravity_enabled(false)
+                    .mass(1.0)
+                    .angular_inertia(Matrix3::identity())
+                    .build(),
+            )
+            .with(LookAt::new(LookAtTarget::Entity(target), 40.0f32, 12.6))
+            .build();
+
+        let forward = |world: &World| {
+            let positions = world.read_storage::<SimplePosition<f32>>();
+            positions.get(turret).unwrap().isometry().rotation * Vector3::z()
+        };
+
+        // the turret starts turning towards the target after the first frame
+        dispatcher.dispatch(&world);
+        world.maintain();
+        assert!(forward(&world).x > 0.0);
+
+        for _ in 0..180 {
+            dispatcher.dispatch(&world);
+            world.maintain();
+        }
+        assert!(forward(&world).dot(&Vector3::x()) > 0.999);
+    }
+}
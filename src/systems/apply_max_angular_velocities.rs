@@ -0,0 +1,143 @@
+use std::marker::PhantomData;
+
+use specs::{Entities, Join, Read, ReadStorage, System, SystemData, World, WriteExpect};
+
+use crate::{
+    bodies::MaxAngularVelocity,
+    handles::PhysicsHandles,
+    nalgebra::RealField,
+    nphysics::algebra::Velocity3,
+    Physics,
+};
+
+/// The `ApplyMaxAngularVelocitiesSystem` scales down the angular velocity of
+/// every body spinning faster than its `MaxAngularVelocity`, keeping its
+/// linear velocity and axis of rotation. The limit has to hold for the
+/// velocities resulting from the integration and the contact resolution, so
+/// the system has to run after the `PhysicsStepperSystem` and before the
+/// `SyncBodiesFromPhysicsSystem`. It is not part of the default `Dispatcher`.
+pub struct ApplyMaxAngularVelocitiesSystem<N> {
+    n_marker: PhantomData<N>,
+}
+
+impl<'s, N: RealField> System<'s> for ApplyMaxAngularVelocitiesSystem<N> {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, MaxAngularVelocity<N>>,
+        Read<'s, PhysicsHandles>,
+        WriteExpect<'s, Physics<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, max_angular_velocities, handles, mut physics) = data;
+
+        for (entity, max_angular_velocity) in (&entities, &max_angular_velocities).join() {
+            let rigid_body = match handles
+                .body_handle(entity)
+                .and_then(|handle| physics.world.rigid_body_mut(handle))
+            {
+                Some(rigid_body) => rigid_body,
+                None => continue,
+            };
+
+            let velocity = *rigid_body.velocity();
+            let speed = velocity.angular.norm();
+            let max_speed = max_angular_velocity.0.max(N::zero());
+            if speed > max_speed {
+                rigid_body.set_velocity(Velocity3::new(
+                    velocity.linear,
+                    velocity.angular * (max_speed / speed),
+                ));
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("ApplyMaxAngularVelocitiesSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+    }
+}
+
+impl<N> Default for ApplyMaxAngularVelocitiesSystem<N>
+where
+    N: RealField,
+{
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        bodies::MaxAngularVelocity,
+        nalgebra::{Isometry3, Vector3},
+        nphysics::{algebra::Velocity3, object::BodyStatus},
+        systems::{
+            ApplyMaxAngularVelocitiesSystem,
+            PhysicsStepperSystem,
+            SyncBodiesFromPhysicsSystem,
+            SyncBodiesToPhysicsSystem,
+        },
+        PhysicsBody,
+        PhysicsBodyBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn clamp_spin() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncBodiesToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_bodies_to_physics_system",
+                &[],
+            )
+            .with(
+                PhysicsStepperSystem::<f32>::default(),
+                "physics_stepper_system",
+                &["sync_bodies_to_physics_system"],
+            )
+            .with(
+                ApplyMaxAngularVelocitiesSystem::<f32>::default(),
+                "apply_max_angular_velocities_system",
+                &["physics_stepper_system"],
+            )
+            .with(
+                SyncBodiesFromPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_bodies_from_physics_system",
+                &["apply_max_angular_velocities_system"],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+
+        // a prop spinning at roughly 1000 RPM, limited to 5 radians per second
+        let prop = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(
+                PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                    .gravity_enabled(false)
+                    .velocity(Velocity3::new(
+                        Vector3::new(1.0, 0.0, 0.0),
+                        Vector3::new(0.0, 100.0, 0.0),
+                    ))
+                    .build(),
+            )
+            .with(MaxAngularVelocity(5.0f32))
+            .build();
+        dispatcher.dispatch(&world);
+
+        let physics_bodies = world.read_storage::<PhysicsBody<f32>>();
+        let velocity = physics_bodies.get(prop).unwrap().velocity;
+        assert!((velocity.angular - Vector3::new(0.0, 5.0, 0.0)).norm() < 1.0e-3);
+        assert!((velocity.linear - Vector3::new(1.0, 0.0, 0.0)).norm() < 1.0e-3);
+    }
+}
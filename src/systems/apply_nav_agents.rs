@@ -0,0 +1,176 @@
+use std::marker::PhantomData;
+
+use specs::{Join, Read, ReadStorage, System, WriteStorage};
+
+use crate::{
+    bodies::PhysicsBody,
+    characters::{CharacterController, NavAgent},
+    nalgebra::{RealField, Vector3},
+    nphysics::{algebra::Force3, object::BodyStatus},
+    parameters::TimeStep,
+};
+
+/// The `ApplyNavAgentsSystem` moves the bodies of `NavAgent`s. Agents with a
+/// `CharacterController` hand their steered velocity to the controller,
+/// which keeps them on the ground, so this system has to run before the
+/// `MoveCharactersSystem`. Other kinematic bodies take the velocity directly,
+/// while dynamic bodies are accelerated horizontally with an external force,
+/// so they keep reacting to gravity and collisions. Either way it has to run
+/// before the `SyncBodiesToPhysicsSystem`.
+pub struct ApplyNavAgentsSystem<N> {
+    n_marker: PhantomData<N>,
+}
+
+impl<'s, N: RealField> System<'s> for ApplyNavAgentsSystem<N> {
+    type SystemData = (
+        Option<Read<'s, TimeStep<N>>>,
+        ReadStorage<'s, NavAgent<N>>,
+        WriteStorage<'s, CharacterController<N>>,
+        WriteStorage<'s, PhysicsBody<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (time_step, nav_agents, mut controllers, mut physics_bodies) = data;
+        let dt = time_step.map_or_else(|| TimeStep::<N>::default().0, |time_step| time_step.0);
+
+        for (nav_agent, controller, physics_body) in
+            (&nav_agents, (&mut controllers).maybe(), &mut physics_bodies).join()
+        {
+            match (physics_body.body_status, controller) {
+                (BodyStatus::Kinematic, Some(controller)) => {
+                    controller.desired_velocity = nav_agent.steer(&controller.desired_velocity, dt);
+                }
+                (BodyStatus::Kinematic, None) => {
+                    physics_body.velocity.linear =
+                        nav_agent.steer(&physics_body.velocity.linear, dt);
+                }
+                (BodyStatus::Dynamic, _) => {
+                    // only steer horizontally and leave the vertical movement to the
+                    // simulation
+                    let velocity = physics_body.velocity.linear;
+                    let steered = nav_agent.steer(&velocity, dt);
+                    let acceleration =
+                        Vector3::new(steered.x - velocity.x, N::zero(), steered.z - velocity.z)
+                            / dt;
+                    physics_body
+                        .apply_external_force(&Force3::linear(acceleration * physics_body.mass));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<N> Default for ApplyNavAgentsSystem<N>
+where
+    N: RealField,
+{
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        characters::{CharacterController, NavAgent},
+        nalgebra::{Isometry3, Vector3},
+        nphysics::{algebra::Velocity3, object::BodyStatus},
+        systems::ApplyNavAgentsSystem,
+        Physics,
+        PhysicsBody,
+        PhysicsBodyBuilder,
+        PhysicsHandles,
+        SimplePosition,
+    };
+
+    #[test]
+    fn steer_towards_desired_velocity() {
+        let mut world = World::new();
+        let mut dispatcher_builder = DispatcherBuilder::new().with(
+            ApplyNavAgentsSystem::<f32>::default(),
+            "apply_nav_agents_system",
+            &[],
+        );
+        crate::register_physics_systems_after::<f32, SimplePosition<f32>>(
+            &mut dispatcher_builder,
+            &["apply_nav_agents_system"],
+        );
+        let mut dispatcher = dispatcher_builder.build();
+        dispatcher.setup(&mut world);
+
+        let agent = |x: f32, y: f32, z: f32| {
+            let mut agent = NavAgent::new(3.5, 30.0);
+            agent.desired_velocity = Vector3::new(x, y, z);
+            agent
+        };
+        let kinematic = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Kinematic).build())
+            .with(agent(0.0, 0.0, 3.0))
+            .build();
+        let character = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(5.0, 0.0, 0.0)))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Kinematic).build())
+            .with(CharacterController::new(0.5, 0.3))
+            .with(agent(0.0, 0.0, 3.0))
+            .build();
+        // the vertical movement of dynamic bodies is left to the simulation
+        let dynamic = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(
+                -5.0, 0.0, 0.0,
+            )))
+            .with(
+                PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                    .gravity_enabled(false)
+                    .velocity(Velocity3::linear(0.0, -1.0, 0.0))
+                    .build(),
+            )
+            .with(agent(3.0, 1.0, 0.0))
+            .build();
+
+        // the acceleration is limited to 0.5 per step
+        dispatcher.dispatch(&world);
+        world.maintain();
+        {
+            let physics_bodies = world.read_storage::<PhysicsBody<f32>>();
+            let velocity = physics_bodies.get(kinematic).unwrap().velocity.linear;
+            assert!((velocity - Vector3::new(0.0, 0.0, 0.5)).norm() < 1.0e-5);
+        }
+
+        for _ in 0..59 {
+            dispatcher.dispatch(&world);
+            world.maintain();
+        }
+
+        let physics_bodies = world.read_storage::<PhysicsBody<f32>>();
+        let velocity = physics_bodies.get(kinematic).unwrap().velocity.linear;
+        assert!((velocity - Vector3::new(0.0, 0.0, 3.0)).norm() < 1.0e-5);
+        let velocity = physics_bodies.get(dynamic).unwrap().velocity.linear;
+        assert!((velocity - Vector3::new(3.0, -1.0, 0.0)).norm() < 1.0e-3);
+
+        // characters are moved by their controller instead
+        let controllers = world.read_storage::<CharacterController<f32>>();
+        let desired_velocity = controllers.get(character).unwrap().desired_velocity;
+        assert!((desired_velocity - Vector3::new(0.0, 0.0, 3.0)).norm() < 1.0e-5);
+        assert_eq!(
+            physics_bodies.get(character).unwrap().velocity.linear,
+            Vector3::zeros()
+        );
+
+        // the kinematic body moved with the velocity of every step
+        let position = world
+            .read_resource::<Physics<f32>>()
+            .body_position(&world.read_resource::<PhysicsHandles>(), kinematic)
+            .unwrap();
+        let travelled = (0.5 + 1.0 + 1.5 + 2.0 + 2.5 + 3.0 * 55.0) / 60.0;
+        assert!((position.translation.vector.z - travelled).abs() < 1.0e-3);
+    }
+}
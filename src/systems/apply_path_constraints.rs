@@ -0,0 +1,166 @@
+use std::marker::PhantomData;
+
+use specs::{Join, Read, ReadStorage, System, WriteStorage};
+
+use crate::{
+    bodies::{PhysicsBody, Position},
+    motion::PathConstraint,
+    nalgebra::{self as na, Point3, RealField},
+    nphysics::object::BodyStatus,
+    parameters::TimeStep,
+};
+
+/// The `ApplyPathConstraintsSystem` locates dynamic and kinematic bodies with
+/// a `PathConstraint` on their `Spline` and replaces their linear velocity
+/// with its component along the tangent of the path, accelerated by the
+/// `PathMotor`, plus the velocity correcting the distance to the path within
+/// the next step. The system is not part of the default `Dispatcher` and has
+/// to run before the `SyncBodiesToPhysicsSystem`.
+pub struct ApplyPathConstraintsSystem<N, P> {
+    n_marker: PhantomData<N>,
+    p_marker: PhantomData<P>,
+}
+
+impl<'s, N, P> System<'s> for ApplyPathConstraintsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    type SystemData = (
+        Option<Read<'s, TimeStep<N>>>,
+        WriteStorage<'s, PathConstraint<N>>,
+        ReadStorage<'s, P>,
+        WriteStorage<'s, PhysicsBody<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (time_step, mut path_constraints, positions, mut physics_bodies) = data;
+        let dt = time_step.map_or_else(|| TimeStep::<N>::default().0, |time_step| time_step.0);
+
+        for (path_constraint, position, physics_body) in
+            (&mut path_constraints, &positions, &mut physics_bodies).join()
+        {
+            match physics_body.body_status {
+                BodyStatus::Dynamic | BodyStatus::Kinematic => {}
+                _ => continue,
+            }
+
+            // follow the parameter of the last step, so self-intersecting paths
+            // do not make the body jump between their branches
+            let spline = &path_constraint.spline;
+            let point = Point3::from(position.isometry().translation.vector);
+            let param = match path_constraint.param {
+                Some(hint) => spline.closest_param_near(&point, hint),
+                None => spline.closest_param(&point),
+            };
+            path_constraint.param = Some(param);
+
+            let tangent = match spline.tangent(param).try_normalize(N::default_epsilon()) {
+                Some(tangent) => tangent,
+                None => continue,
+            };
+            let mut speed = physics_body.velocity.linear.dot(&tangent);
+            if let Some(motor) = path_constraint.motor {
+                let max_change = motor.max_acceleration * dt;
+                speed += na::clamp(motor.target_speed - speed, -max_change, max_change);
+            }
+
+            // open paths end in stops
+            let end: N = na::convert(spline.segments() as f64);
+            if !spline.is_closed()
+                && ((param <= N::zero() && speed < N::zero())
+                    || (param >= end && speed > N::zero()))
+            {
+                speed = N::zero();
+            }
+
+            let correction = (spline.point(param) - point) * (path_constraint.correction / dt);
+            physics_body.velocity.linear = tangent * speed + correction;
+        }
+    }
+}
+
+impl<N, P> Default for ApplyPathConstraintsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+            p_marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        motion::{PathConstraint, PathMotor, Spline},
+        nalgebra::{self as na, Isometry3, Point3},
+        nphysics::object::BodyStatus,
+        systems::ApplyPathConstraintsSystem,
+        PhysicsBody,
+        PhysicsBodyBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn drive_body_along_closed_path() {
+        let mut world = World::new();
+        let mut dispatcher_builder = DispatcherBuilder::new().with(
+            ApplyPathConstraintsSystem::<f32, SimplePosition<f32>>::default(),
+            "apply_path_constraints_system",
+            &[],
+        );
+        crate::register_physics_systems_after::<f32, SimplePosition<f32>>(
+            &mut dispatcher_builder,
+            &["apply_path_constraints_system"],
+        );
+        let mut dispatcher = dispatcher_builder.build();
+        dispatcher.setup(&mut world);
+
+        // a loop through the corners of a square, driven at 3 units per second
+        let spline = Spline::new(
+            vec![
+                Point3::new(5.0, 0.0, 0.0),
+                Point3::new(0.0, 0.0, 5.0),
+                Point3::new(-5.0, 0.0, 0.0),
+                Point3::new(0.0, 0.0, -5.0),
+            ],
+            true,
+        );
+        let cart = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(5.0, 0.0, 0.0)))
+            .with(
+                PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                    .gravity_enabled(false)
+                    .build(),
+            )
+            .with(PathConstraint::new(spline.clone()).with_motor(PathMotor {
+                target_speed: 3.0,
+                max_acceleration: 30.0,
+            }))
+            .build();
+
+        for _ in 0..120 {
+            dispatcher.dispatch(&world);
+        }
+
+        let position = world
+            .read_storage::<SimplePosition<f32>>()
+            .get(cart)
+            .unwrap()
+            .0;
+        let point = Point3::from(position.translation.vector);
+        let closest = spline.point(spline.closest_param(&point));
+        assert!(na::distance(&point, &closest) < 0.1);
+
+        let physics_bodies = world.read_storage::<PhysicsBody<f32>>();
+        let speed = physics_bodies.get(cart).unwrap().velocity.linear.norm();
+        assert!((speed - 3.0).abs() < 0.5);
+    }
+}
@@ -0,0 +1,65 @@
+use std::marker::PhantomData;
+
+use specs::{Join, Read, ReadStorage, System, WriteStorage};
+
+use crate::{
+    bodies::{PhysicsBody, Position},
+    forces::PdController,
+    nalgebra::RealField,
+    nphysics::{algebra::Force3, object::BodyStatus},
+    parameters::TimeStep,
+};
+
+/// The `ApplyPdControllersSystem` converts `PdController`s into external
+/// forces on the `PhysicsBody` of the same `Entity`.
+pub struct ApplyPdControllersSystem<N, P> {
+    n_marker: PhantomData<N>,
+    p_marker: PhantomData<P>,
+}
+
+impl<'s, N, P> System<'s> for ApplyPdControllersSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    type SystemData = (
+        Option<Read<'s, TimeStep<N>>>,
+        ReadStorage<'s, PdController<N>>,
+        ReadStorage<'s, P>,
+        WriteStorage<'s, PhysicsBody<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (time_step, controllers, positions, mut physics_bodies) = data;
+        let dt = time_step.map_or_else(|| TimeStep::<N>::default().0, |time_step| time_step.0);
+
+        for (controller, position, physics_body) in
+            (&controllers, &positions, &mut physics_bodies).join()
+        {
+            if physics_body.body_status != BodyStatus::Dynamic {
+                continue;
+            }
+
+            let acceleration = controller.acceleration(
+                &position.isometry().translation.vector,
+                &physics_body.velocity.linear,
+                dt,
+            );
+            let force = Force3::linear(acceleration * physics_body.mass);
+            physics_body.apply_external_force(&force);
+        }
+    }
+}
+
+impl<N, P> Default for ApplyPdControllersSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+            p_marker: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::marker::PhantomData;
+
+use log::Level;
+use specs::{Entities, Read, System, SystemData, World, Write, WriteExpect, WriteStorage};
+
+use crate::{
+    bodies::PhysicsBody,
+    commands::{PhysicsCommand, PhysicsCommands},
+    diagnostics::{DiagnosticKind, LogSource, PhysicsDiagnostics, PhysicsLogConfig, SystemLogger},
+    handles::PhysicsHandles,
+    nalgebra::RealField,
+    nphysics::{
+        algebra::ForceType,
+        object::{Body, RigidBody},
+    },
+    Physics,
+};
+
+/// The `ApplyPhysicsCommandsSystem` applies the `PhysicsCommand`s queued in the
+/// `PhysicsCommands` `Resource` to the nphysics `World`, in the order they were
+/// queued. It has to run after the bodies have been synchronised and before
+/// the `PhysicsStepperSystem`, so the commands take effect in the same frame.
+pub struct ApplyPhysicsCommandsSystem<N> {
+    n_marker: PhantomData<N>,
+}
+
+impl<'s, N: RealField> System<'s> for ApplyPhysicsCommandsSystem<N> {
+    type SystemData = (
+        Entities<'s>,
+        Option<Read<'s, PhysicsLogConfig>>,
+        Write<'s, PhysicsDiagnostics>,
+        Write<'s, PhysicsCommands<N>>,
+        WriteExpect<'s, Physics<N>>,
+        Write<'s, PhysicsHandles>,
+        WriteStorage<'s, PhysicsBody<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            log_config,
+            mut diagnostics,
+            mut commands,
+            mut physics,
+            mut handles,
+            mut physics_bodies,
+        ) = data;
+        let mut logger = SystemLogger::new(
+            log_config.as_ref().map(|config| &**config),
+            LogSource::Bodies,
+            &mut diagnostics,
+        );
+
+        for command in commands.drain() {
+            let entity = command.entity();
+            let id = entity.id();
+
+            // commands for dead Entities must not reach a body that recycled the index
+            let handle = match handles.body_handle(entity) {
+                Some(handle) if entities.is_alive(entity) => handle,
+                _ => {
+                    logger.log(
+                        module_path!(),
+                        Level::Warn,
+                        DiagnosticKind::CommandDropped(id),
+                        format_args!(
+                            "Dropping physics command for entity without body: {:?}",
+                            command
+                        ),
+                    );
+                    continue;
+                }
+            };
+
+            match command {
+                // removing the PhysicsBody as well keeps the Component from recreating
+                // the body during the next frame
+                PhysicsCommand::RemoveBody { .. } => {
+                    handles.body_handles.remove(&id);
+                    physics.world.remove_bodies(&[handle]);
+                    physics_bodies.remove(entity);
+                }
+                command => {
+                    if let Some(rigid_body) = physics.world.rigid_body_mut(handle) {
+                        apply_to_rigid_body(command, rigid_body);
+                    }
+                }
+            }
+
+            logger.log(
+                module_path!(),
+                Level::Debug,
+                DiagnosticKind::CommandApplied(id),
+                format_args!("Applied physics command: {:?}", command),
+            );
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("ApplyPhysicsCommandsSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+    }
+}
+
+impl<N> Default for ApplyPhysicsCommandsSystem<N>
+where
+    N: RealField,
+{
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+        }
+    }
+}
+
+fn apply_to_rigid_body<N: RealField>(command: PhysicsCommand<N>, rigid_body: &mut RigidBody<N>) {
+    match command {
+        PhysicsCommand::ApplyImpulse { impulse, .. } => {
+            rigid_body.apply_force(0, &impulse, ForceType::Impulse, true);
+        }
+        // sleeping bodies ignore their velocity and are not written back, they have
+        // to be woken up for the command to take effect
+        PhysicsCommand::SetVelocity { velocity, .. } => {
+            rigid_body.set_velocity(velocity);
+            rigid_body.activate();
+        }
+        PhysicsCommand::Teleport { isometry, .. } => {
+            rigid_body.set_position(isometry);
+            rigid_body.activate();
+        }
+        PhysicsCommand::RemoveBody { .. } => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        commands::PhysicsCommands,
+        handles::PhysicsHandles,
+        nalgebra::Isometry3,
+        nphysics::object::BodyStatus,
+        systems::{ApplyPhysicsCommandsSystem, SyncBodiesToPhysicsSystem},
+        Physics,
+        PhysicsBodyBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn teleport_body() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncBodiesToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_bodies_to_physics_system",
+                &[],
+            )
+            .with(
+                ApplyPhysicsCommandsSystem::<f32>::default(),
+                "apply_physics_commands_system",
+                &["sync_bodies_to_physics_system"],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+
+        let entity = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::<f32>::identity()))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .build();
+        dispatcher.dispatch(&world);
+
+        world
+            .read_resource::<PhysicsCommands<f32>>()
+            .teleport(entity, Isometry3::translation(0.0, 10.0, 0.0));
+        dispatcher.dispatch(&world);
+
+        let physics = world.read_resource::<Physics<f32>>();
+        let handle = world
+            .read_resource::<PhysicsHandles>()
+            .body_handle(entity)
+            .unwrap();
+        let rigid_body = physics.world.rigid_body(handle).unwrap();
+        assert_eq!(rigid_body.position().translation.vector.y, 10.0);
+        assert!(world.read_resource::<PhysicsCommands<f32>>().is_empty());
+    }
+}
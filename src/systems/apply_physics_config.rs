@@ -0,0 +1,187 @@
+use log::Level;
+use specs::{Join, Read, System, SystemData, World, Write, WriteExpect, WriteStorage};
+
+use crate::{
+    colliders::PhysicsCollider,
+    diagnostics::{DiagnosticKind, LogSource, PhysicsDiagnostics, PhysicsLogConfig, SystemLogger},
+    nalgebra::RealField,
+    parameters::{Gravity, PhysicsConfig, PhysicsIntegrationParameters, PhysicsProfilingEnabled},
+    Physics,
+};
+
+/// The `ApplyPhysicsConfigSystem` applies changes of the `PhysicsConfig`
+/// `Resource` to the live simulation. Gravity, profiling and integration
+/// parameters are written to their `Resource`s, which are picked up by the
+/// `SyncParametersToPhysicsSystem`; material coefficients are applied to the
+/// nphysics `World` directly. The `CollisionMatrix` is applied to the
+/// `PhysicsCollider`s every frame, so it also covers colliders inserted after
+/// the `PhysicsConfig` changed.
+pub struct ApplyPhysicsConfigSystem<N: RealField> {
+    applied: Option<PhysicsConfig<N>>,
+}
+
+impl<'s, N: RealField> System<'s> for ApplyPhysicsConfigSystem<N> {
+    type SystemData = (
+        Option<Read<'s, PhysicsConfig<N>>>,
+        Option<Read<'s, PhysicsLogConfig>>,
+        Write<'s, PhysicsDiagnostics>,
+        Write<'s, Gravity<N>>,
+        Write<'s, PhysicsProfilingEnabled>,
+        Write<'s, PhysicsIntegrationParameters<N>>,
+        WriteExpect<'s, Physics<N>>,
+        WriteStorage<'s, PhysicsCollider<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            config,
+            log_config,
+            mut diagnostics,
+            mut gravity,
+            mut profiling,
+            mut integration_params,
+            mut physics,
+            mut physics_colliders,
+        ) = data;
+        let mut logger = SystemLogger::new(
+            log_config.as_ref().map(|config| &**config),
+            LogSource::Parameters,
+            &mut diagnostics,
+        );
+
+        let config = match config {
+            Some(config) => config,
+            None => return,
+        };
+
+        if self.applied.as_ref() != Some(&*config) {
+            let previous = self.applied.take();
+            let previous = previous.as_ref();
+
+            // only the changed values are written, so that Resources modified
+            // elsewhere are kept until the PhysicsConfig changes them again
+            if previous.map_or(true, |previous| previous.gravity != config.gravity) {
+                gravity.0 = config.gravity;
+            }
+            if previous.map_or(true, |previous| {
+                previous.profiling_enabled != config.profiling_enabled
+            }) {
+                profiling.0 = config.profiling_enabled;
+            }
+            if previous.map_or(true, |previous| {
+                previous.integration_parameters != config.integration_parameters
+            }) {
+                *integration_params = config.integration_parameters;
+            }
+
+            if previous.map_or(true, |previous| {
+                previous.material_coefficients != config.material_coefficients
+            }) {
+                let table = physics.world.materials_coefficients_table_mut();
+                if let Some(previous) = previous {
+                    for coefficients in &previous.material_coefficients {
+                        table.unset_friction_coefficient(
+                            coefficients.material1,
+                            coefficients.material2,
+                        );
+                        table.unset_restitution_coefficient(
+                            coefficients.material1,
+                            coefficients.material2,
+                        );
+                    }
+                }
+                for coefficients in &config.material_coefficients {
+                    if let Some(friction) = coefficients.friction {
+                        table.set_friction_coefficient(
+                            coefficients.material1,
+                            coefficients.material2,
+                            friction,
+                        );
+                    }
+                    if let Some(restitution) = coefficients.restitution {
+                        table.set_restitution_coefficient(
+                            coefficients.material1,
+                            coefficients.material2,
+                            restitution,
+                        );
+                    }
+                }
+            }
+
+            logger.log(
+                module_path!(),
+                Level::Info,
+                DiagnosticKind::ConfigApplied,
+                format_args!("Applied physics configuration: {:?}", *config),
+            );
+            self.applied = Some(config.clone());
+        }
+
+        // modifying the CollisionGroups flags the PhysicsCollider, which makes the
+        // SyncCollidersToPhysicsSystem update the nphysics World
+        if !config.collision_matrix.0.is_empty() {
+            for mut physics_collider in (&mut physics_colliders.restrict_mut()).join() {
+                let mut collision_groups = physics_collider.get_unchecked().collision_groups;
+                if config.collision_matrix.apply(&mut collision_groups) {
+                    physics_collider.get_mut_unchecked().collision_groups = collision_groups;
+                }
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("ApplyPhysicsConfigSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+    }
+}
+
+impl<N> Default for ApplyPhysicsConfigSystem<N>
+where
+    N: RealField,
+{
+    fn default() -> Self {
+        Self { applied: None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        nalgebra::Vector3,
+        parameters::{Gravity, PhysicsConfig},
+        systems::ApplyPhysicsConfigSystem,
+    };
+
+    #[test]
+    fn reload_gravity() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                ApplyPhysicsConfigSystem::<f32>::default(),
+                "apply_physics_config_system",
+                &[],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+
+        world.insert(PhysicsConfig::<f32> {
+            gravity: Vector3::new(0.0, -9.81, 0.0),
+            ..PhysicsConfig::default()
+        });
+        dispatcher.dispatch(&world);
+        assert_eq!(world.read_resource::<Gravity<f32>>().y, -9.81);
+
+        // unchanged values do not overwrite modified Resources
+        world.write_resource::<Gravity<f32>>().y = -1.0;
+        world
+            .write_resource::<PhysicsConfig<f32>>()
+            .profiling_enabled = true;
+        dispatcher.dispatch(&world);
+        assert_eq!(world.read_resource::<Gravity<f32>>().y, -1.0);
+    }
+}
@@ -0,0 +1,119 @@
+use std::marker::PhantomData;
+
+use specs::{Join, Read, ReadStorage, System, WriteStorage};
+
+use crate::{
+    bodies::{PhysicsBody, Position},
+    motion::ScriptedMotion,
+    nalgebra::RealField,
+    nphysics::object::BodyStatus,
+    parameters::TimeStep,
+};
+
+/// The `ApplyScriptedMotionSystem` advances every `ScriptedMotion` by one
+/// `TimeStep` and sets the velocity of the kinematic `PhysicsBody` of the
+/// same `Entity` so that it reaches the pose of the advanced time within the
+/// step. Bodies which are not kinematic are left untouched. The system is not
+/// part of the default `Dispatcher` and has to run before the
+/// `SyncBodiesToPhysicsSystem`.
+pub struct ApplyScriptedMotionSystem<N, P> {
+    n_marker: PhantomData<N>,
+    p_marker: PhantomData<P>,
+}
+
+impl<'s, N, P> System<'s> for ApplyScriptedMotionSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    type SystemData = (
+        Option<Read<'s, TimeStep<N>>>,
+        WriteStorage<'s, ScriptedMotion<N>>,
+        ReadStorage<'s, P>,
+        WriteStorage<'s, PhysicsBody<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (time_step, mut motions, positions, mut physics_bodies) = data;
+        let dt = time_step.map_or_else(|| TimeStep::<N>::default().0, |time_step| time_step.0);
+
+        for (motion, position, physics_body) in
+            (&mut motions, &positions, &mut physics_bodies).join()
+        {
+            if physics_body.body_status != BodyStatus::Kinematic {
+                continue;
+            }
+
+            motion.time += dt * motion.speed;
+            let current = position.isometry();
+            let target = motion.pose_at(motion.time);
+
+            physics_body.velocity.linear =
+                (target.translation.vector - current.translation.vector) / dt;
+            physics_body.velocity.angular =
+                (target.rotation * current.rotation.inverse()).scaled_axis() / dt;
+        }
+    }
+}
+
+impl<N, P> Default for ApplyScriptedMotionSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+            p_marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        motion::{Keyframe, ScriptedMotion},
+        nalgebra::{Isometry3, Vector3},
+        nphysics::object::BodyStatus,
+        systems::ApplyScriptedMotionSystem,
+        PhysicsBodyBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn follow_keyframes() {
+        let mut world = World::new();
+        let mut dispatcher_builder = DispatcherBuilder::new().with(
+            ApplyScriptedMotionSystem::<f32, SimplePosition<f32>>::default(),
+            "apply_scripted_motion_system",
+            &[],
+        );
+        crate::register_physics_systems_after::<f32, SimplePosition<f32>>(
+            &mut dispatcher_builder,
+            &["apply_scripted_motion_system"],
+        );
+        let mut dispatcher = dispatcher_builder.build();
+        dispatcher.setup(&mut world);
+
+        // a platform sliding one unit along x within one second
+        let platform = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Kinematic).build())
+            .with(ScriptedMotion::keyframes(vec![
+                Keyframe::new(0.0, Isometry3::identity()),
+                Keyframe::new(1.0, Isometry3::translation(1.0, 0.0, 0.0)),
+            ]))
+            .build();
+
+        for _ in 0..30 {
+            dispatcher.dispatch(&world);
+        }
+
+        let positions = world.read_storage::<SimplePosition<f32>>();
+        let translation = positions.get(platform).unwrap().0.translation.vector;
+        assert!((translation - Vector3::new(0.5, 0.0, 0.0)).norm() < 1.0e-3);
+    }
+}
@@ -0,0 +1,232 @@
+use std::marker::PhantomData;
+
+use specs::{Entities, Join, Read, ReadStorage, System, SystemData, World, Write, WriteExpect};
+
+use crate::{
+    bodies::{PhysicsBody, SimulationRate, SimulationRateFrame},
+    handles::PhysicsHandles,
+    nalgebra::{self as na, RealField},
+    nphysics::{
+        algebra::{Force3, ForceType},
+        object::{Body, BodyStatus},
+    },
+    Physics,
+};
+
+/// The `ApplySimulationRatesSystem` prepares the bodies with a reduced
+/// `SimulationRate` for the next step: bodies whose frame it is get their
+/// velocity and gravity scaled up, so that the regular time step covers the
+/// skipped frames, all others are frozen. It has to run right before the
+/// `PhysicsStepperSystem`, followed by the `RestoreSimulationRatesSystem`
+/// right after it.
+pub struct ApplySimulationRatesSystem<N> {
+    n_marker: PhantomData<N>,
+}
+
+impl<'s, N: RealField> System<'s> for ApplySimulationRatesSystem<N> {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, SimulationRate>,
+        ReadStorage<'s, PhysicsBody<N>>,
+        Read<'s, PhysicsHandles>,
+        Write<'s, SimulationRateFrame>,
+        WriteExpect<'s, Physics<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, simulation_rates, physics_bodies, handles, mut rate_frame, mut physics) =
+            data;
+
+        let rate_frame = &mut *rate_frame;
+        rate_frame.frame = rate_frame.frame.wrapping_add(1);
+        let gravity = *physics.world.gravity();
+
+        for (entity, simulation_rate, physics_body) in
+            (&entities, &simulation_rates, &physics_bodies).join()
+        {
+            let divisor = simulation_rate.divisor();
+            if divisor == 1 || physics_body.body_status != BodyStatus::Dynamic {
+                continue;
+            }
+
+            let handle = match handles.body_handle(entity) {
+                Some(handle) => handle,
+                None => continue,
+            };
+            let rigid_body = match physics.world.rigid_body_mut(handle) {
+                Some(rigid_body) if rigid_body.is_active() => rigid_body,
+                _ => continue,
+            };
+
+            // the frames of the bodies are staggered by their Entity index
+            if rate_frame.frame.wrapping_add(entity.id()) % divisor != 0 {
+                rigid_body.set_status(BodyStatus::Disabled);
+                rate_frame.frozen.push(handle);
+                continue;
+            }
+
+            // stepping with the scaled velocity and an additional acceleration of
+            // (k² - 1) * gravity matches a single step with k times the time step,
+            // once the velocity is scaled down again
+            let scale: N = na::convert(f64::from(divisor));
+            let mut velocity = *rigid_body.velocity();
+            velocity.linear *= scale;
+            velocity.angular *= scale;
+            rigid_body.set_velocity(velocity);
+            if rigid_body.gravity_enabled() {
+                rigid_body.apply_force(
+                    0,
+                    &Force3::linear(gravity * (scale * scale - N::one())),
+                    ForceType::AccelerationChange,
+                    false,
+                );
+            }
+            rate_frame.scaled.push((handle, divisor));
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("ApplySimulationRatesSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+    }
+}
+
+impl<N> Default for ApplySimulationRatesSystem<N>
+where
+    N: RealField,
+{
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+        }
+    }
+}
+
+/// The `RestoreSimulationRatesSystem` undoes the modifications of the
+/// `ApplySimulationRatesSystem` after the step, before the bodies are written
+/// back to their `Component`s.
+pub struct RestoreSimulationRatesSystem<N> {
+    n_marker: PhantomData<N>,
+}
+
+impl<'s, N: RealField> System<'s> for RestoreSimulationRatesSystem<N> {
+    type SystemData = (Write<'s, SimulationRateFrame>, WriteExpect<'s, Physics<N>>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut rate_frame, mut physics) = data;
+
+        for (handle, divisor) in rate_frame.scaled.drain(..) {
+            if let Some(rigid_body) = physics.world.rigid_body_mut(handle) {
+                let scale: N = na::convert(f64::from(divisor));
+                let mut velocity = *rigid_body.velocity();
+                velocity.linear /= scale;
+                velocity.angular /= scale;
+                rigid_body.set_velocity(velocity);
+            }
+        }
+
+        // frozen bodies resume with the velocity they had before
+        for handle in rate_frame.frozen.drain(..) {
+            if let Some(rigid_body) = physics.world.rigid_body_mut(handle) {
+                rigid_body.set_status(BodyStatus::Dynamic);
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("RestoreSimulationRatesSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+    }
+}
+
+impl<N> Default for RestoreSimulationRatesSystem<N>
+where
+    N: RealField,
+{
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        bodies::SimulationRate,
+        handles::PhysicsHandles,
+        nalgebra::{Isometry3, Vector3},
+        nphysics::{algebra::Velocity3, object::BodyStatus},
+        systems::{
+            ApplySimulationRatesSystem,
+            PhysicsStepperSystem,
+            RestoreSimulationRatesSystem,
+            SyncBodiesToPhysicsSystem,
+        },
+        Physics,
+        PhysicsBodyBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn half_rate_covers_distance() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncBodiesToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_bodies_to_physics_system",
+                &[],
+            )
+            .with(
+                ApplySimulationRatesSystem::<f32>::default(),
+                "apply_simulation_rates_system",
+                &["sync_bodies_to_physics_system"],
+            )
+            .with(
+                PhysicsStepperSystem::<f32>::default(),
+                "physics_stepper_system",
+                &["apply_simulation_rates_system"],
+            )
+            .with(
+                RestoreSimulationRatesSystem::<f32>::default(),
+                "restore_simulation_rates_system",
+                &["physics_stepper_system"],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+
+        let entity = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(
+                PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                    .gravity_enabled(false)
+                    .velocity(Velocity3::linear(1.0, 0.0, 0.0))
+                    .build(),
+            )
+            .with(SimulationRate::Half)
+            .build();
+        for _ in 0..4 {
+            dispatcher.dispatch(&world);
+        }
+
+        // four frames at half rate cover the same distance as four full frames
+        let physics = world.read_resource::<Physics<f32>>();
+        let handle = world
+            .read_resource::<PhysicsHandles>()
+            .body_handle(entity)
+            .unwrap();
+        let rigid_body = physics.world.rigid_body(handle).unwrap();
+        let expected = Vector3::new(4.0 * physics.timestep(), 0.0, 0.0);
+        assert!((rigid_body.position().translation.vector - expected).norm() < 1.0e-4);
+        assert_eq!(rigid_body.velocity().linear.x, 1.0);
+    }
+}
@@ -0,0 +1,186 @@
+use std::marker::PhantomData;
+
+use specs::{Read, ReadStorage, ReaderId, System, SystemData, World, WriteExpect};
+
+use crate::{
+    colliders::PhysicsCollider,
+    events::{ContactEvent, ContactEvents, ContactType},
+    handles::PhysicsHandles,
+    nalgebra::{RealField, Vector3},
+    nphysics::{
+        algebra::Velocity3,
+        object::{Body, BodyStatus},
+    },
+    Physics,
+};
+
+/// The `ApplyStopOnContactSystem` stops the dynamic bodies of
+/// `PhysicsCollider`s with `stop_on_contact` set when they start touching
+/// another collider: their velocity is set to the velocity of the body they
+/// hit, which removes both the restitution and any separating velocity of the
+/// contact. The nphysics `World` offers no hook to modify contacts while they
+/// are solved, so the velocities are corrected after the step instead; the
+/// system has to run after the `PhysicsStepperSystem` and before the
+/// `SyncBodiesFromPhysicsSystem`. It is not part of the default `Dispatcher`.
+pub struct ApplyStopOnContactSystem<N> {
+    contact_events_reader_id: Option<ReaderId<ContactEvent>>,
+
+    n_marker: PhantomData<N>,
+}
+
+impl<'s, N: RealField> System<'s> for ApplyStopOnContactSystem<N> {
+    type SystemData = (
+        Read<'s, ContactEvents>,
+        ReadStorage<'s, PhysicsCollider<N>>,
+        Read<'s, PhysicsHandles>,
+        WriteExpect<'s, Physics<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (contact_events, physics_colliders, handles, mut physics) = data;
+
+        for contact_event in contact_events.read(self.contact_events_reader_id.as_mut().unwrap()) {
+            if let ContactType::Stopped = contact_event.contact_type {
+                continue;
+            }
+
+            let pairs = [
+                (contact_event.collider1, contact_event.collider2),
+                (contact_event.collider2, contact_event.collider1),
+            ];
+            for &(entity, other) in pairs.iter() {
+                let stops = physics_colliders
+                    .get(entity)
+                    .map_or(false, |physics_collider| physics_collider.stop_on_contact);
+                if !stops {
+                    continue;
+                }
+
+                // colliders without a body of their own are resting
+                let other_velocity = handles
+                    .body_handle(other)
+                    .and_then(|handle| physics.world.rigid_body(handle))
+                    .map_or_else(Vector3::zeros, |rigid_body| rigid_body.velocity().linear);
+                if let Some(rigid_body) = handles
+                    .body_handle(entity)
+                    .and_then(|handle| physics.world.rigid_body_mut(handle))
+                    .filter(|rigid_body| rigid_body.status() == BodyStatus::Dynamic)
+                {
+                    rigid_body.set_velocity(Velocity3::new(other_velocity, Vector3::zeros()));
+                }
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("ApplyStopOnContactSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+
+        // register reader id for the ContactEvents
+        self.contact_events_reader_id = Some(res.fetch_mut::<ContactEvents>().register_reader());
+    }
+}
+
+impl<N> Default for ApplyStopOnContactSystem<N>
+where
+    N: RealField,
+{
+    fn default() -> Self {
+        Self {
+            contact_events_reader_id: None,
+            n_marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        colliders::Shape,
+        nalgebra::{Isometry3, Vector3},
+        nphysics::{algebra::Velocity3, object::BodyStatus},
+        systems::{
+            ApplyStopOnContactSystem,
+            PhysicsStepperSystem,
+            SyncBodiesFromPhysicsSystem,
+            SyncBodiesToPhysicsSystem,
+            SyncCollidersToPhysicsSystem,
+        },
+        PhysicsBody,
+        PhysicsBodyBuilder,
+        PhysicsColliderBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn projectile_stops_at_wall() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncBodiesToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_bodies_to_physics_system",
+                &[],
+            )
+            .with(
+                SyncCollidersToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_colliders_to_physics_system",
+                &["sync_bodies_to_physics_system"],
+            )
+            .with(
+                PhysicsStepperSystem::<f32>::default(),
+                "physics_stepper_system",
+                &["sync_colliders_to_physics_system"],
+            )
+            .with(
+                ApplyStopOnContactSystem::<f32>::default(),
+                "apply_stop_on_contact_system",
+                &["physics_stepper_system"],
+            )
+            .with(
+                SyncBodiesFromPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_bodies_from_physics_system",
+                &["apply_stop_on_contact_system"],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+
+        // a static wall without a PhysicsBody and a bouncy ball flying towards it
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Cuboid {
+                    half_extents: Vector3::new(0.5, 5.0, 5.0),
+                })
+                .build(),
+            )
+            .build();
+        let projectile = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(1.0, 0.0, 0.0)))
+            .with(
+                PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                    .gravity_enabled(false)
+                    .velocity(Velocity3::linear(-10.0, 0.0, 0.0))
+                    .build(),
+            )
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.1 })
+                    .stop_on_contact(true)
+                    .build(),
+            )
+            .build();
+        for _ in 0..10 {
+            dispatcher.dispatch(&world);
+        }
+
+        let physics_bodies = world.read_storage::<PhysicsBody<f32>>();
+        let velocity = physics_bodies.get(projectile).unwrap().velocity.linear;
+        assert!(velocity.norm() < 1.0e-4);
+    }
+}
@@ -0,0 +1,115 @@
+use std::marker::PhantomData;
+
+use specs::{Join, Read, ReadStorage, System, WriteStorage};
+
+use crate::{
+    bodies::PhysicsBody,
+    forces::TopDownFriction,
+    nalgebra::RealField,
+    nphysics::{algebra::Force3, object::BodyStatus},
+    parameters::TimeStep,
+};
+
+/// The `ApplyTopDownFrictionSystem` converts `TopDownFriction`s into braking
+/// forces on the `PhysicsBody` of the same `Entity`.
+pub struct ApplyTopDownFrictionSystem<N> {
+    n_marker: PhantomData<N>,
+}
+
+impl<'s, N: RealField> System<'s> for ApplyTopDownFrictionSystem<N> {
+    type SystemData = (
+        Option<Read<'s, TimeStep<N>>>,
+        ReadStorage<'s, TopDownFriction<N>>,
+        WriteStorage<'s, PhysicsBody<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (time_step, frictions, mut physics_bodies) = data;
+        let dt = time_step.map_or_else(|| TimeStep::<N>::default().0, |time_step| time_step.0);
+
+        for (friction, physics_body) in (&frictions, &mut physics_bodies).join() {
+            if physics_body.body_status != BodyStatus::Dynamic {
+                continue;
+            }
+
+            let acceleration = friction.acceleration(&physics_body.velocity.linear, dt);
+            let force = Force3::linear(acceleration * physics_body.mass);
+            physics_body.apply_external_force(&force);
+        }
+    }
+}
+
+impl<N> Default for ApplyTopDownFrictionSystem<N>
+where
+    N: RealField,
+{
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        forces::TopDownFriction,
+        nalgebra::{Isometry3, Vector3},
+        nphysics::{algebra::Velocity3, object::BodyStatus},
+        systems::ApplyTopDownFrictionSystem,
+        PhysicsBody,
+        PhysicsBodyBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn decay_planar_velocity() {
+        let mut world = World::new();
+        let mut dispatcher_builder = DispatcherBuilder::new().with(
+            ApplyTopDownFrictionSystem::<f32>::default(),
+            "apply_top_down_friction_system",
+            &[],
+        );
+        crate::register_physics_systems_after::<f32, SimplePosition<f32>>(
+            &mut dispatcher_builder,
+            &["apply_top_down_friction_system"],
+        );
+        let mut dispatcher = dispatcher_builder.build();
+        dispatcher.setup(&mut world);
+
+        let sliding = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(
+                PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                    .gravity_enabled(false)
+                    .mass(2.0)
+                    .velocity(Velocity3::linear(4.0, 2.0, 3.0))
+                    .build(),
+            )
+            .with(TopDownFriction::new(2.0f32))
+            .build();
+
+        let velocity = |world: &World| {
+            let physics_bodies = world.read_storage::<PhysicsBody<f32>>();
+            physics_bodies.get(sliding).unwrap().velocity.linear
+        };
+
+        let mut previous = velocity(&world);
+        for _ in 0..60 {
+            dispatcher.dispatch(&world);
+            world.maintain();
+
+            let current = velocity(&world);
+            assert!(current.x < previous.x && current.z < previous.z);
+            previous = current;
+        }
+
+        // the planar velocity shrinks by 1 / (1 + friction * dt) each step while
+        // the movement along the normal is left untouched
+        let decay = (1.0f32 / (1.0 + 2.0 / 60.0)).powi(60);
+        assert!((previous - Vector3::new(4.0 * decay, 2.0, 3.0 * decay)).norm() < 1.0e-3);
+    }
+}
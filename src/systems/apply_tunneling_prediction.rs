@@ -0,0 +1,185 @@
+use std::marker::PhantomData;
+
+use specs::{
+    Entities,
+    Join,
+    ReadExpect,
+    ReadStorage,
+    System,
+    SystemData,
+    World,
+    Write,
+    WriteStorage,
+};
+
+use crate::{
+    bodies::PhysicsBody,
+    colliders::PhysicsCollider,
+    events::{TunnelingRiskEvent, TunnelingRiskEvents},
+    nalgebra::{self as na, RealField},
+    nphysics::object::BodyStatus,
+    Physics,
+};
+
+/// The `ApplyTunnelingPredictionSystem` compares the distance each dynamic
+/// body travels per step with the smallest extent of its collider. A body
+/// travelling more than half its own size per step can pass through thin
+/// colliders between two steps, so the `linear_prediction` of its
+/// `PhysicsCollider` is raised to cover the travel, which makes the physics
+/// `World` generate contacts before the colliders actually touch, and a
+/// `TunnelingRiskEvent` is emitted. The predictions are never lowered again.
+/// The system has to run before the `SyncCollidersToPhysicsSystem` and is not
+/// part of the default `Dispatcher`.
+pub struct ApplyTunnelingPredictionSystem<N> {
+    n_marker: PhantomData<N>,
+}
+
+impl<'s, N: RealField> System<'s> for ApplyTunnelingPredictionSystem<N> {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, PhysicsBody<N>>,
+        WriteStorage<'s, PhysicsCollider<N>>,
+        Write<'s, TunnelingRiskEvents<N>>,
+        ReadExpect<'s, Physics<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, physics_bodies, mut physics_colliders, mut tunneling_risk_events, physics) =
+            data;
+        let dt = physics.timestep();
+
+        // only flag the PhysicsColliders which actually change
+        let mut raised = Vec::new();
+        for (entity, physics_body, physics_collider) in
+            (&entities, &physics_bodies, &physics_colliders).join()
+        {
+            if physics_body.body_status != BodyStatus::Dynamic {
+                continue;
+            }
+
+            // the smallest extent of the collider as created in the physics
+            // World, including its margin
+            let size = match physics_collider
+                .handle
+                .and_then(|handle| physics.world.collider(handle))
+            {
+                Some(collider) => {
+                    let half_extents = collider.shape().local_aabb().half_extents();
+                    half_extents.x.min(half_extents.y).min(half_extents.z) * na::convert(2.0)
+                }
+                None => continue,
+            };
+
+            let travel = physics_body.velocity.linear.norm() * dt;
+            if travel > size * na::convert(0.5) && travel > physics_collider.linear_prediction {
+                raised.push((entity, travel, size));
+            }
+        }
+
+        for (entity, travel, size) in raised {
+            // leave some room for accelerating bodies, so the prediction is not
+            // raised on every step
+            let linear_prediction = travel * na::convert(1.5);
+            if let Some(physics_collider) = physics_colliders.get_mut(entity) {
+                physics_collider.linear_prediction = linear_prediction;
+            }
+            tunneling_risk_events.single_write(TunnelingRiskEvent {
+                entity,
+                travel,
+                size,
+                linear_prediction,
+            });
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("ApplyTunnelingPredictionSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+    }
+}
+
+impl<N> Default for ApplyTunnelingPredictionSystem<N>
+where
+    N: RealField,
+{
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        colliders::{PhysicsCollider, Shape},
+        events::TunnelingRiskEvents,
+        nalgebra::Isometry3,
+        nphysics::{algebra::Velocity3, object::BodyStatus},
+        systems::{
+            ApplyTunnelingPredictionSystem,
+            SyncBodiesToPhysicsSystem,
+            SyncCollidersToPhysicsSystem,
+        },
+        PhysicsBodyBuilder,
+        PhysicsColliderBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn raise_prediction_of_fast_body() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncBodiesToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_bodies_to_physics_system",
+                &[],
+            )
+            .with(
+                SyncCollidersToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_colliders_to_physics_system",
+                &["sync_bodies_to_physics_system"],
+            )
+            .with(
+                ApplyTunnelingPredictionSystem::<f32>::default(),
+                "apply_tunneling_prediction_system",
+                &["sync_colliders_to_physics_system"],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+        let mut reader_id = world
+            .fetch_mut::<TunnelingRiskEvents<f32>>()
+            .register_reader();
+
+        // a small bullet travelling a whole unit per step
+        let bullet = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(
+                PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                    .gravity_enabled(false)
+                    .velocity(Velocity3::linear(60.0, 0.0, 0.0))
+                    .build(),
+            )
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.05 })
+                    .margin(0.01)
+                    .build(),
+            )
+            .build();
+        dispatcher.dispatch(&world);
+
+        let tunneling_risk_events = world.fetch::<TunnelingRiskEvents<f32>>();
+        let tunneling_risk_event = tunneling_risk_events.read(&mut reader_id).next().unwrap();
+        assert_eq!(tunneling_risk_event.entity, bullet);
+
+        let physics_colliders = world.read_storage::<PhysicsCollider<f32>>();
+        let linear_prediction = physics_colliders.get(bullet).unwrap().linear_prediction;
+        assert!(linear_prediction >= tunneling_risk_event.travel);
+    }
+}
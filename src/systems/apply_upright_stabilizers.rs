@@ -0,0 +1,126 @@
+use std::marker::PhantomData;
+
+use specs::{Join, ReadStorage, System, WriteStorage};
+
+use crate::{
+    bodies::{PhysicsBody, Position},
+    forces::UprightStabilizer,
+    nalgebra::{RealField, Vector3},
+    nphysics::{algebra::Force3, object::BodyStatus},
+};
+
+/// The `ApplyUprightStabilizersSystem` converts `UprightStabilizer`s into
+/// external torques on the `PhysicsBody` of the same `Entity`.
+pub struct ApplyUprightStabilizersSystem<N, P> {
+    n_marker: PhantomData<N>,
+    p_marker: PhantomData<P>,
+}
+
+impl<'s, N, P> System<'s> for ApplyUprightStabilizersSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    type SystemData = (
+        ReadStorage<'s, UprightStabilizer<N>>,
+        ReadStorage<'s, P>,
+        WriteStorage<'s, PhysicsBody<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (stabilizers, positions, mut physics_bodies) = data;
+
+        for (stabilizer, position, physics_body) in
+            (&stabilizers, &positions, &mut physics_bodies).join()
+        {
+            if physics_body.body_status != BodyStatus::Dynamic {
+                continue;
+            }
+
+            let rotation = position.isometry().rotation;
+            let acceleration =
+                stabilizer.angular_acceleration(&rotation, &physics_body.velocity.angular);
+
+            // the angular inertia is expressed in local space; rotate it into world
+            // space before scaling the acceleration
+            let rotation_matrix = rotation.to_rotation_matrix();
+            let inertia = rotation_matrix.matrix()
+                * physics_body.angular_inertia
+                * rotation_matrix.matrix().transpose();
+
+            physics_body
+                .apply_external_force(&Force3::new(Vector3::zeros(), inertia * acceleration));
+        }
+    }
+}
+
+impl<N, P> Default for ApplyUprightStabilizersSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+            p_marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        bodies::Position,
+        forces::UprightStabilizer,
+        nalgebra::{Isometry3, Matrix3, Translation3, UnitQuaternion, Vector3},
+        nphysics::object::BodyStatus,
+        systems::ApplyUprightStabilizersSystem,
+        PhysicsBodyBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn converge_to_upright() {
+        let mut world = World::new();
+        let mut dispatcher_builder = DispatcherBuilder::new().with(
+            ApplyUprightStabilizersSystem::<f32, SimplePosition<f32>>::default(),
+            "apply_upright_stabilizers_system",
+            &[],
+        );
+        crate::register_physics_systems_after::<f32, SimplePosition<f32>>(
+            &mut dispatcher_builder,
+            &["apply_upright_stabilizers_system"],
+        );
+        let mut dispatcher = dispatcher_builder.build();
+        dispatcher.setup(&mut world);
+
+        // a hoverbike tilted sideways by roughly 35 degrees
+        let tilt = UnitQuaternion::from_axis_angle(&Vector3::x_axis(), 0.6);
+        let hoverbike = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::from_parts(
+                Translation3::identity(),
+                tilt,
+            )))
+            .with(
+                PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                    .gravity_enabled(false)
+                    .mass(1.0)
+                    .angular_inertia(Matrix3::identity())
+                    .build(),
+            )
+            .with(UprightStabilizer::new(40.0f32, 12.6))
+            .build();
+
+        for _ in 0..180 {
+            dispatcher.dispatch(&world);
+            world.maintain();
+        }
+
+        let positions = world.read_storage::<SimplePosition<f32>>();
+        let rotation = positions.get(hoverbike).unwrap().isometry().rotation;
+        assert!((rotation * Vector3::y_axis()).dot(&Vector3::y()) > 0.999);
+    }
+}
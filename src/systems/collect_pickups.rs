@@ -0,0 +1,255 @@
+use std::{collections::HashSet, marker::PhantomData};
+
+use specs::{
+    storage::ComponentEvent,
+    Entities,
+    Join,
+    Read,
+    ReadStorage,
+    ReaderId,
+    System,
+    SystemData,
+    World,
+    Write,
+    WriteStorage,
+};
+
+use crate::{
+    bodies::Position,
+    diagnostics::{LogSource, PhysicsDiagnostics, PhysicsLogConfig, SystemLogger},
+    events::{PickupEvent, PickupEvents},
+    nalgebra::{Point3, RealField},
+    sensors::{Pickup, PickupCollector, PickupGrid},
+};
+
+use super::{iterate_component_events, rescan_components, ComponentEvents};
+
+/// The `CollectPickupsSystem` sorts `Pickup`s into the `PickupGrid` and
+/// reports a `PickupEvent` whenever a `PickupCollector` reaches one. Only
+/// inserted, moved and removed `Pickup`s update the grid, so thousands of
+/// static `Pickup`s cost next to nothing. The system is independent of the
+/// physics `World` and not part of the default `Dispatcher`; it should run
+/// after the `Position`s of the collectors were synchronised.
+///
+/// # Examples
+/// ```
+/// use specs::DispatcherBuilder;
+/// use specs_physics::{systems::CollectPickupsSystem, SimplePosition};
+///
+/// let dispatcher = DispatcherBuilder::new()
+///     .with(
+///         CollectPickupsSystem::<f32, SimplePosition<f32>>::default(),
+///         "collect_pickups_system",
+///         &[],
+///     )
+///     .build();
+/// ```
+pub struct CollectPickupsSystem<N, P> {
+    positions_reader_id: Option<ReaderId<ComponentEvent>>,
+    pickups_reader_id: Option<ReaderId<ComponentEvent>>,
+    position_events: ComponentEvents,
+    pickup_events: ComponentEvents,
+    rescan: bool,
+
+    n_marker: PhantomData<N>,
+    p_marker: PhantomData<P>,
+}
+
+impl<'s, N, P> System<'s> for CollectPickupsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, P>,
+        ReadStorage<'s, Pickup<N>>,
+        ReadStorage<'s, PickupCollector<N>>,
+        Write<'s, PickupGrid<N>>,
+        Write<'s, PickupEvents>,
+        Option<Read<'s, PhysicsLogConfig>>,
+        Write<'s, PhysicsDiagnostics>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            positions,
+            pickups,
+            pickup_collectors,
+            mut grid,
+            mut pickup_events,
+            log_config,
+            mut diagnostics,
+        ) = data;
+        let mut logger = SystemLogger::new(
+            log_config.as_ref().map(|config| &**config),
+            LogSource::Colliders,
+            &mut diagnostics,
+        );
+
+        // collect all ComponentEvents for the Position storage
+        iterate_component_events(
+            &positions,
+            self.positions_reader_id.as_mut().unwrap(),
+            &mut self.position_events,
+            &mut logger,
+        );
+
+        // collect all ComponentEvents for the Pickup storage
+        iterate_component_events(
+            &pickups,
+            self.pickups_reader_id.as_mut().unwrap(),
+            &mut self.pickup_events,
+            &mut logger,
+        );
+
+        // Pickups inserted before the reader id was registered
+        if self.rescan {
+            self.rescan = false;
+            rescan_components(
+                &pickups,
+                &mut self.pickup_events.inserted,
+                |id| grid.contains(id),
+                &mut logger,
+            );
+        }
+
+        // handle removed events first, so that re-inserted Pickups are not
+        // removed again
+        for id in (&self.pickup_events.removed | &self.position_events.removed).join() {
+            grid.remove(id);
+        }
+
+        // inserted, modified and moved Pickups are (re-)sorted into the grid
+        let changed = (&self.position_events.inserted | &self.position_events.modified)
+            | (&self.pickup_events.inserted | &self.pickup_events.modified);
+        for (entity, position, pickup, _) in (&entities, &positions, &pickups, changed).join() {
+            let center = Point3::from(position.isometry().translation.vector);
+            grid.insert(entity, center, pickup.radius);
+        }
+
+        // report the Pickups collectors reached since the last run
+        let mut overlaps = HashSet::new();
+        for (collector, position, pickup_collector) in
+            (&entities, &positions, &pickup_collectors).join()
+        {
+            let point = Point3::from(position.isometry().translation.vector);
+            for pickup in grid.query(&point, pickup_collector.radius) {
+                let pair = (collector.id(), pickup.id());
+                if !grid.overlaps.contains(&pair) {
+                    logger.debug(
+                        module_path!(),
+                        format_args!("Collected Pickup with id: {}", pickup.id()),
+                    );
+                    pickup_events.single_write(PickupEvent { collector, pickup });
+                }
+                overlaps.insert(pair);
+            }
+        }
+        grid.overlaps = overlaps;
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("CollectPickupsSystem.setup");
+        Self::SystemData::setup(res);
+
+        // register reader id for the Position storage
+        let mut position_storage: WriteStorage<P> = SystemData::fetch(&res);
+        self.positions_reader_id = Some(position_storage.register_reader());
+
+        // register reader id for the Pickup storage
+        let mut pickup_storage: WriteStorage<Pickup<N>> = SystemData::fetch(&res);
+        self.pickups_reader_id = Some(pickup_storage.register_reader());
+        self.rescan = true;
+    }
+}
+
+impl<N, P> Default for CollectPickupsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    fn default() -> Self {
+        Self {
+            positions_reader_id: None,
+            pickups_reader_id: None,
+            position_events: ComponentEvents::default(),
+            pickup_events: ComponentEvents::default(),
+            rescan: false,
+            n_marker: PhantomData,
+            p_marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        events::PickupEvents,
+        nalgebra::Isometry3,
+        sensors::{Pickup, PickupCollector, PickupGrid},
+        systems::CollectPickupsSystem,
+        SimplePosition,
+    };
+
+    #[test]
+    fn collect_coins() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                CollectPickupsSystem::<f32, SimplePosition<f32>>::default(),
+                "collect_pickups_system",
+                &[],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+        let mut reader_id = world.write_resource::<PickupEvents>().register_reader();
+
+        // a row of coins one unit apart
+        let coins = (0..1000)
+            .map(|i| {
+                world
+                    .create_entity()
+                    .with(SimplePosition::<f32>(Isometry3::translation(
+                        i as f32, 0.0, 0.0,
+                    )))
+                    .with(Pickup::new(0.25))
+                    .build()
+            })
+            .collect::<Vec<_>>();
+        let player = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(
+                10.0, 0.5, 0.0,
+            )))
+            .with(PickupCollector::new(0.5))
+            .build();
+        dispatcher.dispatch(&world);
+
+        assert_eq!(world.read_resource::<PickupGrid<f32>>().len(), 1000);
+        {
+            let pickup_events = world.read_resource::<PickupEvents>();
+            let events = pickup_events.read(&mut reader_id).collect::<Vec<_>>();
+            assert_eq!(events.len(), 1);
+            assert_eq!(events[0].collector, player);
+            assert_eq!(events[0].pickup, coins[10]);
+        }
+
+        // staying on the coin does not report it again, removing it empties
+        // its cell
+        world.delete_entity(coins[10]).unwrap();
+        world.maintain();
+        dispatcher.dispatch(&world);
+        assert_eq!(
+            world
+                .read_resource::<PickupEvents>()
+                .read(&mut reader_id)
+                .count(),
+            0
+        );
+        assert_eq!(world.read_resource::<PickupGrid<f32>>().len(), 999);
+    }
+}
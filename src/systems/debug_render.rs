@@ -0,0 +1,405 @@
+use std::marker::PhantomData;
+
+use specs::{
+    Entities,
+    Entity,
+    Join,
+    Read,
+    ReadExpect,
+    ReadStorage,
+    System,
+    SystemData,
+    World,
+    Write,
+};
+
+use crate::{
+    debug::{
+        DebugRender,
+        AABB_COLOR,
+        ANCHOR_COLOR,
+        AXIS_COLOR,
+        CENTER_OF_MASS_COLOR,
+        CONTACT_COLOR,
+        LIMIT_COLOR,
+        MOTOR_COLOR,
+        SENSOR_COLOR,
+        SHAPE_COLOR,
+        VELOCITY_COLOR,
+    },
+    handles::PhysicsHandles,
+    joints::{Elevator, HingedDoor, HingedDoorPoses, WheelJoint},
+    nalgebra::{
+        self as na,
+        Isometry3,
+        Point3,
+        RealField,
+        Rotation3,
+        Unit,
+        UnitQuaternion,
+        Vector3,
+    },
+    ncollide::{
+        query::Proximity,
+        shape::{Ball, Capsule, Compound, Cuboid, Plane, Polyline, Shape},
+    },
+    nphysics::object::{Body, BodyPart, BodyStatus},
+    Physics,
+};
+
+use super::joint_body_handle;
+
+/// The `DebugRenderSystem` fills the `DebugRender` `Resource` with lines
+/// visualising the physics `World`: collider shapes and bounding boxes,
+/// contacts, sensor overlaps, body velocities, centers of mass and joints with
+/// their anchors, axes, limits and motor targets. Which of these are drawn is
+/// controlled by the toggles of the `DebugRender`. It should run after the
+/// `SyncBodiesFromPhysicsSystem` to draw the latest state.
+pub struct DebugRenderSystem<N> {
+    n_marker: PhantomData<N>,
+}
+
+impl<'s, N: RealField> System<'s> for DebugRenderSystem<N> {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, WheelJoint<N>>,
+        ReadStorage<'s, Elevator<N>>,
+        ReadStorage<'s, HingedDoor<N>>,
+        ReadExpect<'s, Physics<N>>,
+        Read<'s, PhysicsHandles>,
+        Option<Read<'s, HingedDoorPoses<N>>>,
+        Write<'s, DebugRender<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            wheel_joints,
+            elevators,
+            hinged_doors,
+            physics,
+            handles,
+            hinged_door_poses,
+            mut debug_render,
+        ) = data;
+        debug_render.clear();
+
+        if debug_render.shapes || debug_render.aabbs {
+            for collider in physics.world.colliders() {
+                if debug_render.shapes {
+                    draw_shape(
+                        collider.shape().as_ref(),
+                        collider.position(),
+                        &mut debug_render,
+                    );
+                }
+                // the bounding box of a half-space is infinite
+                if debug_render.aabbs && collider.shape().as_shape::<Plane<N>>().is_none() {
+                    let aabb = collider.shape().aabb(collider.position());
+                    let isometry = Isometry3::new(aabb.center().coords, Vector3::zeros());
+                    debug_render.cuboid(&isometry, &aabb.half_extents(), AABB_COLOR);
+                }
+            }
+        }
+
+        let collision_world = physics.world.collider_world().as_collision_world();
+        if debug_render.contacts {
+            for (_, _, _, manifold) in collision_world.contact_pairs(true) {
+                for tracked_contact in manifold.contacts() {
+                    let contact = &tracked_contact.contact;
+                    debug_render.cross(contact.world1, na::convert(0.05), CONTACT_COLOR);
+                    debug_render.arrow(
+                        contact.world1,
+                        contact.normal.into_inner() * na::convert::<f64, N>(0.3),
+                        CONTACT_COLOR,
+                    );
+                }
+            }
+        }
+
+        if debug_render.sensors {
+            for (handle1, handle2, _, proximity) in collision_world.proximity_pairs(true) {
+                if proximity != Proximity::Intersecting {
+                    continue;
+                }
+                if let (Some(collider1), Some(collider2)) = (
+                    collision_world.collision_object(handle1),
+                    collision_world.collision_object(handle2),
+                ) {
+                    debug_render.line(
+                        collider1.position().translation.vector.into(),
+                        collider2.position().translation.vector.into(),
+                        SENSOR_COLOR,
+                    );
+                }
+            }
+        }
+
+        if debug_render.velocities {
+            for handle in handles.body_handles.values() {
+                if let Some(rigid_body) = physics.world.rigid_body(*handle) {
+                    debug_render.arrow(
+                        rigid_body.position().translation.vector.into(),
+                        rigid_body.velocity().linear,
+                        VELOCITY_COLOR,
+                    );
+                }
+            }
+        }
+
+        // a cross inside a level ring, which stays visible within the shapes
+        if debug_render.centers_of_mass {
+            for handle in handles.body_handles.values() {
+                if let Some(rigid_body) = physics.world.rigid_body(*handle) {
+                    if rigid_body.status() != BodyStatus::Dynamic {
+                        continue;
+                    }
+                    let center = rigid_body.center_of_mass();
+                    debug_render.cross(center, na::convert(0.15), CENTER_OF_MASS_COLOR);
+                    debug_render.circle(
+                        center,
+                        &Vector3::y_axis(),
+                        na::convert(0.1),
+                        CENTER_OF_MASS_COLOR,
+                    );
+                }
+            }
+        }
+
+        if !debug_render.joints {
+            return;
+        }
+
+        for (entity, wheel_joint) in (&entities, &wheel_joints).join() {
+            if let (Some(chassis), Some(wheel)) = (
+                body_position(&entities, &physics, &handles, Some(wheel_joint.chassis)),
+                body_position(&entities, &physics, &handles, Some(entity)),
+            ) {
+                draw_wheel_joint(wheel_joint, &chassis, &wheel, &mut debug_render);
+            }
+        }
+
+        for elevator in elevators.join() {
+            if let Some(base) = body_position(&entities, &physics, &handles, elevator.base) {
+                draw_elevator(elevator, &base, &mut debug_render);
+            }
+        }
+
+        for (entity, hinged_door) in (&entities, &hinged_doors).join() {
+            if let (Some(frame), Some(door)) = (
+                body_position(&entities, &physics, &handles, hinged_door.frame),
+                body_position(&entities, &physics, &handles, Some(entity)),
+            ) {
+                let closed_rotation = hinged_door_poses
+                    .as_ref()
+                    .and_then(|poses| poses.closed_rotation(entity));
+                draw_hinged_door(
+                    hinged_door,
+                    &frame,
+                    &door,
+                    closed_rotation,
+                    &mut debug_render,
+                );
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("DebugRenderSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+    }
+}
+
+impl<N> Default for DebugRenderSystem<N>
+where
+    N: RealField,
+{
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+        }
+    }
+}
+
+/// Fetches the position of the body of the given `Entity` from the physics
+/// `World`; joints without an `Entity` are attached to the ground.
+fn body_position<N: RealField>(
+    entities: &Entities,
+    physics: &Physics<N>,
+    handles: &PhysicsHandles,
+    entity: Option<Entity>,
+) -> Option<Isometry3<N>> {
+    match joint_body_handle(entities, handles, entity)? {
+        Some(handle) => physics
+            .world
+            .rigid_body(handle)
+            .map(|rigid_body| *rigid_body.position()),
+        None => Some(Isometry3::identity()),
+    }
+}
+
+fn draw_wheel_joint<N: RealField>(
+    wheel_joint: &WheelJoint<N>,
+    chassis: &Isometry3<N>,
+    wheel: &Isometry3<N>,
+    debug_render: &mut DebugRender<N>,
+) {
+    let anchor = chassis * wheel_joint.anchor;
+    let center = Point3::from(wheel.translation.vector);
+    let suspension_axis = chassis.rotation * wheel_joint.suspension_axis.into_inner();
+    let axle = chassis.rotation * wheel_joint.axle.into_inner();
+
+    debug_render.cross(anchor, na::convert(0.1), ANCHOR_COLOR);
+    debug_render.line(
+        anchor - suspension_axis,
+        anchor + suspension_axis,
+        AXIS_COLOR,
+    );
+    debug_render.line(
+        center - axle * na::convert::<f64, N>(0.5),
+        center + axle * na::convert::<f64, N>(0.5),
+        AXIS_COLOR,
+    );
+
+    // the targeted spin of the motor as an arc around the axle, one full
+    // circle per two pi radians per second
+    if let Some(motor) = wheel_joint.motor {
+        let from = suspension_axis * na::convert::<f64, N>(0.5);
+        debug_render.arc(
+            center,
+            &Unit::new_normalize(axle),
+            from,
+            motor.target_velocity,
+            MOTOR_COLOR,
+        );
+    }
+}
+
+fn draw_elevator<N: RealField>(
+    elevator: &Elevator<N>,
+    base: &Isometry3<N>,
+    debug_render: &mut DebugRender<N>,
+) {
+    let anchor = base * elevator.anchor;
+    let axis = base.rotation * elevator.axis.into_inner();
+    let at = |offset: N| anchor + axis * offset;
+
+    debug_render.line(at(elevator.min_offset), at(elevator.max_offset), AXIS_COLOR);
+    debug_render.cross(at(elevator.min_offset), na::convert(0.2), LIMIT_COLOR);
+    debug_render.cross(at(elevator.max_offset), na::convert(0.2), LIMIT_COLOR);
+    for floor in &elevator.floors {
+        debug_render.cross(at(*floor), na::convert(0.1), ANCHOR_COLOR);
+    }
+    debug_render.cross(at(elevator.target_offset()), na::convert(0.3), MOTOR_COLOR);
+}
+
+fn draw_hinged_door<N: RealField>(
+    hinged_door: &HingedDoor<N>,
+    frame: &Isometry3<N>,
+    door: &Isometry3<N>,
+    closed_rotation: Option<&UnitQuaternion<N>>,
+    debug_render: &mut DebugRender<N>,
+) {
+    let anchor = frame * hinged_door.anchor;
+    let axis = Unit::new_unchecked(frame.rotation * hinged_door.axis.into_inner());
+
+    debug_render.cross(anchor, na::convert(0.1), ANCHOR_COLOR);
+    debug_render.line(
+        anchor - axis.into_inner(),
+        anchor + axis.into_inner(),
+        AXIS_COLOR,
+    );
+
+    // the limits are drawn as an arc through the center of the door, starting
+    // from its closed pose
+    let closed_rotation = match closed_rotation {
+        Some(closed_rotation) => closed_rotation,
+        None => return,
+    };
+    let offset = door.translation.vector - anchor.coords;
+    let radius = offset - axis.into_inner() * offset.dot(&axis.into_inner());
+    let to_closed = frame.rotation * closed_rotation * door.rotation.inverse();
+    let closed = to_closed * radius;
+
+    let min_angle = hinged_door
+        .min_angle
+        .map_or(-N::pi(), |angle| angle.max(-N::pi()));
+    let max_angle = hinged_door
+        .max_angle
+        .map_or(N::pi(), |angle| angle.min(N::pi()));
+    let from = Rotation3::from_axis_angle(&axis, min_angle) * closed;
+    debug_render.arc(anchor, &axis, from, max_angle - min_angle, LIMIT_COLOR);
+    debug_render.line(anchor, anchor + closed, LIMIT_COLOR);
+}
+
+/// The half size of the square patch drawn for the infinite `Plane`s.
+const PLANE_HALF_SIZE: f64 = 5.0;
+
+/// Draws the wireframe of the given `Shape`. Shapes without a dedicated
+/// wireframe are drawn as their local bounding box.
+fn draw_shape<N: RealField>(
+    shape: &dyn Shape<N>,
+    isometry: &Isometry3<N>,
+    debug_render: &mut DebugRender<N>,
+) {
+    let center = Point3::from(isometry.translation.vector);
+    if let Some(ball) = shape.as_shape::<Ball<N>>() {
+        for axis in &[Vector3::x_axis(), Vector3::y_axis(), Vector3::z_axis()] {
+            let axis = isometry.rotation * *axis;
+            debug_render.circle(center, &axis, ball.radius(), SHAPE_COLOR);
+        }
+    } else if let Some(cuboid) = shape.as_shape::<Cuboid<N>>() {
+        debug_render.cuboid(isometry, cuboid.half_extents(), SHAPE_COLOR);
+    } else if let Some(capsule) = shape.as_shape::<Capsule<N>>() {
+        // capsules are aligned with the local y axis
+        let axis = isometry.rotation * Vector3::y_axis();
+        let top = center + axis.into_inner() * capsule.half_height();
+        let bottom = center - axis.into_inner() * capsule.half_height();
+        debug_render.circle(top, &axis, capsule.radius(), SHAPE_COLOR);
+        debug_render.circle(bottom, &axis, capsule.radius(), SHAPE_COLOR);
+        for side in &[Vector3::x(), -Vector3::x(), Vector3::z(), -Vector3::z()] {
+            let offset = isometry.rotation * side * capsule.radius();
+            debug_render.line(top + offset, bottom + offset, SHAPE_COLOR);
+        }
+    } else if let Some(plane) = shape.as_shape::<Plane<N>>() {
+        // a square patch of the infinite plane around its origin
+        let normal = isometry.rotation * plane.normal().into_inner();
+        let other = if normal.x.abs() < na::convert(0.9) {
+            Vector3::x()
+        } else {
+            Vector3::y()
+        };
+        let tangent1 = normal.cross(&other).normalize() * na::convert::<f64, N>(PLANE_HALF_SIZE);
+        let tangent2 = normal.cross(&tangent1);
+        let corners = [
+            center + tangent1 + tangent2,
+            center + tangent1 - tangent2,
+            center - tangent1 - tangent2,
+            center - tangent1 + tangent2,
+        ];
+        for i in 0..corners.len() {
+            debug_render.line(corners[i], corners[(i + 1) % corners.len()], SHAPE_COLOR);
+        }
+        debug_render.arrow(center, normal, SHAPE_COLOR);
+    } else if let Some(polyline) = shape.as_shape::<Polyline<N>>() {
+        let points = polyline.points();
+        for edge in polyline.edges() {
+            debug_render.line(
+                isometry * points[edge.indices.x],
+                isometry * points[edge.indices.y],
+                SHAPE_COLOR,
+            );
+        }
+    } else if let Some(compound) = shape.as_shape::<Compound<N>>() {
+        for (delta, part) in compound.shapes() {
+            draw_shape(part.as_ref(), &(isometry * delta), debug_render);
+        }
+    } else {
+        let aabb = shape.local_aabb();
+        let isometry = isometry * Isometry3::new(aabb.center().coords, Vector3::zeros());
+        debug_render.cuboid(&isometry, &aabb.half_extents(), SHAPE_COLOR);
+    }
+}
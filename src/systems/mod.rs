@@ -1,56 +1,356 @@
 use std::ops::Deref;
 
+use log::Level;
 use specs::{
     storage::{ComponentEvent, MaskedStorage},
+    world::Index,
     BitSet,
     Component,
+    Entities,
+    Entity,
+    Join,
     ReaderId,
     Storage,
     Tracked,
 };
 
+use crate::{
+    diagnostics::{DiagnosticKind, SystemLogger},
+    handles::{JointOwner, PhysicsHandles},
+    nalgebra::RealField,
+    nphysics::object::BodyHandle,
+    Physics,
+};
+
 pub use self::{
+    apply_activation_radius::ApplyActivationRadiusSystem,
+    apply_aerodynamics::ApplyAerodynamicsSystem,
+    apply_attractors::ApplyAttractorsSystem,
+    apply_buoyancy::ApplyBuoyancySystem,
+    apply_gravity_volumes::ApplyGravityVolumesSystem,
+    apply_impact_responses::ApplyImpactResponsesSystem,
+    apply_internal_edge_correction::ApplyInternalEdgeCorrectionSystem,
+    apply_look_ats::ApplyLookAtsSystem,
+    apply_max_angular_velocities::ApplyMaxAngularVelocitiesSystem,
+    apply_nav_agents::ApplyNavAgentsSystem,
+    apply_path_constraints::ApplyPathConstraintsSystem,
+    apply_pd_controllers::ApplyPdControllersSystem,
+    apply_physics_commands::ApplyPhysicsCommandsSystem,
+    apply_physics_config::ApplyPhysicsConfigSystem,
+    apply_scripted_motion::ApplyScriptedMotionSystem,
+    apply_simulation_rates::{ApplySimulationRatesSystem, RestoreSimulationRatesSystem},
+    apply_stop_on_contact::ApplyStopOnContactSystem,
+    apply_top_down_friction::ApplyTopDownFrictionSystem,
+    apply_tunneling_prediction::ApplyTunnelingPredictionSystem,
+    apply_upright_stabilizers::ApplyUprightStabilizersSystem,
+    collect_pickups::CollectPickupsSystem,
+    debug_render::DebugRenderSystem,
+    move_characters::MoveCharactersSystem,
     physics_stepper::PhysicsStepperSystem,
+    record_physics_inputs::RecordPhysicsInputsSystem,
+    resize_characters::ResizeCharactersSystem,
+    sync_attachments_to_physics::SyncAttachmentsToPhysicsSystem,
     sync_bodies_from_physics::SyncBodiesFromPhysicsSystem,
     sync_bodies_to_physics::SyncBodiesToPhysicsSystem,
     sync_colliders_to_physics::SyncCollidersToPhysicsSystem,
+    sync_elevators_to_physics::SyncElevatorsToPhysicsSystem,
+    sync_hinged_doors_to_physics::SyncHingedDoorsToPhysicsSystem,
+    sync_joints_to_physics::SyncJointsToPhysicsSystem,
     sync_parameters_to_physics::SyncParametersToPhysicsSystem,
+    sync_sensors::SyncSensorsSystem,
+    sync_sticky_colliders::SyncStickyCollidersSystem,
+    sync_wheel_joints_to_physics::SyncWheelJointsToPhysicsSystem,
 };
 
+mod apply_activation_radius;
+mod apply_aerodynamics;
+mod apply_attractors;
+mod apply_buoyancy;
+mod apply_gravity_volumes;
+mod apply_impact_responses;
+mod apply_internal_edge_correction;
+mod apply_look_ats;
+mod apply_max_angular_velocities;
+mod apply_nav_agents;
+mod apply_path_constraints;
+mod apply_pd_controllers;
+mod apply_physics_commands;
+mod apply_physics_config;
+mod apply_scripted_motion;
+mod apply_simulation_rates;
+mod apply_stop_on_contact;
+mod apply_top_down_friction;
+mod apply_tunneling_prediction;
+mod apply_upright_stabilizers;
+mod collect_pickups;
+mod debug_render;
+mod move_characters;
 mod physics_stepper;
+mod record_physics_inputs;
+mod resize_characters;
+mod sync_attachments_to_physics;
 mod sync_bodies_from_physics;
 mod sync_bodies_to_physics;
 mod sync_colliders_to_physics;
+mod sync_elevators_to_physics;
+mod sync_hinged_doors_to_physics;
+mod sync_joints_to_physics;
 mod sync_parameters_to_physics;
+mod sync_sensors;
+mod sync_sticky_colliders;
+mod sync_wheel_joints_to_physics;
+
+/// The phases of the `SyncBodiesToPhysicsSystem` and the
+/// `SyncCollidersToPhysicsSystem`. By default a single `System` runs all phases
+/// in order; adding one `System` per phase allows running custom `System`s in
+/// between, e.g. to post-process newly inserted colliders before they are
+/// updated.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SyncPhase {
+    /// Creates the physics objects of inserted `Component`s.
+    Insert,
+    /// Applies modified `Component`s to their existing physics objects.
+    Update,
+    /// Removes the physics objects of removed `Component`s.
+    Remove,
+}
+
+/// The `BitSet`s of the indices inserted, modified and removed since the last
+/// run of a `System`. They are stored in the `System` and reused every frame,
+/// so reading the `ComponentEvent`s does not allocate once the `BitSet`s have
+/// grown to the highest index.
+#[derive(Default)]
+pub(crate) struct ComponentEvents {
+    pub inserted: BitSet,
+    pub modified: BitSet,
+    pub removed: BitSet,
+}
 
-/// Iterated over the `ComponentEvent::Inserted`s of a given, tracked `Storage`
-/// and returns the results in a `BitSet`.
+/// Iterated over the `ComponentEvent`s of a given, tracked `Storage` and
+/// collects the results in the `BitSet`s of the given `ComponentEvents`, which
+/// are cleared first. Every event is logged at `Level::Debug` through the
+/// `SystemLogger` of the calling `System`.
+///
+/// Conflicting events of the same index are coalesced in the order they
+/// occurred: a removal wins over earlier insertions and modifications, an
+/// insertion after a removal replaces it and modifications of an inserted
+/// index collapse into the insertion. Every index is therefore contained in at
+/// most one of the `BitSet`s.
 pub(crate) fn iterate_component_events<T, D>(
     tracked_storage: &Storage<T, D>,
     reader_id: &mut ReaderId<ComponentEvent>,
-) -> (BitSet, BitSet, BitSet)
-where
+    events: &mut ComponentEvents,
+    logger: &mut SystemLogger,
+) where
     T: Component,
     T::Storage: Tracked,
     D: Deref<Target = MaskedStorage<T>>,
 {
-    let (mut inserted, mut modified, mut removed) = (BitSet::new(), BitSet::new(), BitSet::new());
+    let ComponentEvents {
+        inserted,
+        modified,
+        removed,
+    } = events;
+    inserted.clear();
+    modified.clear();
+    removed.clear();
+
     for component_event in tracked_storage.channel().read(reader_id) {
         match component_event {
             ComponentEvent::Inserted(id) => {
-                debug!("Got Inserted event with id: {}", id);
+                logger.debug(
+                    module_path!(),
+                    format_args!("Got Inserted event with id: {}", id),
+                );
+                removed.remove(*id);
+                modified.remove(*id);
                 inserted.add(*id);
             }
             ComponentEvent::Modified(id) => {
-                debug!("Got Modified event with id: {}", id);
-                modified.add(*id);
+                logger.debug(
+                    module_path!(),
+                    format_args!("Got Modified event with id: {}", id),
+                );
+                if !inserted.contains(*id) {
+                    modified.add(*id);
+                }
             }
             ComponentEvent::Removed(id) => {
-                debug!("Got Removed event with id: {}", id);
+                logger.debug(
+                    module_path!(),
+                    format_args!("Got Removed event with id: {}", id),
+                );
+                inserted.remove(*id);
+                modified.remove(*id);
                 removed.add(*id);
             }
         }
     }
+}
+
+/// Adds the indices of all `Component`s in the given `Storage` that are not
+/// `known` to the physics `World` to the `inserted` `BitSet`. Reader ids only
+/// receive the events written after their registration, so the sync `System`s
+/// rescan their `Storage`s on the first run after `setup`, e.g. when a
+/// dispatcher is rebuilt while physics `Entity`s already exist.
+pub(crate) fn rescan_components<T, D, F>(
+    tracked_storage: &Storage<T, D>,
+    inserted: &mut BitSet,
+    known: F,
+    logger: &mut SystemLogger,
+) where
+    T: Component,
+    D: Deref<Target = MaskedStorage<T>>,
+    F: Fn(Index) -> bool,
+{
+    for id in tracked_storage.mask().join() {
+        if !known(id) {
+            logger.debug(
+                module_path!(),
+                format_args!("Rescanned unsynchronised component with id: {}", id),
+            );
+            inserted.add(id);
+        }
+    }
+}
+
+/// Removes the joint constraint of the given index from the physics `World`,
+/// if one exists and is owned by the given `JointOwner`.
+pub(crate) fn remove_joint<N: RealField>(
+    id: Index,
+    owner: JointOwner,
+    physics: &mut Physics<N>,
+    handles: &mut PhysicsHandles,
+    logger: &mut SystemLogger,
+) {
+    if handles
+        .joint_handles
+        .get(&id)
+        .map(|(joint_owner, _)| *joint_owner)
+        != Some(owner)
+    {
+        return;
+    }
+
+    if let Some((_, handle)) = handles.joint_handles.remove(&id) {
+        physics.world.remove_constraint(handle);
+
+        logger.log(
+            module_path!(),
+            Level::Info,
+            DiagnosticKind::JointRemoved(id),
+            format_args!("Removed joint from world with id: {}", id),
+        );
+    }
+}
+
+/// Checks whether the joint `Component` of the given `JointOwner` has yet to
+/// create its constraint for the given index. Every `Entity` can own at most
+/// one joint `Component`; while another one owns the constraint, the
+/// `Component` is ignored and the conflict is reported with a
+/// `DiagnosticKind::JointConflict` if `report` is set, i.e. once the
+/// `Component` has been inserted or modified.
+pub(crate) fn joint_vacant(
+    id: Index,
+    owner: JointOwner,
+    handles: &PhysicsHandles,
+    report: bool,
+    logger: &mut SystemLogger,
+) -> bool {
+    match handles.joint_handles.get(&id) {
+        None => true,
+        Some((joint_owner, _)) if *joint_owner == owner => false,
+        Some((joint_owner, _)) => {
+            if report {
+                logger.log(
+                    module_path!(),
+                    Level::Warn,
+                    DiagnosticKind::JointConflict(id, owner),
+                    format_args!(
+                        "Ignoring {:?} joint of entity with id {} which already owns a {:?} joint",
+                        owner, id, joint_owner
+                    ),
+                );
+            }
+            false
+        }
+    }
+}
+
+/// Looks up the `BodyHandle` a joint is attached to. Joints without an
+/// `Entity` are attached to the ground, which is returned as `Some(None)`;
+/// `None` is returned if the `Entity` has no body in the physics `World`.
+pub(crate) fn joint_body_handle(
+    entities: &Entities,
+    handles: &PhysicsHandles,
+    entity: Option<Entity>,
+) -> Option<Option<BodyHandle>> {
+    match entity {
+        Some(entity) if entities.is_alive(entity) => handles.body_handle(entity).map(Some),
+        Some(_) => None,
+        None => Some(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::{prelude::*, BitSet};
+
+    use crate::{
+        diagnostics::{LogSource, PhysicsDiagnostics, SystemLogger},
+        nphysics::object::BodyStatus,
+        systems::{iterate_component_events, ComponentEvents},
+        PhysicsBody,
+        PhysicsBodyBuilder,
+    };
+
+    fn ids(bit_set: &BitSet) -> Vec<u32> {
+        bit_set.join().collect()
+    }
+
+    #[test]
+    fn clear_events_between_frames() {
+        let mut world = World::new();
+        world.register::<PhysicsBody<f32>>();
+        let mut reader_id = world.write_storage::<PhysicsBody<f32>>().register_reader();
+        let mut diagnostics = PhysicsDiagnostics::new();
+        let mut events = ComponentEvents::default();
 
-    (inserted, modified, removed)
+        let mut next_frame = |world: &World, events: &mut ComponentEvents| {
+            let mut logger = SystemLogger::new(None, LogSource::Bodies, &mut diagnostics);
+            iterate_component_events(
+                &world.read_storage::<PhysicsBody<f32>>(),
+                &mut reader_id,
+                events,
+                &mut logger,
+            );
+        };
+
+        let body = || PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build();
+        let first = world.create_entity().with(body()).build();
+        let second = world.create_entity().with(body()).build();
+        next_frame(&world, &mut events);
+        assert_eq!(ids(&events.inserted), vec![first.id(), second.id()]);
+        assert!(ids(&events.modified).is_empty() && ids(&events.removed).is_empty());
+
+        // only the events of the current frame remain
+        world
+            .write_storage::<PhysicsBody<f32>>()
+            .get_mut(first)
+            .unwrap()
+            .mass = 2.0;
+        next_frame(&world, &mut events);
+        assert!(ids(&events.inserted).is_empty() && ids(&events.removed).is_empty());
+        assert_eq!(ids(&events.modified), vec![first.id()]);
+
+        world.write_storage::<PhysicsBody<f32>>().remove(second);
+        next_frame(&world, &mut events);
+        assert!(ids(&events.inserted).is_empty() && ids(&events.modified).is_empty());
+        assert_eq!(ids(&events.removed), vec![second.id()]);
+
+        next_frame(&world, &mut events);
+        assert!(ids(&events.inserted).is_empty());
+        assert!(ids(&events.modified).is_empty());
+        assert!(ids(&events.removed).is_empty());
+    }
 }
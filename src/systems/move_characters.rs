@@ -0,0 +1,721 @@
+use std::{marker::PhantomData, ops::Deref};
+
+use specs::{
+    storage::MaskedStorage,
+    Entities,
+    Entity,
+    Join,
+    Read,
+    ReadExpect,
+    ReadStorage,
+    Storage,
+    System,
+    SystemData,
+    World,
+    Write,
+    WriteStorage,
+};
+
+use crate::{
+    bodies::{PhysicsBody, Position},
+    characters::{
+        ground_tolerance,
+        CharacterController,
+        CharacterQuery,
+        CharacterResolution,
+        CharacterState,
+        Climbable,
+        WaterVolume,
+    },
+    colliders::{PhysicsCollider, Shape},
+    events::{
+        CharacterEvent,
+        CharacterEventKind,
+        CharacterEvents,
+        CharacterTrace,
+        CharacterTraces,
+    },
+    nalgebra::{Point3, RealField, Vector3},
+    ncollide::{query::Ray, world::CollisionGroups},
+    nphysics::{algebra::Force3, object::BodyStatus},
+    parameters::Gravity,
+    queries::{compare_toi, ray_hits},
+    Physics,
+};
+
+/// The `MoveCharactersSystem` converts the `desired_velocity` of every
+/// `CharacterController` into the velocity of its kinematic `PhysicsBody`.
+/// The ground below each character is found with a ray cast; grounded
+/// characters are kept on it, airborne characters fall with the `Gravity`
+/// and characters overlapping a `Climbable` or a `WaterVolume` climb or swim,
+/// emitting `CharacterEvent`s when they enter or exit the water. Characters
+/// with a `push_mass` push the dynamic bodies they walk into, and characters
+/// with `debug` enabled emit a `CharacterTrace` for every move.
+/// It has to run before the `SyncBodiesToPhysicsSystem` and is not part of
+/// the default `Dispatcher`.
+pub struct MoveCharactersSystem<N, P> {
+    n_marker: PhantomData<N>,
+    p_marker: PhantomData<P>,
+}
+
+impl<'s, N, P> System<'s> for MoveCharactersSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    type SystemData = (
+        Entities<'s>,
+        Option<Read<'s, Gravity<N>>>,
+        ReadStorage<'s, P>,
+        ReadStorage<'s, Climbable<N>>,
+        ReadStorage<'s, WaterVolume<N>>,
+        ReadStorage<'s, PhysicsCollider<N>>,
+        WriteStorage<'s, CharacterController<N>>,
+        WriteStorage<'s, PhysicsBody<N>>,
+        Write<'s, CharacterEvents>,
+        Write<'s, CharacterTraces<N>>,
+        ReadExpect<'s, Physics<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            gravity,
+            positions,
+            climbables,
+            water_volumes,
+            physics_colliders,
+            mut controllers,
+            mut physics_bodies,
+            mut character_events,
+            mut character_traces,
+            physics,
+        ) = data;
+        let gravity = gravity.map_or_else(Vector3::zeros, |gravity| gravity.0);
+        let dt = physics.timestep();
+        let collision_groups = CollisionGroups::default();
+        let mut pushes = Vec::new();
+        let any_volume =
+            (&climbables).join().next().is_some() || (&water_volumes).join().next().is_some();
+
+        for (entity, position, controller, physics_body) in
+            (&entities, &positions, &mut controllers, &mut physics_bodies).join()
+        {
+            if physics_body.body_status != BodyStatus::Kinematic {
+                continue;
+            }
+
+            // jumping characters leave climbables and the ground instead of
+            // sticking to them
+            let jumping = controller.vertical_velocity > N::zero();
+            let was_swimming = controller.state == CharacterState::Swimming;
+            let mut queries = Vec::new();
+
+            let overlapping = if any_volume {
+                let capsule = Shape::Capsule {
+                    half_height: controller.half_height,
+                    radius: controller.radius,
+                };
+                physics
+                    .overlaps(&capsule, position.isometry(), &collision_groups)
+                    .into_iter()
+                    .filter(|other| *other != entity)
+                    .collect::<Vec<_>>()
+            } else {
+                Vec::new()
+            };
+            let climbable = overlapping
+                .iter()
+                .filter_map(|other| climbables.get(*other).map(|climbable| (*other, climbable)))
+                .next()
+                .filter(|_| !jumping);
+            let water_volume = overlapping
+                .iter()
+                .filter_map(|other| {
+                    water_volumes
+                        .get(*other)
+                        .map(|water_volume| (*other, water_volume))
+                })
+                .next();
+            if controller.debug && any_volume {
+                queries.push(CharacterQuery::VolumeOverlap { hits: overlapping });
+            }
+
+            let (velocity, resolution) = if let Some((other, climbable)) = climbable {
+                controller.state = CharacterState::Climbing;
+                controller.ground_normal = Vector3::y();
+                controller.vertical_velocity = N::zero();
+                (
+                    climbable.climb_velocity(&controller.desired_velocity),
+                    CharacterResolution::Climbing(other),
+                )
+            } else if let Some((other, water_volume)) = water_volume {
+                controller.state = CharacterState::Swimming;
+                controller.ground_normal = Vector3::y();
+                controller.vertical_velocity =
+                    water_volume.vertical_velocity(controller.vertical_velocity, gravity.y, dt);
+                (
+                    controller.desired_velocity + Vector3::y() * controller.vertical_velocity,
+                    CharacterResolution::Swimming(other),
+                )
+            } else {
+                move_on_ground(
+                    entity,
+                    position.isometry().translation.vector,
+                    controller,
+                    jumping,
+                    gravity.y,
+                    dt,
+                    &physics,
+                    &physics_colliders,
+                    &mut queries,
+                )
+            };
+            physics_body.velocity.linear = velocity;
+
+            // find the bodies the character is about to walk into
+            if let Some(push_mass) = controller.push_mass {
+                let mut isometry = *position.isometry();
+                isometry.translation.vector += velocity * dt;
+                let capsule = Shape::Capsule {
+                    half_height: controller.half_height,
+                    radius: controller.radius + ground_tolerance(),
+                };
+                let pushed = physics
+                    .overlaps(&capsule, &isometry, &collision_groups)
+                    .into_iter()
+                    .filter(|other| *other != entity)
+                    .collect::<Vec<_>>();
+                pushes.extend(pushed.iter().map(|other| Push {
+                    translation: position.isometry().translation.vector,
+                    velocity,
+                    push_mass,
+                    pushed: *other,
+                }));
+                if controller.debug {
+                    queries.push(CharacterQuery::PushOverlap { hits: pushed });
+                }
+            }
+
+            if controller.debug {
+                character_traces.single_write(CharacterTrace {
+                    character: entity,
+                    queries,
+                    resolution,
+                    velocity,
+                });
+            }
+
+            let is_swimming = controller.state == CharacterState::Swimming;
+            if is_swimming != was_swimming {
+                character_events.single_write(CharacterEvent {
+                    character: entity,
+                    kind: if is_swimming {
+                        CharacterEventKind::EnteredWater
+                    } else {
+                        CharacterEventKind::ExitedWater
+                    },
+                });
+            }
+        }
+
+        for push in pushes {
+            push.apply(&positions, &mut physics_bodies, dt);
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("MoveCharactersSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+    }
+}
+
+/// A dynamic body a character with a `push_mass` walks into.
+struct Push<N: RealField> {
+    translation: Vector3<N>,
+    velocity: Vector3<N>,
+    push_mass: N,
+    pushed: Entity,
+}
+
+impl<N: RealField> Push<N> {
+    /// Applies the impulse of an inelastic collision between the pushed body
+    /// and the virtual mass of the character, as an external force over the
+    /// step of length `dt`.
+    fn apply<P, D>(
+        &self,
+        positions: &Storage<P, D>,
+        physics_bodies: &mut WriteStorage<PhysicsBody<N>>,
+        dt: N,
+    ) where
+        P: Position<N>,
+        D: Deref<Target = MaskedStorage<P>>,
+    {
+        let (position, physics_body) = match (
+            positions.get(self.pushed),
+            physics_bodies.get_mut(self.pushed),
+        ) {
+            (Some(position), Some(physics_body))
+                if physics_body.body_status == BodyStatus::Dynamic =>
+            {
+                (position, physics_body)
+            }
+            _ => return,
+        };
+
+        // push horizontally, away from the character
+        let mut direction = position.isometry().translation.vector - self.translation;
+        direction.y = N::zero();
+        let distance = direction.norm();
+        if distance <= N::default_epsilon() {
+            return;
+        }
+        let direction = direction / distance;
+
+        let approach_speed = (self.velocity - physics_body.velocity.linear).dot(&direction);
+        if approach_speed <= N::zero() {
+            return;
+        }
+
+        let reduced_mass =
+            self.push_mass * physics_body.mass / (self.push_mass + physics_body.mass);
+        let impulse = direction * (approach_speed * reduced_mass);
+        physics_body.apply_external_force(&Force3::linear(impulse / dt));
+    }
+}
+
+/// Keeps a character on the ground below it, or lets it fall if there is
+/// none, and returns its new velocity. The ground ray is added to the
+/// `queries` if the character has `debug` enabled.
+#[allow(clippy::too_many_arguments)]
+fn move_on_ground<N, D>(
+    entity: Entity,
+    translation: Vector3<N>,
+    controller: &mut CharacterController<N>,
+    jumping: bool,
+    gravity: N,
+    dt: N,
+    physics: &Physics<N>,
+    physics_colliders: &Storage<PhysicsCollider<N>, D>,
+    queries: &mut Vec<CharacterQuery<N>>,
+) -> (Vector3<N>, CharacterResolution<N>)
+where
+    N: RealField,
+    D: Deref<Target = MaskedStorage<PhysicsCollider<N>>>,
+{
+    // look for ground right below the feet, or within the snapping distance if
+    // enabled
+    let foot_offset = controller.foot_offset();
+    let snap_distance = controller.slope_snap.unwrap_or_else(N::zero);
+    let ray = Ray::new(Point3::from(translation), -Vector3::y());
+    let max_toi = foot_offset + ground_tolerance() + snap_distance;
+    let collision_groups = CollisionGroups::default();
+    let hit = ray_hits(physics, &ray, &collision_groups, max_toi, |other| {
+        // sensors, e.g. Climbables, are no ground to stand on
+        other != entity
+            && !physics_colliders
+                .get(other)
+                .map_or(false, |physics_collider| physics_collider.sensor)
+    })
+    .min_by(compare_toi);
+    if controller.debug {
+        queries.push(CharacterQuery::GroundRay {
+            origin: ray.origin,
+            max_toi,
+            hit,
+        });
+    }
+    let ground = hit.filter(|hit| !jumping && controller.is_walkable(&hit.normal));
+
+    match ground {
+        Some(hit) => {
+            controller.state = CharacterState::Grounded;
+            controller.ground_normal = hit.normal;
+            controller.vertical_velocity = N::zero();
+
+            let mut velocity = if controller.slope_snap.is_some() {
+                controller.velocity_along(&hit.normal)
+            } else {
+                Vector3::new(
+                    controller.desired_velocity.x,
+                    N::zero(),
+                    controller.desired_velocity.z,
+                )
+            };
+            // close the gap to the ground, or resolve the penetration, within a
+            // single step
+            let correction = hit.toi - foot_offset;
+            velocity.y -= correction / dt;
+            (velocity, CharacterResolution::Grounded { correction })
+        }
+        None => {
+            controller.state = CharacterState::Airborne;
+            controller.ground_normal = Vector3::y();
+            controller.vertical_velocity += gravity * dt;
+
+            let resolution = match hit {
+                _ if jumping => CharacterResolution::Jumping,
+                Some(hit) => CharacterResolution::TooSteep { normal: hit.normal },
+                None => CharacterResolution::Falling,
+            };
+            (
+                Vector3::new(
+                    controller.desired_velocity.x,
+                    controller.vertical_velocity,
+                    controller.desired_velocity.z,
+                ),
+                resolution,
+            )
+        }
+    }
+}
+
+impl<N, P> Default for MoveCharactersSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+            p_marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        characters::{CharacterController, CharacterState, Climbable, WaterVolume},
+        colliders::Shape,
+        events::{CharacterEventKind, CharacterEvents},
+        nalgebra::{Isometry3, Point3, Vector3},
+        nphysics::object::BodyStatus,
+        parameters::Gravity,
+        systems::{
+            MoveCharactersSystem,
+            PhysicsStepperSystem,
+            SyncBodiesToPhysicsSystem,
+            SyncCollidersToPhysicsSystem,
+        },
+        PhysicsBody,
+        PhysicsBodyBuilder,
+        PhysicsColliderBuilder,
+        SimplePosition,
+    };
+
+    fn character_dispatcher<'a, 'b>() -> Dispatcher<'a, 'b> {
+        DispatcherBuilder::new()
+            .with(
+                SyncBodiesToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_bodies_to_physics_system",
+                &[],
+            )
+            .with(
+                SyncCollidersToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_colliders_to_physics_system",
+                &["sync_bodies_to_physics_system"],
+            )
+            .with(
+                PhysicsStepperSystem::<f32>::default(),
+                "physics_stepper_system",
+                &["sync_colliders_to_physics_system"],
+            )
+            .with(
+                MoveCharactersSystem::<f32, SimplePosition<f32>>::default(),
+                "move_characters_system",
+                &["physics_stepper_system"],
+            )
+            .build()
+    }
+
+    fn create_character(world: &mut World, x: f32, controller: CharacterController<f32>) -> Entity {
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(x, 1.0, 0.0)))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Kinematic).build())
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Capsule {
+                    half_height: 0.5,
+                    radius: 0.3,
+                })
+                .build(),
+            )
+            .with(controller)
+            .build()
+    }
+
+    #[test]
+    fn snap_to_ground() {
+        let mut world = World::new();
+        let mut dispatcher = character_dispatcher();
+        dispatcher.setup(&mut world);
+
+        // the ground ends at y = 0, the feet of the character float 0.2 above it
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(
+                0.0, -0.5, 0.0,
+            )))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Cuboid {
+                    half_extents: Vector3::new(10.0, 0.5, 10.0),
+                })
+                .build(),
+            )
+            .build();
+        let character = create_character(
+            &mut world,
+            0.0,
+            CharacterController::new(0.5, 0.3).with_slope_snap(0.3),
+        );
+        dispatcher.dispatch(&world);
+
+        // the character snaps down instead of falling
+        let controllers = world.read_storage::<CharacterController<f32>>();
+        assert!(controllers.get(character).unwrap().is_grounded());
+        let physics_bodies = world.read_storage::<PhysicsBody<f32>>();
+        assert!(physics_bodies.get(character).unwrap().velocity.linear.y < 0.0);
+    }
+
+    #[test]
+    fn climb_ladder_without_gravity() {
+        let mut world = World::new();
+        let mut dispatcher = character_dispatcher();
+        dispatcher.setup(&mut world);
+        world.insert(Gravity(Vector3::<f32>::new(0.0, -9.81, 0.0)));
+
+        // a ladder facing towards positive x, and both characters walking
+        // towards it
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(0.0, 1.0, 0.0)))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Cuboid {
+                    half_extents: Vector3::new(1.0, 2.0, 1.0),
+                })
+                .sensor(true)
+                .build(),
+            )
+            .with(Climbable::new(Vector3::x_axis()))
+            .build();
+        let mut controller = CharacterController::new(0.5, 0.3);
+        controller.desired_velocity = Vector3::new(-2.0, 0.0, 0.5);
+        let climbing = create_character(&mut world, 0.5, controller);
+        let walking = create_character(&mut world, 10.0, controller);
+        dispatcher.dispatch(&world);
+
+        let controllers = world.read_storage::<CharacterController<f32>>();
+        let physics_bodies = world.read_storage::<PhysicsBody<f32>>();
+        let climber = controllers.get(climbing).unwrap();
+        assert_eq!(climber.state(), CharacterState::Climbing);
+        assert_eq!(climber.vertical_velocity, 0.0);
+        assert_eq!(
+            physics_bodies.get(climbing).unwrap().velocity.linear,
+            Vector3::new(0.0, 2.0, 0.5)
+        );
+
+        // without a ladder the same move falls
+        let walker = controllers.get(walking).unwrap();
+        assert_eq!(walker.state(), CharacterState::Airborne);
+        assert!((walker.vertical_velocity + 9.81 / 60.0).abs() < 1.0e-6);
+        assert_eq!(
+            physics_bodies.get(walking).unwrap().velocity.linear.y,
+            walker.vertical_velocity
+        );
+    }
+
+    #[test]
+    fn swim_in_water_volume() {
+        let mut world = World::new();
+        let mut dispatcher = character_dispatcher();
+        dispatcher.setup(&mut world);
+        world.insert(Gravity(Vector3::<f32>::new(0.0, -9.81, 0.0)));
+        let mut reader = world.fetch_mut::<CharacterEvents>().register_reader();
+
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Cuboid {
+                    half_extents: Vector3::new(5.0, 5.0, 5.0),
+                })
+                .sensor(true)
+                .build(),
+            )
+            .with(WaterVolume {
+                buoyancy: 0.8,
+                drag: 2.0,
+            })
+            .build();
+        let mut controller = CharacterController::new(0.5, 0.3);
+        controller.desired_velocity = Vector3::new(1.0, 0.5, 0.0);
+        let character = create_character(&mut world, 0.0, controller);
+
+        // the events of the last frame
+        let mut dispatch = |world: &mut World| {
+            dispatcher.dispatch(world);
+            world
+                .fetch::<CharacterEvents>()
+                .read(&mut reader)
+                .map(|event| {
+                    assert_eq!(event.character, character);
+                    match event.kind {
+                        CharacterEventKind::EnteredWater => "entered",
+                        CharacterEventKind::ExitedWater => "exited",
+                        _ => "other",
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // swimming follows the vertical desired velocity and sinks slowly
+        assert_eq!(dispatch(&mut world), vec!["entered"]);
+        let sinking = -9.81 * 0.2 / 60.0 / (1.0 + 2.0 / 60.0);
+        {
+            let controllers = world.read_storage::<CharacterController<f32>>();
+            let controller = controllers.get(character).unwrap();
+            assert_eq!(controller.state(), CharacterState::Swimming);
+            assert!((controller.vertical_velocity - sinking).abs() < 1.0e-6);
+            let velocity = world
+                .read_storage::<PhysicsBody<f32>>()
+                .get(character)
+                .unwrap()
+                .velocity
+                .linear;
+            assert!((velocity - Vector3::new(1.0, 0.5 + sinking, 0.0)).norm() < 1.0e-6);
+        }
+        assert_eq!(dispatch(&mut world), Vec::<&str>::new());
+
+        // leaving the water
+        world
+            .write_storage::<SimplePosition<f32>>()
+            .insert(
+                character,
+                SimplePosition(Isometry3::translation(20.0, 1.0, 0.0)),
+            )
+            .unwrap();
+        assert_eq!(dispatch(&mut world), vec!["exited"]);
+        assert_eq!(
+            world
+                .read_storage::<CharacterController<f32>>()
+                .get(character)
+                .unwrap()
+                .state(),
+            CharacterState::Airborne
+        );
+        assert_eq!(dispatch(&mut world), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn push_with_virtual_mass() {
+        let mut world = World::new();
+        let mut dispatcher = character_dispatcher();
+        dispatcher.setup(&mut world);
+
+        // characters walking into a resting crate each, with a light, a heavy
+        // and no push mass
+        let push = |world: &mut World, x: f32, push_mass: Option<f32>| {
+            let mut controller = CharacterController::new(0.5, 0.3);
+            controller.desired_velocity = Vector3::new(1.0, 0.0, 0.0);
+            controller.push_mass = push_mass;
+            create_character(world, x, controller);
+            world
+                .create_entity()
+                .with(SimplePosition::<f32>(Isometry3::translation(
+                    x + 0.85,
+                    1.0,
+                    0.0,
+                )))
+                .with(
+                    PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                        .gravity_enabled(false)
+                        .mass(2.0)
+                        .build(),
+                )
+                .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+                .build()
+        };
+        let light = push(&mut world, 0.0, Some(2.0));
+        let heavy = push(&mut world, 10.0, Some(18.0));
+        let ignored = push(&mut world, 20.0, None);
+        dispatcher.dispatch(&world);
+
+        // the impulse of an inelastic collision with the reduced mass, applied
+        // over one step
+        let physics_bodies = world.read_storage::<PhysicsBody<f32>>();
+        let force = |entity| {
+            physics_bodies
+                .get(entity)
+                .unwrap()
+                .check_external_force()
+                .linear
+        };
+        assert!((force(light) - Vector3::new(1.0 * 60.0, 0.0, 0.0)).norm() < 1.0e-3);
+        assert!((force(heavy) - Vector3::new(1.8 * 60.0, 0.0, 0.0)).norm() < 1.0e-3);
+        assert_eq!(force(ignored), Vector3::zeros());
+    }
+
+    #[test]
+    fn trace_debug_moves() {
+        let mut world = World::new();
+        let mut dispatcher = character_dispatcher();
+        dispatcher.setup(&mut world);
+        let mut reader = world.fetch_mut::<CharacterTraces<f32>>().register_reader();
+
+        // the ground ends at y = 0, the feet of the characters float 0.2 above it
+        let ground = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(
+                0.0, -0.5, 0.0,
+            )))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Cuboid {
+                    half_extents: Vector3::new(10.0, 0.5, 10.0),
+                })
+                .build(),
+            )
+            .build();
+        let controller = CharacterController::new(0.5, 0.3).with_slope_snap(0.3);
+        let debugged = create_character(&mut world, 0.0, controller.with_debug());
+        create_character(&mut world, 5.0, controller);
+        dispatcher.dispatch(&world);
+
+        // only the character with debug enabled is traced
+        let traces = world.fetch::<CharacterTraces<f32>>();
+        let traces = traces.read(&mut reader).collect::<Vec<_>>();
+        assert_eq!(traces.len(), 1);
+        let trace = traces[0];
+        assert_eq!(trace.character, debugged);
+        assert_eq!(trace.queries.len(), 1);
+        match &trace.queries[0] {
+            CharacterQuery::GroundRay {
+                origin,
+                max_toi,
+                hit: Some(hit),
+            } => {
+                assert_eq!(*origin, Point3::new(0.0, 1.0, 0.0));
+                assert!((max_toi - 1.15).abs() < 1.0e-6);
+                assert_eq!(hit.collider_entity, ground);
+                assert!((hit.toi - 1.0).abs() < 1.0e-4);
+            }
+            query => panic!("unexpected query {:?}", query),
+        }
+        match trace.resolution {
+            CharacterResolution::Grounded { correction } => {
+                assert!((correction - 0.2).abs() < 1.0e-4)
+            }
+            resolution => panic!("unexpected resolution {:?}", resolution),
+        }
+        let physics_bodies = world.read_storage::<PhysicsBody<f32>>();
+        assert_eq!(
+            trace.velocity,
+            physics_bodies.get(debugged).unwrap().velocity.linear
+        );
+    }
+}
@@ -1,116 +1,299 @@
-use std::marker::PhantomData;
+use std::{
+    collections::{HashMap, HashSet},
+    marker::PhantomData,
+    time::Instant,
+};
 
-use specs::{world::Index, Entities, Entity, Read, System, SystemData, World, Write, WriteExpect};
+use log::Level;
+use specs::{Entity, Join, Read, ReadStorage, System, SystemData, World, Write, WriteExpect};
 
 use crate::{
-    events::{ContactEvent, ContactEvents, ContactType, ProximityEvent, ProximityEvents},
-    nalgebra::RealField,
-    ncollide::{events::ContactEvent as NContactEvent, world::CollisionObjectHandle},
-    nphysics::world::ColliderWorld,
-    parameters::TimeStep,
+    colliders::{ContactThrottle, PhysicsCollider},
+    diagnostics::{DiagnosticKind, LogSource, PhysicsDiagnostics, PhysicsLogConfig, SystemLogger},
+    events::{
+        oriented_pair,
+        ContactEvent,
+        ContactEvents,
+        ContactType,
+        DroppedEventCounts,
+        PhysicsEventChannel,
+        ProximityEvent,
+        ProximityEvents,
+        StepsDroppedEvent,
+        StepsDroppedEvents,
+    },
+    handles::entity_from_user_data,
+    nalgebra::{Matrix3, RealField, Vector3},
+    ncollide::{
+        events::ContactEvent as NContactEvent,
+        query::Proximity,
+        world::CollisionObjectHandle,
+    },
+    nphysics::{
+        algebra::ForceType,
+        object::{Body, BodyHandle, BodyStatus},
+        world::ColliderWorld,
+    },
+    parameters::{
+        DeltaTime,
+        EventCapacity,
+        MassScaling,
+        OverflowPolicy,
+        RawSubstepEvents,
+        StepBudget,
+        TimeStep,
+    },
+    shrev::{Event, EventChannel},
     Physics,
 };
 
 /// The `PhysicsStepperSystem` progresses the nphysics `World`.
+///
+/// If a `DeltaTime` `Resource` exists, the elapsed time is accumulated and the
+/// `World` is progressed in as many fixed `TimeStep`s as fit into the
+/// accumulator. The external forces applied during the frame act on every
+/// substep and are dropped if no substep fits into the accumulator. The number
+/// of substeps per dispatch is limited by the `StepBudget`; excess substeps
+/// are dropped and reported through a `StepsDroppedEvent` instead of
+/// spiralling into ever longer frames. With a
+/// `MassScaling` `Resource`, the masses of light bodies touching heavy ones
+/// are raised during each substep. The `ContactEvent`s of `PhysicsCollider`s
+/// with a `ContactThrottle` are filtered before they are written. The events
+/// of all substeps are coalesced per pair of colliders unless
+/// `RawSubstepEvents` are requested, and an `EventCapacity` `Resource` limits
+/// the number of events written per dispatch.
 pub struct PhysicsStepperSystem<N> {
+    accumulator: N,
+    contact_throttling: ContactThrottling<N>,
+
     n_marker: PhantomData<N>,
 }
 
 impl<'s, N: RealField> System<'s> for PhysicsStepperSystem<N> {
     type SystemData = (
-        Entities<'s>,
         Option<Read<'s, TimeStep<N>>>,
+        Option<Read<'s, DeltaTime<N>>>,
+        Option<Read<'s, StepBudget>>,
+        Option<Read<'s, MassScaling<N>>>,
+        ReadStorage<'s, PhysicsCollider<N>>,
         Write<'s, ContactEvents>,
         Write<'s, ProximityEvents>,
+        Write<'s, StepsDroppedEvents>,
+        Option<Read<'s, RawSubstepEvents>>,
+        Option<Read<'s, EventCapacity>>,
+        Write<'s, DroppedEventCounts>,
+        Option<Read<'s, PhysicsLogConfig>>,
+        Write<'s, PhysicsDiagnostics>,
         WriteExpect<'s, Physics<N>>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, time_step, mut contact_events, mut proximity_events, mut physics) = data;
+        let (
+            time_step,
+            delta_time,
+            step_budget,
+            mass_scaling,
+            physics_colliders,
+            mut contact_events,
+            mut proximity_events,
+            mut steps_dropped_events,
+            raw_substep_events,
+            event_capacity,
+            mut dropped_event_counts,
+            log_config,
+            mut diagnostics,
+            mut physics,
+        ) = data;
+        let mut logger = SystemLogger::new(
+            log_config.as_ref().map(|config| &**config),
+            LogSource::Stepper,
+            &mut diagnostics,
+        );
 
         // if a TimeStep resource exits, set the timestep for the nphysics integration
         // accordingly; this should not be required if the Systems are executed in a
         // fixed interval
         if let Some(time_step) = time_step {
-            // only update timestep if it actually differs from the current nphysics World
-            // one; keep in mind that changing the Resource will destabilize the simulation
-            if physics.world.timestep() != time_step.0 {
-                warn!(
-                    "TimeStep and world.timestep() differ, changing worlds timestep from {} to: {:?}",
-                    physics.world.timestep(),
-                    time_step.0
+            // non-positive timesteps would never drain the accumulator and are
+            // rejected, keeping the previous timestep of the World
+            if time_step.0 <= N::zero() {
+                logger.log(
+                    module_path!(),
+                    Level::Error,
+                    DiagnosticKind::TimeStepRejected,
+                    format_args!(
+                        "Rejected non-positive TimeStep {:?}, keeping worlds timestep {}",
+                        time_step.0,
+                        physics.world.timestep()
+                    ),
+                );
+            } else if physics.world.timestep() != time_step.0 {
+                // only update timestep if it actually differs from the current nphysics
+                // World one; keep in mind that changing the Resource will destabilize the
+                // simulation
+                logger.log(
+                    module_path!(),
+                    Level::Warn,
+                    DiagnosticKind::TimeStepChanged,
+                    format_args!(
+                        "TimeStep and world.timestep() differ, changing worlds timestep from {} \
+                         to: {:?}",
+                        physics.world.timestep(),
+                        time_step.0
+                    ),
                 );
                 physics.world.set_timestep(time_step.0);
             }
         }
 
-        physics.world.step();
+        // determine the number of substeps to run this frame; without a DeltaTime
+        // resource the world is progressed exactly once per dispatch
+        let timestep = physics.world.timestep();
+        let substeps = match delta_time {
+            Some(delta_time) => {
+                self.accumulator += delta_time.0;
+                let substeps = (self.accumulator / timestep).floor().max(N::zero());
+                self.accumulator -= substeps * timestep;
+                substeps
+                    .to_subset()
+                    .map_or(0, |substeps: f64| substeps as usize)
+            }
+            None => 1,
+        };
 
-        let collider_world = physics.world.collider_world();
+        // the impulses of throttled contacts are estimated from the velocities
+        // of the bodies before each step, which are only taken if needed
+        let throttled = (&physics_colliders)
+            .join()
+            .any(|physics_collider| physics_collider.contact_throttle.is_some());
 
-        // map occurred ncollide ContactEvents to a custom ContactEvent type; this
-        // custom type contains data that is more relevant for Specs users than
-        // CollisionObjectHandles, such as the Entities that took part in the collision
-        contact_events.iter_write(collider_world.contact_events().iter().map(|contact_event| {
-            debug!("Got ContactEvent: {:?}", contact_event);
-            // retrieve CollisionObjectHandles from ContactEvent and map the ContactEvent
-            // type to our own custom ContactType
-            let (handle1, handle2, contact_type) = match contact_event {
-                NContactEvent::Started(handle1, handle2) => {
-                    (*handle1, *handle2, ContactType::Started)
-                }
-                NContactEvent::Stopped(handle1, handle2) => {
-                    (*handle1, *handle2, ContactType::Stopped)
-                }
-            };
+        let budget = step_budget.map(|budget| *budget).unwrap_or_default();
+        let frame_start = Instant::now();
 
-            // create our own ContactEvent from the extracted data; mapping the
-            // CollisionObjectHandles to Entities is error prone but should work as intended
-            // as long as we're the only ones working directly with the nphysics World
-            ContactEvent {
-                collider1: entity_from_collision_object_handle(&entities, handle1, &collider_world),
-                collider2: entity_from_collision_object_handle(&entities, handle2, &collider_world),
-                contact_type,
-            }
-        }));
+        // the events of all substeps are collected first, so the EventCapacity
+        // applies to the whole dispatch
+        let mut new_contact_events = Vec::new();
+        let mut new_proximity_events = Vec::new();
 
-        // map occurred ncollide ProximityEvents to a custom ProximityEvent type; see
-        // ContactEvents for reasoning
-        proximity_events.iter_write(collider_world.proximity_events().iter().map(
-            |proximity_event| {
-                debug!("Got ProximityEvent: {:?}", proximity_event);
-                // retrieve CollisionObjectHandles and Proximity statuses from the ncollide
-                // ProximityEvent
-                let (handle1, handle2, prev_status, new_status) = (
-                    proximity_event.collider1,
-                    proximity_event.collider2,
-                    proximity_event.prev_status,
-                    proximity_event.new_status,
+        for substep in 0..substeps {
+            // drop all remaining substeps once the budget is exceeded; at least one
+            // step is always executed so the simulation cannot stall entirely
+            if substep > 0
+                && (substep >= budget.max_substeps
+                    || budget
+                        .max_duration
+                        .map_or(false, |max_duration| frame_start.elapsed() >= max_duration))
+            {
+                let dropped = substeps - substep;
+                logger.log(
+                    module_path!(),
+                    Level::Warn,
+                    DiagnosticKind::StepsDropped(dropped),
+                    format_args!(
+                        "Physics step budget exceeded, dropping {} of {} substeps",
+                        dropped, substeps
+                    ),
                 );
+                steps_dropped_events.single_write(StepsDroppedEvent {
+                    requested: substeps,
+                    dropped,
+                });
+                break;
+            }
 
-                // create our own ProximityEvent from the extracted data; mapping
-                // CollisionObjectHandles to Entities is once again error prone, but yeah...
-                // ncollides Proximity types are mapped to our own types
-                ProximityEvent {
-                    collider1: entity_from_collision_object_handle(
-                        &entities,
-                        handle1,
-                        &collider_world,
-                    ),
-                    collider2: entity_from_collision_object_handle(
-                        &entities,
-                        handle2,
-                        &collider_world,
-                    ),
-                    prev_status,
-                    new_status,
-                }
-            },
-        ));
+            #[cfg(feature = "metrics")]
+            let step_start = Instant::now();
+
+            // nphysics clears the external forces of its bodies after every
+            // step, so the forces of this frame are applied before each substep
+            apply_frame_forces(&mut physics);
+
+            let scaled = mass_scaling.as_ref().map_or_else(Vec::new, |mass_scaling| {
+                scale_masses(&mut physics, mass_scaling.max_ratio)
+            });
+            let velocities = if throttled {
+                linear_velocities(&physics)
+            } else {
+                HashMap::new()
+            };
+            physics.world.step();
+            physics.tick += 1;
+            restore_masses(&mut physics, scaled);
+
+            #[cfg(feature = "metrics")]
+            record_step_metrics(&physics, step_start, Instant::now());
+
+            self.contact_throttling.advance(timestep);
+            write_events(
+                &physics,
+                &physics_colliders,
+                &velocities,
+                &mut self.contact_throttling,
+                &mut new_contact_events,
+                &mut new_proximity_events,
+                &mut logger,
+            );
+        }
+
+        // the forces of frames without any substep are dropped instead of
+        // piling up until the next step
+        physics.frame_forces.clear();
+
+        // give readers a clean per-frame view of the contacts
+        if substeps > 1 && !raw_substep_events.map_or(false, |raw| raw.0) {
+            new_contact_events = coalesce_contact_events(new_contact_events);
+            new_proximity_events = coalesce_proximity_events(new_proximity_events);
+        }
+
+        #[cfg(feature = "metrics")]
+        let new_event_counts = (new_contact_events.len(), new_proximity_events.len());
+
+        let event_capacity = event_capacity.as_ref().map(|capacity| &**capacity);
+        let dropped = [
+            (
+                PhysicsEventChannel::Contacts,
+                write_limited(&mut contact_events, new_contact_events, event_capacity),
+            ),
+            (
+                PhysicsEventChannel::Proximities,
+                write_limited(&mut proximity_events, new_proximity_events, event_capacity),
+            ),
+        ];
+
+        #[cfg(feature = "metrics")]
+        record_event_metrics(
+            &physics,
+            new_event_counts.0 - dropped[0].1,
+            new_event_counts.1 - dropped[1].1,
+        );
+
+        for &(channel, dropped) in dropped.iter().filter(|(_, dropped)| *dropped > 0) {
+            dropped_event_counts.add(channel, dropped);
+            logger.log(
+                module_path!(),
+                Level::Warn,
+                DiagnosticKind::EventsDropped(channel, dropped),
+                format_args!(
+                    "EventCapacity exceeded, dropping the {} oldest {:?} events",
+                    dropped, channel
+                ),
+            );
+        }
     }
 
     fn setup(&mut self, res: &mut World) {
         info!("PhysicsStepperSystem.setup");
+
+        // size the EventChannels according to the EventCapacity
+        let capacity = res
+            .try_fetch::<EventCapacity>()
+            .map(|event_capacity| event_capacity.capacity);
+        if let Some(capacity) = capacity {
+            res.entry::<ContactEvents>()
+                .or_insert_with(|| EventChannel::with_capacity(capacity));
+            res.entry::<ProximityEvents>()
+                .or_insert_with(|| EventChannel::with_capacity(capacity));
+        }
         Self::SystemData::setup(res);
 
         // initialise required resources
@@ -124,23 +307,1075 @@ where
 {
     fn default() -> Self {
         Self {
+            accumulator: N::zero(),
+            contact_throttling: ContactThrottling::default(),
             n_marker: PhantomData,
         }
     }
 }
 
+/// Applies the external forces collected in the `Physics` for the current
+/// frame to their bodies, waking them up.
+fn apply_frame_forces<N: RealField>(physics: &mut Physics<N>) {
+    let Physics {
+        world,
+        frame_forces,
+        ..
+    } = physics;
+    for (handle, force) in frame_forces.iter() {
+        if let Some(rigid_body) = world.rigid_body_mut(*handle) {
+            rigid_body.apply_force(0, force, ForceType::Force, true);
+        }
+    }
+}
+
+/// Raises the masses of the light dynamic bodies in each group of bodies
+/// touching each other, so that no body is more than `max_ratio` times
+/// lighter than the heaviest body of its group. The angular inertia is scaled
+/// along with the mass. Returns the original mass and angular inertia of the
+/// scaled bodies.
+fn scale_masses<N: RealField>(
+    physics: &mut Physics<N>,
+    max_ratio: N,
+) -> Vec<(BodyHandle, N, Matrix3<N>)> {
+    // group the dynamic bodies by the contacts of the last step; static
+    // bodies do not join groups, so everything resting on the same floor is
+    // not merged into a single group
+    let mut parents = HashMap::new();
+    {
+        let collider_world = physics.world.collider_world();
+        let dynamic_body = |handle| {
+            collider_world
+                .collider(handle)
+                .map(|collider| collider.body())
+                .filter(|body| {
+                    physics.world.rigid_body(*body).map_or(false, |rigid_body| {
+                        rigid_body.status() == BodyStatus::Dynamic
+                    })
+                })
+        };
+        for (handle1, handle2, ..) in collider_world.as_collision_world().contact_pairs(true) {
+            if let (Some(body1), Some(body2)) = (dynamic_body(handle1), dynamic_body(handle2)) {
+                let root1 = find_root(&mut parents, body1);
+                let root2 = find_root(&mut parents, body2);
+                if root1 != root2 {
+                    parents.insert(root1, root2);
+                }
+            }
+        }
+    }
+
+    let bodies = parents.keys().cloned().collect::<Vec<_>>();
+    let roots = bodies
+        .iter()
+        .map(|body| find_root(&mut parents, *body))
+        .collect::<Vec<_>>();
+    let mut max_masses = HashMap::new();
+    for (body, root) in bodies.iter().zip(roots.iter()) {
+        if let Some(rigid_body) = physics.world.rigid_body(*body) {
+            let mass = rigid_body.local_inertia().linear;
+            let max_mass = max_masses.entry(*root).or_insert(mass);
+            if mass > *max_mass {
+                *max_mass = mass;
+            }
+        }
+    }
+
+    let mut scaled = Vec::new();
+    for (body, root) in bodies.iter().zip(roots.iter()) {
+        let min_mass = max_masses[root] / max_ratio;
+        if let Some(rigid_body) = physics.world.rigid_body_mut(*body) {
+            let local_inertia = rigid_body.local_inertia();
+            let (mass, angular_inertia) = (local_inertia.linear, local_inertia.angular);
+            if mass <= N::zero() || mass >= min_mass {
+                continue;
+            }
+
+            rigid_body.set_mass(min_mass);
+            rigid_body.set_angular_inertia(angular_inertia * (min_mass / mass));
+            scaled.push((*body, mass, angular_inertia));
+        }
+    }
+    scaled
+}
+
+/// Restores the masses changed by `scale_masses`.
+fn restore_masses<N: RealField>(
+    physics: &mut Physics<N>,
+    scaled: Vec<(BodyHandle, N, Matrix3<N>)>,
+) {
+    for (body, mass, angular_inertia) in scaled {
+        if let Some(rigid_body) = physics.world.rigid_body_mut(body) {
+            rigid_body.set_mass(mass);
+            rigid_body.set_angular_inertia(angular_inertia);
+        }
+    }
+}
+
+/// Finds the representative body of the group of the given body, adding the
+/// body as a group of its own if it is unknown.
+fn find_root(parents: &mut HashMap<BodyHandle, BodyHandle>, body: BodyHandle) -> BodyHandle {
+    let mut root = *parents.entry(body).or_insert(body);
+    while parents[&root] != root {
+        root = parents[&root];
+    }
+    // shorten the path for the next lookup
+    parents.insert(body, root);
+    root
+}
+
+/// The state of the `ContactThrottle`s of all `PhysicsCollider`s.
+struct ContactThrottling<N> {
+    /// The simulated time since the first step.
+    elapsed: N,
+    /// The time of the last `Started` event and the `min_interval` of the
+    /// throttled pairs of colliders, as long as the interval lasts.
+    last_started: HashMap<(Entity, Entity), (N, N)>,
+    /// The pairs of colliders in contact whose `Started` event was suppressed.
+    suppressed: HashSet<(Entity, Entity)>,
+}
+
+impl<N: RealField> ContactThrottling<N> {
+    /// Progresses the simulated time by one step and forgets the pairs whose
+    /// interval has passed.
+    fn advance(&mut self, timestep: N) {
+        let elapsed = self.elapsed + timestep;
+        self.elapsed = elapsed;
+        self.last_started
+            .retain(|_, (time, min_interval)| elapsed - *time < *min_interval);
+    }
+
+    /// Decides whether the given `ContactEvent` is emitted, given the
+    /// `ContactThrottle`s of its colliders.
+    fn admit(
+        &mut self,
+        contact_event: &ContactEvent,
+        throttle: Option<ContactThrottle<N>>,
+        impulse: impl FnOnce() -> Option<N>,
+    ) -> bool {
+        let pair = ordered_pair(contact_event.collider1, contact_event.collider2);
+
+        match contact_event.contact_type {
+            ContactType::Stopped => !self.suppressed.remove(&pair),
+            ContactType::Started => {
+                let throttle = match throttle {
+                    Some(throttle) => throttle,
+                    None => return true,
+                };
+
+                let recent = self.last_started.contains_key(&pair);
+                let weak = impulse().map_or(false, |impulse| impulse < throttle.min_impulse);
+                if recent || weak {
+                    self.suppressed.insert(pair);
+                    return false;
+                }
+                if throttle.min_interval > N::zero() {
+                    self.last_started
+                        .insert(pair, (self.elapsed, throttle.min_interval));
+                }
+                true
+            }
+        }
+    }
+}
+
+impl<N: RealField> Default for ContactThrottling<N> {
+    fn default() -> Self {
+        Self {
+            elapsed: N::zero(),
+            last_started: HashMap::new(),
+            suppressed: HashSet::new(),
+        }
+    }
+}
+
+/// Collects the linear velocities of all rigid bodies with a collider.
+fn linear_velocities<N: RealField>(physics: &Physics<N>) -> HashMap<BodyHandle, Vector3<N>> {
+    physics
+        .world
+        .colliders()
+        .filter_map(|collider| {
+            let rigid_body = physics.world.rigid_body(collider.body())?;
+            Some((collider.body(), rigid_body.velocity().linear))
+        })
+        .collect()
+}
+
+/// Estimates the impulse of a contact between two colliders from the
+/// relative velocity their bodies approached each other with and their
+/// reduced mass. The `velocities` have to be taken before the step, as the
+/// solver has already resolved the contact afterwards. Returns `None` if
+/// neither body is dynamic, as the impulse is unbounded.
+fn contact_impulse<N: RealField>(
+    physics: &Physics<N>,
+    velocities: &HashMap<BodyHandle, Vector3<N>>,
+    handle1: CollisionObjectHandle,
+    handle2: CollisionObjectHandle,
+) -> Option<N> {
+    // colliders without a dynamic body of their own have an infinite mass
+    let motion = |handle| {
+        let body = physics.world.collider(handle)?.body();
+        let rigid_body = physics.world.rigid_body(body)?;
+        let inverse_mass = match rigid_body.status() {
+            BodyStatus::Dynamic if rigid_body.local_inertia().linear > N::zero() => {
+                N::one() / rigid_body.local_inertia().linear
+            }
+            _ => N::zero(),
+        };
+        let velocity = velocities
+            .get(&body)
+            .cloned()
+            .unwrap_or(rigid_body.velocity().linear);
+        Some((velocity, inverse_mass))
+    };
+    let (velocity1, inverse_mass1) = motion(handle1).unwrap_or((Vector3::zeros(), N::zero()));
+    let (velocity2, inverse_mass2) = motion(handle2).unwrap_or((Vector3::zeros(), N::zero()));
+
+    let inverse_mass = inverse_mass1 + inverse_mass2;
+    if inverse_mass > N::zero() {
+        Some((velocity1 - velocity2).norm() / inverse_mass)
+    } else {
+        None
+    }
+}
+
+/// Maps the ncollide events of the last step to our own event types and
+/// collects them for their respective `EventChannel`s. `ContactEvent`s are
+/// filtered by the `ContactThrottle`s of their colliders.
+fn write_events<N: RealField>(
+    physics: &Physics<N>,
+    physics_colliders: &ReadStorage<PhysicsCollider<N>>,
+    velocities: &HashMap<BodyHandle, Vector3<N>>,
+    contact_throttling: &mut ContactThrottling<N>,
+    contact_events: &mut Vec<ContactEvent>,
+    proximity_events: &mut Vec<ProximityEvent>,
+    logger: &mut SystemLogger,
+) {
+    let collider_world = physics.world.collider_world();
+
+    // map occurred ncollide ContactEvents to a custom ContactEvent type; this
+    // custom type contains data that is more relevant for Specs users than
+    // CollisionObjectHandles, such as the Entities that took part in the collision
+    for contact_event in collider_world.contact_events().iter() {
+        logger.debug(
+            module_path!(),
+            format_args!("Got ContactEvent: {:?}", contact_event),
+        );
+        // retrieve CollisionObjectHandles from ContactEvent and map the ContactEvent
+        // type to our own custom ContactType
+        let (handle1, handle2, contact_type) = match contact_event {
+            NContactEvent::Started(handle1, handle2) => (*handle1, *handle2, ContactType::Started),
+            NContactEvent::Stopped(handle1, handle2) => (*handle1, *handle2, ContactType::Stopped),
+        };
+
+        // create our own ContactEvent from the extracted data; the
+        // CollisionObjectHandles are mapped to the Entities stored as user data
+        // of the colliders, ordered by their EventRoles. Events of colliders
+        // removed in the meantime are skipped.
+        let (collider1, collider2) = match (
+            entity_from_collision_object_handle(handle1, collider_world),
+            entity_from_collision_object_handle(handle2, collider_world),
+        ) {
+            (Some(entity1), Some(entity2)) => oriented_pair(physics_colliders, entity1, entity2),
+            _ => continue,
+        };
+        let contact_event = ContactEvent {
+            collider1,
+            collider2,
+            contact_type,
+            tick: physics.tick,
+        };
+
+        let throttle = |entity| {
+            physics_colliders
+                .get(entity)
+                .and_then(|physics_collider| physics_collider.contact_throttle)
+        };
+        let throttle = match (
+            throttle(contact_event.collider1),
+            throttle(contact_event.collider2),
+        ) {
+            (Some(throttle1), Some(throttle2)) => Some(throttle1.max(throttle2)),
+            (throttle1, throttle2) => throttle1.or(throttle2),
+        };
+
+        if contact_throttling.admit(&contact_event, throttle, || {
+            contact_impulse(physics, velocities, handle1, handle2)
+        }) {
+            contact_events.push(contact_event);
+        } else {
+            logger.debug(
+                module_path!(),
+                format_args!("Throttled ContactEvent: {:?}", contact_event),
+            );
+        }
+    }
+
+    // map occurred ncollide ProximityEvents to a custom ProximityEvent type; see
+    // ContactEvents for reasoning
+    proximity_events.extend(collider_world.proximity_events().iter().filter_map(
+        |proximity_event| {
+            logger.debug(
+                module_path!(),
+                format_args!("Got ProximityEvent: {:?}", proximity_event),
+            );
+            // retrieve CollisionObjectHandles and Proximity statuses from the ncollide
+            // ProximityEvent
+            let (handle1, handle2, prev_status, new_status) = (
+                proximity_event.collider1,
+                proximity_event.collider2,
+                proximity_event.prev_status,
+                proximity_event.new_status,
+            );
+
+            // create our own ProximityEvent from the extracted data; see ContactEvents
+            // for the mapping of CollisionObjectHandles to Entities
+            let (collider1, collider2) = oriented_pair(
+                physics_colliders,
+                entity_from_collision_object_handle(handle1, collider_world)?,
+                entity_from_collision_object_handle(handle2, collider_world)?,
+            );
+            Some(ProximityEvent {
+                collider1,
+                collider2,
+                prev_status,
+                new_status,
+                tick: physics.tick,
+            })
+        },
+    ));
+}
+
+/// Orders the `Entity`s of a pair of colliders, so both orders map to the
+/// same key.
+fn ordered_pair(entity1: Entity, entity2: Entity) -> (Entity, Entity) {
+    if entity1 < entity2 {
+        (entity1, entity2)
+    } else {
+        (entity2, entity1)
+    }
+}
+
+/// Groups events by their pair of colliders, in the order of the first event
+/// of each pair.
+fn group_by_pair<E>(events: Vec<E>, colliders: impl Fn(&E) -> (Entity, Entity)) -> Vec<Vec<E>> {
+    let mut indices = HashMap::new();
+    let mut groups: Vec<Vec<E>> = Vec::new();
+    for event in events {
+        let (collider1, collider2) = colliders(&event);
+        let index = *indices
+            .entry(ordered_pair(collider1, collider2))
+            .or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+        groups[index].push(event);
+    }
+    groups
+}
+
+/// Coalesces the `ContactEvent`s of several substeps into at most one
+/// `Started` and one `Stopped` event per pair of colliders. A pair that
+/// started or stopped touching reports the last event, a pair touching only
+/// briefly reports both its first `Started` and its last `Stopped` event, and
+/// a pair separating only briefly reports nothing.
+fn coalesce_contact_events(events: Vec<ContactEvent>) -> Vec<ContactEvent> {
+    let mut coalesced = Vec::new();
+    for mut group in group_by_pair(events, |event| (event.collider1, event.collider2)) {
+        let last = group.pop().unwrap();
+        if group.is_empty() {
+            coalesced.push(last);
+            continue;
+        }
+
+        let first = group.swap_remove(0);
+        match (&first.contact_type, &last.contact_type) {
+            (ContactType::Started, ContactType::Stopped) => {
+                coalesced.push(first);
+                coalesced.push(last);
+            }
+            (ContactType::Stopped, ContactType::Started) => (),
+            _ => coalesced.push(last),
+        }
+    }
+    coalesced
+}
+
+/// Coalesces the `ProximityEvent`s of several substeps into the change of
+/// status of each pair of colliders over all substeps. Like contacts, a pair
+/// intersecting only briefly reports entering and leaving the intersection,
+/// while a pair leaving the intersection only briefly reports nothing.
+fn coalesce_proximity_events(events: Vec<ProximityEvent>) -> Vec<ProximityEvent> {
+    let mut coalesced = Vec::new();
+    for group in group_by_pair(events, |event| (event.collider1, event.collider2)) {
+        let (first, last) = (&group[0], &group[group.len() - 1]);
+        let event = |prev_status, new_status, tick| ProximityEvent {
+            collider1: last.collider1,
+            collider2: last.collider2,
+            prev_status,
+            new_status,
+            tick,
+        };
+
+        if first.prev_status != last.new_status {
+            coalesced.push(event(first.prev_status, last.new_status, last.tick));
+        } else if first.prev_status != Proximity::Intersecting {
+            if let Some(entered) = group
+                .iter()
+                .find(|event| event.new_status == Proximity::Intersecting)
+            {
+                coalesced.push(event(
+                    first.prev_status,
+                    Proximity::Intersecting,
+                    entered.tick,
+                ));
+                coalesced.push(event(Proximity::Intersecting, last.new_status, last.tick));
+            }
+        }
+    }
+    coalesced
+}
+
+/// Writes the events of a dispatch to their `EventChannel`, applying the
+/// `OverflowPolicy` of the `EventCapacity` if there are too many. Returns the
+/// number of dropped events.
+fn write_limited<E: Event>(
+    channel: &mut EventChannel<E>,
+    mut events: Vec<E>,
+    event_capacity: Option<&EventCapacity>,
+) -> usize {
+    let excess = event_capacity.map_or(0, |event_capacity| {
+        events.len().saturating_sub(event_capacity.capacity)
+    });
+    if excess > 0 {
+        let event_capacity = event_capacity.unwrap();
+        match event_capacity.overflow {
+            OverflowPolicy::DropOldest => {
+                events.drain(..excess);
+            }
+            OverflowPolicy::Panic => panic!(
+                "{} events exceed the EventCapacity of {}",
+                events.len(),
+                event_capacity.capacity
+            ),
+        }
+    }
+
+    channel.drain_vec_write(&mut events);
+    excess
+}
+
+/// Emits the cost of the last step through the `metrics` facade.
+#[cfg(feature = "metrics")]
+fn record_step_metrics<N: RealField>(physics: &Physics<N>, step_start: Instant, step_end: Instant) {
+    let collider_world = physics.world.collider_world();
+
+    timing!("specs_physics.step_duration", step_start, step_end);
+    gauge!(
+        "specs_physics.bodies",
+        physics.world.bodies().count() as i64
+    );
+    gauge!(
+        "specs_physics.colliders",
+        collider_world.colliders().count() as i64
+    );
+    counter!(
+        "specs_physics.contact_events",
+        collider_world.contact_events().len() as u64
+    );
+    counter!(
+        "specs_physics.proximity_events",
+        collider_world.proximity_events().len() as u64
+    );
+}
+
+/// Emits the number of events written to the `ContactEvents` and
+/// `ProximityEvents` channels during the dispatch, after coalescing and the
+/// `EventCapacity` were applied, as well as the number of touching collider
+/// pairs through the `metrics` facade.
+#[cfg(feature = "metrics")]
+fn record_event_metrics<N: RealField>(
+    physics: &Physics<N>,
+    contact_events: usize,
+    proximity_events: usize,
+) {
+    gauge!("specs_physics.contact_event_channel", contact_events as i64);
+    gauge!(
+        "specs_physics.proximity_event_channel",
+        proximity_events as i64
+    );
+    gauge!(
+        "specs_physics.contact_pairs",
+        physics
+            .world
+            .collider_world()
+            .as_collision_world()
+            .contact_pairs(true)
+            .count() as i64
+    );
+}
+
+/// Resolves the `Entity` stored as user data of the collider, if the collider
+/// still exists.
 fn entity_from_collision_object_handle<N: RealField>(
-    entities: &Entities,
     collision_object_handle: CollisionObjectHandle,
     collider_world: &ColliderWorld<N>,
-) -> Entity {
-    entities.entity(
-        *collider_world
-            .collider(collision_object_handle)
-            .unwrap()
-            .user_data()
-            .unwrap()
-            .downcast_ref::<Index>()
-            .unwrap(),
-    )
+) -> Option<Entity> {
+    collider_world
+        .collider(collision_object_handle)
+        .and_then(|collider| entity_from_user_data(collider.user_data()))
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use super::{coalesce_contact_events, restore_masses, scale_masses};
+    use crate::{
+        bodies::PhysicsBody,
+        colliders::{ContactThrottle, EventRole, Shape},
+        events::{
+            ContactEvent,
+            ContactEvents,
+            ContactType,
+            DroppedEventCounts,
+            PhysicsEventChannel,
+            StepsDroppedEvents,
+        },
+        nalgebra::{Isometry3, Vector3},
+        nphysics::{algebra::Force3, object::BodyStatus},
+        parameters::{
+            DeltaTime,
+            EventCapacity,
+            Gravity,
+            MassScaling,
+            OverflowPolicy,
+            StepBudget,
+            TimeStep,
+        },
+        systems::{PhysicsStepperSystem, SyncBodiesToPhysicsSystem, SyncCollidersToPhysicsSystem},
+        Physics,
+        PhysicsBodyBuilder,
+        PhysicsColliderBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn drop_substeps_over_budget() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                PhysicsStepperSystem::<f32>::default(),
+                "physics_stepper_system",
+                &[],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+        let mut reader = world.fetch_mut::<StepsDroppedEvents>().register_reader();
+
+        // half a second worth of substeps exceeds the budget of two substeps
+        world.insert(DeltaTime(0.5f32));
+        world.insert(StepBudget {
+            max_substeps: 2,
+            ..StepBudget::default()
+        });
+        dispatcher.dispatch(&world);
+
+        let events = world.read_resource::<StepsDroppedEvents>();
+        let dropped = events.read(&mut reader).collect::<Vec<_>>();
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(dropped[0].dropped, dropped[0].requested - 2);
+    }
+
+    #[test]
+    fn reject_non_positive_timestep() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                PhysicsStepperSystem::<f32>::default(),
+                "physics_stepper_system",
+                &[],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+
+        // the default timestep of 1/60s is kept, fitting three substeps
+        world.insert(TimeStep(0.0f32));
+        world.insert(DeltaTime(0.055f32));
+        dispatcher.dispatch(&world);
+
+        let physics = world.read_resource::<Physics<f32>>();
+        assert!(physics.timestep() > 0.0);
+        assert_eq!(physics.tick(), 3);
+    }
+
+    #[test]
+    fn apply_forces_every_substep() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+
+        let entity = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(
+                PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                    .gravity_enabled(false)
+                    .mass(1.0)
+                    .build(),
+            )
+            .build();
+        world.insert(DeltaTime(0.0f32));
+        dispatcher.dispatch(&world);
+
+        // pushes the body during a frame of the given duration and returns the
+        // gained speed
+        let mut push = |world: &mut World, delta_time: f32| {
+            let speed = |world: &World| {
+                world
+                    .read_storage::<PhysicsBody<f32>>()
+                    .get(entity)
+                    .unwrap()
+                    .velocity
+                    .linear
+                    .x
+            };
+            let start = speed(world);
+            world.insert(DeltaTime(delta_time));
+            world
+                .write_storage::<PhysicsBody<f32>>()
+                .get_mut(entity)
+                .unwrap()
+                .apply_external_force(&Force3::linear(Vector3::x()));
+            dispatcher.dispatch(world);
+            speed(world) - start
+        };
+
+        let dt = 1.0 / 60.0;
+        // the force acts during both substeps of two and a half steps
+        assert!((push(&mut world, 2.5 * dt) - 2.0 * dt).abs() < 1.0e-4);
+        // the force of a frame without substeps is dropped, not piled up
+        assert_eq!(push(&mut world, 0.0), 0.0);
+        assert!((push(&mut world, 0.6 * dt) - dt).abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn scale_masses_of_stack() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+        world.insert(Gravity(Vector3::<f32>::new(0.0, -9.81, 0.0)));
+        world.insert(MassScaling::<f32>::default());
+
+        // a heavy crate resting on a light one on the floor
+        let crate_shape = Shape::Cuboid {
+            half_extents: Vector3::new(0.5, 0.5, 0.5),
+        };
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::ground()).build())
+            .build();
+        let light = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(0.0, 0.5, 0.0)))
+            .with(
+                PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                    .mass(1.0)
+                    .build(),
+            )
+            .with(PhysicsColliderBuilder::<f32>::from(crate_shape.clone()).build())
+            .build();
+        let heavy = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(0.0, 1.5, 0.0)))
+            .with(
+                PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                    .mass(1000.0)
+                    .build(),
+            )
+            .with(PhysicsColliderBuilder::<f32>::from(crate_shape).build())
+            .build();
+        for _ in 0..60 {
+            dispatcher.dispatch(&world);
+        }
+
+        // the light crate keeps its mass and is not crushed into the floor
+        let physics_bodies = world.read_storage::<PhysicsBody<f32>>();
+        assert_eq!(physics_bodies.get(light).unwrap().mass, 1.0);
+        let positions = world.read_storage::<SimplePosition<f32>>();
+        assert!(positions.get(light).unwrap().0.translation.vector.y > 0.4);
+
+        // while stepping, the light crate is raised to a tenth of the mass of
+        // the heavy one, with its angular inertia scaled along
+        let handle = |entity| physics_bodies.get(entity).unwrap().handle.unwrap();
+        let (light, heavy) = (handle(light), handle(heavy));
+        let mut physics = world.write_resource::<Physics<f32>>();
+        let inertia = |physics: &Physics<f32>, body| {
+            let local_inertia = physics.world.rigid_body(body).unwrap().local_inertia();
+            (local_inertia.linear, local_inertia.angular)
+        };
+        let (light_mass, light_angular) = inertia(&physics, light);
+        let (heavy_mass, heavy_angular) = inertia(&physics, heavy);
+
+        let scaled = scale_masses(&mut physics, 10.0);
+        assert_eq!(scaled.len(), 1);
+        let (scaled_mass, scaled_angular) = inertia(&physics, light);
+        let ratio = scaled_mass / light_mass;
+        assert!((scaled_mass - heavy_mass / 10.0).abs() < 1.0e-3);
+        assert!((scaled_angular - light_angular * ratio).norm() < 1.0e-3);
+        assert_eq!(inertia(&physics, heavy), (heavy_mass, heavy_angular));
+
+        // and restored after the step
+        restore_masses(&mut physics, scaled);
+        assert_eq!(inertia(&physics, light), (light_mass, light_angular));
+    }
+
+    #[test]
+    fn throttle_weak_contacts() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+        world.insert(Gravity(Vector3::<f32>::new(0.0, -9.81, 0.0)));
+        let mut reader_id = world.fetch_mut::<ContactEvents>().register_reader();
+
+        // two balls resting on the floor, only one of them throttled
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::ground()).build())
+            .build();
+        let throttled = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(
+                -1.0, 0.5, 0.0,
+            )))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 })
+                    .contact_throttle(ContactThrottle {
+                        min_interval: 1.0,
+                        min_impulse: 100.0,
+                    })
+                    .build(),
+            )
+            .build();
+        let unthrottled = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(1.0, 0.5, 0.0)))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .build();
+        for _ in 0..10 {
+            dispatcher.dispatch(&world);
+        }
+
+        let contact_events = world.fetch::<ContactEvents>();
+        let colliders = contact_events
+            .read(&mut reader_id)
+            .flat_map(|contact_event| vec![contact_event.collider1, contact_event.collider2])
+            .collect::<Vec<_>>();
+        assert!(colliders.contains(&unthrottled));
+        assert!(!colliders.contains(&throttled));
+    }
+
+    #[test]
+    fn admit_falling_contacts() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+        world.insert(Gravity(Vector3::<f32>::new(0.0, -9.81, 0.0)));
+        let mut reader_id = world.fetch_mut::<ContactEvents>().register_reader();
+
+        // a ball dropped from five meters hits the floor with an impulse of
+        // about 7, while the solver has already stopped it after the step
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::ground()).build())
+            .build();
+        let ball = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(0.0, 5.5, 0.0)))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 })
+                    .contact_throttle(ContactThrottle {
+                        min_interval: 0.0,
+                        min_impulse: 1.0,
+                    })
+                    .build(),
+            )
+            .build();
+        for _ in 0..90 {
+            dispatcher.dispatch(&world);
+        }
+
+        let contact_events = world.fetch::<ContactEvents>();
+        assert!(contact_events.read(&mut reader_id).any(|contact_event| {
+            contact_event.contact_type == ContactType::Started
+                && (contact_event.collider1 == ball || contact_event.collider2 == ball)
+        }));
+    }
+
+    #[test]
+    fn orient_events_by_role() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncBodiesToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_bodies_to_physics_system",
+                &[],
+            )
+            .with(
+                SyncCollidersToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_colliders_to_physics_system",
+                &["sync_bodies_to_physics_system"],
+            )
+            .with(
+                PhysicsStepperSystem::<f32>::default(),
+                "physics_stepper_system",
+                &["sync_colliders_to_physics_system"],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+        let mut reader_id = world.fetch_mut::<ContactEvents>().register_reader();
+
+        // a player and a plain ball touching the floor, which is the target
+        let floor = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::ground())
+                    .event_role(EventRole::Target)
+                    .build(),
+            )
+            .build();
+        let player = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(0.0, 0.5, 0.0)))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 })
+                    .event_role(EventRole::Source)
+                    .build(),
+            )
+            .build();
+        let ball = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(2.0, 0.5, 0.0)))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .build();
+        dispatcher.dispatch(&world);
+
+        let contact_events = world.fetch::<ContactEvents>();
+        let mut pairs = contact_events
+            .read(&mut reader_id)
+            .map(|contact_event| (contact_event.collider1, contact_event.collider2))
+            .collect::<Vec<_>>();
+        pairs.sort();
+        assert_eq!(pairs, vec![(player, floor), (ball, floor)]);
+    }
+
+    #[test]
+    fn drop_oldest_events_over_capacity() {
+        let mut world = World::new();
+        world.insert(EventCapacity {
+            capacity: 1,
+            overflow: OverflowPolicy::DropOldest,
+        });
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncBodiesToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_bodies_to_physics_system",
+                &[],
+            )
+            .with(
+                SyncCollidersToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_colliders_to_physics_system",
+                &["sync_bodies_to_physics_system"],
+            )
+            .with(
+                PhysicsStepperSystem::<f32>::default(),
+                "physics_stepper_system",
+                &["sync_colliders_to_physics_system"],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+        let mut reader_id = world.fetch_mut::<ContactEvents>().register_reader();
+
+        // three balls touching the floor at once
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::ground()).build())
+            .build();
+        for x in 0..3 {
+            world
+                .create_entity()
+                .with(SimplePosition::<f32>(Isometry3::translation(
+                    x as f32 * 2.0,
+                    0.5,
+                    0.0,
+                )))
+                .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+                .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+                .build();
+        }
+        for _ in 0..3 {
+            dispatcher.dispatch(&world);
+            assert!(world.fetch::<ContactEvents>().read(&mut reader_id).count() <= 1);
+        }
+
+        let dropped_event_counts = world.fetch::<DroppedEventCounts>();
+        assert_eq!(dropped_event_counts.get(PhysicsEventChannel::Contacts), 2);
+    }
+
+    #[test]
+    fn stamp_events_with_tick() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncBodiesToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_bodies_to_physics_system",
+                &[],
+            )
+            .with(
+                SyncCollidersToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_colliders_to_physics_system",
+                &["sync_bodies_to_physics_system"],
+            )
+            .with(
+                PhysicsStepperSystem::<f32>::default(),
+                "physics_stepper_system",
+                &["sync_colliders_to_physics_system"],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+        let mut reader_id = world.fetch_mut::<ContactEvents>().register_reader();
+
+        // a ball touching the floor right away, stepped four times in one frame
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::ground()).build())
+            .build();
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(0.0, 0.5, 0.0)))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .build();
+        world.insert(DeltaTime(4.5f32 / 60.0));
+        dispatcher.dispatch(&world);
+
+        assert_eq!(world.fetch::<Physics<f32>>().tick(), 4);
+        let contact_events = world.fetch::<ContactEvents>();
+        let contact_event = contact_events.read(&mut reader_id).next().unwrap();
+        assert_eq!(contact_event.tick, 1);
+    }
+
+    #[test]
+    fn coalesce_substep_contacts() {
+        let mut world = World::new();
+        let (ball, floor, wall) = (
+            world.create_entity().build(),
+            world.create_entity().build(),
+            world.create_entity().build(),
+        );
+        let contact_event = |collider2, contact_type, tick| ContactEvent {
+            collider1: ball,
+            collider2,
+            contact_type,
+            tick,
+        };
+
+        // the ball bounces on the floor twice and touches the wall briefly
+        let events = vec![
+            contact_event(floor, ContactType::Started, 1),
+            contact_event(wall, ContactType::Started, 1),
+            contact_event(floor, ContactType::Stopped, 2),
+            contact_event(wall, ContactType::Stopped, 2),
+            contact_event(floor, ContactType::Started, 3),
+        ];
+        let coalesced = coalesce_contact_events(events)
+            .into_iter()
+            .map(|event| (event.collider2, event.contact_type, event.tick))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            coalesced,
+            vec![
+                (floor, ContactType::Started, 3),
+                (wall, ContactType::Started, 1),
+                (wall, ContactType::Stopped, 2),
+            ]
+        );
+    }
+
+    #[cfg(feature = "metrics")]
+    thread_local! {
+        // the Recorder is global, the gauges are kept per thread to isolate the
+        // tests running in parallel
+        static GAUGES: std::cell::RefCell<std::collections::HashMap<String, i64>> =
+            Default::default();
+    }
+
+    #[cfg(feature = "metrics")]
+    struct GaugeRecorder;
+
+    #[cfg(feature = "metrics")]
+    impl metrics_facade::Recorder for GaugeRecorder {
+        fn increment_counter(&self, _key: metrics_facade::Key, _value: u64) {}
+
+        fn update_gauge(&self, key: metrics_facade::Key, value: i64) {
+            GAUGES.with(|gauges| {
+                gauges.borrow_mut().insert(key.name().to_string(), value);
+            });
+        }
+
+        fn record_histogram(&self, _key: metrics_facade::Key, _value: u64) {}
+    }
+
+    #[cfg(feature = "metrics")]
+    static RECORDER: GaugeRecorder = GaugeRecorder;
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn record_event_gauges() {
+        let _ = metrics_facade::set_recorder(&RECORDER);
+
+        // the Systems are run on this thread, so their gauges end up in GAUGES
+        let mut world = World::new();
+        let mut sync_bodies = SyncBodiesToPhysicsSystem::<f32, SimplePosition<f32>>::default();
+        let mut sync_colliders =
+            SyncCollidersToPhysicsSystem::<f32, SimplePosition<f32>>::default();
+        let mut stepper = PhysicsStepperSystem::<f32>::default();
+        RunNow::setup(&mut sync_bodies, &mut world);
+        RunNow::setup(&mut sync_colliders, &mut world);
+        RunNow::setup(&mut stepper, &mut world);
+
+        // a ball sunk into the floor
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::ground()).build())
+            .build();
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(0.0, 0.4, 0.0)))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .build();
+        sync_bodies.run_now(&world);
+        sync_colliders.run_now(&world);
+        stepper.run_now(&world);
+
+        GAUGES.with(|gauges| {
+            let gauges = gauges.borrow();
+            assert_eq!(gauges["specs_physics.contact_event_channel"], 1);
+            assert_eq!(gauges["specs_physics.proximity_event_channel"], 0);
+            assert_eq!(gauges["specs_physics.contact_pairs"], 1);
+        });
+    }
 }
@@ -0,0 +1,444 @@
+use std::marker::PhantomData;
+
+use specs::{
+    storage::ComponentEvent,
+    Entities,
+    Join,
+    Read,
+    ReadExpect,
+    ReadStorage,
+    ReaderId,
+    System,
+    SystemData,
+    World,
+    Write,
+    WriteStorage,
+};
+
+use crate::{
+    bodies::{PhysicsBody, Position},
+    colliders::PhysicsCollider,
+    commands::PhysicsCommands,
+    diagnostics::{LogSource, PhysicsDiagnostics, PhysicsLogConfig, SystemLogger},
+    nalgebra::RealField,
+    parameters::{DeltaTime, PhysicsConfig, TimeStep},
+    recording::{PhysicsInput, PhysicsRecording, RecordedFrame},
+    Physics,
+};
+
+use super::{iterate_component_events, ComponentEvents};
+
+/// The `RecordPhysicsInputsSystem` records all changes to the `Position`,
+/// `PhysicsBody` and `PhysicsCollider` `Component`s of physics `Entity`s, the
+/// queued `PhysicsCommands`, as well as the `TimeStep`, `DeltaTime` and
+/// `PhysicsConfig`, into the `PhysicsRecording` `Resource`. It has to run
+/// right before the `SyncBodiesToPhysicsSystem` and after all `System`s
+/// queueing commands or applying forces, e.g. the `ApplyPdControllersSystem`.
+/// The forces of such force `Component`s are recorded as the external forces
+/// of the `PhysicsBody`, so replaying them does not require the `Component`s.
+///
+/// The `Position`s and `PhysicsBody`s written back by the
+/// `SyncBodiesFromPhysicsSystem` are no inputs and are not recorded, unless
+/// they were changed again afterwards.
+pub struct RecordPhysicsInputsSystem<N, P> {
+    positions_reader_id: Option<ReaderId<ComponentEvent>>,
+    physics_bodies_reader_id: Option<ReaderId<ComponentEvent>>,
+    physics_colliders_reader_id: Option<ReaderId<ComponentEvent>>,
+    position_events: ComponentEvents,
+    physics_body_events: ComponentEvents,
+    physics_collider_events: ComponentEvents,
+    config: Option<PhysicsConfig<N>>,
+
+    n_marker: PhantomData<N>,
+    p_marker: PhantomData<P>,
+}
+
+impl<'s, N, P> System<'s> for RecordPhysicsInputsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, P>,
+        ReadStorage<'s, PhysicsBody<N>>,
+        ReadStorage<'s, PhysicsCollider<N>>,
+        Option<Read<'s, TimeStep<N>>>,
+        Option<Read<'s, DeltaTime<N>>>,
+        Option<Read<'s, PhysicsConfig<N>>>,
+        Read<'s, PhysicsCommands<N>>,
+        ReadExpect<'s, Physics<N>>,
+        Write<'s, PhysicsRecording<N>>,
+        Option<Read<'s, PhysicsLogConfig>>,
+        Write<'s, PhysicsDiagnostics>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            positions,
+            physics_bodies,
+            physics_colliders,
+            time_step,
+            delta_time,
+            config,
+            commands,
+            physics,
+            mut recording,
+            log_config,
+            mut diagnostics,
+        ) = data;
+        let mut logger = SystemLogger::new(
+            log_config.as_ref().map(|config| &**config),
+            LogSource::Bodies,
+            &mut diagnostics,
+        );
+
+        // collect all ComponentEvents for the Position, PhysicsBody and
+        // PhysicsCollider storages
+        iterate_component_events(
+            &positions,
+            self.positions_reader_id.as_mut().unwrap(),
+            &mut self.position_events,
+            &mut logger,
+        );
+        iterate_component_events(
+            &physics_bodies,
+            self.physics_bodies_reader_id.as_mut().unwrap(),
+            &mut self.physics_body_events,
+            &mut logger,
+        );
+        iterate_component_events(
+            &physics_colliders,
+            self.physics_colliders_reader_id.as_mut().unwrap(),
+            &mut self.physics_collider_events,
+            &mut logger,
+        );
+        let (position_events, physics_body_events, physics_collider_events) = (
+            &self.position_events,
+            &self.physics_body_events,
+            &self.physics_collider_events,
+        );
+
+        let mut inputs = Vec::new();
+
+        // the rigid body of a PhysicsBody is only used to detect the values
+        // written back by the SyncBodiesFromPhysicsSystem
+        let rigid_body_of = |physics_body: &PhysicsBody<N>| {
+            physics_body
+                .handle
+                .and_then(|handle| physics.world.rigid_body(handle))
+        };
+
+        // only the Positions of Entities taking part in the simulation are inputs
+        for (entity, position, id) in (
+            &entities,
+            &positions,
+            &position_events.inserted | &position_events.modified,
+        )
+            .join()
+        {
+            if !physics_bodies.contains(entity) && !physics_colliders.contains(entity) {
+                continue;
+            }
+
+            let written_back = !position_events.inserted.contains(id)
+                && physics_bodies
+                    .get(entity)
+                    .and_then(rigid_body_of)
+                    .map_or(false, |rigid_body| {
+                        rigid_body.position() == position.isometry()
+                    });
+            if !written_back {
+                inputs.push(PhysicsInput::PositionSet {
+                    id,
+                    isometry: *position.isometry(),
+                });
+            }
+        }
+
+        for (physics_body, id) in (
+            &physics_bodies,
+            &physics_body_events.inserted | &physics_body_events.modified,
+        )
+            .join()
+        {
+            let written_back = !physics_body_events.inserted.contains(id)
+                && rigid_body_of(physics_body).map_or(false, |rigid_body| {
+                    physics_body.matches_physics_world(rigid_body)
+                });
+            if !written_back {
+                inputs.push(PhysicsInput::BodySet {
+                    id,
+                    physics_body: *physics_body,
+                });
+            }
+        }
+        for id in (&physics_body_events.removed).join() {
+            inputs.push(PhysicsInput::BodyRemoved { id });
+        }
+
+        for (physics_collider, id) in (
+            &physics_colliders,
+            &physics_collider_events.inserted | &physics_collider_events.modified,
+        )
+            .join()
+        {
+            inputs.push(PhysicsInput::ColliderSet {
+                id,
+                physics_collider: physics_collider.clone(),
+            });
+        }
+        for id in (&physics_collider_events.removed).join() {
+            inputs.push(PhysicsInput::ColliderRemoved { id });
+        }
+
+        // the commands stay queued for the ApplyPhysicsCommandsSystem
+        for command in commands.queued() {
+            inputs.push(PhysicsInput::Command {
+                id: command.entity().id(),
+                command,
+            });
+        }
+
+        // the PhysicsConfig is only recorded when it was reloaded
+        let config = config
+            .filter(|config| self.config.as_ref() != Some(&**config))
+            .map(|config| (*config).clone());
+        if config.is_some() {
+            self.config = config.clone();
+        }
+
+        recording.frames.push(RecordedFrame {
+            time_step: time_step.map(|time_step| time_step.0),
+            delta_time: delta_time.map(|delta_time| delta_time.0),
+            config,
+            inputs,
+        });
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("RecordPhysicsInputsSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+
+        // register reader id for the Position storage
+        let mut position_storage: WriteStorage<P> = SystemData::fetch(&res);
+        self.positions_reader_id = Some(position_storage.register_reader());
+
+        // register reader id for the PhysicsBody storage
+        let mut physics_body_storage: WriteStorage<PhysicsBody<N>> = SystemData::fetch(&res);
+        self.physics_bodies_reader_id = Some(physics_body_storage.register_reader());
+
+        // register reader id for the PhysicsCollider storage
+        let mut physics_collider_storage: WriteStorage<PhysicsCollider<N>> =
+            SystemData::fetch(&res);
+        self.physics_colliders_reader_id = Some(physics_collider_storage.register_reader());
+    }
+}
+
+impl<N, P> Default for RecordPhysicsInputsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    fn default() -> Self {
+        Self {
+            positions_reader_id: None,
+            physics_bodies_reader_id: None,
+            physics_colliders_reader_id: None,
+            position_events: ComponentEvents::default(),
+            physics_body_events: ComponentEvents::default(),
+            physics_collider_events: ComponentEvents::default(),
+            config: None,
+            n_marker: PhantomData,
+            p_marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        commands::PhysicsCommands,
+        forces::PdController,
+        handles::PhysicsHandles,
+        nalgebra::{Isometry3, Point3, Vector3},
+        nphysics::{algebra::Force3, object::BodyStatus},
+        parameters::PhysicsConfig,
+        recording::{PhysicsInput, PhysicsRecording, PhysicsReplayer},
+        systems::{ApplyPdControllersSystem, RecordPhysicsInputsSystem},
+        Physics,
+        PhysicsBodyBuilder,
+        PhysicsColliderBuilder,
+        Shape,
+        SimplePosition,
+    };
+
+    fn recording_dispatcher<'a, 'b>() -> Dispatcher<'a, 'b> {
+        let mut dispatcher_builder = DispatcherBuilder::new()
+            .with(
+                ApplyPdControllersSystem::<f32, SimplePosition<f32>>::default(),
+                "apply_pd_controllers_system",
+                &[],
+            )
+            .with(
+                RecordPhysicsInputsSystem::<f32, SimplePosition<f32>>::default(),
+                "record_physics_inputs_system",
+                &["apply_pd_controllers_system"],
+            );
+        crate::register_physics_systems_after::<f32, SimplePosition<f32>>(
+            &mut dispatcher_builder,
+            &["record_physics_inputs_system"],
+        );
+        dispatcher_builder.build()
+    }
+
+    fn checksum(world: &World) -> u64 {
+        world
+            .read_resource::<Physics<f32>>()
+            .state_checksum(&world.read_resource::<PhysicsHandles>())
+    }
+
+    #[test]
+    fn record_inserted_body() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                RecordPhysicsInputsSystem::<f32, SimplePosition<f32>>::default(),
+                "record_physics_inputs_system",
+                &[],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::<f32>::translation(
+                1.0, 1.0, 1.0,
+            )))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .build();
+        dispatcher.dispatch(&world);
+        dispatcher.dispatch(&world);
+
+        let recording = world.read_resource::<PhysicsRecording<f32>>();
+        assert_eq!(recording.frames.len(), 2);
+        assert_eq!(recording.frames[0].inputs.len(), 2);
+        match &recording.frames[0].inputs[0] {
+            PhysicsInput::PositionSet { isometry, .. } => {
+                assert_eq!(isometry.translation.vector.x, 1.0)
+            }
+            input => panic!("unexpected input: {:?}", input),
+        }
+        assert!(recording.frames[1].inputs.is_empty());
+    }
+
+    #[test]
+    fn skip_written_back_changes() {
+        let mut world = World::new();
+        let mut dispatcher = recording_dispatcher();
+        dispatcher.setup(&mut world);
+        world.insert(PhysicsConfig::<f32> {
+            gravity: Vector3::new(0.0, -9.81, 0.0),
+            ..PhysicsConfig::default()
+        });
+
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(0.0, 5.0, 0.0)))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .build();
+        for _ in 0..5 {
+            dispatcher.dispatch(&world);
+            world.maintain();
+        }
+
+        // the falling body is written back every frame, which is no input
+        let recording = world.read_resource::<PhysicsRecording<f32>>();
+        assert!(recording.frames[0].config.is_some());
+        assert!(recording.frames[1..].iter().all(|frame| {
+            frame.config.is_none()
+                && frame.inputs.iter().all(|input| match input {
+                    PhysicsInput::PositionSet { .. } | PhysicsInput::BodySet { .. } => false,
+                    _ => true,
+                })
+        }));
+    }
+
+    #[test]
+    fn replay_recorded_checksums() {
+        let mut world = World::new();
+        let mut dispatcher = recording_dispatcher();
+        dispatcher.setup(&mut world);
+        world.insert(PhysicsConfig::<f32> {
+            gravity: Vector3::new(0.0, -9.81, 0.0),
+            ..PhysicsConfig::default()
+        });
+
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::ground()).build())
+            .build();
+        let falling = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(0.0, 3.0, 0.0)))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .build();
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(3.0, 1.0, 0.0)))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .with(PdController::critically_damped(
+                Point3::new(3.0, 4.0, 0.0),
+                1.0,
+            ))
+            .build();
+
+        let mut checksums = Vec::new();
+        for frame in 0..60 {
+            match frame {
+                10 => world
+                    .read_resource::<PhysicsCommands<f32>>()
+                    .apply_impulse(falling, Force3::linear(Vector3::new(2.0, 0.0, 0.0))),
+                20 => world.write_resource::<PhysicsConfig<f32>>().gravity.y = -4.0,
+                30 => {
+                    world
+                        .write_storage::<SimplePosition<f32>>()
+                        .get_mut(falling)
+                        .unwrap()
+                        .0 = Isometry3::translation(0.0, 2.0, 1.0)
+                }
+                _ => {}
+            }
+            dispatcher.dispatch(&world);
+            world.maintain();
+            checksums.push(checksum(&world));
+        }
+
+        // the replayed World neither contains the PdController nor the System
+        // applying it, its forces are part of the recording
+        let recording = world.read_resource::<PhysicsRecording<f32>>().clone();
+        let mut replayed_world = World::new();
+        let mut replayed_dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        replayed_dispatcher.setup(&mut replayed_world);
+
+        let mut replayer = PhysicsReplayer::new(recording);
+        let mut replayed_checksums = Vec::new();
+        while replayer.replay_frame(&mut replayed_world, SimplePosition) {
+            replayed_dispatcher.dispatch(&replayed_world);
+            replayed_world.maintain();
+            replayed_checksums.push(checksum(&replayed_world));
+        }
+
+        assert_eq!(replayed_checksums, checksums);
+    }
+}
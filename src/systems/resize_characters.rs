@@ -0,0 +1,213 @@
+use std::marker::PhantomData;
+
+use specs::{Entities, Join, ReadExpect, System, SystemData, World, Write, WriteStorage};
+
+use crate::{
+    bodies::Position,
+    characters::{ground_tolerance, CharacterController},
+    colliders::{PhysicsCollider, Shape},
+    events::{CharacterEvent, CharacterEventKind, CharacterEvents},
+    nalgebra::RealField,
+    Physics,
+};
+
+/// The `ResizeCharactersSystem` applies the `resize` requests of
+/// `CharacterController`s to the controller, its capsule `PhysicsCollider`
+/// and its `Position`, which moves so that the feet stay in place. Growing
+/// capsules are checked for overlaps first and stay pending while blocked,
+/// e.g. a character cannot stand up below a low ceiling. It has to run before
+/// the `MoveCharactersSystem` and is not part of the default `Dispatcher`.
+pub struct ResizeCharactersSystem<N, P> {
+    n_marker: PhantomData<N>,
+    p_marker: PhantomData<P>,
+}
+
+impl<'s, N, P> System<'s> for ResizeCharactersSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, P>,
+        WriteStorage<'s, CharacterController<N>>,
+        WriteStorage<'s, PhysicsCollider<N>>,
+        Write<'s, CharacterEvents>,
+        ReadExpect<'s, Physics<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut positions,
+            mut controllers,
+            mut physics_colliders,
+            mut character_events,
+            physics,
+        ) = data;
+
+        for (entity, position, controller, physics_collider) in (
+            &entities,
+            &mut positions,
+            &mut controllers,
+            &mut physics_colliders,
+        )
+            .join()
+        {
+            let half_height = match controller.requested_half_height {
+                Some(half_height) => half_height,
+                None => continue,
+            };
+
+            let mut isometry = *position.isometry();
+            isometry.translation.vector.y += half_height - controller.half_height;
+
+            if half_height > controller.half_height {
+                // the slightly thinner test capsule ignores the ground and walls
+                // the character merely touches
+                let test_shape = Shape::Capsule {
+                    half_height,
+                    radius: controller.radius - ground_tolerance(),
+                };
+                let blocked = physics
+                    .overlaps(&test_shape, &isometry, &physics_collider.collision_groups)
+                    .into_iter()
+                    .any(|other| other != entity);
+                if blocked {
+                    if !controller.resize_blocked {
+                        controller.resize_blocked = true;
+                        character_events.single_write(CharacterEvent {
+                            character: entity,
+                            kind: CharacterEventKind::ResizeBlocked,
+                        });
+                    }
+                    continue;
+                }
+            }
+
+            controller.half_height = half_height;
+            controller.requested_half_height = None;
+            controller.resize_blocked = false;
+            position.set_isometry(&isometry);
+            physics_collider.shape = Shape::Capsule {
+                half_height,
+                radius: controller.radius,
+            };
+            character_events.single_write(CharacterEvent {
+                character: entity,
+                kind: CharacterEventKind::Resized,
+            });
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("ResizeCharactersSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+    }
+}
+
+impl<N, P> Default for ResizeCharactersSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    fn default() -> Self {
+        Self {
+            n_marker: PhantomData,
+            p_marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        characters::CharacterController,
+        colliders::Shape,
+        events::{CharacterEventKind, CharacterEvents},
+        nalgebra::{Isometry3, Vector3},
+        nphysics::object::BodyStatus,
+        systems::{
+            PhysicsStepperSystem,
+            ResizeCharactersSystem,
+            SyncBodiesToPhysicsSystem,
+            SyncCollidersToPhysicsSystem,
+        },
+        PhysicsBodyBuilder,
+        PhysicsColliderBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn stand_up_blocked_by_ceiling() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncBodiesToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_bodies_to_physics_system",
+                &[],
+            )
+            .with(
+                SyncCollidersToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_colliders_to_physics_system",
+                &["sync_bodies_to_physics_system"],
+            )
+            .with(
+                PhysicsStepperSystem::<f32>::default(),
+                "physics_stepper_system",
+                &["sync_colliders_to_physics_system"],
+            )
+            .with(
+                ResizeCharactersSystem::<f32, SimplePosition<f32>>::default(),
+                "resize_characters_system",
+                &["physics_stepper_system"],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+        let mut reader_id = world.fetch_mut::<CharacterEvents>().register_reader();
+
+        // a crouched character with its feet at y = 0 below a ceiling at y = 1.2
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(0.0, 1.7, 0.0)))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Cuboid {
+                    half_extents: Vector3::new(10.0, 0.5, 10.0),
+                })
+                .build(),
+            )
+            .build();
+        let mut controller = CharacterController::<f32>::new(0.2, 0.3);
+        controller.resize(0.5);
+        let character = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(0.0, 0.5, 0.0)))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Kinematic).build())
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Capsule {
+                    half_height: 0.2,
+                    radius: 0.3,
+                })
+                .build(),
+            )
+            .with(controller)
+            .build();
+        dispatcher.dispatch(&world);
+
+        let character_events = world.fetch::<CharacterEvents>();
+        let character_event = character_events.read(&mut reader_id).next().unwrap();
+        assert_eq!(character_event.character, character);
+        assert_eq!(character_event.kind, CharacterEventKind::ResizeBlocked);
+
+        // the request stays pending
+        let controllers = world.read_storage::<CharacterController<f32>>();
+        let controller = controllers.get(character).unwrap();
+        assert_eq!(controller.half_height, 0.2);
+        assert_eq!(controller.requested_half_height(), Some(0.5));
+    }
+}
@@ -0,0 +1,269 @@
+use std::marker::PhantomData;
+
+use log::Level;
+
+use specs::{
+    storage::ComponentEvent,
+    world::Index,
+    Entities,
+    Join,
+    Read,
+    ReadStorage,
+    ReaderId,
+    System,
+    SystemData,
+    World,
+    Write,
+    WriteExpect,
+    WriteStorage,
+};
+
+use crate::{
+    diagnostics::{DiagnosticKind, LogSource, PhysicsDiagnostics, PhysicsLogConfig, SystemLogger},
+    handles::{JointOwner, PhysicsHandles},
+    joints::{Attachment, Sockets},
+    nalgebra::{Isometry3, RealField},
+    nphysics::{algebra::Velocity3, joint::FixedConstraint, object::BodyHandle},
+    Physics,
+};
+
+use super::{iterate_component_events, joint_vacant, remove_joint, ComponentEvents};
+
+/// The `SyncAttachmentsToPhysicsSystem` snaps the bodies of `Attachment`s
+/// onto the sockets of their parents and welds them there with fixed
+/// constraints. It has to run after the `SyncBodiesToPhysicsSystem`, as both
+/// bodies have to exist before the constraint can be created.
+pub struct SyncAttachmentsToPhysicsSystem<N> {
+    attachments_reader_id: Option<ReaderId<ComponentEvent>>,
+    attachment_events: ComponentEvents,
+
+    n_marker: PhantomData<N>,
+}
+
+impl<'s, N: RealField> System<'s> for SyncAttachmentsToPhysicsSystem<N> {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Attachment>,
+        ReadStorage<'s, Sockets<N>>,
+        Option<Read<'s, PhysicsLogConfig>>,
+        Write<'s, PhysicsDiagnostics>,
+        WriteExpect<'s, Physics<N>>,
+        Write<'s, PhysicsHandles>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, attachments, sockets, log_config, mut diagnostics, mut physics, mut handles) =
+            data;
+        let mut logger = SystemLogger::new(
+            log_config.as_ref().map(|config| &**config),
+            LogSource::Joints,
+            &mut diagnostics,
+        );
+
+        // collect all ComponentEvents for the Attachment storage
+        iterate_component_events(
+            &attachments,
+            self.attachments_reader_id.as_mut().unwrap(),
+            &mut self.attachment_events,
+            &mut logger,
+        );
+
+        // remove the constraints of removed and modified Attachments; the latter
+        // are recreated at their new sockets below
+        for id in (&self.attachment_events.modified | &self.attachment_events.removed).join() {
+            logger.debug(
+                module_path!(),
+                format_args!("Removed or modified Attachment with id: {}", id),
+            );
+            remove_joint(
+                id,
+                JointOwner::Attachment,
+                &mut physics,
+                &mut handles,
+                &mut logger,
+            );
+        }
+
+        for (entity, attachment) in (&entities, &attachments).join() {
+            let id = entity.id();
+            let parent_handle = if entities.is_alive(attachment.parent) {
+                handles.body_handle(attachment.parent)
+            } else {
+                None
+            };
+            let socket = sockets
+                .get(attachment.parent)
+                .and_then(|sockets| sockets.get(&attachment.socket));
+
+            match (
+                parent_handle,
+                handles.body_handles.get(&id).cloned(),
+                socket,
+            ) {
+                (Some(parent_handle), Some(child_handle), Some(socket)) => {
+                    let report = self.attachment_events.inserted.contains(id)
+                        || self.attachment_events.modified.contains(id);
+                    if joint_vacant(id, JointOwner::Attachment, &handles, report, &mut logger) {
+                        add_attachment(
+                            id,
+                            attachment,
+                            socket,
+                            parent_handle,
+                            child_handle,
+                            &mut physics,
+                            &mut handles,
+                            &mut logger,
+                        );
+                    }
+                }
+                // the constraint must not outlive either of its bodies or the
+                // socket; it is recreated once all of them exist again
+                _ => remove_joint(
+                    id,
+                    JointOwner::Attachment,
+                    &mut physics,
+                    &mut handles,
+                    &mut logger,
+                ),
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("SyncAttachmentsToPhysicsSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+
+        // register reader id for the Attachment storage
+        let mut attachment_storage: WriteStorage<Attachment> = SystemData::fetch(&res);
+        self.attachments_reader_id = Some(attachment_storage.register_reader());
+    }
+}
+
+impl<N> Default for SyncAttachmentsToPhysicsSystem<N>
+where
+    N: RealField,
+{
+    fn default() -> Self {
+        Self {
+            attachments_reader_id: None,
+            attachment_events: ComponentEvents::default(),
+            n_marker: PhantomData,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn add_attachment<N: RealField>(
+    id: Index,
+    attachment: &Attachment,
+    socket: &Isometry3<N>,
+    parent_handle: BodyHandle,
+    child_handle: BodyHandle,
+    physics: &mut Physics<N>,
+    handles: &mut PhysicsHandles,
+    logger: &mut SystemLogger,
+) {
+    let (parent_part, parent_position, parent_velocity) =
+        match physics.world.rigid_body(parent_handle) {
+            Some(rigid_body) => (
+                rigid_body.part_handle(),
+                *rigid_body.position(),
+                *rigid_body.velocity(),
+            ),
+            None => return,
+        };
+
+    // snap the child onto the socket, moving along with the parent
+    let position = parent_position * socket;
+    let offset = position.translation.vector - parent_position.translation.vector;
+    let velocity = Velocity3::new(
+        parent_velocity.linear + parent_velocity.angular.cross(&offset),
+        parent_velocity.angular,
+    );
+    let child_part = match physics.world.rigid_body_mut(child_handle) {
+        Some(rigid_body) => {
+            rigid_body.set_position(position);
+            rigid_body.set_velocity(velocity);
+            rigid_body.part_handle()
+        }
+        None => return,
+    };
+
+    let handle = physics.world.add_constraint(FixedConstraint::new(
+        parent_part,
+        child_part,
+        *socket,
+        Isometry3::identity(),
+    ));
+    handles
+        .joint_handles
+        .insert(id, (JointOwner::Attachment, handle));
+
+    logger.log(
+        module_path!(),
+        Level::Info,
+        DiagnosticKind::JointInserted(id),
+        format_args!(
+            "Attached body with id: {} to socket `{}` of {:?}",
+            id, attachment.socket, attachment.parent
+        ),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        joints::{self, AttachError, Attachment, Sockets},
+        nalgebra::{Isometry3, Vector3},
+        nphysics::object::BodyStatus,
+        PhysicsBodyBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn weld_child_at_socket() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+
+        // a falling parent with a socket one unit above its origin
+        let parent = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(
+                2.0, 10.0, 0.0,
+            )))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .with(Sockets::new().with("top", Isometry3::translation(0.0, 1.0, 0.0)))
+            .build();
+        let child = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .build();
+
+        {
+            let sockets = world.read_storage::<Sockets<f32>>();
+            let mut attachments = world.write_storage::<Attachment>();
+            assert_eq!(
+                joints::attach(&sockets, &mut attachments, child, parent, "bottom"),
+                Err(AttachError::UnknownSocket("bottom".to_owned()))
+            );
+            joints::attach(&sockets, &mut attachments, child, parent, "top").unwrap();
+        }
+
+        for _ in 0..30 {
+            dispatcher.dispatch(&world);
+        }
+
+        let positions = world.read_storage::<SimplePosition<f32>>();
+        let parent_position = positions.get(parent).unwrap().0.translation.vector;
+        let child_position = positions.get(child).unwrap().0.translation.vector;
+        assert!(parent_position.y < 10.0);
+        assert!((child_position - parent_position - Vector3::new(0.0, 1.0, 0.0)).norm() < 0.05);
+    }
+}
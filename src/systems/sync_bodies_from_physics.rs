@@ -1,17 +1,29 @@
 use std::marker::PhantomData;
 
+#[cfg(feature = "parallel")]
+use specs::{storage::ComponentEvent, BitSet, ParJoin};
 use specs::{Join, ReadExpect, System, SystemData, World, WriteStorage};
 
 use crate::{
     bodies::{PhysicsBody, Position},
     nalgebra::RealField,
+    nphysics::object::{Body, RigidBody},
     Physics,
 };
 
 /// The `SyncBodiesFromPhysicsSystem` synchronised the updated position of
 /// the `RigidBody`s in the nphysics `World` with their Specs counterparts. This
 /// affects the `Position` `Component` related to the `Entity`.
+///
+/// Sleeping bodies are skipped, so their `Component`s are not flagged as
+/// modified while they rest.
+///
+/// With the "parallel" feature enabled, the bodies are written back with a
+/// `par_join` on the rayon thread pool of the `Dispatcher`.
 pub struct SyncBodiesFromPhysicsSystem<N, P> {
+    #[cfg(feature = "parallel")]
+    written: BitSet,
+
     n_marker: PhantomData<N>,
     p_marker: PhantomData<P>,
 }
@@ -30,15 +42,16 @@ where
     fn run(&mut self, data: Self::SystemData) {
         let (physics, mut physics_bodies, mut positions) = data;
 
-        // iterate over all PhysicBody components joined with their Positions
-        for (physics_body, position) in (&mut physics_bodies, &mut positions).join() {
-            // if a RigidBody exists in the nphysics World we fetch it and update the
-            // Position component accordingly
-            if let Some(rigid_body) = physics.world.rigid_body(physics_body.handle.unwrap()) {
-                position.set_isometry(rigid_body.position());
-                physics_body.update_from_physics_world(rigid_body);
-            }
-        }
+        #[cfg(not(feature = "parallel"))]
+        write_back(&physics, &mut physics_bodies, &mut positions);
+
+        #[cfg(feature = "parallel")]
+        par_write_back(
+            &physics,
+            &mut self.written,
+            &mut physics_bodies,
+            &mut positions,
+        );
     }
 
     fn setup(&mut self, res: &mut World) {
@@ -57,8 +70,331 @@ where
 {
     fn default() -> Self {
         Self {
+            #[cfg(feature = "parallel")]
+            written: BitSet::new(),
             n_marker: PhantomData,
             p_marker: PhantomData,
         }
     }
 }
+
+/// Writes the pose of every awake body back to its `Component`s; only awake
+/// bodies are written, so that sleeping bodies do not flag their `Component`s
+/// as modified every frame.
+#[cfg(any(not(feature = "parallel"), test))]
+fn write_back<N, P>(
+    physics: &Physics<N>,
+    physics_bodies: &mut WriteStorage<PhysicsBody<N>>,
+    positions: &mut WriteStorage<P>,
+) where
+    N: RealField,
+    P: Position<N>,
+{
+    for (mut physics_body, mut position) in (
+        &mut physics_bodies.restrict_mut(),
+        &mut positions.restrict_mut(),
+    )
+        .join()
+    {
+        if let Some(rigid_body) = awake_rigid_body(physics, physics_body.get_unchecked()) {
+            sync_body_from_physics(
+                rigid_body,
+                physics_body.get_mut_unchecked(),
+                position.get_mut_unchecked(),
+            );
+        }
+    }
+}
+
+/// Writes the pose of every awake body back to its `Component`s with a
+/// `par_join`. FlaggedStorages write a `ComponentEvent` on every mutable
+/// access, which is not thread safe; the events are disabled during the
+/// parallel write-back and written for all awake bodies afterwards, matching
+/// the events of `write_back`.
+#[cfg(feature = "parallel")]
+fn par_write_back<N, P>(
+    physics: &Physics<N>,
+    written: &mut BitSet,
+    physics_bodies: &mut WriteStorage<PhysicsBody<N>>,
+    positions: &mut WriteStorage<P>,
+) where
+    N: RealField,
+    P: Position<N>,
+{
+    written.clear();
+    for (physics_body, id) in (&*physics_bodies, positions.mask()).join() {
+        if awake_rigid_body(physics, physics_body).is_some() {
+            written.add(id);
+        }
+    }
+
+    physics_bodies.set_event_emission(false);
+    positions.set_event_emission(false);
+    (
+        &mut physics_bodies.par_restrict_mut(),
+        &mut positions.par_restrict_mut(),
+        &*written,
+    )
+        .par_join()
+        .for_each(|(mut physics_body, mut position, _)| {
+            if let Some(rigid_body) = awake_rigid_body(physics, physics_body.get_unchecked()) {
+                sync_body_from_physics(
+                    rigid_body,
+                    physics_body.get_mut_unchecked(),
+                    position.get_mut_unchecked(),
+                );
+            }
+        });
+    physics_bodies.set_event_emission(true);
+    positions.set_event_emission(true);
+
+    for id in (&*written).join() {
+        physics_bodies
+            .channel_mut()
+            .single_write(ComponentEvent::Modified(id));
+        positions
+            .channel_mut()
+            .single_write(ComponentEvent::Modified(id));
+    }
+}
+
+/// Returns the `RigidBody` of the given `PhysicsBody` if its pose has to be
+/// written back. Sleeping bodies are skipped, apart from the frame they fell
+/// asleep in, which is detected by nphysics resetting their velocity.
+fn awake_rigid_body<'a, N: RealField>(
+    physics: &'a Physics<N>,
+    physics_body: &PhysicsBody<N>,
+) -> Option<&'a RigidBody<N>> {
+    // bodies inserted after the SyncBodiesToPhysicsSystem ran have no handle yet
+    let rigid_body = physics.world.rigid_body(physics_body.handle?)?;
+    let velocity = rigid_body.velocity();
+
+    if rigid_body.is_active()
+        || velocity.linear != physics_body.velocity.linear
+        || velocity.angular != physics_body.velocity.angular
+    {
+        Some(rigid_body)
+    } else {
+        None
+    }
+}
+
+fn sync_body_from_physics<N, P>(
+    rigid_body: &RigidBody<N>,
+    physics_body: &mut PhysicsBody<N>,
+    position: &mut P,
+) where
+    N: RealField,
+    P: Position<N>,
+{
+    position.set_isometry(rigid_body.position());
+    physics_body.update_from_physics_world(rigid_body);
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::{prelude::*, storage::ComponentEvent};
+
+    use crate::{
+        bodies::Position,
+        colliders::Shape,
+        handles::PhysicsHandles,
+        nalgebra::{Isometry3, UnitQuaternion, Vector3},
+        nphysics::{algebra::Velocity3, object::BodyStatus},
+        parameters::Gravity,
+        Physics,
+        PhysicsBody,
+        PhysicsBodyBuilder,
+        PhysicsColliderBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn sync_rotation_both_ways() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+
+        // a body spinning around the y axis
+        let spinner = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(
+                PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                    .gravity_enabled(false)
+                    .velocity(Velocity3::angular(0.0, 1.0, 0.0))
+                    .build(),
+            )
+            .build();
+        for _ in 0..10 {
+            dispatcher.dispatch(&world);
+        }
+
+        let body_rotation = |world: &World| {
+            world
+                .read_resource::<Physics<f32>>()
+                .body_position(&world.read_resource::<PhysicsHandles>(), spinner)
+                .unwrap()
+                .rotation
+        };
+        {
+            let positions = world.read_storage::<SimplePosition<f32>>();
+            let rotation = positions.get(spinner).unwrap().rotation();
+            assert!(rotation.angle() > 0.1);
+            assert!(rotation.angle_to(&body_rotation(&world)) < 1.0e-4);
+        }
+
+        // an orientation written to the Position reaches the body
+        let target = UnitQuaternion::from_axis_angle(&Vector3::x_axis(), 1.0);
+        world
+            .write_storage::<SimplePosition<f32>>()
+            .get_mut(spinner)
+            .unwrap()
+            .set_rotation(&target);
+        dispatcher.dispatch(&world);
+        assert!(body_rotation(&world).angle_to(&target) < 0.1);
+    }
+
+    #[test]
+    fn write_back_once_when_falling_asleep() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+        world.insert(Gravity(Vector3::<f32>::new(0.0, -9.81, 0.0)));
+        let mut reader = world.write_storage::<PhysicsBody<f32>>().register_reader();
+
+        // a ball dropped onto the ground, where it comes to rest
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::ground()).build())
+            .build();
+        let ball = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(0.0, 1.0, 0.0)))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.5 }).build())
+            .build();
+
+        // returns whether the ball is awake after the frame and was written back
+        let mut next_frame = |world: &mut World| {
+            dispatcher.dispatch(world);
+            world.maintain();
+
+            let active = world
+                .read_resource::<Physics<f32>>()
+                .world
+                .rigid_body(
+                    world
+                        .read_resource::<PhysicsHandles>()
+                        .body_handle(ball)
+                        .unwrap(),
+                )
+                .unwrap()
+                .is_active();
+            let written = world
+                .read_storage::<PhysicsBody<f32>>()
+                .channel()
+                .read(&mut reader)
+                .any(|event| *event == ComponentEvent::Modified(ball.id()));
+            (active, written)
+        };
+
+        let mut frames = 0;
+        loop {
+            let (active, written) = next_frame(&mut world);
+            frames += 1;
+            assert!(written);
+            if !active {
+                break;
+            }
+            assert!(frames < 600, "the ball never fell asleep");
+        }
+
+        // the frame the ball fell asleep in wrote back its resting velocity
+        {
+            let physics_bodies = world.read_storage::<PhysicsBody<f32>>();
+            let velocity = physics_bodies.get(ball).unwrap().velocity;
+            assert_eq!(velocity.linear, Vector3::zeros());
+            assert_eq!(velocity.angular, Vector3::zeros());
+        }
+
+        for _ in 0..30 {
+            assert_eq!(next_frame(&mut world), (false, false));
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn match_serial_write_back() {
+        use specs::BitSet;
+
+        use crate::{
+            scenarios::Scenario,
+            systems::sync_bodies_from_physics::{par_write_back, write_back},
+        };
+
+        // two identical worlds with part of the rain resting on the ground
+        let worlds = (0..2)
+            .map(|_| {
+                let mut world = World::new();
+                let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+                dispatcher.setup(&mut world);
+                world.insert(Gravity(Vector3::<f32>::new(0.0, -9.81, 0.0)));
+                Scenario::<f32>::rain(50, 3).spawn(&mut world, SimplePosition);
+                for _ in 0..300 {
+                    dispatcher.dispatch(&world);
+                    world.maintain();
+                }
+
+                // advance the physics World past the written back state
+                world.write_resource::<Physics<f32>>().world.step();
+                world
+            })
+            .collect::<Vec<_>>();
+
+        let write_backs = worlds
+            .iter()
+            .enumerate()
+            .map(|(i, world)| {
+                let physics = world.read_resource::<Physics<f32>>();
+                let mut physics_bodies = world.write_storage::<PhysicsBody<f32>>();
+                let mut positions = world.write_storage::<SimplePosition<f32>>();
+                let mut body_reader = physics_bodies.register_reader();
+                let mut position_reader = positions.register_reader();
+
+                if i == 0 {
+                    write_back(&physics, &mut physics_bodies, &mut positions);
+                } else {
+                    par_write_back(
+                        &physics,
+                        &mut BitSet::new(),
+                        &mut physics_bodies,
+                        &mut positions,
+                    );
+                }
+
+                let poses = (&world.entities(), &positions)
+                    .join()
+                    .map(|(entity, position)| (entity.id(), *position.isometry()))
+                    .collect::<Vec<_>>();
+                let body_events = physics_bodies
+                    .channel()
+                    .read(&mut body_reader)
+                    .cloned()
+                    .collect::<Vec<ComponentEvent>>();
+                let position_events = positions
+                    .channel()
+                    .read(&mut position_reader)
+                    .cloned()
+                    .collect::<Vec<ComponentEvent>>();
+                (body_events, position_events, poses)
+            })
+            .collect::<Vec<_>>();
+
+        let (body_events, position_events, _) = &write_backs[0];
+        assert!(!body_events.is_empty());
+        assert_eq!(body_events, position_events);
+        assert_eq!(write_backs[0], write_backs[1]);
+    }
+}
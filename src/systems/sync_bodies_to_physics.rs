@@ -1,99 +1,195 @@
 use std::marker::PhantomData;
 
+use log::Level;
+
 use specs::{
     storage::ComponentEvent,
     world::Index,
     BitSet,
+    Entities,
+    Entity,
     Join,
+    Read,
     ReadStorage,
     ReaderId,
     System,
     SystemData,
     World,
+    Write,
     WriteExpect,
     WriteStorage,
 };
 
 use crate::{
     bodies::{PhysicsBody, Position},
-    nalgebra::RealField,
+    diagnostics::{DiagnosticKind, LogSource, PhysicsDiagnostics, PhysicsLogConfig, SystemLogger},
+    handles::PhysicsHandles,
+    nalgebra::{Isometry3, RealField},
+    parameters::PoseEpsilon,
     Physics,
 };
 
-use super::iterate_component_events;
+use super::{iterate_component_events, rescan_components, ComponentEvents, SyncPhase};
 
 /// The `SyncBodiesToPhysicsSystem` handles the synchronisation of `PhysicsBody`
-/// `Component`s into the physics `World`.
+/// `Component`s into the physics `World`. By default it runs all
+/// `SyncPhase`s; see `SyncBodiesToPhysicsSystem::with_phase` for splitting
+/// them into separate `System`s.
 pub struct SyncBodiesToPhysicsSystem<N, P> {
     positions_reader_id: Option<ReaderId<ComponentEvent>>,
     physics_bodies_reader_id: Option<ReaderId<ComponentEvent>>,
+    position_events: ComponentEvents,
+    physics_body_events: ComponentEvents,
+    rescan: bool,
+    phase: Option<SyncPhase>,
 
     n_marker: PhantomData<N>,
     p_marker: PhantomData<P>,
 }
 
+impl<N, P> SyncBodiesToPhysicsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    /// Creates a `SyncBodiesToPhysicsSystem` which only runs the given
+    /// `SyncPhase`.
+    pub fn with_phase(phase: SyncPhase) -> Self {
+        Self {
+            phase: Some(phase),
+            ..Self::default()
+        }
+    }
+
+    fn runs(&self, phase: SyncPhase) -> bool {
+        self.phase.map_or(true, |own_phase| own_phase == phase)
+    }
+}
+
 impl<'s, N, P> System<'s> for SyncBodiesToPhysicsSystem<N, P>
 where
     N: RealField,
     P: Position<N>,
 {
     type SystemData = (
+        Entities<'s>,
         ReadStorage<'s, P>,
+        Option<Read<'s, PhysicsLogConfig>>,
+        Option<Read<'s, PoseEpsilon<N>>>,
+        Write<'s, PhysicsDiagnostics>,
         WriteExpect<'s, Physics<N>>,
+        Write<'s, PhysicsHandles>,
         WriteStorage<'s, PhysicsBody<N>>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (positions, mut physics, mut physics_bodies) = data;
+        let (
+            entities,
+            positions,
+            log_config,
+            pose_epsilon,
+            mut diagnostics,
+            mut physics,
+            mut handles,
+            mut physics_bodies,
+        ) = data;
+        let pose_epsilon = pose_epsilon.map_or(N::zero(), |pose_epsilon| pose_epsilon.0);
+        let mut logger = SystemLogger::new(
+            log_config.as_ref().map(|config| &**config),
+            LogSource::Bodies,
+            &mut diagnostics,
+        );
 
         // collect all ComponentEvents for the Position storage
-        let (inserted_positions, modified_positions, removed_positions) =
-            iterate_component_events(&positions, self.positions_reader_id.as_mut().unwrap());
+        iterate_component_events(
+            &positions,
+            self.positions_reader_id.as_mut().unwrap(),
+            &mut self.position_events,
+            &mut logger,
+        );
 
         // collect all ComponentEvents for the PhysicsBody storage
-        let (inserted_physics_bodies, modified_physics_bodies, removed_physics_bodies) =
-            iterate_component_events(
+        iterate_component_events(
+            &physics_bodies,
+            self.physics_bodies_reader_id.as_mut().unwrap(),
+            &mut self.physics_body_events,
+            &mut logger,
+        );
+
+        // PhysicsBodies inserted before the reader id was registered
+        if self.rescan && self.runs(SyncPhase::Insert) {
+            self.rescan = false;
+            rescan_components(
                 &physics_bodies,
-                self.physics_bodies_reader_id.as_mut().unwrap(),
+                &mut self.physics_body_events.inserted,
+                |id| handles.body_handles.contains_key(&id),
+                &mut logger,
             );
+        }
 
-        // iterate over PhysicsBody and Position components with an id/Index that
-        // exists in either of the collected ComponentEvent BitSets
-        for (position, mut physics_body, id) in (
-            &positions,
-            &mut physics_bodies,
-            &inserted_positions
-                | &modified_positions
-                | &removed_positions
-                | &inserted_physics_bodies
-                | &modified_physics_bodies
-                | &removed_physics_bodies,
-        )
-            .join()
-        {
-            // handle inserted events
-            if inserted_positions.contains(id) || inserted_physics_bodies.contains(id) {
-                debug!("Inserted PhysicsBody with id: {}", id);
-                add_rigid_body::<N, P>(id, &position, &mut physics, &mut physics_body);
+        let (position_events, physics_body_events) =
+            (&self.position_events, &self.physics_body_events);
+
+        // handle inserted events
+        if self.runs(SyncPhase::Insert) {
+            for (entity, position, physics_body, _) in (
+                &entities,
+                &positions,
+                &mut physics_bodies,
+                &position_events.inserted | &physics_body_events.inserted,
+            )
+                .join()
+            {
+                logger.debug(
+                    module_path!(),
+                    format_args!("Inserted PhysicsBody with id: {}", entity.id()),
+                );
+                add_rigid_body::<N, P>(
+                    entity,
+                    position,
+                    &mut physics,
+                    &mut handles,
+                    physics_body,
+                    &mut logger,
+                );
             }
+        }
 
-            // handle modified events
-            if modified_positions.contains(id) || modified_physics_bodies.contains(id) {
-                debug!("Modified PhysicsBody with id: {}", id);
+        // handle modified events
+        if self.runs(SyncPhase::Update) {
+            for (position, physics_body, id) in (
+                &positions,
+                &mut physics_bodies,
+                &position_events.modified | &physics_body_events.modified,
+            )
+                .join()
+            {
+                logger.debug(
+                    module_path!(),
+                    format_args!("Modified PhysicsBody with id: {}", id),
+                );
                 update_rigid_body::<N, P>(
                     id,
-                    &position,
+                    position,
                     &mut physics,
-                    &mut physics_body,
-                    &modified_positions,
-                    &modified_physics_bodies,
+                    physics_body,
+                    &position_events.modified,
+                    &physics_body_events.modified,
+                    pose_epsilon,
+                    &mut logger,
                 );
             }
+        }
 
-            // handle removed events
-            if removed_positions.contains(id) || removed_physics_bodies.contains(id) {
-                debug!("Removed PhysicsBody with id: {}", id);
-                remove_rigid_body::<N, P>(id, &mut physics);
+        // handle removed events; the removed Components can not be joined anymore,
+        // and a PhysicsBody without a Position can not be synchronised either
+        if self.runs(SyncPhase::Remove) {
+            for id in (&position_events.removed | &physics_body_events.removed).join() {
+                logger.debug(
+                    module_path!(),
+                    format_args!("Removed PhysicsBody with id: {}", id),
+                );
+                remove_rigid_body::<N, P>(id, &mut physics, &mut handles, &mut logger);
             }
         }
     }
@@ -112,6 +208,7 @@ where
         // register reader id for the PhysicsBody storage
         let mut physics_body_storage: WriteStorage<PhysicsBody<N>> = SystemData::fetch(&res);
         self.physics_bodies_reader_id = Some(physics_body_storage.register_reader());
+        self.rescan = true;
     }
 }
 
@@ -124,6 +221,10 @@ where
         Self {
             positions_reader_id: None,
             physics_bodies_reader_id: None,
+            position_events: ComponentEvents::default(),
+            physics_body_events: ComponentEvents::default(),
+            rescan: false,
+            phase: None,
             n_marker: PhantomData,
             p_marker: PhantomData,
         }
@@ -131,19 +232,41 @@ where
 }
 
 fn add_rigid_body<N, P>(
-    id: Index,
+    entity: Entity,
     position: &P,
     physics: &mut Physics<N>,
+    handles: &mut PhysicsHandles,
     physics_body: &mut PhysicsBody<N>,
+    logger: &mut SystemLogger,
 ) where
     N: RealField,
     P: Position<N>,
 {
+    let id = entity.id();
+
+    #[cfg(feature = "validation")]
+    {
+        if let Err(error) = crate::validation::validate_body(position.isometry(), physics_body) {
+            logger.log(
+                module_path!(),
+                Level::Error,
+                DiagnosticKind::InvalidInput(id, error),
+                format_args!("Skipped inserting rigid body with id {}: {}", id, error),
+            );
+            return;
+        }
+    }
+
     // remove already existing bodies for this inserted component;
     // this technically should never happen but we need to keep the list of body
     // handles clean
-    if let Some(body_handle) = physics.body_handles.remove(&id) {
-        warn!("Removing orphaned body handle: {:?}", body_handle);
+    if let Some(body_handle) = handles.body_handles.remove(&id) {
+        logger.log(
+            module_path!(),
+            Level::Warn,
+            DiagnosticKind::OrphanedBodyRemoved(id),
+            format_args!("Removing orphaned body handle: {:?}", body_handle),
+        );
         physics.world.remove_bodies(&[body_handle]);
     }
 
@@ -152,16 +275,21 @@ fn add_rigid_body<N, P>(
     let handle = physics_body
         .to_rigid_body_desc()
         .position(*position.isometry())
-        .user_data(id)
+        .user_data(entity)
         .build(&mut physics.world)
         .handle();
 
     physics_body.handle = Some(handle);
-    physics.body_handles.insert(id, handle);
+    handles.body_handles.insert(id, handle);
 
-    info!(
-        "Inserted rigid body to world with values: {:?}",
-        physics_body
+    logger.log(
+        module_path!(),
+        Level::Info,
+        DiagnosticKind::BodyInserted(id),
+        format_args!(
+            "Inserted rigid body to world with values: {:?}",
+            physics_body
+        ),
     );
 }
 
@@ -172,46 +300,107 @@ fn update_rigid_body<N, P>(
     physics_body: &mut PhysicsBody<N>,
     modified_positions: &BitSet,
     modified_physics_bodies: &BitSet,
+    pose_epsilon: N,
+    logger: &mut SystemLogger,
 ) where
     N: RealField,
     P: Position<N>,
 {
-    if let Some(rigid_body) = physics.world.rigid_body_mut(physics_body.handle.unwrap()) {
+    #[cfg(feature = "validation")]
+    {
+        if let Err(error) = crate::validation::validate_body(position.isometry(), physics_body) {
+            logger.log(
+                module_path!(),
+                Level::Error,
+                DiagnosticKind::InvalidInput(id, error),
+                format_args!("Skipped updating rigid body with id {}: {}", id, error),
+            );
+            return;
+        }
+    }
+
+    // bodies rejected on insertion have no handle
+    let handle = match physics_body.handle {
+        Some(handle) => handle,
+        None => return,
+    };
+
+    if let Some(rigid_body) = physics.world.rigid_body_mut(handle) {
         // the PhysicsBody was modified, update everything but the position
         if modified_physics_bodies.contains(id) {
             physics_body.apply_to_physics_world(rigid_body);
         }
 
-        // the Position was modified, update the position directly
+        // the Position was modified, update the position directly unless it is
+        // within the PoseEpsilon of the simulated pose
         if modified_positions.contains(id) {
-            rigid_body.set_position(*position.isometry());
+            if exceeds_epsilon(rigid_body.position(), position.isometry(), pose_epsilon) {
+                rigid_body.set_position(*position.isometry());
+            } else {
+                logger.debug(
+                    module_path!(),
+                    format_args!("Skipped position update within epsilon with id: {}", id),
+                );
+            }
         }
 
-        trace!(
-            "Updated rigid body in world with values: {:?}",
-            physics_body
+        logger.log(
+            module_path!(),
+            Level::Trace,
+            DiagnosticKind::BodyUpdated(id),
+            format_args!(
+                "Updated rigid body in world with values: {:?}",
+                physics_body
+            ),
         );
     }
+
+    // the external forces are applied by the PhysicsStepperSystem before
+    // every substep of this frame
+    if modified_physics_bodies.contains(id) {
+        physics.apply_frame_force(handle, &physics_body.drain_external_force());
+    }
 }
 
-fn remove_rigid_body<N, P>(id: Index, physics: &mut Physics<N>)
-where
+fn exceeds_epsilon<N: RealField>(
+    current: &Isometry3<N>,
+    target: &Isometry3<N>,
+    epsilon: N,
+) -> bool {
+    (target.translation.vector - current.translation.vector).norm() >= epsilon
+        || current.rotation.angle_to(&target.rotation) >= epsilon
+}
+
+fn remove_rigid_body<N, P>(
+    id: Index,
+    physics: &mut Physics<N>,
+    handles: &mut PhysicsHandles,
+    logger: &mut SystemLogger,
+) where
     N: RealField,
     P: Position<N>,
 {
-    if let Some(handle) = physics.body_handles.remove(&id) {
+    if let Some(handle) = handles.body_handles.remove(&id) {
         // remove body if it still exists in the PhysicsWorld
         physics.world.remove_bodies(&[handle]);
-        info!("Removed rigid body from world with id: {}", id);
+        logger.log(
+            module_path!(),
+            Level::Info,
+            DiagnosticKind::BodyRemoved(id),
+            format_args!("Removed rigid body from world with id: {}", id),
+        );
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        nalgebra::Isometry3,
+        bodies::{PhysicsBody, Position},
+        handles::PhysicsHandles,
+        nalgebra::{Isometry3, UnitQuaternion, Vector3},
         nphysics::object::BodyStatus,
-        systems::SyncBodiesToPhysicsSystem,
+        parameters::PoseEpsilon,
+        systems::{SyncBodiesToPhysicsSystem, SyncPhase},
         Physics,
         PhysicsBodyBuilder,
         SimplePosition,
@@ -243,7 +432,167 @@ mod tests {
 
         // fetch the Physics instance and check for new bodies
         let physics = world.read_resource::<Physics<f32>>();
-        assert_eq!(physics.body_handles.len(), 1);
+        let handles = world.read_resource::<PhysicsHandles>();
+        assert_eq!(handles.body_handles.len(), 1);
         assert_eq!(physics.world.bodies().count(), 1);
     }
+
+    #[test]
+    fn remove_rigid_body_in_phases() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncBodiesToPhysicsSystem::<f32, SimplePosition<f32>>::with_phase(
+                    SyncPhase::Insert,
+                ),
+                "insert_bodies_to_physics_system",
+                &[],
+            )
+            .with(
+                SyncBodiesToPhysicsSystem::<f32, SimplePosition<f32>>::with_phase(
+                    SyncPhase::Remove,
+                ),
+                "remove_bodies_from_physics_system",
+                &["insert_bodies_to_physics_system"],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+
+        let entity = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::<f32>::identity()))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .build();
+        dispatcher.dispatch(&world);
+        assert_eq!(
+            world.read_resource::<PhysicsHandles>().body_handles.len(),
+            1
+        );
+
+        world.write_storage::<PhysicsBody<f32>>().remove(entity);
+        dispatcher.dispatch(&world);
+
+        let physics = world.read_resource::<Physics<f32>>();
+        let handles = world.read_resource::<PhysicsHandles>();
+        assert!(handles.body_handles.is_empty());
+        assert_eq!(physics.world.bodies().count(), 0);
+    }
+
+    #[test]
+    fn add_rigid_body_after_rebuild() {
+        let mut world = World::new();
+        let build_dispatcher = || {
+            DispatcherBuilder::new()
+                .with(
+                    SyncBodiesToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                    "sync_bodies_to_physics_system",
+                    &[],
+                )
+                .build()
+        };
+        build_dispatcher().setup(&mut world);
+
+        // the PhysicsBody is inserted before the new dispatcher registers its
+        // reader ids
+        world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::<f32>::identity()))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .build();
+        let mut dispatcher = build_dispatcher();
+        dispatcher.setup(&mut world);
+        dispatcher.dispatch(&world);
+
+        let handles = world.read_resource::<PhysicsHandles>();
+        assert_eq!(handles.body_handles.len(), 1);
+    }
+
+    #[test]
+    fn replace_rigid_body() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncBodiesToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_bodies_to_physics_system",
+                &[],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+
+        let entity = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::<f32>::identity()))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .build();
+        dispatcher.dispatch(&world);
+
+        // removing and inserting the PhysicsBody in the same frame coalesces into a
+        // single insertion
+        {
+            let mut physics_bodies = world.write_storage::<PhysicsBody<f32>>();
+            physics_bodies.remove(entity);
+            physics_bodies
+                .insert(
+                    entity,
+                    PhysicsBodyBuilder::<f32>::from(BodyStatus::Static).build(),
+                )
+                .unwrap();
+        }
+        dispatcher.dispatch(&world);
+
+        let physics = world.read_resource::<Physics<f32>>();
+        let handles = world.read_resource::<PhysicsHandles>();
+        assert_eq!(handles.body_handles.len(), 1);
+        assert_eq!(physics.world.bodies().count(), 1);
+    }
+
+    #[test]
+    fn skip_position_changes_within_epsilon() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncBodiesToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_bodies_to_physics_system",
+                &[],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+        world.insert(PoseEpsilon(0.01f32));
+
+        let entity = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::<f32>::identity()))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .build();
+        dispatcher.dispatch(&world);
+
+        // moves the Position and returns the resulting pose of the body
+        let mut move_to = |isometry: Isometry3<f32>| {
+            world
+                .write_storage::<SimplePosition<f32>>()
+                .get_mut(entity)
+                .unwrap()
+                .set_isometry(&isometry);
+            dispatcher.dispatch(&world);
+            world
+                .read_resource::<Physics<f32>>()
+                .body_position(&world.read_resource::<PhysicsHandles>(), entity)
+                .unwrap()
+        };
+
+        let nudged = Isometry3::translation(0.005, 0.0, 0.0);
+        assert_eq!(move_to(nudged), Isometry3::identity());
+        let moved = Isometry3::translation(0.02, 0.0, 0.0);
+        assert_eq!(move_to(moved), moved);
+
+        // the rotation is compared against the same epsilon, in radians
+        let turned = |angle| {
+            Isometry3::from_parts(
+                moved.translation,
+                UnitQuaternion::from_axis_angle(&Vector3::y_axis(), angle),
+            )
+        };
+        assert_eq!(move_to(turned(0.005)), moved);
+        assert_eq!(move_to(turned(0.02)), turned(0.02));
+    }
 }
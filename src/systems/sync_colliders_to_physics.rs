@@ -1,100 +1,322 @@
 use std::marker::PhantomData;
 
+use log::Level;
+
 use specs::{
     storage::ComponentEvent,
     world::Index,
+    BitSet,
+    Entities,
+    Entity,
     Join,
+    Read,
     ReadStorage,
     ReaderId,
     System,
     SystemData,
     World,
+    Write,
     WriteExpect,
     WriteStorage,
 };
 
 use crate::{
     bodies::Position,
-    colliders::PhysicsCollider,
+    colliders::{PhysicsCollider, ShapeCache},
+    diagnostics::{DiagnosticKind, LogSource, PhysicsDiagnostics, PhysicsLogConfig, SystemLogger},
+    handles::PhysicsHandles,
+    loading::{ColliderLoader, ColliderPending},
     nalgebra::RealField,
-    nphysics::object::{BodyPartHandle, ColliderDesc},
+    ncollide::shape::ShapeHandle,
+    nphysics::object::{BodyPartHandle, ColliderDesc, ColliderHandle},
     Physics,
     PhysicsParent,
 };
 
-use super::iterate_component_events;
+use super::{iterate_component_events, rescan_components, ComponentEvents, SyncPhase};
 
 /// The `SyncCollidersToPhysicsSystem` handles the synchronisation of
-/// `PhysicsCollider` `Component`s into the physics `World`.
+/// `PhysicsCollider` `Component`s into the physics `World`. Modifications of
+/// `PhysicsCollider`s that have not been inserted yet are deferred to the next
+/// frame. If a `ColliderLoader` `Resource` exists, expensive shapes are built
+/// in the background and inserted once they are ready. By default it runs all
+/// `SyncPhase`s; see
+/// `SyncCollidersToPhysicsSystem::with_phase` for splitting them into separate
+/// `System`s.
 pub struct SyncCollidersToPhysicsSystem<N, P> {
     positions_reader_id: Option<ReaderId<ComponentEvent>>,
     physics_colliders_reader_id: Option<ReaderId<ComponentEvent>>,
+    position_events: ComponentEvents,
+    physics_collider_events: ComponentEvents,
+    deferred_updates: BitSet,
+    retried_updates: BitSet,
+    rescan: bool,
+    phase: Option<SyncPhase>,
 
     n_marker: PhantomData<N>,
     p_marker: PhantomData<P>,
 }
 
+impl<N, P> SyncCollidersToPhysicsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    /// Creates a `SyncCollidersToPhysicsSystem` which only runs the given
+    /// `SyncPhase`.
+    pub fn with_phase(phase: SyncPhase) -> Self {
+        Self {
+            phase: Some(phase),
+            ..Self::default()
+        }
+    }
+
+    fn runs(&self, phase: SyncPhase) -> bool {
+        self.phase.map_or(true, |own_phase| own_phase == phase)
+    }
+}
+
 impl<'s, N, P> System<'s> for SyncCollidersToPhysicsSystem<N, P>
 where
     N: RealField,
     P: Position<N>,
 {
     type SystemData = (
+        Entities<'s>,
         ReadStorage<'s, P>,
         ReadStorage<'s, PhysicsParent>,
+        Option<Read<'s, PhysicsLogConfig>>,
+        Write<'s, PhysicsDiagnostics>,
+        Option<Write<'s, ShapeCache<N>>>,
+        Option<Write<'s, ColliderLoader<N>>>,
         WriteExpect<'s, Physics<N>>,
+        Write<'s, PhysicsHandles>,
         WriteStorage<'s, PhysicsCollider<N>>,
+        WriteStorage<'s, ColliderPending>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (positions, parent_entities, mut physics, mut physics_colliders) = data;
+        let (
+            entities,
+            positions,
+            parent_entities,
+            log_config,
+            mut diagnostics,
+            mut shape_cache,
+            mut collider_loader,
+            mut physics,
+            mut handles,
+            mut physics_colliders,
+            mut pending_colliders,
+        ) = data;
+        let mut logger = SystemLogger::new(
+            log_config.as_ref().map(|config| &**config),
+            LogSource::Colliders,
+            &mut diagnostics,
+        );
 
         // collect all ComponentEvents for the Position storage
-        let (inserted_positions, ..) =
-            iterate_component_events(&positions, self.positions_reader_id.as_mut().unwrap());
+        iterate_component_events(
+            &positions,
+            self.positions_reader_id.as_mut().unwrap(),
+            &mut self.position_events,
+            &mut logger,
+        );
 
         // collect all ComponentEvents for the PhysicsCollider storage
-        let (inserted_physics_colliders, modified_physics_colliders, removed_physics_colliders) =
-            iterate_component_events(
+        iterate_component_events(
+            &physics_colliders,
+            self.physics_colliders_reader_id.as_mut().unwrap(),
+            &mut self.physics_collider_events,
+            &mut logger,
+        );
+
+        // PhysicsColliders inserted before the reader id was registered
+        if self.rescan && self.runs(SyncPhase::Insert) {
+            self.rescan = false;
+            rescan_components(
                 &physics_colliders,
-                self.physics_colliders_reader_id.as_mut().unwrap(),
+                &mut self.physics_collider_events.inserted,
+                |id| handles.collider_handles.contains(id),
+                &mut logger,
             );
+        }
 
-        // iterate over PhysicsCollider and Position components with an id/Index that
-        // exists in either of the collected ComponentEvent BitSets
-        for (position, parent_entity, mut physics_collider, id) in (
-            &positions,
-            parent_entities.maybe(),
-            &mut physics_colliders.restrict_mut(),
-            &inserted_positions
-                | &inserted_physics_colliders
-                | &modified_physics_colliders
-                | &removed_physics_colliders,
-        )
-            .join()
+        // insert the colliders whose shapes have been built in the background;
+        // the PhysicsColliders are not flagged, as that would rebuild their
+        // shapes once more
+        if let Some(collider_loader) = collider_loader
+            .as_mut()
+            .filter(|_| self.runs(SyncPhase::Insert))
         {
-            // handle inserted events
-            if inserted_positions.contains(id) || inserted_physics_colliders.contains(id) {
-                debug!("Inserted PhysicsCollider with id: {}", id);
+            physics_colliders.set_event_emission(false);
+            for (id, shape_handle) in collider_loader.completed() {
+                let entity = entities.entity(id);
+                pending_colliders.remove(entity);
+                let shape_handle = match shape_handle {
+                    Ok(shape_handle) => shape_handle,
+                    Err(error) => {
+                        logger.log(
+                            module_path!(),
+                            Level::Error,
+                            DiagnosticKind::InvalidInput(id, error),
+                            format_args!("Skipped building collider with id {}: {}", id, error),
+                        );
+                        continue;
+                    }
+                };
+                if let (Some(position), Some(physics_collider)) =
+                    (positions.get(entity), physics_colliders.get_mut(entity))
+                {
+                    logger.debug(
+                        module_path!(),
+                        format_args!("Built PhysicsCollider shape with id: {}", id),
+                    );
+                    add_collider::<N, P>(
+                        entity,
+                        parent_entities.get(entity),
+                        position,
+                        &mut physics,
+                        &mut handles,
+                        physics_collider,
+                        shape_handle,
+                        &mut logger,
+                    );
+                }
+            }
+            physics_colliders.set_event_emission(true);
+        }
+
+        // handle inserted events
+        if self.runs(SyncPhase::Insert) {
+            for (entity, position, parent_entity, mut physics_collider, _) in (
+                &entities,
+                &positions,
+                parent_entities.maybe(),
+                &mut physics_colliders.restrict_mut(),
+                &self.position_events.inserted | &self.physics_collider_events.inserted,
+            )
+                .join()
+            {
+                logger.debug(
+                    module_path!(),
+                    format_args!("Inserted PhysicsCollider with id: {}", entity.id()),
+                );
+                let physics_collider = physics_collider.get_mut_unchecked();
+
+                // expensive shapes are built in the background; the existing collider of
+                // a re-inserted PhysicsCollider is kept until its replacement is ready
+                if let Some(collider_loader) = collider_loader
+                    .as_mut()
+                    .filter(|_| ColliderLoader::<N>::builds_in_background(&physics_collider.shape))
+                {
+                    collider_loader.submit(entity.id(), physics_collider.shape.clone());
+                    pending_colliders
+                        .insert(entity, ColliderPending)
+                        .expect("Failed to mark PhysicsCollider as pending");
+                    continue;
+                }
+
+                let shape_handle = physics_collider
+                    .shape_handle(shape_cache.as_mut().map(|shape_cache| &mut **shape_cache));
                 add_collider::<N, P>(
-                    id,
+                    entity,
                     parent_entity,
-                    &position,
+                    position,
                     &mut physics,
-                    physics_collider.get_mut_unchecked(),
+                    &mut handles,
+                    physics_collider,
+                    shape_handle,
+                    &mut logger,
                 );
             }
+        }
 
-            // handle modified events
-            if modified_physics_colliders.contains(id) {
-                debug!("Modified PhysicsCollider with id: {}", id);
-                update_collider::<N, P>(id, &mut physics, physics_collider.get_unchecked());
+        // handle modified events; updates deferred during the last frame are
+        // retried once
+        if self.runs(SyncPhase::Update) {
+            std::mem::swap(&mut self.deferred_updates, &mut self.retried_updates);
+            self.deferred_updates.clear();
+            // pending PhysicsColliders are inserted with their latest values
+            let mut rebuilt = Vec::new();
+            for (physics_collider, id, _) in (
+                &physics_colliders,
+                &self.physics_collider_events.modified | &self.retried_updates,
+                !&pending_colliders,
+            )
+                .join()
+            {
+                logger.debug(
+                    module_path!(),
+                    format_args!("Modified PhysicsCollider with id: {}", id),
+                );
+                let shape_cache = shape_cache.as_mut().map(|shape_cache| &mut **shape_cache);
+                match update_collider::<N, P>(
+                    id,
+                    &mut physics,
+                    &mut handles,
+                    physics_collider,
+                    shape_cache,
+                    collider_loader.is_some(),
+                    &mut logger,
+                ) {
+                    ColliderUpdate::Updated => continue,
+                    ColliderUpdate::Rebuild => {
+                        rebuilt.push((id, physics_collider.shape.clone()));
+                        continue;
+                    }
+                    ColliderUpdate::Missing => {}
+                }
+
+                if self.retried_updates.contains(id) {
+                    logger.log(
+                        module_path!(),
+                        Level::Warn,
+                        DiagnosticKind::ColliderUpdateDropped(id),
+                        format_args!(
+                            "Dropping update of collider with id {} which was never inserted",
+                            id
+                        ),
+                    );
+                } else {
+                    logger.log(
+                        module_path!(),
+                        Level::Warn,
+                        DiagnosticKind::ColliderUpdateDeferred(id),
+                        format_args!(
+                            "Deferring update of collider with id {} to the next frame",
+                            id
+                        ),
+                    );
+                    self.deferred_updates.add(id);
+                }
+            }
+
+            // changed expensive shapes are built in the background like inserted
+            // ones; the collider keeps its old shape until the replacement is ready
+            if let Some(collider_loader) = collider_loader.as_mut() {
+                for (id, shape) in rebuilt {
+                    collider_loader.submit(id, shape);
+                    pending_colliders
+                        .insert(entities.entity(id), ColliderPending)
+                        .expect("Failed to mark PhysicsCollider as pending");
+                }
             }
+        }
 
-            // handle removed events
-            if removed_physics_colliders.contains(id) {
-                debug!("Removed PhysicsCollider with id: {}", id);
-                remove_collider::<N, P>(id, &mut physics);
+        // handle removed events; the removed Components can not be joined anymore
+        if self.runs(SyncPhase::Remove) {
+            for id in (&self.physics_collider_events.removed).join() {
+                logger.debug(
+                    module_path!(),
+                    format_args!("Removed PhysicsCollider with id: {}", id),
+                );
+                if let Some(collider_loader) = collider_loader.as_mut() {
+                    if collider_loader.cancel(id) {
+                        pending_colliders.remove(entities.entity(id));
+                    }
+                }
+                remove_collider::<N, P>(id, &mut physics, &mut handles, &mut logger);
             }
         }
 
@@ -120,6 +342,7 @@ where
         let mut physics_collider_storage: WriteStorage<PhysicsCollider<N>> =
             SystemData::fetch(&res);
         self.physics_colliders_reader_id = Some(physics_collider_storage.register_reader());
+        self.rescan = true;
     }
 }
 
@@ -132,6 +355,12 @@ where
         Self {
             positions_reader_id: None,
             physics_colliders_reader_id: None,
+            position_events: ComponentEvents::default(),
+            physics_collider_events: ComponentEvents::default(),
+            deferred_updates: BitSet::new(),
+            retried_updates: BitSet::new(),
+            rescan: false,
+            phase: None,
             n_marker: PhantomData,
             p_marker: PhantomData,
         }
@@ -139,25 +368,63 @@ where
 }
 
 fn add_collider<N, P>(
-    id: Index,
+    entity: Entity,
     parent_entity: Option<&PhysicsParent>,
     position: &P,
     physics: &mut Physics<N>,
+    handles: &mut PhysicsHandles,
     physics_collider: &mut PhysicsCollider<N>,
+    shape_handle: ShapeHandle<N>,
+    logger: &mut SystemLogger,
 ) where
     N: RealField,
     P: Position<N>,
 {
-    // remove already existing colliders for this inserted event
-    if let Some(handle) = physics.collider_handles.remove(&id) {
-        warn!("Removing orphaned collider handle: {:?}", handle);
-        physics.world.remove_colliders(&[handle]);
+    let id = entity.id();
+
+    #[cfg(feature = "validation")]
+    {
+        if let Err(error) =
+            crate::validation::validate_collider(position.isometry(), physics_collider)
+        {
+            logger.log(
+                module_path!(),
+                Level::Error,
+                DiagnosticKind::InvalidInput(id, error),
+                format_args!("Skipped inserting collider with id {}: {}", id, error),
+            );
+            return;
+        }
+    }
+
+    // remove already existing colliders for this inserted event; a
+    // PhysicsCollider re-inserted with its handle replaces its own colliders
+    if let Some(collider_handles) = handles.collider_handles.remove(id) {
+        let replaced = physics_collider
+            .handle
+            .map_or(false, |handle| collider_handles.contains(&handle));
+        if replaced {
+            logger.log(
+                module_path!(),
+                Level::Debug,
+                DiagnosticKind::ColliderRemoved(id),
+                format_args!("Replacing collider handles: {:?}", collider_handles),
+            );
+        } else {
+            logger.log(
+                module_path!(),
+                Level::Warn,
+                DiagnosticKind::OrphanedColliderRemoved(id),
+                format_args!("Removing orphaned collider handles: {:?}", collider_handles),
+            );
+        }
+        remove_existing_colliders(physics, &collider_handles);
     }
 
     // attempt to find an existing RigidBody for this Index; if one exists we'll
     // fetch its BodyPartHandle and use it as the Colliders parent in the
     // nphysics World
-    let parent_part_handle = match physics.body_handles.get(&id) {
+    let parent_part_handle = match handles.body_handles.get(&id) {
         Some(parent_handle) => physics
             .world
             .rigid_body(*parent_handle)
@@ -166,7 +433,7 @@ fn add_collider<N, P>(
             // if BodyHandle was found for the current Entity/Index, check for a potential
             // parent Entity and repeat the first step
             if let Some(parent_entity) = parent_entity {
-                match physics.body_handles.get(&parent_entity.entity.id()) {
+                match handles.body_handles.get(&parent_entity.entity.id()) {
                     Some(parent_handle) => physics
                         .world
                         .rigid_body(*parent_handle)
@@ -198,7 +465,7 @@ fn add_collider<N, P>(
     };
 
     // create the actual Collider in the nphysics World and fetch its handle
-    let handle = ColliderDesc::new(physics_collider.shape_handle())
+    let handle = ColliderDesc::new(shape_handle)
         .position(translation)
         .density(physics_collider.density)
         .material(physics_collider.material.clone())
@@ -207,64 +474,160 @@ fn add_collider<N, P>(
         .linear_prediction(physics_collider.linear_prediction)
         .angular_prediction(physics_collider.angular_prediction)
         .sensor(physics_collider.sensor)
-        .user_data(id)
+        .user_data(entity)
         .build_with_parent(parent_part_handle, &mut physics.world)
         .unwrap()
         .handle();
 
     physics_collider.handle = Some(handle);
-    physics.collider_handles.insert(id, handle);
+    handles.collider_handles.insert(id, handle);
+    match physics_collider.shape.key() {
+        Some(shape_key) => handles.shape_keys.insert(id, shape_key),
+        None => handles.shape_keys.remove(&id),
+    };
 
-    info!(
-        "Inserted collider to world with values: {:?}",
-        physics_collider
+    logger.log(
+        module_path!(),
+        Level::Info,
+        DiagnosticKind::ColliderInserted(id),
+        format_args!(
+            "Inserted collider to world with values: {:?}",
+            physics_collider
+        ),
     );
 }
 
-fn update_collider<N, P>(id: Index, physics: &mut Physics<N>, physics_collider: &PhysicsCollider<N>)
+/// The outcome of `update_collider`.
+enum ColliderUpdate {
+    /// The collider has been updated in the physics `World`.
+    Updated,
+    /// The shape of the collider changed and is expensive enough to be built
+    /// in the background; the collider has not been touched.
+    Rebuild,
+    /// The collider has not been inserted yet.
+    Missing,
+}
+
+fn update_collider<N, P>(
+    id: Index,
+    physics: &mut Physics<N>,
+    handles: &mut PhysicsHandles,
+    physics_collider: &PhysicsCollider<N>,
+    shape_cache: Option<&mut ShapeCache<N>>,
+    build_in_background: bool,
+    logger: &mut SystemLogger,
+) -> ColliderUpdate
 where
     N: RealField,
     P: Position<N>,
 {
-    debug!("Modified PhysicsCollider with id: {}", id);
-    let collider_handle = physics_collider.handle.unwrap();
+    logger.debug(
+        module_path!(),
+        format_args!("Modified PhysicsCollider with id: {}", id),
+    );
+    // the collider has not been inserted yet, e.g. because the modification
+    // arrived before its insertion was processed
+    let collider_handle = match physics_collider.handle {
+        Some(collider_handle) => collider_handle,
+        None => return ColliderUpdate::Missing,
+    };
+
+    // only changed shapes, e.g. the edges of destructible terrain, are
+    // rebuilt; shapes without a ShapeKey can not be compared and always are
+    let shape_key = physics_collider.shape.key();
+    let shape_changed = shape_key.is_none() || handles.shape_keys.get(&id) != shape_key.as_ref();
+    if shape_changed
+        && build_in_background
+        && ColliderLoader::<N>::builds_in_background(&physics_collider.shape)
+    {
+        return ColliderUpdate::Rebuild;
+    }
+
     let collider_world = physics.world.collider_world_mut();
 
     // update collision groups
     collider_world.set_collision_groups(collider_handle, physics_collider.collision_groups);
 
-    info!(
-        "Updated collider in world with values: {:?}",
-        physics_collider
+    // update the shape as well as the margin and the predictions
+    let collision_world = collider_world.as_collision_world_mut();
+    if shape_changed {
+        collision_world.set_shape(collider_handle, physics_collider.shape_handle(shape_cache));
+        match shape_key {
+            Some(shape_key) => handles.shape_keys.insert(id, shape_key),
+            None => handles.shape_keys.remove(&id),
+        };
+    }
+    collision_world.set_query_type(collider_handle, physics_collider.query_type());
+
+    logger.log(
+        module_path!(),
+        Level::Info,
+        DiagnosticKind::ColliderUpdated(id),
+        format_args!(
+            "Updated collider in world with values: {:?}",
+            physics_collider
+        ),
     );
+    ColliderUpdate::Updated
 }
 
-fn remove_collider<N, P>(id: Index, physics: &mut Physics<N>)
-where
+fn remove_collider<N, P>(
+    id: Index,
+    physics: &mut Physics<N>,
+    handles: &mut PhysicsHandles,
+    logger: &mut SystemLogger,
+) where
     N: RealField,
     P: Position<N>,
 {
-    debug!("Removed PhysicsCollider with id: {}", id);
-    if let Some(handle) = physics.collider_handles.remove(&id) {
-        // we have to check if the collider still exists in the nphysics World before
-        // attempting to delete it as removing a collider that does not exist anymore
-        // causes the nphysics World to panic; colliders are implicitly removed when a
-        // parent body is removed so this is actually a valid scenario
-        if physics.world.collider(handle).is_some() {
-            physics.world.remove_colliders(&[handle]);
-        }
-
-        info!("Removed collider from world with id: {}", id);
+    logger.debug(
+        module_path!(),
+        format_args!("Removed PhysicsCollider with id: {}", id),
+    );
+    handles.shape_keys.remove(&id);
+    if let Some(collider_handles) = handles.collider_handles.remove(id) {
+        remove_existing_colliders(physics, &collider_handles);
+
+        logger.log(
+            module_path!(),
+            Level::Info,
+            DiagnosticKind::ColliderRemoved(id),
+            format_args!("Removed collider from world with id: {}", id),
+        );
     }
 }
 
+fn remove_existing_colliders<N: RealField>(physics: &mut Physics<N>, handles: &[ColliderHandle]) {
+    // we have to check if the collider still exists in the nphysics World before
+    // attempting to delete it as removing a collider that does not exist anymore
+    // causes the nphysics World to panic; colliders are implicitly removed when a
+    // parent body is removed so this is actually a valid scenario
+    let existing_handles = handles
+        .iter()
+        .cloned()
+        .filter(|handle| physics.world.collider(*handle).is_some())
+        .collect::<Vec<_>>();
+    physics.world.remove_colliders(&existing_handles);
+}
+
 #[cfg(test)]
 mod tests {
-    use specs::prelude::*;
+    use std::{thread, time::Duration};
+
+    use specs::{prelude::*, storage::ComponentEvent};
+
+    use log::LevelFilter;
 
     use crate::{
-        colliders::Shape,
-        nalgebra::Isometry3,
+        colliders::{PhysicsCollider, Shape},
+        diagnostics::{DiagnosticKind, PhysicsDiagnostics, PhysicsLogConfig},
+        handles::PhysicsHandles,
+        loading::{ColliderLoader, ColliderPending},
+        nalgebra::{Isometry3, Point3, Vector3},
+        ncollide::{
+            shape::{Compound, Cuboid, Polyline},
+            world::CollisionGroups,
+        },
         systems::SyncCollidersToPhysicsSystem,
         Physics,
         PhysicsColliderBuilder,
@@ -285,7 +648,7 @@ mod tests {
 
         // create an Entity with the PhysicsCollider component and execute the
         // dispatcher
-        world
+        let entity = world
             .create_entity()
             .with(SimplePosition::<f32>(Isometry3::<f32>::translation(
                 1.0, 1.0, 1.0,
@@ -296,7 +659,357 @@ mod tests {
 
         // fetch the Physics instance and check for new colliders
         let physics = world.read_resource::<Physics<f32>>();
-        assert_eq!(physics.collider_handles.len(), 1);
+        let handles = world.read_resource::<PhysicsHandles>();
+        assert_eq!(handles.collider_handles.len(), 1);
         assert_eq!(physics.world.colliders().count(), 1);
+
+        // the collider maps back to the Entity that owns it
+        let handle = handles.collider_handles(entity)[0];
+        assert_eq!(physics.collider_entity(handle), Some(entity));
+    }
+
+    #[test]
+    fn update_collider_shape() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncCollidersToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_colliders_to_physics_system",
+                &[],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+
+        // the outline of a piece of terrain, which is cut in two afterwards
+        let entity = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::chain(
+                    vec![
+                        Point3::new(0.0, 0.0, 0.0),
+                        Point3::new(4.0, 0.0, 0.0),
+                        Point3::new(4.0, 1.0, 0.0),
+                    ],
+                    false,
+                ))
+                .build(),
+            )
+            .build();
+        dispatcher.dispatch(&world);
+
+        world
+            .write_storage::<PhysicsCollider<f32>>()
+            .get_mut(entity)
+            .unwrap()
+            .shape = Shape::chain(
+            vec![Point3::new(0.0, 0.0, 0.0), Point3::new(2.0, 0.0, 0.0)],
+            false,
+        );
+        dispatcher.dispatch(&world);
+
+        let physics = world.read_resource::<Physics<f32>>();
+        let handles = world.read_resource::<PhysicsHandles>();
+        let handle = handles.collider_handles(entity)[0];
+        let collider = physics.world.collider(handle).unwrap();
+        let polyline = collider.shape().as_shape::<Polyline<f32>>().unwrap();
+        assert_eq!(polyline.edges().len(), 1);
+    }
+
+    #[test]
+    fn defer_update_of_pending_insertion() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncCollidersToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_colliders_to_physics_system",
+                &[],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+        world.insert(PhysicsLogConfig {
+            structured: true,
+            ..PhysicsLogConfig::with_level(LevelFilter::Trace)
+        });
+        let mut reader_id = world
+            .write_resource::<PhysicsDiagnostics>()
+            .register_reader();
+        let mut diagnostics = |world: &World| {
+            world
+                .read_resource::<PhysicsDiagnostics>()
+                .read(&mut reader_id)
+                .map(|diagnostic| diagnostic.kind.clone())
+                .collect::<Vec<_>>()
+        };
+
+        // without a Position neither PhysicsCollider can be inserted yet
+        let positioned = world
+            .create_entity()
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 1.0 }).build())
+            .build();
+        let unpositioned = world
+            .create_entity()
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 1.0 }).build())
+            .build();
+        for entity in &[positioned, unpositioned] {
+            world
+                .write_storage::<PhysicsCollider<f32>>()
+                .get_mut(*entity)
+                .unwrap()
+                .margin = 0.02;
+        }
+        dispatcher.dispatch(&world);
+
+        let deferred = diagnostics(&world);
+        assert!(deferred.contains(&DiagnosticKind::ColliderUpdateDeferred(positioned.id())));
+        assert!(deferred.contains(&DiagnosticKind::ColliderUpdateDeferred(unpositioned.id())));
+
+        // the retried update succeeds once the PhysicsCollider was inserted, and
+        // is dropped on the second miss
+        world
+            .write_storage::<SimplePosition<f32>>()
+            .insert(positioned, SimplePosition(Isometry3::identity()))
+            .unwrap();
+        dispatcher.dispatch(&world);
+
+        let retried = diagnostics(&world);
+        assert!(retried.contains(&DiagnosticKind::ColliderUpdated(positioned.id())));
+        assert!(!retried.contains(&DiagnosticKind::ColliderUpdateDropped(positioned.id())));
+        assert!(retried.contains(&DiagnosticKind::ColliderUpdateDropped(unpositioned.id())));
+
+        let physics = world.read_resource::<Physics<f32>>();
+        let handles = world.read_resource::<PhysicsHandles>();
+        let handle = handles.collider_handles(positioned)[0];
+        assert_eq!(physics.world.collider(handle).unwrap().margin(), 0.02);
+        assert!(handles.collider_handles(unpositioned).is_empty());
+    }
+
+    #[test]
+    fn keep_unchanged_shape() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncCollidersToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_colliders_to_physics_system",
+                &[],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+
+        let entity = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Cuboid {
+                    half_extents: Vector3::new(0.5, 0.5, 0.5),
+                })
+                .build(),
+            )
+            .build();
+        dispatcher.dispatch(&world);
+        let cuboid = |world: &World| {
+            let physics = world.read_resource::<Physics<f32>>();
+            let handle = world
+                .read_resource::<PhysicsHandles>()
+                .collider_handles(entity)[0];
+            let collider = physics.world.collider(handle).unwrap();
+            let cuboid = collider.shape().as_shape::<Cuboid<f32>>().unwrap();
+            (cuboid as *const Cuboid<f32>, *cuboid.half_extents())
+        };
+        let (built, _) = cuboid(&world);
+
+        // changing the collision groups keeps the shape
+        world
+            .write_storage::<PhysicsCollider<f32>>()
+            .get_mut(entity)
+            .unwrap()
+            .collision_groups = CollisionGroups::new().with_membership(&[1]);
+        dispatcher.dispatch(&world);
+        assert_eq!(cuboid(&world).0, built);
+
+        // changing its dimensions rebuilds it
+        world
+            .write_storage::<PhysicsCollider<f32>>()
+            .get_mut(entity)
+            .unwrap()
+            .shape = Shape::Cuboid {
+            half_extents: Vector3::new(1.0, 0.5, 0.5),
+        };
+        dispatcher.dispatch(&world);
+        assert_eq!(cuboid(&world).1, Vector3::new(1.0, 0.5, 0.5));
+    }
+
+    #[test]
+    fn finish_loads_without_flagging() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncCollidersToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_colliders_to_physics_system",
+                &[],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+        world.insert(ColliderLoader::<f32>::new(1));
+        let mut reader_id = world
+            .write_storage::<PhysicsCollider<f32>>()
+            .register_reader();
+
+        let entity = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::ConvexHull {
+                    points: vec![
+                        Point3::new(0.0, 0.0, 0.0),
+                        Point3::new(1.0, 0.0, 0.0),
+                        Point3::new(0.0, 1.0, 0.0),
+                        Point3::new(0.0, 0.0, 1.0),
+                    ],
+                })
+                .build(),
+            )
+            .build();
+        for _ in 0..100 {
+            dispatcher.dispatch(&world);
+            if world
+                .read_storage::<ColliderPending>()
+                .get(entity)
+                .is_none()
+            {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(
+            world
+                .read_resource::<PhysicsHandles>()
+                .collider_handles(entity)
+                .len(),
+            1
+        );
+
+        // only the insertion of the PhysicsCollider has been reported
+        let physics_colliders = world.read_storage::<PhysicsCollider<f32>>();
+        assert!(physics_colliders
+            .channel()
+            .read(&mut reader_id)
+            .all(|event| match event {
+                ComponentEvent::Inserted(_) => true,
+                _ => false,
+            }));
+    }
+
+    #[test]
+    fn mark_pending_until_built() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncCollidersToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_colliders_to_physics_system",
+                &[],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+        world.insert(ColliderLoader::<f32>::new(1));
+
+        let entity = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::ConvexHull {
+                    points: vec![
+                        Point3::new(0.0, 0.0, 0.0),
+                        Point3::new(1.0, 0.0, 0.0),
+                        Point3::new(0.0, 1.0, 0.0),
+                        Point3::new(0.0, 0.0, 1.0),
+                    ],
+                })
+                .build(),
+            )
+            .build();
+        let pending = |world: &World| world.read_storage::<ColliderPending>().contains(entity);
+        let collider_count = |world: &World| {
+            world
+                .read_resource::<PhysicsHandles>()
+                .collider_handles(entity)
+                .len()
+        };
+
+        // the shape is submitted to the loader instead of being built right away
+        dispatcher.dispatch(&world);
+        assert!(pending(&world));
+        assert_eq!(collider_count(&world), 0);
+
+        for _ in 0..100 {
+            thread::sleep(Duration::from_millis(10));
+            dispatcher.dispatch(&world);
+            if !pending(&world) {
+                break;
+            }
+        }
+        assert!(!pending(&world));
+        assert_eq!(collider_count(&world), 1);
+    }
+
+    #[test]
+    fn fracture_compound_part() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncCollidersToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_colliders_to_physics_system",
+                &[],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+
+        // a wall of two blocks, the second of which breaks into two halves
+        let block = Shape::Cuboid {
+            half_extents: Vector3::new(0.5, 0.5, 0.5),
+        };
+        let entity = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Compound {
+                    parts: vec![
+                        (Isometry3::identity(), block.clone()),
+                        (Isometry3::translation(1.0, 0.0, 0.0), block),
+                    ],
+                })
+                .build(),
+            )
+            .build();
+        dispatcher.dispatch(&world);
+        let handle = world
+            .read_resource::<PhysicsHandles>()
+            .collider_handles(entity)[0];
+
+        let half = Shape::Cuboid {
+            half_extents: Vector3::new(0.25, 0.5, 0.5),
+        };
+        world
+            .write_storage::<PhysicsCollider<f32>>()
+            .get_mut(entity)
+            .unwrap()
+            .shape
+            .fracture_part(
+                1,
+                vec![
+                    (Isometry3::translation(-0.25, 0.0, 0.0), half.clone()),
+                    (Isometry3::translation(0.25, 0.0, 0.0), half),
+                ],
+            )
+            .unwrap();
+        dispatcher.dispatch(&world);
+
+        // the collider is kept and only its shape is replaced
+        let physics = world.read_resource::<Physics<f32>>();
+        let handles = world.read_resource::<PhysicsHandles>();
+        assert_eq!(handles.collider_handles(entity)[0], handle);
+        let collider = physics.world.collider(handle).unwrap();
+        let compound = collider.shape().as_shape::<Compound<f32>>().unwrap();
+        assert_eq!(compound.shapes().len(), 3);
     }
 }
@@ -0,0 +1,357 @@
+use std::{collections::HashMap, marker::PhantomData};
+
+use log::Level;
+
+use specs::{
+    storage::ComponentEvent,
+    world::Index,
+    Entities,
+    Entity,
+    Join,
+    Read,
+    ReadStorage,
+    ReaderId,
+    System,
+    SystemData,
+    World,
+    Write,
+    WriteExpect,
+    WriteStorage,
+};
+
+use crate::{
+    diagnostics::{DiagnosticKind, LogSource, PhysicsDiagnostics, PhysicsLogConfig, SystemLogger},
+    events::{ElevatorEvent, ElevatorEventKind, ElevatorEvents},
+    handles::{JointOwner, PhysicsHandles},
+    joints::Elevator,
+    nalgebra::{self as na, Isometry3, Point3, RealField},
+    nphysics::{
+        algebra::{Force3, Velocity3},
+        joint::PrismaticConstraint,
+        object::{BodyHandle, BodyPart, BodyPartHandle},
+    },
+    Physics,
+};
+
+use super::{
+    iterate_component_events,
+    joint_body_handle,
+    joint_vacant,
+    remove_joint,
+    ComponentEvents,
+};
+
+/// The `SyncElevatorsToPhysicsSystem` creates the prismatic constraints of
+/// `Elevator`s in the physics `World`, drives their cabins towards the target
+/// floor and emits `ElevatorEvent`s. It has to run after the
+/// `SyncBodiesToPhysicsSystem`, as the bodies of an `Elevator` have to exist
+/// before its constraint can be created.
+pub struct SyncElevatorsToPhysicsSystem<N> {
+    elevators_reader_id: Option<ReaderId<ComponentEvent>>,
+    elevator_events: ComponentEvents,
+    stops: HashMap<Index, ElevatorStops>,
+
+    n_marker: PhantomData<N>,
+}
+
+/// The stops an `Elevator` cabin was at during the last run, used to emit
+/// every `ElevatorEvent` only once.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct ElevatorStops {
+    floor: Option<usize>,
+    min: bool,
+    max: bool,
+}
+
+impl<'s, N: RealField> System<'s> for SyncElevatorsToPhysicsSystem<N> {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Elevator<N>>,
+        Option<Read<'s, PhysicsLogConfig>>,
+        Write<'s, PhysicsDiagnostics>,
+        Write<'s, ElevatorEvents>,
+        WriteExpect<'s, Physics<N>>,
+        Write<'s, PhysicsHandles>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            elevators,
+            log_config,
+            mut diagnostics,
+            mut elevator_events,
+            mut physics,
+            mut handles,
+        ) = data;
+        let mut logger = SystemLogger::new(
+            log_config.as_ref().map(|config| &**config),
+            LogSource::Joints,
+            &mut diagnostics,
+        );
+
+        // collect all ComponentEvents for the Elevator storage
+        iterate_component_events(
+            &elevators,
+            self.elevators_reader_id.as_mut().unwrap(),
+            &mut self.elevator_events,
+            &mut logger,
+        );
+
+        // remove the constraints of removed and modified Elevators; the latter
+        // are recreated with their new values below
+        for id in (&self.elevator_events.modified | &self.elevator_events.removed).join() {
+            logger.debug(
+                module_path!(),
+                format_args!("Removed or modified Elevator with id: {}", id),
+            );
+            remove_joint(
+                id,
+                JointOwner::Elevator,
+                &mut physics,
+                &mut handles,
+                &mut logger,
+            );
+        }
+        for id in (&self.elevator_events.removed).join() {
+            self.stops.remove(&id);
+        }
+
+        for (entity, elevator) in (&entities, &elevators).join() {
+            let id = entity.id();
+            let base_handle = joint_body_handle(&entities, &handles, elevator.base);
+
+            match (base_handle, handles.body_handles.get(&id).cloned()) {
+                (Some(base_handle), Some(cabin_handle)) => {
+                    let report = self.elevator_events.inserted.contains(id)
+                        || self.elevator_events.modified.contains(id);
+                    if joint_vacant(id, JointOwner::Elevator, &handles, report, &mut logger) {
+                        add_elevator(
+                            id,
+                            elevator,
+                            base_handle,
+                            cabin_handle,
+                            &mut physics,
+                            &mut handles,
+                            &mut logger,
+                        );
+                    }
+
+                    if let Some(offset) =
+                        drive_elevator(elevator, base_handle, cabin_handle, &mut physics)
+                    {
+                        let stops = self.stops.entry(id).or_default();
+                        write_events(entity, elevator, offset, stops, &mut elevator_events);
+                    }
+                }
+                // the constraint must not outlive either of its bodies; it is
+                // recreated once both bodies exist again
+                _ => remove_joint(
+                    id,
+                    JointOwner::Elevator,
+                    &mut physics,
+                    &mut handles,
+                    &mut logger,
+                ),
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("SyncElevatorsToPhysicsSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+
+        // register reader id for the Elevator storage
+        let mut elevator_storage: WriteStorage<Elevator<N>> = SystemData::fetch(&res);
+        self.elevators_reader_id = Some(elevator_storage.register_reader());
+    }
+}
+
+impl<N> Default for SyncElevatorsToPhysicsSystem<N>
+where
+    N: RealField,
+{
+    fn default() -> Self {
+        Self {
+            elevators_reader_id: None,
+            elevator_events: ComponentEvents::default(),
+            stops: HashMap::new(),
+            n_marker: PhantomData,
+        }
+    }
+}
+
+fn add_elevator<N: RealField>(
+    id: Index,
+    elevator: &Elevator<N>,
+    base_handle: Option<BodyHandle>,
+    cabin_handle: BodyHandle,
+    physics: &mut Physics<N>,
+    handles: &mut PhysicsHandles,
+    logger: &mut SystemLogger,
+) {
+    let base_part = match base_handle {
+        Some(base_handle) => match physics.world.rigid_body(base_handle) {
+            Some(rigid_body) => rigid_body.part_handle(),
+            None => return,
+        },
+        None => BodyPartHandle::ground(),
+    };
+    let cabin_part = match physics.world.rigid_body(cabin_handle) {
+        Some(rigid_body) => rigid_body.part_handle(),
+        None => return,
+    };
+
+    let mut constraint = PrismaticConstraint::new(
+        base_part,
+        cabin_part,
+        elevator.anchor,
+        elevator.axis,
+        Point3::origin(),
+    );
+    constraint.enable_min_offset(elevator.min_offset);
+    constraint.enable_max_offset(elevator.max_offset);
+
+    let handle = physics.world.add_constraint(constraint);
+    handles
+        .joint_handles
+        .insert(id, (JointOwner::Elevator, handle));
+
+    logger.log(
+        module_path!(),
+        Level::Info,
+        DiagnosticKind::JointInserted(id),
+        format_args!("Inserted elevator to world with values: {:?}", elevator),
+    );
+}
+
+/// Applies the motor force of the `Elevator` and returns the current offset of
+/// the cabin along the shaft.
+fn drive_elevator<N: RealField>(
+    elevator: &Elevator<N>,
+    base_handle: Option<BodyHandle>,
+    cabin_handle: BodyHandle,
+    physics: &mut Physics<N>,
+) -> Option<N> {
+    let (base_position, base_velocity, base_center) = match base_handle {
+        Some(base_handle) => {
+            let rigid_body = physics.world.rigid_body(base_handle)?;
+            (
+                *rigid_body.position(),
+                *rigid_body.velocity(),
+                rigid_body.center_of_mass(),
+            )
+        }
+        None => (Isometry3::identity(), Velocity3::zero(), Point3::origin()),
+    };
+    let (cabin_position, cabin_velocity, cabin_mass) = {
+        let rigid_body = physics.world.rigid_body(cabin_handle)?;
+        (
+            *rigid_body.position(),
+            *rigid_body.velocity(),
+            rigid_body.local_inertia().linear,
+        )
+    };
+
+    let anchor = base_position * elevator.anchor;
+    let axis = (base_position.rotation * elevator.axis).into_inner();
+    let cabin_center = Point3::from(cabin_position.translation.vector);
+    let lever = cabin_center - base_center;
+
+    let offset = (cabin_center - anchor).dot(&axis);
+    let shaft_velocity = base_velocity.linear + base_velocity.angular.cross(&lever);
+    let speed = (cabin_velocity.linear - shaft_velocity).dot(&axis);
+
+    // reach the motor velocity within a single step while carrying the weight
+    // of the cabin, limited by the strength of the motor
+    let dt = physics.world.timestep();
+    let acceleration =
+        (elevator.motor_velocity(offset, dt) - speed) / dt - physics.world.gravity().dot(&axis);
+    let force = axis
+        * na::clamp(
+            acceleration * cabin_mass,
+            -elevator.max_force,
+            elevator.max_force,
+        );
+
+    physics.apply_frame_force(cabin_handle, &Force3::linear(force));
+    if let Some(base_handle) = base_handle {
+        physics.apply_frame_force(base_handle, &Force3::new(-force, lever.cross(&-force)));
+    }
+
+    Some(offset)
+}
+
+fn write_events<N: RealField>(
+    entity: Entity,
+    elevator: &Elevator<N>,
+    offset: N,
+    stops: &mut ElevatorStops,
+    elevator_events: &mut ElevatorEvents,
+) {
+    let tolerance = na::convert::<f64, N>(0.01);
+    let current = ElevatorStops {
+        floor: if (elevator.target_offset() - offset).abs() <= tolerance {
+            Some(elevator.target_floor)
+        } else {
+            None
+        },
+        min: offset <= elevator.min_offset + tolerance,
+        max: offset >= elevator.max_offset - tolerance,
+    };
+
+    // only emit events for stops that were reached during this run
+    if let Some(floor) = current.floor {
+        if stops.floor != current.floor {
+            elevator_events.single_write(ElevatorEvent {
+                elevator: entity,
+                kind: ElevatorEventKind::ReachedFloor(floor),
+            });
+        }
+    }
+    if current.min && !stops.min {
+        elevator_events.single_write(ElevatorEvent {
+            elevator: entity,
+            kind: ElevatorEventKind::ReachedMinStop,
+        });
+    }
+    if current.max && !stops.max {
+        elevator_events.single_write(ElevatorEvent {
+            elevator: entity,
+            kind: ElevatorEventKind::ReachedMaxStop,
+        });
+    }
+
+    *stops = current;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        joints::Elevator,
+        nalgebra::{Point3, Vector3},
+    };
+
+    #[test]
+    fn motor_velocity_stops_at_target() {
+        let mut elevator = Elevator::new(
+            Point3::origin(),
+            Vector3::y_axis(),
+            vec![0.0f32, 4.0],
+            2.0,
+            100.0,
+        );
+        elevator.go_to(1);
+
+        assert_eq!(elevator.motor_velocity(0.0, 0.5), 2.0);
+        assert_eq!(elevator.motor_velocity(3.5, 0.5), 1.0);
+        assert_eq!(elevator.motor_velocity(4.0, 0.5), 0.0);
+
+        // unknown floors keep the cabin at the lower end stop
+        elevator.go_to(5);
+        assert_eq!(elevator.motor_velocity(4.0, 0.5), -2.0);
+    }
+}
@@ -0,0 +1,295 @@
+use std::marker::PhantomData;
+
+use log::Level;
+
+use specs::{
+    storage::ComponentEvent,
+    world::Index,
+    Entities,
+    Join,
+    Read,
+    ReadStorage,
+    ReaderId,
+    System,
+    SystemData,
+    World,
+    Write,
+    WriteExpect,
+    WriteStorage,
+};
+
+use crate::{
+    diagnostics::{DiagnosticKind, LogSource, PhysicsDiagnostics, PhysicsLogConfig, SystemLogger},
+    handles::{JointOwner, PhysicsHandles},
+    joints::{HingedDoor, HingedDoorPoses},
+    nalgebra::{Isometry3, RealField, Vector3},
+    nphysics::{
+        algebra::{Force3, Velocity3},
+        joint::{FixedConstraint, RevoluteConstraint},
+        object::{BodyHandle, BodyPartHandle},
+    },
+    Physics,
+};
+
+use super::{
+    iterate_component_events,
+    joint_body_handle,
+    joint_vacant,
+    remove_joint,
+    ComponentEvents,
+};
+
+/// The `SyncHingedDoorsToPhysicsSystem` creates the revolute constraints of
+/// `HingedDoor`s in the physics `World`, replaces them with fixed constraints
+/// while a door is locked and applies the `DoorSpring` torques. It has to run
+/// after the `SyncBodiesToPhysicsSystem`, as the bodies of a `HingedDoor` have
+/// to exist before its constraint can be created.
+pub struct SyncHingedDoorsToPhysicsSystem<N> {
+    hinged_doors_reader_id: Option<ReaderId<ComponentEvent>>,
+    hinged_door_events: ComponentEvents,
+
+    n_marker: PhantomData<N>,
+}
+
+impl<'s, N: RealField> System<'s> for SyncHingedDoorsToPhysicsSystem<N> {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, HingedDoor<N>>,
+        Option<Read<'s, PhysicsLogConfig>>,
+        Write<'s, PhysicsDiagnostics>,
+        WriteExpect<'s, Physics<N>>,
+        Write<'s, PhysicsHandles>,
+        Write<'s, HingedDoorPoses<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            hinged_doors,
+            log_config,
+            mut diagnostics,
+            mut physics,
+            mut handles,
+            mut hinged_door_poses,
+        ) = data;
+        let mut logger = SystemLogger::new(
+            log_config.as_ref().map(|config| &**config),
+            LogSource::Joints,
+            &mut diagnostics,
+        );
+
+        // collect all ComponentEvents for the HingedDoor storage
+        iterate_component_events(
+            &hinged_doors,
+            self.hinged_doors_reader_id.as_mut().unwrap(),
+            &mut self.hinged_door_events,
+            &mut logger,
+        );
+
+        // remove the constraints of removed and modified HingedDoors; the latter
+        // are recreated with their new values below, e.g. after being locked
+        for id in (&self.hinged_door_events.modified | &self.hinged_door_events.removed).join() {
+            logger.debug(
+                module_path!(),
+                format_args!("Removed or modified HingedDoor with id: {}", id),
+            );
+            remove_joint(
+                id,
+                JointOwner::HingedDoor,
+                &mut physics,
+                &mut handles,
+                &mut logger,
+            );
+        }
+        for id in (&self.hinged_door_events.removed).join() {
+            hinged_door_poses.remove(id);
+        }
+
+        for (entity, hinged_door) in (&entities, &hinged_doors).join() {
+            let id = entity.id();
+            let frame_handle = joint_body_handle(&entities, &handles, hinged_door.frame);
+
+            match (frame_handle, handles.body_handles.get(&id).cloned()) {
+                (Some(frame_handle), Some(door_handle)) => {
+                    let (frame_pose, door_pose) = match (
+                        body_pose(&physics, frame_handle),
+                        body_pose(&physics, Some(door_handle)),
+                    ) {
+                        (Some(frame_pose), Some(door_pose)) => (frame_pose, door_pose),
+                        _ => continue,
+                    };
+
+                    let relative_rotation = frame_pose.0.rotation.inverse() * door_pose.0.rotation;
+                    let closed_rotation =
+                        hinged_door_poses.closed_rotation_or_insert(id, relative_rotation);
+
+                    let report = self.hinged_door_events.inserted.contains(id)
+                        || self.hinged_door_events.modified.contains(id);
+                    if joint_vacant(id, JointOwner::HingedDoor, &handles, report, &mut logger) {
+                        add_hinged_door(
+                            id,
+                            hinged_door,
+                            frame_handle,
+                            door_handle,
+                            &frame_pose.0,
+                            &door_pose.0,
+                            &mut physics,
+                            &mut handles,
+                            &mut logger,
+                        );
+                    }
+
+                    if hinged_door.spring.is_some() && !hinged_door.locked {
+                        // signed opening angle around the hinge axis
+                        let angle = (relative_rotation * closed_rotation.inverse())
+                            .scaled_axis()
+                            .dot(&hinged_door.axis.into_inner());
+                        apply_spring_torque(
+                            hinged_door,
+                            angle,
+                            frame_handle,
+                            door_handle,
+                            &frame_pose,
+                            &door_pose,
+                            &mut physics,
+                        );
+                    }
+                }
+                // the constraint must not outlive either of its bodies; it is
+                // recreated once both bodies exist again
+                _ => remove_joint(
+                    id,
+                    JointOwner::HingedDoor,
+                    &mut physics,
+                    &mut handles,
+                    &mut logger,
+                ),
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("SyncHingedDoorsToPhysicsSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+
+        // register reader id for the HingedDoor storage
+        let mut hinged_door_storage: WriteStorage<HingedDoor<N>> = SystemData::fetch(&res);
+        self.hinged_doors_reader_id = Some(hinged_door_storage.register_reader());
+    }
+}
+
+impl<N> Default for SyncHingedDoorsToPhysicsSystem<N>
+where
+    N: RealField,
+{
+    fn default() -> Self {
+        Self {
+            hinged_doors_reader_id: None,
+            hinged_door_events: ComponentEvents::default(),
+            n_marker: PhantomData,
+        }
+    }
+}
+
+/// Fetches the position and velocity of the body with the given handle; the
+/// ground is returned for `None`.
+fn body_pose<N: RealField>(
+    physics: &Physics<N>,
+    handle: Option<BodyHandle>,
+) -> Option<(Isometry3<N>, Velocity3<N>)> {
+    match handle {
+        Some(handle) => physics
+            .world
+            .rigid_body(handle)
+            .map(|rigid_body| (*rigid_body.position(), *rigid_body.velocity())),
+        None => Some((Isometry3::identity(), Velocity3::zero())),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn add_hinged_door<N: RealField>(
+    id: Index,
+    hinged_door: &HingedDoor<N>,
+    frame_handle: Option<BodyHandle>,
+    door_handle: BodyHandle,
+    frame_position: &Isometry3<N>,
+    door_position: &Isometry3<N>,
+    physics: &mut Physics<N>,
+    handles: &mut PhysicsHandles,
+    logger: &mut SystemLogger,
+) {
+    let frame_part = match frame_handle {
+        Some(frame_handle) => match physics.world.rigid_body(frame_handle) {
+            Some(rigid_body) => rigid_body.part_handle(),
+            None => return,
+        },
+        None => BodyPartHandle::ground(),
+    };
+    let door_part = match physics.world.rigid_body(door_handle) {
+        Some(rigid_body) => rigid_body.part_handle(),
+        None => return,
+    };
+
+    // express the hinge in the local space of the door at its current pose
+    let frame_to_door = door_position.inverse() * frame_position;
+    let handle = if hinged_door.locked {
+        let anchor = Isometry3::new(hinged_door.anchor.coords, Vector3::zeros());
+        physics.world.add_constraint(FixedConstraint::new(
+            frame_part,
+            door_part,
+            anchor,
+            frame_to_door * anchor,
+        ))
+    } else {
+        let mut constraint = RevoluteConstraint::new(
+            frame_part,
+            door_part,
+            hinged_door.anchor,
+            hinged_door.axis,
+            frame_to_door * hinged_door.anchor,
+            frame_to_door.rotation * hinged_door.axis,
+        );
+        if let Some(min_angle) = hinged_door.min_angle {
+            constraint.enable_min_angle(min_angle);
+        }
+        if let Some(max_angle) = hinged_door.max_angle {
+            constraint.enable_max_angle(max_angle);
+        }
+        physics.world.add_constraint(constraint)
+    };
+    handles
+        .joint_handles
+        .insert(id, (JointOwner::HingedDoor, handle));
+
+    logger.log(
+        module_path!(),
+        Level::Info,
+        DiagnosticKind::JointInserted(id),
+        format_args!(
+            "Inserted hinged door to world with values: {:?}",
+            hinged_door
+        ),
+    );
+}
+
+fn apply_spring_torque<N: RealField>(
+    hinged_door: &HingedDoor<N>,
+    angle: N,
+    frame_handle: Option<BodyHandle>,
+    door_handle: BodyHandle,
+    frame_pose: &(Isometry3<N>, Velocity3<N>),
+    door_pose: &(Isometry3<N>, Velocity3<N>),
+    physics: &mut Physics<N>,
+) {
+    let axis = (frame_pose.0.rotation * hinged_door.axis).into_inner();
+    let velocity = (door_pose.1.angular - frame_pose.1.angular).dot(&axis);
+    let torque = axis * hinged_door.spring_torque(angle, velocity);
+
+    physics.apply_frame_force(door_handle, &Force3::torque(torque));
+    if let Some(frame_handle) = frame_handle {
+        physics.apply_frame_force(frame_handle, &Force3::torque(-torque));
+    }
+}
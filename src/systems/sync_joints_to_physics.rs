@@ -0,0 +1,312 @@
+use std::marker::PhantomData;
+
+use log::Level;
+
+use specs::{
+    storage::ComponentEvent,
+    world::Index,
+    Entities,
+    Join,
+    Read,
+    ReadStorage,
+    ReaderId,
+    System,
+    SystemData,
+    World,
+    Write,
+    WriteExpect,
+    WriteStorage,
+};
+
+use crate::{
+    diagnostics::{DiagnosticKind, LogSource, PhysicsDiagnostics, PhysicsLogConfig, SystemLogger},
+    handles::{JointOwner, PhysicsHandles},
+    joints::{JointKind, PhysicsJoint},
+    nalgebra::{Isometry3, RealField},
+    nphysics::{
+        joint::{BallConstraint, FixedConstraint, PrismaticConstraint, RevoluteConstraint},
+        object::{BodyHandle, BodyPartHandle},
+    },
+    Physics,
+};
+
+use super::{
+    iterate_component_events,
+    joint_body_handle,
+    joint_vacant,
+    remove_joint,
+    ComponentEvents,
+};
+
+/// The `SyncJointsToPhysicsSystem` creates the constraints of `PhysicsJoint`s
+/// in the physics `World`, recreates them when the `PhysicsJoint` is modified
+/// and removes them with the `PhysicsJoint` or either body. It has to run
+/// after the `SyncBodiesToPhysicsSystem`, as both bodies of a `PhysicsJoint`
+/// have to exist before its constraint can be created.
+pub struct SyncJointsToPhysicsSystem<N> {
+    physics_joints_reader_id: Option<ReaderId<ComponentEvent>>,
+    physics_joint_events: ComponentEvents,
+
+    n_marker: PhantomData<N>,
+}
+
+impl<'s, N: RealField> System<'s> for SyncJointsToPhysicsSystem<N> {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, PhysicsJoint<N>>,
+        Option<Read<'s, PhysicsLogConfig>>,
+        Write<'s, PhysicsDiagnostics>,
+        WriteExpect<'s, Physics<N>>,
+        Write<'s, PhysicsHandles>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, physics_joints, log_config, mut diagnostics, mut physics, mut handles) =
+            data;
+        let mut logger = SystemLogger::new(
+            log_config.as_ref().map(|config| &**config),
+            LogSource::Joints,
+            &mut diagnostics,
+        );
+
+        // collect all ComponentEvents for the PhysicsJoint storage
+        iterate_component_events(
+            &physics_joints,
+            self.physics_joints_reader_id.as_mut().unwrap(),
+            &mut self.physics_joint_events,
+            &mut logger,
+        );
+
+        // remove the constraints of removed and modified PhysicsJoints; the
+        // latter are recreated with their new values below
+        for id in (&self.physics_joint_events.modified | &self.physics_joint_events.removed).join()
+        {
+            logger.debug(
+                module_path!(),
+                format_args!("Removed or modified PhysicsJoint with id: {}", id),
+            );
+            remove_joint(
+                id,
+                JointOwner::Joint,
+                &mut physics,
+                &mut handles,
+                &mut logger,
+            );
+        }
+
+        for (entity, physics_joint) in (&entities, &physics_joints).join() {
+            let id = entity.id();
+            let other_handle = joint_body_handle(&entities, &handles, physics_joint.other);
+
+            match (other_handle, handles.body_handles.get(&id).cloned()) {
+                (Some(other_handle), Some(body_handle)) => {
+                    let report = self.physics_joint_events.inserted.contains(id)
+                        || self.physics_joint_events.modified.contains(id);
+                    if joint_vacant(id, JointOwner::Joint, &handles, report, &mut logger) {
+                        add_joint(
+                            id,
+                            physics_joint,
+                            other_handle,
+                            body_handle,
+                            &mut physics,
+                            &mut handles,
+                            &mut logger,
+                        );
+                    }
+                }
+                // the constraint must not outlive either of its bodies; it is
+                // recreated once both bodies exist again
+                _ => remove_joint(
+                    id,
+                    JointOwner::Joint,
+                    &mut physics,
+                    &mut handles,
+                    &mut logger,
+                ),
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("SyncJointsToPhysicsSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+
+        // register reader id for the PhysicsJoint storage
+        let mut physics_joint_storage: WriteStorage<PhysicsJoint<N>> = SystemData::fetch(&res);
+        self.physics_joints_reader_id = Some(physics_joint_storage.register_reader());
+    }
+}
+
+impl<N> Default for SyncJointsToPhysicsSystem<N>
+where
+    N: RealField,
+{
+    fn default() -> Self {
+        Self {
+            physics_joints_reader_id: None,
+            physics_joint_events: ComponentEvents::default(),
+            n_marker: PhantomData,
+        }
+    }
+}
+
+fn add_joint<N: RealField>(
+    id: Index,
+    physics_joint: &PhysicsJoint<N>,
+    other_handle: Option<BodyHandle>,
+    body_handle: BodyHandle,
+    physics: &mut Physics<N>,
+    handles: &mut PhysicsHandles,
+    logger: &mut SystemLogger,
+) {
+    let (other_part, other_position) = match other_handle {
+        Some(other_handle) => match physics.world.rigid_body(other_handle) {
+            Some(rigid_body) => (rigid_body.part_handle(), *rigid_body.position()),
+            None => return,
+        },
+        None => (BodyPartHandle::ground(), Isometry3::identity()),
+    };
+    let (body_part, body_position) = match physics.world.rigid_body(body_handle) {
+        Some(rigid_body) => (rigid_body.part_handle(), *rigid_body.position()),
+        None => return,
+    };
+
+    // the body is attached at the anchor in its current pose relative to the
+    // other body
+    let relative = body_position.inverse() * other_position;
+    let anchor = physics_joint.anchor;
+    let handle =
+        match physics_joint.kind {
+            JointKind::Ball => physics.world.add_constraint(BallConstraint::new(
+                other_part,
+                body_part,
+                anchor,
+                relative * anchor,
+            )),
+            JointKind::Fixed => {
+                let frame = Isometry3::translation(anchor.x, anchor.y, anchor.z);
+                physics.world.add_constraint(FixedConstraint::new(
+                    other_part,
+                    body_part,
+                    frame,
+                    relative * frame,
+                ))
+            }
+            JointKind::Prismatic { axis } => physics.world.add_constraint(
+                PrismaticConstraint::new(other_part, body_part, anchor, axis, relative * anchor),
+            ),
+            JointKind::Revolute { axis } => physics.world.add_constraint(RevoluteConstraint::new(
+                other_part,
+                body_part,
+                anchor,
+                axis,
+                relative * anchor,
+                relative.rotation * axis,
+            )),
+        };
+    handles
+        .joint_handles
+        .insert(id, (JointOwner::Joint, handle));
+
+    logger.log(
+        module_path!(),
+        Level::Info,
+        DiagnosticKind::JointInserted(id),
+        format_args!("Inserted joint to world with values: {:?}", physics_joint),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        diagnostics::{DiagnosticKind, PhysicsDiagnostics, PhysicsLogConfig},
+        handles::{JointOwner, PhysicsHandles},
+        joints::{HingedDoor, PhysicsJoint},
+        nalgebra::{self as na, Isometry3, Point3, Vector3},
+        nphysics::object::BodyStatus,
+        parameters::Gravity,
+        PhysicsBodyBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn swing_pendulum() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+        world.insert(Gravity(Vector3::<f32>::new(0.0, -9.81, 0.0)));
+
+        // a bob hanging sideways from a ball joint one unit away
+        let anchor = Point3::new(0.0, 2.0, 0.0);
+        let bob = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(1.0, 2.0, 0.0)))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .with(PhysicsJoint::ball(None, anchor))
+            .build();
+
+        for _ in 0..30 {
+            dispatcher.dispatch(&world);
+        }
+
+        // the bob swung down while keeping its distance to the anchor
+        let positions = world.read_storage::<SimplePosition<f32>>();
+        let position = Point3::from(positions.get(bob).unwrap().0.translation.vector);
+        assert!(position.y < 1.9);
+        assert!((na::distance(&position, &anchor) - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn keep_joint_of_first_component() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+        world.insert(PhysicsLogConfig {
+            structured: true,
+            ..PhysicsLogConfig::default()
+        });
+        let mut reader_id = world
+            .write_resource::<PhysicsDiagnostics>()
+            .register_reader();
+
+        // a body with two joint Components only keeps the constraint of one
+        let anchor = Point3::new(0.0, 2.0, 0.0);
+        let entity = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(1.0, 2.0, 0.0)))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .with(PhysicsJoint::ball(None, anchor))
+            .with(HingedDoor::new(anchor, Vector3::y_axis()))
+            .build();
+        dispatcher.dispatch(&world);
+
+        let ignored = world
+            .read_resource::<PhysicsDiagnostics>()
+            .read(&mut reader_id)
+            .filter_map(|diagnostic| match diagnostic.kind {
+                DiagnosticKind::JointConflict(id, owner) if id == entity.id() => Some(owner),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(ignored.len(), 1);
+        let joint_handle = world.read_resource::<PhysicsHandles>().joint_handle(entity);
+        assert!(joint_handle.is_some());
+
+        // removing the ignored Component keeps the constraint of the other one
+        if ignored[0] == JointOwner::HingedDoor {
+            world.write_storage::<HingedDoor<f32>>().remove(entity);
+        } else {
+            world.write_storage::<PhysicsJoint<f32>>().remove(entity);
+        }
+        dispatcher.dispatch(&world);
+        assert_eq!(
+            world.read_resource::<PhysicsHandles>().joint_handle(entity),
+            joint_handle
+        );
+    }
+}
@@ -1,8 +1,10 @@
 use std::marker::PhantomData;
 
-use specs::{Read, System, SystemData, World, WriteExpect};
+use log::Level;
+use specs::{Read, System, SystemData, World, Write, WriteExpect};
 
 use crate::{
+    diagnostics::{DiagnosticKind, LogSource, PhysicsDiagnostics, PhysicsLogConfig, SystemLogger},
     nalgebra::RealField,
     parameters::{Gravity, PhysicsIntegrationParameters, PhysicsProfilingEnabled},
     Physics,
@@ -19,19 +21,32 @@ impl<'s, N: RealField> System<'s> for SyncParametersToPhysicsSystem<N> {
         Option<Read<'s, Gravity<N>>>,
         Option<Read<'s, PhysicsProfilingEnabled>>,
         Option<Read<'s, PhysicsIntegrationParameters<N>>>,
+        Option<Read<'s, PhysicsLogConfig>>,
+        Write<'s, PhysicsDiagnostics>,
         WriteExpect<'s, Physics<N>>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (gravity, profiling, integration_params, mut physics) = data;
+        let (gravity, profiling, integration_params, log_config, mut diagnostics, mut physics) =
+            data;
+        let mut logger = SystemLogger::new(
+            log_config.as_ref().map(|config| &**config),
+            LogSource::Parameters,
+            &mut diagnostics,
+        );
 
         // if a Gravity resource exists, synchronise its values with the nphysics World
         if let Some(gravity) = gravity {
             if gravity.0 != *physics.gravity() {
-                info!(
-                    "Global physics gravity modified from {}, updating to {}.",
-                    physics.gravity(),
-                    gravity.0
+                logger.log(
+                    module_path!(),
+                    Level::Info,
+                    DiagnosticKind::GravityChanged,
+                    format_args!(
+                        "Global physics gravity modified from {}, updating to {}.",
+                        physics.gravity(),
+                        gravity.0
+                    ),
                 );
                 physics.world.set_gravity(gravity.0);
             }
@@ -40,10 +55,20 @@ impl<'s, N: RealField> System<'s> for SyncParametersToPhysicsSystem<N> {
         if let Some(enable_profiling) = profiling {
             if enable_profiling.0 != physics.performance_counters().enabled() {
                 if enable_profiling.0 {
-                    info!("Physics performance counters enabled.");
+                    logger.log(
+                        module_path!(),
+                        Level::Info,
+                        DiagnosticKind::ProfilingToggled(true),
+                        format_args!("Physics performance counters enabled."),
+                    );
                     physics.world.enable_performance_counters();
                 } else {
-                    info!("Physics performance counters disabled.");
+                    logger.log(
+                        module_path!(),
+                        Level::Info,
+                        DiagnosticKind::ProfilingToggled(false),
+                        format_args!("Physics performance counters disabled."),
+                    );
                     physics.world.disable_performance_counters();
                 }
             }
@@ -52,7 +77,12 @@ impl<'s, N: RealField> System<'s> for SyncParametersToPhysicsSystem<N> {
         if let Some(params) = integration_params {
             if *params != *physics.integration_parameters() {
                 params.apply(physics.world.integration_parameters_mut());
-                info!("Integration parameters have been updated.");
+                logger.log(
+                    module_path!(),
+                    Level::Info,
+                    DiagnosticKind::IntegrationParametersChanged,
+                    format_args!("Integration parameters have been updated."),
+                );
             }
         }
     }
@@ -79,12 +109,12 @@ where
 
 #[cfg(test)]
 mod tests {
-    use approx::assert_ulps_eq;
+    use approx::{assert_relative_eq, assert_ulps_eq};
     use specs::prelude::*;
 
     use crate::{
         nalgebra::Vector3,
-        parameters::Gravity,
+        parameters::{Gravity, PhysicsIntegrationParameters},
         systems::SyncParametersToPhysicsSystem,
         Physics,
     };
@@ -109,4 +139,31 @@ mod tests {
         assert_ulps_eq!(physics.world.gravity().y, 2.0);
         assert_ulps_eq!(physics.world.gravity().z, 3.0);
     }
+
+    #[test]
+    fn limit_depenetration_velocity() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncParametersToPhysicsSystem::<f32>::default(),
+                "sync_parameters_to_physics_system",
+                &[],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+
+        world.insert(PhysicsIntegrationParameters::<f32> {
+            max_depenetration_velocity: Some(0.3),
+            ..PhysicsIntegrationParameters::default()
+        });
+        dispatcher.dispatch(&world);
+
+        // the 3 position iterations of a step together correct at most 0.3 m/s
+        let physics = world.read_resource::<Physics<f32>>();
+        let timestep = physics.world.timestep();
+        assert_relative_eq!(
+            physics.integration_parameters().max_linear_correction,
+            0.1 * timestep
+        );
+    }
 }
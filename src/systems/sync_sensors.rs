@@ -0,0 +1,370 @@
+use std::marker::PhantomData;
+
+use log::Level;
+
+use specs::{
+    storage::ComponentEvent,
+    world::Index,
+    Entities,
+    Entity,
+    Join,
+    Read,
+    ReadStorage,
+    ReaderId,
+    System,
+    SystemData,
+    World,
+    Write,
+    WriteStorage,
+};
+
+use crate::{
+    bodies::Position,
+    colliders::{PhysicsCollider, ShapeCache},
+    diagnostics::{DiagnosticKind, LogSource, PhysicsDiagnostics, PhysicsLogConfig, SystemLogger},
+    events::{oriented_pair, ProximityEvent, ProximityEvents},
+    nalgebra::{Isometry3, RealField},
+    ncollide::world::GeometricQueryType,
+    sensors::SensorWorld,
+};
+
+use super::{iterate_component_events, rescan_components, ComponentEvents};
+
+/// The `SyncSensorsSystem` registers `PhysicsCollider`s in the `SensorWorld`
+/// instead of the physics `World` and updates their collision detection. It
+/// replaces all other physics `System`s for games that only need triggers:
+/// colliders follow their `Position` and overlaps are reported as
+/// `ProximityEvent`s.
+///
+/// # Examples
+/// ```
+/// use specs::DispatcherBuilder;
+/// use specs_physics::{systems::SyncSensorsSystem, SimplePosition};
+///
+/// let dispatcher = DispatcherBuilder::new()
+///     .with(
+///         SyncSensorsSystem::<f32, SimplePosition<f32>>::default(),
+///         "sync_sensors_system",
+///         &[],
+///     )
+///     .build();
+/// ```
+pub struct SyncSensorsSystem<N, P> {
+    positions_reader_id: Option<ReaderId<ComponentEvent>>,
+    physics_colliders_reader_id: Option<ReaderId<ComponentEvent>>,
+    position_events: ComponentEvents,
+    physics_collider_events: ComponentEvents,
+    rescan: bool,
+
+    n_marker: PhantomData<N>,
+    p_marker: PhantomData<P>,
+}
+
+impl<'s, N, P> System<'s> for SyncSensorsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, P>,
+        ReadStorage<'s, PhysicsCollider<N>>,
+        Option<Read<'s, PhysicsLogConfig>>,
+        Write<'s, PhysicsDiagnostics>,
+        Option<Write<'s, ShapeCache<N>>>,
+        Write<'s, SensorWorld<N>>,
+        Write<'s, ProximityEvents>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            positions,
+            physics_colliders,
+            log_config,
+            mut diagnostics,
+            mut shape_cache,
+            mut sensor_world,
+            mut proximity_events,
+        ) = data;
+        let mut logger = SystemLogger::new(
+            log_config.as_ref().map(|config| &**config),
+            LogSource::Colliders,
+            &mut diagnostics,
+        );
+
+        // collect all ComponentEvents for the Position storage
+        iterate_component_events(
+            &positions,
+            self.positions_reader_id.as_mut().unwrap(),
+            &mut self.position_events,
+            &mut logger,
+        );
+
+        // collect all ComponentEvents for the PhysicsCollider storage
+        iterate_component_events(
+            &physics_colliders,
+            self.physics_colliders_reader_id.as_mut().unwrap(),
+            &mut self.physics_collider_events,
+            &mut logger,
+        );
+
+        // PhysicsColliders inserted before the reader id was registered
+        if self.rescan {
+            self.rescan = false;
+            rescan_components(
+                &physics_colliders,
+                &mut self.physics_collider_events.inserted,
+                |id| sensor_world.handles.contains_key(&id),
+                &mut logger,
+            );
+        }
+
+        // handle removed events first, so that re-inserted PhysicsColliders are
+        // not removed again
+        for id in (&self.physics_collider_events.removed).join() {
+            remove_sensor(id, &mut sensor_world, &mut logger);
+        }
+
+        // handle inserted events
+        for (entity, position, physics_collider, _) in (
+            &entities,
+            &positions,
+            &physics_colliders,
+            &self.position_events.inserted | &self.physics_collider_events.inserted,
+        )
+            .join()
+        {
+            add_sensor(
+                entity,
+                position,
+                physics_collider,
+                &mut sensor_world,
+                shape_cache.as_mut().map(|shape_cache| &mut **shape_cache),
+                &mut logger,
+            );
+        }
+
+        // handle modified events; moved Positions only update the pose
+        for (entity, position, physics_collider, _) in (
+            &entities,
+            &positions,
+            &physics_colliders,
+            &self.position_events.modified | &self.physics_collider_events.modified,
+        )
+            .join()
+        {
+            let handle = match sensor_world.handle(entity) {
+                Some(handle) => handle,
+                None => continue,
+            };
+            let world = &mut sensor_world.world;
+            world.set_position(handle, sensor_position(position, physics_collider));
+            if self.physics_collider_events.modified.contains(entity.id()) {
+                world.set_collision_groups(handle, physics_collider.collision_groups);
+
+                logger.log(
+                    module_path!(),
+                    Level::Info,
+                    DiagnosticKind::ColliderUpdated(entity.id()),
+                    format_args!(
+                        "Updated sensor in world with values: {:?}",
+                        physics_collider
+                    ),
+                );
+            }
+        }
+
+        update_sensor_world(
+            &mut sensor_world,
+            &physics_colliders,
+            &mut proximity_events,
+            &mut logger,
+        );
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("SyncSensorsSystem.setup");
+        Self::SystemData::setup(res);
+
+        // register reader id for the Position storage
+        let mut position_storage: WriteStorage<P> = SystemData::fetch(&res);
+        self.positions_reader_id = Some(position_storage.register_reader());
+
+        // register reader id for the PhysicsCollider storage
+        let mut physics_collider_storage: WriteStorage<PhysicsCollider<N>> =
+            SystemData::fetch(&res);
+        self.physics_colliders_reader_id = Some(physics_collider_storage.register_reader());
+        self.rescan = true;
+    }
+}
+
+impl<N, P> Default for SyncSensorsSystem<N, P>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    fn default() -> Self {
+        Self {
+            positions_reader_id: None,
+            physics_colliders_reader_id: None,
+            position_events: ComponentEvents::default(),
+            physics_collider_events: ComponentEvents::default(),
+            rescan: false,
+            n_marker: PhantomData,
+            p_marker: PhantomData,
+        }
+    }
+}
+
+/// Runs the collision detection of the `SensorWorld` and maps the occurred
+/// ncollide events to our own `ProximityEvent`s.
+fn update_sensor_world<N: RealField>(
+    sensor_world: &mut SensorWorld<N>,
+    physics_colliders: &ReadStorage<PhysicsCollider<N>>,
+    proximity_events: &mut ProximityEvents,
+    logger: &mut SystemLogger,
+) {
+    sensor_world.world.update();
+    sensor_world.tick += 1;
+
+    let sensor_world = &*sensor_world;
+    proximity_events.iter_write(sensor_world.world.proximity_events().iter().filter_map(
+        |proximity_event| {
+            logger.debug(
+                module_path!(),
+                format_args!("Got ProximityEvent: {:?}", proximity_event),
+            );
+            let (collider1, collider2) = oriented_pair(
+                physics_colliders,
+                sensor_world.entity(proximity_event.collider1)?,
+                sensor_world.entity(proximity_event.collider2)?,
+            );
+            Some(ProximityEvent {
+                collider1,
+                collider2,
+                prev_status: proximity_event.prev_status,
+                new_status: proximity_event.new_status,
+                tick: sensor_world.tick,
+            })
+        },
+    ));
+}
+
+fn add_sensor<N, P>(
+    entity: Entity,
+    position: &P,
+    physics_collider: &PhysicsCollider<N>,
+    sensor_world: &mut SensorWorld<N>,
+    shape_cache: Option<&mut ShapeCache<N>>,
+    logger: &mut SystemLogger,
+) where
+    N: RealField,
+    P: Position<N>,
+{
+    let id = entity.id();
+
+    // a Position inserted for an existing PhysicsCollider replaces its sensor
+    if let Some(handle) = sensor_world.handles.remove(&id) {
+        sensor_world.world.remove(&[handle]);
+    }
+
+    let handle = sensor_world
+        .world
+        .add(
+            sensor_position(position, physics_collider),
+            physics_collider.shape_handle(shape_cache),
+            physics_collider.collision_groups,
+            GeometricQueryType::Proximity(physics_collider.linear_prediction),
+            entity,
+        )
+        .handle();
+    sensor_world.handles.insert(id, handle);
+
+    logger.log(
+        module_path!(),
+        Level::Info,
+        DiagnosticKind::ColliderInserted(id),
+        format_args!(
+            "Inserted sensor to world with values: {:?}",
+            physics_collider
+        ),
+    );
+}
+
+fn remove_sensor<N: RealField>(
+    id: Index,
+    sensor_world: &mut SensorWorld<N>,
+    logger: &mut SystemLogger,
+) {
+    if let Some(handle) = sensor_world.handles.remove(&id) {
+        sensor_world.world.remove(&[handle]);
+
+        logger.log(
+            module_path!(),
+            Level::Info,
+            DiagnosticKind::ColliderRemoved(id),
+            format_args!("Removed sensor from world with id: {}", id),
+        );
+    }
+}
+
+fn sensor_position<N, P>(position: &P, physics_collider: &PhysicsCollider<N>) -> Isometry3<N>
+where
+    N: RealField,
+    P: Position<N>,
+{
+    position.isometry() * physics_collider.offset_from_parent
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        colliders::Shape,
+        events::ProximityEvents,
+        nalgebra::Isometry3,
+        ncollide::query::Proximity,
+        sensors::SensorWorld,
+        systems::SyncSensorsSystem,
+        PhysicsColliderBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn report_overlap() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncSensorsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_sensors_system",
+                &[],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+        let mut reader_id = world.write_resource::<ProximityEvents>().register_reader();
+
+        let trigger = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 1.0 }).build())
+            .build();
+        let player = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(1.5, 0.0, 0.0)))
+            .with(PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 1.0 }).build())
+            .build();
+        dispatcher.dispatch(&world);
+
+        let proximity_events = world.read_resource::<ProximityEvents>();
+        let event = proximity_events.read(&mut reader_id).next().unwrap();
+        assert_eq!(event.new_status, Proximity::Intersecting);
+        assert_eq!(
+            world
+                .read_resource::<SensorWorld<f32>>()
+                .intersecting(trigger),
+            vec![player]
+        );
+    }
+}
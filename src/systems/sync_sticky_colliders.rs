@@ -0,0 +1,303 @@
+use std::marker::PhantomData;
+
+use log::Level;
+use specs::{
+    storage::ComponentEvent,
+    Entities,
+    Entity,
+    Join,
+    Read,
+    ReadStorage,
+    ReaderId,
+    System,
+    SystemData,
+    World,
+    Write,
+    WriteExpect,
+    WriteStorage,
+};
+
+use crate::{
+    colliders::PhysicsCollider,
+    diagnostics::{DiagnosticKind, LogSource, PhysicsDiagnostics, PhysicsLogConfig, SystemLogger},
+    events::{ContactEvent, ContactEvents, ContactType},
+    handles::{JointOwner, PhysicsHandles},
+    joints::StuckTo,
+    nalgebra::{Isometry3, RealField},
+    nphysics::{
+        joint::FixedConstraint,
+        object::{Body, BodyPartHandle, BodyStatus},
+    },
+    Physics,
+};
+
+use super::{iterate_component_events, remove_joint, ComponentEvents};
+
+/// The `SyncStickyCollidersSystem` fixes the body of a sticky
+/// `PhysicsCollider` to the first collider it starts touching and marks its
+/// `Entity` with `StuckTo`. The joint is removed again with the `StuckTo`
+/// `Component` or the `Entity` it is stuck to. As it reacts to the
+/// `ContactEvent`s of the last step, the system has to run after the
+/// `PhysicsStepperSystem`; it is not part of the default `Dispatcher`.
+pub struct SyncStickyCollidersSystem<N> {
+    contact_events_reader_id: Option<ReaderId<ContactEvent>>,
+    stuck_to_reader_id: Option<ReaderId<ComponentEvent>>,
+    stuck_to_events: ComponentEvents,
+
+    n_marker: PhantomData<N>,
+}
+
+impl<'s, N: RealField> System<'s> for SyncStickyCollidersSystem<N> {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, ContactEvents>,
+        ReadStorage<'s, PhysicsCollider<N>>,
+        WriteStorage<'s, StuckTo>,
+        Option<Read<'s, PhysicsLogConfig>>,
+        Write<'s, PhysicsDiagnostics>,
+        Write<'s, PhysicsHandles>,
+        WriteExpect<'s, Physics<N>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            contact_events,
+            physics_colliders,
+            mut stuck_to,
+            log_config,
+            mut diagnostics,
+            mut handles,
+            mut physics,
+        ) = data;
+        let mut logger = SystemLogger::new(
+            log_config.as_ref().map(|config| &**config),
+            LogSource::Joints,
+            &mut diagnostics,
+        );
+
+        // release the bodies whose StuckTo has been removed
+        iterate_component_events(
+            &stuck_to,
+            self.stuck_to_reader_id.as_mut().unwrap(),
+            &mut self.stuck_to_events,
+            &mut logger,
+        );
+        for id in (&self.stuck_to_events.removed).join() {
+            logger.debug(
+                module_path!(),
+                format_args!("Removed StuckTo with id: {}", id),
+            );
+            remove_joint(
+                id,
+                JointOwner::Sticky,
+                &mut physics,
+                &mut handles,
+                &mut logger,
+            );
+        }
+
+        // release the bodies stuck to deleted Entities
+        let orphaned = (&entities, &stuck_to)
+            .join()
+            .filter(|(_, stuck_to)| !entities.is_alive(stuck_to.0))
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+        for entity in orphaned {
+            remove_joint(
+                entity.id(),
+                JointOwner::Sticky,
+                &mut physics,
+                &mut handles,
+                &mut logger,
+            );
+            stuck_to.remove(entity);
+        }
+
+        for contact_event in contact_events.read(self.contact_events_reader_id.as_mut().unwrap()) {
+            if let ContactType::Stopped = contact_event.contact_type {
+                continue;
+            }
+
+            let pairs = [
+                (contact_event.collider1, contact_event.collider2),
+                (contact_event.collider2, contact_event.collider1),
+            ];
+            for &(entity, target) in pairs.iter() {
+                let is_sticky = physics_colliders
+                    .get(entity)
+                    .map_or(false, |physics_collider| physics_collider.sticky);
+                if !is_sticky
+                    || stuck_to.contains(entity)
+                    || handles.joint_handles.contains_key(&entity.id())
+                {
+                    continue;
+                }
+
+                if stick(entity, target, &mut physics, &mut handles, &mut logger) {
+                    stuck_to
+                        .insert(entity, StuckTo(target))
+                        .expect("Failed to insert StuckTo component");
+                }
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("SyncStickyCollidersSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+
+        // register reader ids for the ContactEvents and the StuckTo storage
+        self.contact_events_reader_id = Some(res.fetch_mut::<ContactEvents>().register_reader());
+        let mut stuck_to_storage: WriteStorage<StuckTo> = SystemData::fetch(&res);
+        self.stuck_to_reader_id = Some(stuck_to_storage.register_reader());
+    }
+}
+
+impl<N> Default for SyncStickyCollidersSystem<N>
+where
+    N: RealField,
+{
+    fn default() -> Self {
+        Self {
+            contact_events_reader_id: None,
+            stuck_to_reader_id: None,
+            stuck_to_events: ComponentEvents::default(),
+            n_marker: PhantomData,
+        }
+    }
+}
+
+/// Fixes the dynamic body of the `entity` to the body of the `target` in
+/// their current poses. Returns whether the joint has been created.
+fn stick<N: RealField>(
+    entity: Entity,
+    target: Entity,
+    physics: &mut Physics<N>,
+    handles: &mut PhysicsHandles,
+    logger: &mut SystemLogger,
+) -> bool {
+    let id = entity.id();
+    let (body_part, position) = match handles
+        .body_handle(entity)
+        .and_then(|handle| physics.world.rigid_body(handle))
+    {
+        Some(rigid_body) if rigid_body.status() == BodyStatus::Dynamic => {
+            (rigid_body.part_handle(), *rigid_body.position())
+        }
+        _ => return false,
+    };
+    let (target_part, target_position) = match handles
+        .body_handle(target)
+        .and_then(|handle| physics.world.rigid_body(handle))
+    {
+        Some(rigid_body) => (rigid_body.part_handle(), *rigid_body.position()),
+        None => (BodyPartHandle::ground(), Isometry3::identity()),
+    };
+
+    // anchor the joint at the origin of the stuck body
+    let handle = physics.world.add_constraint(FixedConstraint::new(
+        target_part,
+        body_part,
+        target_position.inverse() * position,
+        Isometry3::identity(),
+    ));
+    handles
+        .joint_handles
+        .insert(id, (JointOwner::Sticky, handle));
+
+    logger.log(
+        module_path!(),
+        Level::Info,
+        DiagnosticKind::JointInserted(id),
+        format_args!("Stuck body with id: {} to {:?}", id, target),
+    );
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::{
+        colliders::Shape,
+        joints::StuckTo,
+        nalgebra::{Isometry3, Vector3},
+        nphysics::{algebra::Velocity3, object::BodyStatus},
+        systems::{
+            PhysicsStepperSystem,
+            SyncBodiesToPhysicsSystem,
+            SyncCollidersToPhysicsSystem,
+            SyncStickyCollidersSystem,
+        },
+        PhysicsBodyBuilder,
+        PhysicsColliderBuilder,
+        SimplePosition,
+    };
+
+    #[test]
+    fn arrow_sticks_to_wall() {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(
+                SyncBodiesToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_bodies_to_physics_system",
+                &[],
+            )
+            .with(
+                SyncCollidersToPhysicsSystem::<f32, SimplePosition<f32>>::default(),
+                "sync_colliders_to_physics_system",
+                &["sync_bodies_to_physics_system"],
+            )
+            .with(
+                PhysicsStepperSystem::<f32>::default(),
+                "physics_stepper_system",
+                &["sync_colliders_to_physics_system"],
+            )
+            .with(
+                SyncStickyCollidersSystem::<f32>::default(),
+                "sync_sticky_colliders_system",
+                &["physics_stepper_system"],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+
+        // a static wall without a PhysicsBody and an arrow flying towards it
+        let wall = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Cuboid {
+                    half_extents: Vector3::new(0.5, 5.0, 5.0),
+                })
+                .build(),
+            )
+            .build();
+        let arrow = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(1.0, 0.0, 0.0)))
+            .with(
+                PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+                    .gravity_enabled(false)
+                    .velocity(Velocity3::linear(-10.0, 0.0, 0.0))
+                    .build(),
+            )
+            .with(
+                PhysicsColliderBuilder::<f32>::from(Shape::Ball { radius: 0.1 })
+                    .sticky(true)
+                    .build(),
+            )
+            .build();
+        for _ in 0..10 {
+            dispatcher.dispatch(&world);
+        }
+
+        assert_eq!(
+            world.read_storage::<StuckTo>().get(arrow),
+            Some(&StuckTo(wall))
+        );
+    }
+}
@@ -0,0 +1,252 @@
+use std::marker::PhantomData;
+
+use log::Level;
+
+use specs::{
+    storage::ComponentEvent,
+    world::Index,
+    Entities,
+    Join,
+    Read,
+    ReadStorage,
+    ReaderId,
+    System,
+    SystemData,
+    World,
+    Write,
+    WriteExpect,
+    WriteStorage,
+};
+
+use crate::{
+    diagnostics::{DiagnosticKind, LogSource, PhysicsDiagnostics, PhysicsLogConfig, SystemLogger},
+    handles::{JointOwner, PhysicsHandles},
+    joints::WheelJoint,
+    nalgebra::{Point3, RealField, Vector3},
+    nphysics::{
+        algebra::Force3,
+        joint::PinSlotConstraint,
+        object::{BodyHandle, BodyPart},
+    },
+    Physics,
+};
+
+use super::{iterate_component_events, joint_vacant, remove_joint, ComponentEvents};
+
+/// The `SyncWheelJointsToPhysicsSystem` creates the constraints of
+/// `WheelJoint`s in the physics `World` and applies their suspension and motor
+/// forces. It has to run after the `SyncBodiesToPhysicsSystem`, as both bodies
+/// of a `WheelJoint` have to exist before its constraint can be created.
+pub struct SyncWheelJointsToPhysicsSystem<N> {
+    wheel_joints_reader_id: Option<ReaderId<ComponentEvent>>,
+    wheel_joint_events: ComponentEvents,
+
+    n_marker: PhantomData<N>,
+}
+
+impl<'s, N: RealField> System<'s> for SyncWheelJointsToPhysicsSystem<N> {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, WheelJoint<N>>,
+        Option<Read<'s, PhysicsLogConfig>>,
+        Write<'s, PhysicsDiagnostics>,
+        WriteExpect<'s, Physics<N>>,
+        Write<'s, PhysicsHandles>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, wheel_joints, log_config, mut diagnostics, mut physics, mut handles) = data;
+        let mut logger = SystemLogger::new(
+            log_config.as_ref().map(|config| &**config),
+            LogSource::Joints,
+            &mut diagnostics,
+        );
+
+        // collect all ComponentEvents for the WheelJoint storage
+        iterate_component_events(
+            &wheel_joints,
+            self.wheel_joints_reader_id.as_mut().unwrap(),
+            &mut self.wheel_joint_events,
+            &mut logger,
+        );
+
+        // remove the constraints of removed and modified WheelJoints; the latter
+        // are recreated with their new values below
+        for id in (&self.wheel_joint_events.modified | &self.wheel_joint_events.removed).join() {
+            logger.debug(
+                module_path!(),
+                format_args!("Removed or modified WheelJoint with id: {}", id),
+            );
+            remove_joint(
+                id,
+                JointOwner::WheelJoint,
+                &mut physics,
+                &mut handles,
+                &mut logger,
+            );
+        }
+
+        for (entity, wheel_joint) in (&entities, &wheel_joints).join() {
+            let id = entity.id();
+            let body_handles = if entities.is_alive(wheel_joint.chassis) {
+                match (
+                    handles.body_handles.get(&wheel_joint.chassis.id()),
+                    handles.body_handles.get(&id),
+                ) {
+                    (Some(chassis_handle), Some(wheel_handle)) => {
+                        Some((*chassis_handle, *wheel_handle))
+                    }
+                    _ => None,
+                }
+            } else {
+                None
+            };
+
+            match body_handles {
+                Some((chassis_handle, wheel_handle)) => {
+                    let report = self.wheel_joint_events.inserted.contains(id)
+                        || self.wheel_joint_events.modified.contains(id);
+                    if joint_vacant(id, JointOwner::WheelJoint, &handles, report, &mut logger) {
+                        add_wheel_joint(
+                            id,
+                            wheel_joint,
+                            chassis_handle,
+                            wheel_handle,
+                            &mut physics,
+                            &mut handles,
+                            &mut logger,
+                        );
+                    }
+                    apply_wheel_forces(wheel_joint, chassis_handle, wheel_handle, &mut physics);
+                }
+                // the constraint must not outlive either of its bodies; it is
+                // recreated once both bodies exist again
+                None => remove_joint(
+                    id,
+                    JointOwner::WheelJoint,
+                    &mut physics,
+                    &mut handles,
+                    &mut logger,
+                ),
+            }
+        }
+    }
+
+    fn setup(&mut self, res: &mut World) {
+        info!("SyncWheelJointsToPhysicsSystem.setup");
+        Self::SystemData::setup(res);
+
+        // initialise required resources
+        res.entry::<Physics<N>>().or_insert_with(Physics::default);
+
+        // register reader id for the WheelJoint storage
+        let mut wheel_joint_storage: WriteStorage<WheelJoint<N>> = SystemData::fetch(&res);
+        self.wheel_joints_reader_id = Some(wheel_joint_storage.register_reader());
+    }
+}
+
+impl<N> Default for SyncWheelJointsToPhysicsSystem<N>
+where
+    N: RealField,
+{
+    fn default() -> Self {
+        Self {
+            wheel_joints_reader_id: None,
+            wheel_joint_events: ComponentEvents::default(),
+            n_marker: PhantomData,
+        }
+    }
+}
+
+fn add_wheel_joint<N: RealField>(
+    id: Index,
+    wheel_joint: &WheelJoint<N>,
+    chassis_handle: BodyHandle,
+    wheel_handle: BodyHandle,
+    physics: &mut Physics<N>,
+    handles: &mut PhysicsHandles,
+    logger: &mut SystemLogger,
+) {
+    let (chassis_part, chassis_rotation) = match physics.world.rigid_body(chassis_handle) {
+        Some(rigid_body) => (rigid_body.part_handle(), rigid_body.position().rotation),
+        None => return,
+    };
+    let (wheel_part, wheel_rotation) = match physics.world.rigid_body(wheel_handle) {
+        Some(rigid_body) => (rigid_body.part_handle(), rigid_body.position().rotation),
+        None => return,
+    };
+
+    // the axle is fixed within the wheel body at the orientation the wheel has
+    // when the constraint is created
+    let wheel_axle = wheel_rotation.inverse() * (chassis_rotation * wheel_joint.axle);
+
+    let constraint = PinSlotConstraint::new(
+        chassis_part,
+        wheel_part,
+        wheel_joint.anchor,
+        wheel_joint.suspension_axis,
+        wheel_joint.axle,
+        Point3::origin(),
+        wheel_axle,
+    );
+    let handle = physics.world.add_constraint(constraint);
+    handles
+        .joint_handles
+        .insert(id, (JointOwner::WheelJoint, handle));
+
+    logger.log(
+        module_path!(),
+        Level::Info,
+        DiagnosticKind::JointInserted(id),
+        format_args!(
+            "Inserted wheel joint to world with values: {:?}",
+            wheel_joint
+        ),
+    );
+}
+
+fn apply_wheel_forces<N: RealField>(
+    wheel_joint: &WheelJoint<N>,
+    chassis_handle: BodyHandle,
+    wheel_handle: BodyHandle,
+    physics: &mut Physics<N>,
+) {
+    let (chassis_position, chassis_velocity, chassis_center) =
+        match physics.world.rigid_body(chassis_handle) {
+            Some(rigid_body) => (
+                *rigid_body.position(),
+                *rigid_body.velocity(),
+                rigid_body.center_of_mass(),
+            ),
+            None => return,
+        };
+    let (wheel_position, wheel_velocity) = match physics.world.rigid_body(wheel_handle) {
+        Some(rigid_body) => (*rigid_body.position(), *rigid_body.velocity()),
+        None => return,
+    };
+
+    let anchor = chassis_position * wheel_joint.anchor;
+    let lever = anchor - chassis_center;
+    let suspension_axis = (chassis_position.rotation * wheel_joint.suspension_axis).into_inner();
+    let axle = (chassis_position.rotation * wheel_joint.axle).into_inner();
+
+    // suspension spring between the anchor and the wheel along the suspension axis
+    let anchor_velocity = chassis_velocity.linear + chassis_velocity.angular.cross(&lever);
+    let offset = (wheel_position.translation.vector - anchor.coords).dot(&suspension_axis);
+    let speed = (wheel_velocity.linear - anchor_velocity).dot(&suspension_axis);
+    let force = suspension_axis * wheel_joint.suspension_force(offset, speed);
+
+    // motor torque around the axle, relative to the rotation of the chassis
+    let torque = wheel_joint.motor.map_or_else(Vector3::zeros, |motor| {
+        axle * motor.torque((wheel_velocity.angular - chassis_velocity.angular).dot(&axle))
+    });
+
+    physics.apply_frame_force(wheel_handle, &Force3::new(force, torque));
+
+    // the chassis receives the opposite force at the anchor and the reaction
+    // torque of the motor
+    physics.apply_frame_force(
+        chassis_handle,
+        &Force3::new(-force, lever.cross(&-force) - torque),
+    );
+}
@@ -0,0 +1,418 @@
+//! # Terrain module
+//! Streaming of large terrains. `TerrainChunks` splits one big height field
+//! into a grid of chunk colliders, which can be loaded and unloaded
+//! independently as the player moves around. Neighbouring chunks share the
+//! heights along their borders and set `smooth_edges`, so the
+//! `ApplyInternalEdgeCorrectionSystem` stitches their seams and bodies slide
+//! across them as if the terrain was a single height field.
+//! `Physics::modify_heightfield` and `TerrainChunks::modify` deform height
+//! fields at runtime, e.g. for craters and digging.
+
+use std::{collections::HashMap, error::Error, fmt};
+
+use specs::{Builder, Entity, World, WorldExt};
+
+use crate::{
+    bodies::Position,
+    colliders::{PhysicsCollider, Shape},
+    nalgebra::{self as na, DMatrix, Isometry3, Point3, RealField, Vector3},
+    nphysics::object::BodyStatus,
+    Physics,
+    PhysicsBodyBuilder,
+    PhysicsColliderBuilder,
+};
+
+/// The reasons modifying the heights of a height field can fail.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HeightFieldError {
+    /// The `Entity` has no `PhysicsCollider` with a `Shape::HeightField`.
+    NoHeightField(Entity),
+    /// The modified region exceeds the heights of the height field.
+    OutOfBounds,
+}
+
+impl fmt::Display for HeightFieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeightFieldError::NoHeightField(entity) => {
+                write!(f, "no height field collider on {:?}", entity)
+            }
+            HeightFieldError::OutOfBounds => write!(f, "region out of bounds"),
+        }
+    }
+}
+
+impl Error for HeightFieldError {}
+
+/// Overwrites the `target` heights starting at the row and column of the
+/// `region` with the given `heights`.
+pub(crate) fn write_heights<N: RealField>(
+    target: &mut DMatrix<N>,
+    region: (usize, usize),
+    heights: &DMatrix<N>,
+) -> Result<(), HeightFieldError> {
+    if region.0 + heights.nrows() > target.nrows() || region.1 + heights.ncols() > target.ncols() {
+        return Err(HeightFieldError::OutOfBounds);
+    }
+    target.slice_mut(region, heights.shape()).copy_from(heights);
+    Ok(())
+}
+
+/// A height field split into square chunks of `chunk_cells` cells per side.
+/// The rows of the `heights` run along the z axis and the columns along the
+/// x axis, starting at the `origin` corner of the terrain.
+///
+/// # Example
+///
+/// ```rust
+/// use specs::{World, WorldExt};
+/// use specs_physics::{
+///     nalgebra::{DMatrix, Point3},
+///     terrain::TerrainChunks,
+///     SimplePosition,
+/// };
+///
+/// let mut world = World::new();
+/// let mut dispatcher = specs_physics::physics_dispatcher::<f32, SimplePosition<f32>>();
+/// dispatcher.setup(&mut world);
+///
+/// let heights = DMatrix::from_fn(65, 65, |row, col| ((row + col) as f32 * 0.1).sin());
+/// let mut terrain = TerrainChunks::new(heights, 1.0, 2.0, 16, Point3::origin());
+///
+/// // keep the chunks around the player loaded
+/// terrain.stream(&mut world, &Point3::new(8.0, 0.0, 8.0), 20.0, SimplePosition);
+/// assert_eq!(terrain.loaded().count(), 4);
+/// ```
+pub struct TerrainChunks<N: RealField> {
+    heights: DMatrix<N>,
+    cell_size: N,
+    height_scale: N,
+    chunk_cells: usize,
+    origin: Point3<N>,
+    loaded: HashMap<(usize, usize), Entity>,
+}
+
+impl<N: RealField> TerrainChunks<N> {
+    /// Creates the chunks of a terrain with the given `heights`, spaced
+    /// `cell_size` apart and multiplied by the `height_scale`.
+    pub fn new(
+        heights: DMatrix<N>,
+        cell_size: N,
+        height_scale: N,
+        chunk_cells: usize,
+        origin: Point3<N>,
+    ) -> Self {
+        assert!(
+            heights.nrows() >= 2 && heights.ncols() >= 2,
+            "A terrain needs at least 2x2 heights."
+        );
+        assert!(chunk_cells > 0, "A chunk needs at least one cell.");
+        Self {
+            heights,
+            cell_size,
+            height_scale,
+            chunk_cells,
+            origin,
+            loaded: HashMap::new(),
+        }
+    }
+
+    pub fn heights(&self) -> &DMatrix<N> {
+        &self.heights
+    }
+
+    /// The number of chunk rows along the z axis and columns along the x
+    /// axis.
+    pub fn chunk_count(&self) -> (usize, usize) {
+        let count = |vertices: usize| (vertices - 1 + self.chunk_cells - 1) / self.chunk_cells;
+        (count(self.heights.nrows()), count(self.heights.ncols()))
+    }
+
+    /// Returns the pose and the `Shape::HeightField` of the chunk in the
+    /// given row and column. The chunk includes the heights along its
+    /// borders, which it shares with its neighbours.
+    pub fn chunk(&self, row: usize, col: usize) -> (Isometry3<N>, Shape<N>) {
+        let (first_row, first_col, rows, cols) = self.cells(row, col);
+        let heights = self
+            .heights
+            .slice((first_row, first_col), (rows + 1, cols + 1))
+            .into_owned();
+
+        // height fields are centered on their collider
+        let half: N = na::convert(0.5);
+        let width = na::convert::<f64, N>(cols as f64) * self.cell_size;
+        let depth = na::convert::<f64, N>(rows as f64) * self.cell_size;
+        let corner = Vector3::new(
+            na::convert::<f64, N>(first_col as f64) * self.cell_size,
+            N::zero(),
+            na::convert::<f64, N>(first_row as f64) * self.cell_size,
+        );
+        let center = self.origin + corner + Vector3::new(width * half, N::zero(), depth * half);
+
+        (
+            Isometry3::translation(center.x, center.y, center.z),
+            Shape::HeightField {
+                heights,
+                scale: Vector3::new(width, self.height_scale, depth),
+            },
+        )
+    }
+
+    /// The `Entity` of the chunk in the given row and column, if loaded.
+    pub fn get(&self, row: usize, col: usize) -> Option<Entity> {
+        self.loaded.get(&(row, col)).cloned()
+    }
+
+    /// Iterates the rows and columns of the loaded chunks and their
+    /// `Entity`s.
+    pub fn loaded(&self) -> impl Iterator<Item = ((usize, usize), Entity)> + '_ {
+        self.loaded.iter().map(|(chunk, entity)| (*chunk, *entity))
+    }
+
+    /// Creates the static `Entity` of the chunk in the given row and column,
+    /// unless it is loaded already. Returns the `Entity` of the chunk.
+    pub fn load<P, F>(&mut self, world: &mut World, row: usize, col: usize, position: F) -> Entity
+    where
+        P: Position<N>,
+        F: Fn(Isometry3<N>) -> P,
+    {
+        if let Some(entity) = self.get(row, col) {
+            return entity;
+        }
+
+        let (isometry, shape) = self.chunk(row, col);
+        let entity = world
+            .create_entity()
+            .with(position(isometry))
+            .with(PhysicsBodyBuilder::<N>::from(BodyStatus::Static).build())
+            .with(
+                PhysicsColliderBuilder::<N>::from(shape)
+                    .smooth_edges(true)
+                    .build(),
+            )
+            .build();
+        self.loaded.insert((row, col), entity);
+        entity
+    }
+
+    /// Deletes the `Entity` of the chunk in the given row and column. Returns
+    /// whether the chunk was loaded.
+    pub fn unload(&mut self, world: &mut World, row: usize, col: usize) -> bool {
+        match self.loaded.remove(&(row, col)) {
+            Some(entity) => {
+                if let Err(error) = world.delete_entity(entity) {
+                    warn!("Failed to unload terrain chunk: {}", error);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Overwrites the heights of the terrain starting at the row and column of
+    /// the `region` with the given `heights`, and applies them to every loaded
+    /// chunk they overlap, including the borders shared by neighbouring
+    /// chunks. Unloaded chunks pick the heights up once they are loaded.
+    pub fn modify(
+        &mut self,
+        world: &mut World,
+        region: (usize, usize),
+        heights: &DMatrix<N>,
+    ) -> Result<(), HeightFieldError> {
+        write_heights(&mut self.heights, region, heights)?;
+
+        let mut physics = world.write_resource::<Physics<N>>();
+        let mut physics_colliders = world.write_storage::<PhysicsCollider<N>>();
+        for (&(row, col), entity) in &self.loaded {
+            // the overlap of the region with the heights of the chunk
+            let (first_row, first_col, rows, cols) = self.cells(row, col);
+            let top = region.0.max(first_row);
+            let left = region.1.max(first_col);
+            let bottom = (region.0 + heights.nrows()).min(first_row + rows + 1);
+            let right = (region.1 + heights.ncols()).min(first_col + cols + 1);
+            if top >= bottom || left >= right {
+                continue;
+            }
+
+            let overlap = heights
+                .slice(
+                    (top - region.0, left - region.1),
+                    (bottom - top, right - left),
+                )
+                .into_owned();
+            physics.modify_heightfield(
+                &mut physics_colliders,
+                *entity,
+                (top - first_row, left - first_col),
+                &overlap,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Loads the chunks within the `radius` of the `focus`, measured in the
+    /// xz plane, and unloads all others.
+    pub fn stream<P, F>(&mut self, world: &mut World, focus: &Point3<N>, radius: N, position: F)
+    where
+        P: Position<N>,
+        F: Fn(Isometry3<N>) -> P,
+    {
+        let (rows, cols) = self.chunk_count();
+        for row in 0..rows {
+            for col in 0..cols {
+                if self.distance(row, col, focus) <= radius {
+                    self.load(world, row, col, &position);
+                } else {
+                    self.unload(world, row, col);
+                }
+            }
+        }
+    }
+
+    /// The first row and column of the cells of a chunk and its number of
+    /// cell rows and columns; chunks along the far borders may be smaller.
+    fn cells(&self, row: usize, col: usize) -> (usize, usize, usize, usize) {
+        let (chunk_rows, chunk_cols) = self.chunk_count();
+        assert!(
+            row < chunk_rows && col < chunk_cols,
+            "Invalid terrain chunk {}x{}.",
+            row,
+            col
+        );
+        let first_row = row * self.chunk_cells;
+        let first_col = col * self.chunk_cells;
+        (
+            first_row,
+            first_col,
+            self.chunk_cells.min(self.heights.nrows() - 1 - first_row),
+            self.chunk_cells.min(self.heights.ncols() - 1 - first_col),
+        )
+    }
+
+    /// The distance of the `focus` to the bounds of a chunk in the xz plane.
+    fn distance(&self, row: usize, col: usize, focus: &Point3<N>) -> N {
+        let (first_row, first_col, rows, cols) = self.cells(row, col);
+        let axis = |first: usize, cells: usize, origin: N, focus: N| {
+            let min = origin + na::convert::<f64, N>(first as f64) * self.cell_size;
+            let max = min + na::convert::<f64, N>(cells as f64) * self.cell_size;
+            if focus < min {
+                min - focus
+            } else if focus > max {
+                focus - max
+            } else {
+                N::zero()
+            }
+        };
+        let x = axis(first_col, cols, self.origin.x, focus.x);
+        let z = axis(first_row, rows, self.origin.z, focus.z);
+        (x * x + z * z).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use super::TerrainChunks;
+    use crate::{
+        colliders::{PhysicsCollider, Shape},
+        nalgebra::{DMatrix, Point3, Vector3},
+        ncollide::shape::HeightField,
+        Physics,
+        SimplePosition,
+    };
+
+    #[test]
+    fn split_and_stream_chunks() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+
+        // 5x7 heights make 4x6 cells, split into 2x3 chunks of 2x2 cells
+        let heights = DMatrix::from_fn(5, 7, |row, col| (row * 7 + col) as f32);
+        let mut terrain = TerrainChunks::new(heights, 2.0, 1.0, 2, Point3::origin());
+        assert_eq!(terrain.chunk_count(), (2, 3));
+
+        // neighbouring chunks share the heights along their border
+        let (isometry, shape) = terrain.chunk(1, 1);
+        assert_eq!(isometry.translation.vector, Vector3::new(6.0, 0.0, 6.0));
+        match (shape, terrain.chunk(1, 2).1) {
+            (
+                Shape::HeightField { heights, scale },
+                Shape::HeightField {
+                    heights: neighbour, ..
+                },
+            ) => {
+                assert_eq!(heights.shape(), (3, 3));
+                assert_eq!(scale, Vector3::new(4.0, 1.0, 4.0));
+                assert_eq!(heights.column(2), neighbour.column(0));
+            }
+            _ => panic!("expected height fields"),
+        }
+
+        terrain.stream(&mut world, &Point3::new(1.0, 0.0, 1.0), 1.0, SimplePosition);
+        world.maintain();
+        assert_eq!(terrain.loaded().count(), 1);
+        let chunk = terrain.get(0, 0).unwrap();
+        assert!(
+            world
+                .read_storage::<PhysicsCollider<f32>>()
+                .get(chunk)
+                .unwrap()
+                .smooth_edges
+        );
+
+        terrain.stream(
+            &mut world,
+            &Point3::new(11.0, 0.0, 7.0),
+            3.0,
+            SimplePosition,
+        );
+        world.maintain();
+        assert!(!world.is_alive(chunk));
+        let mut loaded = terrain.loaded().map(|(chunk, _)| chunk).collect::<Vec<_>>();
+        loaded.sort();
+        assert_eq!(loaded, vec![(0, 2), (1, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn deform_across_seam() {
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+
+        let mut terrain =
+            TerrainChunks::new(DMatrix::<f32>::zeros(5, 5), 1.0, 1.0, 2, Point3::origin());
+        terrain.stream(&mut world, &Point3::origin(), 100.0, SimplePosition);
+        dispatcher.dispatch(&world);
+
+        // a crater around the vertex shared by all four chunks
+        let crater = DMatrix::from_element(3, 3, -1.0);
+        terrain.modify(&mut world, (1, 1), &crater).unwrap();
+        assert_eq!(terrain.heights()[(2, 2)], -1.0);
+
+        let physics = world.read_resource::<Physics<f32>>();
+        let physics_colliders = world.read_storage::<PhysicsCollider<f32>>();
+        for &(row, col) in &[(0, 0), (0, 1), (1, 0), (1, 1)] {
+            let physics_collider = physics_colliders
+                .get(terrain.get(row, col).unwrap())
+                .unwrap();
+            let (corner_row, corner_col) = (2 - row * 2, 2 - col * 2);
+            match &physics_collider.shape {
+                Shape::HeightField { heights, .. } => {
+                    assert_eq!(heights[(corner_row, corner_col)], -1.0)
+                }
+                _ => panic!("expected a height field"),
+            }
+
+            let collider = physics
+                .world
+                .collider(physics_collider.handle.unwrap())
+                .unwrap();
+            let heightfield = collider.shape().as_shape::<HeightField<f32>>().unwrap();
+            assert_eq!(heightfield.heights()[(corner_row, corner_col)], -1.0);
+        }
+
+        assert!(terrain.modify(&mut world, (4, 4), &crater).is_err());
+    }
+}
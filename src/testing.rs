@@ -0,0 +1,121 @@
+//! # Testing module
+//! Assertions on the physics state for the integration tests of downstream
+//! crates. The `assert_body_at!`, `assert_in_contact!` and
+//! `assert_not_in_contact!` macros are built on the public `Physics` queries
+//! and report the actual state when they fail.
+
+use specs::{Entity, World, WorldExt};
+
+use crate::{
+    handles::PhysicsHandles,
+    nalgebra::{Point3, RealField},
+    Physics,
+};
+
+/// Asserts that the body of the `Entity` is within `eps` of the given
+/// position in the physics `World`.
+///
+/// # Example
+///
+/// ```rust
+/// use specs::{Builder, World, WorldExt};
+/// use specs_physics::{
+///     assert_body_at,
+///     nalgebra::{Isometry3, Point3},
+///     nphysics::object::BodyStatus,
+///     PhysicsBodyBuilder,
+///     SimplePosition,
+/// };
+///
+/// let mut world = World::new();
+/// let mut dispatcher = specs_physics::physics_dispatcher::<f32, SimplePosition<f32>>();
+/// dispatcher.setup(&mut world);
+///
+/// let anchor = world
+///     .create_entity()
+///     .with(SimplePosition::<f32>(Isometry3::translation(1.0, 2.0, 3.0)))
+///     .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Static).build())
+///     .build();
+/// dispatcher.dispatch(&world);
+///
+/// assert_body_at!(world, anchor, Point3::new(1.0, 2.0, 3.0), 1.0e-5);
+/// ```
+#[macro_export]
+macro_rules! assert_body_at {
+    ($world:expr, $entity:expr, $position:expr, $eps:expr) => {
+        $crate::testing::assert_body_at(&$world, $entity, &$position, $eps)
+    };
+}
+
+/// Asserts that the colliders of both `Entity`s touch each other. The scalar
+/// type of the `Physics` defaults to `f32` and can be passed as the last
+/// argument.
+#[macro_export]
+macro_rules! assert_in_contact {
+    ($world:expr, $entity1:expr, $entity2:expr) => {
+        $crate::assert_in_contact!($world, $entity1, $entity2, f32)
+    };
+    ($world:expr, $entity1:expr, $entity2:expr, $n:ty) => {
+        $crate::testing::assert_contact::<$n>(&$world, $entity1, $entity2, true)
+    };
+}
+
+/// Asserts that the colliders of both `Entity`s do not touch each other. The
+/// scalar type of the `Physics` defaults to `f32` and can be passed as the
+/// last argument.
+#[macro_export]
+macro_rules! assert_not_in_contact {
+    ($world:expr, $entity1:expr, $entity2:expr) => {
+        $crate::assert_not_in_contact!($world, $entity1, $entity2, f32)
+    };
+    ($world:expr, $entity1:expr, $entity2:expr, $n:ty) => {
+        $crate::testing::assert_contact::<$n>(&$world, $entity1, $entity2, false)
+    };
+}
+
+/// Implementation of `assert_body_at!`.
+pub fn assert_body_at<N: RealField>(world: &World, entity: Entity, position: &Point3<N>, eps: N) {
+    let physics = world.fetch::<Physics<N>>();
+    let handles = world.fetch::<PhysicsHandles>();
+    let actual = match physics.body_position(&handles, entity) {
+        Some(isometry) => Point3::from(isometry.translation.vector),
+        None => panic!("{:?} has no body in the physics World", entity),
+    };
+
+    let distance = (actual - position).norm();
+    assert!(
+        distance <= eps,
+        "body of {:?} is at {}, {} away from the expected {}",
+        entity,
+        actual,
+        distance,
+        position
+    );
+}
+
+/// Implementation of `assert_in_contact!` and `assert_not_in_contact!`.
+pub fn assert_contact<N: RealField>(
+    world: &World,
+    entity1: Entity,
+    entity2: Entity,
+    expected: bool,
+) {
+    let physics = world.fetch::<Physics<N>>();
+    let handles = world.fetch::<PhysicsHandles>();
+    for entity in &[entity1, entity2] {
+        assert!(
+            !handles.collider_handles(*entity).is_empty(),
+            "{:?} has no collider in the physics World",
+            entity
+        );
+    }
+
+    assert_eq!(
+        physics.in_contact(&handles, entity1, entity2),
+        expected,
+        "expected {:?} and {:?} {}in contact",
+        entity1,
+        entity2,
+        if expected { "" } else { "not " }
+    );
+}
@@ -0,0 +1,340 @@
+//! # Validation module
+//! Checks for the inputs of the physics `World`. NaN, infinite or negative
+//! values in positions, shapes or densities make nphysics panic deep inside
+//! the solver, far away from the `Entity` that caused them. With the
+//! "validation" feature enabled, the synchronisation `System`s validate every
+//! inserted or modified `PhysicsBody` and `PhysicsCollider` and skip the
+//! offending `Entity`, reporting it with a `DiagnosticKind::InvalidInput` at
+//! `Level::Error`.
+
+use std::fmt;
+
+use crate::{
+    bodies::PhysicsBody,
+    colliders::{PhysicsCollider, Shape},
+    nalgebra::{Isometry3, RealField},
+};
+
+/// The `InvalidInput` names the part of a physics `Component` that failed
+/// validation.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum InvalidInput {
+    Position,
+    Velocity,
+    Mass,
+    AngularInertia,
+    CenterOfMass,
+    ExternalForce,
+    Shape,
+    ColliderOffset,
+    Density,
+    Margin,
+    Prediction,
+}
+
+impl fmt::Display for InvalidInput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self {
+            InvalidInput::Position => "non-finite position",
+            InvalidInput::Velocity => "non-finite velocity",
+            InvalidInput::Mass => "non-finite or negative mass",
+            InvalidInput::AngularInertia => "non-finite or negative angular inertia",
+            InvalidInput::CenterOfMass => "non-finite center of mass",
+            InvalidInput::ExternalForce => "non-finite external force",
+            InvalidInput::Shape => "non-finite, negative or degenerate shape dimensions",
+            InvalidInput::ColliderOffset => "non-finite offset from parent",
+            InvalidInput::Density => "non-finite or negative density",
+            InvalidInput::Margin => "non-finite or negative margin",
+            InvalidInput::Prediction => "non-finite or negative prediction",
+        };
+        f.write_str(description)
+    }
+}
+
+/// Validates the `Position` and `PhysicsBody` of a rigid body.
+pub fn validate_body<N: RealField>(
+    isometry: &Isometry3<N>,
+    physics_body: &PhysicsBody<N>,
+) -> Result<(), InvalidInput> {
+    check(is_finite_isometry(isometry), InvalidInput::Position)?;
+    check(
+        all_finite(physics_body.velocity.linear.iter())
+            && all_finite(physics_body.velocity.angular.iter()),
+        InvalidInput::Velocity,
+    )?;
+    check(is_non_negative(physics_body.mass), InvalidInput::Mass)?;
+    check(
+        all_finite(physics_body.angular_inertia.iter())
+            && physics_body
+                .angular_inertia
+                .diagonal()
+                .iter()
+                .all(|value| is_non_negative(*value)),
+        InvalidInput::AngularInertia,
+    )?;
+    check(
+        all_finite(physics_body.local_center_of_mass.coords.iter()),
+        InvalidInput::CenterOfMass,
+    )?;
+
+    let external_force = physics_body.check_external_force();
+    check(
+        all_finite(external_force.linear.iter()) && all_finite(external_force.angular.iter()),
+        InvalidInput::ExternalForce,
+    )
+}
+
+/// Validates the `Position` and `PhysicsCollider` of a collider, including
+/// its `Shape`.
+pub fn validate_collider<N: RealField>(
+    isometry: &Isometry3<N>,
+    physics_collider: &PhysicsCollider<N>,
+) -> Result<(), InvalidInput> {
+    check(is_finite_isometry(isometry), InvalidInput::Position)?;
+    validate_shape(&physics_collider.shape)?;
+    check(
+        is_finite_isometry(&physics_collider.offset_from_parent),
+        InvalidInput::ColliderOffset,
+    )?;
+    check(
+        is_non_negative(physics_collider.density),
+        InvalidInput::Density,
+    )?;
+    check(
+        is_non_negative(physics_collider.margin),
+        InvalidInput::Margin,
+    )?;
+    check(
+        is_non_negative(physics_collider.linear_prediction)
+            && is_non_negative(physics_collider.angular_prediction),
+        InvalidInput::Prediction,
+    )
+}
+
+/// Validates the dimensions of a `Shape`. Triangle meshes are only built by
+/// nphysics and baked shapes are already built, neither can be validated up
+/// front.
+pub fn validate_shape<N: RealField>(shape: &Shape<N>) -> Result<(), InvalidInput> {
+    let valid = match shape {
+        Shape::Baked { .. } => true,
+        Shape::Ball { radius } => is_non_negative(*radius),
+        Shape::Capsule {
+            half_height,
+            radius,
+        } => is_non_negative(*half_height) && is_non_negative(*radius),
+        Shape::Compound { parts } => {
+            !parts.is_empty()
+                && parts.iter().all(|(isometry, part)| {
+                    is_finite_isometry(isometry) && validate_shape(part).is_ok()
+                })
+        }
+        Shape::ConvexHull { points } => {
+            !points.is_empty() && points.iter().all(|point| all_finite(point.coords.iter()))
+        }
+        Shape::Cuboid { half_extents } => half_extents.iter().all(|value| is_non_negative(*value)),
+        Shape::HeightField { heights, scale } => {
+            all_finite(heights.iter()) && scale.iter().all(|value| is_non_negative(*value))
+        }
+        Shape::Plane { normal } => all_finite(normal.iter()),
+        Shape::Polyline { points, .. } => {
+            points.iter().all(|point| all_finite(point.coords.iter()))
+        }
+        Shape::RoundedCuboid {
+            half_extents,
+            border_radius,
+        } => {
+            half_extents.iter().all(|value| is_positive(*value)) && is_non_negative(*border_radius)
+        }
+        Shape::RoundedCylinder {
+            half_height,
+            radius,
+            border_radius,
+        } => is_positive(*half_height) && is_positive(*radius) && is_non_negative(*border_radius),
+        Shape::Segment { a, b } => all_finite(a.coords.iter()) && all_finite(b.coords.iter()),
+        Shape::TriMesh { .. } => true,
+        Shape::Triangle { a, b, c } => {
+            all_finite(a.coords.iter())
+                && all_finite(b.coords.iter())
+                && all_finite(c.coords.iter())
+        }
+    };
+    check(valid, InvalidInput::Shape)
+}
+
+fn check(valid: bool, error: InvalidInput) -> Result<(), InvalidInput> {
+    if valid {
+        Ok(())
+    } else {
+        Err(error)
+    }
+}
+
+fn is_finite<N: RealField>(value: N) -> bool {
+    value
+        .to_subset()
+        .map_or(false, |value: f64| value.is_finite())
+}
+
+pub(crate) fn is_non_negative<N: RealField>(value: N) -> bool {
+    is_finite(value) && value >= N::zero()
+}
+
+pub(crate) fn is_positive<N: RealField>(value: N) -> bool {
+    is_finite(value) && value > N::zero()
+}
+
+fn all_finite<'a, N: RealField>(mut values: impl Iterator<Item = &'a N>) -> bool {
+    values.all(|value| is_finite(*value))
+}
+
+fn is_finite_isometry<N: RealField>(isometry: &Isometry3<N>) -> bool {
+    all_finite(isometry.translation.vector.iter()) && all_finite(isometry.rotation.coords.iter())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_body, validate_collider, validate_shape, InvalidInput};
+    use crate::{
+        colliders::Shape,
+        nalgebra::{Isometry3, Point3, Vector3},
+        nphysics::{algebra::Velocity3, object::BodyStatus},
+        PhysicsBodyBuilder,
+        PhysicsColliderBuilder,
+    };
+
+    #[test]
+    fn reject_invalid_body() {
+        let physics_body = PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build();
+        assert_eq!(validate_body(&Isometry3::identity(), &physics_body), Ok(()));
+
+        let nan_position = Isometry3::translation(std::f32::NAN, 0.0, 0.0);
+        assert_eq!(
+            validate_body(&nan_position, &physics_body),
+            Err(InvalidInput::Position)
+        );
+
+        let fast_body = PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic)
+            .velocity(Velocity3::linear(std::f32::INFINITY, 0.0, 0.0))
+            .build();
+        assert_eq!(
+            validate_body(&Isometry3::identity(), &fast_body),
+            Err(InvalidInput::Velocity)
+        );
+    }
+
+    #[test]
+    fn reject_invalid_collider() {
+        let shape = Shape::Ball { radius: 0.5f32 };
+        let physics_collider = PhysicsColliderBuilder::from(shape.clone()).build();
+        assert_eq!(
+            validate_collider(&Isometry3::identity(), &physics_collider),
+            Ok(())
+        );
+
+        let negative_density = PhysicsColliderBuilder::from(shape.clone())
+            .density(-1.0)
+            .build();
+        assert_eq!(
+            validate_collider(&Isometry3::identity(), &negative_density),
+            Err(InvalidInput::Density)
+        );
+
+        let negative_margin = PhysicsColliderBuilder::from(shape).margin(-0.01).build();
+        assert_eq!(
+            validate_collider(&Isometry3::identity(), &negative_margin),
+            Err(InvalidInput::Margin)
+        );
+    }
+
+    #[test]
+    fn reject_degenerate_shapes() {
+        let degenerate_shapes = vec![
+            Shape::Ball { radius: -1.0f32 },
+            Shape::Compound { parts: Vec::new() },
+            Shape::ConvexHull { points: Vec::new() },
+            Shape::Cuboid {
+                half_extents: Vector3::new(1.0, std::f32::NAN, 1.0),
+            },
+            Shape::RoundedCuboid {
+                half_extents: Vector3::new(1.0, 0.0, 1.0),
+                border_radius: 0.1,
+            },
+            Shape::Triangle {
+                a: Point3::origin(),
+                b: Point3::new(std::f32::INFINITY, 0.0, 0.0),
+                c: Point3::new(0.0, 1.0, 0.0),
+            },
+        ];
+        for shape in &degenerate_shapes {
+            assert_eq!(
+                validate_shape(shape),
+                Err(InvalidInput::Shape),
+                "{:?}",
+                shape
+            );
+        }
+
+        // a compound is only as valid as its parts
+        let compound = Shape::Compound {
+            parts: vec![(Isometry3::identity(), degenerate_shapes[0].clone())],
+        };
+        assert_eq!(validate_shape(&compound), Err(InvalidInput::Shape));
+    }
+
+    #[cfg(feature = "validation")]
+    #[test]
+    fn skip_and_report_invalid_entity() {
+        use specs::prelude::*;
+
+        use crate::{
+            diagnostics::{DiagnosticKind, PhysicsDiagnostics, PhysicsLogConfig},
+            handles::PhysicsHandles,
+            SimplePosition,
+        };
+
+        let mut world = World::new();
+        let mut dispatcher = crate::physics_dispatcher::<f32, SimplePosition<f32>>();
+        dispatcher.setup(&mut world);
+        world.insert(PhysicsLogConfig {
+            structured: true,
+            ..PhysicsLogConfig::default()
+        });
+        let mut reader_id = world
+            .write_resource::<PhysicsDiagnostics>()
+            .register_reader();
+
+        let valid = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::identity()))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .build();
+        let invalid = world
+            .create_entity()
+            .with(SimplePosition::<f32>(Isometry3::translation(
+                std::f32::NAN,
+                0.0,
+                0.0,
+            )))
+            .with(PhysicsBodyBuilder::<f32>::from(BodyStatus::Dynamic).build())
+            .build();
+        dispatcher.dispatch(&world);
+
+        // the valid body is simulated along without the invalid one
+        let handles = world.read_resource::<PhysicsHandles>();
+        assert!(handles.body_handle(valid).is_some());
+        assert!(handles.body_handle(invalid).is_none());
+
+        let reported = world
+            .read_resource::<PhysicsDiagnostics>()
+            .read(&mut reader_id)
+            .filter_map(|diagnostic| match diagnostic.kind {
+                DiagnosticKind::InvalidInput(id, error) => Some((id, error)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(!reported.is_empty());
+        assert!(reported
+            .iter()
+            .all(|reported| *reported == (invalid.id(), InvalidInput::Position)));
+    }
+}